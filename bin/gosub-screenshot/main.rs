@@ -5,14 +5,18 @@
 //! system libraries (skia-safe is statically linked). The page is rasterized into small
 //! cached tiles (`ExternalHandle::TileCache`) which we composite here, so there is no
 //! GPU texture-size limit and pages of any height can be captured.
+//!
+//! With `--pdf` the page is printed instead: laid out on pages with the print styles and written
+//! as a vector PDF through cairo. This needs the `pdf` feature, which builds the cairo backend.
 
 use clap::Parser;
 use gosub_engine::events::{EngineEvent, NavigationEvent, TabCommand};
 use gosub_engine::storage::{InMemorySessionStore, PartitionPolicy, SqliteLocalStore, StorageService};
-use gosub_engine::tab::{TabDefaults, TabId};
+use gosub_engine::tab::{TabDefaults, TabHandle, TabId};
 use gosub_engine::zone::{ZoneConfig, ZoneId, ZoneServices};
 use gosub_engine::DefaultRenderConfig;
 use gosub_engine::GosubEngine;
+use gosub_render_pipeline::paged::named_page_size;
 use gosub_render_pipeline::render::backend::ExternalHandle;
 use gosub_render_pipeline::render::DefaultCompositor;
 #[cfg(all(feature = "backend_skia", not(feature = "backend_cairo")))]
@@ -25,6 +29,7 @@ use once_cell::sync::Lazy;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::runtime::{Builder, Runtime};
+use tokio::sync::broadcast;
 use url::Url;
 use uuid::uuid;
 
@@ -50,9 +55,8 @@ type AppConfig = DefaultRenderConfig<CairoBackend, PangoFontSystem>;
 struct Args {
     /// URL to capture (https:// is prepended if no scheme is given)
     url: String,
-    /// Output PNG path
-    #[arg(default_value = "screenshot.png")]
-    output: String,
+    /// Output path [default: screenshot.png, or screenshot.pdf with --pdf]
+    output: Option<String>,
     /// Viewport width in CSS pixels
     #[arg(default_value = "1280")]
     width: u32,
@@ -66,6 +70,13 @@ struct Args {
     /// decode and repaint before the capture
     #[arg(long, default_value = "0")]
     settle: u64,
+    /// Print the page to a paginated PDF instead of taking a screenshot
+    #[arg(long)]
+    pdf: bool,
+    /// Page size for --pdf when the document has no `@page { size }` (A4, A5, A3, B5, B4,
+    /// letter, legal, ledger)
+    #[arg(long, default_value = "A4")]
+    page_size: String,
 }
 
 const DEFAULT_ZONE: uuid::Uuid = uuid!("f1234567-abcd-4000-8000-000000000003");
@@ -97,7 +108,13 @@ fn main() {
     } else {
         format!("https://{}", args.url)
     };
-    let output = args.output;
+    let output = args
+        .output
+        .unwrap_or_else(|| if args.pdf { "screenshot.pdf" } else { "screenshot.png" }.to_string());
+    let Some(page_size) = named_page_size(&args.page_size) else {
+        eprintln!("Unknown page size: {}", args.page_size);
+        std::process::exit(1);
+    };
     let viewport_w = args.width;
 
     eprintln!("gosub-screenshot {BUILD_VERSION}");
@@ -229,6 +246,11 @@ fn main() {
         while rx_redraw.try_recv().is_ok() {}
     }
 
+    if args.pdf {
+        save_pdf(&tab, tab_id, &mut event_rx, page_size, &output);
+        return;
+    }
+
     let phase1_handle = compositor.frame_for(tab_id);
     let mut tile_cache_handle: Option<ExternalHandle> = match phase1_handle {
         Some(h @ ExternalHandle::TileCache { .. }) => Some(h),
@@ -339,3 +361,51 @@ fn main() {
     image::save_buffer(&output, &pixels, page_w, page_h, ColorType::Rgba8).expect("save PNG");
     eprintln!("Saved {output} ({}×{})", page_w, page_h);
 }
+
+/// Prints the loaded page and saves it as a PDF at `output`.
+#[cfg(feature = "pdf")]
+fn save_pdf(
+    tab: &TabHandle,
+    tab_id: TabId,
+    event_rx: &mut broadcast::Receiver<EngineEvent>,
+    page_size: gosub_render_pipeline::common::geo::Dimension,
+    output: &str,
+) {
+    let tab_print = tab.clone();
+    TOKIO_RT.spawn(async move {
+        let _ = tab_print.send(TabCommand::Print { page_size }).await;
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let document = loop {
+        if Instant::now() >= deadline {
+            eprintln!("Timeout waiting for the printed document");
+            std::process::exit(1);
+        }
+        match event_rx.try_recv() {
+            Ok(EngineEvent::Printed { tab_id: tid, document }) if tid == tab_id => break document,
+            Ok(_) => {}
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    let Some(document) = document else {
+        eprintln!("No document to print.");
+        std::process::exit(1);
+    };
+
+    let pdf = gosub_renderer_cairo::pdf::render_pdf(&document.pages, &document.media_store).expect("render PDF");
+    std::fs::write(output, pdf).expect("save PDF");
+    eprintln!("Saved {output} ({} page(s))", document.pages.len());
+}
+
+#[cfg(not(feature = "pdf"))]
+fn save_pdf(
+    _tab: &TabHandle,
+    _tab_id: TabId,
+    _event_rx: &mut broadcast::Receiver<EngineEvent>,
+    _page_size: gosub_render_pipeline::common::geo::Dimension,
+    _output: &str,
+) {
+    eprintln!("--pdf needs gosub-screenshot built with the `pdf` feature");
+    std::process::exit(1);
+}
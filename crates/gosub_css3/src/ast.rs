@@ -1,12 +1,15 @@
 use cow_utils::CowUtils;
 use log::warn;
 
+use crate::media::MediaQueryList;
 use crate::node::{Node as CssNode, NodeType};
 use crate::stylesheet::{
     AttributeSelector, Combinator, CssDeclaration, CssRule, CssSelector, CssSelectorPart, CssStylesheet, CssValue,
-    FontFace, MatcherType,
+    MatcherType, PseudoFunction,
+};
+use gosub_interface::css3::{
+    CounterStyleRule, CssOrigin, FontDisplay, FontFaceRule, Keyframe, KeyframesRule, PageMarginRule, PageRule,
 };
-use gosub_interface::css3::CssOrigin;
 use gosub_shared::errors::{CssError, CssResult};
 
 /*
@@ -65,6 +68,7 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
     let mut rule = CssRule {
         selectors: vec![],
         declarations: vec![],
+        media: vec![],
     };
 
     let Some((prelude, declarations)) = node.as_rule() else {
@@ -75,89 +79,7 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
             return Ok(None);
         };
 
-        let mut selector = CssSelector { parts: vec![vec![]] };
-        for node in selectors {
-            let Some(selector_children) = node.as_selector() else {
-                continue;
-            };
-
-            for node in selector_children {
-                let part = match &*node.node_type {
-                    NodeType::Ident { value } => CssSelectorPart::Type(value.clone()),
-                    NodeType::ClassSelector { value } => CssSelectorPart::Class(value.clone()),
-                    NodeType::Combinator { value } => {
-                        let combinator = match value.as_str() {
-                            ">" => Combinator::Child,
-                            "+" => Combinator::NextSibling,
-                            "~" => Combinator::SubsequentSibling,
-                            " " => Combinator::Descendant,
-                            "||" => Combinator::Column,
-                            "|" => Combinator::Namespace,
-                            _ => return Err(CssError::new(format!("Unknown combinator: {value}").as_str())),
-                        };
-
-                        CssSelectorPart::Combinator(combinator)
-                    }
-                    NodeType::IdSelector { value } => CssSelectorPart::Id(value.clone()),
-                    NodeType::TypeSelector { value, .. } if value == "*" => CssSelectorPart::Universal,
-                    NodeType::PseudoClassSelector { value, .. } => CssSelectorPart::PseudoClass(value.to_string()),
-                    NodeType::PseudoElementSelector { value, .. } => CssSelectorPart::PseudoElement(value.to_string()),
-                    NodeType::TypeSelector { value, .. } => CssSelectorPart::Type(value.clone()),
-                    NodeType::AttributeSelector {
-                        name,
-                        value,
-                        flags,
-                        matcher,
-                    } => {
-                        let matcher = match matcher {
-                            None => MatcherType::None,
-
-                            Some(matcher) => {
-                                if let NodeType::Operator(op) = &*matcher.node_type {
-                                    match op.as_str() {
-                                        "=" => MatcherType::Equals,
-                                        "~=" => MatcherType::Includes,
-                                        "|=" => MatcherType::DashMatch,
-                                        "^=" => MatcherType::PrefixMatch,
-                                        "$=" => MatcherType::SuffixMatch,
-                                        "*=" => MatcherType::SubstringMatch,
-                                        _ => {
-                                            warn!("Unsupported matcher: {matcher:?}");
-                                            MatcherType::Equals
-                                        }
-                                    }
-                                } else {
-                                    warn!("Unsupported matcher: {matcher:?}");
-                                    MatcherType::Equals
-                                }
-                            }
-                        };
-
-                        CssSelectorPart::Attribute(Box::new(AttributeSelector {
-                            name: name.clone(),
-                            matcher,
-                            value: value.clone(),
-                            case_insensitive: flags.eq_ignore_ascii_case("i"),
-                        }))
-                    }
-                    NodeType::Comma => {
-                        selector.parts.push(vec![]);
-                        continue;
-                    }
-                    _ => {
-                        return Err(CssError::new(
-                            format!("Unsupported selector part: {:?}", node.node_type).as_str(),
-                        ));
-                    }
-                };
-                if let Some(x) = selector.parts.last_mut() {
-                    x.push(part);
-                } else {
-                    selector.parts.push(vec![part]); //unreachable, but still, we handle it
-                }
-            }
-        }
-        rule.selectors.push(selector);
+        rule.selectors.push(convert_selector_list(selectors)?);
     }
 
     if let Some(declaration) = declarations {
@@ -201,12 +123,160 @@ fn collect_rule(node: &CssNode) -> CssResult<Option<CssRule>> {
     Ok(Some(rule))
 }
 
-fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut Vec<FontFace>) -> CssResult<()> {
+/// Converts the selectors of a `SelectorList` node. Alternatives separated by commas become separate
+/// entries of `CssSelector::parts`.
+pub(crate) fn convert_selector_list(selectors: &[CssNode]) -> CssResult<CssSelector> {
+    let mut selector = CssSelector { parts: vec![vec![]] };
+    for node in selectors {
+        let Some(selector_children) = node.as_selector() else {
+            continue;
+        };
+
+        for node in selector_children {
+            let part = match &*node.node_type {
+                NodeType::Ident { value } => CssSelectorPart::Type(value.clone()),
+                NodeType::ClassSelector { value } => CssSelectorPart::Class(value.clone()),
+                NodeType::Combinator { value } => {
+                    let combinator = match value.as_str() {
+                        ">" => Combinator::Child,
+                        "+" => Combinator::NextSibling,
+                        "~" => Combinator::SubsequentSibling,
+                        " " => Combinator::Descendant,
+                        "||" => Combinator::Column,
+                        "|" => Combinator::Namespace,
+                        _ => return Err(CssError::new(format!("Unknown combinator: {value}").as_str())),
+                    };
+
+                    CssSelectorPart::Combinator(combinator)
+                }
+                NodeType::IdSelector { value } => CssSelectorPart::Id(value.clone()),
+                NodeType::TypeSelector { value, .. } if value == "*" => CssSelectorPart::Universal,
+                NodeType::PseudoClassSelector { value, .. } => convert_pseudo_class(value)?,
+                NodeType::PseudoElementSelector { value, .. } => CssSelectorPart::PseudoElement(value.to_string()),
+                NodeType::TypeSelector { value, .. } => CssSelectorPart::Type(value.clone()),
+                NodeType::AttributeSelector {
+                    name,
+                    value,
+                    flags,
+                    matcher,
+                } => {
+                    let matcher = match matcher {
+                        None => MatcherType::None,
+
+                        Some(matcher) => {
+                            if let NodeType::Operator(op) = &*matcher.node_type {
+                                match op.as_str() {
+                                    "=" => MatcherType::Equals,
+                                    "~=" => MatcherType::Includes,
+                                    "|=" => MatcherType::DashMatch,
+                                    "^=" => MatcherType::PrefixMatch,
+                                    "$=" => MatcherType::SuffixMatch,
+                                    "*=" => MatcherType::SubstringMatch,
+                                    _ => {
+                                        warn!("Unsupported matcher: {matcher:?}");
+                                        MatcherType::Equals
+                                    }
+                                }
+                            } else {
+                                warn!("Unsupported matcher: {matcher:?}");
+                                MatcherType::Equals
+                            }
+                        }
+                    };
+
+                    CssSelectorPart::Attribute(Box::new(AttributeSelector {
+                        name: name.clone(),
+                        matcher,
+                        value: value.clone(),
+                        case_insensitive: flags.eq_ignore_ascii_case("i"),
+                    }))
+                }
+                NodeType::Comma => {
+                    trim_trailing_descendant(&mut selector);
+                    selector.parts.push(vec![]);
+                    continue;
+                }
+                _ => {
+                    return Err(CssError::new(
+                        format!("Unsupported selector part: {:?}", node.node_type).as_str(),
+                    ));
+                }
+            };
+            if let Some(x) = selector.parts.last_mut() {
+                // Whitespace in front of a selector (`:nth-child(2 of .a)`) is not a combinator
+                if x.is_empty() && part == CssSelectorPart::Combinator(Combinator::Descendant) {
+                    continue;
+                }
+                x.push(part);
+            } else {
+                selector.parts.push(vec![part]); //unreachable, but still, we handle it
+            }
+        }
+    }
+    trim_trailing_descendant(&mut selector);
+
+    Ok(selector)
+}
+
+/// Whitespace before a comma (`a , b`) is reported as a descendant combinator; drop it from the last
+/// alternative
+fn trim_trailing_descendant(selector: &mut CssSelector) {
+    if let Some(parts) = selector.parts.last_mut() {
+        if parts.last() == Some(&CssSelectorPart::Combinator(Combinator::Descendant)) {
+            parts.pop();
+        }
+    }
+}
+
+/// Converts a pseudo-class, parsing the argument of the functional ones that can be matched
+fn convert_pseudo_class(value: &CssNode) -> CssResult<CssSelectorPart> {
+    let NodeType::Function { name, arguments } = &*value.node_type else {
+        return Ok(CssSelectorPart::PseudoClass(value.to_string()));
+    };
+    let Some(argument) = arguments.first() else {
+        return Ok(CssSelectorPart::PseudoClass(value.to_string()));
+    };
+
+    let function = match (name.as_str(), &*argument.node_type) {
+        (
+            "is" | "where" | "not" | "has" | "matches" | "-webkit-any" | "-moz-any",
+            NodeType::SelectorList { selectors },
+        ) => PseudoFunction::Selector(name.clone(), convert_selector_list(selectors)?),
+        ("nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type", NodeType::Nth { nth, selector }) => {
+            let parse_int = |value: &str| {
+                value
+                    .parse::<i32>()
+                    .map_err(|_| CssError::new(format!("Invalid An+B value: {value}").as_str()))
+            };
+            let (a, b) = match &*nth.node_type {
+                NodeType::AnPlusB { a, b } => (parse_int(a)?, parse_int(b)?),
+                #[allow(clippy::cast_possible_truncation)]
+                NodeType::Number { value } => (0, *value as i32),
+                _ => return Err(CssError::new(format!("Unsupported nth argument: {nth}").as_str())),
+            };
+            let of = match selector.as_ref().map(|s| &*s.node_type) {
+                Some(NodeType::SelectorList { selectors }) => Some(convert_selector_list(selectors)?),
+                _ => None,
+            };
+            PseudoFunction::Nth {
+                name: name.clone(),
+                a,
+                b,
+                of,
+            }
+        }
+        _ => PseudoFunction::Other(name.clone(), argument.to_string()),
+    };
+
+    Ok(CssSelectorPart::PseudoFunction(Box::new(function)))
+}
+
+fn collect_rules(nodes: &[CssNode], sheet: &mut CssStylesheet) -> CssResult<()> {
     for node in nodes {
         match &*node.node_type {
             NodeType::Rule { .. } => {
                 if let Some(rule) = collect_rule(node)? {
-                    rules.push(rule);
+                    sheet.rules.push(rule);
                 }
             }
             NodeType::AtRule {
@@ -215,7 +285,32 @@ fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut V
                 ..
             } if name.eq_ignore_ascii_case("layer") => {
                 if let Some(children) = block.as_block() {
-                    collect_rules(children, rules, font_faces)?;
+                    collect_rules(children, sheet)?;
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude: Some(prelude),
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("media") => {
+                if let Some(children) = block.as_block() {
+                    // Every rule collected from the block, including those of nested `@media`
+                    // blocks, carries this block's query list.
+                    let start = sheet.rules.len();
+                    collect_rules(children, sheet)?;
+                    let media = MediaQueryList::from_node(prelude);
+                    for rule in &mut sheet.rules[start..] {
+                        rule.media.push(media.clone());
+                    }
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude,
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("page") => {
+                if let Some(children) = block.as_block() {
+                    sheet.pages.push(collect_page(prelude.as_ref(), children));
                 }
             }
             NodeType::AtRule {
@@ -225,7 +320,29 @@ fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut V
             } if name.eq_ignore_ascii_case("font-face") => {
                 if let Some(children) = block.as_block() {
                     if let Some(face) = collect_font_face(children) {
-                        font_faces.push(face);
+                        sheet.font_faces.push(face);
+                    }
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude: Some(prelude),
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("counter-style") => {
+                if let Some(children) = block.as_block() {
+                    if let Some(style) = collect_counter_style(prelude, children) {
+                        sheet.counter_styles.push(style);
+                    }
+                }
+            }
+            NodeType::AtRule {
+                name,
+                prelude: Some(prelude),
+                block: Some(block),
+            } if name.eq_ignore_ascii_case("keyframes") || name.eq_ignore_ascii_case("-webkit-keyframes") => {
+                if let Some(children) = block.as_block() {
+                    if let Some(rule) = collect_keyframes(prelude, children) {
+                        sheet.keyframes.push(rule);
                     }
                 }
             }
@@ -235,12 +352,13 @@ fn collect_rules(nodes: &[CssNode], rules: &mut Vec<CssRule>, font_faces: &mut V
     Ok(())
 }
 
-/// Build a [`FontFace`] from the declarations inside an `@font-face` block. Requires a
+/// Build a [`FontFaceRule`] from the declarations inside an `@font-face` block. Requires a
 /// `font-family` and at least one `src: url(...)`; returns `None` otherwise.
-fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
+fn collect_font_face(nodes: &[CssNode]) -> Option<FontFaceRule> {
     let mut family: Option<String> = None;
     let mut sources: Vec<String> = Vec::new();
     let mut unicode_range: Option<String> = None;
+    let mut display = FontDisplay::Auto;
 
     for decl in nodes {
         let Some((property, value_nodes, _important)) = decl.as_declaration() else {
@@ -286,6 +404,11 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
                     unicode_range = Some(raw);
                 }
             }
+            "font-display" => {
+                if let Some(kw) = value_nodes.iter().find_map(|n| n.as_ident()) {
+                    display = FontDisplay::from_keyword(kw).unwrap_or(display);
+                }
+            }
             _ => {}
         }
     }
@@ -294,13 +417,227 @@ fn collect_font_face(nodes: &[CssNode]) -> Option<FontFace> {
     if sources.is_empty() {
         return None;
     }
-    Some(FontFace {
+    Some(FontFaceRule {
         family,
         sources,
         unicode_range,
+        display,
     })
 }
 
+/// Build a [`CounterStyleRule`] from an `@counter-style` prelude (the name) and its descriptors.
+fn collect_counter_style(prelude: &CssNode, nodes: &[CssNode]) -> Option<CounterStyleRule> {
+    let name = match &*prelude.node_type {
+        NodeType::Container { children } => children.iter().find_map(|n| n.as_ident().cloned()),
+        _ => prelude.as_ident().cloned(),
+    }?;
+    let mut style = CounterStyleRule {
+        name,
+        ..Default::default()
+    };
+
+    for decl in nodes {
+        let Some((property, value_nodes, _important)) = decl.as_declaration() else {
+            continue;
+        };
+        let values: Vec<CssValue> = value_nodes
+            .iter()
+            .filter_map(|n| CssValue::parse_ast_node(n).ok())
+            .flat_map(CssValue::into_vec)
+            .collect();
+        let text = || {
+            let parts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            Some(parts.join(" ")).filter(|s| !s.is_empty())
+        };
+        match property.cow_to_ascii_lowercase().as_ref() {
+            "system" => style.system = text(),
+            "symbols" => {
+                style.symbols = values
+                    .iter()
+                    .filter_map(|v| match v {
+                        CssValue::String(s) => Some(s.clone()),
+                        CssValue::Number(_) | CssValue::Zero => Some(v.to_string()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            "additive-symbols" => {
+                // `<integer> <symbol>` tuples, comma separated.
+                let mut weight = None;
+                for v in &values {
+                    match v {
+                        CssValue::Number(n) => weight = Some(*n as u32),
+                        CssValue::Zero => weight = Some(0),
+                        CssValue::String(s) => {
+                            if let Some(w) = weight.take() {
+                                style.additive_symbols.push((w, s.clone()));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "prefix" => style.prefix = values.iter().find_map(counter_symbol),
+            "suffix" => style.suffix = values.iter().find_map(counter_symbol),
+            "negative" => style.negative = values.iter().find_map(counter_symbol),
+            "fallback" => style.fallback = text(),
+            _ => {}
+        }
+    }
+    Some(style)
+}
+
+/// Build a [`KeyframesRule`] from an `@keyframes` prelude (the name) and its keyframe blocks.
+/// Declaration values are kept as CSS text; the consumer parses them like an inline style.
+fn collect_keyframes(prelude: &CssNode, nodes: &[CssNode]) -> Option<KeyframesRule> {
+    let name_of = |n: &CssNode| match &*n.node_type {
+        NodeType::Ident { value } | NodeType::String { value } => Some(value.clone()),
+        _ => None,
+    };
+    let name = match &*prelude.node_type {
+        NodeType::Container { children } => children.iter().find_map(name_of),
+        _ => name_of(prelude),
+    }?;
+
+    let mut keyframes = Vec::new();
+    for node in nodes {
+        let NodeType::Rule {
+            prelude: Some(selectors),
+            block: Some(block),
+        } = &*node.node_type
+        else {
+            continue;
+        };
+        let NodeType::Container { children: selectors } = &*selectors.node_type else {
+            continue;
+        };
+        let offsets = selectors
+            .iter()
+            .filter_map(|n| match &*n.node_type {
+                NodeType::Ident { value } if value == "from" => Some(0.0),
+                NodeType::Ident { value } if value == "to" => Some(1.0),
+                NodeType::Percentage { value } if (0.0..=100.0).contains(value) => Some(value / 100.0),
+                _ => None,
+            })
+            .collect::<Vec<f32>>();
+        if offsets.is_empty() {
+            continue;
+        }
+
+        let declarations = block
+            .as_block()
+            .into_iter()
+            .flatten()
+            .filter_map(|decl| {
+                let (property, value_nodes, _important) = decl.as_declaration()?;
+                let values: Vec<CssValue> = value_nodes
+                    .iter()
+                    .filter_map(|n| CssValue::parse_ast_node(n).ok())
+                    .flat_map(CssValue::into_vec)
+                    .collect();
+                Some((property.cow_to_ascii_lowercase().into_owned(), css_text(&values)))
+            })
+            .collect();
+        keyframes.push(Keyframe { offsets, declarations });
+    }
+    Some(KeyframesRule { name, keyframes })
+}
+
+/// Build a [`PageRule`] from an `@page` prelude (the page selectors) and its block, which holds
+/// the page's declarations and nested page-margin boxes.
+fn collect_page(prelude: Option<&CssNode>, nodes: &[CssNode]) -> PageRule {
+    let mut selectors = match prelude.map(|p| &*p.node_type) {
+        Some(NodeType::SelectorList { selectors }) => selectors.iter().map(ToString::to_string).collect(),
+        _ => vec![],
+    };
+    if selectors.is_empty() {
+        selectors.push(String::new());
+    }
+
+    let mut page = PageRule {
+        selectors,
+        ..Default::default()
+    };
+    page.declarations = page_declarations(nodes);
+    for node in nodes {
+        if let NodeType::AtRule {
+            name,
+            block: Some(block),
+            ..
+        } = &*node.node_type
+        {
+            page.margin_boxes.push(PageMarginRule {
+                name: name.cow_to_ascii_lowercase().into_owned(),
+                declarations: page_declarations(block.as_block().map_or(&[], Vec::as_slice)),
+            });
+        }
+    }
+    page
+}
+
+/// The declarations of an `@page` or page-margin block as `(property, value)` text pairs. Values
+/// are serialized from the AST rather than [`CssValue`]s so quoted strings stay quoted, which
+/// `content: "Page " counter(page)` depends on.
+fn page_declarations(nodes: &[CssNode]) -> Vec<(String, String)> {
+    nodes
+        .iter()
+        .filter_map(|decl| {
+            let (property, value_nodes, _important) = decl.as_declaration()?;
+            Some((property.cow_to_ascii_lowercase().into_owned(), node_text(value_nodes)))
+        })
+        .collect()
+}
+
+fn node_text(nodes: &[CssNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        if matches!(*node.node_type, NodeType::Comma) {
+            out.push_str(", ");
+            continue;
+        }
+        if !out.is_empty() && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        match &*node.node_type {
+            NodeType::String { value } => out.push_str(&format!("\"{value}\"")),
+            NodeType::Function { name, arguments } => out.push_str(&format!("{name}({})", node_text(arguments))),
+            _ => out.push_str(&node.to_string()),
+        }
+    }
+    out.trim().to_string()
+}
+
+/// Serializes values back to CSS text, space separated, with commas and function arguments kept
+/// in place (`translate(10px, 0) rotate(5deg)`).
+fn css_text(values: &[CssValue]) -> String {
+    let mut out = String::new();
+    for value in values {
+        if matches!(value, CssValue::Comma) {
+            out.push_str(", ");
+            continue;
+        }
+        if !out.is_empty() && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        match value {
+            CssValue::Function(name, args) => {
+                out.push_str(&format!("{name}({})", css_text(args)));
+            }
+            CssValue::List(list) => out.push_str(&css_text(list)),
+            other => out.push_str(&other.to_string()),
+        }
+    }
+    out.trim().to_string()
+}
+
+/// A `<symbol>` descriptor value: a string or identifier.
+fn counter_symbol(v: &CssValue) -> Option<String> {
+    match v {
+        CssValue::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
 /// Recursively collect `url(...)` targets from an `@font-face` `src` value.
 fn collect_src_urls(value: &CssValue, out: &mut Vec<String>) {
     match value {
@@ -332,12 +669,15 @@ pub fn convert_ast_to_stylesheet(css_ast: &CssNode, origin: CssOrigin, url: &str
     let mut sheet = CssStylesheet {
         rules: vec![],
         font_faces: vec![],
+        counter_styles: vec![],
+        keyframes: vec![],
+        pages: vec![],
         origin,
         url: url.to_string(),
         parse_log: vec![],
     };
 
-    collect_rules(children, &mut sheet.rules, &mut sheet.font_faces)?;
+    collect_rules(children, &mut sheet)?;
     Ok(sheet)
 }
 
@@ -357,6 +697,7 @@ mod tests {
               font-weight: 600;
               src: url(https://example.com/ss.ttf) format('truetype');
               unicode-range: U+0000-00FF, U+0131, U+0152-0153;
+              font-display: swap;
             }
             h1 { color: red; }
             "#,
@@ -372,6 +713,67 @@ mod tests {
         assert_eq!(face.family, "Source Serif 4");
         assert_eq!(face.sources, vec!["https://example.com/ss.ttf".to_string()]);
         assert!(face.unicode_range.as_deref().unwrap_or("").contains("U+0000"));
+        assert_eq!(face.display, FontDisplay::Swap);
+    }
+
+    #[test]
+    fn counter_style_rules_are_collected() {
+        let stylesheet = Css3::parse_str(
+            r#"
+            @counter-style thumbs {
+              system: cyclic;
+              symbols: "👍" "👎";
+              suffix: " ";
+            }
+            ol { list-style-type: thumbs; }
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(stylesheet.counter_styles.len(), 1);
+        let style = &stylesheet.counter_styles[0];
+        assert_eq!(style.name, "thumbs");
+        assert_eq!(style.system.as_deref(), Some("cyclic"));
+        assert_eq!(style.symbols, vec!["👍".to_string(), "👎".to_string()]);
+        assert_eq!(style.suffix.as_deref(), Some(" "));
+    }
+
+    #[test]
+    fn keyframes_rules_are_collected() {
+        let stylesheet = Css3::parse_str(
+            r#"
+            @keyframes fade {
+              from { opacity: 0; }
+              50%, 75% { opacity: 0.5; transform: translate(10px, 0) rotate(5deg); }
+              to { opacity: 1; }
+            }
+            div { animation-name: fade; }
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(stylesheet.rules.len(), 1, "the div rule is still collected");
+        assert_eq!(stylesheet.keyframes.len(), 1);
+        let rule = &stylesheet.keyframes[0];
+        assert_eq!(rule.name, "fade");
+        assert_eq!(rule.keyframes.len(), 3);
+        assert_eq!(rule.keyframes[0].offsets, vec![0.0]);
+        assert_eq!(rule.keyframes[1].offsets, vec![0.5, 0.75]);
+        assert_eq!(rule.keyframes[2].offsets, vec![1.0]);
+        assert_eq!(
+            rule.keyframes[1].declarations,
+            vec![
+                ("opacity".to_string(), "0.5".to_string()),
+                ("transform".to_string(), "translate(10px, 0) rotate(5deg)".to_string()),
+            ]
+        );
     }
 
     #[test]
@@ -478,4 +880,55 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn media_rules_carry_their_queries() {
+        let stylesheet = Css3::parse_str(
+            "p { color: red; } @media print { p { color: black; } @media (min-width: 600px) { h1 { color: blue; } } }",
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(stylesheet.rules.len(), 3);
+        assert!(stylesheet.rules[0].media.is_empty());
+        assert_eq!(stylesheet.rules[1].media.len(), 1);
+        assert_eq!(stylesheet.rules[2].media.len(), 2, "nested blocks stack their queries");
+    }
+
+    #[test]
+    fn page_rules_are_collected() {
+        let stylesheet = Css3::parse_str(
+            r#"
+            @page { size: A4; margin: 2cm 1cm; @bottom-center { content: "Page " counter(page) " of " counter(pages); } }
+            @page :first { margin-top: 4cm; }
+            "#,
+            ParserConfig::default(),
+            CssOrigin::Author,
+            "test.css",
+        )
+        .unwrap();
+
+        assert_eq!(stylesheet.pages.len(), 2);
+        let page = &stylesheet.pages[0];
+        assert_eq!(page.selectors, vec![String::new()]);
+        assert_eq!(
+            page.declarations,
+            vec![
+                ("size".to_string(), "A4".to_string()),
+                ("margin".to_string(), "2cm 1cm".to_string())
+            ]
+        );
+        assert_eq!(page.margin_boxes.len(), 1);
+        assert_eq!(page.margin_boxes[0].name, "bottom-center");
+        assert_eq!(
+            page.margin_boxes[0].declarations,
+            vec![(
+                "content".to_string(),
+                r#""Page " counter(page) " of " counter(pages)"#.to_string()
+            )]
+        );
+        assert_eq!(stylesheet.pages[1].selectors, vec![":first".to_string()]);
+    }
 }
@@ -3,9 +3,9 @@
 //! This parser is heavily based on the MIT-licensed `CssTree` parser written by Roman Dvornov
 //! (<https://github.com/lahmatiy>). The original can be found at <https://github.com/csstree/csstree>.
 
-use crate::ast::convert_ast_to_stylesheet;
-use crate::stylesheet::CssStylesheet;
-use crate::tokenizer::Tokenizer;
+use crate::ast::{convert_ast_to_stylesheet, convert_selector_list};
+use crate::stylesheet::{CssSelector, CssSelectorPart, CssStylesheet};
+use crate::tokenizer::{TokenType, Tokenizer};
 
 use gosub_interface::css3::CssOrigin;
 use gosub_shared::byte_stream::{ByteStream, Encoding, Location};
@@ -18,6 +18,7 @@ pub mod ast;
 pub mod colors;
 mod functions;
 pub mod matcher;
+pub mod media;
 // The as_* accessors panic by contract when called on the wrong node type;
 // callers are expected to check the matching is_* predicate first.
 #[allow(clippy::panic)]
@@ -98,6 +99,42 @@ impl<'stream> Css3<'stream> {
         Css3::parse_stream(&mut stream, config, origin, source_url)
     }
 
+    /// Parses a selector list on its own, like the argument of `querySelectorAll()`. The whole input
+    /// has to be a valid selector list.
+    pub fn parse_selector_str(data: &str) -> CssResult<CssSelector> {
+        let mut stream = ByteStream::from_str(data, Encoding::UTF8);
+        let mut parser = Css3::new(&mut stream, ParserConfig::default(), CssOrigin::Author, "");
+
+        parser.consume_whitespace_comments();
+        let node = parser.parse_selector_list()?;
+        parser.consume_whitespace_comments();
+        if parser.tokenizer.lookahead(0).token_type != TokenType::Eof {
+            return Err(CssError::with_location(
+                format!("Unexpected input in selector {data:?}").as_str(),
+                parser.tokenizer.current_location(),
+            ));
+        }
+
+        let selector = match node.as_selector_list() {
+            Some(selectors) => convert_selector_list(selectors)?,
+            None => return Err(CssError::new("Expected a selector list")),
+        };
+        if selector.parts.iter().any(Vec::is_empty) {
+            return Err(CssError::new(format!("Empty selector in {data:?}").as_str()));
+        }
+        if selector
+            .parts
+            .iter()
+            .any(|parts| matches!(parts.last(), Some(CssSelectorPart::Combinator(_))))
+        {
+            return Err(CssError::new(
+                format!("Selector ends in a combinator in {data:?}").as_str(),
+            ));
+        }
+
+        Ok(selector)
+    }
+
     /// Parses a direct stream to a `CssStyleSheet`
     pub fn parse_stream(
         stream: &mut ByteStream,
@@ -173,4 +210,50 @@ mod tests {
             println!("{:?}", res.err().unwrap());
         }
     }
+
+    #[test]
+    fn selector_strings() {
+        use crate::stylesheet::{Combinator, PseudoFunction};
+
+        let selector = Css3::parse_selector_str(" ul > li.item ,  #main p ").unwrap();
+        assert_eq!(
+            selector.parts,
+            vec![
+                vec![
+                    CssSelectorPart::Type("ul".into()),
+                    CssSelectorPart::Combinator(Combinator::Child),
+                    CssSelectorPart::Type("li".into()),
+                    CssSelectorPart::Class("item".into()),
+                ],
+                vec![
+                    CssSelectorPart::Id("main".into()),
+                    CssSelectorPart::Combinator(Combinator::Descendant),
+                    CssSelectorPart::Type("p".into()),
+                ],
+            ]
+        );
+
+        let selector = Css3::parse_selector_str("li:nth-child(2n+1 of .a)").unwrap();
+        let CssSelectorPart::PseudoFunction(function) = &selector.parts[0][1] else {
+            panic!("expected a pseudo-class function, got {:?}", selector.parts[0][1]);
+        };
+        let PseudoFunction::Nth {
+            name,
+            a,
+            b,
+            of: Some(of),
+        } = function.as_ref()
+        else {
+            panic!("expected :nth-child(), got {function:?}");
+        };
+        assert_eq!((name.as_str(), *a, *b), ("nth-child", 2, 1));
+        assert_eq!(of.parts, vec![vec![CssSelectorPart::Class("a".into())]]);
+
+        for invalid in ["", "p >", "a,", "p {", "#"] {
+            assert!(
+                Css3::parse_selector_str(invalid).is_err(),
+                "{invalid:?} should not parse"
+            );
+        }
+    }
 }
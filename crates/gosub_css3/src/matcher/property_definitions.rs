@@ -989,6 +989,23 @@ mod tests {
                     ("url(a.png) no-repeat, url(b.png), blue", true),
                 ],
             ),
+            (
+                // css-images-4 gradients: conic forms and a `<color-interpolation-method>`.
+                "background-image",
+                &[
+                    ("linear-gradient(red, blue)", true),
+                    ("linear-gradient(to right in oklch, red, blue)", true),
+                    ("repeating-linear-gradient(45deg, red 0, blue 10px)", true),
+                    ("radial-gradient(circle at center, red, blue)", true),
+                    ("radial-gradient(in oklab, red, blue)", true),
+                    ("conic-gradient(from 90deg, red, blue)", true),
+                    ("repeating-conic-gradient(red 0deg, blue 30deg)", true),
+                    ("url(a.png), radial-gradient(red, blue)", true),
+                    ("linear-gradient(0, red, blue)", true),
+                    ("linear-gradient(banana, red, blue)", false),
+                    ("radial-gradient(banana, red, blue)", false),
+                ],
+            ),
         ];
 
         let defs = get_css_definitions();
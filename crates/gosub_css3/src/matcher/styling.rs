@@ -12,7 +12,7 @@ use gosub_interface::node::NodeType;
 use gosub_shared::node::NodeId;
 
 use crate::matcher::property_definitions::get_css_definitions;
-use crate::stylesheet::{Combinator, CssSelector, CssSelectorPart, CssValue, MatcherType, Specificity};
+use crate::stylesheet::{Combinator, CssSelector, CssSelectorPart, CssValue, MatcherType, PseudoFunction, Specificity};
 use crate::system::Css3System;
 
 // Matches a complete selector (all parts) against the given node(id).
@@ -29,6 +29,12 @@ pub(crate) fn match_selector<C: HasDocument>(
     selector: &CssSelector,
     pseudo: Option<&str>,
 ) -> (bool, Specificity) {
+    let ctx = MatchContext {
+        pseudo,
+        scope: None,
+        anchor: None,
+    };
+
     for part in &selector.parts {
         // When matching a pseudo-element, the selector must explicitly target it.
         if let Some(target) = pseudo {
@@ -40,7 +46,7 @@ pub(crate) fn match_selector<C: HasDocument>(
             }
         }
 
-        if match_selector_parts::<C>(document, node_id, part, pseudo) {
+        if match_complex::<C>(document, node_id, part, &ctx) {
             return (true, Specificity::from(part.as_slice()));
         }
     }
@@ -48,40 +54,165 @@ pub(crate) fn match_selector<C: HasDocument>(
     (false, Specificity::new(0, 0, 0))
 }
 
+/// Returns true when the element matches any alternative of the selector list, as used by
+/// `querySelectorAll()` and `Element.matches()`. `scope` is the element `:scope` refers to; without
+/// one `:scope` matches the root element.
+pub fn matches_selector<C: HasDocument>(
+    document: &C::Document,
+    node_id: NodeId,
+    selector: &CssSelector,
+    scope: Option<NodeId>,
+) -> bool {
+    if document.node_type(node_id) != NodeType::ElementNode {
+        return false;
+    }
+
+    let ctx = MatchContext {
+        pseudo: None,
+        scope,
+        anchor: None,
+    };
+    selector
+        .parts
+        .iter()
+        .any(|part| match_complex::<C>(document, node_id, part, &ctx))
+}
+
+/// State shared by every part of a selector being matched
+struct MatchContext<'a> {
+    /// The pseudo-element being styled, see [`match_selector`]
+    pseudo: Option<&'a str>,
+    /// The element `:scope` matches
+    scope: Option<NodeId>,
+    /// The element a relative selector inside `:has()` is anchored at
+    anchor: Option<NodeId>,
+}
+
 /// Case-insensitive compare of a pseudo-element name against a target (`before`/`after`).
 fn pseudo_eq(name: &str, target: &str) -> bool {
     name.eq_ignore_ascii_case(target)
 }
 
-fn consume<'a, T>(this: &mut &'a [T]) -> Option<&'a T> {
-    let last = this.last()?;
+/// Returns true when the node matches a complex selector: compounds separated by combinators. The
+/// rightmost compound is matched against the node, and the rest of the selector against the nodes the
+/// combinator leads to, trying every candidate so `div.x p` finds a `div.x` ancestor even when a nearer
+/// ancestor also has class `x`.
+fn match_complex<C: HasDocument>(
+    doc: &C::Document,
+    node_id: NodeId,
+    parts: &[CssSelectorPart],
+    ctx: &MatchContext,
+) -> bool {
+    let split = parts
+        .iter()
+        .rposition(|p| matches!(p, CssSelectorPart::Combinator(c) if *c != Combinator::Namespace));
+    let (rest, combinator, compound) = match split {
+        Some(idx) => match &parts[idx] {
+            CssSelectorPart::Combinator(combinator) => (&parts[..idx], Some(combinator), &parts[idx + 1..]),
+            _ => return false,
+        },
+        None => (&parts[..0], None, parts),
+    };
 
-    if let Some(parts) = this.get(..this.len() - 1) {
-        *this = parts;
+    if !match_compound::<C>(doc, node_id, compound, ctx) {
+        return false;
     }
 
-    Some(last)
+    let Some(combinator) = combinator else {
+        return true;
+    };
+
+    // A relative selector from `:has()` starts with a combinator; it leads to the anchor element
+    let matches_rest = |candidate: NodeId| {
+        if rest.is_empty() {
+            ctx.anchor == Some(candidate)
+        } else {
+            match_complex::<C>(doc, candidate, rest, ctx)
+        }
+    };
+
+    match combinator {
+        Combinator::Descendant => {
+            let mut current = doc.parent(node_id);
+            while let Some(ancestor) = current {
+                if doc.node_type(ancestor) != NodeType::ElementNode {
+                    return false;
+                }
+                if matches_rest(ancestor) {
+                    return true;
+                }
+                current = doc.parent(ancestor);
+            }
+            false
+        }
+        Combinator::Child => doc
+            .parent(node_id)
+            .is_some_and(|parent| doc.node_type(parent) == NodeType::ElementNode && matches_rest(parent)),
+        Combinator::NextSibling => previous_element_siblings::<C>(doc, node_id)
+            .next()
+            .is_some_and(matches_rest),
+        Combinator::SubsequentSibling => previous_element_siblings::<C>(doc, node_id).any(matches_rest),
+        Combinator::Column | Combinator::Namespace => false,
+    }
+}
+
+/// The element siblings before the node, nearest first
+fn previous_element_siblings<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    let siblings = doc
+        .parent(node_id)
+        .map(|parent| doc.children(parent))
+        .unwrap_or_default();
+    let position = siblings.iter().position(|&id| id == node_id).unwrap_or(0);
+    siblings[..position]
+        .iter()
+        .rev()
+        .copied()
+        .filter(move |&id| doc.node_type(id) == NodeType::ElementNode)
 }
 
-/// Returns true when the given node matches the part(s)
-fn match_selector_parts<C: HasDocument>(
+/// The element siblings of the node, including itself, in tree order
+fn element_siblings<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> Vec<NodeId> {
+    match doc.parent(node_id) {
+        Some(parent) => doc
+            .children(parent)
+            .iter()
+            .copied()
+            .filter(|&id| doc.node_type(id) == NodeType::ElementNode)
+            .collect(),
+        None => vec![node_id],
+    }
+}
+
+/// Returns true when the node matches every part of a compound selector
+fn match_compound<C: HasDocument>(
     doc: &C::Document,
     node_id: NodeId,
-    mut parts: &[CssSelectorPart],
-    pseudo: Option<&str>,
+    compound: &[CssSelectorPart],
+    ctx: &MatchContext,
 ) -> bool {
-    let mut next_current_id: Option<NodeId> = Some(node_id);
+    // The document node itself is never matched
+    if doc.parent(node_id).is_none() {
+        return false;
+    }
 
-    while let Some(part) = consume(&mut parts) {
-        let Some(current_id) = next_current_id else {
-            return false;
+    for (idx, part) in compound.iter().enumerate() {
+        let matched = match part {
+            // `ns|type`: the part before the namespace combinator names the namespace
+            CssSelectorPart::Combinator(Combinator::Namespace) => match idx.checked_sub(1).map(|i| &compound[i]) {
+                Some(CssSelectorPart::Universal) => true,
+                Some(CssSelectorPart::Type(namespace)) => doc.namespace(node_id).is_some_and(|ns| ns == namespace),
+                _ => false,
+            },
+            _ if matches!(
+                compound.get(idx + 1),
+                Some(CssSelectorPart::Combinator(Combinator::Namespace))
+            ) =>
+            {
+                true
+            }
+            _ => match_selector_part::<C>(part, node_id, doc, ctx),
         };
-
-        if doc.parent(current_id).is_none() {
-            return false;
-        }
-
-        if !match_selector_part::<C>(part, current_id, doc, &mut next_current_id, &mut parts, pseudo) {
+        if !matched {
             return false;
         }
     }
@@ -93,9 +224,7 @@ fn match_selector_part<C: HasDocument>(
     part: &CssSelectorPart,
     current_id: NodeId,
     doc: &C::Document,
-    next_id: &mut Option<NodeId>,
-    parts: &mut &[CssSelectorPart],
-    pseudo: Option<&str>,
+    ctx: &MatchContext,
 ) -> bool {
     match part {
         CssSelectorPart::Universal => true,
@@ -121,7 +250,7 @@ fn match_selector_part<C: HasDocument>(
             let mut _got_buf = String::new();
 
             let (wanted_attr_value, got_attr_value): (&str, &str) = if attr.case_insensitive {
-                _wanted_buf = attr.name.cow_to_lowercase().to_string();
+                _wanted_buf = attr.value.cow_to_lowercase().to_string();
                 _got_buf = got_raw.cow_to_lowercase().to_string();
                 (&_wanted_buf, &_got_buf)
             } else {
@@ -131,13 +260,19 @@ fn match_selector_part<C: HasDocument>(
             match attr.matcher {
                 MatcherType::None => true,
                 MatcherType::Equals => wanted_attr_value == got_attr_value,
-                MatcherType::Includes => wanted_attr_value.split_whitespace().any(|s| s == got_attr_value),
+                MatcherType::Includes => got_attr_value.split_whitespace().any(|s| s == wanted_attr_value),
                 MatcherType::DashMatch => {
                     got_attr_value == wanted_attr_value || got_attr_value.starts_with(&format!("{wanted_attr_value}-"))
                 }
-                MatcherType::PrefixMatch => got_attr_value.starts_with(wanted_attr_value),
-                MatcherType::SuffixMatch => got_attr_value.ends_with(wanted_attr_value),
-                MatcherType::SubstringMatch => got_attr_value.contains(wanted_attr_value),
+                MatcherType::PrefixMatch => {
+                    !wanted_attr_value.is_empty() && got_attr_value.starts_with(wanted_attr_value)
+                }
+                MatcherType::SuffixMatch => {
+                    !wanted_attr_value.is_empty() && got_attr_value.ends_with(wanted_attr_value)
+                }
+                MatcherType::SubstringMatch => {
+                    !wanted_attr_value.is_empty() && got_attr_value.contains(wanted_attr_value)
+                }
             }
         }
         CssSelectorPart::PseudoClass(name) => match name.as_ref() {
@@ -153,81 +288,50 @@ fn match_selector_part<C: HasDocument>(
                     && doc.attribute(current_id, "href").is_some()
             }
             "visited" => false,
-            // Structural pseudo-classes
-            "first-child" => {
-                if let Some(parent_id) = doc.parent(current_id) {
-                    let siblings = doc.children(parent_id);
-                    siblings.first().is_some_and(|&id| id == current_id)
-                } else {
-                    false
-                }
-            }
-            "last-child" => {
-                if let Some(parent_id) = doc.parent(current_id) {
-                    let siblings = doc.children(parent_id);
-                    siblings.last().is_some_and(|&id| id == current_id)
-                } else {
-                    false
-                }
-            }
+            // Structural pseudo-classes. Only element siblings count, so whitespace between
+            // elements does not stop `:first-child` from matching.
+            "first-child" => element_siblings::<C>(doc, current_id).first() == Some(&current_id),
+            "last-child" => element_siblings::<C>(doc, current_id).last() == Some(&current_id),
+            "only-child" => element_siblings::<C>(doc, current_id) == [current_id],
             "first-of-type" => {
                 let tag = doc.tag_name(current_id);
-                if let (Some(parent_id), Some(tag)) = (doc.parent(current_id), tag) {
-                    doc.children(parent_id)
-                        .iter()
-                        .find(|&&id| doc.tag_name(id) == Some(tag))
-                        .is_some_and(|&id| id == current_id)
-                } else {
-                    false
-                }
+                element_siblings::<C>(doc, current_id)
+                    .into_iter()
+                    .find(|&id| doc.tag_name(id) == tag)
+                    == Some(current_id)
             }
             "last-of-type" => {
                 let tag = doc.tag_name(current_id);
-                if let (Some(parent_id), Some(tag)) = (doc.parent(current_id), tag) {
-                    doc.children(parent_id)
-                        .iter()
-                        .filter(|&&id| doc.tag_name(id) == Some(tag))
-                        .last()
-                        .is_some_and(|&id| id == current_id)
-                } else {
-                    false
-                }
-            }
-            "only-child" => {
-                if let Some(parent_id) = doc.parent(current_id) {
-                    let elem_siblings: Vec<_> = doc
-                        .children(parent_id)
-                        .iter()
-                        .filter(|&&id| doc.node_type(id) == NodeType::ElementNode)
-                        .copied()
-                        .collect();
-                    elem_siblings.len() == 1 && elem_siblings[0] == current_id
-                } else {
-                    false
-                }
+                element_siblings::<C>(doc, current_id)
+                    .into_iter()
+                    .rfind(|&id| doc.tag_name(id) == tag)
+                    == Some(current_id)
             }
             "only-of-type" => {
                 let tag = doc.tag_name(current_id);
-                if let (Some(parent_id), Some(tag)) = (doc.parent(current_id), tag) {
-                    doc.children(parent_id)
-                        .iter()
-                        .filter(|&&id| doc.tag_name(id) == Some(tag))
-                        .count()
-                        == 1
-                } else {
-                    false
-                }
+                element_siblings::<C>(doc, current_id)
+                    .into_iter()
+                    .filter(|&id| doc.tag_name(id) == tag)
+                    .count()
+                    == 1
             }
+            "empty" => doc
+                .children(current_id)
+                .iter()
+                .all(|&child| match doc.node_type(child) {
+                    NodeType::ElementNode => false,
+                    NodeType::TextNode => doc.text_value(child).is_none_or(str::is_empty),
+                    _ => true,
+                }),
             // The document's root element (`<html>`): an element whose parent is absent or
             // a non-element node (the Document). Checking `parent().is_none()` alone fails
             // because `<html>`'s parent is the Document node, so `:root` would match nothing
             // and `:root { --custom: … }` custom properties would never be collected.
-            "root" => {
-                doc.node_type(current_id) == NodeType::ElementNode
-                    && doc
-                        .parent(current_id)
-                        .is_none_or(|p| doc.node_type(p) != NodeType::ElementNode)
-            }
+            "root" => is_root_element::<C>(doc, current_id),
+            "scope" => match ctx.scope {
+                Some(scope) => scope == current_id,
+                None => is_root_element::<C>(doc, current_id),
+            },
             "checked" => doc.attribute(current_id, "checked").is_some(),
             "disabled" => doc.attribute(current_id, "disabled").is_some(),
             "enabled" => {
@@ -244,115 +348,141 @@ fn match_selector_part<C: HasDocument>(
             // Unknown / unimplemented pseudo-classes never match.
             _ => false,
         },
+        CssSelectorPart::PseudoFunction(function) => match_pseudo_function::<C>(function, current_id, doc, ctx),
         // A pseudo-element part matches only when we are explicitly computing the styles for that
-        // pseudo-element (`pseudo == Some(name)`). It does not advance `next_id`: the remaining
-        // compound continues to match against the originating element.
-        CssSelectorPart::PseudoElement(name) => pseudo.is_some_and(|target| pseudo_eq(name, target)),
-        CssSelectorPart::Combinator(combinator) => match combinator {
-            Combinator::Descendant => {
-                let Some(mut parent_id) = doc.parent(current_id) else {
-                    return false;
-                };
-
-                let Some(last) = consume(parts) else {
-                    return false;
-                };
-
-                loop {
-                    *next_id = Some(parent_id);
-
-                    if match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo) {
-                        return true;
-                    }
-
-                    let Some(p) = doc.parent(parent_id) else {
-                        return false;
-                    };
+        // pseudo-element (`pseudo == Some(name)`); the rest of the compound continues to match
+        // against the originating element.
+        CssSelectorPart::PseudoElement(name) => ctx.pseudo.is_some_and(|target| pseudo_eq(name, target)),
+        // Combinators are handled by `match_complex`
+        CssSelectorPart::Combinator(_) => false,
+    }
+}
 
-                    parent_id = p;
-                }
-            }
-            Combinator::Child => {
-                let Some(parent_id) = doc.parent(current_id) else {
-                    return false;
-                };
+fn is_root_element<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> bool {
+    doc.node_type(node_id) == NodeType::ElementNode
+        && doc
+            .parent(node_id)
+            .is_none_or(|p| doc.node_type(p) != NodeType::ElementNode)
+}
 
-                let Some(last) = consume(parts) else {
-                    return false;
+fn match_pseudo_function<C: HasDocument>(
+    function: &PseudoFunction,
+    current_id: NodeId,
+    doc: &C::Document,
+    ctx: &MatchContext,
+) -> bool {
+    // Arguments are matched against the element itself, never against a pseudo-element
+    let inner = MatchContext {
+        pseudo: None,
+        scope: ctx.scope,
+        anchor: None,
+    };
+    let any_alternative = |selector: &CssSelector, node_id: NodeId, ctx: &MatchContext| {
+        selector
+            .parts
+            .iter()
+            .any(|part| match_complex::<C>(doc, node_id, part, ctx))
+    };
+
+    match function {
+        PseudoFunction::Selector(name, selector) => match name.as_str() {
+            "is" | "where" | "matches" | "-webkit-any" | "-moz-any" => any_alternative(selector, current_id, &inner),
+            "not" => !any_alternative(selector, current_id, &inner),
+            "has" => {
+                let relative = MatchContext {
+                    pseudo: None,
+                    scope: ctx.scope,
+                    anchor: Some(current_id),
                 };
-
-                *next_id = Some(parent_id);
-
-                match_selector_part::<C>(last, parent_id, doc, next_id, parts, pseudo)
+                selector.parts.iter().any(|part| {
+                    let candidates = match part.first() {
+                        Some(CssSelectorPart::Combinator(Combinator::NextSibling | Combinator::SubsequentSibling)) => {
+                            following_siblings_and_descendants::<C>(doc, current_id)
+                        }
+                        _ => descendants::<C>(doc, current_id),
+                    };
+                    // Without a leading combinator the argument is a descendant selector
+                    let implied;
+                    let part: &[CssSelectorPart] = if matches!(part.first(), Some(CssSelectorPart::Combinator(_))) {
+                        part
+                    } else {
+                        implied = std::iter::once(CssSelectorPart::Combinator(Combinator::Descendant))
+                            .chain(part.iter().cloned())
+                            .collect::<Vec<_>>();
+                        &implied
+                    };
+                    candidates
+                        .into_iter()
+                        .any(|candidate| match_complex::<C>(doc, candidate, part, &relative))
+                })
             }
-            Combinator::NextSibling => {
-                let Some(parent_id) = doc.parent(current_id) else {
-                    return false;
-                };
-
-                let children = doc.children(parent_id);
-
-                let Some(my_index) = children.iter().position(|&c| c == current_id) else {
-                    return false;
-                };
-
-                if my_index == 0 {
-                    return false;
-                }
-
-                let Some(&prev_id) = children.get(my_index - 1) else {
-                    return false;
-                };
-
-                let Some(last) = consume(parts) else {
-                    return false;
-                };
-
-                *next_id = Some(prev_id);
-
-                match_selector_part::<C>(last, prev_id, doc, next_id, parts, pseudo)
+            _ => false,
+        },
+        PseudoFunction::Nth { name, a, b, of } => {
+            if doc.node_type(current_id) != NodeType::ElementNode {
+                return false;
             }
-            Combinator::SubsequentSibling => {
-                let Some(parent_id) = doc.parent(current_id) else {
-                    return false;
-                };
-
-                let children: Vec<NodeId> = doc.children(parent_id).to_vec();
-
-                let Some(last) = consume(parts) else {
-                    return false;
-                };
-
-                for child_id in children {
-                    if child_id == current_id {
-                        break;
-                    }
-
-                    if match_selector_part::<C>(last, child_id, doc, next_id, parts, pseudo) {
-                        return true;
-                    }
-                }
-
-                false
+            let tag = doc.tag_name(current_id);
+            let mut siblings: Vec<NodeId> = element_siblings::<C>(doc, current_id)
+                .into_iter()
+                .filter(|&id| match name.as_str() {
+                    "nth-of-type" | "nth-last-of-type" => doc.tag_name(id) == tag,
+                    _ => of.as_ref().is_none_or(|of| any_alternative(of, id, &inner)),
+                })
+                .collect();
+            if name.starts_with("nth-last") {
+                siblings.reverse();
             }
-            Combinator::Namespace => {
-                let Some(namespace) = consume(parts) else {
-                    return false;
-                };
+            let Some(index) = siblings.iter().position(|&id| id == current_id) else {
+                return false;
+            };
+            nth_matches(*a, *b, index as i64 + 1)
+        }
+        PseudoFunction::Other(..) => false,
+    }
+}
 
-                if *namespace == CssSelectorPart::Universal {
-                    return true;
-                }
+/// Returns true when `position` (1-based) is `a*n + b` for some n >= 0
+fn nth_matches(a: i32, b: i32, position: i64) -> bool {
+    let (a, b) = (i64::from(a), i64::from(b));
+    if a == 0 {
+        return position == b;
+    }
+    let diff = position - b;
+    diff % a == 0 && diff / a >= 0
+}
 
-                let CssSelectorPart::Type(namespace) = namespace else {
-                    return false;
-                };
+/// The element descendants of a node in tree order
+fn descendants<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> Vec<NodeId> {
+    let mut found = Vec::new();
+    let mut stack: Vec<NodeId> = doc.children(node_id).iter().rev().copied().collect();
+    while let Some(id) = stack.pop() {
+        if doc.node_type(id) == NodeType::ElementNode {
+            found.push(id);
+        }
+        stack.extend(doc.children(id).iter().rev().copied());
+    }
+    found
+}
 
-                doc.namespace(current_id).is_some_and(|ns| ns == namespace)
-            }
-            Combinator::Column => false,
-        },
+/// The element siblings after a node together with their descendants, in tree order
+fn following_siblings_and_descendants<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> Vec<NodeId> {
+    let siblings = doc
+        .parent(node_id)
+        .map(|parent| doc.children(parent))
+        .unwrap_or_default();
+    let position = siblings
+        .iter()
+        .position(|&id| id == node_id)
+        .map_or(siblings.len(), |p| p + 1);
+    let mut found = Vec::new();
+    for &sibling in &siblings[position..] {
+        if doc.node_type(sibling) == NodeType::ElementNode {
+            found.push(sibling);
+            found.extend(descendants::<C>(doc, sibling));
+        }
     }
+    found
 }
 
 /// A declarationProperty defines a single value for a property (color: red;). It consists of the value,
@@ -672,6 +802,10 @@ impl css3::CssProperty<Css3System> for CssProperty {
     fn is_none(&self) -> bool {
         matches!(self.actual, CssValue::None)
     }
+
+    fn winning_declaration(&self) -> Option<(CssOrigin, &str)> {
+        self.declared.iter().max().map(|d| (d.origin, d.location.as_str()))
+    }
 }
 
 /// Map of all declared values for a single node. Note that these are only the defined properties, not
@@ -875,6 +1009,32 @@ mod tests {
         assert_eq!(d, d);
     }
 
+    #[test]
+    fn winning_declaration_is_reported() {
+        use css3::CssProperty as _;
+
+        let mut prop = CssProperty::new("color");
+        assert_eq!(prop.winning_declaration(), None);
+
+        for (origin, important, location) in [
+            (CssOrigin::Author, false, "site.css"),
+            (CssOrigin::UserAgent, true, "useragent.css"),
+            (CssOrigin::Author, true, "theme.css"),
+        ] {
+            prop.declared.push(DeclarationProperty {
+                value: CssValue::String("red".into()),
+                origin,
+                important,
+                location: location.into(),
+                specificity: Specificity::new(0, 0, 1),
+            });
+        }
+        assert_eq!(
+            prop.winning_declaration(),
+            Some((CssOrigin::UserAgent, "useragent.css"))
+        );
+    }
+
     #[test]
     fn is_inheritable() {
         let prop = CssProperty::new("border");
@@ -444,6 +444,25 @@ fn match_component_single<'a>(input: &'a [CssValue], component: &SyntaxComponent
                     CssValue::String(s) if s != "/" => return first_match(input),
                     _ => {}
                 },
+                // `<zero>` is the number `0` and nothing else; through the catch-all, the
+                // `<angle> | <zero>` of a gradient's direction accepted any identifier.
+                "zero" => match value {
+                    CssValue::Zero => return first_match(input),
+                    CssValue::Number(n) if *n == 0.0 => return first_match(input),
+                    _ => {}
+                },
+                // A `<url>` is always parsed to a `url()` (or `src()`) function. Through the
+                // permissive catch-all it claimed any value, so `background-image` accepted a
+                // gradient with invalid arguments, e.g. `linear-gradient(banana, red, blue)`,
+                // as a URL.
+                "url" => match value {
+                    CssValue::Function(name, _)
+                        if name.eq_ignore_ascii_case("url") || name.eq_ignore_ascii_case("src") =>
+                    {
+                        return first_match(input)
+                    }
+                    _ => {}
+                },
                 "dashed-ident" => match value {
                     CssValue::String(s) if s.starts_with("--") => return first_match(input),
                     _ => {}
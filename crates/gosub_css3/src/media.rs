@@ -0,0 +1,422 @@
+//! Media query evaluation.
+//!
+//! `@media` blocks are converted into [`MediaQueryList`]s when the stylesheet is built and attached
+//! to every rule they contain. The cascade evaluates them against the layout viewport (see
+//! [`crate::stylesheet::set_layout_viewport`]) and the current [`MediaType`], so a document laid
+//! out for print picks up `@media print` rules while the screen layout skips them.
+
+use cow_utils::CowUtils;
+use std::cell::Cell;
+
+use crate::node::{Node as CssNode, NodeType};
+use crate::stylesheet::layout_viewport;
+
+/// The media type a document is being styled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MediaType {
+    #[default]
+    Screen,
+    Print,
+}
+
+thread_local! {
+    /// Media type used to evaluate `@media` rules during style computation on this thread.
+    static MEDIA_TYPE: Cell<MediaType> = const { Cell::new(MediaType::Screen) };
+}
+
+/// Set the media type used for subsequent style computations on this thread. The print path sets
+/// [`MediaType::Print`] around its layout pass and restores [`MediaType::Screen`] afterwards.
+pub fn set_media_type(media_type: MediaType) {
+    MEDIA_TYPE.with(|mt| mt.set(media_type));
+}
+
+/// The media type currently used for evaluating `@media` rules on this thread.
+#[must_use]
+pub fn media_type() -> MediaType {
+    MEDIA_TYPE.with(Cell::get)
+}
+
+/// Comparison operator of a media feature test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(Self::Lt),
+            "<=" => Some(Self::Le),
+            "=" => Some(Self::Eq),
+            ">=" => Some(Self::Ge),
+            ">" => Some(Self::Gt),
+            _ => None,
+        }
+    }
+
+    /// The same test with its operands swapped: `400px < width` is `width > 400px`.
+    fn flip(self) -> Self {
+        match self {
+            Self::Lt => Self::Gt,
+            Self::Le => Self::Ge,
+            Self::Eq => Self::Eq,
+            Self::Ge => Self::Le,
+            Self::Gt => Self::Lt,
+        }
+    }
+
+    fn test(self, left: f32, right: f32) -> bool {
+        match self {
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Eq => (left - right).abs() < 0.001,
+            Self::Ge => left >= right,
+            Self::Gt => left > right,
+        }
+    }
+}
+
+/// A media feature value: a length resolved to CSS px, a plain number or ratio, or a keyword.
+#[derive(Debug, Clone, PartialEq)]
+enum FeatureValue {
+    Number(f32),
+    Ident(String),
+}
+
+/// A media condition, the part of a query after the media type.
+#[derive(Debug, Clone, PartialEq)]
+enum MediaCondition {
+    /// `(name)` or `(name: value)`, including the `min-`/`max-` prefixed forms.
+    Feature { name: String, value: Option<FeatureValue> },
+    /// One side of a range test such as `(width >= 600px)`; `400px <= width <= 700px` becomes two.
+    Range {
+        name: String,
+        comparison: Comparison,
+        value: FeatureValue,
+    },
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    /// Anything we could not interpret; never matches.
+    Unknown,
+}
+
+/// A single query of a media query list: `not print and (min-width: 600px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    negated: bool,
+    /// Lowercased media type; empty when the query only has a condition.
+    media_type: String,
+    condition: Option<MediaCondition>,
+}
+
+/// A comma separated media query list; matches when any of its queries matches.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaQueryList {
+    queries: Vec<MediaQuery>,
+}
+
+impl MediaQueryList {
+    /// Converts the `MediaQueryList` prelude of an `@media` rule. Anything else gives an empty list,
+    /// which never matches.
+    #[must_use]
+    pub fn from_node(node: &CssNode) -> Self {
+        let NodeType::MediaQueryList { media_queries } = &*node.node_type else {
+            return Self::default();
+        };
+        let queries = media_queries
+            .iter()
+            .filter_map(|query| match &*query.node_type {
+                NodeType::MediaQuery {
+                    modifier,
+                    media_type,
+                    condition,
+                } => Some(MediaQuery {
+                    negated: modifier.eq_ignore_ascii_case("not"),
+                    media_type: media_type.cow_to_ascii_lowercase().into_owned(),
+                    condition: condition.as_ref().map(convert_condition),
+                }),
+                _ => None,
+            })
+            .collect();
+        Self { queries }
+    }
+
+    /// Evaluates the list against the current viewport and media type.
+    #[must_use]
+    pub fn matches(&self) -> bool {
+        let (width, height) = layout_viewport();
+        self.matches_environment(media_type(), width, height)
+    }
+
+    /// Evaluates the list against an explicit media type and viewport size (CSS px).
+    #[must_use]
+    pub fn matches_environment(&self, media_type: MediaType, width: f32, height: f32) -> bool {
+        let env = Environment {
+            media_type,
+            width,
+            height,
+        };
+        self.queries.iter().any(|query| query.matches(&env))
+    }
+}
+
+struct Environment {
+    media_type: MediaType,
+    width: f32,
+    height: f32,
+}
+
+impl MediaQuery {
+    fn matches(&self, env: &Environment) -> bool {
+        let type_matches = match self.media_type.as_str() {
+            "" | "all" => true,
+            "screen" => env.media_type == MediaType::Screen,
+            "print" => env.media_type == MediaType::Print,
+            _ => false,
+        };
+        let result = type_matches && self.condition.as_ref().is_none_or(|c| c.matches(env));
+        result != self.negated
+    }
+}
+
+impl MediaCondition {
+    fn matches(&self, env: &Environment) -> bool {
+        match self {
+            Self::Feature { name, value: None } => feature_value(name, env).is_some_and(|v| match v {
+                FeatureValue::Number(n) => n != 0.0,
+                FeatureValue::Ident(ident) => ident != "none",
+            }),
+            Self::Feature {
+                name,
+                value: Some(value),
+            } => {
+                let (name, comparison) = if let Some(name) = name.strip_prefix("min-") {
+                    (name, Comparison::Ge)
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    (name, Comparison::Le)
+                } else {
+                    (name.as_str(), Comparison::Eq)
+                };
+                compare(name, comparison, value, env)
+            }
+            Self::Range {
+                name,
+                comparison,
+                value,
+            } => compare(name, *comparison, value, env),
+            Self::Not(condition) => !condition.matches(env),
+            Self::And(conditions) => conditions.iter().all(|c| c.matches(env)),
+            Self::Or(conditions) => conditions.iter().any(|c| c.matches(env)),
+            Self::Unknown => false,
+        }
+    }
+}
+
+fn compare(name: &str, comparison: Comparison, value: &FeatureValue, env: &Environment) -> bool {
+    match (feature_value(name, env), value) {
+        (Some(FeatureValue::Number(actual)), FeatureValue::Number(expected)) => comparison.test(actual, *expected),
+        (Some(FeatureValue::Ident(actual)), FeatureValue::Ident(expected)) => {
+            comparison == Comparison::Eq && actual == *expected
+        }
+        _ => false,
+    }
+}
+
+/// The environment's value for a media feature, or `None` for features we do not know.
+fn feature_value(name: &str, env: &Environment) -> Option<FeatureValue> {
+    let print = env.media_type == MediaType::Print;
+    let value = match name {
+        "width" | "device-width" => FeatureValue::Number(env.width),
+        "height" | "device-height" => FeatureValue::Number(env.height),
+        "aspect-ratio" | "device-aspect-ratio" => FeatureValue::Number(env.width / env.height.max(1.0)),
+        "orientation" if env.height >= env.width => FeatureValue::Ident("portrait".into()),
+        "orientation" => FeatureValue::Ident("landscape".into()),
+        "color" => FeatureValue::Number(8.0),
+        "monochrome" => FeatureValue::Number(0.0),
+        "hover" | "any-hover" if print => FeatureValue::Ident("none".into()),
+        "hover" | "any-hover" => FeatureValue::Ident("hover".into()),
+        "pointer" | "any-pointer" if print => FeatureValue::Ident("none".into()),
+        "pointer" | "any-pointer" => FeatureValue::Ident("fine".into()),
+        "prefers-color-scheme" => FeatureValue::Ident("light".into()),
+        "prefers-reduced-motion" => FeatureValue::Ident("no-preference".into()),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn convert_condition(node: &CssNode) -> MediaCondition {
+    let NodeType::Condition { list } = &*node.node_type else {
+        return convert_term(node);
+    };
+
+    let mut terms = Vec::new();
+    let mut negate_next = false;
+    let mut any_or = false;
+    for item in list {
+        match &*item.node_type {
+            NodeType::Ident { value } if value.eq_ignore_ascii_case("not") => negate_next = true,
+            NodeType::Ident { value } if value.eq_ignore_ascii_case("and") => {}
+            NodeType::Ident { value } if value.eq_ignore_ascii_case("or") => any_or = true,
+            _ => {
+                let term = convert_term(item);
+                terms.push(if negate_next {
+                    MediaCondition::Not(Box::new(term))
+                } else {
+                    term
+                });
+                negate_next = false;
+            }
+        }
+    }
+
+    match terms.len() {
+        0 => MediaCondition::Unknown,
+        1 => terms.pop().unwrap_or(MediaCondition::Unknown),
+        _ if any_or => MediaCondition::Or(terms),
+        _ => MediaCondition::And(terms),
+    }
+}
+
+fn convert_term(node: &CssNode) -> MediaCondition {
+    match &*node.node_type {
+        NodeType::Condition { .. } => convert_condition(node),
+        NodeType::Feature { name, value, .. } => {
+            let value = match value {
+                Some(value) => match convert_value(value) {
+                    Some(value) => Some(value),
+                    None => return MediaCondition::Unknown,
+                },
+                None => None,
+            };
+            MediaCondition::Feature {
+                name: name.cow_to_ascii_lowercase().into_owned(),
+                value,
+            }
+        }
+        NodeType::Range {
+            left,
+            left_comparison,
+            middle,
+            right_comparison,
+            right,
+        } => convert_range(left, left_comparison, middle, right_comparison.as_ref(), right.as_ref()),
+        _ => MediaCondition::Unknown,
+    }
+}
+
+/// Converts `(width >= 600px)`, `(600px <= width)` and `(400px <= width < 700px)` into range tests
+/// with the feature name on the left.
+fn convert_range(
+    left: &CssNode,
+    left_comparison: &CssNode,
+    middle: &CssNode,
+    right_comparison: Option<&CssNode>,
+    right: Option<&CssNode>,
+) -> MediaCondition {
+    let operator = |node: &CssNode| match &*node.node_type {
+        NodeType::Operator(op) => Comparison::parse(op),
+        _ => None,
+    };
+    let range = |name: &str, comparison: Option<Comparison>, value: &CssNode| match (comparison, convert_value(value)) {
+        (Some(comparison), Some(value)) => MediaCondition::Range {
+            name: name.cow_to_ascii_lowercase().into_owned(),
+            comparison,
+            value,
+        },
+        _ => MediaCondition::Unknown,
+    };
+
+    if let NodeType::Ident { value: name } = &*left.node_type {
+        return range(name, operator(left_comparison), middle);
+    }
+    let NodeType::Ident { value: name } = &*middle.node_type else {
+        return MediaCondition::Unknown;
+    };
+    let lower = range(name, operator(left_comparison).map(Comparison::flip), left);
+    match (right_comparison, right) {
+        (Some(comparison), Some(right)) => MediaCondition::And(vec![lower, range(name, operator(comparison), right)]),
+        _ => lower,
+    }
+}
+
+fn convert_value(node: &CssNode) -> Option<FeatureValue> {
+    match &*node.node_type {
+        NodeType::Number { value } => Some(FeatureValue::Number(*value)),
+        NodeType::Dimension { value, unit } => length_to_px(*value, unit).map(FeatureValue::Number),
+        NodeType::Ident { value } => Some(FeatureValue::Ident(value.cow_to_ascii_lowercase().into_owned())),
+        // `<ratio>`: `16 / 9`
+        NodeType::Value { children } => match children.as_slice() {
+            [numerator, _, denominator] => {
+                let number = |node: &CssNode| match &*node.node_type {
+                    NodeType::Number { value } => Some(*value),
+                    _ => None,
+                };
+                let (numerator, denominator) = (number(numerator)?, number(denominator)?);
+                (denominator != 0.0).then(|| FeatureValue::Number(numerator / denominator))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Resolves an absolute or font-relative length to CSS px. Font-relative units use the initial
+/// font size (16px), as media queries are evaluated without an element.
+fn length_to_px(value: f32, unit: &str) -> Option<f32> {
+    let factor = match unit.cow_to_ascii_lowercase().as_ref() {
+        "px" => 1.0,
+        "em" | "rem" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Css3;
+    use gosub_interface::css3::CssOrigin;
+    use gosub_shared::config::ParserConfig;
+
+    fn query(prelude: &str) -> MediaQueryList {
+        let css = format!("@media {prelude} {{ p {{ color: red; }} }}");
+        let sheet = Css3::parse_str(&css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap();
+        sheet.rules[0].media[0].clone()
+    }
+
+    #[test]
+    fn media_types() {
+        assert!(query("print").matches_environment(MediaType::Print, 800.0, 600.0));
+        assert!(!query("print").matches_environment(MediaType::Screen, 800.0, 600.0));
+        assert!(query("screen, print").matches_environment(MediaType::Print, 800.0, 600.0));
+        assert!(query("not print").matches_environment(MediaType::Screen, 800.0, 600.0));
+        assert!(!query("not print").matches_environment(MediaType::Print, 800.0, 600.0));
+        assert!(query("all").matches_environment(MediaType::Print, 800.0, 600.0));
+    }
+
+    #[test]
+    fn width_features_and_ranges() {
+        let screen = |prelude: &str, width: f32| query(prelude).matches_environment(MediaType::Screen, width, 600.0);
+        assert!(screen("(min-width: 600px)", 800.0));
+        assert!(!screen("(min-width: 600px)", 500.0));
+        assert!(screen("(max-width: 40em)", 640.0));
+        assert!(!screen("(max-width: 40em)", 641.0));
+        assert!(screen("screen and (width >= 600px)", 600.0));
+        assert!(screen("(400px <= width < 700px)", 500.0));
+        assert!(!screen("(400px <= width < 700px)", 700.0));
+        assert!(screen("(orientation: landscape)", 800.0));
+        assert!(!screen("print and (min-width: 100px)", 800.0));
+        assert!(!screen("(unknown-feature: 3)", 800.0));
+    }
+}
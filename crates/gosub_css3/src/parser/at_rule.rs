@@ -1,6 +1,7 @@
 mod container;
 mod font_face;
 mod import;
+mod keyframes;
 mod layer;
 mod media;
 mod nest;
@@ -10,6 +11,7 @@ mod starting_style;
 mod supports;
 
 use crate::node::{Node, NodeType};
+use page::PAGE_MARGIN_BOXES;
 use crate::parser::block::BlockParseMode;
 use crate::tokenizer::TokenType;
 use crate::Css3;
@@ -105,6 +107,7 @@ impl Css3<'_> {
             "page" => Some(self.parse_at_rule_page_prelude()?),
             "scope" => Some(self.parse_at_rule_scope_prelude()?),
             "starting-style" => None,
+            name if PAGE_MARGIN_BOXES.contains(&name) => None,
             "supports" => Some(self.parse_at_rule_supports_prelude()?),
             _ => Some(self.read_sequence_at_rule_prelude()?),
         };
@@ -140,12 +143,15 @@ impl Css3<'_> {
         // parse block. They may or may not have nested rules depending on the is_declaration and block type
         let node = match name.cow_to_lowercase().as_ref() {
             "container" => Some(self.parse_block(mode)?),
+            "counter-style" => Some(self.parse_block(BlockParseMode::StyleBlock)?),
             "font-face" => Some(self.parse_block(BlockParseMode::StyleBlock)?),
             "import" => None,
+            "keyframes" | "-webkit-keyframes" => Some(self.parse_at_rule_keyframes_block()?),
             "layer" => Some(self.parse_block(BlockParseMode::RegularBlock)?),
             "media" => Some(self.parse_block(mode)?),
             "nest" => Some(self.parse_block(BlockParseMode::StyleBlock)?),
             "page" => Some(self.parse_block(BlockParseMode::StyleBlock)?),
+            name if PAGE_MARGIN_BOXES.contains(&name) => Some(self.parse_block(BlockParseMode::StyleBlock)?),
             "scope" => Some(self.parse_block(mode)?),
             "starting-style" => Some(self.parse_block(mode)?),
             "supports" => Some(self.parse_block(mode)?),
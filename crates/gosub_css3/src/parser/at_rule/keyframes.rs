@@ -0,0 +1,75 @@
+use crate::node::{Node, NodeType};
+use crate::parser::block::BlockParseMode;
+use crate::tokenizer::TokenType;
+use crate::Css3;
+use cow_utils::CowUtils;
+use gosub_shared::errors::{CssError, CssResult};
+
+impl Css3<'_> {
+    /// Parses the body of a `@keyframes` rule: keyframe blocks, each a comma-separated list of
+    /// keyframe selectors (`from`, `to` or a percentage) followed by a declaration block. Every
+    /// keyframe block becomes a `Rule` whose prelude holds its selectors as `Ident` and
+    /// `Percentage` nodes.
+    pub fn parse_at_rule_keyframes_block(&mut self) -> CssResult<Node> {
+        log::trace!("parse_at_rule_keyframes_block");
+
+        let loc = self.tokenizer.current_location();
+        let mut children = Vec::new();
+
+        loop {
+            self.consume_whitespace_comments();
+            if self.tokenizer.eof() || self.tokenizer.lookahead(0).token_type == TokenType::RCurly {
+                break;
+            }
+            children.push(self.parse_keyframe_block()?);
+        }
+
+        Ok(Node::new(NodeType::Block { children }, loc))
+    }
+
+    fn parse_keyframe_block(&mut self) -> CssResult<Node> {
+        log::trace!("parse_keyframe_block");
+
+        let loc = self.tokenizer.current_location();
+        let mut selectors = vec![];
+
+        loop {
+            self.consume_whitespace_comments();
+            let t = self.consume_any()?;
+            let selector = match t.token_type {
+                TokenType::Ident(value) if value.eq_ignore_ascii_case("from") || value.eq_ignore_ascii_case("to") => {
+                    NodeType::Ident {
+                        value: value.cow_to_ascii_lowercase().into_owned(),
+                    }
+                }
+                TokenType::Percentage(value) => NodeType::Percentage { value },
+                _ => return Err(CssError::with_location("Expected a keyframe selector", t.location)),
+            };
+            selectors.push(Node::new(selector, t.location));
+
+            self.consume_whitespace_comments();
+            let t = self.consume_any()?;
+            match t.token_type {
+                TokenType::Comma => {}
+                TokenType::LCurly => break,
+                _ => {
+                    return Err(CssError::with_location(
+                        "Expected comma or left curly brace",
+                        t.location,
+                    ))
+                }
+            }
+        }
+
+        let block = self.parse_block(BlockParseMode::StyleBlock)?;
+        self.consume(TokenType::RCurly)?;
+
+        Ok(Node::new(
+            NodeType::Rule {
+                prelude: Some(Node::new(NodeType::Container { children: selectors }, loc)),
+                block: Some(block),
+            },
+            loc,
+        ))
+    }
+}
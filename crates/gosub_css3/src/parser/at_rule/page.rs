@@ -2,6 +2,26 @@ use crate::node::{Node, NodeType};
 use crate::Css3;
 use gosub_shared::errors::CssResult;
 
+/// The page-margin boxes that can be nested in an `@page` rule, e.g. `@bottom-center`
+pub const PAGE_MARGIN_BOXES: [&str; 16] = [
+    "top-left-corner",
+    "top-left",
+    "top-center",
+    "top-right",
+    "top-right-corner",
+    "right-top",
+    "right-middle",
+    "right-bottom",
+    "bottom-right-corner",
+    "bottom-right",
+    "bottom-center",
+    "bottom-left",
+    "bottom-left-corner",
+    "left-bottom",
+    "left-middle",
+    "left-top",
+];
+
 impl Css3<'_> {
     pub fn parse_at_rule_page_prelude(&mut self) -> CssResult<Node> {
         log::trace!("parse_at_rule_page_prelude");
@@ -1,7 +1,7 @@
 use core::fmt::Debug;
 use core::slice;
 use cow_utils::CowUtils;
-use gosub_interface::css3::CssOrigin;
+use gosub_interface::css3::{CounterStyleRule, CssOrigin, FontFaceRule, KeyframesRule, PageRule};
 use gosub_shared::byte_stream::Location;
 use gosub_shared::errors::CssError;
 use gosub_shared::errors::CssResult;
@@ -10,6 +10,7 @@ use std::cmp::Ordering;
 use std::fmt::Display;
 
 use crate::colors::{oklab_to_srgb, oklch_to_srgb, RgbColor};
+use crate::media::MediaQueryList;
 
 thread_local! {
     /// Viewport size (CSS px) used to resolve viewport-relative units (`vw`/`vh`/`vmin`/`vmax`)
@@ -29,12 +30,12 @@ pub fn set_layout_viewport(width: f32, height: f32) {
 }
 
 /// The current viewport (CSS px) for resolving viewport-relative units on this thread.
-fn layout_viewport() -> (f32, f32) {
+pub(crate) fn layout_viewport() -> (f32, f32) {
     LAYOUT_VIEWPORT.with(Cell::get)
 }
 
 /// Severity of a CSS error
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Severity {
     /// A critical error that will prevent the stylesheet from being applied
     Error,
@@ -55,7 +56,7 @@ impl Display for Severity {
 }
 
 /// Defines a CSS log during
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct CssLog {
     /// Severity of the error
     pub severity: Severity,
@@ -123,26 +124,19 @@ impl Debug for CssLog {
     }
 }
 
-/// A parsed `@font-face` rule: a logical font family and the (unresolved) URLs that
-/// provide it. URLs are relative to the stylesheet's own URL until resolved by the consumer.
-#[derive(Debug, PartialEq, Clone)]
-pub struct FontFace {
-    /// The `font-family` name this face provides (unquoted).
-    pub family: String,
-    /// Candidate `src: url(...)` targets in declared order.
-    pub sources: Vec<String>,
-    /// The raw `unicode-range` descriptor, if any (e.g. `"U+0000-00FF, U+0131"`). Used to
-    /// pick the subset that covers the content; `None` means the face covers all code points.
-    pub unicode_range: Option<String>,
-}
-
 /// Defines a complete stylesheet with all its rules and the location where it was found
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct CssStylesheet {
     /// List of rules found in this stylesheet
     pub rules: Vec<CssRule>,
     /// `@font-face` rules found in this stylesheet (web fonts).
-    pub font_faces: Vec<FontFace>,
+    pub font_faces: Vec<FontFaceRule>,
+    /// `@counter-style` rules found in this stylesheet.
+    pub counter_styles: Vec<CounterStyleRule>,
+    /// `@keyframes` rules found in this stylesheet.
+    pub keyframes: Vec<KeyframesRule>,
+    /// `@page` rules found in this stylesheet.
+    pub pages: Vec<PageRule>,
     /// Origin of the stylesheet (user agent, author, user)
     pub origin: CssOrigin,
     /// Url or file path where the stylesheet was found
@@ -160,11 +154,20 @@ impl gosub_interface::css3::CssStylesheet for CssStylesheet {
         &self.url
     }
 
-    fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
-        self.font_faces
-            .iter()
-            .map(|f| (f.family.clone(), f.sources.clone(), f.unicode_range.clone()))
-            .collect()
+    fn font_faces(&self) -> Vec<FontFaceRule> {
+        self.font_faces.clone()
+    }
+
+    fn counter_styles(&self) -> Vec<CounterStyleRule> {
+        self.counter_styles.clone()
+    }
+
+    fn keyframes(&self) -> Vec<KeyframesRule> {
+        self.keyframes.clone()
+    }
+
+    fn page_rules(&self) -> Vec<PageRule> {
+        self.pages.clone()
     }
 }
 
@@ -175,6 +178,9 @@ pub struct CssRule {
     pub selectors: Vec<CssSelector>,
     /// Actual declarations that will be applied if the selectors match
     pub declarations: Vec<CssDeclaration>,
+    /// Media query lists of the enclosing `@media` blocks; all must match for the rule to apply.
+    /// Empty for a rule outside any `@media` block.
+    pub media: Vec<MediaQueryList>,
 }
 
 impl CssRule {
@@ -187,6 +193,12 @@ impl CssRule {
     pub fn declarations(&self) -> &Vec<CssDeclaration> {
         &self.declarations
     }
+
+    /// Whether the enclosing `@media` blocks match the current viewport and media type.
+    #[must_use]
+    pub fn matches_media(&self) -> bool {
+        self.media.iter().all(MediaQueryList::matches)
+    }
 }
 
 /// A CSS declaration, which contains a property, value and a flag for !important
@@ -227,11 +239,54 @@ pub enum CssSelectorPart {
     Class(String),
     Id(String),
     PseudoClass(String),
+    /// Functional pseudo-class, like `:not(.a)` or `:nth-child(2n+1)`
+    PseudoFunction(Box<PseudoFunction>),
     PseudoElement(String),
     Combinator(Combinator),
     Type(String),
 }
 
+/// A functional pseudo-class together with its parsed argument
+#[derive(Debug, PartialEq, Clone)]
+pub enum PseudoFunction {
+    /// Pseudo-classes taking a selector list: `:is()`, `:where()`, `:not()` and `:has()`, plus the
+    /// legacy `:matches()`, `:-webkit-any()` and `:-moz-any()` aliases of `:is()`. The alternatives
+    /// of `:has()` are relative selectors and may start with a combinator.
+    Selector(String, CssSelector),
+    /// `:nth-child()`, `:nth-last-child()`, `:nth-of-type()` and `:nth-last-of-type()`: matches the
+    /// positions `a*n + b`, optionally counting only the siblings matching the `of` selector list
+    Nth {
+        name: String,
+        a: i32,
+        b: i32,
+        of: Option<CssSelector>,
+    },
+    /// Any other function, with its argument as written (`:lang(en)`, `:dir(rtl)`)
+    Other(String, String),
+}
+
+impl PseudoFunction {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            PseudoFunction::Selector(name, _) | PseudoFunction::Other(name, _) => name,
+            PseudoFunction::Nth { name, .. } => name,
+        }
+    }
+
+    /// Specificity the pseudo-class adds to its compound: that of its most specific argument, and
+    /// nothing at all for `:where()`
+    fn specificity(&self) -> Specificity {
+        let most_specific = |selector: &CssSelector| selector.specificity().into_iter().max();
+        match self {
+            PseudoFunction::Selector(name, _) if name == "where" => Specificity::new(0, 0, 0),
+            PseudoFunction::Selector(_, selector) => most_specific(selector).unwrap_or(Specificity::new(0, 0, 0)),
+            PseudoFunction::Nth { of: Some(of), .. } => most_specific(of).unwrap_or(Specificity::new(0, 0, 0)),
+            PseudoFunction::Nth { of: None, .. } | PseudoFunction::Other(..) => Specificity::new(0, 0, 0),
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Default, Debug)]
 pub struct AttributeSelector {
     pub name: String,
@@ -285,6 +340,17 @@ impl Debug for CssSelectorPart {
             CssSelectorPart::PseudoClass(name) => {
                 write!(f, ":{name}")
             }
+            CssSelectorPart::PseudoFunction(function) => match function.as_ref() {
+                PseudoFunction::Selector(name, selector) => write!(f, ":{name}({:?})", selector.parts),
+                PseudoFunction::Nth { name, a, b, of: None } => write!(f, ":{name}({a}n+{b})"),
+                PseudoFunction::Nth {
+                    name,
+                    a,
+                    b,
+                    of: Some(of),
+                } => write!(f, ":{name}({a}n+{b} of {:?})", of.parts),
+                PseudoFunction::Other(name, argument) => write!(f, ":{name}({argument})"),
+            },
             CssSelectorPart::PseudoElement(name) => {
                 write!(f, "::{name}")
             }
@@ -366,6 +432,12 @@ impl From<&[CssSelectorPart]> for Specificity {
                 CssSelectorPart::Type(_) => {
                     element_count += 1;
                 }
+                CssSelectorPart::PseudoFunction(function) => {
+                    let Specificity(a, b, c) = function.specificity();
+                    id_count += a;
+                    class_count += b;
+                    element_count += c;
+                }
                 _ => {}
             }
         }
@@ -936,6 +1008,7 @@ mod test {
                 value: CssValue::String("red".to_string()),
                 important: false,
             }],
+            media: vec![],
         };
 
         assert_eq!(rule.selectors().len(), 1);
@@ -4,11 +4,15 @@ use crate::functions::var::resolve_var;
 use crate::matcher::property_definitions::get_css_definitions;
 use crate::matcher::shorthands::{FixList, FixListInfo};
 use crate::matcher::styling::{match_selector, CssProperties, CssProperty, DeclarationProperty};
-use crate::stylesheet::{CssDeclaration, CssStylesheet, CssValue, Specificity};
+use crate::stylesheet::{
+    Combinator, CssDeclaration, CssSelector, CssSelectorPart, CssStylesheet, CssValue, PseudoFunction, Specificity,
+};
 use crate::{load_default_useragent_stylesheet, Css3};
 use cow_utils::CowUtils;
 use gosub_interface::config::HasDocument;
-use gosub_interface::css3::{CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints};
+use gosub_interface::css3::{
+    CssOrigin, CssPropertyMap, CssSystem, HoverFingerprints, InvalidationScope, InvalidationSets,
+};
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
 use gosub_shared::config::ParserConfig;
@@ -66,11 +70,23 @@ impl CssSystem for Css3System {
         sheets: &[Self::Stylesheet],
         pseudo: &str,
     ) -> Option<Self::PropertyMap> {
-        // Only `::before` / `::after` generate boxes; ignore other pseudo-elements.
-        if !matches!(pseudo, "before" | "after") {
+        // Only `::before` / `::after` / `::marker` generate boxes and only `::selection` restyles
+        // existing text; ignore other pseudo-elements.
+        if !matches!(pseudo, "before" | "after" | "marker" | "selection") {
             return None;
         }
         let map = compute_properties::<C>(doc, id, sheets, Some(pseudo))?;
+        // `::selection` only honours colours; a rule setting neither leaves the default highlight.
+        if pseudo == "selection" {
+            let get = |name| <CssProperties as CssPropertyMap<Css3System>>::get(&map, name);
+            get("color").or(get("background-color")).or(get("background"))?;
+            return Some(map);
+        }
+        // A marker box exists for every list item whether or not a rule targets it; the
+        // consumer decides from `display` and `list-style-*` whether to render one.
+        if pseudo == "marker" {
+            return Some(map);
+        }
         // A pseudo-element only generates a box when a matching rule sets `content`. With no
         // `content` declaration there is nothing to render, so report "no pseudo-element".
         <CssProperties as CssPropertyMap<Css3System>>::get(&map, "content")?;
@@ -84,10 +100,22 @@ impl CssSystem for Css3System {
     fn hover_fingerprints(sheets: &[Self::Stylesheet]) -> HoverFingerprints {
         hover_fingerprints_impl(sheets)
     }
+
+    fn invalidation_sets(sheets: &[Self::Stylesheet]) -> InvalidationSets {
+        let mut sets = InvalidationSets::default();
+        for sheet in sheets {
+            for rule in &sheet.rules {
+                for selector in &rule.selectors {
+                    collect_invalidation_sets(&mut sets, selector, InvalidationScope::Element);
+                }
+            }
+        }
+        sets
+    }
 }
 
 /// Shared style-collection core for both real elements (`pseudo == None`) and pseudo-elements
-/// (`pseudo == Some("before"|"after")`). When matching a pseudo-element, selectors are matched
+/// (`pseudo == Some("before"|"after"|"marker")`). When matching a pseudo-element, selectors are matched
 /// against the originating element `id` but only those carrying the matching `::pseudo` part apply.
 fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     doc: &C::Document,
@@ -111,7 +139,7 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     let mut fix_list = FixList::new();
 
     for sheet in sheets {
-        for rule in &sheet.rules {
+        for rule in sheet.rules.iter().filter(|rule| rule.matches_media()) {
             for selector in rule.selectors() {
                 let (matched, specificity) = match_selector::<C>(doc, id, selector, pseudo);
 
@@ -134,14 +162,17 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
                     // `content` carries arbitrary tokens (strings, `attr()`, counters,
                     // quotes) that the property-syntax matcher cannot validate - notably the
                     // empty string `content: ""`. Pass it through verbatim; the render
-                    // pipeline resolves it into generated text itself.
-                    if declaration.property == "content" {
+                    // pipeline resolves it into generated text itself. Transitions, animations
+                    // and transforms are comma-separated lists of timing functions and
+                    // transform functions the pipeline parses too, and their shorthands are
+                    // kept whole rather than expanded.
+                    if declaration.property == "content" || is_animation_property(&declaration.property) {
                         add_property_to_map(
                             &mut css_map_entry,
                             sheet,
                             specificity,
                             &CssDeclaration {
-                                property: "content".to_string(),
+                                property: declaration.property.clone(),
                                 value,
                                 important: declaration.important,
                             },
@@ -290,6 +321,11 @@ fn compute_properties<C: HasDocument<CssSystem = Css3System>>(
     Some(css_map_entry)
 }
 
+/// `transform` and the `transition-*` / `animation-*` properties, shorthands included.
+fn is_animation_property(name: &str) -> bool {
+    name == "transform" || name.starts_with("transition") || name.starts_with("animation")
+}
+
 fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
     use crate::stylesheet::CssSelectorPart;
 
@@ -345,6 +381,62 @@ fn hover_fingerprints_impl(sheets: &[CssStylesheet]) -> HoverFingerprints {
     fp
 }
 
+/// Add the classes, ids and attributes tested by `selector` to `sets`. `outer` is the scope of
+/// the position the selector appears in (the subject for a top-level selector, or the compound
+/// holding an `:is()`/`:not()` argument). Compounds are scoped by the combinator to their right:
+/// none for the subject, a descendant/child combinator reaches the subtree and a sibling
+/// combinator reaches the siblings.
+fn collect_invalidation_sets(sets: &mut InvalidationSets, selector: &CssSelector, outer: InvalidationScope) {
+    fn note(map: &mut HashMap<String, InvalidationScope>, name: &str, scope: InvalidationScope) {
+        let entry = map.entry(name.to_string()).or_insert(scope);
+        *entry = (*entry).max(scope);
+    }
+
+    for part_list in &selector.parts {
+        let mut scope = outer;
+        for part in part_list.iter().rev() {
+            let part_scope = match part {
+                CssSelectorPart::Combinator(Combinator::Namespace) => continue,
+                CssSelectorPart::Combinator(combinator) => {
+                    let reach = match combinator {
+                        Combinator::Descendant | Combinator::Child => InvalidationScope::Subtree,
+                        Combinator::NextSibling | Combinator::SubsequentSibling => InvalidationScope::Siblings,
+                        Combinator::Column | Combinator::Namespace => InvalidationScope::Document,
+                    };
+                    scope = outer.max(reach);
+                    continue;
+                }
+                _ => scope,
+            };
+            match part {
+                CssSelectorPart::Class(name) => note(&mut sets.classes, name, part_scope),
+                CssSelectorPart::Id(name) => note(&mut sets.ids, name, part_scope),
+                CssSelectorPart::Attribute(attr) => {
+                    note(&mut sets.attributes, &attr.name.cow_to_lowercase(), part_scope)
+                }
+                // The link pseudo-classes match on the presence of `href`.
+                CssSelectorPart::PseudoClass(name)
+                    if matches!(name.as_str(), "link" | "any-link" | "-webkit-any-link") =>
+                {
+                    note(&mut sets.attributes, "href", part_scope)
+                }
+                CssSelectorPart::PseudoFunction(function) => match function.as_ref() {
+                    PseudoFunction::Selector(name, inner) if name == "has" => {
+                        sets.relational = true;
+                        collect_invalidation_sets(sets, inner, InvalidationScope::Document);
+                    }
+                    PseudoFunction::Selector(_, inner) => collect_invalidation_sets(sets, inner, part_scope),
+                    PseudoFunction::Nth { of: Some(inner), .. } => {
+                        collect_invalidation_sets(sets, inner, part_scope.max(InvalidationScope::Siblings));
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
 #[must_use]
 pub fn prop_is_inherit(name: &str) -> bool {
     get_css_definitions()
@@ -405,7 +497,7 @@ fn collect_custom_props<C: HasDocument<CssSystem = Css3System>>(
     let mut custom_props: HashMap<String, CssValue> = HashMap::new();
     for node_id in chain {
         for sheet in sheets {
-            for rule in &sheet.rules {
+            for rule in sheet.rules.iter().filter(|rule| rule.matches_media()) {
                 for selector in rule.selectors() {
                     let (matched, _) = match_selector::<C>(doc, node_id, selector, None);
                     if !matched {
@@ -506,3 +598,65 @@ pub fn resolve_functions<C: HasDocument>(
         resolve::<C>(value, doc, id, custom_props)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InvalidationScope::{Document, Element, Siblings, Subtree};
+
+    fn sets(css: &str) -> InvalidationSets {
+        let sheet = Css3::parse_str(css, ParserConfig::default(), CssOrigin::Author, "test.css").unwrap();
+        Css3System::invalidation_sets(slice::from_ref(&sheet))
+    }
+
+    #[test]
+    fn invalidation_scope_follows_combinators() {
+        let sets = sets(
+            ".a { color: red }
+             .b > p.c { color: red }
+             .d + .e span { color: red }
+             .f ~ .g { color: red }
+             #main .a { color: red }
+             [data-x] { color: red }
+             [HREF] > a { color: red }",
+        );
+        assert_eq!(sets.class_scope("a"), Some(Element));
+        assert_eq!(sets.class_scope("b"), Some(Subtree));
+        assert_eq!(sets.class_scope("c"), Some(Element));
+        assert_eq!(sets.class_scope("d"), Some(Siblings));
+        assert_eq!(sets.class_scope("e"), Some(Subtree));
+        assert_eq!(sets.class_scope("f"), Some(Siblings));
+        assert_eq!(sets.class_scope("g"), Some(Element));
+        assert_eq!(sets.id_scope("main"), Some(Subtree));
+        assert_eq!(sets.attribute_scope("data-x"), Some(Element));
+        assert_eq!(sets.attribute_scope("href"), Some(Subtree));
+        assert_eq!(sets.class_scope("unused"), None);
+        assert_eq!(sets.attribute_scope("title"), None);
+        assert!(!sets.relational);
+    }
+
+    #[test]
+    fn invalidation_scope_of_functional_pseudo_classes() {
+        let sets = sets(
+            "p:not(.a) { color: red }
+             :is(.b .c) span { color: red }
+             div:has(> .d) { color: red }
+             li:nth-child(2 of .e) { color: red }
+             [class~=f] { color: red }",
+        );
+        assert_eq!(sets.class_scope("a"), Some(Element));
+        assert_eq!(sets.class_scope("b"), Some(Subtree));
+        assert_eq!(sets.class_scope("c"), Some(Subtree));
+        assert_eq!(sets.class_scope("d"), Some(Document));
+        assert_eq!(sets.class_scope("e"), Some(Siblings));
+        assert!(sets.relational);
+        // An attribute selector on `class` applies to every class name
+        assert_eq!(sets.class_scope("anything"), Some(Element));
+    }
+
+    #[test]
+    fn link_pseudo_classes_depend_on_href() {
+        assert_eq!(sets("a:any-link { color: red }").attribute_scope("href"), Some(Element));
+        assert_eq!(sets(":link span { color: red }").attribute_scope("href"), Some(Subtree));
+    }
+}
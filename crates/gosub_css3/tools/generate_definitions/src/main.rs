@@ -51,7 +51,7 @@ const MISSING_VALUE_PATCHES: [(&str, &str); 4] = [
 /// Pins value definitions that multiple specs define differently, so the
 /// choice is explicit instead of an artifact of decode order (first spec
 /// wins).
-const VALUE_SYNTAX_PATCHES: [(&str, &str); 1] = [
+const VALUE_SYNTAX_PATCHES: [(&str, &str); 4] = [
     // Defined by css-masking-1 (legacy `rect( <top>, <right>, <bottom>,
     // <left> )`, only for `clip`) and css-shapes-1 (the modern basic-shape
     // used by clip-path etc.). Pin the modern form; `clip` reaches the legacy
@@ -60,6 +60,20 @@ const VALUE_SYNTAX_PATCHES: [(&str, &str); 1] = [
         "rect()",
         "rect( [ <length-percentage> | auto ]{4} [ round <'border-radius'> ]? )",
     ),
+    // css-images-3 grammars; css-images-4 adds the conic forms to <gradient> and a
+    // <color-interpolation-method> (`in oklch`) to the linear and radial preludes.
+    (
+        "<gradient>",
+        "<linear-gradient()> | <repeating-linear-gradient()> | <radial-gradient()> | <repeating-radial-gradient()> | <conic-gradient()> | <repeating-conic-gradient()>",
+    ),
+    (
+        "<linear-gradient-syntax>",
+        "[ [ <angle> | <zero> | to <side-or-corner> ] || <color-interpolation-method> ]? ',' <color-stop-list>",
+    ),
+    (
+        "<radial-gradient-syntax>",
+        "[ [ [ <radial-shape> || <radial-size> ]? [ at <position> ]? ] || <color-interpolation-method> ]? ',' <color-stop-list>",
+    ),
 ];
 
 /// Adds the css-sizing-4 bare `fit-content` keyword alongside the functional
@@ -0,0 +1,677 @@
+//! Remote inspection server speaking a subset of the Chrome DevTools Protocol (CDP).
+//!
+//! Register the tabs to expose in a [`Targets`] set and call [`start`]. Tooling that drives
+//! Chrome over CDP lists the targets over HTTP and then opens a WebSocket per tab. Every call
+//! becomes a [`TabCommand`](crate::events::TabCommand), and answers and notifications come from
+//! the [`EngineEvent`] broadcast.
+//!
+//! # Endpoints
+//!
+//! | Method | Path                       | Description                                 |
+//! |--------|----------------------------|---------------------------------------------|
+//! | GET    | `/json/version`            | Browser and protocol version                |
+//! | GET    | `/json`, `/json/list`      | The registered tabs and their WebSocket URL |
+//! | GET    | `/devtools/page/{tab_id}`  | WebSocket upgrade to a session with the tab |
+//!
+//! # Methods
+//!
+//! | Method                     | Tab command                                              |
+//! |----------------------------|----------------------------------------------------------|
+//! | `Page.navigate`            | `Navigate`                                               |
+//! | `Page.captureScreenshot`   | `CaptureScreenshot` (PNG only)                           |
+//! | `DOM.getDocument`          | `GetDocument`                                            |
+//! | `DOM.querySelector`        | `QuerySelector`                                          |
+//! | `DOM.setAttributeValue`    | `SetAttribute`                                           |
+//! | `DOM.removeAttribute`      | `SetAttribute` (without a value)                         |
+//! | `Runtime.evaluate`         | `ExecuteScript`                                          |
+//! | `Input.dispatchMouseEvent` | `MouseMove`, `MouseDown`, `MouseUp`, `MouseScroll`       |
+//! | `Input.dispatchKeyEvent`   | `KeyDown`, `KeyUp`, `TextInput`                          |
+//! | `Input.insertText`         | `TextInput`                                              |
+//!
+//! After `Page.enable` a session receives `Page.frameNavigated` and `Page.loadEventFired`; after
+//! `Network.enable`, `Network.requestWillBeSent`, `responseReceived`, `loadingFinished` and
+//! `loadingFailed`. CDP node ids are the engine's node ids plus one, as CDP reserves 0 for "no
+//! node".
+//!
+//! # Security
+//!
+//! The server only listens on the loopback interface, but a web page can still reach it from the
+//! browser the user runs. Requests whose `Host` is not `127.0.0.1:{port}` or `localhost:{port}`
+//! are refused with `403`, so a DNS-rebound name cannot read the target list, and a WebSocket
+//! upgrade carrying an `Origin` is refused unless the origin is on the allow-list passed to
+//! [`start`] (like Chrome's `--remote-allow-origins`; `*` allows every origin). Clients that send
+//! no `Origin`, such as automation libraries, are not affected.
+//!
+//! # Example
+//! ```no_run,ignore
+//! let targets = gosub_engine::devtools::Targets::new();
+//! targets.add(tab_handle.clone());
+//! gosub_engine::devtools::start(9222, targets, Vec::new(), engine.subscribe_events());
+//! ```
+
+mod protocol;
+mod websocket;
+
+use crate::events::EngineEvent;
+use crate::tab::{TabHandle, TabId};
+use parking_lot::RwLock;
+use protocol::Session;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use websocket::{write_frame, Message, MessageReader, OP_CLOSE, OP_PONG, OP_TEXT};
+
+/// Largest HTTP request head accepted.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// The tabs a devtools server exposes. Clones share the same set, so tabs can be added after
+/// the server has started.
+#[derive(Clone, Default)]
+pub struct Targets {
+    inner: Arc<RwLock<HashMap<TabId, Target>>>,
+}
+
+struct Target {
+    handle: TabHandle,
+    title: String,
+    url: String,
+}
+
+impl Targets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exposes the tab of `handle` until it closes or is removed.
+    pub fn add(&self, handle: TabHandle) {
+        self.inner.write().insert(
+            handle.tab_id,
+            Target {
+                handle,
+                title: String::new(),
+                url: "about:blank".to_string(),
+            },
+        );
+    }
+
+    pub fn remove(&self, tab_id: TabId) {
+        self.inner.write().remove(&tab_id);
+    }
+
+    /// The handle of the tab whose id prints as `tab_id`.
+    fn handle(&self, tab_id: &str) -> Option<TabHandle> {
+        self.inner
+            .read()
+            .iter()
+            .find(|(id, _)| id.to_string() == tab_id)
+            .map(|(_, target)| target.handle.clone())
+    }
+
+    /// Keeps titles and URLs current and forgets closed tabs.
+    fn observe(&self, event: &EngineEvent) {
+        match event {
+            EngineEvent::TitleChanged { tab_id, title } => {
+                if let Some(target) = self.inner.write().get_mut(tab_id) {
+                    target.title = title.clone();
+                }
+            }
+            EngineEvent::LocationChanged { tab_id, url } => {
+                if let Some(target) = self.inner.write().get_mut(tab_id) {
+                    target.url = url.clone();
+                }
+            }
+            EngineEvent::TabClosed { tab_id, .. } => self.remove(*tab_id),
+            _ => {}
+        }
+    }
+
+    /// The `/json/list` answer for a server listening on `addr`.
+    fn list(&self, addr: SocketAddr) -> Value {
+        let targets: Vec<Value> = self
+            .inner
+            .read()
+            .iter()
+            .map(|(tab_id, target)| {
+                json!({
+                    "id": tab_id.to_string(),
+                    "type": "page",
+                    "title": target.title,
+                    "url": target.url,
+                    "webSocketDebuggerUrl": format!("ws://{addr}/devtools/page/{tab_id}"),
+                })
+            })
+            .collect();
+        Value::Array(targets)
+    }
+}
+
+/// Spawn the devtools server on `127.0.0.1:{port}` in a background Tokio task. `allowed_origins`
+/// are the origins (e.g. `http://localhost:3000`, or `*` for any) whose pages may open a
+/// session; see the [module docs](self#security). `events` is the engine's event stream from
+/// [`GosubEngine::subscribe_events`](crate::GosubEngine::subscribe_events).
+///
+/// The function returns immediately; the server runs until the process exits.
+pub fn start(port: u16, targets: Targets, allowed_origins: Vec<String>, events: broadcast::Receiver<EngineEvent>) {
+    tokio::spawn(async move {
+        let result = match TcpListener::bind(format!("127.0.0.1:{port}")).await {
+            Ok(listener) => serve(listener, targets, allowed_origins, events).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log::error!("[devtools] server stopped: {e}");
+        }
+    });
+    log::info!("[devtools] server starting on http://127.0.0.1:{port}/json");
+}
+
+/// Serves devtools clients on an already bound `listener`; [`start`] binds one for you.
+pub async fn serve(
+    listener: TcpListener,
+    targets: Targets,
+    allowed_origins: Vec<String>,
+    events: broadcast::Receiver<EngineEvent>,
+) -> std::io::Result<()> {
+    let addr = listener.local_addr()?;
+    let allowed_origins: Arc<[String]> = allowed_origins.into();
+    log::info!("[devtools] listening on http://{addr}");
+
+    let mut tracker = events.resubscribe();
+    let tracked = targets.clone();
+    tokio::spawn(async move {
+        loop {
+            match tracker.recv().await {
+                Ok(event) => tracked.observe(&event),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    loop {
+        let (stream, _addr) = listener.accept().await?;
+        tokio::spawn(handle(
+            stream,
+            addr,
+            targets.clone(),
+            Arc::clone(&allowed_origins),
+            events.resubscribe(),
+        ));
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    targets: Targets,
+    allowed_origins: Arc<[String]>,
+    events: broadcast::Receiver<EngineEvent>,
+) {
+    let Some(head) = read_request_head(&mut stream).await else {
+        return;
+    };
+    let first_line = head.lines().next().unwrap_or("");
+    let mut parts = first_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+    let header = |name: &str| {
+        head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    };
+
+    if !header("host").is_some_and(|host| is_loopback_host(host, addr.port())) {
+        respond(&mut stream, 403, "Forbidden", &json!({ "error": "host not allowed" })).await;
+        return;
+    }
+
+    if method != "GET" {
+        respond(
+            &mut stream,
+            405,
+            "Method Not Allowed",
+            &json!({ "error": "method not allowed" }),
+        )
+        .await;
+        return;
+    }
+
+    if let Some(tab_id) = path.strip_prefix("/devtools/page/") {
+        if header("origin").is_some_and(|origin| !is_allowed_origin(origin, &allowed_origins)) {
+            respond(&mut stream, 403, "Forbidden", &json!({ "error": "origin not allowed" })).await;
+            return;
+        }
+        let upgrade = header("upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+        let (Some(handle), Some(key), true) = (targets.handle(tab_id), header("sec-websocket-key"), upgrade) else {
+            respond(&mut stream, 404, "Not Found", &json!({ "error": "no such target" })).await;
+            return;
+        };
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket::accept_key(key)
+        );
+        if stream.write_all(response.as_bytes()).await.is_ok() {
+            run_session(stream, Session::new(handle, events)).await;
+        }
+        return;
+    }
+
+    let (code, phrase, body) = match path {
+        "/json/version" => (
+            200,
+            "OK",
+            json!({
+                "Browser": format!("Gosub/{}", env!("CARGO_PKG_VERSION")),
+                "Protocol-Version": "1.3",
+            }),
+        ),
+        "/json" | "/json/list" => (200, "OK", targets.list(addr)),
+        _ => (404, "Not Found", json!({ "error": "not found" })),
+    };
+    respond(&mut stream, code, phrase, &body).await;
+}
+
+/// Whether a `Host` header names this server by its loopback address: `127.0.0.1` or
+/// `localhost`, on `port`. Anything else may be a DNS-rebound name pointing at the loopback.
+fn is_loopback_host(host: &str, port: u16) -> bool {
+    let Some((name, host_port)) = host.rsplit_once(':') else {
+        return false;
+    };
+    (name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost")) && host_port.parse::<u16>().ok() == Some(port)
+}
+
+/// Whether a WebSocket upgrade from a page of `origin` may open a session.
+fn is_allowed_origin(origin: &str, allowed: &[String]) -> bool {
+    allowed
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// Reads up to the blank line ending the request head; `None` when the client hangs up first or
+/// sends more than [`MAX_REQUEST_HEAD`] bytes.
+async fn read_request_head(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 || head.len() + n > MAX_REQUEST_HEAD {
+            return None;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8(head).ok()
+}
+
+async fn respond(stream: &mut TcpStream, code: u16, phrase: &str, body: &Value) {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {code} {phrase}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// What a session loop woke up for.
+enum Step {
+    Message(Option<Message>),
+    Event(Option<EngineEvent>),
+}
+
+/// Pumps one CDP session: calls in, responses and the tab's events out.
+async fn run_session(stream: TcpStream, mut session: Session) {
+    let (read_half, mut writer) = stream.into_split();
+    // Reading a frame is not cancel safe, so a task reads whole messages and hands them over
+    let (msg_tx, mut msg_rx) = mpsc::channel(16);
+    let reader = tokio::spawn(async move {
+        let mut reader = MessageReader::new(read_half);
+        while let Ok(message) = reader.read().await {
+            let close = message == Message::Close;
+            if msg_tx.send(message).await.is_err() || close {
+                break;
+            }
+        }
+    });
+
+    loop {
+        let step = tokio::select! {
+            message = msg_rx.recv() => Step::Message(message),
+            event = session.next_event() => Step::Event(event),
+        };
+        let sent = match step {
+            Step::Message(Some(Message::Text(text))) => {
+                let response = session.handle_message(&text).await;
+                let mut messages = session.take_notifications();
+                messages.push(response);
+                send_all(&mut writer, messages).await
+            }
+            Step::Message(Some(Message::Ping(payload))) => write_frame(&mut writer, OP_PONG, &payload, None).await,
+            Step::Message(Some(Message::Binary | Message::Pong)) => Ok(()),
+            Step::Message(Some(Message::Close) | None) => {
+                let _ = write_frame(&mut writer, OP_CLOSE, &[], None).await;
+                break;
+            }
+            Step::Event(Some(event)) => {
+                session.observe(&event);
+                let messages = session.take_notifications();
+                send_all(&mut writer, messages).await
+            }
+            Step::Event(None) => break,
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+    reader.abort();
+}
+
+async fn send_all(writer: &mut OwnedWriteHalf, messages: Vec<Value>) -> std::io::Result<()> {
+    for message in messages {
+        write_frame(writer, OP_TEXT, message.to_string().as_bytes(), None).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::events::NavigationEvent;
+    use crate::events::TabCommand;
+    use crate::inspect::DomNode;
+    use crate::tab::TabSink;
+    use crate::NavigationId;
+    use tokio::net::tcp::OwnedReadHalf;
+
+    /// A tab stand-in: answers commands the way a tab worker would and reports each command.
+    fn fake_tab(events: broadcast::Sender<EngineEvent>) -> (TabHandle, mpsc::UnboundedReceiver<TabCommand>) {
+        let (cmd_tx, mut cmd_rx) = mpsc::channel(16);
+        let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+        let tab_id = TabId::new();
+        tokio::spawn(async move {
+            while let Some(cmd) = cmd_rx.recv().await {
+                let answers = match &cmd {
+                    TabCommand::Navigate { url } => {
+                        let url = url::Url::parse(url).unwrap();
+                        let nav_id = NavigationId::new();
+                        vec![
+                            NavigationEvent::Committed {
+                                nav_id,
+                                url: url.clone(),
+                            },
+                            NavigationEvent::Finished { nav_id, url },
+                        ]
+                        .into_iter()
+                        .map(|event| EngineEvent::Navigation { tab_id, event })
+                        .collect()
+                    }
+                    TabCommand::GetDocument => vec![EngineEvent::DomDocument {
+                        tab_id,
+                        root: Some(DomNode {
+                            node_id: 0,
+                            node_type: 9,
+                            node_name: "#document".into(),
+                            local_name: String::new(),
+                            node_value: String::new(),
+                            attributes: Vec::new(),
+                            children: Vec::new(),
+                        }),
+                    }],
+                    TabCommand::QuerySelector { node_id, .. } => vec![EngineEvent::QuerySelectorResult {
+                        tab_id,
+                        node_id: Some(node_id + 5),
+                    }],
+                    TabCommand::CaptureScreenshot => vec![EngineEvent::Screenshot {
+                        tab_id,
+                        png: vec![1, 2, 3],
+                    }],
+                    TabCommand::ExecuteScript { .. } => vec![EngineEvent::JavaScriptError {
+                        tab_id,
+                        message: "no script engine available".into(),
+                        line: 0,
+                        column: 0,
+                    }],
+                    _ => Vec::new(),
+                };
+                let _ = seen_tx.send(cmd);
+                for answer in answers {
+                    let _ = events.send(answer);
+                }
+            }
+        });
+        let handle = TabHandle {
+            tab_id,
+            cmd_tx,
+            sink: Arc::new(TabSink::new()),
+        };
+        (handle, seen_rx)
+    }
+
+    async fn http_get(addr: SocketAddr, path: &str) -> Value {
+        request(addr, &format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n"))
+            .await
+            .1
+    }
+
+    /// Sends a raw request head and returns the status code and JSON body of the answer.
+    async fn request(addr: SocketAddr, head: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let code = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (code, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    /// A CDP client over a loopback WebSocket.
+    struct Client {
+        reader: MessageReader<OwnedReadHalf>,
+        writer: OwnedWriteHalf,
+        next_id: u64,
+    }
+
+    impl Client {
+        async fn connect(ws_url: &str) -> Self {
+            let rest = ws_url.strip_prefix("ws://").unwrap();
+            let (host, path) = rest.split_at(rest.find('/').unwrap());
+            let mut stream = TcpStream::connect(host).await.unwrap();
+            let request = format!(
+                "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let head = read_request_head(&mut stream).await.unwrap();
+            assert!(head.starts_with("HTTP/1.1 101"));
+            assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+            let (reader, writer) = stream.into_split();
+            Self {
+                reader: MessageReader::new(reader),
+                writer,
+                next_id: 1,
+            }
+        }
+
+        async fn next(&mut self) -> Value {
+            match self.reader.read().await.unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                other => panic!("unexpected message {other:?}"),
+            }
+        }
+
+        /// Sends a call and returns its response, skipping notifications.
+        async fn call(&mut self, method: &str, params: Value) -> Value {
+            let id = self.next_id;
+            self.next_id += 1;
+            let request = json!({ "id": id, "method": method, "params": params }).to_string();
+            write_frame(&mut self.writer, OP_TEXT, request.as_bytes(), Some([7, 1, 8, 2]))
+                .await
+                .unwrap();
+            loop {
+                let message = self.next().await;
+                if message["id"] == id {
+                    return message;
+                }
+            }
+        }
+
+        async fn notification(&mut self, method: &str) -> Value {
+            loop {
+                let message = self.next().await;
+                if message["method"] == method {
+                    return message["params"].clone();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn drives_a_tab_over_cdp() {
+        let (event_tx, event_rx) = broadcast::channel(64);
+        let (handle, mut seen) = fake_tab(event_tx.clone());
+        let tab_id = handle.tab_id;
+        let targets = Targets::new();
+        targets.add(handle);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, targets, Vec::new(), event_rx));
+
+        let version = http_get(addr, "/json/version").await;
+        assert_eq!(version["Protocol-Version"], "1.3");
+        let list = http_get(addr, "/json/list").await;
+        assert_eq!(list[0]["id"], tab_id.to_string());
+        let ws_url = list[0]["webSocketDebuggerUrl"].as_str().unwrap().to_string();
+
+        let mut client = Client::connect(&ws_url).await;
+        assert_eq!(client.call("Page.enable", json!({})).await["result"], json!({}));
+
+        let navigate = client
+            .call("Page.navigate", json!({ "url": "https://example.com/" }))
+            .await;
+        assert_eq!(navigate["result"]["frameId"], tab_id.to_string());
+        assert!(matches!(
+            seen.recv().await,
+            Some(TabCommand::Navigate { url }) if url == "https://example.com/"
+        ));
+        let frame = client.notification("Page.frameNavigated").await;
+        assert_eq!(frame["frame"]["url"], "https://example.com/");
+        client.notification("Page.loadEventFired").await;
+
+        let document = client.call("DOM.getDocument", json!({})).await;
+        assert_eq!(document["result"]["root"]["nodeId"], 1);
+        assert_eq!(document["result"]["root"]["nodeName"], "#document");
+
+        let found = client
+            .call("DOM.querySelector", json!({ "nodeId": 1, "selector": "p" }))
+            .await;
+        // Node 1 is engine node 0; the stand-in answers with engine node 5
+        assert_eq!(found["result"]["nodeId"], 6);
+
+        let set = client
+            .call(
+                "DOM.setAttributeValue",
+                json!({ "nodeId": 6, "name": "class", "value": "hl" }),
+            )
+            .await;
+        assert_eq!(set["result"], json!({}));
+        client
+            .call("DOM.removeAttribute", json!({ "nodeId": 6, "name": "title" }))
+            .await;
+        let mut edits = Vec::new();
+        while let Some(cmd) = seen.recv().await {
+            if let TabCommand::SetAttribute { node_id, name, value } = cmd {
+                edits.push((node_id, name, value));
+                if edits.len() == 2 {
+                    break;
+                }
+            }
+        }
+        assert_eq!(
+            edits,
+            [
+                (5, "class".to_string(), Some("hl".to_string())),
+                (5, "title".to_string(), None)
+            ]
+        );
+
+        let shot = client.call("Page.captureScreenshot", json!({})).await;
+        assert_eq!(shot["result"]["data"], "AQID");
+
+        let eval = client.call("Runtime.evaluate", json!({ "expression": "1 + 1" })).await;
+        assert_eq!(eval["result"]["exceptionDetails"]["text"], "no script engine available");
+
+        client
+            .call(
+                "Input.dispatchMouseEvent",
+                json!({ "type": "mousePressed", "x": 10, "y": 20, "button": "right" }),
+            )
+            .await;
+        let mut pressed = None;
+        while let Some(cmd) = seen.recv().await {
+            if let TabCommand::MouseDown { x, y, button } = cmd {
+                pressed = Some((x, y, button));
+                break;
+            }
+        }
+        assert_eq!(pressed, Some((10.0, 20.0, crate::events::MouseButton::Right)));
+
+        let unknown = client.call("Tracing.start", json!({})).await;
+        assert_eq!(unknown["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn unknown_targets_are_not_upgraded() {
+        let (_event_tx, event_rx) = broadcast::channel(4);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Targets::new(), Vec::new(), event_rx));
+
+        assert_eq!(http_get(addr, "/json/list").await, json!([]));
+        let missing = http_get(addr, "/devtools/page/nope").await;
+        assert_eq!(missing["error"], "no such target");
+    }
+
+    #[tokio::test]
+    async fn foreign_hosts_and_origins_are_refused() {
+        let (event_tx, event_rx) = broadcast::channel(4);
+        let (handle, _seen) = fake_tab(event_tx);
+        let tab_id = handle.tab_id;
+        let targets = Targets::new();
+        targets.add(handle);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let allowed = vec!["http://localhost:3000".to_string()];
+        tokio::spawn(serve(listener, targets, allowed, event_rx));
+        let port = addr.port();
+
+        // A DNS-rebound name resolving to the loopback still carries its own Host.
+        let rebound = format!("GET /json/list HTTP/1.1\r\nHost: attacker.example:{port}\r\n\r\n");
+        let (code, body) = request(addr, &rebound).await;
+        assert_eq!((code, &body["error"]), (403, &json!("host not allowed")));
+        let (code, _) = request(addr, "GET /json/list HTTP/1.1\r\n\r\n").await;
+        assert_eq!(code, 403, "a request without Host is refused");
+        let local = format!("GET /json/list HTTP/1.1\r\nHost: localhost:{port}\r\n\r\n");
+        assert_eq!(request(addr, &local).await.0, 200);
+
+        let upgrade = |origin: &str| {
+            format!(
+                "GET /devtools/page/{tab_id} HTTP/1.1\r\nHost: {addr}\r\nOrigin: {origin}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            )
+        };
+        let (code, body) = request(addr, &upgrade("https://attacker.example")).await;
+        assert_eq!((code, &body["error"]), (403, &json!("origin not allowed")));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(upgrade("http://localhost:3000").as_bytes())
+            .await
+            .unwrap();
+        let head = read_request_head(&mut stream).await.unwrap();
+        assert!(head.starts_with("HTTP/1.1 101"), "an allowed origin is upgraded");
+    }
+}
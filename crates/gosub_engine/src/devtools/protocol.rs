@@ -0,0 +1,650 @@
+//! Translation between CDP messages and a tab's `TabCommand`s and `EngineEvent`s.
+
+use crate::engine::events::{Modifiers, NavigationEvent, ResourceEvent};
+use crate::engine::types::RequestId;
+use crate::events::{EngineEvent, MouseButton, TabCommand};
+use crate::inspect::DomNode;
+use crate::net::types::ResourceKind;
+use crate::tab::{TabHandle, TabId};
+use base64::Engine as _;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// How long a call waits for the tab to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A failed call, sent back as the `error` member of the response.
+#[derive(Debug)]
+struct CallError {
+    code: i64,
+    message: String,
+}
+
+impl CallError {
+    fn method_not_found(method: &str) -> Self {
+        Self {
+            code: -32601,
+            message: format!("'{method}' wasn't found"),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+
+    fn server(message: impl Into<String>) -> Self {
+        Self {
+            code: -32000,
+            message: message.into(),
+        }
+    }
+}
+
+/// One client's CDP session with one tab.
+pub(crate) struct Session {
+    handle: TabHandle,
+    events: broadcast::Receiver<EngineEvent>,
+    /// Whether the client asked for `Page.*` events.
+    page_enabled: bool,
+    /// Whether the client asked for `Network.*` events.
+    network_enabled: bool,
+    /// CDP request id and resource type of each resource in flight.
+    requests: HashMap<RequestId, (String, &'static str)>,
+    next_request_id: u64,
+    /// Origin of the session's event timestamps.
+    started: Instant,
+    /// Notifications not yet sent to the client.
+    notifications: Vec<Value>,
+}
+
+impl Session {
+    pub(crate) fn new(handle: TabHandle, events: broadcast::Receiver<EngineEvent>) -> Self {
+        Self {
+            handle,
+            events,
+            page_enabled: false,
+            network_enabled: false,
+            requests: HashMap::new(),
+            next_request_id: 1,
+            started: Instant::now(),
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Answers one client message. Events that arrived while waiting on the tab are queued for
+    /// [`Self::take_notifications`] and go out before the response.
+    pub(crate) async fn handle_message(&mut self, text: &str) -> Value {
+        let request: Value = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(e) => return json!({ "error": { "code": -32700, "message": format!("invalid JSON: {e}") } }),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return json!({ "id": id, "error": { "code": -32600, "message": "missing method" } });
+        };
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        match self.call(method, &params).await {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(e) => json!({ "id": id, "error": { "code": e.code, "message": e.message } }),
+        }
+    }
+
+    pub(crate) fn take_notifications(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    /// Waits for the next event about the session's tab; `None` once the engine has shut down.
+    pub(crate) async fn next_event(&mut self) -> Option<EngineEvent> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) if event_tab(&event) == Some(self.handle.tab_id) => return Some(event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("[devtools] session for tab {} missed {n} events", self.handle.tab_id);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Queues the notifications `event` means for the domains the client enabled.
+    pub(crate) fn observe(&mut self, event: &EngineEvent) {
+        match event {
+            EngineEvent::Navigation { event, .. } if self.page_enabled => match event {
+                NavigationEvent::Committed { url, .. } => {
+                    let frame = json!({
+                        "id": self.handle.tab_id.to_string(),
+                        "loaderId": self.handle.tab_id.to_string(),
+                        "url": url.as_str(),
+                        "mimeType": "text/html",
+                    });
+                    self.notify("Page.frameNavigated", json!({ "frame": frame }));
+                }
+                NavigationEvent::Finished { .. } => {
+                    self.notify("Page.loadEventFired", json!({ "timestamp": self.timestamp() }));
+                }
+                _ => {}
+            },
+            EngineEvent::Resource { event, .. } if self.network_enabled => self.observe_resource(event),
+            EngineEvent::TabClosed { .. } => {
+                self.notify("Inspector.detached", json!({ "reason": "target_closed" }));
+            }
+            _ => {}
+        }
+    }
+
+    fn observe_resource(&mut self, event: &ResourceEvent) {
+        let timestamp = self.timestamp();
+        match event {
+            ResourceEvent::Started {
+                request_id, url, kind, ..
+            } => {
+                let cdp_id = self.request_id(*request_id, Some(*kind));
+                self.notify(
+                    "Network.requestWillBeSent",
+                    json!({
+                        "requestId": cdp_id,
+                        "loaderId": self.handle.tab_id.to_string(),
+                        "documentURL": url,
+                        "request": { "url": url, "method": "GET", "headers": {} },
+                        "timestamp": timestamp,
+                        "initiator": { "type": "other" },
+                        "type": resource_type(*kind),
+                    }),
+                );
+            }
+            ResourceEvent::Redirected {
+                request_id,
+                from,
+                to,
+                status,
+                ..
+            } => {
+                let cdp_id = self.request_id(*request_id, None);
+                self.notify(
+                    "Network.requestWillBeSent",
+                    json!({
+                        "requestId": cdp_id,
+                        "loaderId": self.handle.tab_id.to_string(),
+                        "documentURL": to,
+                        "request": { "url": to, "method": "GET", "headers": {} },
+                        "timestamp": timestamp,
+                        "initiator": { "type": "other" },
+                        "redirectResponse": { "url": from, "status": status, "headers": {} },
+                    }),
+                );
+            }
+            ResourceEvent::Headers {
+                request_id,
+                url,
+                status,
+                content_type,
+                headers,
+                ..
+            } => {
+                let cdp_id = self.request_id(*request_id, None);
+                let kind = self.requests.get(request_id).map_or("Other", |(_, kind)| *kind);
+                let mime_type = content_type
+                    .as_deref()
+                    .and_then(|ct| ct.split(';').next())
+                    .unwrap_or("")
+                    .trim();
+                self.notify(
+                    "Network.responseReceived",
+                    json!({
+                        "requestId": cdp_id,
+                        "loaderId": self.handle.tab_id.to_string(),
+                        "timestamp": timestamp,
+                        "type": kind,
+                        "response": {
+                            "url": url,
+                            "status": status,
+                            "statusText": "",
+                            "headers": header_object(headers),
+                            "mimeType": mime_type,
+                        },
+                    }),
+                );
+            }
+            ResourceEvent::Finished {
+                request_id,
+                received_bytes,
+                ..
+            } => {
+                let cdp_id = self.finish_request(*request_id);
+                self.notify(
+                    "Network.loadingFinished",
+                    json!({ "requestId": cdp_id, "timestamp": timestamp, "encodedDataLength": received_bytes }),
+                );
+            }
+            ResourceEvent::Failed { request_id, error, .. } => {
+                let cdp_id = self.finish_request(*request_id);
+                self.notify(
+                    "Network.loadingFailed",
+                    json!({
+                        "requestId": cdp_id,
+                        "timestamp": timestamp,
+                        "type": "Other",
+                        "errorText": error.to_string(),
+                        "canceled": false,
+                    }),
+                );
+            }
+            ResourceEvent::Cancelled { request_id, reason, .. } => {
+                let cdp_id = self.finish_request(*request_id);
+                self.notify(
+                    "Network.loadingFailed",
+                    json!({
+                        "requestId": cdp_id,
+                        "timestamp": timestamp,
+                        "type": "Other",
+                        "errorText": reason.to_string(),
+                        "canceled": true,
+                    }),
+                );
+            }
+            ResourceEvent::Queued { .. } | ResourceEvent::Progress { .. } => {}
+        }
+    }
+
+    async fn call(&mut self, method: &str, params: &Value) -> Result<Value, CallError> {
+        match method {
+            "Page.enable" | "Page.disable" => {
+                self.page_enabled = method == "Page.enable";
+                Ok(json!({}))
+            }
+            "Network.enable" | "Network.disable" => {
+                self.network_enabled = method == "Network.enable";
+                Ok(json!({}))
+            }
+            // Nothing to switch on; accepted so clients that always enable these keep working
+            "DOM.enable" | "DOM.disable" | "Runtime.enable" | "Runtime.disable" => Ok(json!({})),
+            "Page.navigate" => {
+                let url = str_param(params, "url")?;
+                self.send(TabCommand::Navigate { url: url.to_string() }).await?;
+                Ok(json!({ "frameId": self.handle.tab_id.to_string() }))
+            }
+            "Page.captureScreenshot" => {
+                if let Some(format) = params.get("format").and_then(Value::as_str) {
+                    if format != "png" {
+                        return Err(CallError::invalid_params(format!("unsupported format '{format}'")));
+                    }
+                }
+                self.send(TabCommand::CaptureScreenshot).await?;
+                let png = self
+                    .wait_for(|event| match event {
+                        EngineEvent::Screenshot { png, .. } => Some(png.clone()),
+                        _ => None,
+                    })
+                    .await?;
+                if png.is_empty() {
+                    return Err(CallError::server("the tab has nothing to capture"));
+                }
+                Ok(json!({ "data": base64::engine::general_purpose::STANDARD.encode(png) }))
+            }
+            "DOM.getDocument" => {
+                let depth = params.get("depth").and_then(Value::as_i64).unwrap_or(1);
+                self.send(TabCommand::GetDocument).await?;
+                let root = self
+                    .wait_for(|event| match event {
+                        EngineEvent::DomDocument { root, .. } => Some(root.clone()),
+                        _ => None,
+                    })
+                    .await?
+                    .ok_or_else(|| CallError::server("no document has loaded"))?;
+                Ok(json!({ "root": cdp_node(&root, depth) }))
+            }
+            "DOM.querySelector" => {
+                let node_id = params
+                    .get("nodeId")
+                    .and_then(Value::as_u64)
+                    .filter(|&id| id > 0)
+                    .ok_or_else(|| CallError::invalid_params("nodeId is required"))?;
+                let selector = str_param(params, "selector")?;
+                self.send(TabCommand::QuerySelector {
+                    node_id: node_id - 1,
+                    selector: selector.to_string(),
+                })
+                .await?;
+                let found = self
+                    .wait_for(|event| match event {
+                        EngineEvent::QuerySelectorResult { node_id, .. } => Some(*node_id),
+                        _ => None,
+                    })
+                    .await?;
+                Ok(json!({ "nodeId": found.map_or(0, |id| id + 1) }))
+            }
+            "DOM.setAttributeValue" | "DOM.removeAttribute" => {
+                let node_id = params
+                    .get("nodeId")
+                    .and_then(Value::as_u64)
+                    .filter(|&id| id > 0)
+                    .ok_or_else(|| CallError::invalid_params("nodeId is required"))?;
+                let name = str_param(params, "name")?;
+                let value = match method {
+                    "DOM.setAttributeValue" => Some(str_param(params, "value")?.to_string()),
+                    _ => None,
+                };
+                self.send(TabCommand::SetAttribute {
+                    node_id: node_id - 1,
+                    name: name.to_string(),
+                    value,
+                })
+                .await?;
+                Ok(json!({}))
+            }
+            "Runtime.evaluate" => {
+                let expression = str_param(params, "expression")?;
+                self.send(TabCommand::ExecuteScript {
+                    source: expression.to_string(),
+                })
+                .await?;
+                self.wait_for(|event| match event {
+                    EngineEvent::ScriptResult { result, .. } => Some(json!({ "result": remote_object(result) })),
+                    EngineEvent::JavaScriptError {
+                        message, line, column, ..
+                    } => Some(json!({
+                        "result": { "type": "undefined" },
+                        "exceptionDetails": {
+                            "exceptionId": 1,
+                            "text": message,
+                            "lineNumber": line,
+                            "columnNumber": column,
+                        },
+                    })),
+                    _ => None,
+                })
+                .await
+            }
+            "Input.dispatchMouseEvent" => {
+                self.send(mouse_command(params)?).await?;
+                Ok(json!({}))
+            }
+            "Input.dispatchKeyEvent" => {
+                for cmd in key_commands(params)? {
+                    self.send(cmd).await?;
+                }
+                Ok(json!({}))
+            }
+            "Input.insertText" => {
+                let text = str_param(params, "text")?;
+                self.send(TabCommand::TextInput { text: text.to_string() }).await?;
+                Ok(json!({}))
+            }
+            _ => Err(CallError::method_not_found(method)),
+        }
+    }
+
+    async fn send(&self, cmd: TabCommand) -> Result<(), CallError> {
+        self.handle
+            .send(cmd)
+            .await
+            .map_err(|e| CallError::server(e.to_string()))
+    }
+
+    /// Waits for the tab's answer to a command. Events arriving before it are observed as usual.
+    async fn wait_for<T>(&mut self, mut pick: impl FnMut(&EngineEvent) -> Option<T>) -> Result<T, CallError> {
+        let deadline = tokio::time::Instant::now() + REPLY_TIMEOUT;
+        loop {
+            let event = tokio::time::timeout_at(deadline, self.next_event())
+                .await
+                .map_err(|_| CallError::server("the tab did not answer in time"))?
+                .ok_or_else(|| CallError::server("the engine has shut down"))?;
+            if let Some(answer) = pick(&event) {
+                return Ok(answer);
+            }
+            self.observe(&event);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.notifications.push(json!({ "method": method, "params": params }));
+    }
+
+    /// Seconds since the session started, as CDP's monotonic timestamps.
+    fn timestamp(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// The CDP id for `request_id`, handing out the next one for a resource not seen before.
+    fn request_id(&mut self, request_id: RequestId, kind: Option<ResourceKind>) -> String {
+        if let Some((cdp_id, _)) = self.requests.get(&request_id) {
+            return cdp_id.clone();
+        }
+        let cdp_id = self.next_request_id.to_string();
+        self.next_request_id += 1;
+        self.requests
+            .insert(request_id, (cdp_id.clone(), kind.map_or("Other", resource_type)));
+        cdp_id
+    }
+
+    /// The CDP id for `request_id`, which is done with.
+    fn finish_request(&mut self, request_id: RequestId) -> String {
+        let cdp_id = self.request_id(request_id, None);
+        self.requests.remove(&request_id);
+        cdp_id
+    }
+}
+
+/// The tab an event is about, for the events a session cares about.
+fn event_tab(event: &EngineEvent) -> Option<TabId> {
+    match event {
+        EngineEvent::Navigation { tab_id, .. }
+        | EngineEvent::Resource { tab_id, .. }
+        | EngineEvent::Screenshot { tab_id, .. }
+        | EngineEvent::DomDocument { tab_id, .. }
+        | EngineEvent::QuerySelectorResult { tab_id, .. }
+        | EngineEvent::ScriptResult { tab_id, .. }
+        | EngineEvent::JavaScriptError { tab_id, .. }
+        | EngineEvent::TabClosed { tab_id, .. } => Some(*tab_id),
+        _ => None,
+    }
+}
+
+fn str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, CallError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| CallError::invalid_params(format!("{name} is required")))
+}
+
+fn f32_param(params: &Value, name: &str) -> Result<f32, CallError> {
+    params
+        .get(name)
+        .and_then(Value::as_f64)
+        .map(|v| v as f32)
+        .ok_or_else(|| CallError::invalid_params(format!("{name} is required")))
+}
+
+/// A DOM snapshot node as CDP's `DOM.Node`, with children `depth` levels deep (all for -1).
+fn cdp_node(node: &DomNode, depth: i64) -> Value {
+    let mut value = Map::new();
+    value.insert("nodeId".into(), json!(node.node_id + 1));
+    value.insert("backendNodeId".into(), json!(node.node_id + 1));
+    value.insert("nodeType".into(), json!(node.node_type));
+    value.insert("nodeName".into(), json!(node.node_name));
+    value.insert("localName".into(), json!(node.local_name));
+    value.insert("nodeValue".into(), json!(node.node_value));
+    value.insert("childNodeCount".into(), json!(node.children.len()));
+    if node.node_type == 1 {
+        let attributes: Vec<&str> = node
+            .attributes
+            .iter()
+            .flat_map(|(name, value)| [name.as_str(), value.as_str()])
+            .collect();
+        value.insert("attributes".into(), json!(attributes));
+    }
+    if depth != 0 {
+        let children: Vec<Value> = node.children.iter().map(|child| cdp_node(child, depth - 1)).collect();
+        value.insert("children".into(), Value::Array(children));
+    }
+    Value::Object(value)
+}
+
+/// A script result as CDP's `Runtime.RemoteObject`, passed by value.
+fn remote_object(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "type": "object", "subtype": "null", "value": null }),
+        Value::Bool(_) => json!({ "type": "boolean", "value": value }),
+        Value::Number(n) => json!({ "type": "number", "value": value, "description": n.to_string() }),
+        Value::String(_) => json!({ "type": "string", "value": value }),
+        Value::Array(_) => json!({ "type": "object", "subtype": "array", "value": value }),
+        Value::Object(_) => json!({ "type": "object", "value": value }),
+    }
+}
+
+/// CDP's `Network.ResourceType` for a resource kind.
+fn resource_type(kind: ResourceKind) -> &'static str {
+    match kind {
+        ResourceKind::Document => "Document",
+        ResourceKind::Stylesheet => "Stylesheet",
+        ResourceKind::Script { .. } => "Script",
+        ResourceKind::Image => "Image",
+        ResourceKind::Font => "Font",
+        ResourceKind::Media => "Media",
+        ResourceKind::Xhr => "XHR",
+        ResourceKind::Fetch => "Fetch",
+        ResourceKind::WebSocket => "WebSocket",
+        ResourceKind::Other => "Other",
+    }
+}
+
+/// Response headers as a CDP `Network.Headers` object; repeated headers are joined by newlines.
+fn header_object(headers: &[(String, String)]) -> Value {
+    let mut object = Map::new();
+    for (name, value) in headers {
+        match object.get_mut(name) {
+            Some(Value::String(existing)) => {
+                existing.push('\n');
+                existing.push_str(value);
+            }
+            _ => {
+                object.insert(name.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+/// The tab command for an `Input.dispatchMouseEvent`.
+fn mouse_command(params: &Value) -> Result<TabCommand, CallError> {
+    let button = match params.get("button").and_then(Value::as_str) {
+        Some("middle") => MouseButton::Middle,
+        Some("right") => MouseButton::Right,
+        _ => MouseButton::Left,
+    };
+    let delta = |name| params.get(name).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+
+    match params.get("type").and_then(Value::as_str) {
+        Some("mouseMoved") => Ok(TabCommand::MouseMove {
+            x: f32_param(params, "x")?,
+            y: f32_param(params, "y")?,
+        }),
+        Some("mousePressed") => Ok(TabCommand::MouseDown {
+            x: f32_param(params, "x")?,
+            y: f32_param(params, "y")?,
+            button,
+        }),
+        Some("mouseReleased") => Ok(TabCommand::MouseUp {
+            x: f32_param(params, "x")?,
+            y: f32_param(params, "y")?,
+            button,
+        }),
+        Some("mouseWheel") => Ok(TabCommand::MouseScroll {
+            delta_x: delta("deltaX"),
+            delta_y: delta("deltaY"),
+        }),
+        other => Err(CallError::invalid_params(format!(
+            "unsupported mouse event type {other:?}"
+        ))),
+    }
+}
+
+/// The tab commands for an `Input.dispatchKeyEvent`. A `keyDown` carrying text also types it,
+/// as it would in a browser; `rawKeyDown` does not.
+fn key_commands(params: &Value) -> Result<Vec<TabCommand>, CallError> {
+    let key = params.get("key").and_then(Value::as_str).unwrap_or("").to_string();
+    let code = params.get("code").and_then(Value::as_str).unwrap_or("").to_string();
+    let text = params.get("text").and_then(Value::as_str).unwrap_or("");
+    // CDP's bit field: Alt=1, Ctrl=2, Meta=4, Shift=8
+    let bits = params.get("modifiers").and_then(Value::as_u64).unwrap_or(0);
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::ALT, bits & 1 != 0);
+    modifiers.set(Modifiers::CONTROL, bits & 2 != 0);
+    modifiers.set(Modifiers::META, bits & 4 != 0);
+    modifiers.set(Modifiers::SHIFT, bits & 8 != 0);
+
+    let mut commands = Vec::new();
+    match params.get("type").and_then(Value::as_str) {
+        Some("keyDown") => {
+            commands.push(TabCommand::KeyDown { key, code, modifiers });
+            if !text.is_empty() {
+                commands.push(TabCommand::TextInput { text: text.to_string() });
+            }
+        }
+        Some("rawKeyDown") => commands.push(TabCommand::KeyDown { key, code, modifiers }),
+        Some("keyUp") => commands.push(TabCommand::KeyUp { key, code, modifiers }),
+        Some("char") => commands.push(TabCommand::TextInput { text: text.to_string() }),
+        other => {
+            return Err(CallError::invalid_params(format!(
+                "unsupported key event type {other:?}"
+            )))
+        }
+    }
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_id: u64, node_type: u16, name: &str, children: Vec<DomNode>) -> DomNode {
+        DomNode {
+            node_id,
+            node_type,
+            node_name: name.to_string(),
+            local_name: String::new(),
+            node_value: String::new(),
+            attributes: Vec::new(),
+            children,
+        }
+    }
+
+    #[test]
+    fn document_nodes_are_offset_and_depth_limited() {
+        let mut html = node(1, 1, "HTML", vec![node(2, 1, "BODY", Vec::new())]);
+        html.attributes = vec![("lang".into(), "en".into())];
+        let doc = node(0, 9, "#document", vec![html]);
+
+        let shallow = cdp_node(&doc, 1);
+        assert_eq!(shallow["nodeId"], 1);
+        assert_eq!(shallow["children"][0]["nodeId"], 2);
+        assert_eq!(shallow["children"][0]["attributes"], json!(["lang", "en"]));
+        assert_eq!(shallow["children"][0]["childNodeCount"], 1);
+        assert!(shallow["children"][0].get("children").is_none());
+
+        let full = cdp_node(&doc, -1);
+        assert_eq!(full["children"][0]["children"][0]["nodeName"], "BODY");
+    }
+
+    #[test]
+    fn key_events_map_to_tab_commands() {
+        let commands = key_commands(&json!({ "type": "keyDown", "key": "a", "text": "a", "modifiers": 8 })).unwrap();
+        assert!(matches!(
+            &commands[..],
+            [TabCommand::KeyDown { key, modifiers, .. }, TabCommand::TextInput { text }]
+                if key == "a" && *modifiers == Modifiers::SHIFT && text == "a"
+        ));
+
+        let commands = key_commands(&json!({ "type": "rawKeyDown", "key": "Enter", "text": "\r" })).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(key_commands(&json!({ "type": "bogus" })).is_err());
+    }
+}
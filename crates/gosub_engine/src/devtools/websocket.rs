@@ -0,0 +1,196 @@
+//! The parts of RFC 6455 (WebSocket) the devtools server needs: the opening handshake's accept
+//! key and reading and writing frames.
+
+use base64::Engine as _;
+use sha1::{Digest, Sha1};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// GUID every server appends to the client's key (RFC 6455 §1.3).
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message accepted from a client. CDP requests are small; this only guards the server
+/// against a peer announcing an absurd length.
+pub(crate) const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+pub(crate) const OP_CONTINUATION: u8 = 0x0;
+pub(crate) const OP_TEXT: u8 = 0x1;
+pub(crate) const OP_BINARY: u8 = 0x2;
+pub(crate) const OP_CLOSE: u8 = 0x8;
+pub(crate) const OP_PING: u8 = 0x9;
+pub(crate) const OP_PONG: u8 = 0xA;
+
+/// A complete message, with fragmented data frames joined. CDP only speaks text, so the
+/// payloads of binary messages and pongs are dropped.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Message {
+    Text(String),
+    Binary,
+    Ping(Vec<u8>),
+    Pong,
+    Close,
+}
+
+/// The `Sec-WebSocket-Accept` value answering a client's `Sec-WebSocket-Key`.
+pub(crate) fn accept_key(key: &str) -> String {
+    let mut sha = Sha1::new();
+    sha.update(key.trim().as_bytes());
+    sha.update(HANDSHAKE_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(sha.finalize())
+}
+
+/// Reads whole messages off a stream.
+pub(crate) struct MessageReader<R> {
+    reader: R,
+    /// Opcode and payload so far of a fragmented data message.
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: AsyncRead + Unpin> MessageReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self { reader, partial: None }
+    }
+
+    /// Reads the next message. Control frames may arrive between the fragments of a data
+    /// message; they are returned as they come and the data message continues with the next call.
+    pub(crate) async fn read(&mut self) -> io::Result<Message> {
+        loop {
+            let (fin, opcode, payload) = read_frame(&mut self.reader).await?;
+            match (opcode, self.partial.as_mut()) {
+                (OP_CLOSE, _) => return Ok(Message::Close),
+                (OP_PING, _) => return Ok(Message::Ping(payload)),
+                (OP_PONG, _) => return Ok(Message::Pong),
+                (OP_TEXT | OP_BINARY, None) => self.partial = Some((opcode, payload)),
+                (OP_CONTINUATION, Some((_, buf))) => {
+                    if buf.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+                    }
+                    buf.extend_from_slice(&payload);
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unexpected frame opcode {opcode:#x}"),
+                    ))
+                }
+            }
+
+            if fin {
+                if let Some((opcode, buf)) = self.partial.take() {
+                    if opcode == OP_BINARY {
+                        return Ok(Message::Binary);
+                    }
+                    return String::from_utf8(buf)
+                        .map(Message::Text)
+                        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "text message is not UTF-8"));
+                }
+            }
+        }
+    }
+}
+
+/// Reads a single frame as `(fin, opcode, unmasked payload)`.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7F {
+        126 => u64::from(reader.read_u16().await?),
+        127 => reader.read_u64().await?,
+        len => u64::from(len),
+    };
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await?;
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Writes `payload` as one final frame. Servers send unmasked frames; clients must pass a `mask`.
+pub(crate) async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[tokio::test]
+    async fn masked_frames_round_trip() {
+        let long = "x".repeat(70_000);
+        let mut wire = Vec::new();
+        write_frame(&mut wire, OP_TEXT, b"hello", Some([1, 2, 3, 4]))
+            .await
+            .unwrap();
+        write_frame(&mut wire, OP_PING, b"", None).await.unwrap();
+        write_frame(&mut wire, OP_TEXT, long.as_bytes(), Some([9, 8, 7, 6]))
+            .await
+            .unwrap();
+
+        let mut reader = MessageReader::new(wire.as_slice());
+        assert_eq!(reader.read().await.unwrap(), Message::Text("hello".into()));
+        assert_eq!(reader.read().await.unwrap(), Message::Ping(Vec::new()));
+        assert_eq!(reader.read().await.unwrap(), Message::Text(long));
+    }
+
+    #[tokio::test]
+    async fn fragments_are_joined_around_control_frames() {
+        // "Hel" + ping + "lo", as a client would split a message
+        let mut wire = vec![0x01, 0x03];
+        wire.extend_from_slice(b"Hel");
+        wire.extend_from_slice(&[0x89, 0x00]);
+        wire.extend_from_slice(&[0x80, 0x02]);
+        wire.extend_from_slice(b"lo");
+
+        let mut reader = MessageReader::new(wire.as_slice());
+        assert_eq!(reader.read().await.unwrap(), Message::Ping(Vec::new()));
+        assert_eq!(reader.read().await.unwrap(), Message::Text("Hello".into()));
+        assert!(reader.read().await.is_err());
+    }
+}
@@ -10,6 +10,8 @@ mod errors;
 pub mod events;
 
 pub mod cookies;
+pub mod inspect;
+pub mod media;
 pub mod storage;
 pub mod tab;
 pub mod zone;
@@ -8,26 +8,49 @@
 //! context via `set_document`, after which the context rebuilds whichever render
 //! representation the active backend consumes.
 
+use crate::engine::errors::EngineError;
 use crate::engine::storage::{StorageArea, StorageHandles};
 use crate::html::EngineDocument;
 use gosub_config::{Config, HasConfig};
 use gosub_render_pipeline::rasterizer::{
-    collect_placed_gpu_tiles, cpu_cached_tiles, rasterize_parallel, rasterize_sequential, BakedTile, RasterStrategy,
-    Rasterable, TilePixelCache,
+    collect_placed_gpu_tiles, cpu_cached_tiles, rasterize_parallel, rasterize_sequential, refresh_compositing,
+    BakedTile, RasterStrategy, Rasterable, TilePixelCache,
 };
-use gosub_render_pipeline::render::{Color, DisplayItem, RenderContext, RenderList, Viewport};
-use std::sync::Arc;
+use gosub_render_pipeline::render::{
+    argb_u32_to_rgba8, composite_tiles, Color, DisplayItem, RenderContext, RenderList, TileTarget, Viewport,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 
 use crate::html::RenderConfiguration;
-use gosub_interface::css3::{CssSystem, HoverFingerprints};
-use gosub_interface::document::Document as _;
+use gosub_interface::css3::{CssSystem, HoverFingerprints, InvalidationSets};
+use gosub_interface::document::{Document, DomMutation};
+use gosub_render_pipeline::animation::{AnimatedStyles, AnimationTimeline};
+use gosub_render_pipeline::common::browser_state::BlockedFont;
+use gosub_render_pipeline::common::document::pipeline_doc::{GosubDocumentAdapter, PipelineDocument, StyleSnapshot};
+use gosub_render_pipeline::common::geo::{Dimension, Rect};
+use gosub_render_pipeline::common::media::{DecodedImage, ImageAnimation, MediaId};
 use gosub_render_pipeline::common::texture::TilePixels;
+use gosub_render_pipeline::find::FindMatches;
+use gosub_render_pipeline::invalidation::{damage_rects, invalidate, style_damage, Damage, Restyle};
 use gosub_render_pipeline::layering::layer::LayerList;
-use gosub_render_pipeline::layouter::LayoutElementId;
+use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+use gosub_render_pipeline::layouter::{LayoutElementId, LayoutTree};
+use gosub_render_pipeline::media_element::{self, MediaControls, MediaPresentation};
+use gosub_render_pipeline::paged::{self, PrintedDocument};
 use gosub_render_pipeline::painter::{PaintScene, Painter};
 use gosub_render_pipeline::render::backend::{CachedTile, ExternalHandle};
+use gosub_render_pipeline::selection::{self, TextPosition, TextSelection};
 use gosub_shared::node::NodeId;
 use std::any::Any;
+use std::time::{Duration, Instant};
+
+/// Presses closer together than this (in time and in CSS px) count as a double or triple click.
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+const MULTI_CLICK_SLOP: f64 = 4.0;
+/// How far outside the viewport, in CSS pixels, a lazy image starts loading, so it has usually
+/// arrived by the time it scrolls into view.
+const LAZY_LOAD_MARGIN: f64 = 1250.0;
 
 /// GPU-scene cache: the layer list (for hit-testing) plus the whole-page paint command list
 /// (for the backend to render). The GPU equivalent of [`PipelineCache`] - it skips tiling,
@@ -37,6 +60,14 @@ struct SceneCache {
     scene: PaintScene,
 }
 
+/// What the last layout was made with, kept so DOM mutations can be laid out incrementally: the
+/// document adapter with its cached styles, and a snapshot of those styles taken before the first
+/// mutation since, which the restyle is compared against.
+struct LayoutState<C: RenderConfiguration> {
+    adapter: Arc<GosubDocumentAdapter<C>>,
+    snapshot: Option<StyleSnapshot<C>>,
+}
+
 /// True if `node_id` could be affected by a `:hover` rule, per the [`HoverFingerprints`]
 /// computed by the CSS system. Uses only [`Document`] trait methods so it stays generic.
 fn hover_matches<C: RenderConfiguration>(fp: &HoverFingerprints, doc: &EngineDocument<C>, node_id: NodeId) -> bool {
@@ -102,6 +133,14 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     style_dirty: bool,
     /// Layout dirty flag, used to determine if the layout has changed
     layout_dirty: bool,
+    /// DOM mutations made through [`Self::mutate_document`] since the last render. Unless a full
+    /// render is due anyway, the next rebuild restyles, lays out and repaints only what they reach.
+    pending_mutations: Vec<DomMutation>,
+    /// The classes, ids and attributes the document's selectors test; built on first use and
+    /// dropped when the stylesheets change.
+    invalidation_sets: Option<InvalidationSets>,
+    /// The adapter of the last layout, for incremental relayout.
+    layout_state: Option<LayoutState<C>>,
 
     /// Current scroll offset in CSS pixels.
     scroll_x: f64,
@@ -133,6 +172,53 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
     /// The href of the link currently under the pointer, if any.
     pub hover_link_url: Option<String>,
 
+    /// Where the text selection starts and the end it is extended from; `None` without one.
+    selection_anchor: Option<TextPosition>,
+    selection_focus: Option<TextPosition>,
+    /// The text between anchor and focus, handed to the painter for highlighting.
+    selection: TextSelection,
+    /// True while the primary button is held after a press on the page, so moves drag the focus.
+    selecting: bool,
+    /// Time, position and click count of the last primary press, for double- and triple-clicks.
+    last_press: Option<(Instant, f64, f64, u8)>,
+    /// Elements whose paint changed since the last paint without a re-layout: text whose selection
+    /// or find highlight changed, iframes with a new rendering. Repainted on the hover path.
+    paint_dirty_elements: Vec<LayoutElementId>,
+
+    /// The find-in-page query and its case sensitivity; `None` when find is not active.
+    find_query: Option<(String, bool)>,
+    /// The layer list `find` was searched in, so a query is searched again after a re-layout.
+    find_layer_list: Weak<LayerList>,
+    /// Matches of `find_query`, handed to the painter for its overlay.
+    find: FindMatches,
+    /// The latest rendering of each iframe's nested document, in `media_store`, by iframe element.
+    frames: HashMap<NodeId, MediaId>,
+    /// The playback state of each video and audio element; a shown video frame lives in
+    /// `media_store` like a frame's rendering.
+    media: HashMap<NodeId, MediaPresentation>,
+    /// The frame each animated image shows, by the image's media id; see
+    /// [`Self::advance_image_animations`].
+    image_frames: HashMap<MediaId, MediaId>,
+    /// Web font faces in their block period; see [`Self::set_blocked_fonts`].
+    blocked_fonts: Vec<BlockedFont>,
+    /// When each animated image started playing, by its media id.
+    animation_starts: HashMap<MediaId, Instant>,
+    /// The still-running animated images of `animated_layer_list`, with the elements showing them.
+    animated_images: Vec<(LayoutElementId, MediaId, ImageAnimation)>,
+    animated_layer_list: Weak<LayerList>,
+    /// When animated images were paused, while drawing is suspended.
+    animations_paused_at: Option<Instant>,
+    /// The CSS transition and animation values the document adapters answer ahead of the
+    /// cascade, written by `animation_timeline`; see [`Self::advance_css_animations`].
+    animated_styles: Arc<AnimatedStyles>,
+    animation_timeline: AnimationTimeline,
+    /// The layer list the timeline last scanned, and whether a hover change may have started or
+    /// stopped animations on it.
+    animation_layer_list: Weak<LayerList>,
+    animation_rescan: bool,
+    /// The display's device-pixel ratio, which responsive images pick their source for.
+    device_pixel_ratio: f32,
+
     /// The active backend's per-tile rasterizer and how to drive it. Built once by the tab
     /// worker from the engine's `RenderBackend` (replacing the former per-backend cfg cascade).
     rasterizer: Option<Box<dyn Rasterable + Send + Sync>>,
@@ -151,6 +237,7 @@ pub struct BrowsingContext<C: RenderConfiguration = crate::html::DefaultRenderCo
 impl<C: RenderConfiguration> BrowsingContext<C> {
     /// Creates a new runtime browsing context, sharing the given per-engine settings store.
     pub(crate) fn new(config_store: Config) -> BrowsingContext<C> {
+        let animated_styles = Arc::new(AnimatedStyles::new());
         Self {
             document: None,
             storage: None,
@@ -161,6 +248,9 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             dom_dirty: false,
             style_dirty: false,
             layout_dirty: false,
+            pending_mutations: Vec::new(),
+            invalidation_sets: None,
+            layout_state: None,
             scroll_x: 0.0,
             scroll_y: 0.0,
             scroll_dirty: false,
@@ -174,6 +264,28 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             hover_fingerprints: None,
             hover_chain_sensitive: false,
             hover_link_url: None,
+            selection_anchor: None,
+            selection_focus: None,
+            selection: TextSelection::default(),
+            selecting: false,
+            last_press: None,
+            paint_dirty_elements: Vec::new(),
+            find_query: None,
+            find_layer_list: Weak::new(),
+            find: FindMatches::default(),
+            frames: HashMap::new(),
+            media: HashMap::new(),
+            image_frames: HashMap::new(),
+            blocked_fonts: Vec::new(),
+            animation_starts: HashMap::new(),
+            animated_images: Vec::new(),
+            animated_layer_list: Weak::new(),
+            animations_paused_at: None,
+            animated_styles: Arc::clone(&animated_styles),
+            animation_timeline: AnimationTimeline::new(animated_styles),
+            animation_layer_list: Weak::new(),
+            animation_rescan: false,
+            device_pixel_ratio: 1.0,
             rasterizer: None,
             raster_strategy: RasterStrategy::None,
             media_store: std::sync::Arc::new(gosub_render_pipeline::common::media::MediaStore::new()),
@@ -213,11 +325,92 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.invalidate_render();
         self.pipeline_cache = None;
         self.scene_cache = None;
+        self.pending_mutations.clear();
+        self.invalidation_sets = None;
+        self.layout_state = None;
         self.hover_dirty = false;
         self.hover_leaf = None;
         self.hover_layout_element = None;
         self.hover_fingerprints = None;
         self.hover_chain_sensitive = false;
+        self.selection_anchor = None;
+        self.selection_focus = None;
+        self.selection = TextSelection::default();
+        self.selecting = false;
+        self.paint_dirty_elements.clear();
+        self.find_query = None;
+        self.find_layer_list = Weak::new();
+        self.find = FindMatches::default();
+        for (_, media_id) in self.frames.drain() {
+            self.media_store.remove(media_id);
+        }
+        for frame in self.media.drain().filter_map(|(_, presentation)| presentation.frame) {
+            self.media_store.remove(frame);
+        }
+        self.image_frames.clear();
+        self.blocked_fonts.clear();
+        self.animation_starts.clear();
+        self.animated_images.clear();
+        self.animated_layer_list = Weak::new();
+        self.animation_timeline.reset();
+        self.animation_layer_list = Weak::new();
+        self.animation_rescan = false;
+    }
+
+    /// Runs `f` against the document in place and records the mutations it makes, so the next
+    /// rebuild restyles, lays out and repaints only what they reach instead of the whole page.
+    /// The styles of the last rendering are snapshotted first, for [`style_damage`] to compare
+    /// the restyle against. `Ok(None)` without a document.
+    ///
+    /// Fails with [`EngineError::DocumentShared`], leaving the document untouched, while anything
+    /// besides the context and its last rendering (e.g. a devtools snapshot) holds the document.
+    pub fn mutate_document<R>(
+        &mut self,
+        f: impl FnOnce(&mut EngineDocument<C>) -> R,
+    ) -> Result<Option<R>, EngineError> {
+        let Some(mut document) = self.document.take() else {
+            return Ok(None);
+        };
+        let mutate = |doc: &mut EngineDocument<C>| {
+            doc.record_mutations(true);
+            let result = f(doc);
+            let mutations = doc.take_mutations();
+            doc.record_mutations(false);
+            (result, mutations)
+        };
+
+        // The last rendering's adapter holds the other reference, so edit through it.
+        let state = self
+            .layout_state
+            .as_mut()
+            .filter(|state| Arc::ptr_eq(&state.adapter.document(), &document));
+        let edited = match state {
+            Some(state) => {
+                if state.snapshot.is_none() {
+                    state.snapshot = Some(state.adapter.snapshot());
+                }
+                drop(document);
+                let edited = state.adapter.edit_document(mutate);
+                self.document = Some(state.adapter.document());
+                edited
+            }
+            None => {
+                let edited = Arc::get_mut(&mut document).map(mutate);
+                self.document = Some(document);
+                edited
+            }
+        };
+        let (result, mutations) = edited.ok_or(EngineError::DocumentShared)?;
+
+        if mutations.contains(&DomMutation::Stylesheets) {
+            self.invalidation_sets = None;
+            self.hover_fingerprints = None;
+        }
+        if !mutations.is_empty() {
+            self.dom_dirty = true;
+            self.pending_mutations.extend(mutations);
+        }
+        Ok(Some(result))
     }
 
     /// Update the viewport SIZE. Only triggers a full re-layout when width or height changes.
@@ -234,6 +427,20 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.scene_cache = None;
     }
 
+    /// Update the display's device-pixel ratio. A change re-lays-out the page, as `srcset` and
+    /// `<picture>` may pick other images for it. Returns whether it changed.
+    pub fn set_device_pixel_ratio(&mut self, dpr: f32) -> bool {
+        if self.device_pixel_ratio == dpr || dpr <= 0.0 {
+            return false;
+        }
+        self.device_pixel_ratio = dpr;
+        self.layout_dirty = true;
+        self.invalidate_render();
+        self.pipeline_cache = None;
+        self.scene_cache = None;
+        true
+    }
+
     /// Update the scroll offset without triggering a full re-layout.
     /// The next composite will shift tiles by (x, y).
     pub fn set_scroll(&mut self, x: f64, y: f64) {
@@ -271,6 +478,18 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         self.render_dirty = true;
     }
 
+    /// Set the web font faces still in their `font-display` block period. Text in them is left
+    /// out of the rendering until they leave it, so a change repaints the page. Returns whether
+    /// the set changed.
+    pub fn set_blocked_fonts(&mut self, blocked_fonts: Vec<BlockedFont>) -> bool {
+        if self.blocked_fonts == blocked_fonts {
+            return false;
+        }
+        self.blocked_fonts = blocked_fonts;
+        self.render_dirty = true;
+        true
+    }
+
     /// Poll whether a background media fetch (e.g. an image download started during layout) has
     /// completed since the last call. When it has, the cached layout is stale, so mark the render
     /// dirty and report `true` so the caller can also wake its own draw loop. The completion flag
@@ -289,15 +508,21 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// Shared by [`Self::rebuild_pipeline_cache_if_needed`] and
     /// [`Self::rebuild_render_list_if_needed`].
     fn rebuild_full_pipeline(&mut self) {
-        if let Some(doc) = &self.document {
+        if let Some(layout_tree) = self.layout_document() {
             let prev_tile_cache = self
                 .pipeline_cache
                 .as_mut()
                 .map(|c| std::mem::take(&mut c.tile_pixel_cache))
                 .unwrap_or_default();
             self.pipeline_cache = Some(pipeline_build_cache(
-                doc.clone(),
+                layout_tree,
                 &self.viewport,
+                &self.selection,
+                &self.find,
+                &self.frames,
+                &self.media,
+                &self.image_frames,
+                &self.blocked_fonts,
                 self.rasterizer.as_deref(),
                 self.raster_strategy,
                 prev_tile_cache,
@@ -307,11 +532,142 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         }
         self.render_dirty = false;
         self.hover_dirty = false;
+        self.paint_dirty_elements.clear();
+        self.pending_mutations.clear();
         self.dom_dirty = false;
         self.style_dirty = false;
         self.layout_dirty = false;
     }
 
+    /// Stages 1–2 for the whole document from scratch, keeping the adapter for incremental
+    /// relayout. `None` without a document.
+    fn layout_document(&mut self) -> Option<LayoutTree> {
+        let doc = self.document.clone()?;
+        let adapter =
+            Arc::new(GosubDocumentAdapter::<C>::new(doc).with_animated_styles(Arc::clone(&self.animated_styles)));
+        let layout_tree = pipeline_layout(
+            adapter.clone(),
+            &self.viewport,
+            self.device_pixel_ratio,
+            self.rasterizer.as_deref(),
+            self.media_store.clone(),
+        );
+        self.layout_state = Some(LayoutState {
+            adapter,
+            snapshot: None,
+        });
+        Some(layout_tree)
+    }
+
+    /// Lays the page out again after the pending DOM mutations, doing only as much as they call
+    /// for: the previous boxes when no used value changed, a fresh paint when only paint changed,
+    /// and a new layout that keeps every other node's cached style otherwise. `hover_nodes` gained or lost `:hover` and
+    /// are restyled alongside. Returns the layout and the nodes the mutations restyled, or `None`
+    /// when every node must be restyled and only a full render will do.
+    fn layout_mutations(&mut self, previous: &LayoutTree, hover_nodes: &[NodeId]) -> Option<(LayoutTree, Vec<NodeId>)> {
+        let mutations = std::mem::take(&mut self.pending_mutations);
+        let doc = self.document.clone()?;
+        let sets = self
+            .invalidation_sets
+            .get_or_insert_with(|| <C::CssSystem as CssSystem>::invalidation_sets(doc.stylesheets()));
+        let invalidation = invalidate::<C>(&doc, sets, &mutations);
+        let Restyle::Nodes(restyled) = invalidation.restyle else {
+            return None;
+        };
+        let state = self.layout_state.as_mut()?;
+        let snapshot = state.snapshot.take()?;
+
+        let mut targets = restyled.clone();
+        targets.extend_from_slice(hover_nodes);
+        gosub_css3::stylesheet::set_layout_viewport(self.viewport.width as f32, self.viewport.height as f32);
+        let adapter = Arc::new(state.adapter.with_document(Arc::clone(&doc), &targets));
+        let old = GosubDocumentAdapter::<C>::from_snapshot(snapshot, doc);
+        let damage = invalidation
+            .damage
+            .max(style_damage(&old, &*adapter, &targets))
+            .max(old.snapshot_damage());
+        state.adapter = adapter.clone();
+        let layout_tree = match damage {
+            Damage::None | Damage::Paint => pipeline_layouter(self.rasterizer.as_deref(), self.media_store.clone())
+                .restyle(previous, adapter, &targets),
+            Damage::Layout | Damage::Rebuild => pipeline_layout(
+                adapter,
+                &self.viewport,
+                self.device_pixel_ratio,
+                self.rasterizer.as_deref(),
+                self.media_store.clone(),
+            ),
+        };
+        Some((layout_tree, restyled))
+    }
+
+    /// Brings the tile cache up to date with the pending DOM mutations (and any pending hover or
+    /// paint change) by repainting only the tiles they damage. When the mutations changed the
+    /// layering, every tile is repainted, but still without restyling untouched nodes. Returns
+    /// `false` when a full rebuild is needed instead.
+    fn repaint_mutations(&mut self) -> bool {
+        let Some(previous) = self.pipeline_cache.as_ref().map(|c| Arc::clone(&c.layer_list)) else {
+            return false;
+        };
+        let hover_nodes = if self.hover_dirty {
+            self.hover_dirty_nodes.clone()
+        } else {
+            Vec::new()
+        };
+        let Some((layout_tree, restyled)) = self.layout_mutations(&previous.layout_tree, &hover_nodes) else {
+            return false;
+        };
+        let Some(old_cache) = self.pipeline_cache.take() else {
+            return false;
+        };
+
+        let layer_list = LayerList::new(layout_tree);
+        let page_height = layer_list.layout_tree.root_dimension.height;
+        let damage = if layer_list.has_same_layers(&previous) {
+            let mut damage = damage_rects(&previous, &layer_list, &restyled);
+            // The hovered elements and any element whose paint changed repaint too.
+            let mut elements = std::mem::take(&mut self.paint_dirty_elements);
+            if self.hover_dirty {
+                elements.extend([self.hover_old_lei, self.hover_layout_element].into_iter().flatten());
+            }
+            for id in elements {
+                for tree in [&previous.layout_tree, &layer_list.layout_tree] {
+                    damage.extend(tree.get_node_by_id(id).map(|node| node.box_model.margin_box));
+                }
+            }
+            damage
+        } else {
+            let height = page_height.max(old_cache.page_height).max(1.0);
+            vec![Rect::new(0.0, 0.0, self.viewport.width as f64, height)]
+        };
+
+        self.pipeline_cache = Some(pipeline_partial_repaint(
+            Arc::new(layer_list),
+            page_height,
+            old_cache.tiles,
+            &damage,
+            &[],
+            &self.viewport,
+            &self.selection,
+            &self.find,
+            &self.frames,
+            &self.media,
+            &self.image_frames,
+            &self.blocked_fonts,
+            self.rasterizer.as_deref(),
+            self.raster_strategy,
+            old_cache.tile_pixel_cache,
+            self.media_store.clone(),
+            self.config_store.get_uint("renderer.tile.size") as f64,
+        ));
+        self.hover_dirty = false;
+        self.paint_dirty_elements.clear();
+        self.dom_dirty = false;
+        self.style_dirty = false;
+        self.layout_dirty = false;
+        true
+    }
+
     /// Rebuild stages 1-6 (pipeline cache) if content has changed, without building a display
     /// list. Used by TileCache backends (Cairo, Skia, Vello) which composite tiles directly
     /// on the host thread and never consume the render list.
@@ -319,13 +675,15 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// Two paths:
     /// - **Full pipeline** (`render_dirty`): runs stages 1–6 for the whole page and caches
     ///   tiles. Triggered by navigation, DOM/style changes, or viewport resize.
+    /// - **Mutation repaint** (pending [`Self::mutate_document`] changes): restyles and lays out
+    ///   only what the mutations reach, then repaints only the damaged tiles.
     /// - **Paint-only repaint** (`hover_dirty`): reuses the cached layout tree and repaints
     ///   only the affected tiles, skipping stages 1–2.
     pub fn rebuild_pipeline_cache_if_needed(&mut self) {
-        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty {
+        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty && self.pending_mutations.is_empty() {
             return;
         }
-        if self.render_dirty {
+        if self.render_dirty || (!self.pending_mutations.is_empty() && !self.repaint_mutations()) {
             self.rebuild_full_pipeline();
         } else if self.hover_dirty {
             // Paint-only repaint: reuse the cached layout tree, skip stages 1–2.
@@ -337,14 +695,30 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                     tiles: prev_baked_tiles,
                     ..
                 } = old_cache;
-                self.pipeline_cache = Some(pipeline_hover_repaint(
+                // Repaint the old and new hovered element plus any element whose paint changed.
+                let mut repaint: Vec<LayoutElementId> = [self.hover_old_lei, self.hover_layout_element]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                repaint.append(&mut self.paint_dirty_elements);
+                let damage: Vec<Rect> = repaint
+                    .iter()
+                    .filter_map(|&id| layer_list.layout_tree.get_node_by_id(id))
+                    .map(|node| node.box_model.margin_box)
+                    .collect();
+                self.pipeline_cache = Some(pipeline_partial_repaint(
                     layer_list,
                     page_height,
                     prev_baked_tiles,
-                    self.hover_old_lei,
-                    self.hover_layout_element,
+                    &damage,
                     &self.hover_dirty_nodes,
                     &self.viewport,
+                    &self.selection,
+                    &self.find,
+                    &self.frames,
+                    &self.media,
+                    &self.image_frames,
+                    &self.blocked_fonts,
                     self.rasterizer.as_deref(),
                     self.raster_strategy,
                     prev_tile_cache,
@@ -353,10 +727,16 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                 ));
             } else {
                 // No cached layout yet - fall back to a full rebuild.
-                if let Some(doc) = &self.document {
+                if let Some(layout_tree) = self.layout_document() {
                     self.pipeline_cache = Some(pipeline_build_cache(
-                        doc.clone(),
+                        layout_tree,
                         &self.viewport,
+                        &self.selection,
+                        &self.find,
+                        &self.frames,
+                        &self.media,
+                        &self.image_frames,
+                        &self.blocked_fonts,
                         self.rasterizer.as_deref(),
                         self.raster_strategy,
                         std::collections::HashMap::new(),
@@ -366,6 +746,7 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
                 }
             }
             self.hover_dirty = false;
+            self.paint_dirty_elements.clear();
         }
         self.scroll_dirty = false;
         self.scene_epoch = self.scene_epoch.wrapping_add(1);
@@ -376,14 +757,16 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// Two paths:
     /// - **Full pipeline** (`render_dirty`): runs stages 1–6 for the whole page, caches tiles,
     ///   then composites. Triggered by navigation, DOM/style changes, or viewport resize.
+    /// - **Mutation repaint** (pending [`Self::mutate_document`] changes): repaints only the
+    ///   damaged tiles, then composites.
     /// - **Scroll composite** (`scroll_dirty`): re-composites visible tiles from the cache with
     ///   the new scroll offset. No layout or rasterization work.
     pub fn rebuild_render_list_if_needed(&mut self) {
-        if !self.render_dirty && !self.scroll_dirty {
+        if !self.render_dirty && !self.scroll_dirty && self.pending_mutations.is_empty() {
             return;
         }
 
-        if self.render_dirty {
+        if self.render_dirty || (!self.pending_mutations.is_empty() && !self.repaint_mutations()) {
             self.rebuild_full_pipeline();
         }
 
@@ -414,23 +797,46 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     /// don't rebuild anything (the backend re-renders with a new translate); they just advance the
     /// scene epoch so the worker emits a frame.
     pub fn rebuild_scene_cache_if_needed(&mut self) {
-        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty {
+        let mutated = !self.pending_mutations.is_empty();
+        if !self.render_dirty && !self.hover_dirty && !self.scroll_dirty && !mutated {
             return;
         }
-        // Both content changes and hover-style changes rebuild the command list. Hover could reuse
-        // the cached layout (it only changes paint), but a GPU re-paint is cheap and avoids the
-        // tile path's hover-repaint bookkeeping; revisit if hover proves hot.
-        if self.render_dirty || self.hover_dirty {
-            if let Some(doc) = &self.document {
+        // Content changes, DOM mutations and hover-style changes all rebuild the command list.
+        // Mutations are laid out incrementally; hover could reuse the cached layout too (it only
+        // changes paint), but a GPU re-paint is cheap and avoids the tile path's hover-repaint
+        // bookkeeping; revisit if hover proves hot.
+        if self.render_dirty || self.hover_dirty || mutated {
+            let previous = self.scene_cache.as_ref().map(|c| Arc::clone(&c.layer_list));
+            let incremental = match previous {
+                Some(previous) if !self.render_dirty && mutated => {
+                    let hover_nodes = if self.hover_dirty {
+                        self.hover_dirty_nodes.clone()
+                    } else {
+                        Vec::new()
+                    };
+                    self.layout_mutations(&previous.layout_tree, &hover_nodes)
+                        .map(|(layout_tree, _)| layout_tree)
+                }
+                _ => None,
+            };
+            if let Some(layout_tree) = incremental.or_else(|| self.layout_document()) {
                 self.scene_cache = Some(pipeline_build_scene(
-                    doc.clone(),
+                    layout_tree,
                     &self.viewport,
+                    &self.selection,
+                    &self.find,
+                    &self.frames,
+                    &self.media,
+                    &self.image_frames,
+                    &self.blocked_fonts,
                     self.rasterizer.as_deref(),
                     self.media_store.clone(),
                 ));
             }
             self.render_dirty = false;
             self.hover_dirty = false;
+            self.paint_dirty_elements.clear();
+            self.pending_mutations.clear();
             self.dom_dirty = false;
             self.style_dirty = false;
             self.layout_dirty = false;
@@ -448,6 +854,60 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             .or_else(|| self.pipeline_cache.as_ref().map(|c| &c.layer_list))
     }
 
+    /// The layers, layout tree and render tree of the last rendered frame. `None` until the page
+    /// has been rendered.
+    pub fn layer_list(&self) -> Option<&Arc<LayerList>> {
+        self.active_layer_list()
+    }
+
+    /// The document laid out for print and cut into pages of `default_size`, or the size its
+    /// `@page` rules ask for. `@media print` rules apply and the layout is as wide as the first
+    /// page's content box; the screen rendering is left as it was. `None` without a document.
+    pub fn print(&self, default_size: Dimension) -> Option<PrintedDocument> {
+        use gosub_css3::media::{set_media_type, MediaType};
+
+        let doc = self.document.clone()?;
+        // A fresh adapter: the screen one holds styles cascaded without the print rules.
+        let adapter = Arc::new(GosubDocumentAdapter::<C>::new(doc));
+        let rules = adapter.page_rules();
+        let content_box = paged::page_style(&rules, 0, default_size).content_box();
+        let viewport = Viewport::new(0, 0, content_box.width as u32, content_box.height as u32);
+
+        // Painting resolves styles too, so print stays the media type until the pages are cut.
+        set_media_type(MediaType::Print);
+        let layout_tree = pipeline_layout(
+            adapter,
+            &viewport,
+            1.0,
+            self.rasterizer.as_deref(),
+            self.media_store.clone(),
+        );
+        let page_height = layout_tree.root_dimension.height;
+        let layer_list = Arc::new(LayerList::new(layout_tree));
+        let state = full_page_state(
+            &layer_list,
+            &viewport,
+            page_height,
+            &TextSelection::default(),
+            &FindMatches::default(),
+            &self.frames,
+            &self.media,
+            &self.image_frames,
+            // A printout is not redrawn when the font arrives, so it keeps the fallback.
+            &[],
+        );
+        let font_system = self.rasterizer.as_deref().and_then(|r| r.font_system());
+        let painter = Painter::new(Arc::clone(&layer_list), font_system);
+        let pages = paged::paginate(&layer_list.layout_tree, &painter, &state, &rules, default_size);
+        set_media_type(MediaType::Screen);
+        gosub_css3::stylesheet::set_layout_viewport(self.viewport.width as f32, self.viewport.height as f32);
+
+        Some(PrintedDocument {
+            pages,
+            media_store: self.media_store.clone(),
+        })
+    }
+
     /// The active full-page height, from whichever cache this tab populates.
     fn active_page_height(&self) -> Option<f64> {
         self.scene_cache
@@ -462,7 +922,7 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
     ///
     /// Calling this consumes the scroll-dirty flag and advances the scene epoch.
     pub fn take_scroll_handle(&mut self, dpr: u32) -> Option<ExternalHandle> {
-        if !self.scroll_dirty || self.render_dirty || self.hover_dirty {
+        if !self.scroll_dirty || self.render_dirty || self.hover_dirty || !self.pending_mutations.is_empty() {
             return None;
         }
         let cache = self.pipeline_cache.as_ref()?;
@@ -515,6 +975,323 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
         (self.scroll_x, self.scroll_y)
     }
 
+    /// The parsed DOM document, once one is set.
+    pub fn document(&self) -> Option<&Arc<EngineDocument<C>>> {
+        self.document.as_ref()
+    }
+
+    /// Every `<iframe>` of the laid-out page, with the page-space content box its nested document
+    /// shows in. Empty until the first render.
+    pub fn frame_elements(&self) -> Vec<(NodeId, Rect)> {
+        self.active_layer_list()
+            .map(|layer_list| {
+                layer_list
+                    .layout_tree
+                    .frame_elements()
+                    .into_iter()
+                    .map(|(_, ctx, content_box)| (ctx.node_id, content_box))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Hands the painter a new rendering of the iframe `node_id`'s nested document, replacing the
+    /// previous one. The iframe is repainted on the paint-only path, without a re-layout.
+    pub fn set_frame_image(&mut self, node_id: NodeId, image: DecodedImage) {
+        let media_id = self.media_store.insert_image("gosub://iframe", image);
+        if let Some(old) = self.frames.insert(node_id, media_id) {
+            self.media_store.remove(old);
+        }
+        let element = self.active_layer_list().and_then(|layer_list| {
+            layer_list
+                .layout_tree
+                .frame_elements()
+                .into_iter()
+                .find(|(_, ctx, _)| ctx.node_id == node_id)
+                .map(|(id, _, _)| id)
+        });
+        if let Some(id) = element {
+            if !self.paint_dirty_elements.contains(&id) {
+                self.paint_dirty_elements.push(id);
+            }
+            self.hover_dirty = true;
+        }
+    }
+
+    /// Every `<video>` and `<audio>` element of the laid-out page that shows controls, with where
+    /// they are in page space. Empty until the first render.
+    pub fn media_controls(&self) -> Vec<(NodeId, MediaControls)> {
+        self.active_layer_list()
+            .map(|layer_list| {
+                layer_list
+                    .layout_tree
+                    .media_elements()
+                    .into_iter()
+                    .filter(|(_, ctx, _)| ctx.controls)
+                    .map(|(_, ctx, content_box)| (ctx.node_id, MediaControls::new(content_box, ctx.audio)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Hands the painter the playback state of the media element `node_id`: whether it plays, how
+    /// far it got and, when the shown video frame changed, the new `frame`. The element is
+    /// repainted on the paint-only path, without a re-layout.
+    pub fn set_media_presentation(
+        &mut self,
+        node_id: NodeId,
+        frame: Option<DecodedImage>,
+        playing: bool,
+        progress: f32,
+    ) {
+        let previous = self.media.get(&node_id).copied().unwrap_or_default();
+        let mut presentation = MediaPresentation {
+            playing,
+            progress,
+            icon: media_element::control_icon(&self.media_store, playing),
+            ..previous
+        };
+        if let Some(image) = frame {
+            presentation.frame_size = (image.width(), image.height());
+            presentation.frame = Some(self.media_store.insert_image("gosub://media", image));
+            if let Some(old) = previous.frame {
+                self.media_store.remove(old);
+            }
+        }
+        if presentation == previous {
+            return;
+        }
+        self.media.insert(node_id, presentation);
+
+        let element = self.active_layer_list().and_then(|layer_list| {
+            layer_list
+                .layout_tree
+                .media_elements()
+                .into_iter()
+                .find(|(_, ctx, _)| ctx.node_id == node_id)
+                .map(|(id, _, _)| id)
+        });
+        if let Some(id) = element {
+            if !self.paint_dirty_elements.contains(&id) {
+                self.paint_dirty_elements.push(id);
+            }
+            self.hover_dirty = true;
+        }
+    }
+
+    /// Advances the page's animated images to `now`. Elements whose image moved on to another
+    /// frame are repainted on the paint-only path, so only their tiles are re-rasterized. An image
+    /// starts animating the first time it is advanced; one that played its last loop is dropped.
+    /// Returns whether anything needs a repaint.
+    pub fn advance_image_animations(&mut self, now: Instant) -> bool {
+        if self.animations_paused_at.is_some() {
+            return false;
+        }
+        let Some(layer_list) = self.active_layer_list().cloned() else {
+            return false;
+        };
+        if !self
+            .animated_layer_list
+            .upgrade()
+            .is_some_and(|l| Arc::ptr_eq(&l, &layer_list))
+        {
+            self.animated_layer_list = Arc::downgrade(&layer_list);
+            self.animated_images = layer_list
+                .layout_tree
+                .image_elements()
+                .into_iter()
+                .filter_map(|(id, media_id)| Some((id, media_id, self.media_store.animation(media_id)?)))
+                .collect();
+        }
+        if self.animated_images.is_empty() {
+            return false;
+        }
+
+        // Images shown by several elements advance once, then every element showing one repaints
+        let mut advanced = Vec::new();
+        for (_, media_id, animation) in &self.animated_images {
+            if advanced.contains(media_id) {
+                continue;
+            }
+            let start = *self.animation_starts.entry(*media_id).or_insert(now);
+            let Some(frame) = animation.frame_at(now.saturating_duration_since(start)) else {
+                continue;
+            };
+            if self.image_frames.get(media_id).copied().unwrap_or(*media_id) != frame {
+                self.image_frames.insert(*media_id, frame);
+                advanced.push(*media_id);
+            }
+        }
+        for (id, media_id, _) in &self.animated_images {
+            if advanced.contains(media_id) && !self.paint_dirty_elements.contains(id) {
+                self.paint_dirty_elements.push(*id);
+            }
+        }
+
+        let starts = &self.animation_starts;
+        self.animated_images.retain(|(_, media_id, animation)| {
+            starts
+                .get(media_id)
+                .is_none_or(|start| !animation.is_finished(now.saturating_duration_since(*start)))
+        });
+        if advanced.is_empty() {
+            return false;
+        }
+        self.hover_dirty = true;
+        true
+    }
+
+    /// Advances the page's CSS transitions and keyframe animations to `now`. What a changed value
+    /// costs depends on the property: `opacity` and `transform` only update the element's layer and
+    /// re-composite the cached tiles, colours repaint the element's tiles on the paint-only path,
+    /// and anything else lays the page out again. With `renderer.reduced_motion` set, animations
+    /// don't run and transitions finish at once. Returns whether anything needs a new frame.
+    pub fn advance_css_animations(&mut self, now: Instant) -> bool {
+        if self.animations_paused_at.is_some() {
+            return false;
+        }
+        let Some(layer_list) = self.active_layer_list().cloned() else {
+            return false;
+        };
+        let doc = Arc::clone(&layer_list.layout_tree.render_tree.doc);
+        // A hover change that wasn't painted yet is already in effect: the timeline must see the
+        // new styles to transition from what is shown now.
+        if self.hover_dirty {
+            doc.invalidate_style_for_nodes(&self.hover_dirty_nodes);
+        }
+        if self.animation_rescan
+            || !self
+                .animation_layer_list
+                .upgrade()
+                .is_some_and(|l| Arc::ptr_eq(&l, &layer_list))
+        {
+            self.animation_layer_list = Arc::downgrade(&layer_list);
+            self.animation_rescan = false;
+            self.animation_timeline.scan(&layer_list.layout_tree, now);
+        }
+        if self.animation_timeline.is_idle() {
+            return false;
+        }
+
+        let reduced_motion = self.config_store.get_bool("renderer.reduced_motion");
+        let update = self.animation_timeline.tick(&*doc, now, reduced_motion);
+        if update.is_empty() {
+            return false;
+        }
+
+        // A layer created before its element started animating can't be updated in place
+        let relayout = update.relayout
+            || update
+                .composite
+                .iter()
+                .any(|(_, element)| !layer_list.update_composited_layer(*element));
+        if relayout {
+            self.layout_dirty = true;
+            self.invalidate_render();
+            return true;
+        }
+
+        for id in update.repaint {
+            if !self.paint_dirty_elements.contains(&id) {
+                self.paint_dirty_elements.push(id);
+            }
+            self.hover_dirty = true;
+        }
+        if !update.composite.is_empty() {
+            if let Some(cache) = &mut self.pipeline_cache {
+                refresh_compositing(&mut cache.tiles, &cache.layer_list);
+                cache.cached_tiles = Arc::new(cpu_cached_tiles(&cache.tiles));
+            }
+            if let Some(cache) = &mut self.scene_cache {
+                cache.scene.commands = scene_paint_all(
+                    &cache.layer_list,
+                    &self.viewport,
+                    cache.scene.page_height,
+                    &self.selection,
+                    &self.find,
+                    &self.frames,
+                    &self.media,
+                    &self.image_frames,
+                    &self.blocked_fonts,
+                    self.rasterizer.as_deref(),
+                );
+            }
+            self.scroll_dirty = true;
+        }
+        true
+    }
+
+    /// Starts fetching the `loading="lazy"` images whose box has come within
+    /// [`LAZY_LOAD_MARGIN`] of the viewport. The reflow after a fetch lands lays them out with
+    /// their image.
+    pub fn load_lazy_images(&self) {
+        let Some(layer_list) = self.active_layer_list() else {
+            return;
+        };
+        let top = self.scroll_y - LAZY_LOAD_MARGIN;
+        let bottom = self.scroll_y + self.viewport.height as f64 + LAZY_LOAD_MARGIN;
+        let left = self.scroll_x - LAZY_LOAD_MARGIN;
+        let right = self.scroll_x + self.viewport.width as f64 + LAZY_LOAD_MARGIN;
+        let layout_tree = &layer_list.layout_tree;
+        for (id, url) in &layout_tree.deferred_images {
+            let Some(node) = layout_tree.get_node_by_id(*id) else {
+                continue;
+            };
+            let m = node.box_model.margin_box;
+            if m.y > bottom || m.y + m.height < top || m.x > right || m.x + m.width < left {
+                continue;
+            }
+            // The store fetches a URL once, however often it is requested
+            self.media_store.request_media(url);
+        }
+    }
+
+    /// Freezes animated images, CSS animations and transitions where they are, e.g. while drawing
+    /// is suspended.
+    pub fn pause_image_animations(&mut self, now: Instant) {
+        self.animations_paused_at.get_or_insert(now);
+    }
+
+    /// Lets paused animated images carry on from the frame they were paused on, and CSS
+    /// animations and transitions from where they stopped.
+    pub fn resume_image_animations(&mut self, now: Instant) {
+        if let Some(paused_at) = self.animations_paused_at.take() {
+            let paused_for = now.saturating_duration_since(paused_at);
+            for start in self.animation_starts.values_mut() {
+                *start += paused_for;
+            }
+            self.animation_timeline.shift(paused_at, now);
+        }
+    }
+
+    /// The visible part of the page as one opaque image of `dpr` device pixels per CSS pixel,
+    /// composited from the cached tiles over white - how a nested browsing context hands its
+    /// rendering to its parent. `None` before the first render; tiles without CPU pixels (GPU
+    /// tile compositing) leave it blank.
+    pub fn snapshot(&self, dpr: u32) -> Option<DecodedImage> {
+        let cache = self.pipeline_cache.as_ref()?;
+        let w = (self.viewport.width * dpr) as usize;
+        let h = (self.viewport.height * dpr) as usize;
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let mut buf = vec![0xFFFF_FFFFu32; w * h];
+        composite_tiles(
+            &cache.cached_tiles,
+            dpr,
+            (self.scroll_x as f32, self.scroll_y as f32),
+            &mut TileTarget {
+                buf: &mut buf,
+                stride: w,
+                origin_x: 0,
+                origin_y: 0,
+                width: w,
+                height: h,
+            },
+        );
+        DecodedImage::new_rgba8(w as u32, h as u32, argb_u32_to_rgba8(&buf)).ok()
+    }
+
     /// Hit-test at viewport coordinates `(vp_x, vp_y)` and update hover state.
     ///
     /// Returns `(visual_dirty, url_changed, link_url)`:
@@ -618,11 +1395,188 @@ impl<C: RenderConfiguration> BrowsingContext<C> {
             // Hover-only changes are paint-only (color, background, box-shadow).
             // Use the cheap hover-dirty path which skips render-tree + layout.
             self.hover_dirty = true;
+            // ...unless a `:hover` rule starts or stops a transition or animation.
+            self.animation_rescan = true;
         }
 
         (visual_dirty, url_changed, link_url)
     }
 
+    /// Starts a text selection on a primary-button press at viewport `(vp_x, vp_y)`: a caret on
+    /// a single click, the word under the pointer on a double click and its paragraph on a
+    /// triple click. A press away from any text clears the selection.
+    ///
+    /// Returns true when the highlight changed and the page needs a repaint.
+    pub fn selection_press(&mut self, vp_x: f64, vp_y: f64) -> bool {
+        let now = Instant::now();
+        let clicks = match self.last_press {
+            Some((at, x, y, clicks))
+                if now.duration_since(at) <= MULTI_CLICK_INTERVAL
+                    && (x - vp_x).abs() <= MULTI_CLICK_SLOP
+                    && (y - vp_y).abs() <= MULTI_CLICK_SLOP =>
+            {
+                clicks % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_press = Some((now, vp_x, vp_y, clicks));
+        self.selecting = true;
+
+        let Some(layer_list) = self.active_layer_list().cloned() else {
+            return false;
+        };
+        let (anchor, focus) = match self.text_position_at(&layer_list, vp_x, vp_y) {
+            Some(pos) => {
+                let (anchor, focus) = match clicks {
+                    2 => selection::word_at(&layer_list.layout_tree, pos),
+                    3 => selection::paragraph_at(&layer_list.layout_tree, pos),
+                    _ => (pos, pos),
+                };
+                (Some(anchor), Some(focus))
+            }
+            None => (None, None),
+        };
+        self.selection_anchor = anchor;
+        self.selection_focus = focus;
+        self.update_selection(&layer_list)
+    }
+
+    /// Drags the selection's focus to viewport `(vp_x, vp_y)` while the primary button is held.
+    /// Points away from any text leave it where it was.
+    ///
+    /// Returns true when the highlight changed and the page needs a repaint.
+    pub fn selection_drag(&mut self, vp_x: f64, vp_y: f64) -> bool {
+        if !self.selecting || self.selection_anchor.is_none() {
+            return false;
+        }
+        let Some(layer_list) = self.active_layer_list().cloned() else {
+            return false;
+        };
+        let Some(pos) = self.text_position_at(&layer_list, vp_x, vp_y) else {
+            return false;
+        };
+        if self.selection_focus == Some(pos) {
+            return false;
+        }
+        self.selection_focus = Some(pos);
+        self.update_selection(&layer_list)
+    }
+
+    /// Ends a drag started by [`Self::selection_press`]; the selection itself stays.
+    pub fn selection_release(&mut self) {
+        self.selecting = false;
+    }
+
+    /// The selected text as it should land on the clipboard (empty without a selection).
+    pub fn selection_text(&self) -> String {
+        self.active_layer_list()
+            .map(|layer_list| self.selection.text(&layer_list.layout_tree))
+            .unwrap_or_default()
+    }
+
+    /// Steps find-in-page to the next match of `query`, or the previous one when `forward` is
+    /// false. A changed query, case sensitivity or layout searches the page again first.
+    ///
+    /// Returns the 1-based index of the active match (0 without one), the number of matches, and
+    /// true when the overlay changed and the page needs a repaint.
+    pub fn find(&mut self, query: &str, case_sensitive: bool, forward: bool) -> (usize, usize, bool) {
+        let Some(layer_list) = self.active_layer_list().cloned() else {
+            return (0, 0, false);
+        };
+        let same_search = self
+            .find_query
+            .as_ref()
+            .is_some_and(|(q, cs)| q == query && *cs == case_sensitive)
+            && self
+                .find_layer_list
+                .upgrade()
+                .is_some_and(|l| Arc::ptr_eq(&l, &layer_list));
+        let mut found = if same_search {
+            self.find.clone()
+        } else {
+            let _t = gosub_shared::timing_guard!("find.search");
+            FindMatches::search(&layer_list.layout_tree, query, case_sensitive)
+        };
+        found.step(forward);
+        self.find_query = Some((query.to_string(), case_sensitive));
+        self.find_layer_list = Arc::downgrade(&layer_list);
+        let active = found.active().map_or(0, |i| i + 1);
+        let total = found.len();
+        (active, total, self.update_find(found))
+    }
+
+    /// Ends find-in-page. Returns true when there were highlights to remove.
+    pub fn stop_find(&mut self) -> bool {
+        self.find_query = None;
+        self.find_layer_list = Weak::new();
+        self.update_find(FindMatches::default())
+    }
+
+    /// The page-space box of the active find match, to scroll into view.
+    pub fn find_active_rect(&self) -> Option<Rect> {
+        let layer_list = self.active_layer_list()?;
+        let font_system = self.rasterizer.as_ref().and_then(|r| r.font_system());
+        self.find
+            .match_rect(layer_list, font_system.as_ref(), self.find.active()?)
+    }
+
+    /// The caret position under viewport `(vp_x, vp_y)`, shaped with the font system the page
+    /// was painted with.
+    fn text_position_at(&self, layer_list: &LayerList, vp_x: f64, vp_y: f64) -> Option<TextPosition> {
+        let _t = gosub_shared::timing_guard!("selection.hit_test");
+        let font_system = self.rasterizer.as_ref().and_then(|r| r.font_system());
+        selection::hit_test(
+            layer_list,
+            font_system.as_ref(),
+            vp_x,
+            vp_y,
+            self.scroll_x,
+            self.scroll_y,
+        )
+    }
+
+    /// Recomputes the selected ranges from anchor and focus. Text whose range changed is queued
+    /// for the paint-only repaint path, like a hover change.
+    fn update_selection(&mut self, layer_list: &LayerList) -> bool {
+        let selection = match (self.selection_anchor, self.selection_focus) {
+            (Some(anchor), Some(focus)) => TextSelection::between(&layer_list.layout_tree, anchor, focus),
+            _ => TextSelection::default(),
+        };
+        if selection == self.selection {
+            return false;
+        }
+        for &id in self.selection.elements().iter().chain(selection.elements()) {
+            if self.selection.range(id) != selection.range(id) && !self.paint_dirty_elements.contains(&id) {
+                self.paint_dirty_elements.push(id);
+            }
+        }
+        self.selection = selection;
+        self.hover_dirty = true;
+        true
+    }
+
+    /// Swaps in new find matches. Text whose matches or active match changed is queued for the
+    /// paint-only repaint path, like a selection change.
+    fn update_find(&mut self, found: FindMatches) -> bool {
+        if found == self.find {
+            return false;
+        }
+        let changed: Vec<LayoutElementId> = self
+            .find
+            .elements()
+            .chain(found.elements())
+            .filter(|&id| !self.find.ranges(id).eq(found.ranges(id)))
+            .collect();
+        for id in changed {
+            if !self.paint_dirty_elements.contains(&id) {
+                self.paint_dirty_elements.push(id);
+            }
+        }
+        self.find = found;
+        self.hover_dirty = true;
+        true
+    }
+
     /// Returns the render list
     #[inline]
     pub fn render_list(&self) -> &RenderList {
@@ -666,46 +1620,81 @@ impl<C: RenderConfiguration> RenderContext for BrowsingContext<C> {
     }
 }
 
-/// GPU-scene build: stages 1–3 (render tree → layout → layering) plus a paint pass over every
-/// element, producing one ordered paint-command list for the whole page. Skips tiling,
-/// rasterization, and compositing - the backend renders the commands into a GPU texture.
-fn pipeline_build_scene<C: RenderConfiguration>(
-    doc: Arc<EngineDocument<C>>,
+/// Stages 1–2: builds the render tree over `adapter` and lays it out.
+fn pipeline_layout<C: RenderConfiguration>(
+    adapter: Arc<GosubDocumentAdapter<C>>,
     viewport: &Viewport,
+    device_pixel_ratio: f32,
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
-) -> SceneCache {
-    use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
-    use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
-    use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
-    use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+) -> LayoutTree {
     use gosub_render_pipeline::layouter::CanLayout;
     use gosub_render_pipeline::rendertree_builder::RenderTree;
+    use gosub_shared::{timing_start, timing_stop};
 
     // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) against the
     // real viewport. Must precede parse(), which computes styles for display:none filtering.
     gosub_css3::stylesheet::set_layout_viewport(viewport.width as f32, viewport.height as f32);
 
     // Stage 1: render tree
-    let adapter = GosubDocumentAdapter::<C>::new(doc);
-    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    let ts1 = timing_start!("pipeline.render_tree");
+    let mut render_tree = RenderTree::new(adapter);
     if let Err(e) = render_tree.parse() {
+        // The layouter tolerates a tree without a root; the frame degrades to empty.
         log::error!("Failed to build render tree: {e}");
     }
+    timing_stop!(ts1);
 
-    let vp_dim = if viewport.width > 0 && viewport.height > 0 {
-        Some(PipelineDimension::new(viewport.width as f64, viewport.height as f64))
-    } else {
-        None
-    };
+    // Stage 2: layout
+    let ts2 = timing_start!("pipeline.layout");
+    let mut layouter = pipeline_layouter(rasterizer, media_store);
+    let layout_tree = layouter.layout(render_tree, viewport_dimension(viewport), device_pixel_ratio);
+    timing_stop!(ts2);
+
+    layout_tree
+}
 
-    // Stage 2: layout (share the rasterizer's font system, as the tile path does)
+/// A layouter for one pass. Its Taffy tree isn't `Send`, so the context builds one per layout
+/// instead of keeping it.
+fn pipeline_layouter(
+    rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
+    media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
+) -> TaffyLayouter {
+    // Share the rasterizer's font system so layout and rendering measure/draw against the
+    // same font collection (and it's created once, not per layout pass). Backends without a
+    // FontSystem (null, Cairo/Pango) fall back to the layouter's own instance.
     let mut layouter = match rasterizer.and_then(|r| r.font_system()) {
         Some(font_system) => TaffyLayouter::with_font_system(font_system),
         None => TaffyLayouter::new(),
     };
-    layouter.set_media_store(Arc::clone(&media_store));
-    let layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
+    // Share the persistent media store so resources loaded during layout are visible to the
+    // rasterizer (which resolves them by id). Otherwise every image renders as a placeholder.
+    layouter.set_media_store(media_store);
+    layouter
+}
+
+/// The viewport as the layouter takes it; `None` (lay out at max-content) while it has no size.
+fn viewport_dimension(viewport: &Viewport) -> Option<gosub_render_pipeline::common::geo::Dimension> {
+    (viewport.width > 0 && viewport.height > 0)
+        .then(|| gosub_render_pipeline::common::geo::Dimension::new(viewport.width as f64, viewport.height as f64))
+}
+
+/// GPU-scene build: stage 3 (layering) over a finished layout plus a paint pass over every
+/// element, producing one ordered paint-command list for the whole page. Skips tiling,
+/// rasterization, and compositing - the backend renders the commands into a GPU texture.
+#[allow(clippy::too_many_arguments)]
+fn pipeline_build_scene(
+    layout_tree: LayoutTree,
+    viewport: &Viewport,
+    selection: &TextSelection,
+    find: &FindMatches,
+    frames: &HashMap<NodeId, MediaId>,
+    media: &HashMap<NodeId, MediaPresentation>,
+    image_frames: &HashMap<MediaId, MediaId>,
+    blocked_fonts: &[BlockedFont],
+    rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
+    media_store: Arc<gosub_render_pipeline::common::media::MediaStore>,
+) -> SceneCache {
     let page_height = layout_tree.root_dimension.height;
 
     // Stage 3: layering
@@ -713,40 +1702,112 @@ fn pipeline_build_scene<C: RenderConfiguration>(
 
     // Stage 5′: paint every element into one ordered list (no tiling). Paint over the full page
     // so scrolling reveals already-painted content without a rebuild.
+    let commands = scene_paint_all(
+        &layer_list,
+        viewport,
+        page_height,
+        selection,
+        find,
+        frames,
+        media,
+        image_frames,
+        blocked_fonts,
+        rasterizer,
+    );
+
+    SceneCache {
+        layer_list,
+        scene: PaintScene {
+            commands,
+            media_store,
+            page_height,
+        },
+    }
+}
+
+/// Paints every element of `layer_list` into one ordered command list for the GPU scene. Used
+/// by [`pipeline_build_scene`], and on its own when only layer opacity or transform changed.
+#[allow(clippy::too_many_arguments)]
+fn scene_paint_all(
+    layer_list: &Arc<LayerList>,
+    viewport: &Viewport,
+    page_height: f64,
+    selection: &TextSelection,
+    find: &FindMatches,
+    frames: &HashMap<NodeId, MediaId>,
+    media: &HashMap<NodeId, MediaPresentation>,
+    image_frames: &HashMap<MediaId, MediaId>,
+    blocked_fonts: &[BlockedFont],
+    rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
+) -> Vec<gosub_render_pipeline::painter::commands::PaintCommand> {
+    let state = full_page_state(
+        layer_list,
+        viewport,
+        page_height,
+        selection,
+        find,
+        frames,
+        media,
+        image_frames,
+        blocked_fonts,
+    );
+    let painter = Painter::new(Arc::clone(layer_list), rasterizer.and_then(|r| r.font_system()));
+    painter.paint_all(&state)
+}
+
+/// Browser state for painting the whole of `layer_list` in one pass, with every layer visible.
+#[allow(clippy::too_many_arguments)]
+fn full_page_state(
+    layer_list: &LayerList,
+    viewport: &Viewport,
+    page_height: f64,
+    selection: &TextSelection,
+    find: &FindMatches,
+    frames: &HashMap<NodeId, MediaId>,
+    media: &HashMap<NodeId, MediaPresentation>,
+    image_frames: &HashMap<MediaId, MediaId>,
+    blocked_fonts: &[BlockedFont],
+) -> gosub_render_pipeline::common::browser_state::BrowserState {
+    use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
+    use gosub_render_pipeline::common::geo::Rect as PipelineRect;
+
     let layer_count = layer_list.layer_ids.read().len();
     let full_page_rect = PipelineRect::new(0.0, 0.0, viewport.width as f64, page_height.max(1.0));
-    let state = BrowserState {
+    BrowserState {
         visible_layer_list: vec![true; layer_count],
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        selection: selection.clone(),
+        find: find.clone(),
+        frames: frames.clone(),
+        media: media.clone(),
+        image_frames: image_frames.clone(),
+        blocked_fonts: blocked_fonts.to_vec(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
         tile_list: None,
         dpi_scale_factor: 1.0,
-    };
-    let painter = Painter::new(Arc::clone(&layer_list), rasterizer.and_then(|r| r.font_system()));
-    let commands = painter.paint_all(&state);
-
-    SceneCache {
-        layer_list,
-        scene: PaintScene {
-            commands,
-            media_store,
-            page_height,
-        },
     }
 }
 
-/// Runs pipeline stages 1–6 for the **entire page** (all tiles, not just the viewport slice)
-/// and returns a `PipelineCache` of rasterized tiles ready for repeated compositing.
+/// Runs pipeline stages 3–6 over a finished layout of the **entire page** (all tiles, not just
+/// the viewport slice) and returns a `PipelineCache` of rasterized tiles ready for repeated
+/// compositing.
 ///
 /// Splitting the full pipeline from compositing lets scroll re-use the cached tiles without
 /// re-running layout or rasterization.
-fn pipeline_build_cache<C: RenderConfiguration>(
-    doc: Arc<EngineDocument<C>>,
+#[allow(clippy::too_many_arguments)]
+fn pipeline_build_cache(
+    layout_tree: LayoutTree,
     viewport: &Viewport,
+    selection: &TextSelection,
+    find: &FindMatches,
+    frames: &HashMap<NodeId, MediaId>,
+    media: &HashMap<NodeId, MediaPresentation>,
+    image_frames: &HashMap<MediaId, MediaId>,
+    blocked_fonts: &[BlockedFont],
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     strategy: RasterStrategy,
     prev_tile_cache: TilePixelCache,
@@ -754,52 +1815,12 @@ fn pipeline_build_cache<C: RenderConfiguration>(
     tile_size: f64,
 ) -> PipelineCache {
     use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
-    use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
     use gosub_render_pipeline::common::geo::{Dimension as PipelineDimension, Rect as PipelineRect};
-    use gosub_render_pipeline::layering::layer::LayerList;
-    use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
-    use gosub_render_pipeline::layouter::CanLayout;
     use gosub_render_pipeline::painter::Painter;
-    use gosub_render_pipeline::rendertree_builder::RenderTree;
     use gosub_render_pipeline::tiler::{TileList, TileState};
     use gosub_shared::{timing_start, timing_stop};
 
     let ts_total = timing_start!("pipeline.total");
-
-    // Resolve viewport-relative CSS units (vw/vh/vmin/vmax, incl. inside clamp()) against the
-    // real viewport. Must precede parse(), which computes styles for display:none filtering.
-    gosub_css3::stylesheet::set_layout_viewport(viewport.width as f32, viewport.height as f32);
-
-    // Stage 1: render tree
-    let ts1 = timing_start!("pipeline.render_tree");
-    let adapter = GosubDocumentAdapter::<C>::new(doc);
-    let mut render_tree = RenderTree::new(Arc::new(adapter));
-    if let Err(e) = render_tree.parse() {
-        // The layouter tolerates a tree without a root; the frame degrades to empty.
-        log::error!("Failed to build render tree: {e}");
-    }
-    timing_stop!(ts1);
-
-    let vp_dim = if viewport.width > 0 && viewport.height > 0 {
-        Some(PipelineDimension::new(viewport.width as f64, viewport.height as f64))
-    } else {
-        None
-    };
-
-    // Stage 2: layout
-    let ts2 = timing_start!("pipeline.layout");
-    // Share the rasterizer's font system so layout and rendering measure/draw against the
-    // same font collection (and it's created once, not per layout pass). Backends without a
-    // FontSystem (null, Cairo/Pango) fall back to the layouter's own instance.
-    let mut layouter = match rasterizer.and_then(|r| r.font_system()) {
-        Some(font_system) => TaffyLayouter::with_font_system(font_system),
-        None => TaffyLayouter::new(),
-    };
-    // Share the persistent media store so resources loaded during layout are visible to the
-    // rasterizer (which resolves them by id). Otherwise every image renders as a placeholder.
-    layouter.set_media_store(Arc::clone(&media_store));
-    let layout_tree = layouter.layout(render_tree, vp_dim, 1.0);
-    timing_stop!(ts2);
     let page_height = layout_tree.root_dimension.height;
 
     // Stage 3: layering
@@ -828,6 +1849,12 @@ fn pipeline_build_cache<C: RenderConfiguration>(
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        selection: selection.clone(),
+        find: find.clone(),
+        frames: frames.clone(),
+        media: media.clone(),
+        image_frames: image_frames.clone(),
+        blocked_fonts: blocked_fonts.to_vec(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
@@ -885,19 +1912,24 @@ fn pipeline_build_cache<C: RenderConfiguration>(
     }
 }
 
-/// Hover-only repaint: skip stages 1–2 (render-tree + layout), reuse the cached
-/// `LayerList`, and only repaint tiles that intersect the old or new hovered element.
-/// All other tiles are carried over from `prev_baked_tiles` unchanged - no CSS
-/// re-evaluation, no re-rasterization.
+/// Partial repaint: skip stages 1–2 (render-tree + layout), take `layer_list` as laid out, and
+/// only repaint tiles that intersect a `damage` rect (the old and new hovered element, text whose
+/// selection or find highlight changed, or what a DOM mutation changed). All other tiles are
+/// carried over from `prev_baked_tiles` unchanged - no CSS re-evaluation, no re-rasterization.
 #[allow(clippy::too_many_arguments)]
-fn pipeline_hover_repaint(
+fn pipeline_partial_repaint(
     layer_list: Arc<gosub_render_pipeline::layering::layer::LayerList>,
     page_height: f64,
     prev_baked_tiles: Vec<BakedTile>,
-    old_hover_lei: Option<LayoutElementId>,
-    new_hover_lei: Option<LayoutElementId>,
+    damage: &[Rect],
     hover_dirty_nodes: &[NodeId],
     viewport: &gosub_render_pipeline::render::Viewport,
+    selection: &TextSelection,
+    find: &FindMatches,
+    frames: &HashMap<NodeId, MediaId>,
+    media: &HashMap<NodeId, MediaPresentation>,
+    image_frames: &HashMap<MediaId, MediaId>,
+    blocked_fonts: &[BlockedFont],
     rasterizer: Option<&(dyn Rasterable + Send + Sync)>,
     strategy: RasterStrategy,
     prev_tile_cache: TilePixelCache,
@@ -928,46 +1960,25 @@ fn pipeline_hover_repaint(
         .map(|t| ((t.page_x.to_bits(), t.page_y.to_bits(), t.layer_id), t))
         .collect();
 
-    // Compute the union bounding box of old and new hovered elements.  Tiles that
-    // don't intersect this region cannot have changed visually, so we skip them.
-    let hover_rect: Option<PipelineRect> = {
-        let mut union: Option<PipelineRect> = None;
-        for lei in [old_hover_lei, new_hover_lei].into_iter().flatten() {
-            if let Some(el) = layer_list.layout_tree.get_node_by_id(lei) {
-                let m = el.box_model.margin_box;
-                let r = PipelineRect::new(m.x, m.y, m.width, m.height);
-                union = Some(match union {
-                    None => r,
-                    Some(u) => {
-                        let x0 = u.x.min(r.x);
-                        let y0 = u.y.min(r.y);
-                        let x1 = (u.x + u.width).max(r.x + r.width);
-                        let y1 = (u.y + u.height).max(r.y + r.height);
-                        PipelineRect::new(x0, y0, x1 - x0, y1 - y0)
-                    }
-                });
-            }
-        }
-        union
-    };
-
     // Full-page paint rect and back-to-front layer order - used both to re-emit carried tiles in
     // order (below / in the early-return) and by stages 5–6 further down.
     let full_page_rect = PipelineRect::new(0.0, 0.0, viewport.width as f64, page_height.max(1.0));
     let layer_ids = tile_list.layer_list.layer_ids.read().clone();
 
-    // Mark tiles that DON'T intersect the hover region as Clean.  For Clean tiles we
-    // carry the previous BakedTile forward; for Dirty tiles we re-evaluate CSS only
-    // for the elements they contain (targeted invalidation).
+    // Mark tiles that DON'T intersect the damage as Clean. Tiles outside it cannot have changed
+    // visually: for Clean tiles we carry the previous BakedTile forward; for Dirty tiles we
+    // re-evaluate CSS only for the elements they contain (targeted invalidation).
     let mut clean_baked: Vec<BakedTile> = Vec::with_capacity(total_tiles);
-    if let Some(hover_rect) = hover_rect {
+    if !damage.is_empty() {
         let doc = &layer_list.layout_tree.render_tree.doc;
         for tile in tile_list.arena.values_mut() {
             let tile_rect = tile.rect;
-            let overlaps = tile_rect.x < hover_rect.x + hover_rect.width
-                && tile_rect.x + tile_rect.width > hover_rect.x
-                && tile_rect.y < hover_rect.y + hover_rect.height
-                && tile_rect.y + tile_rect.height > hover_rect.y;
+            let overlaps = damage.iter().any(|rect| {
+                tile_rect.x < rect.x + rect.width
+                    && tile_rect.x + tile_rect.width > rect.x
+                    && tile_rect.y < rect.y + rect.height
+                    && tile_rect.y + tile_rect.height > rect.y
+            });
             if overlaps {
                 // Invalidate cached styles only for the hover-chain nodes (old + new ancestors).
                 // Non-hover elements in this tile keep their cached CSS - only the nodes that
@@ -983,7 +1994,7 @@ fn pipeline_hover_repaint(
             }
         }
     } else {
-        // No hover element visible - carry every previous tile forward, but re-emit in
+        // Nothing damaged - carry every previous tile forward, but re-emit in
         // back-to-front layer order (see order_baked_tiles_by_layer): `into_values()` is
         // unordered and would scramble overlapping-layer compositing.
         let all_tiles = order_baked_tiles_by_layer(&tile_list, &layer_ids, full_page_rect, prev_by_pos);
@@ -1005,6 +2016,12 @@ fn pipeline_hover_repaint(
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        selection: selection.clone(),
+        find: find.clone(),
+        frames: frames.clone(),
+        media: media.clone(),
+        image_frames: image_frames.clone(),
+        blocked_fonts: blocked_fonts.to_vec(),
         show_tilegrid: false,
         debug_table_cells: std::env::var("GOSUB_DEBUG_TABLE_CELLS").is_ok(),
         viewport: full_page_rect,
@@ -1107,7 +2124,13 @@ fn pipeline_composite(cache: &PipelineCache, scroll_x: f64, scroll_y: f64, vp_w:
     for tile in &cache.tiles {
         // Resolve the tile's position in viewport space (fixed tiles ignore scroll), then cull
         // against the viewport rect [0, vp].
-        let (ex, ey) = anchored_tile_pos(tile.page_x, tile.page_y, scroll_x, scroll_y, tile.anchor);
+        let (ex, ey) = anchored_tile_pos(
+            tile.page_x + tile.translate.0,
+            tile.page_y + tile.translate.1,
+            scroll_x,
+            scroll_y,
+            tile.anchor,
+        );
         if ex + tile.width as f64 <= 0.0 || ey + tile.height as f64 <= 0.0 || ex >= vp_w || ey >= vp_h {
             continue;
         }
@@ -1133,7 +2156,69 @@ fn pipeline_composite(cache: &PipelineCache, scroll_x: f64, scroll_y: f64, vp_w:
 
 #[cfg(test)]
 mod tests {
-    use super::parse_clear_color;
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_css3::system::Css3System;
+    use gosub_render_pipeline::common::media::MediaStore;
+    use gosub_render_pipeline::common::texture::TextureId;
+    use gosub_render_pipeline::common::texture_store::TextureStore;
+    use gosub_render_pipeline::tiler::Tile;
+    use parking_lot::Mutex;
+
+    /// Records where each tile it is asked for lies, and draws nothing.
+    struct RecordingRasterizer(Arc<Mutex<Vec<Rect>>>);
+
+    impl Rasterable for RecordingRasterizer {
+        fn rasterize(&self, tile: &Tile, _: &mut TextureStore, _: &MediaStore) -> Option<TextureId> {
+            self.0.lock().push(tile.rect);
+            None
+        }
+    }
+
+    #[test]
+    fn attribute_change_rerasterizes_only_the_damaged_tiles() {
+        let mut doc = gosub_html5::html_compile::<DefaultRenderConfig>(
+            r#"<body style="margin: 0">
+                <div id="a" style="height: 50px; background: red"></div>
+                <div style="height: 2000px; background: green"></div>
+            </body>"#,
+        );
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        let a = doc.node_by_named_id("a").unwrap();
+
+        let rasterized = Arc::new(Mutex::new(Vec::new()));
+        let mut context = BrowsingContext::<DefaultRenderConfig>::new(crate::engine::default_settings());
+        context.set_rasterizer(
+            Box::new(RecordingRasterizer(Arc::clone(&rasterized))),
+            RasterStrategy::Sequential,
+        );
+        context.set_viewport(Viewport {
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        });
+        context.set_document(Arc::new(doc));
+        context.rebuild_pipeline_cache_if_needed();
+        let full = std::mem::take(&mut *rasterized.lock());
+        assert!(
+            full.iter().any(|tile| tile.y > 1000.0),
+            "the first render covers the page"
+        );
+
+        // The document is edited in place through the last rendering's adapter, not copied.
+        let before = context.document().map(Arc::as_ptr);
+        context
+            .mutate_document(|doc| doc.set_attribute(a, "style", "height: 50px; background: blue"))
+            .expect("the document isn't shared");
+        assert_eq!(context.document().map(Arc::as_ptr), before);
+        context.rebuild_pipeline_cache_if_needed();
+        let repainted = rasterized.lock();
+        assert!(!repainted.is_empty());
+        assert!(repainted.len() < full.len());
+        // Only the tiles under `#a`, the top row, are rasterized again.
+        assert!(repainted.iter().all(|tile| tile.y < 50.0), "{repainted:?}");
+    }
 
     #[test]
     fn parse_clear_color_handles_rgb_rgba_and_garbage() {
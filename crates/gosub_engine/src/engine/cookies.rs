@@ -117,6 +117,7 @@ pub use cookie_jar::CookieJar;
 pub use cookie_jar::DefaultCookieJar;
 pub use cookie_jar::SameSiteContext;
 pub use cookie_jar::ThirdPartyCookiePolicy;
+pub(crate) use cookie_jar::same_site;
 pub use persistent_cookie_jar::PersistentCookieJar;
 
 pub use store::CookieStore;
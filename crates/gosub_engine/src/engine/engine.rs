@@ -25,6 +25,7 @@ use crate::engine::events::{EngineCommand, EngineEvent};
 use crate::engine::types::{EventChannel, IoChannel};
 use crate::engine::DEFAULT_CHANNEL_CAPACITY;
 use crate::html::RenderConfiguration;
+use crate::media::{StreamDecoder, StreamDecoders};
 use crate::net::req_ref_tracker::RequestReferenceMap;
 use crate::net::{fetcher_config_from, spawn_io_thread, IoHandle};
 use crate::zone::{Zone, ZoneConfig, ZoneId, ZoneServices, ZoneSink};
@@ -86,6 +87,9 @@ pub struct EngineContext {
     pub io_tx: OnceLock<IoChannel>,
     /// Map for requests to tabs
     pub request_reference_map: Arc<RwLock<RequestReferenceMap>>,
+    /// Decoders for `<video>`/`<audio>` resources, shared with every zone; see
+    /// [`GosubEngine::register_media_decoder`].
+    pub media_decoders: Arc<RwLock<StreamDecoders>>,
 }
 
 impl Default for EngineContext {
@@ -96,6 +100,7 @@ impl Default for EngineContext {
             config_store: crate::engine::settings_store::default_config(),
             io_tx: OnceLock::new(),
             request_reference_map: Arc::new(RwLock::new(RequestReferenceMap::new())),
+            media_decoders: Arc::new(RwLock::new(StreamDecoders::with_defaults())),
         }
     }
 }
@@ -134,6 +139,7 @@ impl<C: RenderConfiguration> GosubEngine<C> {
                 config_store: crate::engine::settings_store::default_config(),
                 io_tx: OnceLock::new(),
                 request_reference_map: Arc::new(RwLock::new(RequestReferenceMap::new())),
+                media_decoders: Arc::new(RwLock::new(StreamDecoders::with_defaults())),
             }),
             render_backend: backend,
             compositor,
@@ -190,6 +196,12 @@ impl<C: RenderConfiguration> GosubEngine<C> {
         Arc::clone(&self.compositor)
     }
 
+    /// Add a decoder for `<video>`/`<audio>` resources, tried after the built-in ones. Applies to
+    /// media loaded from then on, in every zone.
+    pub fn register_media_decoder(&self, decoder: Box<dyn StreamDecoder>) {
+        self.context.media_decoders.write().register(decoder);
+    }
+
     /// Get a clone of the engine’s command sender (mainly for testing or
     /// custom handles).
     #[cfg(test)]
@@ -79,6 +79,10 @@ pub enum EngineError {
     /// A cookie/storage backing store failed to initialize.
     #[error("Cookie store error: {0}")]
     CookieStore(#[source] anyhow::Error),
+
+    /// The document is shared with something else, so it can't be mutated in place
+    #[error("Document is shared and cannot be mutated")]
+    DocumentShared,
 }
 
 #[derive(thiserror::Error, Debug)]
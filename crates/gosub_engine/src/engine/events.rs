@@ -14,6 +14,8 @@
 
 use crate::cookies::Cookie;
 use crate::engine::types::{Action, NavigationId, RequestId};
+use crate::inspect::{DomNode, TreeDump};
+use crate::media::PlaybackState;
 use crate::net::req_ref_tracker::RequestReference;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResult, FetchResultMeta, Initiator, Priority, ResourceKind};
 use crate::net::DecisionToken;
@@ -22,6 +24,8 @@ use crate::tab::TabId;
 use crate::zone::ZoneId;
 use crate::EngineError;
 use bitflags::bitflags;
+use gosub_render_pipeline::common::geo::Dimension;
+use gosub_render_pipeline::paged::PrintedDocument;
 use gosub_render_pipeline::render::backend::ExternalHandle;
 use gosub_render_pipeline::render::Viewport;
 use std::fmt::{Debug, Display, Formatter};
@@ -167,6 +171,18 @@ pub enum TabCommand {
     TextInput { text: String },
     /// Char input (@TODO: Needed since we have TextInput)?
     CharInput { ch: char },
+    /// Request the selected text, answered with an `EngineEvent::SelectionText` (e.g. on copy)
+    GetSelectionText,
+    /// Find `query` in the page's text and step to the next match, or the previous one when
+    /// `forward` is false. A new query starts over at the first (or last) match. Answered with
+    /// an `EngineEvent::FindResult`
+    Find {
+        query: String,
+        case_sensitive: bool,
+        forward: bool,
+    },
+    /// End find-in-page and remove its highlights
+    StopFind,
 
     // ****************************************
     // ** Session / zone state
@@ -192,8 +208,35 @@ pub enum TabCommand {
 
     // ****************************************
     // ** Debug / devtools
-    /// Dump dom tree
+    /// Dump the DOM tree, answered with an `EngineEvent::TreeDump`
     DumpDomTree,
+    /// Dump the render tree of the last layout, answered with an `EngineEvent::TreeDump`
+    DumpRenderTree,
+    /// Dump the layout tree with its box models, answered with an `EngineEvent::TreeDump`
+    DumpLayoutTree,
+    /// Dump the compositing layers, answered with an `EngineEvent::TreeDump`
+    DumpLayers,
+    /// Dump the styles of `node_id`, or of every element when `None`, with the declaration each
+    /// value came from. Answered with an `EngineEvent::TreeDump`
+    DumpComputedStyles { node_id: Option<u64> },
+    /// Capture the page at the current viewport as a PNG, answered with an
+    /// `EngineEvent::Screenshot`
+    CaptureScreenshot,
+    /// Lay the document out on pages for print, answered with an `EngineEvent::Printed`. Pages
+    /// are `page_size` (CSS px) unless the document's `@page` rules set a size.
+    Print { page_size: Dimension },
+    /// Request a snapshot of the document, answered with an `EngineEvent::DomDocument`
+    GetDocument,
+    /// Find the first element below `node_id` that matches `selector`, answered with an
+    /// `EngineEvent::QuerySelectorResult`
+    QuerySelector { node_id: u64, selector: String },
+    /// Set an attribute of element `node_id`, or remove it when `value` is `None`. Only what the
+    /// change reaches is restyled, laid out and repainted.
+    SetAttribute {
+        node_id: u64,
+        name: String,
+        value: Option<String>,
+    },
 }
 
 #[derive(Debug)]
@@ -437,6 +480,28 @@ pub enum EngineEvent {
         tab_id: TabId,
         url: Option<String>,
     },
+    /// The selected text, in reply to `TabCommand::GetSelectionText`. Empty without a selection.
+    SelectionText {
+        tab_id: TabId,
+        text: String,
+    },
+    /// Progress of find-in-page, in reply to `TabCommand::Find`: the 1-based index of the active
+    /// match and the number of matches. `active` is 0 when nothing matched.
+    FindResult {
+        tab_id: TabId,
+        active: usize,
+        total: usize,
+    },
+    /// A `<video>`/`<audio>` element's playback state changed: it loaded, started or stopped
+    /// playing, reached its end or failed. `element_id` is the id `TabCommand::PlayMedia` takes;
+    /// `duration` is known once the media has loaded.
+    MediaStateChanged {
+        tab_id: TabId,
+        element_id: u64,
+        state: PlaybackState,
+        position: Duration,
+        duration: Option<Duration>,
+    },
     /// Title of the tab has changed
     TitleChanged {
         tab_id: TabId,
@@ -532,6 +597,38 @@ pub enum EngineEvent {
         result: serde_json::Value,
     },
 
+    // ****************************************
+    // ** Inspection
+    /// The page as a PNG, in reply to `TabCommand::CaptureScreenshot`. Empty when the render
+    /// backend draws straight to a GPU texture, or nothing has been rendered yet.
+    Screenshot {
+        tab_id: TabId,
+        png: Vec<u8>,
+    },
+    /// The document on pages, in reply to `TabCommand::Print`. `None` before the first document
+    /// has loaded.
+    Printed {
+        tab_id: TabId,
+        document: Option<Arc<PrintedDocument>>,
+    },
+    /// Snapshot of the document, in reply to `TabCommand::GetDocument`. `None` before the first
+    /// document has loaded.
+    DomDocument {
+        tab_id: TabId,
+        root: Option<DomNode>,
+    },
+    /// The matching element, in reply to `TabCommand::QuerySelector`
+    QuerySelectorResult {
+        tab_id: TabId,
+        node_id: Option<u64>,
+    },
+    /// A pipeline stage, in reply to one of the `TabCommand::Dump*` commands. `None` when there is
+    /// nothing to dump yet: no document has loaded, or the page has not been laid out.
+    TreeDump {
+        tab_id: TabId,
+        dump: Option<TreeDump>,
+    },
+
     // ****************************************
     // ** Errors / diagnostics
     /// Network error occurred
@@ -0,0 +1,173 @@
+//! Snapshots of a tab's document for inspection tooling, such as the `devtools` server.
+//!
+//! The tab worker builds these on request (`TabCommand::GetDocument`, `TabCommand::QuerySelector`)
+//! and hands them back through the `EngineEvent` broadcast, so tooling never touches the document
+//! the worker owns. The `TabCommand::Dump*` commands answer with a [`TreeDump`] of one pipeline
+//! stage in the same way.
+
+mod dump;
+
+pub use dump::{
+    BoxDump, EdgesDump, LayerDump, LayoutDumpNode, NodeStyles, RectDump, RenderDumpNode, StyleDump, TreeDump,
+};
+
+use crate::html::{EngineDocument, RenderConfiguration};
+use cow_utils::CowUtils;
+use gosub_html5::node::HTML_NAMESPACE;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_shared::node::NodeId;
+use serde::Serialize;
+
+/// A node of a DOM snapshot, with its subtree.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DomNode {
+    /// The node's id, as `TabCommand::QuerySelector` and `TabCommand::PlayMedia` take it.
+    pub node_id: u64,
+    /// The DOM `nodeType`: 1 for elements, 3 text, 8 comments, 9 the document and 10 doctypes.
+    pub node_type: u16,
+    /// The DOM `nodeName`: the tag name (upper case for HTML elements), the doctype's name, or
+    /// `#text`, `#comment` or `#document`.
+    pub node_name: String,
+    /// The tag name of an element as written; empty for other nodes.
+    pub local_name: String,
+    /// The text of a text or comment node; empty for other nodes.
+    pub node_value: String,
+    /// The attributes of an element, sorted by name.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<DomNode>,
+}
+
+impl DomNode {
+    /// Snapshot of the subtree of `doc` rooted at `id`.
+    pub fn from_document<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Self {
+        let (node_type, node_name, local_name, node_value) = match doc.node_type(id) {
+            NodeType::DocumentNode => (9, "#document".to_string(), String::new(), String::new()),
+            NodeType::DocTypeNode => (
+                10,
+                doc.doctype_name(id).unwrap_or("html").to_string(),
+                String::new(),
+                String::new(),
+            ),
+            NodeType::TextNode => (
+                3,
+                "#text".to_string(),
+                String::new(),
+                doc.text_value(id).unwrap_or_default().to_string(),
+            ),
+            NodeType::CommentNode => (
+                8,
+                "#comment".to_string(),
+                String::new(),
+                doc.comment_value(id).unwrap_or_default().to_string(),
+            ),
+            NodeType::ElementNode => {
+                let tag = doc.tag_name(id).unwrap_or_default();
+                let html = doc.namespace(id).is_none_or(|ns| ns == HTML_NAMESPACE);
+                let name = if html {
+                    tag.cow_to_ascii_uppercase().into_owned()
+                } else {
+                    tag.to_string()
+                };
+                (1, name, tag.to_string(), String::new())
+            }
+        };
+
+        let mut attributes: Vec<(String, String)> = doc
+            .attributes(id)
+            .map(|attrs| attrs.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        attributes.sort();
+
+        Self {
+            node_id: id.into(),
+            node_type,
+            node_name,
+            local_name,
+            node_value,
+            attributes,
+            children: doc
+                .children(id)
+                .iter()
+                .map(|&child| Self::from_document(doc, child))
+                .collect(),
+        }
+    }
+}
+
+/// The first element below `scope` in tree order that matches the selector list `selector`. A
+/// selector that does not parse matches nothing.
+pub fn query_selector<C: RenderConfiguration>(
+    doc: &EngineDocument<C>,
+    scope: NodeId,
+    selector: &str,
+) -> Option<NodeId> {
+    doc.query_selector(scope, selector).ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_html5::document::builder::DocumentBuilderImpl;
+    use gosub_html5::parser::Html5Parser;
+    use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+    fn parse(html: &str) -> EngineDocument<DefaultRenderConfig> {
+        let mut stream = ByteStream::new(Encoding::UTF8, None);
+        stream.read_from_str(html, Some(Encoding::UTF8));
+        stream.close();
+        let mut doc = DocumentBuilderImpl::new_document::<DefaultRenderConfig>(None);
+        let _ = Html5Parser::<DefaultRenderConfig>::parse_document(&mut stream, &mut doc, None);
+        doc
+    }
+
+    fn find<'a>(node: &'a DomNode, name: &str) -> Option<&'a DomNode> {
+        if node.node_name == name {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find(child, name))
+    }
+
+    #[test]
+    fn snapshot_mirrors_the_tree() {
+        let doc = parse(r#"<!DOCTYPE html><p id="a" class="b">Hi<!--c--></p>"#);
+        let root = DomNode::from_document(&doc, doc.root());
+        assert_eq!(root.node_type, 9);
+        assert_eq!(root.children[0].node_type, 10);
+
+        let p = find(&root, "P").unwrap();
+        assert_eq!(p.local_name, "p");
+        assert_eq!(
+            p.attributes,
+            vec![
+                ("class".to_string(), "b".to_string()),
+                ("id".to_string(), "a".to_string())
+            ]
+        );
+        assert_eq!(p.children[0].node_value, "Hi");
+        assert_eq!(p.children[1].node_name, "#comment");
+    }
+
+    #[test]
+    fn selectors_are_queried() {
+        let doc = parse(r#"<div id="x"><p class="note">1</p><p class="note" data-k>2</p></div><p class="note">3</p>"#);
+        let div = query_selector(&doc, doc.root(), "div#x").unwrap();
+        assert_eq!(doc.attribute(div, "id"), Some("x"));
+
+        let second = query_selector(&doc, div, "p.note[data-k]").unwrap();
+        assert_eq!(doc.attribute(second, "data-k"), Some(""));
+        assert!(query_selector(&doc, second, "p").is_none());
+
+        let last = query_selector(&doc, doc.root(), "div + p").unwrap();
+        assert_eq!(doc.attribute(last, "class"), Some("note"));
+        assert_eq!(
+            query_selector(&doc, doc.root(), "div > p:not([data-k])"),
+            doc.children(div).first().copied()
+        );
+        assert!(query_selector(&doc, doc.root(), "p:hover").is_none());
+
+        // Selectors that do not parse match nothing
+        assert!(query_selector(&doc, doc.root(), "p >").is_none());
+    }
+}
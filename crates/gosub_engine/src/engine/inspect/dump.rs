@@ -0,0 +1,550 @@
+//! Debug dumps of a tab's pipeline stages: the DOM, the render tree, the layout tree with its box
+//! models, the layer list and the computed styles of nodes.
+//!
+//! A [`TreeDump`] is plain data. `Display` writes it as indented text for reading, and
+//! [`TreeDump::to_json`] as stable JSON for diffing between releases. Geometry is rounded to
+//! hundredths of a CSS pixel so float noise does not show up as a difference.
+
+use super::DomNode;
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_interface::css3::{CssOrigin, CssProperty as _, CssPropertyMap as _, CssSystem};
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_render_pipeline::common::document::inline_style::parse_inline_style_attr;
+use gosub_render_pipeline::common::document::node::NodeType as PipelineNodeType;
+use gosub_render_pipeline::common::document::pipeline_doc::{PipelineDocument, PipelineNodeKind};
+use gosub_render_pipeline::common::geo;
+use gosub_render_pipeline::layering::layer::LayerList;
+use gosub_render_pipeline::layouter::{Edges, ElementContext, LayoutElementId, LayoutTree};
+use gosub_render_pipeline::render::backend::TileAnchor;
+use gosub_render_pipeline::rendertree_builder::{RenderNodeId, RenderTree};
+use gosub_shared::node::NodeId;
+use serde::Serialize;
+use std::fmt;
+
+/// One pipeline stage of a tab, as asked for by the `TabCommand::Dump*` commands.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "tree", rename_all = "snake_case")]
+pub enum TreeDump {
+    Dom(DomNode),
+    RenderTree(RenderDumpNode),
+    LayoutTree(Box<LayoutDumpNode>),
+    /// Layers in composite order, back to front.
+    Layers(Vec<LayerDump>),
+    /// Elements in tree order.
+    ComputedStyles(Vec<NodeStyles>),
+}
+
+impl TreeDump {
+    /// The dump as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for TreeDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeDump::Dom(root) => write_dom(f, root, 0),
+            TreeDump::RenderTree(root) => root.write(f, 0),
+            TreeDump::LayoutTree(root) => root.write(f, 0),
+            TreeDump::Layers(layers) => layers.iter().try_for_each(|layer| writeln!(f, "{layer}")),
+            TreeDump::ComputedStyles(nodes) => nodes.iter().try_for_each(|node| write!(f, "{node}")),
+        }
+    }
+}
+
+fn write_dom(f: &mut fmt::Formatter<'_>, node: &DomNode, depth: usize) -> fmt::Result {
+    let indent = "  ".repeat(depth);
+    match node.node_type {
+        1 => {
+            write!(f, "{indent}<{}", node.local_name)?;
+            for (name, value) in &node.attributes {
+                write!(f, " {name}=\"{value}\"")?;
+            }
+            writeln!(f, "> #{}", node.node_id)?;
+        }
+        3 => writeln!(f, "{indent}{:?} #{}", node.node_value, node.node_id)?,
+        8 => writeln!(f, "{indent}<!--{}--> #{}", node.node_value, node.node_id)?,
+        10 => writeln!(f, "{indent}<!DOCTYPE {}> #{}", node.node_name, node.node_id)?,
+        _ => writeln!(f, "{indent}{} #{}", node.node_name, node.node_id)?,
+    }
+    node.children
+        .iter()
+        .try_for_each(|child| write_dom(f, child, depth + 1))
+}
+
+/// A node of the render tree: the part of the DOM that renders.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RenderDumpNode {
+    /// The DOM node it renders.
+    pub node_id: u64,
+    /// Tag name of an element, `#text`, or `::pseudo` for generated content.
+    pub name: String,
+    /// The text of a text node.
+    pub text: Option<String>,
+    pub children: Vec<RenderDumpNode>,
+}
+
+impl RenderDumpNode {
+    /// Snapshot of `tree`; `None` when it has no root.
+    pub fn from_render_tree(tree: &RenderTree) -> Option<Self> {
+        tree.root_id.and_then(|root| Self::from_node(tree, root))
+    }
+
+    fn from_node(tree: &RenderTree, id: RenderNodeId) -> Option<Self> {
+        let node = tree.get_node_by_id(id)?;
+        let dom_id = NodeId::from(id);
+        Some(Self {
+            node_id: id.to_u64(),
+            name: node_name(tree.doc.as_ref(), dom_id),
+            text: node_text(tree.doc.as_ref(), dom_id),
+            children: node
+                .children
+                .iter()
+                .filter_map(|&child| Self::from_node(tree, child))
+                .collect(),
+        })
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{}{} #{}", "  ".repeat(depth), self.name, self.node_id)?;
+        if let Some(text) = &self.text {
+            write!(f, " {text:?}")?;
+        }
+        writeln!(f)?;
+        self.children.iter().try_for_each(|child| child.write(f, depth + 1))
+    }
+}
+
+/// A box of the layout tree.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LayoutDumpNode {
+    /// The layout element id, as the layer list refers to it.
+    pub id: u64,
+    /// The DOM node the box was generated for.
+    pub node_id: u64,
+    /// Tag name of an element, `#text`, or `::pseudo` for generated content.
+    pub name: String,
+    /// What the box holds: `box`, `text`, `image`, `svg`, `frame`, `video` or `audio`.
+    pub content: String,
+    /// The text of a text box, or the source of an image or SVG.
+    pub detail: Option<String>,
+    pub box_model: BoxDump,
+    pub children: Vec<LayoutDumpNode>,
+}
+
+impl LayoutDumpNode {
+    /// Snapshot of `tree`, starting at its root box.
+    pub fn from_layout_tree(tree: &LayoutTree) -> Option<Self> {
+        Self::from_node(tree, tree.root_id)
+    }
+
+    fn from_node(tree: &LayoutTree, id: LayoutElementId) -> Option<Self> {
+        let node = tree.get_node_by_id(id)?;
+        let (content, detail) = match &node.context {
+            ElementContext::None => ("box", None),
+            ElementContext::Text(ctx) => ("text", Some(ctx.text.clone())),
+            ElementContext::Image(ctx) => ("image", Some(ctx.src.clone())),
+            ElementContext::Svg(ctx) => ("svg", Some(ctx.src.clone())),
+            ElementContext::Frame(_) => ("frame", None),
+            ElementContext::Media(ctx) => (if ctx.audio { "audio" } else { "video" }, None),
+        };
+        let bm = &node.box_model;
+        Some(Self {
+            id: id.as_u64(),
+            node_id: node.dom_node_id.into(),
+            name: node_name(tree.render_tree.doc.as_ref(), node.dom_node_id),
+            content: content.to_string(),
+            detail,
+            box_model: BoxDump {
+                content_box: bm.content_box.into(),
+                padding_box: bm.padding_box.into(),
+                border_box: bm.border_box.into(),
+                margin_box: bm.margin_box.into(),
+                padding: bm.padding.into(),
+                border: bm.border.into(),
+                margin: bm.margin.into(),
+            },
+            children: node
+                .children
+                .iter()
+                .filter_map(|&child| Self::from_node(tree, child))
+                .collect(),
+        })
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(
+            f,
+            "{indent}{} #{} (layout {}) {}",
+            self.name, self.node_id, self.id, self.content
+        )?;
+        if let Some(detail) = &self.detail {
+            write!(f, " {detail:?}")?;
+        }
+        let bm = &self.box_model;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{indent}  content {} margin {} border {} padding {}",
+            bm.content_box, bm.margin, bm.border, bm.padding
+        )?;
+        self.children.iter().try_for_each(|child| child.write(f, depth + 1))
+    }
+}
+
+/// The box model of a layout box, in page-space CSS pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct BoxDump {
+    pub content_box: RectDump,
+    pub padding_box: RectDump,
+    pub border_box: RectDump,
+    pub margin_box: RectDump,
+    pub padding: EdgesDump,
+    pub border: EdgesDump,
+    pub margin: EdgesDump,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RectDump {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl From<geo::Rect> for RectDump {
+    fn from(rect: geo::Rect) -> Self {
+        Self {
+            x: round(rect.x),
+            y: round(rect.y),
+            width: round(rect.width),
+            height: round(rect.height),
+        }
+    }
+}
+
+impl fmt::Display for RectDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) {}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct EdgesDump {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl From<Edges> for EdgesDump {
+    fn from(edges: Edges) -> Self {
+        Self {
+            top: round(edges.top),
+            right: round(edges.right),
+            bottom: round(edges.bottom),
+            left: round(edges.left),
+        }
+    }
+}
+
+impl fmt::Display for EdgesDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} {} {} {}]", self.top, self.right, self.bottom, self.left)
+    }
+}
+
+/// A compositing layer.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LayerDump {
+    pub layer_id: u64,
+    /// Stacking order within the parent layer.
+    pub order: isize,
+    pub parent: Option<u64>,
+    pub children: Vec<u64>,
+    pub opacity: f32,
+    /// The `mix-blend-mode`, as its variant name.
+    pub blend_mode: String,
+    pub isolated: bool,
+    /// `scroll`, `fixed` or `sticky`.
+    pub anchor: String,
+    pub filters: Vec<String>,
+    pub translation: (f64, f64),
+    /// The layout boxes painted into the layer.
+    pub elements: Vec<u64>,
+}
+
+impl LayerDump {
+    /// Snapshot of every layer of `layers`, in composite order.
+    pub fn from_layer_list(layers: &LayerList) -> Vec<Self> {
+        let by_id = layers.layers.read();
+        layers
+            .layer_ids
+            .read()
+            .iter()
+            .filter_map(|id| by_id.get(id))
+            .map(|layer| Self {
+                layer_id: layer.layer_id.as_u64(),
+                order: layer.order,
+                parent: layer.parent.map(|id| id.as_u64()),
+                children: layer.children.iter().map(|id| id.as_u64()).collect(),
+                opacity: layer.opacity,
+                blend_mode: format!("{:?}", layer.blend_mode),
+                isolated: layer.isolated,
+                anchor: match layer.anchor {
+                    TileAnchor::Scroll => "scroll",
+                    TileAnchor::Fixed => "fixed",
+                    TileAnchor::Sticky(_) => "sticky",
+                }
+                .to_string(),
+                filters: layer.filters.iter().map(|filter| format!("{filter:?}")).collect(),
+                translation: (round(layer.translation.0), round(layer.translation.1)),
+                elements: layer.elements.iter().map(|id| id.as_u64()).collect(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for LayerDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "layer {} order {}", self.layer_id, self.order)?;
+        if let Some(parent) = self.parent {
+            write!(f, " parent {parent}")?;
+        }
+        write!(
+            f,
+            " opacity {} blend {} anchor {}",
+            self.opacity, self.blend_mode, self.anchor
+        )?;
+        if self.isolated {
+            write!(f, " isolated")?;
+        }
+        if self.translation != (0.0, 0.0) {
+            write!(f, " translate ({}, {})", self.translation.0, self.translation.1)?;
+        }
+        if !self.filters.is_empty() {
+            write!(f, " filters [{}]", self.filters.join(", "))?;
+        }
+        write!(f, " elements {:?}", self.elements)
+    }
+}
+
+/// The styles that apply to one element.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NodeStyles {
+    pub node_id: u64,
+    pub name: String,
+    /// The properties declared for the element, sorted by name. Inherited and initial values
+    /// are not listed.
+    pub properties: Vec<StyleDump>,
+}
+
+/// A property's value and the declaration it came from.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StyleDump {
+    pub name: String,
+    pub value: String,
+    /// Origin of the winning declaration: `user-agent`, `user`, `author`, or `inline` for the
+    /// `style` attribute.
+    pub origin: Option<String>,
+    /// The stylesheet of the winning declaration; empty for inline styles.
+    pub location: String,
+}
+
+impl NodeStyles {
+    /// The styles of `node`, or of every element of `doc` in tree order when `node` is `None`.
+    /// Elements that never render (`<head>`, `<script>`, ...) are left out.
+    pub fn from_document<C: RenderConfiguration>(doc: &EngineDocument<C>, node: Option<NodeId>) -> Vec<Self> {
+        let mut out = Vec::new();
+        let mut stack = vec![node.unwrap_or_else(|| doc.root())];
+        while let Some(id) = stack.pop() {
+            if doc.node_type(id) == NodeType::ElementNode {
+                out.extend(Self::from_element::<C>(doc, id));
+            }
+            if node.is_none() {
+                stack.extend(doc.children(id).iter().rev());
+            }
+        }
+        out
+    }
+
+    fn from_element<C: RenderConfiguration>(doc: &EngineDocument<C>, id: NodeId) -> Option<Self> {
+        let mut map = C::CssSystem::properties_from_node::<C>(doc, id, doc.stylesheets())?;
+        let mut properties: Vec<StyleDump> = map
+            .iter_mut()
+            .map(|(name, prop)| {
+                prop.compute_value();
+                let (origin, location) = match prop.winning_declaration() {
+                    Some((origin, location)) => (Some(origin_name(origin)), location.to_string()),
+                    None => (None, String::new()),
+                };
+                StyleDump {
+                    name: name.to_string(),
+                    value: prop.to_string(),
+                    origin: origin.map(str::to_string),
+                    location,
+                }
+            })
+            .collect();
+
+        // The pipeline lets the `style` attribute override every stylesheet.
+        if let Some(style) = doc.attribute(id, "style") {
+            for (name, value) in parse_inline_style_attr(style).to_string_map() {
+                properties.retain(|prop| prop.name != name);
+                properties.push(StyleDump {
+                    name,
+                    value,
+                    origin: Some("inline".to_string()),
+                    location: String::new(),
+                });
+            }
+        }
+        properties.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Some(Self {
+            node_id: id.into(),
+            name: doc.tag_name(id).unwrap_or_default().to_string(),
+            properties,
+        })
+    }
+}
+
+impl fmt::Display for NodeStyles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} #{}", self.name, self.node_id)?;
+        for prop in &self.properties {
+            write!(f, "  {}: {}", prop.name, prop.value)?;
+            match (&prop.origin, prop.location.as_str()) {
+                (Some(origin), "") => writeln!(f, " [{origin}]")?,
+                (Some(origin), location) => writeln!(f, " [{origin} {location}]")?,
+                (None, _) => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn origin_name(origin: CssOrigin) -> &'static str {
+    match origin {
+        CssOrigin::UserAgent => "user-agent",
+        CssOrigin::User => "user",
+        CssOrigin::Author => "author",
+    }
+}
+
+fn node_name(doc: &dyn PipelineDocument, id: NodeId) -> String {
+    if let Some(tag) = doc.tag_name(id) {
+        return tag;
+    }
+    match doc.node_kind(id) {
+        PipelineNodeKind::Text => "#text",
+        PipelineNodeKind::Comment => "#comment",
+        PipelineNodeKind::Element => "::pseudo",
+    }
+    .to_string()
+}
+
+fn node_text(doc: &dyn PipelineDocument, id: NodeId) -> Option<String> {
+    match doc.get_node_by_id(id)?.node_type {
+        PipelineNodeType::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Rounds to hundredths, so dumps of the same layout compare equal across float noise.
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::DefaultRenderConfig;
+    use gosub_css3::system::Css3System;
+    use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+    use gosub_render_pipeline::common::geo::Dimension;
+    use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+    use gosub_render_pipeline::layouter::CanLayout;
+    use std::sync::Arc;
+
+    fn parse(html: &str) -> EngineDocument<DefaultRenderConfig> {
+        let mut doc = gosub_html5::html_compile::<DefaultRenderConfig>(html);
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        doc
+    }
+
+    fn layout(html: &str) -> LayerList {
+        let adapter = GosubDocumentAdapter::<DefaultRenderConfig>::new(Arc::new(parse(html)));
+        let mut render_tree = RenderTree::new(Arc::new(adapter));
+        render_tree.parse().unwrap();
+        let layout_tree = TaffyLayouter::new().layout(render_tree, Some(Dimension::new(800.0, 600.0)), 1.0);
+        LayerList::new(layout_tree)
+    }
+
+    fn find<'a>(node: &'a LayoutDumpNode, name: &str) -> Option<&'a LayoutDumpNode> {
+        if node.name == name {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find(child, name))
+    }
+
+    #[test]
+    fn computed_styles_name_the_winning_declaration() {
+        let doc = parse(r#"<style>p { color: red }</style><p style="margin-top: 4px">Hi</p>"#);
+        let styles = NodeStyles::from_document(&doc, None);
+        let p = styles.iter().find(|node| node.name == "p").unwrap();
+        let prop = |name: &str| p.properties.iter().find(|prop| prop.name == name).unwrap();
+
+        assert_eq!(prop("color").value, "red");
+        assert_eq!(prop("color").origin.as_deref(), Some("author"));
+        assert_eq!(prop("color").location, "<unknown>#inline");
+        assert_eq!(prop("display").origin.as_deref(), Some("user-agent"));
+        assert_eq!(prop("margin-top").origin.as_deref(), Some("inline"));
+
+        // Unrenderable elements are left out, and a single node can be asked for
+        assert!(styles.iter().all(|node| node.name != "style"));
+        let single = NodeStyles::from_document(&doc, Some(NodeId::from(p.node_id)));
+        assert_eq!(single, vec![p.clone()]);
+    }
+
+    #[test]
+    fn layout_dumps_carry_box_models() {
+        let layers = layout("<body><p>Hello</p></body>");
+        let root = LayoutDumpNode::from_layout_tree(&layers.layout_tree).unwrap();
+        let body = find(&root, "body").unwrap();
+        assert_eq!(body.box_model.margin.left, 8.0);
+        assert_eq!(body.box_model.content_box.x, 8.0);
+        let text = find(body, "#text").unwrap();
+        assert_eq!(text.content, "text");
+        assert_eq!(text.detail.as_deref(), Some("Hello"));
+
+        let dump = TreeDump::LayoutTree(Box::new(root.clone()));
+        assert!(dump
+            .to_string()
+            .contains(&format!("body #{} (layout {}) box", body.node_id, body.id)));
+        let json: serde_json::Value = serde_json::from_str(&dump.to_json()).unwrap();
+        assert_eq!(json["kind"], "layout_tree");
+        assert_eq!(json["tree"]["id"], root.id);
+
+        let render = RenderDumpNode::from_render_tree(&layers.layout_tree.render_tree).unwrap();
+        let render_text = TreeDump::RenderTree(render).to_string();
+        assert!(render_text.contains(&format!("body #{}\n", body.node_id)));
+        assert!(render_text.contains("#text #"));
+
+        let layer_dump = LayerDump::from_layer_list(&layers);
+        assert!(!layer_dump.is_empty());
+        assert!(layer_dump.iter().any(|layer| layer.elements.contains(&body.id)));
+    }
+
+    #[test]
+    fn dom_dump_is_indented_text() {
+        let doc = parse(r#"<p class="a">Hi</p>"#);
+        let dump = TreeDump::Dom(DomNode::from_document(&doc, doc.root())).to_string();
+        assert!(dump.starts_with("#document #"));
+        assert!(dump.contains("\n      <p class=\"a\"> #"));
+        assert!(dump.contains("\n        \"Hi\" #"));
+    }
+}
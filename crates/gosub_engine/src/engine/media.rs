@@ -0,0 +1,22 @@
+//! Playback of `<video>` and `<audio>` elements.
+//!
+//! A media resource is fetched whole and handed to a [`StreamDecoders`] registry, which picks a
+//! [`StreamDecoder`] by MIME type or magic bytes and opens it as a [`MediaStream`]: its duration,
+//! its video size, and its frames by index. A [`Playback`] runs the element's state machine off a
+//! clock; the tab presents the frame under its position and reports state changes as
+//! [`EngineEvent::MediaStateChanged`](crate::events::EngineEvent::MediaStateChanged).
+//!
+//! Decoders are pluggable through [`GosubEngine::register_media_decoder`](crate::GosubEngine).
+//! The built-in set is pure Rust and small: uncompressed Y4M video ([`Y4mDecoder`]) and PCM WAV
+//! audio ([`WavDecoder`]). The engine has no audio output, so audio plays silently against the
+//! clock.
+
+mod decoder;
+mod playback;
+mod wav;
+mod y4m;
+
+pub use decoder::{MediaStream, StreamDecodeError, StreamDecoder, StreamDecoders, StreamInfo};
+pub use playback::{Playback, PlaybackState};
+pub use wav::WavDecoder;
+pub use y4m::Y4mDecoder;
@@ -0,0 +1,162 @@
+use bytes::Bytes;
+use gosub_render_pipeline::common::media::DecodedImage;
+use std::fmt;
+use std::time::Duration;
+
+use crate::media::{WavDecoder, Y4mDecoder};
+
+/// What a [`MediaStream`] holds, known once it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// Width and height of the video frames; `None` for audio.
+    pub video_size: Option<(u32, u32)>,
+    /// Number of video frames (0 for audio).
+    pub frame_count: u64,
+    /// How long the media plays.
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamDecodeError {
+    /// No registered decoder claimed the MIME type or recognised the magic bytes.
+    UnsupportedFormat,
+    /// A decoder claimed the bytes but failed to open them.
+    Decode(String),
+}
+
+impl fmt::Display for StreamDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamDecodeError::UnsupportedFormat => f.write_str("unsupported media format"),
+            StreamDecodeError::Decode(msg) => write!(f, "failed to decode media: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for StreamDecodeError {}
+
+/// An opened media resource. Frames are decoded on demand, so opening only has to index them.
+pub trait MediaStream: Send {
+    fn info(&self) -> StreamInfo;
+
+    /// Index of the video frame on screen at `position`, or `None` without video. Past the end
+    /// this is the last frame.
+    fn frame_index_at(&self, position: Duration) -> Option<u64> {
+        let info = self.info();
+        if info.frame_count == 0 || info.duration.is_zero() {
+            return None;
+        }
+        let index = (position.as_secs_f64() / info.duration.as_secs_f64() * info.frame_count as f64) as u64;
+        Some(index.min(info.frame_count - 1))
+    }
+
+    /// Decode video frame `index`; `None` without video or when the frame is corrupt.
+    fn decode_frame(&mut self, index: u64) -> Option<DecodedImage>;
+}
+
+/// A single format handler. Decoders are matched first by MIME hint, then by magic bytes.
+pub trait StreamDecoder: Send + Sync {
+    /// Short, stable identifier (used in logs).
+    fn name(&self) -> &'static str;
+
+    /// Whether this decoder handles the given MIME type. Implementations must compare
+    /// case-insensitively and tolerate `;`-delimited parameters.
+    fn supports_mime(&self, mime: &str) -> bool;
+
+    /// Whether the leading bytes look like a format this decoder handles.
+    fn supports_magic(&self, bytes: &[u8]) -> bool;
+
+    fn open(&self, data: Bytes) -> Result<Box<dyn MediaStream>, StreamDecodeError>;
+}
+
+/// Ordered set of decoders. As for images, the MIME hint is only a hint: a MIME-matched decoder
+/// that fails to open the bytes falls through to magic-byte sniffing before giving up.
+pub struct StreamDecoders {
+    decoders: Vec<Box<dyn StreamDecoder>>,
+}
+
+impl StreamDecoders {
+    /// An empty registry; see [`StreamDecoders::with_defaults`] for the built-in set.
+    pub fn new() -> Self {
+        Self { decoders: Vec::new() }
+    }
+
+    /// The built-in decoder set: Y4M video and WAV audio.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(Y4mDecoder));
+        registry.register(Box::new(WavDecoder));
+        registry
+    }
+
+    /// Add a decoder. Later decoders are tried after the earlier ones.
+    pub fn register(&mut self, decoder: Box<dyn StreamDecoder>) {
+        self.decoders.push(decoder);
+    }
+
+    /// Whether some decoder handles `mime`, as for a `<source type>` (cf. `canPlayType()`).
+    pub fn can_play_type(&self, mime: &str) -> bool {
+        self.decoders.iter().any(|d| d.supports_mime(mime))
+    }
+
+    /// Open `data`, using `mime` as a hint. Tries MIME-matched decoders first, then falls back
+    /// to magic-byte sniffing.
+    pub fn open(&self, mime: Option<&str>, data: Bytes) -> Result<Box<dyn MediaStream>, StreamDecodeError> {
+        let mut last_err: Option<StreamDecodeError> = None;
+
+        if let Some(mime) = mime {
+            for decoder in self.decoders.iter().filter(|d| d.supports_mime(mime)) {
+                match decoder.open(data.clone()) {
+                    Ok(stream) => return Ok(stream),
+                    Err(e) => {
+                        log::debug!("media decoder '{}' (mime '{}') failed: {}", decoder.name(), mime, e);
+                        last_err = Some(e);
+                    }
+                }
+            }
+        }
+
+        for decoder in self.decoders.iter().filter(|d| d.supports_magic(&data)) {
+            match decoder.open(data.clone()) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    log::debug!("media decoder '{}' (magic) failed: {}", decoder.name(), e);
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or(StreamDecodeError::UnsupportedFormat))
+    }
+}
+
+impl Default for StreamDecoders {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// The MIME essence of `mime` (`type/subtype`, lowercased, parameters dropped) equals one of
+/// `types`.
+pub(super) fn mime_is(mime: &str, types: &[&str]) -> bool {
+    let essence = mime.split(';').next().unwrap_or("").trim();
+    types.iter().any(|t| essence.eq_ignore_ascii_case(t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_bytes_error() {
+        let registry = StreamDecoders::with_defaults();
+        let err = registry.open(Some("video/mp4"), Bytes::from_static(b"not media")).err();
+        assert_eq!(err, Some(StreamDecodeError::UnsupportedFormat));
+    }
+
+    #[test]
+    fn mime_parameters_are_ignored() {
+        assert!(mime_is("Video/X-YUV4MPEG; codecs=raw", &["video/x-yuv4mpeg"]));
+        assert!(!mime_is("video/mp4", &["video/x-yuv4mpeg"]));
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+/// Where a media element's playback is, as reported in
+/// [`EngineEvent::MediaStateChanged`](crate::events::EngineEvent::MediaStateChanged).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    /// The resource is being fetched and opened.
+    Loading,
+    Paused,
+    Playing,
+    /// Played to the end without `loop`; playing again starts over.
+    Ended,
+    /// The resource could not be fetched or no decoder opened it.
+    Error,
+}
+
+/// The playback state machine of one media element, driven by a clock the caller passes in.
+///
+/// While playing, the position is not stepped per tick but derived from the instant playback
+/// last started, so a late tick never slows the media down - it only skips frames.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    state: PlaybackState,
+    /// The position when playback last started or stopped.
+    position: Duration,
+    /// While playing, the instant `position` was taken.
+    anchor: Option<Instant>,
+    duration: Duration,
+    looping: bool,
+    /// Play was asked for (or `autoplay` set) before the media loaded.
+    pending_play: bool,
+}
+
+impl Playback {
+    pub fn new(autoplay: bool, looping: bool) -> Self {
+        Self {
+            state: PlaybackState::Loading,
+            position: Duration::ZERO,
+            anchor: None,
+            duration: Duration::ZERO,
+            looping,
+            pending_play: autoplay,
+        }
+    }
+
+    pub fn state(&self) -> PlaybackState {
+        self.state
+    }
+
+    /// The media's length, once loaded.
+    pub fn duration(&self) -> Option<Duration> {
+        match self.state {
+            PlaybackState::Loading | PlaybackState::Error => None,
+            _ => Some(self.duration),
+        }
+    }
+
+    /// The playback position at `now`. A looping element wraps around; any other stops at the end.
+    pub fn position(&self, now: Instant) -> Duration {
+        let Some(anchor) = self.anchor else {
+            return self.position;
+        };
+        let position = self.position + now.saturating_duration_since(anchor);
+        if self.looping && !self.duration.is_zero() {
+            Duration::from_nanos((position.as_nanos() % self.duration.as_nanos()) as u64)
+        } else {
+            position.min(self.duration)
+        }
+    }
+
+    /// The resource opened: pause at the start, or start playing if that was asked for.
+    pub fn loaded(&mut self, duration: Duration, now: Instant) {
+        if self.state != PlaybackState::Loading {
+            return;
+        }
+        self.duration = duration;
+        self.state = PlaybackState::Paused;
+        if std::mem::take(&mut self.pending_play) {
+            self.play(now);
+        }
+    }
+
+    pub fn failed(&mut self) {
+        self.state = PlaybackState::Error;
+        self.anchor = None;
+    }
+
+    /// Start or resume playback; an ended element starts over. Before the media has loaded this
+    /// only remembers to play once it does. Returns whether the state changed.
+    pub fn play(&mut self, now: Instant) -> bool {
+        match self.state {
+            PlaybackState::Loading => {
+                self.pending_play = true;
+                false
+            }
+            PlaybackState::Paused | PlaybackState::Ended => {
+                if self.state == PlaybackState::Ended {
+                    self.position = Duration::ZERO;
+                }
+                self.state = PlaybackState::Playing;
+                self.anchor = Some(now);
+                true
+            }
+            PlaybackState::Playing | PlaybackState::Error => false,
+        }
+    }
+
+    /// Pause at the current position. Returns whether the state changed.
+    pub fn pause(&mut self, now: Instant) -> bool {
+        match self.state {
+            PlaybackState::Loading => {
+                self.pending_play = false;
+                false
+            }
+            PlaybackState::Playing => {
+                self.position = self.position(now);
+                self.anchor = None;
+                self.state = PlaybackState::Paused;
+                true
+            }
+            PlaybackState::Paused | PlaybackState::Ended | PlaybackState::Error => false,
+        }
+    }
+
+    /// Advance the clock to `now`: a non-looping element that reached its end stops there.
+    /// Returns whether the state changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        if self.state != PlaybackState::Playing || self.looping {
+            return false;
+        }
+        let position = self.position(now);
+        if position < self.duration {
+            return false;
+        }
+        self.position = self.duration;
+        self.anchor = None;
+        self.state = PlaybackState::Ended;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn play_pause_and_end() {
+        let t0 = Instant::now();
+        let mut playback = Playback::new(false, false);
+        assert!(!playback.play(t0), "play while loading is deferred");
+        assert!(!playback.pause(t0), "and pause cancels it");
+        playback.loaded(3 * SECOND, t0);
+        assert_eq!(playback.state(), PlaybackState::Paused);
+
+        assert!(playback.play(t0));
+        assert_eq!(playback.position(t0 + SECOND), SECOND);
+        assert!(playback.pause(t0 + SECOND));
+        assert_eq!(playback.position(t0 + 5 * SECOND), SECOND, "paused time doesn't count");
+
+        assert!(playback.play(t0 + 5 * SECOND));
+        assert!(!playback.tick(t0 + 6 * SECOND));
+        assert!(playback.tick(t0 + 7 * SECOND));
+        assert_eq!(playback.state(), PlaybackState::Ended);
+        assert_eq!(playback.position(t0 + 9 * SECOND), 3 * SECOND);
+
+        assert!(playback.play(t0 + 10 * SECOND), "playing an ended element starts over");
+        assert_eq!(playback.position(t0 + 10 * SECOND), Duration::ZERO);
+    }
+
+    #[test]
+    fn autoplay_starts_on_load_and_loop_wraps() {
+        let t0 = Instant::now();
+        let mut playback = Playback::new(true, true);
+        assert_eq!(playback.duration(), None);
+        playback.loaded(2 * SECOND, t0);
+        assert_eq!(playback.state(), PlaybackState::Playing);
+        assert_eq!(playback.duration(), Some(2 * SECOND));
+        assert!(!playback.tick(t0 + 5 * SECOND), "a looping element never ends");
+        assert_eq!(playback.position(t0 + 5 * SECOND), SECOND);
+    }
+
+    #[test]
+    fn a_failed_load_cannot_play() {
+        let t0 = Instant::now();
+        let mut playback = Playback::new(true, false);
+        playback.failed();
+        assert!(!playback.play(t0));
+        assert_eq!(playback.state(), PlaybackState::Error);
+    }
+}
@@ -0,0 +1,121 @@
+//! RIFF WAVE audio. Only the length matters while the engine has no audio output, so opening reads
+//! the `fmt ` chunk's byte rate and the size of the `data` chunk and leaves the samples alone.
+
+use bytes::Bytes;
+use gosub_render_pipeline::common::media::DecodedImage;
+use std::time::Duration;
+
+use crate::media::decoder::mime_is;
+use crate::media::{MediaStream, StreamDecodeError, StreamDecoder, StreamInfo};
+
+/// Opens WAV audio.
+pub struct WavDecoder;
+
+impl StreamDecoder for WavDecoder {
+    fn name(&self) -> &'static str {
+        "wav"
+    }
+
+    fn supports_mime(&self, mime: &str) -> bool {
+        mime_is(mime, &["audio/wav", "audio/wave", "audio/x-wav", "audio/vnd.wave"])
+    }
+
+    fn supports_magic(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+    }
+
+    fn open(&self, data: Bytes) -> Result<Box<dyn MediaStream>, StreamDecodeError> {
+        Ok(Box::new(WavStream {
+            duration: wav_duration(&data)?,
+        }))
+    }
+}
+
+struct WavStream {
+    duration: Duration,
+}
+
+impl MediaStream for WavStream {
+    fn info(&self) -> StreamInfo {
+        StreamInfo {
+            video_size: None,
+            frame_count: 0,
+            duration: self.duration,
+        }
+    }
+
+    fn decode_frame(&mut self, _index: u64) -> Option<DecodedImage> {
+        None
+    }
+}
+
+/// The play length of a WAV file: its `data` chunk size over the `fmt ` chunk's byte rate.
+fn wav_duration(data: &[u8]) -> Result<Duration, StreamDecodeError> {
+    let err = |msg: &str| StreamDecodeError::Decode(format!("wav: {msg}"));
+    if !WavDecoder.supports_magic(data) {
+        return Err(err("not a RIFF WAVE file"));
+    }
+
+    let u32_at = |at: usize| -> Option<u32> { Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?)) };
+    let mut byte_rate = None;
+    let mut pos = 12;
+    while let (Some(id), Some(size)) = (data.get(pos..pos + 4), u32_at(pos + 4)) {
+        let body = pos + 8;
+        match id {
+            b"fmt " => byte_rate = u32_at(body + 8),
+            b"data" => {
+                let byte_rate = byte_rate
+                    .filter(|r| *r > 0)
+                    .ok_or_else(|| err("no byte rate before data"))?;
+                // A streamed file may leave the size unset; it then runs to the end of the file
+                let len = (size as usize).min(data.len().saturating_sub(body));
+                return Ok(Duration::from_secs_f64(len as f64 / byte_rate as f64));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        pos = body + size as usize + (size as usize & 1);
+    }
+    Err(err("no data chunk"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mono 8 kHz 16-bit WAV holding `samples` samples of silence.
+    fn wav(samples: usize) -> Vec<u8> {
+        let data_len = (samples * 2) as u32;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // channels
+        out.extend_from_slice(&8000u32.to_le_bytes()); // sample rate
+        out.extend_from_slice(&16000u32.to_le_bytes()); // byte rate
+        out.extend_from_slice(&2u16.to_le_bytes()); // block align
+        out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.resize(out.len() + data_len as usize, 0);
+        out
+    }
+
+    #[test]
+    fn duration_comes_from_the_data_chunk() {
+        let stream = WavDecoder.open(Bytes::from(wav(4000))).expect("valid wav");
+        let info = stream.info();
+        assert_eq!(info.duration, Duration::from_millis(500));
+        assert_eq!(info.video_size, None);
+        assert_eq!(stream.frame_index_at(Duration::ZERO), None);
+    }
+
+    #[test]
+    fn rejects_files_without_data() {
+        let mut bytes = wav(0);
+        bytes.truncate(36);
+        assert!(WavDecoder.open(Bytes::from(bytes)).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! YUV4MPEG2 (`.y4m`): uncompressed planar YUV frames behind a one-line text header. No codec, so
+//! it is the test format of choice, and every frame is a fixed-size slice of the file.
+
+use bytes::Bytes;
+use gosub_render_pipeline::common::media::DecodedImage;
+use std::time::Duration;
+
+use crate::media::decoder::mime_is;
+use crate::media::{MediaStream, StreamDecodeError, StreamDecoder, StreamInfo};
+
+const MAGIC: &[u8] = b"YUV4MPEG2 ";
+const FRAME_TAG: &[u8] = b"FRAME";
+
+/// Opens YUV4MPEG2 video.
+pub struct Y4mDecoder;
+
+impl StreamDecoder for Y4mDecoder {
+    fn name(&self) -> &'static str {
+        "y4m"
+    }
+
+    fn supports_mime(&self, mime: &str) -> bool {
+        mime_is(mime, &["video/x-yuv4mpeg", "video/yuv4mpeg"])
+    }
+
+    fn supports_magic(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    fn open(&self, data: Bytes) -> Result<Box<dyn MediaStream>, StreamDecodeError> {
+        Ok(Box::new(Y4mStream::parse(data)?))
+    }
+}
+
+/// How the chroma planes are subsampled (the `C` header parameter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chroma {
+    C420,
+    C422,
+    C444,
+    Mono,
+}
+
+impl Chroma {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            // 420jpeg, 420paldv and 420mpeg2 only differ in chroma siting
+            v if v.starts_with("420") => Some(Chroma::C420),
+            "422" => Some(Chroma::C422),
+            "444" => Some(Chroma::C444),
+            "mono" => Some(Chroma::Mono),
+            _ => None,
+        }
+    }
+
+    /// Width and height of each chroma plane for a `width`×`height` frame.
+    fn plane_size(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Chroma::C420 => (width.div_ceil(2), height.div_ceil(2)),
+            Chroma::C422 => (width.div_ceil(2), height),
+            Chroma::C444 => (width, height),
+            Chroma::Mono => (0, 0),
+        }
+    }
+}
+
+struct Y4mStream {
+    data: Bytes,
+    width: usize,
+    height: usize,
+    chroma: Chroma,
+    /// Byte offset of each frame's Y plane.
+    frames: Vec<usize>,
+    duration: Duration,
+}
+
+impl Y4mStream {
+    fn parse(data: Bytes) -> Result<Self, StreamDecodeError> {
+        let err = |msg: &str| StreamDecodeError::Decode(format!("y4m: {msg}"));
+
+        let header_end = data.iter().position(|b| *b == b'\n').ok_or_else(|| err("no header"))?;
+        let header = std::str::from_utf8(&data[..header_end]).map_err(|_| err("header is not text"))?;
+        let params = header.strip_prefix("YUV4MPEG2").ok_or_else(|| err("bad magic"))?;
+
+        let (mut width, mut height, mut rate, mut chroma) = (0usize, 0usize, (25u64, 1u64), Chroma::C420);
+        for param in params.split_ascii_whitespace() {
+            let mut chars = param.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => width = value.parse().map_err(|_| err("bad width"))?,
+                Some('H') => height = value.parse().map_err(|_| err("bad height"))?,
+                Some('F') => {
+                    let (num, den) = value.split_once(':').ok_or_else(|| err("bad frame rate"))?;
+                    rate = (
+                        num.parse().map_err(|_| err("bad frame rate"))?,
+                        den.parse().map_err(|_| err("bad frame rate"))?,
+                    );
+                }
+                Some('C') => chroma = Chroma::parse(value).ok_or_else(|| err("unsupported colour space"))?,
+                // Interlacing, aspect ratio and extensions don't change how frames decode
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 || rate.0 == 0 || rate.1 == 0 {
+            return Err(err("missing frame size or rate"));
+        }
+
+        let (cw, ch) = chroma.plane_size(width, height);
+        let frame_len = width * height + 2 * cw * ch;
+        let mut frames = Vec::new();
+        let mut pos = header_end + 1;
+        while data[pos..].starts_with(FRAME_TAG) {
+            // Frame headers may carry parameters of their own; skip to the end of the line
+            let Some(line_end) = data[pos..].iter().position(|b| *b == b'\n') else {
+                break;
+            };
+            let start = pos + line_end + 1;
+            if start + frame_len > data.len() {
+                log::debug!("y4m: dropping truncated frame {}", frames.len());
+                break;
+            }
+            frames.push(start);
+            pos = start + frame_len;
+        }
+        if frames.is_empty() {
+            return Err(err("no frames"));
+        }
+
+        let nanos = frames.len() as u128 * rate.1 as u128 * 1_000_000_000 / rate.0 as u128;
+        let duration = Duration::from_nanos(nanos.min(u64::MAX as u128) as u64);
+        Ok(Self {
+            data,
+            width,
+            height,
+            chroma,
+            frames,
+            duration,
+        })
+    }
+}
+
+impl MediaStream for Y4mStream {
+    fn info(&self) -> StreamInfo {
+        StreamInfo {
+            video_size: Some((self.width as u32, self.height as u32)),
+            frame_count: self.frames.len() as u64,
+            duration: self.duration,
+        }
+    }
+
+    fn decode_frame(&mut self, index: u64) -> Option<DecodedImage> {
+        let start = *self.frames.get(index as usize)?;
+        let (w, h) = (self.width, self.height);
+        let (cw, ch) = self.chroma.plane_size(w, h);
+        let y_plane = &self.data[start..start + w * h];
+        let u_plane = &self.data[start + w * h..start + w * h + cw * ch];
+        let v_plane = &self.data[start + w * h + cw * ch..start + w * h + 2 * cw * ch];
+
+        let mut rgba = Vec::with_capacity(w * h * 4);
+        for row in 0..h {
+            for col in 0..w {
+                let y = y_plane[row * w + col];
+                let (u, v) = match self.chroma {
+                    Chroma::Mono => (128, 128),
+                    _ => {
+                        let (cx, cy) = (col * cw / w, row * ch / h);
+                        (u_plane[cy * cw + cx], v_plane[cy * cw + cx])
+                    }
+                };
+                let [r, g, b] = yuv_to_rgb(y, u, v);
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        DecodedImage::new_rgba8(w as u32, h as u32, rgba).ok()
+    }
+}
+
+/// BT.601 limited-range YCbCr to RGB, the colour model Y4M files default to.
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let y = (y as f32 - 16.0) * 1.164;
+    let u = u as f32 - 128.0;
+    let v = v as f32 - 128.0;
+    let clamp = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(y + 1.596 * v),
+        clamp(y - 0.392 * u - 0.813 * v),
+        clamp(y + 2.017 * u),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2×2 4:2:0 clip at 10 fps: one black frame, then one white frame.
+    fn clip() -> Bytes {
+        let mut data = b"YUV4MPEG2 W2 H2 F10:1 Ip A1:1 C420jpeg\n".to_vec();
+        for luma in [16u8, 235] {
+            data.extend_from_slice(b"FRAME\n");
+            data.extend_from_slice(&[luma; 4]);
+            data.extend_from_slice(&[128, 128]);
+        }
+        Bytes::from(data)
+    }
+
+    #[test]
+    fn indexes_frames_and_derives_duration() {
+        let stream = Y4mDecoder.open(clip()).expect("valid y4m");
+        let info = stream.info();
+        assert_eq!(info.video_size, Some((2, 2)));
+        assert_eq!(info.frame_count, 2);
+        assert_eq!(info.duration, Duration::from_millis(200));
+        assert_eq!(stream.frame_index_at(Duration::from_millis(50)), Some(0));
+        assert_eq!(stream.frame_index_at(Duration::from_millis(150)), Some(1));
+        assert_eq!(stream.frame_index_at(Duration::from_secs(5)), Some(1));
+    }
+
+    #[test]
+    fn decodes_frames_to_rgba() {
+        let mut stream = Y4mDecoder.open(clip()).expect("valid y4m");
+        let black = stream.decode_frame(0).expect("frame 0");
+        assert_eq!(&black.as_raw()[..4], &[0, 0, 0, 255]);
+        let white = stream.decode_frame(1).expect("frame 1");
+        assert_eq!(&white.as_raw()[..4], &[255, 255, 255, 255]);
+        assert!(stream.decode_frame(2).is_none());
+    }
+
+    #[test]
+    fn truncated_clip_keeps_its_whole_frames() {
+        let mut data = clip().to_vec();
+        data.truncate(data.len() - 3);
+        let stream = Y4mDecoder.open(Bytes::from(data)).expect("valid y4m");
+        assert_eq!(stream.info().frame_count, 1);
+        assert!(Y4mDecoder.open(Bytes::from_static(b"YUV4MPEG2 W2 F10:1\n")).is_err());
+    }
+}
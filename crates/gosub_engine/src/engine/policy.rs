@@ -4,7 +4,7 @@ pub struct UaPolicy {
     /// Enable sniffing of content to determine MIME type
     pub enable_sniffing: bool,
     /// Allow mislabelled document navigations (e.g. `text/plain` or `application/octet-stream`
-    /// bodies that sniff as HTML) to be upgraded to the HTML parser.
+    /// bodies that sniff as HTML or XML) to be upgraded to the HTML or XML parser.
     pub enable_sniffing_navigation_upgrade: bool,
     /// Enable PDF viewer
     pub enable_pdf_viewer: bool,
@@ -7,15 +7,18 @@ use crate::engine::resource_pipeline::font::{FontPipeline, FontPipelineImpl};
 use crate::engine::resource_pipeline::html::{HtmlPipeline, HtmlPipelineImpl};
 use crate::engine::resource_pipeline::image::{ImagePipeline, ImagePipelineImpl};
 use crate::engine::resource_pipeline::js::{JsPipeline, JsPipelineImpl};
+use crate::engine::resource_pipeline::media::{MediaPipeline, MediaPipelineImpl};
 use crate::engine::types::IoChannel;
 use crate::html::RenderConfiguration;
 use crate::zone::ZoneId;
+use gosub_render_pipeline::image_source::ImageEnvironment;
 
 pub mod css;
 pub mod font;
 pub mod html;
 pub mod image;
 pub mod js;
+pub mod media;
 
 /// Resource pipeline entry points used by the router for each resource type.
 pub struct ResourcePipelines<C: RenderConfiguration> {
@@ -24,6 +27,7 @@ pub struct ResourcePipelines<C: RenderConfiguration> {
     pub js: Box<dyn JsPipeline + Send>,
     pub images: Box<dyn ImagePipeline + Send>,
     pub fonts: Box<dyn FontPipeline + Send>,
+    pub media: Box<dyn MediaPipeline + Send>,
     // pub viewer: &'a mut dyn ViewerPipeline,
     // pub download: &'a mut dyn DownloadManager,
     // pub external: &'a mut dyn ExternalOpener,
@@ -31,17 +35,34 @@ pub struct ResourcePipelines<C: RenderConfiguration> {
 
 impl<C: RenderConfiguration> ResourcePipelines<C> {
     pub fn new(zone_id: ZoneId, io_tx: IoChannel, accept_language: Option<String>, max_document_bytes: usize) -> Self {
+        Self::with_image_environment(
+            zone_id,
+            io_tx,
+            accept_language,
+            max_document_bytes,
+            ImageEnvironment::default(),
+        )
+    }
+
+    /// Like [`Self::new`], with the viewport and device-pixel ratio a document's preload scanner
+    /// chooses responsive image candidates for.
+    pub fn with_image_environment(
+        zone_id: ZoneId,
+        io_tx: IoChannel,
+        accept_language: Option<String>,
+        max_document_bytes: usize,
+        image_environment: ImageEnvironment,
+    ) -> Self {
         Self {
-            html: Box::new(HtmlPipelineImpl::new(
-                zone_id,
-                io_tx,
-                accept_language,
-                max_document_bytes,
-            )),
+            html: Box::new(
+                HtmlPipelineImpl::new(zone_id, io_tx, accept_language, max_document_bytes)
+                    .with_image_environment(image_environment),
+            ),
             css: Box::new(CssPipelineImpl {}),
             js: Box::new(JsPipelineImpl {}),
             images: Box::new(ImagePipelineImpl {}),
             fonts: Box::new(FontPipelineImpl {}),
+            media: Box::new(MediaPipelineImpl {}),
         }
     }
 }
@@ -2,9 +2,15 @@ use crate::engine::types::PeekBuf;
 use crate::net::types::FetchResultMeta;
 use crate::net::{stream_to_bytes, SharedBody};
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::sync::Arc;
 
-pub type DummyFont = String;
+/// A downloaded web font, unwrapped to bytes the font systems can register: a flat SFNT
+/// (TrueType/OpenType/collection), or WOFF1, which the backends decode themselves.
+#[derive(Debug, Clone)]
+pub struct WebFont {
+    pub data: Vec<u8>,
+}
 
 #[async_trait]
 pub trait FontPipeline {
@@ -13,9 +19,9 @@ pub trait FontPipeline {
         meta: FetchResultMeta,
         peek_buf: PeekBuf,
         body: Arc<SharedBody>,
-    ) -> anyhow::Result<DummyFont>;
+    ) -> anyhow::Result<WebFont>;
 
-    async fn parse_bytes(&mut self, meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<DummyFont>;
+    async fn parse_bytes(&mut self, meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<WebFont>;
 }
 
 pub struct FontPipelineImpl;
@@ -27,15 +33,155 @@ impl FontPipeline for FontPipelineImpl {
         _meta: FetchResultMeta,
         peek_buf: PeekBuf,
         shared: Arc<SharedBody>,
-    ) -> anyhow::Result<DummyFont> {
-        // Normally, we send chunks to the Font parser. Right now, we just collect everything
+    ) -> anyhow::Result<WebFont> {
+        // Fonts can't be used before they are complete, so collect everything first
         match stream_to_bytes(peek_buf, shared).await {
-            Ok(buf) => Ok(String::from_utf8_lossy(buf.as_ref()).to_string()),
+            Ok(buf) => decode_web_font(buf.to_vec()),
             Err(e) => Err(anyhow::anyhow!("Failed to read font stream: {}", e)),
         }
     }
 
-    async fn parse_bytes(&mut self, _meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<DummyFont> {
-        Ok(String::from_utf8_lossy(body).to_string())
+    async fn parse_bytes(&mut self, _meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<WebFont> {
+        decode_web_font(body.to_vec())
+    }
+}
+
+/// Unwrap a downloaded web-font payload into bytes the font backends can decode.
+///
+/// WOFF2 (magic `wOF2`) is a Brotli-compressed wrapper around an OpenType/TrueType font,
+/// with the `glyf`/`loca` tables stored in a transformed form. Skia and fontconfig don't
+/// decode it (e.g. Google Fonts serves WOFF2 to modern UAs like ours), so we decompress it
+/// to a flat SFNT here. Bare SFNT (`OTTO`/`true`/`ttcf`/`0x00010000`) and WOFF1 are returned
+/// unchanged; anything else is not a font.
+fn decode_web_font(bytes: Vec<u8>) -> anyhow::Result<WebFont> {
+    const WOFF2_MAGIC: &[u8; 4] = b"wOF2";
+    const PASSTHROUGH: [&[u8; 4]; 5] = [b"OTTO", b"true", b"ttcf", b"\x00\x01\x00\x00", b"wOFF"];
+
+    let Some(magic) = bytes.first_chunk::<4>() else {
+        return Err(anyhow::anyhow!("Font payload too short ({} bytes)", bytes.len()));
+    };
+    if magic == WOFF2_MAGIC {
+        let sfnt = woff2_to_sfnt(&bytes).map_err(|e| anyhow::anyhow!("Failed to decode WOFF2 font: {e}"))?;
+        log::debug!("Decoded WOFF2 web font ({} → {} bytes)", bytes.len(), sfnt.len());
+        return Ok(WebFont { data: sfnt });
+    }
+    if PASSTHROUGH.contains(&magic) {
+        return Ok(WebFont { data: bytes });
+    }
+    Err(anyhow::anyhow!("Unrecognised font format (magic {magic:02x?})"))
+}
+
+/// Decompress a WOFF2 font to a flat SFNT (TTF/OTF) byte buffer. allsorts handles the Brotli
+/// decompression and the `glyf`/`loca` transform reconstruction; we then re-assemble the
+/// reconstructed tables into the on-disk SFNT layout (offset table + table directory + 4-byte
+/// aligned table data) that font backends expect.
+fn woff2_to_sfnt(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    use allsorts::binary::read::ReadScope;
+    use allsorts::woff2::Woff2Font;
+
+    let font = ReadScope::new(bytes)
+        .read::<Woff2Font<'_>>()
+        .map_err(|e| format!("parse: {e:?}"))?;
+    let sfnt_version = font.flavor();
+    let tables = font
+        .table_provider(0)
+        .map_err(|e| format!("reconstruct: {e:?}"))?
+        .into_tables();
+
+    Ok(assemble_sfnt(sfnt_version, tables))
+}
+
+/// Pack a set of font tables into an SFNT byte buffer per the OpenType spec: a 12-byte offset
+/// table, a 16-byte directory entry per table (sorted by tag), then each table's data padded to
+/// a 4-byte boundary. Per-table checksums are computed; the `head` table's `checkSumAdjustment`
+/// is left as-is (font backends parse without validating it).
+fn assemble_sfnt(sfnt_version: u32, tables: HashMap<u32, Box<[u8]>>) -> Vec<u8> {
+    let mut entries: Vec<(u32, Box<[u8]>)> = tables.into_iter().collect();
+    entries.sort_by_key(|(tag, _)| *tag);
+    let num_tables = entries.len() as u16;
+
+    // Binary-search hint fields: largest power of two <= num_tables.
+    let mut entry_selector = 0u16;
+    while (1u16 << (entry_selector + 1)) <= num_tables {
+        entry_selector += 1;
+    }
+    let search_range = (1u16 << entry_selector) * 16;
+    let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
+
+    let mut directory = Vec::with_capacity(16 * entries.len());
+    let mut data = Vec::new();
+    let mut offset = 12 + 16 * entries.len();
+    for (tag, table) in &entries {
+        directory.extend_from_slice(&tag.to_be_bytes());
+        directory.extend_from_slice(&sfnt_table_checksum(table).to_be_bytes());
+        directory.extend_from_slice(&(offset as u32).to_be_bytes());
+        directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        data.extend_from_slice(table);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset += (table.len() + 3) & !3;
+    }
+
+    let mut out = Vec::with_capacity(12 + directory.len() + data.len());
+    out.extend_from_slice(&sfnt_version.to_be_bytes());
+    out.extend_from_slice(&num_tables.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&range_shift.to_be_bytes());
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&data);
+    out
+}
+
+/// SFNT table checksum: the sum of the table's contents read as big-endian `u32`s, with the
+/// final partial word zero-padded, in wrapping (mod 2^32) arithmetic.
+fn sfnt_table_checksum(data: &[u8]) -> u32 {
+    let mut sum = 0u32;
+    for chunk in data.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum = sum.wrapping_add(u32::from_be_bytes(word));
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    /// A subset of Open Sans Light Italic (Apache 2.0, see `OPEN-SANS-LICENSE.txt` next to it).
+    const WOFF2_FIXTURE: &[u8] = include_bytes!("../../../tests/data/fonts/open-sans-300italic.woff2");
+
+    /// Verify `decode_web_font` turns a real WOFF2 payload into an SFNT the font stack can
+    /// parse.
+    #[test]
+    fn decode_web_font_woff2_roundtrips_to_sfnt() {
+        let woff2 = WOFF2_FIXTURE.to_vec();
+        assert_eq!(&woff2[0..4], b"wOF2", "fixture must be WOFF2");
+
+        let sfnt = super::decode_web_font(woff2).expect("decode WOFF2").data;
+
+        // Output must be a different, valid SFNT (TrueType `0x00010000` or OpenType `OTTO`).
+        let magic = u32::from_be_bytes([sfnt[0], sfnt[1], sfnt[2], sfnt[3]]);
+        assert!(magic == 0x0001_0000 || magic == 0x4F54_544F, "not SFNT: {magic:#010x}");
+
+        // It must re-parse and expose the core tables a backend reads.
+        use allsorts::binary::read::ReadScope;
+        use allsorts::font_data::FontData;
+        use allsorts::tables::FontTableProvider;
+        let font = ReadScope::new(&sfnt).read::<FontData<'_>>().expect("parse SFNT");
+        let provider = font.table_provider(0).expect("table provider");
+        for tag in [allsorts::tag::HEAD, allsorts::tag::CMAP, allsorts::tag::GLYF] {
+            assert!(provider.has_table(tag), "missing table {tag:#010x}");
+        }
+    }
+
+    #[test]
+    fn decode_web_font_passes_sfnt_through_and_rejects_non_fonts() {
+        let roboto = gosub_shared::ROBOTO_FONT.to_vec();
+        let decoded = super::decode_web_font(roboto.clone()).expect("bare SFNT is accepted");
+        assert_eq!(decoded.data, roboto, "SFNT must pass through unchanged");
+
+        assert!(super::decode_web_font(b"<!doctype html>".to_vec()).is_err());
+        assert!(super::decode_web_font(vec![0, 1]).is_err());
     }
 }
@@ -1,5 +1,7 @@
 use crate::engine::types::{IoChannel, PeekBuf, RequestId};
-use crate::html::{parse_main_document_stream, EngineDocument, RenderConfiguration, ResourceHint};
+use crate::html::{
+    parse_main_document_stream, parse_xml_document_stream, EngineDocument, RenderConfiguration, ResourceHint,
+};
 use crate::net::req_ref_tracker::REF_REGISTRY;
 use crate::net::types::{FetchHandle, FetchRequest, FetchResultMeta, Initiator};
 use crate::net::{submit_to_io, SharedBody};
@@ -9,6 +11,7 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::stream;
+use gosub_render_pipeline::image_source::ImageEnvironment;
 use gosub_shared::timing_guard;
 use http::Method;
 use parking_lot::Mutex;
@@ -35,6 +38,32 @@ pub trait HtmlPipeline<C: RenderConfiguration> {
         meta: FetchResultMeta,
         body: &[u8],
     ) -> anyhow::Result<EngineDocument<C>>;
+
+    /// Like [`HtmlPipeline::parse_stream`], for XML documents (XHTML, SVG and other XML)
+    async fn parse_xml_stream(
+        &mut self,
+        request: FetchRequest,
+        handle: FetchHandle,
+        meta: FetchResultMeta,
+        peek_buf: PeekBuf,
+        body: Arc<SharedBody>,
+    ) -> anyhow::Result<EngineDocument<C>>;
+
+    /// Like [`HtmlPipeline::parse_bytes`], for XML documents (XHTML, SVG and other XML)
+    async fn parse_xml_bytes(
+        &mut self,
+        request: FetchRequest,
+        handle: FetchHandle,
+        meta: FetchResultMeta,
+        body: &[u8],
+    ) -> anyhow::Result<EngineDocument<C>>;
+}
+
+/// Which parser a main document goes to
+#[derive(Clone, Copy)]
+enum DocumentSyntax {
+    Html,
+    Xml,
 }
 
 pub struct HtmlPipelineImpl {
@@ -44,6 +73,8 @@ pub struct HtmlPipelineImpl {
     accept_language: Option<String>,
     /// Max document size in bytes (`net.document.max_bytes`); larger documents are truncated.
     max_document_bytes: usize,
+    /// What the preload scanner picks `srcset` candidates for.
+    image_environment: ImageEnvironment,
 }
 
 impl HtmlPipelineImpl {
@@ -53,15 +84,22 @@ impl HtmlPipelineImpl {
             zone_id,
             accept_language,
             max_document_bytes,
+            image_environment: ImageEnvironment::default(),
         }
     }
 
+    pub fn with_image_environment(mut self, image_environment: ImageEnvironment) -> Self {
+        self.image_environment = image_environment;
+        self
+    }
+
     async fn parse_with_reader<C, R>(
         &mut self,
         request: FetchRequest,
         handle: FetchHandle,
         meta: FetchResultMeta,
         reader: R,
+        syntax: DocumentSyntax,
     ) -> anyhow::Result<EngineDocument<C>>
     where
         C: RenderConfiguration,
@@ -69,6 +107,7 @@ impl HtmlPipelineImpl {
     {
         let cfg = crate::html::HtmlParseConfig {
             max_bytes: self.max_document_bytes,
+            image_environment: self.image_environment,
         };
 
         let io_tx = self.io_tx.clone();
@@ -132,14 +171,21 @@ impl HtmlPipelineImpl {
         let was_cancelled = handle.cancel.is_cancelled();
 
         let _doc_timer = timing_guard!("html.document", meta.final_url.as_str());
-        let res = parse_main_document_stream(
-            meta.final_url, // This is the base URL
-            reader,
-            handle.cancel.clone(),
-            cfg,
-            &mut on_discover,
-        )
-        .await;
+        let res = match syntax {
+            DocumentSyntax::Html => {
+                parse_main_document_stream(
+                    meta.final_url, // This is the base URL
+                    reader,
+                    handle.cancel.clone(),
+                    cfg,
+                    &mut on_discover,
+                )
+                .await
+            }
+            DocumentSyntax::Xml => {
+                parse_xml_document_stream(meta.final_url, reader, handle.cancel.clone(), cfg, &mut on_discover).await
+            }
+        };
 
         // Cancel the parent token so that all child fetch tokens (which are children of
         // parent_cancel via child_token()) are also cancelled. This works regardless of
@@ -159,7 +205,10 @@ impl HtmlPipelineImpl {
             }
         }
 
-        res.map_err(|e| anyhow!("Failed to parse HTML document: {:?}", e))
+        res.map_err(|e| match syntax {
+            DocumentSyntax::Html => anyhow!("Failed to parse HTML document: {:?}", e),
+            DocumentSyntax::Xml => anyhow!("Failed to parse XML document: {:?}", e),
+        })
     }
 }
 
@@ -174,7 +223,8 @@ impl<C: RenderConfiguration> HtmlPipeline<C> for HtmlPipelineImpl {
         shared: Arc<SharedBody>,
     ) -> anyhow::Result<EngineDocument<C>> {
         let reader = SharedBody::combined_reader(peek_buf, shared);
-        self.parse_with_reader::<C, _>(request, handle, meta, reader).await
+        self.parse_with_reader::<C, _>(request, handle, meta, reader, DocumentSyntax::Html)
+            .await
     }
 
     async fn parse_bytes(
@@ -187,7 +237,34 @@ impl<C: RenderConfiguration> HtmlPipeline<C> for HtmlPipelineImpl {
         // parsing bytes is just creating a stream of those bytes and passing it to the stream reader
         let stream = stream::iter(vec![Ok::<Bytes, std::io::Error>(Bytes::copy_from_slice(body))]);
         let reader = StreamReader::new(stream);
-        self.parse_with_reader::<C, _>(request, handle, meta, reader).await
+        self.parse_with_reader::<C, _>(request, handle, meta, reader, DocumentSyntax::Html)
+            .await
+    }
+
+    async fn parse_xml_stream(
+        &mut self,
+        request: FetchRequest,
+        handle: FetchHandle,
+        meta: FetchResultMeta,
+        peek_buf: PeekBuf,
+        shared: Arc<SharedBody>,
+    ) -> anyhow::Result<EngineDocument<C>> {
+        let reader = SharedBody::combined_reader(peek_buf, shared);
+        self.parse_with_reader::<C, _>(request, handle, meta, reader, DocumentSyntax::Xml)
+            .await
+    }
+
+    async fn parse_xml_bytes(
+        &mut self,
+        request: FetchRequest,
+        handle: FetchHandle,
+        meta: FetchResultMeta,
+        body: &[u8],
+    ) -> anyhow::Result<EngineDocument<C>> {
+        let stream = stream::iter(vec![Ok::<Bytes, std::io::Error>(Bytes::copy_from_slice(body))]);
+        let reader = StreamReader::new(stream);
+        self.parse_with_reader::<C, _>(request, handle, meta, reader, DocumentSyntax::Xml)
+            .await
     }
 }
 
@@ -0,0 +1,62 @@
+use crate::engine::types::PeekBuf;
+use crate::net::types::FetchResultMeta;
+use crate::net::{stream_to_bytes, SharedBody};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+
+/// A downloaded `<video>`/`<audio>` resource, still encoded. The tab opens it with the engine's
+/// [`StreamDecoders`](crate::media::StreamDecoders).
+#[derive(Debug, Clone)]
+pub struct MediaResource {
+    /// The `Content-Type` the server declared, a hint for picking the decoder.
+    pub mime: Option<String>,
+    pub data: Bytes,
+}
+
+#[async_trait]
+pub trait MediaPipeline {
+    async fn parse_stream(
+        &mut self,
+        meta: FetchResultMeta,
+        peek_buf: PeekBuf,
+        body: Arc<SharedBody>,
+    ) -> anyhow::Result<MediaResource>;
+
+    async fn parse_bytes(&mut self, meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<MediaResource>;
+}
+
+pub struct MediaPipelineImpl;
+
+#[async_trait]
+impl MediaPipeline for MediaPipelineImpl {
+    async fn parse_stream(
+        &mut self,
+        meta: FetchResultMeta,
+        peek_buf: PeekBuf,
+        shared: Arc<SharedBody>,
+    ) -> anyhow::Result<MediaResource> {
+        // Decoders index the whole resource up front, so collect everything first
+        match stream_to_bytes(peek_buf, shared).await {
+            Ok(data) => Ok(MediaResource {
+                mime: content_type(&meta),
+                data,
+            }),
+            Err(e) => Err(anyhow::anyhow!("Failed to read media stream: {}", e)),
+        }
+    }
+
+    async fn parse_bytes(&mut self, meta: FetchResultMeta, body: &[u8]) -> anyhow::Result<MediaResource> {
+        Ok(MediaResource {
+            mime: content_type(&meta),
+            data: Bytes::copy_from_slice(body),
+        })
+    }
+}
+
+fn content_type(meta: &FetchResultMeta) -> Option<String> {
+    meta.headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
@@ -1,4 +1,6 @@
+mod frames;
 mod handle;
+mod media_elements;
 mod options;
 mod scroll;
 pub mod services;
@@ -6,6 +8,7 @@ mod sink;
 mod state;
 #[allow(clippy::module_inception)]
 mod tab;
+mod web_fonts;
 mod worker;
 
 pub use handle::TabHandle;
@@ -0,0 +1,328 @@
+//! Nested browsing contexts for the document's `<iframe>` elements.
+//!
+//! Every iframe gets a [`ChildFrame`]: a [`BrowsingContext`] of its own - document, style cascade,
+//! layout and scroll offset - sized to the iframe's content box. The worker loads its document
+//! through the zone fetcher and renders it offscreen; the parent paints that rendering as the
+//! iframe's replaced content, so it is clipped to the iframe box. [`Frames`] only does the
+//! bookkeeping - which iframes exist, which must load, which need a new rendering - and the
+//! worker does the fetching and drawing.
+//!
+//! Frames nest one level: iframes inside a frame's document stay empty boxes.
+
+use crate::engine::BrowsingContext;
+use crate::html::{EngineDocument, RenderConfiguration};
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// What an iframe shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FrameSource {
+    /// The `srcdoc` markup, parsed as the frame's document.
+    Srcdoc(String),
+    /// A document fetched from this URL.
+    Url(Url),
+    /// `about:blank`: an empty document, so nothing paints over the iframe's background.
+    Blank,
+}
+
+impl FrameSource {
+    /// The source of an iframe with these attributes (HTML §4.8.5): `srcdoc` wins over `src`, and
+    /// `src` resolves against the parent's base URL. A missing, empty or unresolvable `src` and
+    /// `about:` URLs show a blank document.
+    pub(crate) fn from_attributes(src: Option<&str>, srcdoc: Option<&str>, base: Option<&Url>) -> Self {
+        if let Some(srcdoc) = srcdoc {
+            return FrameSource::Srcdoc(srcdoc.to_string());
+        }
+        let src = src.map(str::trim).filter(|s| !s.is_empty());
+        let url = src.and_then(|src| match base {
+            Some(base) => base.join(src).ok(),
+            None => Url::parse(src).ok(),
+        });
+        match url {
+            Some(url) if matches!(url.scheme(), "http" | "https" | "file" | "data") => FrameSource::Url(url),
+            _ => FrameSource::Blank,
+        }
+    }
+}
+
+/// Whether a `sandbox` attribute puts the frame's document in a unique opaque origin, so it gets
+/// no cookies and storage of its own instead of its origin's. Any `sandbox` does, unless its
+/// tokens include `allow-same-origin`. Scripts never run in the engine, so the origin is the
+/// restriction left to enforce.
+pub(crate) fn sandbox_isolates(sandbox: Option<&str>) -> bool {
+    sandbox.is_some_and(|tokens| {
+        !tokens
+            .split_ascii_whitespace()
+            .any(|token| token.eq_ignore_ascii_case("allow-same-origin"))
+    })
+}
+
+/// What an iframe element asks for. A change loads the frame's document again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FrameRequest {
+    pub source: FrameSource,
+    /// The frame's document lives in an opaque origin (see [`sandbox_isolates`]).
+    pub isolated: bool,
+}
+
+/// The nested browsing context of one iframe.
+pub(crate) struct ChildFrame<C: RenderConfiguration> {
+    pub request: FrameRequest,
+    pub context: BrowsingContext<C>,
+    /// The iframe's content box in the parent's page space.
+    pub rect: Rect,
+    /// Set when the frame's rendering changed and the parent needs a new snapshot of it.
+    pub dirty: bool,
+    /// Id of the in-flight load; a document carrying any other id is a stale load's.
+    load_id: u64,
+    /// Cancels the frame's load when the frame goes away or loads something else.
+    cancel: CancellationToken,
+}
+
+impl<C: RenderConfiguration> ChildFrame<C> {
+    /// The frame's viewport: its content box, rounded to whole CSS pixels.
+    fn viewport(&self) -> Viewport {
+        Viewport::new(0, 0, self.rect.width.round() as u32, self.rect.height.round() as u32)
+    }
+}
+
+/// A frame load to start, as returned by [`Frames::sync`].
+pub(crate) struct FrameLoad {
+    pub node_id: NodeId,
+    pub load_id: u64,
+    pub request: FrameRequest,
+    pub cancel: CancellationToken,
+}
+
+/// The child frames of the tab's document, keyed by iframe element.
+pub(crate) struct Frames<C: RenderConfiguration> {
+    frames: HashMap<NodeId, ChildFrame<C>>,
+    next_load_id: u64,
+}
+
+impl<C: RenderConfiguration> Default for Frames<C> {
+    fn default() -> Self {
+        Self {
+            frames: HashMap::new(),
+            next_load_id: 0,
+        }
+    }
+}
+
+impl<C: RenderConfiguration> Frames<C> {
+    /// Reconcile with the iframes of the parent's latest layout. An iframe that is new or asks for
+    /// something else gets a fresh context from `new_context` and comes back as a load to start;
+    /// one that is gone is dropped, cancelling its load. A moved or resized frame is re-rendered.
+    /// Loads are derived from `parent_cancel`, so a new navigation of the tab cancels them.
+    pub(crate) fn sync(
+        &mut self,
+        elements: Vec<(NodeId, Rect, FrameRequest)>,
+        parent_cancel: &CancellationToken,
+        mut new_context: impl FnMut() -> BrowsingContext<C>,
+    ) -> Vec<FrameLoad> {
+        let present: Vec<NodeId> = elements.iter().map(|(node_id, _, _)| *node_id).collect();
+        self.frames.retain(|node_id, frame| {
+            let keep = present.contains(node_id);
+            if !keep {
+                frame.cancel.cancel();
+            }
+            keep
+        });
+
+        let mut loads = Vec::new();
+        for (node_id, rect, request) in elements {
+            if let Some(frame) = self.frames.get_mut(&node_id) {
+                if frame.request == request {
+                    if frame.rect != rect {
+                        frame.rect = rect;
+                        let viewport = frame.viewport();
+                        frame.context.set_viewport(viewport);
+                        frame.dirty = true;
+                    }
+                    continue;
+                }
+                frame.cancel.cancel();
+            }
+
+            let load_id = self.next_load_id;
+            self.next_load_id += 1;
+            let cancel = parent_cancel.child_token();
+            let mut frame = ChildFrame {
+                request: request.clone(),
+                context: new_context(),
+                rect,
+                dirty: false,
+                load_id,
+                cancel: cancel.clone(),
+            };
+            let viewport = frame.viewport();
+            frame.context.set_viewport(viewport);
+            self.frames.insert(node_id, frame);
+            loads.push(FrameLoad {
+                node_id,
+                load_id,
+                request,
+                cancel,
+            });
+        }
+        loads
+    }
+
+    /// Install the document of load `load_id` in the frame of `node_id`. Returns `false` when the
+    /// load is stale: the frame is gone or has started another load since.
+    pub(crate) fn finish(&mut self, node_id: NodeId, load_id: u64, doc: Arc<EngineDocument<C>>) -> bool {
+        match self.frames.get_mut(&node_id) {
+            Some(frame) if frame.load_id == load_id => {
+                frame.context.set_document(doc);
+                frame.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The frame whose iframe box contains page point `(x, y)`.
+    pub(crate) fn at_mut(&mut self, x: f64, y: f64) -> Option<&mut ChildFrame<C>> {
+        self.frames.values_mut().find(|frame| {
+            let r = frame.rect;
+            x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+        })
+    }
+
+    pub(crate) fn get_mut(&mut self, node_id: NodeId) -> Option<&mut ChildFrame<C>> {
+        self.frames.get_mut(&node_id)
+    }
+
+    /// Pick up images the frames' documents finished loading; those frames need a new snapshot.
+    /// Returns whether any did.
+    pub(crate) fn poll_media_completed(&mut self) -> bool {
+        let mut any = false;
+        for frame in self.frames.values_mut() {
+            if frame.context.poll_media_completed() {
+                frame.dirty = true;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Advance the animated images of every frame's document; see
+    /// [`BrowsingContext::advance_image_animations`].
+    pub(crate) fn advance_image_animations(&mut self, now: Instant) -> bool {
+        let mut any = false;
+        for frame in self.frames.values_mut() {
+            if frame.context.advance_image_animations(now) {
+                frame.dirty = true;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Advance the CSS transitions and animations of every frame's document; see
+    /// [`BrowsingContext::advance_css_animations`].
+    pub(crate) fn advance_css_animations(&mut self, now: Instant) -> bool {
+        let mut any = false;
+        for frame in self.frames.values_mut() {
+            if frame.context.advance_css_animations(now) {
+                frame.dirty = true;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Pass the display's device-pixel ratio on to every frame's document; see
+    /// [`BrowsingContext::set_device_pixel_ratio`].
+    pub(crate) fn set_device_pixel_ratio(&mut self, dpr: f32) -> bool {
+        let mut any = false;
+        for frame in self.frames.values_mut() {
+            if frame.context.set_device_pixel_ratio(dpr) {
+                frame.dirty = true;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Start fetching the lazy images that came near each frame's viewport.
+    pub(crate) fn load_lazy_images(&self) {
+        for frame in self.frames.values() {
+            frame.context.load_lazy_images();
+        }
+    }
+
+    /// Pause the animated images of every frame's document along with the parent's.
+    pub(crate) fn pause_image_animations(&mut self, now: Instant) {
+        for frame in self.frames.values_mut() {
+            frame.context.pause_image_animations(now);
+        }
+    }
+
+    pub(crate) fn resume_image_animations(&mut self, now: Instant) {
+        for frame in self.frames.values_mut() {
+            frame.context.resume_image_animations(now);
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (NodeId, &mut ChildFrame<C>)> {
+        self.frames.iter_mut().map(|(node_id, frame)| (*node_id, frame))
+    }
+
+    /// Drop every frame and cancel their loads (a new document replaces the parent's).
+    pub(crate) fn clear(&mut self) {
+        for frame in self.frames.values() {
+            frame.cancel.cancel();
+        }
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srcdoc_wins_and_src_resolves_against_the_base() {
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        assert_eq!(
+            FrameSource::from_attributes(Some("inner.html"), Some("<p>hi</p>"), Some(&base)),
+            FrameSource::Srcdoc("<p>hi</p>".into())
+        );
+        assert_eq!(
+            FrameSource::from_attributes(Some(" inner.html "), None, Some(&base)),
+            FrameSource::Url(Url::parse("https://example.com/dir/inner.html").unwrap())
+        );
+        assert_eq!(
+            FrameSource::from_attributes(None, None, Some(&base)),
+            FrameSource::Blank
+        );
+        assert_eq!(
+            FrameSource::from_attributes(Some(""), None, Some(&base)),
+            FrameSource::Blank
+        );
+        assert_eq!(
+            FrameSource::from_attributes(Some("about:blank"), None, Some(&base)),
+            FrameSource::Blank
+        );
+        assert_eq!(
+            FrameSource::from_attributes(Some("javascript:alert(1)"), None, Some(&base)),
+            FrameSource::Blank
+        );
+    }
+
+    #[test]
+    fn sandbox_isolates_unless_same_origin_is_allowed() {
+        assert!(!sandbox_isolates(None));
+        assert!(sandbox_isolates(Some("")));
+        assert!(sandbox_isolates(Some("allow-scripts allow-forms")));
+        assert!(!sandbox_isolates(Some("allow-scripts ALLOW-SAME-ORIGIN")));
+        assert!(!sandbox_isolates(Some("\tallow-same-origin\n")));
+    }
+}
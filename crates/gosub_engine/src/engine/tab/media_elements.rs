@@ -0,0 +1,399 @@
+//! Playback of the document's `<video>` and `<audio>` elements.
+//!
+//! Every media element gets a [`MediaElement`]: its resource, once fetched and opened by one of
+//! the engine's [`StreamDecoders`](crate::media::StreamDecoders), and a [`Playback`] clock. The
+//! worker fetches and opens the resources and hands what changed to the painter; [`MediaElements`]
+//! only does the bookkeeping - which elements exist and what they load, and which frame each one
+//! shows at a given instant.
+
+use crate::media::{MediaStream, Playback, PlaybackState};
+use gosub_render_pipeline::common::media::DecodedImage;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// What a media element's attributes ask for. A change loads the element again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MediaRequest {
+    /// The resource to play (see [`select_source`]).
+    pub source: Option<Url>,
+    pub audio: bool,
+    pub autoplay: bool,
+    pub looping: bool,
+    /// A `poster` is set, so it shows instead of the first frame until playback starts.
+    pub poster: bool,
+}
+
+/// The resource a media element plays (HTML §4.8.11.5, without the retry on failure): its `src`,
+/// else the first `<source>` child (given as `(src, type)`) with a `src` whose `type` is absent or
+/// one `can_play` accepts. URLs resolve against `base`; one that doesn't, or that isn't fetchable,
+/// leaves the element without a resource.
+pub(crate) fn select_source<'a>(
+    src: Option<&str>,
+    sources: impl IntoIterator<Item = (Option<&'a str>, Option<&'a str>)>,
+    base: Option<&Url>,
+    can_play: impl Fn(&str) -> bool,
+) -> Option<Url> {
+    let src = match src {
+        Some(src) => src,
+        None => sources.into_iter().find_map(|(src, mime)| {
+            let mime = mime.map(str::trim).filter(|m| !m.is_empty());
+            src.filter(|_| mime.is_none_or(&can_play))
+        })?,
+    };
+    let src = src.trim();
+    if src.is_empty() {
+        return None;
+    }
+    let url = match base {
+        Some(base) => base.join(src).ok()?,
+        None => Url::parse(src).ok()?,
+    };
+    matches!(url.scheme(), "http" | "https" | "file" | "data").then_some(url)
+}
+
+/// A media element's playback state as `EngineEvent::MediaStateChanged` reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MediaStatus {
+    pub state: PlaybackState,
+    pub position: Duration,
+    pub duration: Option<Duration>,
+}
+
+/// What changed about one media element since the last [`MediaElements::tick`].
+pub(crate) struct MediaUpdate {
+    pub node_id: NodeId,
+    /// A newly decoded video frame to show.
+    pub frame: Option<DecodedImage>,
+    pub playing: bool,
+    /// Playback position as a fraction of the duration.
+    pub progress: f32,
+    /// Set when the playback state changed, to be reported.
+    pub status: Option<MediaStatus>,
+}
+
+struct MediaElement {
+    request: MediaRequest,
+    playback: Playback,
+    /// The opened resource, once loaded.
+    stream: Option<Box<dyn MediaStream>>,
+    /// Index of the frame on screen.
+    shown_frame: Option<u64>,
+    /// Playback has started at least once, so frames replace the poster.
+    started: bool,
+    /// The state last reported.
+    reported: Option<PlaybackState>,
+    /// Whether it played and how far, as last handed to the painter.
+    presented: Option<(bool, f32)>,
+    /// Id of the in-flight load; a resource carrying any other id is a stale load's.
+    load_id: u64,
+    /// Cancels the element's load when it goes away or loads something else.
+    cancel: CancellationToken,
+}
+
+/// A resource load to start, as returned by [`MediaElements::sync`].
+pub(crate) struct MediaLoad {
+    pub node_id: NodeId,
+    pub load_id: u64,
+    pub url: Url,
+    pub audio: bool,
+    pub cancel: CancellationToken,
+}
+
+/// The media elements of the tab's document, keyed by element.
+#[derive(Default)]
+pub(crate) struct MediaElements {
+    elements: HashMap<NodeId, MediaElement>,
+    next_load_id: u64,
+}
+
+impl MediaElements {
+    /// Reconcile with the media elements of the document. An element that is new or asks for
+    /// another resource starts over - paused, or playing once loaded with `autoplay` - and comes
+    /// back as a load to start; one without a resource errors right away. One that is gone is
+    /// dropped, cancelling its load. Loads are derived from `parent_cancel`, so a new navigation
+    /// of the tab cancels them.
+    pub(crate) fn sync(
+        &mut self,
+        elements: Vec<(NodeId, MediaRequest)>,
+        parent_cancel: &CancellationToken,
+    ) -> Vec<MediaLoad> {
+        let present: Vec<NodeId> = elements.iter().map(|(node_id, _)| *node_id).collect();
+        self.elements.retain(|node_id, element| {
+            let keep = present.contains(node_id);
+            if !keep {
+                element.cancel.cancel();
+            }
+            keep
+        });
+
+        let mut loads = Vec::new();
+        for (node_id, request) in elements {
+            if let Some(element) = self.elements.get(&node_id) {
+                if element.request == request {
+                    continue;
+                }
+                element.cancel.cancel();
+            }
+
+            let load_id = self.next_load_id;
+            self.next_load_id += 1;
+            let cancel = parent_cancel.child_token();
+            let mut playback = Playback::new(request.autoplay, request.looping);
+            match &request.source {
+                Some(url) => loads.push(MediaLoad {
+                    node_id,
+                    load_id,
+                    url: url.clone(),
+                    audio: request.audio,
+                    cancel: cancel.clone(),
+                }),
+                None => playback.failed(),
+            }
+            self.elements.insert(
+                node_id,
+                MediaElement {
+                    request,
+                    playback,
+                    stream: None,
+                    shown_frame: None,
+                    started: false,
+                    reported: None,
+                    presented: None,
+                    load_id,
+                    cancel,
+                },
+            );
+        }
+        loads
+    }
+
+    /// Install the outcome of load `load_id` of `node_id`: the opened resource, or why it failed.
+    /// Returns `false` when the load is stale: the element is gone or has started another since.
+    pub(crate) fn finish(
+        &mut self,
+        node_id: NodeId,
+        load_id: u64,
+        stream: Result<Box<dyn MediaStream>, String>,
+        now: Instant,
+    ) -> bool {
+        let Some(element) = self.elements.get_mut(&node_id).filter(|e| e.load_id == load_id) else {
+            return false;
+        };
+        match stream {
+            Ok(stream) => {
+                element.playback.loaded(stream.info().duration, now);
+                element.stream = Some(stream);
+            }
+            Err(e) => {
+                log::warn!("Media element {node_id:?} failed to load: {e}");
+                element.playback.failed();
+            }
+        }
+        true
+    }
+
+    /// Start or resume playback of `node_id`. Returns whether there is such an element.
+    pub(crate) fn play(&mut self, node_id: NodeId, now: Instant) -> bool {
+        self.elements
+            .get_mut(&node_id)
+            .map(|element| element.playback.play(now))
+            .is_some()
+    }
+
+    /// Pause `node_id`. Returns whether there is such an element.
+    pub(crate) fn pause(&mut self, node_id: NodeId, now: Instant) -> bool {
+        self.elements
+            .get_mut(&node_id)
+            .map(|element| element.playback.pause(now))
+            .is_some()
+    }
+
+    /// Pause `node_id` when it plays, else play it - the controls' play/pause button.
+    pub(crate) fn toggle(&mut self, node_id: NodeId, now: Instant) -> bool {
+        match self.elements.get(&node_id).map(|e| e.playback.state()) {
+            Some(PlaybackState::Playing) => self.pause(node_id, now),
+            Some(_) => self.play(node_id, now),
+            None => false,
+        }
+    }
+
+    /// Advance every element's clock to `now` and collect what changed: a new frame to show,
+    /// progress while playing, a state to report.
+    pub(crate) fn tick(&mut self, now: Instant) -> Vec<MediaUpdate> {
+        let mut updates = Vec::new();
+        for (node_id, element) in self.elements.iter_mut() {
+            element.playback.tick(now);
+            let state = element.playback.state();
+            let position = element.playback.position(now);
+            element.started |= state == PlaybackState::Playing;
+
+            let mut frame = None;
+            if element.started || !element.request.poster {
+                if let Some(stream) = &mut element.stream {
+                    let index = stream.frame_index_at(position);
+                    if index.is_some() && index != element.shown_frame {
+                        frame = index.and_then(|index| stream.decode_frame(index));
+                        element.shown_frame = index;
+                    }
+                }
+            }
+
+            let playing = state == PlaybackState::Playing;
+            let progress = match element.playback.duration() {
+                Some(duration) if !duration.is_zero() => (position.as_secs_f64() / duration.as_secs_f64()) as f32,
+                _ => 0.0,
+            };
+            let status = (element.reported != Some(state)).then(|| MediaStatus {
+                state,
+                position,
+                duration: element.playback.duration(),
+            });
+            element.reported = Some(state);
+
+            if frame.is_none() && status.is_none() && element.presented == Some((playing, progress)) {
+                continue;
+            }
+            element.presented = Some((playing, progress));
+            updates.push(MediaUpdate {
+                node_id: *node_id,
+                frame,
+                playing,
+                progress,
+                status,
+            });
+        }
+        updates
+    }
+
+    /// Drop every element and cancel their loads (a new document replaces the tab's).
+    pub(crate) fn clear(&mut self) {
+        for element in self.elements.values() {
+            element.cancel.cancel();
+        }
+        self.elements.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::StreamInfo;
+
+    /// A one-pixel video of `frames` frames at one frame per second; frame `i` is grey level `i`.
+    struct TestStream {
+        frames: u64,
+    }
+
+    impl MediaStream for TestStream {
+        fn info(&self) -> StreamInfo {
+            StreamInfo {
+                video_size: Some((1, 1)),
+                frame_count: self.frames,
+                duration: Duration::from_secs(self.frames),
+            }
+        }
+
+        fn decode_frame(&mut self, index: u64) -> Option<DecodedImage> {
+            let level = index as u8;
+            DecodedImage::new_rgba8(1, 1, vec![level, level, level, 255]).ok()
+        }
+    }
+
+    fn request(poster: bool, autoplay: bool) -> MediaRequest {
+        MediaRequest {
+            source: Url::parse("https://example.com/clip.y4m").ok(),
+            audio: false,
+            autoplay,
+            looping: false,
+            poster,
+        }
+    }
+
+    #[test]
+    fn src_wins_then_the_first_playable_source() {
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        let can_play = |mime: &str| mime == "video/x-yuv4mpeg";
+        assert_eq!(
+            select_source(Some("a.y4m"), [(Some("b.y4m"), None)], Some(&base), can_play),
+            Some(Url::parse("https://example.com/dir/a.y4m").unwrap())
+        );
+        assert_eq!(
+            select_source(
+                None,
+                [
+                    (None, None),
+                    (Some("a.mp4"), Some("video/mp4")),
+                    (Some("b.y4m"), Some("video/x-yuv4mpeg"))
+                ],
+                Some(&base),
+                can_play
+            ),
+            Some(Url::parse("https://example.com/dir/b.y4m").unwrap())
+        );
+        assert_eq!(select_source(None, [], Some(&base), can_play), None);
+        assert_eq!(select_source(Some(" "), [], Some(&base), can_play), None);
+        assert_eq!(
+            select_source(Some("javascript:void(0)"), [], Some(&base), can_play),
+            None
+        );
+    }
+
+    #[test]
+    fn frames_follow_the_clock_and_states_are_reported_once() {
+        let t0 = Instant::now();
+        let node = NodeId::from(7u64);
+        let mut media = MediaElements::default();
+        let loads = media.sync(vec![(node, request(false, true))], &CancellationToken::new());
+        assert_eq!(loads.len(), 1);
+
+        let updates = media.tick(t0);
+        assert_eq!(updates[0].status.map(|s| s.state), Some(PlaybackState::Loading));
+        assert!(media.tick(t0).is_empty(), "nothing changed");
+
+        assert!(media.finish(node, loads[0].load_id, Ok(Box::new(TestStream { frames: 3 })), t0));
+        let updates = media.tick(t0);
+        assert_eq!(updates[0].status.map(|s| s.state), Some(PlaybackState::Playing));
+        assert_eq!(updates[0].frame.as_ref().map(|f| f.as_raw()[0]), Some(0));
+
+        let updates = media.tick(t0 + Duration::from_millis(1500));
+        assert_eq!(updates[0].frame.as_ref().map(|f| f.as_raw()[0]), Some(1));
+        assert_eq!(updates[0].progress, 0.5);
+        assert!(updates[0].status.is_none());
+
+        let updates = media.tick(t0 + Duration::from_secs(4));
+        let status = updates[0].status.expect("ended is reported");
+        assert_eq!(status.state, PlaybackState::Ended);
+        assert_eq!(status.duration, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn a_poster_shows_until_playback_starts() {
+        let t0 = Instant::now();
+        let node = NodeId::from(7u64);
+        let mut media = MediaElements::default();
+        let loads = media.sync(vec![(node, request(true, false))], &CancellationToken::new());
+        assert!(media.finish(node, loads[0].load_id, Ok(Box::new(TestStream { frames: 3 })), t0));
+        assert!(media.tick(t0)[0].frame.is_none());
+
+        assert!(media.toggle(node, t0));
+        assert!(media.tick(t0)[0].frame.is_some());
+    }
+
+    #[test]
+    fn stale_loads_and_missing_sources() {
+        let t0 = Instant::now();
+        let node = NodeId::from(7u64);
+        let mut media = MediaElements::default();
+        let cancel = CancellationToken::new();
+        let first = media.sync(vec![(node, request(false, false))], &cancel);
+        let mut other = request(false, false);
+        other.source = None;
+        assert!(media.sync(vec![(node, other)], &cancel).is_empty());
+        assert!(first[0].cancel.is_cancelled());
+        assert!(!media.finish(node, first[0].load_id, Ok(Box::new(TestStream { frames: 1 })), t0));
+        assert_eq!(media.tick(t0)[0].status.map(|s| s.state), Some(PlaybackState::Error));
+    }
+}
@@ -81,6 +81,17 @@ impl ScrollState {
         None
     }
 
+    /// Move the target to an absolute offset (CSS px), clamped like [`scroll_by`](Self::scroll_by)
+    /// and applied or animated the same way.
+    pub(crate) fn scroll_to(&mut self, x: f64, y: f64, max_x: f64, max_y: f64) -> Option<(i32, i32)> {
+        self.scroll_by(x - self.target.0, y - self.target.1, max_x, max_y)
+    }
+
+    /// The offset the scroll is heading to: the current one unless an animation is in flight.
+    pub(crate) fn target(&self) -> (f64, f64) {
+        self.target
+    }
+
     /// Advance an in-flight animation by `dt` seconds, returning the new integer offset while
     /// animating, or `None` when idle. Settles exactly on the target and stops animating.
     pub(crate) fn tick(&mut self, dt: f64) -> Option<(i32, i32)> {
@@ -175,6 +186,18 @@ mod tests {
         assert_eq!(last, (0, 200), "converges on the extended target");
     }
 
+    #[test]
+    fn scroll_to_moves_the_target_absolutely() {
+        let mut s = ScrollState::new(ScrollBehavior::Instant);
+        s.scroll_by(0.0, 300.0, f64::MAX, 1000.0);
+        assert_eq!(s.scroll_to(0.0, 120.0, f64::MAX, 1000.0), Some((0, 120)));
+        assert_eq!(s.scroll_to(0.0, 4000.0, f64::MAX, 1000.0), Some((0, 1000)));
+
+        let mut s = ScrollState::new(tween(200));
+        assert_eq!(s.scroll_to(0.0, 400.0, f64::MAX, 1000.0), None);
+        assert_eq!(s.target(), (0.0, 400.0));
+    }
+
     #[test]
     fn tick_when_idle_is_none() {
         let mut s = ScrollState::new(tween(200));
@@ -0,0 +1,189 @@
+//! `@font-face` loads in flight and their `font-display` timelines.
+//!
+//! Each face moves through the three periods of CSS Fonts 4 §4.9 from the moment its fetch
+//! starts: a *block* period (text waits for the face), a *swap* period (text renders with a
+//! fallback, and the face replaces it when it arrives) and a *failure* period (the fallback is
+//! kept). [`WebFontLoads`] only does the bookkeeping - which faces are pending, whether a face
+//! that just arrived may still be used, which faces text must still wait for - so it can be
+//! unit-tested without a network; the worker does the fetching and registering.
+
+use gosub_interface::css3::FontDisplay;
+use gosub_interface::font_system::UnicodeRange;
+use gosub_render_pipeline::common::browser_state::BlockedFont;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The block period of `block` (and `auto`) faces: long enough for a typical font download.
+const LONG_BLOCK: Duration = Duration::from_secs(3);
+/// The block period of `fallback` and `optional` faces.
+const SHORT_BLOCK: Duration = Duration::from_millis(100);
+/// The swap period of `fallback` faces.
+const SHORT_SWAP: Duration = Duration::from_secs(3);
+
+/// Block and swap period lengths for `display`. A swap period of `None` never ends.
+fn periods(display: FontDisplay) -> (Duration, Option<Duration>) {
+    match display {
+        FontDisplay::Auto | FontDisplay::Block => (LONG_BLOCK, None),
+        FontDisplay::Swap => (Duration::ZERO, None),
+        FontDisplay::Fallback => (SHORT_BLOCK, Some(SHORT_SWAP)),
+        FontDisplay::Optional => (SHORT_BLOCK, Some(Duration::ZERO)),
+    }
+}
+
+/// Where a face is on its `font-display` timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FacePeriod {
+    Block,
+    Swap,
+    Failure,
+}
+
+/// An `@font-face` subset whose fetch is in flight.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingFace {
+    pub family: String,
+    pub range: UnicodeRange,
+    pub display: FontDisplay,
+    started: Instant,
+}
+
+impl PendingFace {
+    /// The face's period `now`.
+    pub(crate) fn period(&self, now: Instant) -> FacePeriod {
+        let elapsed = now.saturating_duration_since(self.started);
+        let (block, swap) = periods(self.display);
+        if elapsed < block {
+            FacePeriod::Block
+        } else if swap.is_none_or(|swap| elapsed < block + swap) {
+            FacePeriod::Swap
+        } else {
+            FacePeriod::Failure
+        }
+    }
+}
+
+/// The web font loads of the current document, keyed by load id.
+#[derive(Debug, Default)]
+pub(crate) struct WebFontLoads {
+    pending: HashMap<u64, PendingFace>,
+    next_id: u64,
+}
+
+impl WebFontLoads {
+    /// Track a face whose fetch starts `now`; returns the id its result must carry.
+    pub(crate) fn start(&mut self, family: String, range: UnicodeRange, display: FontDisplay, now: Instant) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(
+            id,
+            PendingFace {
+                family,
+                range,
+                display,
+                started: now,
+            },
+        );
+        id
+    }
+
+    /// Settle load `id`, which arrived (or failed) `now`. Returns the face when it may still be
+    /// used - it landed in its block or swap period - and `None` when it is unknown (a load of a
+    /// previous document) or already in its failure period.
+    pub(crate) fn finish(&mut self, id: u64, now: Instant) -> Option<PendingFace> {
+        let face = self.pending.remove(&id)?;
+        match face.period(now) {
+            FacePeriod::Block | FacePeriod::Swap => Some(face),
+            FacePeriod::Failure => {
+                log::debug!(
+                    "Web font '{}' arrived after its font-display={:?} swap period; keeping the fallback",
+                    face.family,
+                    face.display
+                );
+                None
+            }
+        }
+    }
+
+    /// The faces still in their block period `now`, in load order: text set in them is not
+    /// painted yet.
+    pub(crate) fn blocked(&self, now: Instant) -> Vec<BlockedFont> {
+        let mut blocked: Vec<(u64, BlockedFont)> = self
+            .pending
+            .iter()
+            .filter(|(_, face)| face.period(now) == FacePeriod::Block)
+            .map(|(&id, face)| {
+                let font = BlockedFont {
+                    family: face.family.clone(),
+                    range: face.range.clone(),
+                };
+                (id, font)
+            })
+            .collect();
+        blocked.sort_by_key(|(id, _)| *id);
+        blocked.into_iter().map(|(_, font)| font).collect()
+    }
+
+    /// Forget every pending load (a new document replaces the old one).
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(loads: &mut WebFontLoads, display: FontDisplay, now: Instant) -> u64 {
+        loads.start("Web".into(), UnicodeRange::all(), display, now)
+    }
+
+    #[test]
+    fn periods_follow_font_display() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut loads = WebFontLoads::default();
+
+        let block = start(&mut loads, FontDisplay::Block, t0);
+        let swap = start(&mut loads, FontDisplay::Swap, t0);
+        let fallback = start(&mut loads, FontDisplay::Fallback, t0);
+        let optional = start(&mut loads, FontDisplay::Optional, t0);
+        let period = |loads: &WebFontLoads, id, at| loads.pending[&id].period(at);
+
+        assert_eq!(period(&loads, block, ms(2_000)), FacePeriod::Block);
+        assert_eq!(period(&loads, block, ms(60_000)), FacePeriod::Swap);
+        assert_eq!(period(&loads, swap, t0), FacePeriod::Swap);
+        assert_eq!(period(&loads, fallback, ms(50)), FacePeriod::Block);
+        assert_eq!(period(&loads, fallback, ms(1_000)), FacePeriod::Swap);
+        assert_eq!(period(&loads, fallback, ms(4_000)), FacePeriod::Failure);
+        assert_eq!(period(&loads, optional, ms(50)), FacePeriod::Block);
+        assert_eq!(period(&loads, optional, ms(200)), FacePeriod::Failure);
+    }
+
+    #[test]
+    fn late_and_stale_loads_are_dropped() {
+        let t0 = Instant::now();
+        let mut loads = WebFontLoads::default();
+
+        let optional = start(&mut loads, FontDisplay::Optional, t0);
+        let swap = start(&mut loads, FontDisplay::Swap, t0);
+        assert_eq!(loads.blocked(t0).len(), 1, "the optional face blocks briefly");
+        assert!(loads.blocked(t0 + Duration::from_millis(200)).is_empty());
+
+        assert!(
+            loads.finish(optional, t0 + Duration::from_secs(1)).is_none(),
+            "optional arrived too late"
+        );
+        assert!(
+            loads.finish(swap, t0 + Duration::from_secs(30)).is_some(),
+            "swap faces always swap in"
+        );
+        assert!(loads.finish(swap, t0).is_none(), "a load settles once");
+
+        let stale = start(&mut loads, FontDisplay::Block, t0);
+        loads.clear();
+        assert!(
+            loads.finish(stale, t0).is_none(),
+            "loads of a previous document are ignored"
+        );
+    }
+}
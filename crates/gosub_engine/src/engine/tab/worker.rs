@@ -1,26 +1,37 @@
-use crate::cookies::SameSiteContext;
+use crate::cookies::{same_site, CookieJarHandle, SameSiteContext};
 use crate::engine::errors::NavigationError;
 use crate::engine::events::{EngineEvent, NavigationEvent};
+use crate::engine::resource_pipeline::font::WebFont;
+use crate::engine::resource_pipeline::media::MediaResource;
 use crate::engine::resource_pipeline::ResourcePipelines;
-use crate::engine::types::{NavigationId, RequestId};
+use crate::engine::types::{IoChannel, NavigationId, RequestId};
 use crate::engine::{BrowsingContext, UaPolicy};
 use crate::events::{IoCommand, TabCommand};
-use crate::html::RenderConfiguration;
+use crate::html::{parse_main_document_stream, EngineDocument, HtmlParseConfig, RenderConfiguration};
+use crate::inspect::{query_selector, DomNode, LayerDump, LayoutDumpNode, NodeStyles, RenderDumpNode, TreeDump};
+use crate::media::MediaStream;
 use crate::net::req_ref_tracker::{RequestReference, REF_REGISTRY};
 use crate::net::types::{FetchRequest, FetchResult, Initiator, NetError, Priority, ResourceKind};
 use crate::net::{route_response_for, submit_to_io, RequestDestination, RoutedOutcome};
 use crate::storage::types::compute_partition_key;
 use crate::storage::StorageHandles;
+use crate::tab::frames::{sandbox_isolates, FrameLoad, FrameRequest, FrameSource, Frames};
+use crate::tab::media_elements::{select_source, MediaElements, MediaLoad, MediaRequest};
 use crate::tab::scroll::{default_text_scroll, ScrollState};
 use crate::tab::services::EffectiveTabServices;
 use crate::tab::state::{TabRuntime, TabState};
+use crate::tab::web_fonts::WebFontLoads;
 use crate::tab::{TabId, TabSink};
 use crate::util::spawn_named;
 use crate::zone::{ZoneContext, ZoneId};
 use anyhow::{anyhow, Context};
-use gosub_render_pipeline::rasterizer::RasterStrategy;
+use gosub_interface::font_system::UnicodeRange;
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::image_source::ImageEnvironment;
+use gosub_render_pipeline::rasterizer::{downcast_rasterizer, RasterStrategy};
 use gosub_render_pipeline::render::backend::{CompositorSink, ErasedSurface, PresentMode, RenderBackend, SurfaceSize};
 use gosub_render_pipeline::render::Viewport;
+use gosub_shared::node::NodeId;
 use http::{HeaderMap, Method};
 use std::sync::Arc;
 use tokio::select;
@@ -29,7 +40,7 @@ use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use tokio::time::MissedTickBehavior;
 use tokio_util::sync::CancellationToken;
-use url::Url;
+use url::{Origin, Url};
 
 /// Fallback URL used when a navigation has no usable URL.
 fn about_blank() -> Url {
@@ -123,160 +134,53 @@ pub struct TabWorker<C: RenderConfiguration> {
     load: Option<NavJoin<C>>,
     /// Current active navigation (if any)
     active_nav: Option<ActiveNav>,
+    /// `@font-face` loads of the current document and their `font-display` timelines
+    web_fonts: WebFontLoads,
+    /// Fetch tasks report settled web fonts here
+    font_tx: mpsc::UnboundedSender<WebFontResult>,
+    font_rx: mpsc::UnboundedReceiver<WebFontResult>,
+    /// Nested browsing contexts of the document's iframes
+    frames: Frames<C>,
+    /// Frame load tasks report their documents here
+    frame_tx: mpsc::UnboundedSender<FrameLoadResult<C>>,
+    frame_rx: mpsc::UnboundedReceiver<FrameLoadResult<C>>,
+    /// Last pointer position in viewport CSS px, so wheel scrolling over an iframe scrolls it.
+    pointer: (f64, f64),
+    /// Playback of the document's video and audio elements
+    media: MediaElements,
+    /// Whether `media` has picked up the current document's media elements yet
+    media_synced: bool,
+    /// Media load tasks report their opened resources here
+    media_tx: mpsc::UnboundedSender<MediaLoadResult>,
+    media_rx: mpsc::UnboundedReceiver<MediaLoadResult>,
 }
 
-/// Whether a CSS `unicode-range` descriptor (e.g. `"U+0000-00FF, U+0131"`) includes the
-/// Basic-Latin letter `U+0041` ('A') - our proxy for "covers Latin-script text".
-fn unicode_range_covers_basic_latin(range: &str) -> bool {
-    const TARGET: u32 = 0x41; // 'A'
-    for token in range.split([',', ' ', '\t', '\n', '\r']).filter(|t| !t.is_empty()) {
-        let Some(hex) = token
-            .trim()
-            .strip_prefix("U+")
-            .or_else(|| token.trim().strip_prefix("u+"))
-        else {
-            continue;
-        };
-        let (lo, hi) = match hex.split_once('-') {
-            Some((a, b)) => (parse_hex_bound(a, false), parse_hex_bound(b, true)),
-            None => (parse_hex_bound(hex, false), parse_hex_bound(hex, true)),
-        };
-        if let (Some(lo), Some(hi)) = (lo, hi) {
-            if lo <= TARGET && TARGET <= hi {
-                return true;
-            }
-        }
-    }
-    false
+/// A settled `@font-face` fetch, sent from its fetch task back to the worker.
+struct WebFontResult {
+    /// Load id from [`WebFontLoads::start`].
+    load_id: u64,
+    /// The decoded font, or `None` when every source failed.
+    font: Option<WebFont>,
 }
 
-/// Unwrap a downloaded web-font payload into raw SFNT bytes the font backends can decode.
-///
-/// WOFF2 (magic `wOF2`) is a Brotli-compressed wrapper around an OpenType/TrueType font,
-/// with the `glyf`/`loca` tables stored in a transformed form. Skia and fontconfig don't
-/// decode it (e.g. Google Fonts serves WOFF2 to modern UAs like ours), so we decompress it
-/// to a flat SFNT here. Bare SFNT (`OTTO`/`true`/`ttcf`/`0x00010000`) and anything we don't
-/// recognise are returned unchanged - including WOFF1, which the backends already handle.
-/// On a decode error we log and return the original bytes so the subsequent `register_font`
-/// surfaces a single, consistent failure path.
-fn decode_web_font(bytes: Vec<u8>, font_url: &Url) -> Vec<u8> {
-    const WOFF2_MAGIC: &[u8; 4] = b"wOF2";
-    if bytes.len() < 4 || &bytes[0..4] != WOFF2_MAGIC {
-        return bytes;
-    }
-    match woff2_to_sfnt(&bytes) {
-        Ok(sfnt) => {
-            log::debug!(
-                "Decoded WOFF2 web font from {font_url} ({} → {} bytes)",
-                bytes.len(),
-                sfnt.len()
-            );
-            sfnt
-        }
-        Err(e) => {
-            log::warn!("Failed to decode WOFF2 web font from {font_url}: {e}");
-            bytes
-        }
-    }
+/// A settled iframe load, sent from its load task back to the worker.
+struct FrameLoadResult<C: RenderConfiguration> {
+    /// The iframe element the document is for.
+    node_id: NodeId,
+    /// Load id from [`Frames::sync`].
+    load_id: u64,
+    /// The parsed document, or `None` when the load failed.
+    doc: Option<Arc<EngineDocument<C>>>,
 }
 
-/// Decompress a WOFF2 font to a flat SFNT (TTF/OTF) byte buffer. allsorts handles the Brotli
-/// decompression and the `glyf`/`loca` transform reconstruction; we then re-assemble the
-/// reconstructed tables into the on-disk SFNT layout (offset table + table directory + 4-byte
-/// aligned table data) that font backends expect.
-fn woff2_to_sfnt(bytes: &[u8]) -> Result<Vec<u8>, String> {
-    use allsorts::binary::read::ReadScope;
-    use allsorts::woff2::Woff2Font;
-
-    let font = ReadScope::new(bytes)
-        .read::<Woff2Font<'_>>()
-        .map_err(|e| format!("parse: {e:?}"))?;
-    let sfnt_version = font.flavor();
-    let tables = font
-        .table_provider(0)
-        .map_err(|e| format!("reconstruct: {e:?}"))?
-        .into_tables();
-
-    Ok(assemble_sfnt(sfnt_version, tables))
-}
-
-/// Pack a set of font tables into an SFNT byte buffer per the OpenType spec: a 12-byte offset
-/// table, a 16-byte directory entry per table (sorted by tag), then each table's data padded to
-/// a 4-byte boundary. Per-table checksums are computed; the `head` table's `checkSumAdjustment`
-/// is left as-is (font backends parse without validating it).
-fn assemble_sfnt(sfnt_version: u32, tables: std::collections::HashMap<u32, Box<[u8]>>) -> Vec<u8> {
-    let mut entries: Vec<(u32, Box<[u8]>)> = tables.into_iter().collect();
-    entries.sort_by_key(|(tag, _)| *tag);
-    let num_tables = entries.len() as u16;
-
-    // Binary-search hint fields: largest power of two <= num_tables.
-    let mut entry_selector = 0u16;
-    while (1u16 << (entry_selector + 1)) <= num_tables {
-        entry_selector += 1;
-    }
-    let search_range = (1u16 << entry_selector) * 16;
-    let range_shift = num_tables.wrapping_mul(16).wrapping_sub(search_range);
-
-    let mut directory = Vec::with_capacity(16 * entries.len());
-    let mut data = Vec::new();
-    let mut offset = 12 + 16 * entries.len();
-    for (tag, table) in &entries {
-        directory.extend_from_slice(&tag.to_be_bytes());
-        directory.extend_from_slice(&sfnt_table_checksum(table).to_be_bytes());
-        directory.extend_from_slice(&(offset as u32).to_be_bytes());
-        directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
-        data.extend_from_slice(table);
-        while data.len() % 4 != 0 {
-            data.push(0);
-        }
-        offset += (table.len() + 3) & !3;
-    }
-
-    let mut out = Vec::with_capacity(12 + directory.len() + data.len());
-    out.extend_from_slice(&sfnt_version.to_be_bytes());
-    out.extend_from_slice(&num_tables.to_be_bytes());
-    out.extend_from_slice(&search_range.to_be_bytes());
-    out.extend_from_slice(&entry_selector.to_be_bytes());
-    out.extend_from_slice(&range_shift.to_be_bytes());
-    out.extend_from_slice(&directory);
-    out.extend_from_slice(&data);
-    out
-}
-
-/// SFNT table checksum: the sum of the table's contents read as big-endian `u32`s, with the
-/// final partial word zero-padded, in wrapping (mod 2^32) arithmetic.
-fn sfnt_table_checksum(data: &[u8]) -> u32 {
-    let mut sum = 0u32;
-    for chunk in data.chunks(4) {
-        let mut word = [0u8; 4];
-        word[..chunk.len()].copy_from_slice(chunk);
-        sum = sum.wrapping_add(u32::from_be_bytes(word));
-    }
-    sum
-}
-
-/// Parse a `unicode-range` hex bound, expanding `?` wildcards to `0` (low bound) or `F`
-/// (high bound), e.g. `U+00??` → `0x0000..=0x00FF`.
-fn parse_hex_bound(s: &str, high: bool) -> Option<u32> {
-    let s = s.trim();
-    if s.is_empty() {
-        return None;
-    }
-    let filled: String = s
-        .chars()
-        .map(|c| {
-            if c == '?' {
-                if high {
-                    'F'
-                } else {
-                    '0'
-                }
-            } else {
-                c
-            }
-        })
-        .collect();
-    u32::from_str_radix(&filled, 16).ok()
+/// A settled media load, sent from its load task back to the worker.
+struct MediaLoadResult {
+    /// The media element the resource is for.
+    node_id: NodeId,
+    /// Load id from [`MediaElements::sync`].
+    load_id: u64,
+    /// The opened resource, or why it could not be fetched or opened.
+    stream: Result<Box<dyn MediaStream>, String>,
 }
 
 impl<C: RenderConfiguration> TabWorker<C> {
@@ -292,6 +196,9 @@ impl<C: RenderConfiguration> TabWorker<C> {
         let config_store = zone_context.config_store.clone();
         let context = BrowsingContext::new(config_store.clone());
         let runtime = TabRuntime::with_fps(config_store.get_uint("renderer.tab.default_fps") as u32);
+        let (font_tx, font_rx) = mpsc::unbounded_channel();
+        let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+        let (media_tx, media_rx) = mpsc::unbounded_channel();
 
         Self {
             tab_id,
@@ -319,6 +226,17 @@ impl<C: RenderConfiguration> TabWorker<C> {
             runtime,
             load: None,
             active_nav: None,
+            web_fonts: WebFontLoads::default(),
+            font_tx,
+            font_rx,
+            frames: Frames::default(),
+            frame_tx,
+            frame_rx,
+            pointer: (0.0, 0.0),
+            media: MediaElements::default(),
+            media_synced: false,
+            media_tx,
+            media_rx,
         }
     }
 
@@ -383,6 +301,21 @@ impl<C: RenderConfiguration> TabWorker<C> {
                     }
                 }
 
+                // A web font fetch settled; the worker holds a sender, so this never yields `None`
+                Some(result) = self.font_rx.recv() => {
+                    self.on_web_font(result);
+                }
+
+                // An iframe load settled; the worker holds a sender, so this never yields `None`
+                Some(result) = self.frame_rx.recv() => {
+                    self.on_frame_loaded(result);
+                }
+
+                // A media load settled; the worker holds a sender, so this never yields `None`
+                Some(result) = self.media_rx.recv() => {
+                    self.on_media_loaded(result);
+                }
+
                 // Handle incoming tab commands from the UA
                 msg = self.cmd_rx.recv() => {
                     let Some(cmd) = msg else { break; };
@@ -409,66 +342,492 @@ impl<C: RenderConfiguration> TabWorker<C> {
         self.services.storage.drop_tab(self.zone_id, self.tab_id);
     }
 
-    /// Fetch and register any `@font-face` web fonts declared in the document's stylesheets
-    /// so the first layout/paint can use them. Runs once per navigation, before the first
-    /// render, and deduplicates by resolved font URL. Fetches are synchronous (blocking this
-    /// worker briefly during initial load); each face is registered under its CSS family so
-    /// the font system selects the right weight/style from the font's own metadata.
-    fn load_web_fonts(&self, doc: &C::Document, base_url: &Url) {
+    /// Start fetching every `@font-face` subset declared in the document's stylesheets. Fetches
+    /// run in the background through the I/O thread; each face is registered when it arrives
+    /// (see [`Self::on_web_font`]), under its CSS family and `unicode-range`, so the font system
+    /// can pick the subset covering each code point. Identical declarations are fetched once.
+    fn load_web_fonts(&mut self, doc: &C::Document, base_url: &Url, nav_id: NavigationId) {
         use gosub_interface::css3::CssStylesheet as _;
         use gosub_interface::document::Document as _;
-        use gosub_interface::font_system::FontSystem as _;
 
-        let mut fetched: std::collections::HashSet<String> = std::collections::HashSet::new();
+        self.web_fonts.clear();
+        let cancel = match &self.active_nav {
+            Some(nav) => nav.cancel.child_token(),
+            None => CancellationToken::new(),
+        };
+
+        let mut requested: std::collections::HashSet<(String, Vec<Url>)> = std::collections::HashSet::new();
+        let now = std::time::Instant::now();
         for sheet in doc.stylesheets() {
             let sheet_url = Url::parse(sheet.url()).ok();
-            for (family, sources, unicode_range) in sheet.font_faces() {
-                // Google-style web fonts split a family into many `unicode-range` subsets
-                // (latin, cyrillic, greek, …). We don't do per-glyph subset fallback, so
-                // register only subsets covering Basic Latin (and ranges with no descriptor),
-                // which covers Latin-script content without piling unusable subsets onto the
-                // same family.
-                if let Some(range) = &unicode_range {
-                    if !unicode_range_covers_basic_latin(range) {
-                        continue;
-                    }
+            for face in sheet.font_faces() {
+                let urls: Vec<Url> = face
+                    .sources
+                    .iter()
+                    .filter_map(|src| {
+                        sheet_url
+                            .as_ref()
+                            .unwrap_or(base_url)
+                            .join(src)
+                            .or_else(|_| base_url.join(src))
+                            .ok()
+                    })
+                    .collect();
+                if urls.is_empty() || !requested.insert((face.family.clone(), urls.clone())) {
+                    continue;
                 }
-                for src in &sources {
-                    let resolved = sheet_url
-                        .as_ref()
-                        .unwrap_or(base_url)
-                        .join(src)
-                        .or_else(|_| base_url.join(src));
-                    let Ok(font_url) = resolved else { continue };
-                    if !fetched.insert(font_url.to_string()) {
-                        break; // this exact font file is already registered
-                    }
-                    match gosub_sonar::net::simple::sync_fetch(&font_url) {
-                        Ok(resp) if resp.status == 200 && !resp.body.is_empty() => {
-                            // Web fonts are commonly served as WOFF2 (e.g. Google Fonts content-
-                            // negotiates WOFF2 for modern UAs like ours). The font backends
-                            // (Skia/fontconfig) only decode raw SFNT (TTF/OTF), so unwrap WOFF2
-                            // to TTF first. Other formats pass through unchanged.
-                            let font_bytes = decode_web_font(resp.body, &font_url);
-                            match self
-                                .zone_context
-                                .font_system
-                                .lock()
-                                .register_font(font_bytes, Some(&family))
-                            {
-                                Ok(()) => {
-                                    log::debug!("Registered web font '{family}' from {font_url}");
-                                    break; // family face loaded; skip remaining sources
-                                }
-                                Err(e) => log::warn!("Failed to register web font '{family}': {e:?}"),
-                            }
+                // An invalid descriptor is dropped, leaving the initial value (every code point).
+                let range = face
+                    .unicode_range
+                    .as_deref()
+                    .and_then(UnicodeRange::parse)
+                    .unwrap_or_default();
+                let load_id = self.web_fonts.start(face.family, range, face.display, now);
+
+                let font_tx = self.font_tx.clone();
+                let zone_id = self.zone_id;
+                let io_tx = self.zone_context.io_tx.clone();
+                let accept_language = self.services.accept_language.clone();
+                let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
+                let cancel = cancel.clone();
+                spawn_named("web-font-fetcher", async move {
+                    let font = fetch_web_font::<C>(
+                        zone_id,
+                        io_tx,
+                        nav_id,
+                        urls,
+                        cancel,
+                        accept_language,
+                        max_document_bytes,
+                    )
+                    .await;
+                    // The worker may be gone (tab closed); nothing left to register into then.
+                    let _ = font_tx.send(WebFontResult { load_id, font });
+                });
+            }
+        }
+    }
+
+    /// Register a web font that arrived, if its `font-display` timeline still allows using it,
+    /// and re-lay out the page so text picks it up.
+    fn on_web_font(&mut self, result: WebFontResult) {
+        use gosub_interface::font_system::FontSystem as _;
+
+        let face = self.web_fonts.finish(result.load_id, std::time::Instant::now());
+        // Text may have been hidden for this face's block period; let the next tick draw it.
+        self.runtime.dirty = true;
+        let (Some(face), Some(font)) = (face, result.font) else {
+            return;
+        };
+        match self
+            .zone_context
+            .font_system
+            .lock()
+            .register_font_face(font.data, &face.family, &face.range)
+        {
+            Ok(()) => {
+                log::debug!("Registered web font '{}'", face.family);
+                self.context.invalidate_render();
+            }
+            Err(e) => log::warn!("Failed to register web font '{}': {e:?}", face.family),
+        }
+    }
+
+    /// Reconcile the child frames with the iframes of the latest layout, start the loads of new
+    /// ones, and hand the parent a new rendering of every frame that changed. Returns whether the
+    /// parent got one, so it needs a repaint.
+    ///
+    /// Frames render through the CPU tile path only; on backends compositing tiles on the GPU an
+    /// iframe shows just its own box.
+    fn update_frames(&mut self) -> bool {
+        use gosub_interface::document::Document as _;
+
+        let render_backend = self.zone_context.render_backend.clone();
+        if render_backend.raster_strategy() == RasterStrategy::None || render_backend.gpu_tile_compositing() {
+            return false;
+        }
+        let Some(doc) = self.context.document().cloned() else {
+            return false;
+        };
+        let base = doc.url();
+        let elements: Vec<(NodeId, Rect, FrameRequest)> = self
+            .context
+            .frame_elements()
+            .into_iter()
+            .map(|(node_id, rect)| {
+                let request = FrameRequest {
+                    source: FrameSource::from_attributes(
+                        doc.attribute(node_id, "src"),
+                        doc.attribute(node_id, "srcdoc"),
+                        base.as_ref(),
+                    ),
+                    isolated: sandbox_isolates(doc.attribute(node_id, "sandbox")),
+                };
+                (node_id, rect, request)
+            })
+            .collect();
+
+        let parent_cancel = match &self.active_nav {
+            Some(nav) => nav.cancel.clone(),
+            None => CancellationToken::new(),
+        };
+        let config_store = self.zone_context.config_store.clone();
+        let loads = self
+            .frames
+            .sync(elements, &parent_cancel, || BrowsingContext::new(config_store.clone()));
+        for load in loads {
+            self.start_frame_load(load);
+        }
+
+        let dpr = render_backend.device_pixel_ratio();
+        let mut snapshots = Vec::new();
+        for (node_id, frame) in self.frames.iter_mut() {
+            if !frame.dirty {
+                continue;
+            }
+            frame.dirty = false;
+            if !frame.context.has_rasterizer() {
+                if let Some(rasterizer) =
+                    downcast_rasterizer(render_backend.create_rasterizer(self.zone_context.font_system.clone()))
+                {
+                    frame
+                        .context
+                        .set_rasterizer(rasterizer, render_backend.raster_strategy());
+                }
+            }
+            frame.context.rebuild_pipeline_cache_if_needed();
+            if let Some(image) = frame.context.snapshot(dpr) {
+                snapshots.push((node_id, image));
+            }
+        }
+
+        let changed = !snapshots.is_empty();
+        for (node_id, image) in snapshots {
+            self.context.set_frame_image(node_id, image);
+        }
+        changed
+    }
+
+    /// Bind the frame's storage and start loading its document. A sandboxed frame lives in an
+    /// opaque origin: fresh storage and no cookies. Everything else is partitioned under the tab's
+    /// top-level site like the tab's own storage.
+    fn start_frame_load(&mut self, load: FrameLoad) {
+        let FrameLoad {
+            node_id,
+            load_id,
+            request,
+            cancel,
+        } = load;
+        let Some(top_level) = self.current_url.clone() else {
+            return;
+        };
+
+        let origin = match (&request.source, request.isolated) {
+            (_, true) => Origin::new_opaque(),
+            (FrameSource::Url(url), false) => url.origin(),
+            // srcdoc and about:blank documents inherit the parent's origin
+            (_, false) => top_level.origin(),
+        };
+        let pk = compute_partition_key(&top_level, self.services.partition_policy);
+        let storage = self
+            .services
+            .storage
+            .local_for(self.zone_id, &pk, &origin)
+            .and_then(|local| {
+                self.services
+                    .storage
+                    .session_for(self.zone_id, self.tab_id, &pk, &origin)
+                    .map(|session| (local, session))
+            });
+        match (storage, self.frames.get_mut(node_id)) {
+            (Ok((local, session)), Some(frame)) => frame.context.bind_storage(local, session),
+            (Err(e), _) => log::warn!("Tab[{:?}]: cannot bind storage for iframe: {e}", self.tab_id),
+            _ => {}
+        }
+
+        let frame_tx = self.frame_tx.clone();
+        let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
+        match request.source {
+            FrameSource::Srcdoc(markup) => {
+                spawn_named("iframe-srcdoc-parser", async move {
+                    let parsed = parse_main_document_stream::<C, _, _>(
+                        top_level,
+                        std::io::Cursor::new(markup.into_bytes()),
+                        cancel,
+                        HtmlParseConfig {
+                            max_bytes: max_document_bytes,
+                            ..Default::default()
+                        },
+                        |_| {},
+                    )
+                    .await;
+                    let doc = match parsed {
+                        Ok(doc) => Some(Arc::new(doc)),
+                        Err(e) => {
+                            log::warn!("iframe srcdoc failed to parse: {e:?}");
+                            None
                         }
-                        Ok(resp) => log::warn!("Web font fetch {font_url} returned status {}", resp.status),
-                        Err(e) => log::warn!("Web font fetch {font_url} failed: {e}"),
-                    }
+                    };
+                    let _ = frame_tx.send(FrameLoadResult { node_id, load_id, doc });
+                });
+            }
+            FrameSource::Url(url) => {
+                let Some(nav_id) = self.active_nav.as_ref().map(|nav| nav.nav_id) else {
+                    return;
+                };
+                let fetch_headers = self.subresource_headers(&url, &top_level, !request.isolated);
+
+                let req_id = RequestId::new();
+                REF_REGISTRY.register_request(req_id, ResourceKind::Document, Initiator::Parser);
+                let req = FetchRequest::builder(Method::GET, url.clone())
+                    .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+                    .with_req_id(req_id)
+                    .with_headers(fetch_headers)
+                    .with_priority(Priority::Normal)
+                    .with_kind(ResourceKind::Document.to_net())
+                    .with_initiator(Initiator::Parser.to_net())
+                    .with_streaming(false)
+                    .with_auto_decode(true)
+                    .build();
+
+                let cookies = (!request.isolated).then(|| (self.services.cookie_jar.clone(), top_level));
+                let zone_id = self.zone_id;
+                let io_tx = self.zone_context.io_tx.clone();
+                let accept_language = self.services.accept_language.clone();
+                spawn_named("iframe-fetcher", async move {
+                    let doc = match fetch_frame_document::<C>(
+                        zone_id,
+                        io_tx,
+                        req,
+                        cancel,
+                        cookies,
+                        accept_language,
+                        max_document_bytes,
+                    )
+                    .await
+                    {
+                        Ok(doc) => Some(doc),
+                        Err(e) => {
+                            log::warn!("iframe fetch {url} failed: {e}");
+                            None
+                        }
+                    };
+                    let _ = frame_tx.send(FrameLoadResult { node_id, load_id, doc });
+                });
+            }
+            FrameSource::Blank => {}
+        }
+    }
+
+    /// Pick up a newly set document's media elements, then advance their playback and hand the
+    /// painter what changed, reporting state changes. Returns whether anything changed.
+    fn update_media(&mut self) -> bool {
+        if !self.media_synced {
+            if let Some(doc) = self.context.document().cloned() {
+                self.media_synced = true;
+                let elements = self.media_requests(&doc);
+                let parent_cancel = match &self.active_nav {
+                    Some(nav) => nav.cancel.clone(),
+                    None => CancellationToken::new(),
+                };
+                for load in self.media.sync(elements, &parent_cancel) {
+                    self.start_media_load(load);
                 }
             }
         }
+
+        let updates = self.media.tick(std::time::Instant::now());
+        let changed = !updates.is_empty();
+        for update in updates {
+            if let Some(status) = update.status {
+                self.send_event(EngineEvent::MediaStateChanged {
+                    tab_id: self.tab_id,
+                    element_id: update.node_id.into(),
+                    state: status.state,
+                    position: status.position,
+                    duration: status.duration,
+                });
+            }
+            self.context
+                .set_media_presentation(update.node_id, update.frame, update.playing, update.progress);
+        }
+        changed
+    }
+
+    /// What each `<video>` and `<audio>` element of `doc` asks for, in document order.
+    fn media_requests(&self, doc: &EngineDocument<C>) -> Vec<(NodeId, MediaRequest)> {
+        use gosub_interface::document::Document as _;
+
+        let base = doc.url();
+        let decoders = self.zone_context.media_decoders.read();
+        let is_tag = |node_id: NodeId, name: &str| doc.tag_name(node_id).is_some_and(|t| t.eq_ignore_ascii_case(name));
+        let mut out = Vec::new();
+        let mut stack = vec![doc.root()];
+        while let Some(node_id) = stack.pop() {
+            stack.extend(doc.children(node_id).iter().rev());
+            let audio = is_tag(node_id, "audio");
+            if !audio && !is_tag(node_id, "video") {
+                continue;
+            }
+            let sources = doc
+                .children(node_id)
+                .iter()
+                .filter(|&&child| is_tag(child, "source"))
+                .map(|&child| (doc.attribute(child, "src"), doc.attribute(child, "type")));
+            let source = select_source(doc.attribute(node_id, "src"), sources, base.as_ref(), |mime| {
+                decoders.can_play_type(mime)
+            });
+            let poster = !audio && doc.attribute(node_id, "poster").is_some_and(|p| !p.trim().is_empty());
+            out.push((
+                node_id,
+                MediaRequest {
+                    source,
+                    audio,
+                    autoplay: doc.attribute(node_id, "autoplay").is_some(),
+                    looping: doc.attribute(node_id, "loop").is_some(),
+                    poster,
+                },
+            ));
+        }
+        out
+    }
+
+    /// Fetch a media element's resource like any subresource of the page and open it with the
+    /// engine's media decoders.
+    fn start_media_load(&mut self, load: MediaLoad) {
+        let MediaLoad {
+            node_id,
+            load_id,
+            url,
+            audio,
+            cancel,
+        } = load;
+        let media_tx = self.media_tx.clone();
+        let (Some(nav_id), Some(top_level)) =
+            (self.active_nav.as_ref().map(|nav| nav.nav_id), self.current_url.clone())
+        else {
+            let stream = Err("no page to load it for".to_string());
+            let _ = media_tx.send(MediaLoadResult {
+                node_id,
+                load_id,
+                stream,
+            });
+            return;
+        };
+
+        let req_id = RequestId::new();
+        REF_REGISTRY.register_request(req_id, ResourceKind::Media, Initiator::Parser);
+        let req = FetchRequest::builder(Method::GET, url.clone())
+            .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+            .with_req_id(req_id)
+            .with_headers(self.subresource_headers(&url, &top_level, true))
+            .with_priority(Priority::Low)
+            .with_kind(ResourceKind::Media.to_net())
+            .with_initiator(Initiator::Parser.to_net())
+            .with_streaming(false)
+            .with_auto_decode(true)
+            .build();
+        let dest = if audio {
+            RequestDestination::Audio
+        } else {
+            RequestDestination::Video
+        };
+
+        let zone_id = self.zone_id;
+        let io_tx = self.zone_context.io_tx.clone();
+        let accept_language = self.services.accept_language.clone();
+        let max_bytes = self.zone_context.config_store.get_uint("net.media.max_bytes");
+        let decoders = self.zone_context.media_decoders.clone();
+        spawn_named("media-fetcher", async move {
+            let stream = match fetch_media::<C>(zone_id, io_tx, dest, req, cancel, accept_language, max_bytes).await {
+                Ok(resource) => decoders
+                    .read()
+                    .open(resource.mime.as_deref(), resource.data)
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(format!("fetching {url} failed: {e}")),
+            };
+            let _ = media_tx.send(MediaLoadResult {
+                node_id,
+                load_id,
+                stream,
+            });
+        });
+    }
+
+    /// Install a media element's opened resource, unless the element moved on to another since.
+    fn on_media_loaded(&mut self, result: MediaLoadResult) {
+        let now = std::time::Instant::now();
+        if self.media.finish(result.node_id, result.load_id, result.stream, now) {
+            self.runtime.dirty = true;
+        }
+    }
+
+    /// The media element whose play/pause button is at viewport point `(x, y)`.
+    fn media_button_at(&self, x: f64, y: f64) -> Option<NodeId> {
+        let (sx, sy) = self.context.scroll_xy();
+        self.context
+            .media_controls()
+            .into_iter()
+            .find(|(_, controls)| controls.button_contains(x + sx, y + sy))
+            .map(|(node_id, _)| node_id)
+    }
+
+    /// Request headers for a subresource of the top-level page at `top_level`: the accept language
+    /// and, with `cookies`, the jar's cookies for `url` - SameSite=Strict and Lax cookies only
+    /// when `url` is of the same site.
+    fn subresource_headers(&self, url: &Url, top_level: &Url, cookies: bool) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if cookies {
+            let ctx = match (url.host_str(), top_level.host_str()) {
+                (Some(a), Some(b)) if same_site(a, b) => SameSiteContext::SameSite,
+                _ => SameSiteContext::CrossSite,
+            };
+            if let Some(cookie_str) = self
+                .services
+                .cookie_jar
+                .read()
+                .get_request_cookies(url, Some(top_level), ctx)
+            {
+                if let Ok(val) = cookie_str.parse() {
+                    headers.insert(http::header::COOKIE, val);
+                }
+            }
+        }
+        if let Some(langs) = &self.services.accept_language {
+            if let Ok(val) = langs.parse() {
+                headers.insert(http::header::ACCEPT_LANGUAGE, val);
+            }
+        }
+        headers
+    }
+
+    /// Install a frame's loaded document, unless the frame moved on to another load since.
+    fn on_frame_loaded(&mut self, result: FrameLoadResult<C>) {
+        let Some(doc) = result.doc else {
+            return;
+        };
+        if self.frames.finish(result.node_id, result.load_id, doc) {
+            self.runtime.dirty = true;
+        }
+    }
+
+    /// Scroll the iframe under the pointer instead of the page, if it can still scroll that way.
+    fn scroll_frame(&mut self, delta_x: f64, delta_y: f64) -> bool {
+        let (sx, sy) = self.context.scroll_xy();
+        let Some(frame) = self.frames.at_mut(self.pointer.0 + sx, self.pointer.1 + sy) else {
+            return false;
+        };
+        let context = &mut frame.context;
+        let (x, y) = context.scroll_xy();
+        let max_y = (context.page_height() - context.viewport().height as f64).max(0.0);
+        let new_y = (y + delta_y).clamp(0.0, max_y);
+        let new_x = (x + delta_x).max(0.0);
+        if new_x == x && new_y == y {
+            return false;
+        }
+        context.set_scroll(new_x, new_y);
+        frame.dirty = true;
+        true
     }
 
     fn on_nav_result(&mut self, res: NavigationResult<C>) {
@@ -479,8 +838,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 title,
                 doc,
             } => {
+                self.frames.clear();
+                self.media.clear();
+                self.media_synced = false;
                 self.context.set_document(Arc::clone(&doc));
-                self.load_web_fonts(&doc, &final_url);
+                self.load_web_fonts(&doc, &final_url, nav_id);
                 self.current_url = Some(final_url.clone());
                 if let Some(t) = title {
                     self.title = t;
@@ -552,55 +914,18 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 ControlFlow::Continue
             }
             TabCommand::MouseScroll { delta_x, delta_y } => {
-                // When page height is known, clamp to the real maximum so worker and context
-                // stay in sync. When the page hasn't rendered yet, allow free scrolling (the
-                // context will clamp to the actual page height on its own).
-                let max_y = {
-                    let ph = self.context.page_height();
-                    if ph > 0.0 {
-                        (ph - self.desired_viewport.height as f64).max(0.0)
-                    } else {
-                        f64::MAX
-                    }
-                };
-
-                match self.scroll.scroll_by(delta_x as f64, delta_y as f64, f64::MAX, max_y) {
-                    // Instant behavior: apply the new offset now and keep the immediate-submit fast
-                    // path (avoids up to 1/fps of latency per scroll event).
-                    Some((x, y)) => {
-                        let moved = x != self.scroll_x || y != self.scroll_y;
-                        self.scroll_x = x;
-                        self.scroll_y = y;
-                        self.context.set_scroll(x as f64, y as f64);
-
-                        // GPU-tile-compositing backends skip this CPU TileCache fast path (their
-                        // tiles have no CPU pixels); they re-composite on the next tick.
-                        if self.zone_context.render_backend.raster_strategy() != RasterStrategy::None
-                            && !self.zone_context.render_backend.gpu_tile_compositing()
-                        {
-                            let dpr = self.zone_context.render_backend.device_pixel_ratio();
-                            if let Some(handle) = self.context.take_scroll_handle(dpr) {
-                                self.runtime.committed_scene_epoch = self.context.scene_epoch();
-                                self.zone_context.compositor.submit_frame(self.tab_id, handle);
-                                return ControlFlow::Continue;
-                            }
-                        }
-
-                        // TileCache not ready yet; fall back to the timer path. Only mark dirty if
-                        // the integer offset actually moved (sub-pixel deltas are no-ops).
-                        if moved {
-                            self.runtime.dirty = true;
-                        }
-                    }
-                    // Animated behavior: tick_draw advances the ease toward the new target. Request
-                    // an immediate tick so the first frame lands without waiting up to 1/fps.
-                    None => {
-                        self.runtime.render_now = true;
-                    }
+                if self.scroll_frame(delta_x as f64, delta_y as f64) {
+                    self.runtime.dirty = true;
+                    self.runtime.render_now = true;
+                    return ControlFlow::Continue;
                 }
+                let max_y = self.max_scroll_y();
+                let step = self.scroll.scroll_by(delta_x as f64, delta_y as f64, f64::MAX, max_y);
+                self.apply_scroll(step);
                 ControlFlow::Continue
             }
             TabCommand::MouseMove { x, y } => {
+                self.pointer = (x as f64, y as f64);
                 // Process the hit-test immediately so hover doesn't wait for the next tick.
                 let (visual_dirty, url_changed, link_url) = self.context.update_hover(x as f64, y as f64);
                 if url_changed {
@@ -609,14 +934,20 @@ impl<C: RenderConfiguration> TabWorker<C> {
                         url: link_url,
                     });
                 }
-                if visual_dirty {
+                let selection_dirty = self.context.selection_drag(x as f64, y as f64);
+                if visual_dirty || selection_dirty {
                     self.runtime.dirty = true;
                     self.runtime.render_now = true;
                 }
                 ControlFlow::Continue
             }
-            TabCommand::MouseDown { button, .. } => {
+            TabCommand::MouseDown { x, y, button } => {
                 if matches!(button, crate::events::MouseButton::Left) {
+                    if let Some(node_id) = self.media_button_at(x as f64, y as f64) {
+                        self.media.toggle(node_id, std::time::Instant::now());
+                        self.runtime.render_now = true;
+                        return ControlFlow::Continue;
+                    }
                     if let Some(href) = self.context.hover_link_url.clone() {
                         let resolved = self
                             .current_url
@@ -627,19 +958,75 @@ impl<C: RenderConfiguration> TabWorker<C> {
                         self.navigate_to(resolved, false);
                         return ControlFlow::Continue;
                     }
+                    if self.context.selection_press(x as f64, y as f64) {
+                        self.runtime.render_now = true;
+                    }
+                }
+                self.runtime.dirty = true;
+                ControlFlow::Continue
+            }
+            TabCommand::MouseUp { button, .. } => {
+                if matches!(button, crate::events::MouseButton::Left) {
+                    self.context.selection_release();
                 }
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
-            TabCommand::MouseUp { .. }
-            | TabCommand::KeyDown { .. }
-            | TabCommand::KeyUp { .. }
-            | TabCommand::CharInput { .. } => {
+            TabCommand::GetSelectionText => {
+                self.send_event(EngineEvent::SelectionText {
+                    tab_id: self.tab_id,
+                    text: self.context.selection_text(),
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::Find {
+                query,
+                case_sensitive,
+                forward,
+            } => {
+                let (active, total, repaint) = self.context.find(&query, case_sensitive, forward);
+                if repaint {
+                    self.runtime.dirty = true;
+                    self.runtime.render_now = true;
+                }
+                self.scroll_to_find_match();
+                self.send_event(EngineEvent::FindResult {
+                    tab_id: self.tab_id,
+                    active,
+                    total,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::StopFind => {
+                if self.context.stop_find() {
+                    self.runtime.dirty = true;
+                    self.runtime.render_now = true;
+                }
+                ControlFlow::Continue
+            }
+            TabCommand::PlayMedia { element_id } => {
+                if !self.media.play(NodeId::from(element_id), std::time::Instant::now()) {
+                    log::debug!("Tab {:?}: no media element {element_id} to play", self.tab_id);
+                }
+                self.runtime.render_now = true;
+                ControlFlow::Continue
+            }
+            TabCommand::PauseMedia { element_id } => {
+                if !self.media.pause(NodeId::from(element_id), std::time::Instant::now()) {
+                    log::debug!("Tab {:?}: no media element {element_id} to pause", self.tab_id);
+                }
+                self.runtime.render_now = true;
+                ControlFlow::Continue
+            }
+            TabCommand::KeyDown { .. } | TabCommand::KeyUp { .. } | TabCommand::CharInput { .. } => {
                 self.runtime.dirty = true;
                 ControlFlow::Continue
             }
             TabCommand::ResumeDrawing { fps: wanted_fps } => {
                 self.runtime.drawing_enabled = true;
+                let now = std::time::Instant::now();
+                self.context.resume_image_animations(now);
+                self.frames.resume_image_animations(now);
                 self.runtime.fps = wanted_fps.max(1) as u32;
                 let period = Duration::from_secs_f64(1.0 / (self.runtime.fps as f64));
                 self.runtime.interval = tokio::time::interval(period);
@@ -651,6 +1038,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
             }
             TabCommand::SuspendDrawing => {
                 self.runtime.drawing_enabled = false;
+                // Animated images and CSS animations pick up where they left off rather than
+                // skipping the hidden time
+                let now = std::time::Instant::now();
+                self.context.pause_image_animations(now);
+                self.frames.pause_image_animations(now);
                 ControlFlow::Continue
             }
             TabCommand::CancelNavigation => {
@@ -672,6 +1064,85 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 // Decisions are handled in the fetcher/io thread, so we can ignore this here
                 ControlFlow::Continue
             }
+            TabCommand::ExecuteScript { .. } => {
+                // There is no script engine yet; report that rather than leaving the caller waiting
+                self.send_event(EngineEvent::JavaScriptError {
+                    tab_id: self.tab_id,
+                    message: "no script engine available".to_string(),
+                    line: 0,
+                    column: 0,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::CaptureScreenshot => {
+                let png = self.screenshot_png();
+                self.send_event(EngineEvent::Screenshot {
+                    tab_id: self.tab_id,
+                    png,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::Print { page_size } => {
+                // Brings up the rasterizer, whose font system the print layout shapes text with.
+                self.refresh_tiles();
+                let document = self.context.print(page_size).map(Arc::new);
+                self.send_event(EngineEvent::Printed {
+                    tab_id: self.tab_id,
+                    document,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::GetDocument => {
+                use gosub_interface::document::Document as _;
+                let root = self
+                    .context
+                    .document()
+                    .map(|doc| DomNode::from_document::<C>(doc, doc.root()));
+                self.send_event(EngineEvent::DomDocument {
+                    tab_id: self.tab_id,
+                    root,
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::QuerySelector { node_id, selector } => {
+                let found = self
+                    .context
+                    .document()
+                    .and_then(|doc| query_selector::<C>(doc, NodeId::from(node_id), &selector));
+                self.send_event(EngineEvent::QuerySelectorResult {
+                    tab_id: self.tab_id,
+                    node_id: found.map(u64::from),
+                });
+                ControlFlow::Continue
+            }
+            TabCommand::SetAttribute { node_id, name, value } => {
+                use gosub_interface::document::Document as _;
+                let node_id = NodeId::from(node_id);
+                let mutated = self.context.mutate_document(|doc| match &value {
+                    Some(value) => doc.set_attribute(node_id, &name, value),
+                    None => doc.remove_attribute(node_id, &name),
+                });
+                if let Err(e) = mutated {
+                    log::warn!(
+                        "Tab[{:?}]: cannot set attribute '{name}' on node {node_id:?}: {e}",
+                        self.tab_id
+                    );
+                }
+                self.runtime.dirty = true;
+                ControlFlow::Continue
+            }
+            TabCommand::DumpDomTree
+            | TabCommand::DumpRenderTree
+            | TabCommand::DumpLayoutTree
+            | TabCommand::DumpLayers
+            | TabCommand::DumpComputedStyles { .. } => {
+                let dump = self.tree_dump(&cmd);
+                self.send_event(EngineEvent::TreeDump {
+                    tab_id: self.tab_id,
+                    dump,
+                });
+                ControlFlow::Continue
+            }
             _ => {
                 log::warn!("Tab {:?} received unhandled command: {:?}", self.tab_id, cmd);
                 ControlFlow::Continue
@@ -679,6 +1150,148 @@ impl<C: RenderConfiguration> TabWorker<C> {
         }
     }
 
+    /// The furthest the page scrolls down. When page height is known, clamp to the real maximum so
+    /// worker and context stay in sync. When the page hasn't rendered yet, allow free scrolling
+    /// (the context will clamp to the actual page height on its own).
+    fn max_scroll_y(&self) -> f64 {
+        let ph = self.context.page_height();
+        if ph > 0.0 {
+            (ph - self.desired_viewport.height as f64).max(0.0)
+        } else {
+            f64::MAX
+        }
+    }
+
+    /// Applies a move from `scroll`: `Some` offset now for the instant behavior, or `None` for an
+    /// animation `tick_draw` advances.
+    fn apply_scroll(&mut self, step: Option<(i32, i32)>) {
+        match step {
+            // Instant behavior: apply the new offset now and keep the immediate-submit fast
+            // path (avoids up to 1/fps of latency per scroll event).
+            Some((x, y)) => {
+                let moved = x != self.scroll_x || y != self.scroll_y;
+                self.scroll_x = x;
+                self.scroll_y = y;
+                self.context.set_scroll(x as f64, y as f64);
+
+                // GPU-tile-compositing backends skip this CPU TileCache fast path (their
+                // tiles have no CPU pixels); they re-composite on the next tick.
+                if self.zone_context.render_backend.raster_strategy() != RasterStrategy::None
+                    && !self.zone_context.render_backend.gpu_tile_compositing()
+                {
+                    let dpr = self.zone_context.render_backend.device_pixel_ratio();
+                    if let Some(handle) = self.context.take_scroll_handle(dpr) {
+                        self.runtime.committed_scene_epoch = self.context.scene_epoch();
+                        self.zone_context.compositor.submit_frame(self.tab_id, handle);
+                        return;
+                    }
+                }
+
+                // TileCache not ready yet; fall back to the timer path. Only mark dirty if
+                // the integer offset actually moved (sub-pixel deltas are no-ops).
+                if moved {
+                    self.runtime.dirty = true;
+                }
+            }
+            // Animated behavior: tick_draw advances the ease toward the new target. Request
+            // an immediate tick so the first frame lands without waiting up to 1/fps.
+            None => {
+                self.runtime.render_now = true;
+            }
+        }
+    }
+
+    /// The visible part of the page as a PNG, for `TabCommand::CaptureScreenshot`. CPU tile
+    /// backends bring their tiles up to date first; backends that draw straight to a GPU texture
+    /// keep no CPU pixels, so they give an empty image.
+    fn screenshot_png(&mut self) -> Vec<u8> {
+        let render_backend = self.zone_context.render_backend.clone();
+        if render_backend.renders_to_gpu_texture() {
+            return Vec::new();
+        }
+        self.refresh_tiles();
+
+        let Some(image) = self.context.snapshot(render_backend.device_pixel_ratio()) else {
+            return Vec::new();
+        };
+        let Some(rgba) = image::RgbaImage::from_raw(image.width(), image.height(), image.as_raw().to_vec()) else {
+            return Vec::new();
+        };
+        let mut png = std::io::Cursor::new(Vec::new());
+        if let Err(e) = rgba.write_to(&mut png, image::ImageFormat::Png) {
+            log::warn!("Tab {:?}: could not encode screenshot: {e}", self.tab_id);
+            return Vec::new();
+        }
+        png.into_inner()
+    }
+
+    /// Brings the tiles of a CPU tile backend up to date with the document and viewport ahead of
+    /// the next frame, for requests that read the rendered page. Other backends keep what their
+    /// last frame built.
+    fn refresh_tiles(&mut self) {
+        let render_backend = self.zone_context.render_backend.clone();
+        if render_backend.renders_to_gpu_texture() || render_backend.raster_strategy() == RasterStrategy::None {
+            return;
+        }
+        if !self.context.has_rasterizer() {
+            if let Some(rasterizer) =
+                downcast_rasterizer(render_backend.create_rasterizer(self.zone_context.font_system.clone()))
+            {
+                self.context
+                    .set_rasterizer(rasterizer, render_backend.raster_strategy());
+            }
+        }
+        self.context.set_viewport(self.desired_viewport);
+        self.context.rebuild_pipeline_cache_if_needed();
+        // The rebuilt tiles still have to reach the compositor
+        self.runtime.dirty = true;
+    }
+
+    /// One pipeline stage for the `TabCommand::Dump*` commands; `None` when there is nothing to
+    /// dump yet.
+    fn tree_dump(&mut self, cmd: &TabCommand) -> Option<TreeDump> {
+        use gosub_interface::document::Document as _;
+
+        if let TabCommand::DumpDomTree = cmd {
+            let doc = self.context.document()?;
+            return Some(TreeDump::Dom(DomNode::from_document::<C>(doc, doc.root())));
+        }
+        if let TabCommand::DumpComputedStyles { node_id } = cmd {
+            let doc = self.context.document()?;
+            let styles = NodeStyles::from_document::<C>(doc, node_id.map(NodeId::from));
+            return Some(TreeDump::ComputedStyles(styles));
+        }
+
+        self.refresh_tiles();
+        let layers = self.context.layer_list()?;
+        match cmd {
+            TabCommand::DumpRenderTree => {
+                RenderDumpNode::from_render_tree(&layers.layout_tree.render_tree).map(TreeDump::RenderTree)
+            }
+            TabCommand::DumpLayoutTree => {
+                LayoutDumpNode::from_layout_tree(&layers.layout_tree).map(|root| TreeDump::LayoutTree(Box::new(root)))
+            }
+            TabCommand::DumpLayers => Some(TreeDump::Layers(LayerDump::from_layer_list(layers))),
+            _ => None,
+        }
+    }
+
+    /// Scrolls the active find match to the middle of the viewport, unless it is already in view.
+    fn scroll_to_find_match(&mut self) {
+        let Some(rect) = self.context.find_active_rect() else {
+            return;
+        };
+        let (x, y) = self.scroll.target();
+        let height = self.desired_viewport.height as f64;
+        if rect.y >= y && rect.y + rect.height <= y + height {
+            return;
+        }
+        let target = rect.y + rect.height / 2.0 - height / 2.0;
+        let max_y = self.max_scroll_y();
+        let step = self.scroll.scroll_to(x, target, f64::MAX, max_y);
+        self.apply_scroll(step);
+    }
+
     /// Send an engine event upwards to the UA
     fn send_event(&self, evt: EngineEvent) {
         match self.zone_context.event_tx.send(evt.clone()) {
@@ -787,6 +1400,15 @@ impl<C: RenderConfiguration> TabWorker<C> {
         let cookie_jar = self.services.cookie_jar.clone();
         let accept_language = self.services.accept_language.clone();
         let max_document_bytes = self.zone_context.config_store.get_uint("net.document.max_bytes");
+        // The preload scanner picks `srcset` candidates for the viewport the page will be shown in
+        let image_environment = match self.desired_viewport {
+            vp if vp.width > 0 && vp.height > 0 => ImageEnvironment {
+                viewport_width: vp.width as f64,
+                viewport_height: vp.height as f64,
+                device_pixel_ratio: self.zone_context.render_backend.device_pixel_ratio() as f64,
+            },
+            _ => ImageEnvironment::default(),
+        };
 
         let span = tracing::info_span!(
             "tab_nav",
@@ -851,8 +1473,13 @@ impl<C: RenderConfiguration> TabWorker<C> {
                 allow_download_without_user_activation: false,
             };
 
-            let mut hooks =
-                ResourcePipelines::<C>::new(zone_id, io_tx.clone(), accept_language.clone(), max_document_bytes);
+            let mut hooks = ResourcePipelines::<C>::with_image_environment(
+                zone_id,
+                io_tx.clone(),
+                accept_language.clone(),
+                max_document_bytes,
+                image_environment,
+            );
 
             let outcome = route_response_for(
                 RequestDestination::Document,
@@ -888,7 +1515,8 @@ impl<C: RenderConfiguration> TabWorker<C> {
                     RoutedOutcome::CssLoaded(_)
                     | RoutedOutcome::ScriptExecuted(_)
                     | RoutedOutcome::ImageDecoded(_)
-                    | RoutedOutcome::FontLoaded(_),
+                    | RoutedOutcome::FontLoaded(_)
+                    | RoutedOutcome::MediaLoaded(_),
                 ) => {
                     log::trace!("Tab[{:?}] subresource outcome; nothing to do for navigation", tab_id);
                 }
@@ -952,12 +1580,54 @@ impl<C: RenderConfiguration> TabWorker<C> {
             }
         }
 
+        // Responsive images pick their source for the display's density, so a change re-lays-out.
+        let dpr = self.zone_context.render_backend.device_pixel_ratio() as f32;
+        if self.context.set_device_pixel_ratio(dpr) {
+            self.runtime.dirty = true;
+        }
+        if self.frames.set_device_pixel_ratio(dpr) {
+            self.runtime.dirty = true;
+        }
+        // Lazy images that scrolled near the viewport start loading; they land like any fetch.
+        self.context.load_lazy_images();
+        self.frames.load_lazy_images();
+
         // A background media fetch (e.g. an image that started downloading during layout) landing
         // must wake the render loop even when nothing else changed, so the now-available image is
         // laid out and painted. This marks the render dirty under the hood.
         if self.context.poll_media_completed() {
             self.runtime.dirty = true;
         }
+        if self.frames.poll_media_completed() {
+            self.runtime.dirty = true;
+        }
+        // Playing media advances with the clock, whether or not anything else changed.
+        if self.update_media() {
+            self.runtime.dirty = true;
+        }
+        // So do animated images, which only repaint the tiles they cover.
+        let now = std::time::Instant::now();
+        if self.context.advance_image_animations(now) {
+            self.runtime.dirty = true;
+        }
+        if self.frames.advance_image_animations(now) {
+            self.runtime.dirty = true;
+        }
+        // CSS transitions and animations only re-lay-out when they change a layout property.
+        if self.context.advance_css_animations(now) {
+            self.runtime.dirty = true;
+        }
+        if self.frames.advance_css_animations(now) {
+            self.runtime.dirty = true;
+        }
+
+        // Text in a web font still in its font-display block period is left out of the frame
+        // rather than flashed in a fallback font; the rest of the page paints. The frame is
+        // redrawn when a face leaves its block period.
+        let blocked_fonts = self.web_fonts.blocked(now);
+        if self.context.set_blocked_fonts(blocked_fonts) {
+            self.runtime.dirty = true;
+        }
 
         // Skip rendering when nothing has changed to avoid burning CPU at the tick rate.
         if !self.runtime.dirty {
@@ -1005,6 +1675,11 @@ impl<C: RenderConfiguration> TabWorker<C> {
             // Full render: rebuild stages 1-6 only (no display list), then submit TileCache.
             self.context.set_viewport(self.desired_viewport);
             self.context.rebuild_pipeline_cache_if_needed();
+            // Frame renderings need the parent's layout for their boxes, so they follow it and
+            // repaint just the iframes.
+            if self.update_frames() {
+                self.context.rebuild_pipeline_cache_if_needed();
+            }
             let scene_epoch = self.context.scene_epoch();
             if let Some(handle) = self.context.tile_cache_handle(dpr) {
                 self.runtime.committed_scene_epoch = scene_epoch;
@@ -1064,6 +1739,9 @@ impl<C: RenderConfiguration> TabWorker<C> {
             }
 
             self.context.rebuild_scene_cache_if_needed();
+            if self.update_frames() {
+                self.context.rebuild_scene_cache_if_needed();
+            }
 
             let scene_epoch = self.context.scene_epoch();
             if !surface_recreated && scene_epoch == self.runtime.committed_scene_epoch {
@@ -1299,42 +1977,169 @@ impl ControlFlow {
     }
 }
 
+/// Fetch the first of `urls` that yields a usable font, through the zone's I/O thread and the
+/// font resource pipeline (which unwraps WOFF2). `None` when every source fails or `cancel` fires.
+async fn fetch_web_font<C: RenderConfiguration>(
+    zone_id: ZoneId,
+    io_tx: IoChannel,
+    nav_id: NavigationId,
+    urls: Vec<Url>,
+    cancel: CancellationToken,
+    accept_language: Option<String>,
+    max_document_bytes: usize,
+) -> Option<WebFont> {
+    // Fonts are often served as `application/octet-stream`; sniffing recognises them by magic.
+    let ua_policy = UaPolicy {
+        enable_sniffing: true,
+        enable_sniffing_navigation_upgrade: false,
+        enable_pdf_viewer: false,
+        allow_download_without_user_activation: false,
+    };
+    let mut hooks = ResourcePipelines::<C>::new(zone_id, io_tx.clone(), accept_language, max_document_bytes);
+
+    for url in urls {
+        let req_id = RequestId::new();
+        REF_REGISTRY.register_request(req_id, ResourceKind::Font, Initiator::CSS);
+        let req = FetchRequest::builder(Method::GET, url.clone())
+            .with_reference(REF_REGISTRY.to_net(RequestReference::Navigation(nav_id)))
+            .with_req_id(req_id)
+            .with_priority(Priority::High)
+            .with_kind(ResourceKind::Font.to_net())
+            .with_initiator(Initiator::CSS.to_net())
+            .with_streaming(false)
+            .with_auto_decode(true)
+            .build();
+
+        let Ok((handle, rx)) = submit_to_io(zone_id, req.clone(), io_tx.clone(), Some(cancel.clone())).await else {
+            return None;
+        };
+        let fetch_result: FetchResult = tokio::select! {
+            _ = cancel.cancelled() => {
+                handle.cancel.cancel();
+                return None;
+            }
+            r = rx => match r {
+                Ok(r) => r,
+                Err(_) => return None,
+            }
+        };
+
+        match route_response_for(
+            RequestDestination::Font,
+            handle,
+            req,
+            fetch_result,
+            &ua_policy,
+            &mut hooks,
+        )
+        .await
+        {
+            Ok(RoutedOutcome::FontLoaded(font)) => {
+                log::debug!("Loaded web font from {url}");
+                return Some(font);
+            }
+            Ok(RoutedOutcome::Blocked(reason)) => log::warn!("Web font fetch {url} blocked: {reason}"),
+            Ok(_) => log::warn!("Web font fetch {url} did not produce a font"),
+            Err(e) => log::warn!("Web font fetch {url} failed: {e}"),
+        }
+    }
+    None
+}
+
+/// Fetch a `<video>`/`<audio>` resource whole (decoders index it up front), through the zone's I/O
+/// thread and the media resource pipeline. `max_bytes` caps the download.
+async fn fetch_media<C: RenderConfiguration>(
+    zone_id: ZoneId,
+    io_tx: IoChannel,
+    dest: RequestDestination,
+    req: FetchRequest,
+    cancel: CancellationToken,
+    accept_language: Option<String>,
+    max_bytes: usize,
+) -> anyhow::Result<MediaResource> {
+    let (handle, rx) = submit_to_io(zone_id, req.clone(), io_tx.clone(), Some(cancel.clone()))
+        .await
+        .map_err(|_| anyhow!("I/O channel closed"))?;
+    let fetch_result: FetchResult = tokio::select! {
+        _ = cancel.cancelled() => {
+            handle.cancel.cancel();
+            return Err(anyhow!("cancelled"));
+        }
+        r = rx => r.map_err(|_| anyhow!("response channel closed"))?,
+    };
+
+    // Media servers often label files `application/octet-stream`; the decoders sniff them.
+    let ua_policy = UaPolicy {
+        enable_sniffing: true,
+        enable_sniffing_navigation_upgrade: false,
+        enable_pdf_viewer: false,
+        allow_download_without_user_activation: false,
+    };
+    let mut hooks = ResourcePipelines::<C>::new(zone_id, io_tx, accept_language, max_bytes);
+    match route_response_for(dest, handle, req, fetch_result, &ua_policy, &mut hooks).await? {
+        RoutedOutcome::MediaLoaded(resource) if resource.data.len() <= max_bytes => Ok(resource),
+        RoutedOutcome::MediaLoaded(_) => Err(anyhow!("larger than {max_bytes} bytes")),
+        RoutedOutcome::Blocked(reason) => Err(anyhow!("blocked: {reason}")),
+        _ => Err(anyhow!("response is not media")),
+    }
+}
+
+/// Fetch an iframe's document. `cookies` is the jar to store the response's cookies in and the
+/// tab's top-level URL, or `None` for a sandboxed frame.
+async fn fetch_frame_document<C: RenderConfiguration>(
+    zone_id: ZoneId,
+    io_tx: IoChannel,
+    req: FetchRequest,
+    cancel: CancellationToken,
+    cookies: Option<(CookieJarHandle, Url)>,
+    accept_language: Option<String>,
+    max_document_bytes: usize,
+) -> anyhow::Result<Arc<EngineDocument<C>>> {
+    let (handle, rx) = submit_to_io(zone_id, req.clone(), io_tx.clone(), Some(cancel.clone()))
+        .await
+        .map_err(|_| anyhow!("I/O channel closed"))?;
+    let fetch_result: FetchResult = tokio::select! {
+        _ = cancel.cancelled() => {
+            handle.cancel.cancel();
+            return Err(anyhow!("cancelled"));
+        }
+        r = rx => r.map_err(|_| anyhow!("response channel closed"))?,
+    };
+
+    if let (Some(meta), Some((jar, top_level))) = (fetch_result.meta(), &cookies) {
+        jar.write()
+            .store_response_cookies(&meta.final_url, &meta.headers, Some(top_level));
+    }
+
+    let ua_policy = UaPolicy {
+        enable_sniffing: false,
+        enable_sniffing_navigation_upgrade: false,
+        enable_pdf_viewer: false,
+        allow_download_without_user_activation: false,
+    };
+    let mut hooks = ResourcePipelines::<C>::new(zone_id, io_tx, accept_language, max_document_bytes);
+    match route_response_for(
+        RequestDestination::Document,
+        handle,
+        req,
+        fetch_result,
+        &ua_policy,
+        &mut hooks,
+    )
+    .await?
+    {
+        RoutedOutcome::MainDocument(doc) => Ok(doc),
+        RoutedOutcome::Blocked(reason) => Err(anyhow!("blocked: {reason}")),
+        _ => Err(anyhow!("response is not an HTML document")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::net::SharedBody;
     use bytes::Bytes;
     use futures_util::TryStreamExt;
 
-    /// Verify `decode_web_font` turns a real WOFF2 payload into an SFNT the font stack can
-    /// parse. Reads the fixture path from `GOSUB_WOFF2_FIXTURE` so we neither hit the network
-    /// nor commit a binary font; skips when unset.
-    #[test]
-    fn decode_web_font_woff2_roundtrips_to_sfnt() {
-        let Ok(path) = std::env::var("GOSUB_WOFF2_FIXTURE") else {
-            eprintln!("skipping: set GOSUB_WOFF2_FIXTURE to a .woff2 file to run");
-            return;
-        };
-        let woff2 = std::fs::read(&path).expect("read fixture");
-        assert_eq!(&woff2[0..4], b"wOF2", "fixture must be WOFF2");
-
-        let url = url::Url::parse("https://example.test/font.woff2").unwrap();
-        let sfnt = super::decode_web_font(woff2, &url);
-
-        // Output must be a different, valid SFNT (TrueType `0x00010000` or OpenType `OTTO`).
-        let magic = u32::from_be_bytes([sfnt[0], sfnt[1], sfnt[2], sfnt[3]]);
-        assert!(magic == 0x0001_0000 || magic == 0x4F54_544F, "not SFNT: {magic:#010x}");
-
-        // It must re-parse and expose the core tables a backend reads.
-        use allsorts::binary::read::ReadScope;
-        use allsorts::font_data::FontData;
-        use allsorts::tables::FontTableProvider;
-        let font = ReadScope::new(&sfnt).read::<FontData<'_>>().expect("parse SFNT");
-        let provider = font.table_provider(0).expect("table provider");
-        for tag in [allsorts::tag::HEAD, allsorts::tag::CMAP, allsorts::tag::GLYF] {
-            assert!(provider.has_table(tag), "missing table {tag:#010x}");
-        }
-    }
-
     #[tokio::test]
     async fn shared_body_streamreader_eof() {
         use std::io;
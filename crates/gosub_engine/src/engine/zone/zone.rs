@@ -7,6 +7,7 @@ use crate::engine::tab::TabId;
 use crate::engine::types::{EventChannel, IoChannel, TabChannel};
 use crate::events::TabCommand;
 use crate::html::RenderConfiguration;
+use crate::media::StreamDecoders;
 use crate::net::req_ref_tracker::RequestReferenceMap;
 use crate::storage::types::PartitionPolicy;
 use crate::tab::services::resolve_tab_services;
@@ -125,6 +126,8 @@ pub struct ZoneContext<C: RenderConfiguration = crate::html::DefaultRenderConfig
     pub(crate) font_system: Arc<Mutex<C::FontSystem>>,
     /// Per-engine settings store, cloned from the engine context and passed on to each tab.
     pub(crate) config_store: Config,
+    /// The engine's `<video>`/`<audio>` decoders.
+    pub(crate) media_decoders: Arc<RwLock<StreamDecoders>>,
 }
 
 // Things that are shared upwards to the engine
@@ -226,6 +229,7 @@ impl<C: RenderConfiguration> Zone<C> {
         let io_tx = engine_context.io_tx.get().cloned().ok_or(EngineError::IoNotStarted)?;
         let request_reference_map = engine_context.request_reference_map.clone();
         let config_store = engine_context.config_store.clone();
+        let media_decoders = engine_context.media_decoders.clone();
 
         let zone = Self {
             engine_context,
@@ -248,6 +252,7 @@ impl<C: RenderConfiguration> Zone<C> {
                 render_backend,
                 font_system,
                 config_store,
+                media_decoders,
             }),
             id: zone_id,
             tabs: HashMap::new(),
@@ -1,11 +1,14 @@
 //! HTML parsing and related utilities.
 //!
 //! This module provides functionality to parse HTML documents, extract resource hints,
-//! and handle various HTML configurations.
+//! and handle various HTML configurations. XML documents (XHTML, SVG and other XML) are
+//! parsed by [`parse_xml_document_stream`].
 mod parser;
+mod xml;
 
 pub use parser::parse_main_document_stream;
 pub use parser::{DocumentError, HtmlParseConfig, ResourceHint};
+pub use xml::parse_xml_document_stream;
 
 use gosub_css3::system::Css3System;
 use gosub_fontmanager::ParleyFontSystem;
@@ -8,6 +8,7 @@ use gosub_html5::document::builder::DocumentBuilderImpl;
 use gosub_html5::parser::Html5Parser;
 use gosub_interface::css3::CssSystem;
 use gosub_interface::document::Document as _;
+use gosub_render_pipeline::image_source::{select_from_attributes, ImageEnvironment};
 use gosub_shared::byte_stream::{ByteStream, Encoding};
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -60,6 +61,8 @@ pub struct HtmlParseConfig {
     /// Max bytes to buffer from the stream; a larger document is truncated (with a warning).
     /// The engine reads this from the `net.document.max_bytes` setting.
     pub max_bytes: usize,
+    /// Viewport and device-pixel ratio the preload scanner picks `srcset` candidates for.
+    pub image_environment: ImageEnvironment,
 }
 
 impl Default for HtmlParseConfig {
@@ -67,6 +70,7 @@ impl Default for HtmlParseConfig {
         // Matches the `net.document.max_bytes` schema default.
         Self {
             max_bytes: 10 * 1024 * 1024,
+            image_environment: ImageEnvironment::default(),
         }
     }
 }
@@ -91,7 +95,46 @@ where
     R: AsyncRead + Unpin + Send + 'static,
     F: FnMut(ResourceHint) + Send,
 {
-    // Buffer the full stream (up to cfg.max_bytes); bail on cancellation.
+    let buf = read_document(&base_url, &mut reader, &cancel, cfg.max_bytes).await?;
+
+    // Use lossy UTF-8 only for the fast resource-discovery regex scan.
+    let html_lossy = String::from_utf8_lossy(&buf);
+
+    // Fire sub-resource callbacks using the fast regex-based scanner so that
+    // image/CSS/script fetches are submitted before the full parse completes.
+    for hint in discover_resources(&html_lossy, &base_url, &cfg.image_environment) {
+        on_discover(hint);
+    }
+
+    // Detect encoding from the raw bytes (BOM check + chardetng), then build a
+    // properly-decoded stream.  We cannot call set_encoding() on an Unknown-
+    // encoded stream because tell_bytes() returns buffer.len() when chars is
+    // empty, which would advance the position to EOF.
+    let encoding = {
+        let mut tmp = ByteStream::new(Encoding::Unknown, None);
+        tmp.read_from_bytes(&buf)?;
+        tmp.detect_encoding()
+    };
+    let mut stream = ByteStream::new(encoding, None);
+    stream.read_from_bytes(&buf)?;
+    let mut doc = DocumentBuilderImpl::new_document::<C>(Some(base_url));
+    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+    let ua = <C::CssSystem as CssSystem>::load_default_useragent_stylesheet();
+    doc.add_stylesheet(ua);
+
+    Ok(doc)
+}
+
+/// Buffer the full document stream, up to `max_bytes`; bails on cancellation.
+pub(super) async fn read_document<R>(
+    base_url: &Url,
+    reader: &mut R,
+    cancel: &CancellationToken,
+    max_bytes: usize,
+) -> Result<Vec<u8>, DocumentError>
+where
+    R: AsyncRead + Unpin,
+{
     let mut buf = Vec::with_capacity(32 * 1024);
     let mut tmp = [0u8; 16 * 1024];
 
@@ -105,16 +148,15 @@ where
             break;
         }
 
-        let remaining = cfg.max_bytes.saturating_sub(buf.len()).min(n);
+        let remaining = max_bytes.saturating_sub(buf.len()).min(n);
         if remaining > 0 {
             buf.extend_from_slice(&tmp[..remaining]);
         }
         // If we hit the cap, we still drain the stream to EOF quickly
         // to avoid keeping the connection open unnecessarily.
-        if buf.len() >= cfg.max_bytes {
+        if buf.len() >= max_bytes {
             log::warn!(
-                "Document {base_url} exceeds the {} byte limit (net.document.max_bytes); parsing truncated content",
-                cfg.max_bytes
+                "Document {base_url} exceeds the {max_bytes} byte limit (net.document.max_bytes); parsing truncated content"
             );
             // Drain (non-blocking-ish) without growing memory
             // We don't strictly need to, but it's polite to the transport.
@@ -128,32 +170,7 @@ where
         }
     }
 
-    // Use lossy UTF-8 only for the fast resource-discovery regex scan.
-    let html_lossy = String::from_utf8_lossy(&buf);
-
-    // Fire sub-resource callbacks using the fast regex-based scanner so that
-    // image/CSS/script fetches are submitted before the full parse completes.
-    for hint in discover_resources(&html_lossy, &base_url) {
-        on_discover(hint);
-    }
-
-    // Detect encoding from the raw bytes (BOM check + chardetng), then build a
-    // properly-decoded stream.  We cannot call set_encoding() on an Unknown-
-    // encoded stream because tell_bytes() returns buffer.len() when chars is
-    // empty, which would advance the position to EOF.
-    let encoding = {
-        let mut tmp = ByteStream::new(Encoding::Unknown, None);
-        tmp.read_from_bytes(&buf)?;
-        tmp.detect_encoding()
-    };
-    let mut stream = ByteStream::new(encoding, None);
-    stream.read_from_bytes(&buf)?;
-    let mut doc = DocumentBuilderImpl::new_document::<C>(Some(base_url));
-    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
-    let ua = <C::CssSystem as CssSystem>::load_default_useragent_stylesheet();
-    doc.add_stylesheet(ua);
-
-    Ok(doc)
+    Ok(buf)
 }
 
 // ======== Forgiving resource discovery (regex-based) ========
@@ -186,10 +203,17 @@ static RE_ASYNC_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"\basync\b"#));
 
 static RE_DEFER_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"\bdefer\b"#));
 
-static RE_IMG_SRC: Lazy<Regex> =
-    Lazy::new(|| re(r#"(?is)<\s*img\b[^>]*\bsrc\s*=\s*(?P<src>"[^"]*"|'[^']*'|[^\s>]+)[^>]*>"#));
+static RE_IMG: Lazy<Regex> = Lazy::new(|| re(r#"(?is)<\s*img\b[^>]*>"#));
+
+static RE_SRC_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"(?is)\bsrc\s*=\s*(?P<v>"[^"]*"|'[^']*'|[^\s>]+)"#));
+
+static RE_SRCSET_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"(?is)\bsrcset\s*=\s*(?P<v>"[^"]*"|'[^']*'|[^\s>]+)"#));
 
-fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
+static RE_SIZES_ATTR: Lazy<Regex> = Lazy::new(|| re(r#"(?is)\bsizes\s*=\s*(?P<v>"[^"]*"|'[^']*'|[^\s>]+)"#));
+
+static RE_LOADING_LAZY: Lazy<Regex> = Lazy::new(|| re(r#"(?i)\bloading\s*=\s*["']?lazy\b"#));
+
+pub(super) fn discover_resources(html: &str, base: &Url, image_environment: &ImageEnvironment) -> Vec<ResourceHint> {
     let mut out = Vec::new();
 
     // Stylesheets
@@ -238,12 +262,22 @@ fn discover_resources(html: &str, base: &Url) -> Vec<ResourceHint> {
         });
     }
 
-    // Images
-    for cap in RE_IMG_SRC.captures_iter(html) {
-        let Some(m) = cap.name("src") else {
+    // Images, except lazy ones: those load once layout finds them near the viewport. With a
+    // `srcset` the candidate layout would pick is fetched; `<picture>` sources are left to layout.
+    for tag in RE_IMG.find_iter(html).map(|m| m.as_str()) {
+        if RE_LOADING_LAZY.is_match(tag) {
+            continue;
+        }
+        let attr = |re: &Regex| re.captures(tag).and_then(|c| c.name("v")).map(|m| unquote(m.as_str()));
+        let Some(source) = select_from_attributes(
+            attr(&RE_SRCSET_ATTR),
+            attr(&RE_SIZES_ATTR),
+            attr(&RE_SRC_ATTR),
+            image_environment,
+        ) else {
             continue;
         };
-        let Ok(u) = resolve(base, unquote(m.as_str())) else {
+        let Ok(u) = resolve(base, &source.url) else {
             continue;
         };
         out.push(ResourceHint {
@@ -326,6 +360,26 @@ mod tests {
             .any(|h| h.kind == ResourceKind::Image && h.url.as_str() == "https://example.com/path/images/logo.png"));
     }
 
+    #[test]
+    fn preloads_the_srcset_candidate_and_skips_lazy_images() {
+        let html = r#"
+            <img src="small.png" srcset="small.png 1x, large.png 2x">
+            <img src="below.png" loading=lazy>
+            <img srcset="w400.png 400w, w800.png 800w" sizes="50vw">
+        "#;
+        let base = Url::parse("https://example.com/").unwrap();
+        let env = ImageEnvironment {
+            viewport_width: 800.0,
+            viewport_height: 600.0,
+            device_pixel_ratio: 2.0,
+        };
+        let urls: Vec<_> = discover_resources(html, &base, &env)
+            .into_iter()
+            .map(|h| h.url.to_string())
+            .collect();
+        assert_eq!(urls, ["https://example.com/large.png", "https://example.com/w800.png"]);
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn honors_cancellation() {
         let base = Url::parse("https://e.test/").unwrap();
@@ -357,7 +411,10 @@ mod tests {
     async fn truncates_at_max_bytes() {
         let base = Url::parse("https://e.test/").unwrap();
         let big = "A".repeat(150_000); // 150 KiB
-        let cfg = HtmlParseConfig { max_bytes: 64 * 1024 }; // 64 KiB
+        let cfg = HtmlParseConfig {
+            max_bytes: 64 * 1024, // 64 KiB
+            ..Default::default()
+        };
 
         // Just verify truncated input still produces a valid document (no panic).
         parse_main_document_stream::<DefaultRenderConfig, _, _>(
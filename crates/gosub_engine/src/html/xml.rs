@@ -0,0 +1,332 @@
+use crate::html::parser::{discover_resources, read_document};
+use crate::html::{DocumentError, EngineDocument, HtmlParseConfig, RenderConfiguration, ResourceHint};
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::errors::ParseError;
+use gosub_html5::node::{HTML_NAMESPACE, SVG_NAMESPACE};
+use gosub_html5::parser::Html5Parser;
+use gosub_html5::xml::{decode, XmlParser};
+use gosub_interface::css3::CssSystem;
+use gosub_interface::document::Document as _;
+use gosub_interface::node::NodeType;
+use gosub_render_pipeline::image_source::ImageEnvironment;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+use gosub_shared::node::NodeId;
+use gosub_svg::SVGDocument;
+use tokio::io::AsyncRead;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Styles the generated tree viewer and error pages
+const VIEWER_STYLE: &str = "
+    body { font-family: monospace; font-size: 13px; }
+    .notice { font-family: sans-serif; border-bottom: 1px solid #ccc; padding-bottom: 8px; }
+    .element .element, .element .comment, .element .text { margin-left: 1.5em; }
+    .tag { color: #881280; }
+    .attribute-name { color: #994500; }
+    .attribute-value { color: #1a1aa6; }
+    .comment { color: #236e25; }
+    .error { font-family: sans-serif; color: #b00; }
+";
+
+/// Counterpart of [`parse_main_document_stream`](crate::html::parse_main_document_stream) for XML
+/// responses (XHTML, SVG and other XML): buffer the stream and build the document to show for it.
+///
+/// - XHTML becomes a document like HTML does, with its sub-resources reported to `on_discover`.
+/// - Standalone SVG becomes a document with the `<svg>` root, provided `gosub_svg` can render it.
+/// - Other XML is shown in a tree viewer, as it has no presentation of its own.
+/// - A document that is not well-formed becomes an error page pointing at the offending line, and
+///   SVG that cannot be rendered one with the renderer's error.
+pub async fn parse_xml_document_stream<C, R, F>(
+    base_url: Url,
+    mut reader: R,
+    cancel: CancellationToken,
+    cfg: HtmlParseConfig,
+    on_discover: F,
+) -> Result<EngineDocument<C>, DocumentError>
+where
+    C: RenderConfiguration,
+    R: AsyncRead + Unpin + Send + 'static,
+    F: FnMut(ResourceHint) + Send,
+{
+    let buf = read_document(&base_url, &mut reader, &cancel, cfg.max_bytes).await?;
+    Ok(xml_document::<C, _>(
+        base_url,
+        &buf,
+        &cfg.image_environment,
+        on_discover,
+    ))
+}
+
+/// Builds the document shown for the XML response `bytes` (see [`parse_xml_document_stream`])
+fn xml_document<C, F>(
+    url: Url,
+    bytes: &[u8],
+    image_environment: &ImageEnvironment,
+    mut on_discover: F,
+) -> EngineDocument<C>
+where
+    C: RenderConfiguration,
+    F: FnMut(ResourceHint),
+{
+    let text = match decode(bytes) {
+        Ok(text) => text,
+        Err(e) => return parse_error_page(&url, &e, &String::from_utf8_lossy(bytes)),
+    };
+
+    let mut doc = DocumentBuilderImpl::new_document::<C>(Some(url.clone()));
+    if let Err(e) = XmlParser::<C>::parse_document(&text, &mut doc) {
+        return parse_error_page(&url, &e, &text);
+    }
+
+    let root = doc.root();
+    let Some(element) = doc
+        .children(root)
+        .iter()
+        .copied()
+        .find(|&child| doc.node_type(child) == NodeType::ElementNode)
+    else {
+        return tree_viewer(&url, &doc);
+    };
+
+    match doc.namespace(element) {
+        Some(HTML_NAMESPACE) => {
+            for hint in discover_resources(&text, &url, image_environment) {
+                on_discover(hint);
+            }
+        }
+        Some(SVG_NAMESPACE) => {
+            // Layout draws the <svg> root with the same renderer (usvg), so an error here would
+            // otherwise be a blank page
+            if let Err(e) = SVGDocument::from_html_doc::<C>(element, &doc) {
+                let body = format!(
+                    "<h1 class=\"error\">SVG Rendering Error: {}</h1><p>Location: {}</p>",
+                    escape(&e.to_string()),
+                    escape(url.as_str())
+                );
+                return html_page(&url, "SVG Rendering Error", &body);
+            }
+        }
+        _ => return tree_viewer(&url, &doc),
+    }
+
+    doc.add_stylesheet(<C::CssSystem as CssSystem>::load_default_useragent_stylesheet());
+    doc
+}
+
+/// The page shown for a document that is not well-formed: the error, where it is and the source
+/// line with a marker under the offending column
+fn parse_error_page<C: RenderConfiguration>(url: &Url, error: &ParseError, source: &str) -> EngineDocument<C> {
+    let location = &error.location;
+    let line = source.lines().nth(location.line.saturating_sub(1)).unwrap_or_default();
+    let body = format!(
+        "<h1 class=\"error\">XML Parsing Error: {}</h1><p>Location: {}</p><p>Line Number {}, Column {}:</p><pre>{}\n{}^</pre>",
+        escape(&error.message),
+        escape(url.as_str()),
+        location.line,
+        location.column,
+        escape(line),
+        "-".repeat(location.column.saturating_sub(1)),
+    );
+    html_page(url, "XML Parsing Error", &body)
+}
+
+/// A page showing the tree of an XML document without presentation of its own
+fn tree_viewer<C: RenderConfiguration>(url: &Url, doc: &EngineDocument<C>) -> EngineDocument<C> {
+    let mut body = String::from(
+        "<p class=\"notice\">This XML file does not appear to have any style information associated with it. \
+         The document tree is shown below.</p>",
+    );
+    for &child in doc.children(doc.root()) {
+        write_tree_node(doc, child, &mut body);
+    }
+    html_page(url, url.as_str(), &body)
+}
+
+/// Writes `node` and its descendants as tree viewer markup. Recursion is bounded by the XML
+/// parser's nesting limit.
+fn write_tree_node<C: RenderConfiguration>(doc: &EngineDocument<C>, node: NodeId, out: &mut String) {
+    match doc.node_type(node) {
+        NodeType::ElementNode => {
+            let name = qualified_name(doc, node);
+            out.push_str("<div class=\"element\"><span class=\"tag\">&lt;");
+            out.push_str(&escape(&name));
+            out.push_str("</span>");
+
+            let mut attributes: Vec<(String, &str)> = doc
+                .attributes(node)
+                .into_iter()
+                .flatten()
+                .map(|(name, value)| match name.split_once(' ') {
+                    // Namespaced attributes are stored as "prefix local"
+                    Some((prefix, local)) => (format!("{prefix}:{local}"), value.as_str()),
+                    None => (name.clone(), value.as_str()),
+                })
+                .collect();
+            attributes.sort();
+            for (name, value) in attributes {
+                out.push_str(&format!(
+                    " <span class=\"attribute-name\">{}</span>=\"<span class=\"attribute-value\">{}</span>\"",
+                    escape(&name),
+                    escape(value)
+                ));
+            }
+
+            let children = doc.children(node);
+            if children.is_empty() {
+                out.push_str("<span class=\"tag\">/&gt;</span></div>");
+                return;
+            }
+            out.push_str("<span class=\"tag\">&gt;</span>");
+            for &child in children {
+                write_tree_node(doc, child, out);
+            }
+            out.push_str(&format!("<span class=\"tag\">&lt;/{}&gt;</span></div>", escape(&name)));
+        }
+        NodeType::TextNode => {
+            let text = doc.text_value(node).unwrap_or_default().trim();
+            if !text.is_empty() {
+                out.push_str(&format!("<div class=\"text\">{}</div>", escape(text)));
+            }
+        }
+        NodeType::CommentNode => {
+            let text = doc.comment_value(node).unwrap_or_default();
+            out.push_str(&format!("<div class=\"comment\">&lt;!--{}--&gt;</div>", escape(text)));
+        }
+        _ => {}
+    }
+}
+
+/// The name of an element as written in the source. The arena keeps only the namespace, so the
+/// prefix is that of the nearest declaration of the namespace.
+fn qualified_name<C: RenderConfiguration>(doc: &EngineDocument<C>, node: NodeId) -> String {
+    let name = doc.tag_name(node).unwrap_or_default();
+    let Some(namespace) = doc.namespace(node).filter(|ns| !ns.is_empty()) else {
+        return name.to_string();
+    };
+
+    let mut current = Some(node);
+    while let Some(id) = current {
+        let attributes = doc.attributes(id).into_iter().flatten();
+        for (attribute, _) in attributes.filter(|(_, value)| value.as_str() == namespace) {
+            if attribute == "xmlns" {
+                return name.to_string();
+            }
+            if let Some(prefix) = attribute.strip_prefix("xmlns ") {
+                return format!("{prefix}:{name}");
+            }
+        }
+        current = doc.parent(id);
+    }
+    name.to_string()
+}
+
+/// Parses generated markup into a document for `url`, styled by [`VIEWER_STYLE`]
+fn html_page<C: RenderConfiguration>(url: &Url, title: &str, body: &str) -> EngineDocument<C> {
+    let html = format!(
+        "<!DOCTYPE html><html><head><title>{}</title><style>{VIEWER_STYLE}</style></head><body>{body}</body></html>",
+        escape(title)
+    );
+    let mut stream = ByteStream::new(Encoding::UTF8, None);
+    stream.read_from_str(&html, Some(Encoding::UTF8));
+    stream.close();
+
+    let mut doc = DocumentBuilderImpl::new_document::<C>(Some(url.clone()));
+    let _ = Html5Parser::<C>::parse_document(&mut stream, &mut doc, None);
+    doc.add_stylesheet(<C::CssSystem as CssSystem>::load_default_useragent_stylesheet());
+    doc
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::{document_title, DefaultRenderConfig};
+
+    fn build(xml: &str) -> (EngineDocument<DefaultRenderConfig>, Vec<ResourceHint>) {
+        let url = Url::parse("https://example.com/doc.xml").unwrap();
+        let mut hints = Vec::new();
+        let doc = xml_document::<DefaultRenderConfig, _>(url, xml.as_bytes(), &ImageEnvironment::default(), |h| {
+            hints.push(h)
+        });
+        (doc, hints)
+    }
+
+    fn text(doc: &EngineDocument<DefaultRenderConfig>) -> String {
+        let mut out = String::new();
+        let mut stack = vec![doc.root()];
+        while let Some(node) = stack.pop() {
+            if let Some(text) = doc.text_value(node) {
+                out.push_str(text);
+            }
+            stack.extend(doc.children(node).iter().rev());
+        }
+        out
+    }
+
+    #[test]
+    fn xhtml_becomes_a_document() {
+        let (doc, hints) = build(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><head><title>XHTML</title></head>
+               <body><img src="logo.png"/></body></html>"#,
+        );
+        assert_eq!(document_title(&doc).as_deref(), Some("XHTML"));
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].url.as_str(), "https://example.com/logo.png");
+    }
+
+    #[test]
+    fn svg_becomes_a_document() {
+        let (doc, _) = build(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect/></svg>"#);
+        let svg = doc.children(doc.root())[0];
+        assert_eq!(doc.tag_name(svg), Some("svg"));
+        assert_eq!(doc.namespace(svg), Some(SVG_NAMESPACE));
+
+        let (doc, _) = build(r#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="0"/>"#);
+        assert_eq!(document_title(&doc).as_deref(), Some("SVG Rendering Error"));
+    }
+
+    #[test]
+    fn errors_point_at_the_source() {
+        let (doc, _) = build("<feed>\n  <entry></feed>");
+        assert_eq!(document_title(&doc).as_deref(), Some("XML Parsing Error"));
+        let text = text(&doc);
+        assert!(text.contains("Line Number 2, Column 10:"), "{text}");
+        assert!(text.contains("  <entry></feed>\n---------^"), "{text}");
+    }
+
+    #[test]
+    fn other_xml_gets_a_tree_viewer() {
+        let (doc, _) = build(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+               <!-- latest --><title>News &amp; more</title><media:thumbnail url="a.png"/></feed>"#,
+        );
+        assert_eq!(document_title(&doc).as_deref(), Some("https://example.com/doc.xml"));
+        let text = text(&doc);
+        assert!(text.contains("does not appear to have any style information"), "{text}");
+        for expected in [
+            "<feed",
+            "xmlns:media",
+            "<!-- latest -->",
+            "News & more",
+            "<media:thumbnail",
+            "a.png",
+            "/>",
+            "</feed>",
+        ] {
+            assert!(text.contains(expected), "{expected:?} missing from {text}");
+        }
+    }
+}
@@ -149,6 +149,9 @@ pub mod html;
 #[cfg(feature = "metrics")]
 pub mod metrics;
 
+#[cfg(feature = "devtools")]
+pub mod devtools;
+
 pub use engine::{BrowsingContext, EngineError, GosubEngine};
 
 /// The engine's ready-made config: a marker that implements both
@@ -179,6 +182,14 @@ pub use engine::cookies;
 /// Storage APIs for local/session data.
 pub use engine::storage;
 
+#[doc(inline)]
+/// `<video>`/`<audio>` decoding and playback.
+pub use engine::media;
+
+#[doc(inline)]
+/// Document snapshots for inspection tooling.
+pub use engine::inspect;
+
 // EngineConfig at crate root:
 #[doc(inline)]
 pub use crate::engine::config::EngineConfig;
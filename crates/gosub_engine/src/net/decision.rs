@@ -29,6 +29,7 @@ pub mod types;
 ///   prefer **Render(PdfViewer)**.
 /// - If navigation sniffing upgrade is enabled, allow HTML upgrade for mislabelled
 ///   navigations (e.g., `text/plain` / `application/octet-stream` that sniff as HTML).
+/// - XHTML and other XML go to the XML parser, as does an SVG image navigated to as a document.
 ///
 /// Returns a [`DecisionOutcome`] with both the *final* class and the auxiliary evidence
 /// (declared MIME, sniffed class, disposition flag).
@@ -97,26 +98,42 @@ pub fn decide_handling(
     let class = effective_class.unwrap_or(ResponseClass::Binary);
 
     let decision = match class {
-        ResponseClass::Html | ResponseClass::XHtml | ResponseClass::Xml => {
-            HandlingDecision::Render(RenderTarget::HtmlParser)
+        ResponseClass::Html => HandlingDecision::Render(RenderTarget::HtmlParser),
+        ResponseClass::XHtml | ResponseClass::Xml => HandlingDecision::Render(RenderTarget::XmlParser),
+        ResponseClass::Image
+            if matches!(dest, RequestDestination::Document) && declared_mime.as_ref().is_some_and(mime_is_svg) =>
+        {
+            HandlingDecision::Render(RenderTarget::XmlParser)
         }
         ResponseClass::Image => HandlingDecision::Render(RenderTarget::ImageDecoder),
         ResponseClass::Js => HandlingDecision::Render(RenderTarget::JsEngine),
         ResponseClass::Css => HandlingDecision::Render(RenderTarget::CssParser),
         ResponseClass::Font => HandlingDecision::Render(RenderTarget::FontLoader),
+        ResponseClass::Audio | ResponseClass::Video => HandlingDecision::Render(RenderTarget::MediaPlayer),
         ResponseClass::Pdf => {
             // If we reached here without pdf viewer enabled, download by default.
             HandlingDecision::Download {
                 path: std::path::PathBuf::new(),
             }
         }
+        // Media decoders sniff formats the generic sniffer doesn't know (e.g. Y4M), so a media
+        // element gets an unlabelled body to try.
+        ResponseClass::Binary | ResponseClass::Unknown
+            if matches!(dest, RequestDestination::Audio | RequestDestination::Video) =>
+        {
+            HandlingDecision::Render(RenderTarget::MediaPlayer)
+        }
         ResponseClass::Json | ResponseClass::Text | ResponseClass::Binary => match dest {
             RequestDestination::Document
-                if policy.enable_sniffing_navigation_upgrade
-                    && matches!(sniffed_class, Some(ResponseClass::Html | ResponseClass::Xml)) =>
+                if policy.enable_sniffing_navigation_upgrade && matches!(sniffed_class, Some(ResponseClass::Html)) =>
             {
                 HandlingDecision::Render(RenderTarget::HtmlParser)
             }
+            RequestDestination::Document
+                if policy.enable_sniffing_navigation_upgrade && matches!(sniffed_class, Some(ResponseClass::Xml)) =>
+            {
+                HandlingDecision::Render(RenderTarget::XmlParser)
+            }
             _ => HandlingDecision::Download {
                 path: std::path::PathBuf::new(),
             },
@@ -168,11 +185,19 @@ fn mime_is_pdf(m: &mime::Mime) -> bool {
     (m.type_() == mime::APPLICATION && m.subtype() == "pdf") || m.essence_str().eq_ignore_ascii_case("application/pdf")
 }
 
+/// Check if a MIME type is SVG (`image/svg+xml`).
+fn mime_is_svg(m: &mime::Mime) -> bool {
+    m.type_() == mime::IMAGE && m.subtype() == mime::SVG
+}
+
 /// Map a MIME type to a coarse `ResponseClass`.
 fn class_from_mime(m: &mime::Mime) -> Option<ResponseClass> {
     use ResponseClass::*;
     if m.type_() == mime::TEXT && m.subtype() == mime::HTML {
         Some(Html)
+    } else if m.type_() != mime::IMAGE && matches!(ResponseClass::from_mime(m), XHtml | Xml) {
+        // application/xhtml+xml, application/xml, text/xml and other +xml types (but not SVG)
+        Some(ResponseClass::from_mime(m))
     } else if m.type_() == mime::TEXT && m.subtype() == mime::CSS {
         Some(Css)
     } else if m.type_() == mime::APPLICATION && m.subtype() == "javascript" {
@@ -181,6 +206,10 @@ fn class_from_mime(m: &mime::Mime) -> Option<ResponseClass> {
         Some(Image)
     } else if m.type_() == mime::FONT {
         Some(Font)
+    } else if m.type_() == mime::VIDEO {
+        Some(Video)
+    } else if m.type_() == mime::AUDIO {
+        Some(Audio)
     } else if mime_is_pdf(m) {
         Some(Pdf)
     } else if m.type_() == mime::APPLICATION && m.subtype() == "json" {
@@ -191,3 +220,61 @@ fn class_from_mime(m: &mime::Mime) -> Option<ResponseClass> {
         None // treat as untrusted; let sniffing or policy decide
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn decision(content_type: &str, dest: RequestDestination, body: &[u8]) -> HandlingDecision {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::CONTENT_TYPE, content_type.parse().unwrap());
+        let meta = FetchResultMeta {
+            final_url: Url::parse("https://example.com/").unwrap(),
+            status: 200,
+            status_text: "OK".into(),
+            headers,
+            content_length: None,
+            content_type: None,
+            has_body: true,
+        };
+        decide_handling(&meta, dest, PeekBuf::from_slice(body), &UaPolicy::default()).decision
+    }
+
+    #[test]
+    fn xml_documents_go_to_the_xml_parser() {
+        let xml_parser = HandlingDecision::Render(RenderTarget::XmlParser);
+        for content_type in [
+            "application/xhtml+xml",
+            "application/xml",
+            "text/xml; charset=utf-8",
+            "application/atom+xml",
+            "image/svg+xml",
+        ] {
+            assert_eq!(
+                decision(content_type, RequestDestination::Document, b"<a/>"),
+                xml_parser,
+                "{content_type}"
+            );
+        }
+
+        assert_eq!(
+            decision("text/html", RequestDestination::Document, b"<p>"),
+            HandlingDecision::Render(RenderTarget::HtmlParser)
+        );
+        // Mislabelled XML is upgraded like mislabelled HTML
+        assert_eq!(
+            decision(
+                "text/plain",
+                RequestDestination::Document,
+                b"<?xml version=\"1.0\"?><a/>"
+            ),
+            xml_parser
+        );
+        // SVG used as an image is still decoded as one
+        assert_eq!(
+            decision("image/svg+xml", RequestDestination::Image, b"<svg/>"),
+            HandlingDecision::Render(RenderTarget::ImageDecoder)
+        );
+    }
+}
@@ -57,6 +57,9 @@ impl ResponseClass {
             ("application", "pdf") => ResponseClass::Pdf,
             ("application", "octet-stream") => ResponseClass::Binary,
 
+            // Other XML vocabularies (application/atom+xml, application/rss+xml, ...)
+            _ if suffix == Some("xml") => ResponseClass::Xml,
+
             _ => ResponseClass::Unknown,
         }
     }
@@ -106,6 +109,10 @@ mod tests {
         let cases = vec![
             ("text/html", ResponseClass::Html),
             ("application/xhtml+xml", ResponseClass::XHtml),
+            ("application/xml", ResponseClass::Xml),
+            ("text/xml", ResponseClass::Xml),
+            ("application/atom+xml", ResponseClass::Xml),
+            ("image/svg+xml", ResponseClass::Image),
             ("text/plain", ResponseClass::Text),
             ("text/css", ResponseClass::Css),
             ("application/javascript", ResponseClass::Js),
@@ -1,6 +1,7 @@
 use crate::engine::resource_pipeline::css::DummyStylesheet;
-use crate::engine::resource_pipeline::font::DummyFont;
+use crate::engine::resource_pipeline::font::WebFont;
 use crate::engine::resource_pipeline::js::DummyJsDocument;
+use crate::engine::resource_pipeline::media::MediaResource;
 use crate::engine::resource_pipeline::ResourcePipelines;
 use crate::engine::types::PeekBuf;
 use crate::engine::UaPolicy;
@@ -27,7 +28,9 @@ pub enum RoutedOutcome<C: RenderConfiguration> {
     /// An image has been decoded.
     ImageDecoded(image::DynamicImage),
     /// A font has been loaded.
-    FontLoaded(DummyFont),
+    FontLoaded(WebFont),
+    /// A `<video>`/`<audio>` resource has been downloaded.
+    MediaLoaded(MediaResource),
 
     /// The request was blocked (with reason).
     Blocked(BlockReason),
@@ -91,11 +94,26 @@ pub async fn route_response_for<C: RenderConfiguration>(
                 };
                 Ok(RoutedOutcome::MainDocument(Arc::new(doc)))
             }
+            RenderTarget::XmlParser => {
+                let doc = match body_content {
+                    BodyContent::Stream { shared } => {
+                        hooks
+                            .html
+                            .parse_xml_stream(request, handle, meta, peek_buf, shared)
+                            .await?
+                    }
+                    BodyContent::Buffered { body } => {
+                        hooks.html.parse_xml_bytes(request, handle, meta, body.as_ref()).await?
+                    }
+                };
+                Ok(RoutedOutcome::MainDocument(Arc::new(doc)))
+            }
             RenderTarget::CssParser => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
             RenderTarget::JsEngine => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
             RenderTarget::ImageDecoder => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
             RenderTarget::FontLoader => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
             RenderTarget::PdfViewer => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
+            RenderTarget::MediaPlayer => Ok(RoutedOutcome::ViewerRendered(body_content.to_bytes(peek_buf).await?)),
         },
         (RequestDestination::Document, HandlingDecision::Download { .. }, _) => {
             Err(anyhow!("Cannot download main document"))
@@ -130,6 +148,17 @@ pub async fn route_response_for<C: RenderConfiguration>(
             };
             Ok(RoutedOutcome::FontLoaded(font))
         }
+        (
+            RequestDestination::Audio | RequestDestination::Video,
+            HandlingDecision::Render(RenderTarget::MediaPlayer),
+            body_content,
+        ) => {
+            let media = match body_content {
+                BodyContent::Stream { shared } => hooks.media.parse_stream(meta, peek_buf, shared).await?,
+                BodyContent::Buffered { body } => hooks.media.parse_bytes(meta, body.as_ref()).await?,
+            };
+            Ok(RoutedOutcome::MediaLoaded(media))
+        }
 
         // Safety net: any other subresource decision (Download, or a Render target that
         // doesn't match the destination) is treated as a policy block.
@@ -4,16 +4,20 @@
 //! and a backend that paints [`ShapedText`] glyph runs can render with it.
 //!
 //! Note: cosmic-text doesn't expose the underlying shared font bytes, so `blob_for` copies them
-//! when filling a [`FontBlob`] (cached upstream by whoever holds the `ResolvedFont`).
+//! when filling a [`FontBlob`] (cached upstream by whoever holds the `ResolvedFont`). It also
+//! can't set variable-font axes per span, so [`TextStyle::variation_axes`] is ignored here and
+//! variable fonts shape at their default instance.
 
+use crate::parley_system::split_css_families;
 use cosmic_text::{
-    fontdb, Align, Attrs, Buffer, Family, FontSystem as CosmicTextFontSystem, Metrics, Shaping, Stretch, Style, Weight,
+    fontdb, Align, Attrs, Buffer, Family, FeatureTag, FontFeatures, FontSystem as CosmicTextFontSystem, Metrics,
+    Shaping, Stretch, Style, Weight,
 };
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontStretch, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign,
-    TextStyle,
+    measure_broken, measure_vertical, shape_broken, shape_vertical, FontQuery, FontStretch, FontSystem, GlyphRotation,
+    ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle,
 };
 use std::sync::Arc;
 
@@ -73,16 +77,38 @@ impl CosmicFontSystem {
     }
 
     /// Build and shape a cosmic-text buffer for `text` in the given style.
+    ///
+    /// The text is split into spans by [`CosmicFontSystem::fallback_spans`], each naming the
+    /// `font-family` entry that covers it; cosmic-text's own fallback only covers what no entry
+    /// in the list has (emoji, scripts the page didn't plan for).
     fn shaped_buffer(&mut self, text: &str, style: &TextStyle) -> Buffer {
-        let metrics = Metrics::new(style.size, style.line_height.unwrap_or(style.size * 1.2));
-        let mut buffer = Buffer::new(&mut self.inner, metrics);
-        buffer.set_size(style.max_width, None);
+        // cosmic-text takes the paragraph direction from the text itself; a leading mark pins it.
+        let text = style.direction.with_base_mark(text);
+        let families = split_css_families(&style.family);
+        let spans = self.fallback_spans(&text, &families, style);
+
+        let mut features = FontFeatures::new();
+        for feature in style.opentype_features() {
+            features.set(FeatureTag::new(&feature.tag), feature.value);
+        }
         let attrs = Attrs::new()
-            .family(css_family(&style.family))
             .weight(Weight(style.weight.0))
             .style(to_style(style.style))
-            .stretch(to_stretch(style.stretch));
-        buffer.set_text(text, &attrs, Shaping::Advanced, None);
+            .stretch(to_stretch(style.stretch))
+            .font_features(features);
+
+        let metrics = Metrics::new(style.size, style.line_height.unwrap_or(style.size * 1.2));
+        let mut buffer = Buffer::new(&mut self.inner, metrics);
+        buffer.set_size(style.max_width, None);
+        let default_attrs = attrs.clone().family(css_family(families[0]));
+        buffer.set_rich_text(
+            spans
+                .into_iter()
+                .map(|(span, family)| (span, attrs.clone().family(css_family(families[family])))),
+            &default_attrs,
+            Shaping::Advanced,
+            None,
+        );
         let align = match style.align {
             TextAlign::Start => None, // natural per-direction default
             TextAlign::Center => Some(Align::Center),
@@ -98,6 +124,57 @@ impl CosmicFontSystem {
         buffer
     }
 
+    /// Split `text` into spans tagged with the index in `families` of the first family whose
+    /// face has a glyph for the span's characters - CSS per-character fallback along the
+    /// `font-family` list. Whitespace stays with the span around it; characters no family covers
+    /// go to the first family that resolved at all.
+    fn fallback_spans<'t>(&mut self, text: &'t str, families: &[&str], style: &TextStyle) -> Vec<(&'t str, usize)> {
+        let weight = Weight(style.weight.0);
+        let faces: Vec<Option<fontdb::ID>> = families
+            .iter()
+            .map(|name| {
+                self.inner.db().query(&fontdb::Query {
+                    families: &[css_family(name)],
+                    weight,
+                    stretch: to_stretch(style.stretch),
+                    style: to_style(style.style),
+                })
+            })
+            .collect();
+        let fonts: Vec<_> = faces
+            .iter()
+            .map(|id| id.and_then(|id| self.inner.get_font(id, weight)))
+            .collect();
+        let primary = fonts.iter().position(Option::is_some).unwrap_or(0);
+        let covering = |c: char| {
+            fonts
+                .iter()
+                .position(|font| font.as_ref().is_some_and(|font| font.as_swash().charmap().map(c) != 0))
+                .unwrap_or(primary)
+        };
+
+        let mut spans: Vec<(&'t str, usize)> = Vec::new();
+        let mut start = 0;
+        let mut current: Option<usize> = None;
+        for (at, c) in text.char_indices() {
+            if c.is_whitespace() || c.is_control() {
+                continue;
+            }
+            let family = covering(c);
+            match current {
+                Some(cur) if cur != family => {
+                    spans.push((&text[start..at], cur));
+                    start = at;
+                    current = Some(family);
+                }
+                Some(_) => {}
+                None => current = Some(family),
+            }
+        }
+        spans.push((&text[start..], current.unwrap_or(primary)));
+        spans
+    }
+
     /// Raw font bytes for a resolved face, as a [`FontBlob`].
     ///
     /// cosmic-text doesn't expose the underlying shared `Arc<[u8]>`, so this copies the file
@@ -121,6 +198,12 @@ impl FontSystem for CosmicFontSystem {
         if text.is_empty() {
             return (0.0, 0.0);
         }
+        if style.writing_mode.is_vertical() {
+            return measure_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return measure_broken(self, text, style);
+        }
         let buffer = self.shaped_buffer(text, style);
         let mut width = 0.0f32;
         let mut height = 0.0f32;
@@ -182,8 +265,26 @@ impl FontSystem for CosmicFontSystem {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        if style.writing_mode.is_vertical() {
+            return shape_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return shape_broken(self, text, style);
+        }
 
         let buffer = self.shaped_buffer(text, style);
+        // Glyph offsets are relative to their buffer line, which starts after the base mark and
+        // the lines before it.
+        let mark = style.direction.with_base_mark(text).len() - text.len();
+        let line_starts: Vec<usize> = buffer
+            .lines
+            .iter()
+            .scan(0, |start, line| {
+                let line_start = *start;
+                *start += line.text().len() + line.ending().as_str().len();
+                Some(line_start)
+            })
+            .collect();
 
         // Collect owned run data first (borrows `buffer`), then look up font blobs afterwards
         // (borrows `self.inner`) so the two borrows don't overlap.
@@ -212,10 +313,12 @@ impl FontSystem for CosmicFontSystem {
                 let mut glyphs = Vec::new();
                 while i < run.glyphs.len() && run.glyphs[i].font_id == fid {
                     let g = &run.glyphs[i];
+                    let line_start = line_starts.get(run.line_i).copied().unwrap_or(0);
                     glyphs.push(ShapedGlyph {
                         id: g.glyph_id as u32,
                         x: g.x,
                         y: run.line_y + g.y,
+                        cluster: (line_start + g.start).saturating_sub(mark) as u32,
                     });
                     run_width = (g.x + g.w) - run_x;
                     i += 1;
@@ -238,9 +341,16 @@ impl FontSystem for CosmicFontSystem {
             .into_iter()
             .filter_map(|r| {
                 let blob = self.blob_for(r.id, r.weight)?;
+                // Name the face that was actually used: a fallback run isn't `style.family`.
+                let family = self
+                    .inner
+                    .db()
+                    .face(r.id)
+                    .and_then(|face| face.families.first().map(|(name, _)| name.clone()))
+                    .unwrap_or_else(|| style.family.clone());
                 Some(ShapedRun {
                     font: ResolvedFont {
-                        family: style.family.clone(),
+                        family,
                         style: style.style,
                         weight: style.weight,
                         stretch: style.stretch,
@@ -252,6 +362,7 @@ impl FontSystem for CosmicFontSystem {
                     width: r.width,
                     metrics: heuristic_metrics(style.size),
                     glyphs: r.glyphs,
+                    rotation: GlyphRotation::None,
                 })
             })
             .collect();
@@ -313,7 +424,7 @@ fn to_stretch(s: FontStretch) -> Stretch {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use gosub_interface::font_system::FontQuery;
+    use gosub_interface::font_system::{FontKerning, FontQuery};
 
     /// Same contract as the Parley test: the bundled Roboto (loaded in `new()`) must appear,
     /// and the list must be sorted and de-duplicated.
@@ -343,4 +454,34 @@ mod tests {
         let glyphs: usize = shaped.runs.iter().map(|r| r.glyphs.len()).sum();
         assert!(glyphs >= 5, "expected >= 5 glyphs for \"Hello\", got {glyphs}");
     }
+
+    /// The `font-family` list is walked per character: an uninstalled leading family is skipped
+    /// instead of the whole list being treated as one family name.
+    #[test]
+    fn falls_back_along_the_family_list() {
+        let mut fs = CosmicFontSystem::new();
+        let style = TextStyle::new("\"Gosub Missing Family\", Roboto", 16.0);
+        let families = split_css_families(&style.family);
+        assert_eq!(
+            fs.fallback_spans("Hello world", &families, &style),
+            vec![("Hello world", 1)]
+        );
+
+        let shaped = fs.shape("Hello", &style);
+        assert!(!shaped.runs.is_empty());
+        assert!(shaped.runs.iter().all(|run| run.font.family == "Roboto"));
+    }
+
+    #[test]
+    fn font_kerning_none_disables_kerning() {
+        let mut fs = CosmicFontSystem::new();
+        let mut style = TextStyle::new("Roboto", 32.0);
+        let (kerned, _) = fs.measure("AVAVAV", &style);
+        style.kerning = FontKerning::None;
+        let (unkerned, _) = fs.measure("AVAVAV", &style);
+        assert!(
+            unkerned > kerned,
+            "kerning off must widen AV pairs: {kerned} -> {unkerned}"
+        );
+    }
 }
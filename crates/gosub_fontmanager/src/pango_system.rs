@@ -7,7 +7,8 @@
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle,
+    measure_broken, measure_vertical, shape_broken, shape_vertical, FontQuery, FontSystem, GlyphRotation, ResolvedFont,
+    RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextDirection, TextStyle,
 };
 use gtk4::pango;
 use gtk4::pango::Weight;
@@ -345,6 +346,24 @@ impl PangoFontSystem {
         out
     }
 
+    /// The CSS `font-family` value as a Pango family list (`Inter,Noto Sans CJK JP,sans`).
+    ///
+    /// Pango hands the whole list to fontconfig, whose fontset falls back per glyph through
+    /// every family in order - then through its own coverage-based fallback for emoji and
+    /// other scripts - rather than stopping at the first installed family.
+    fn pango_family_list(&self, families: &str) -> String {
+        let names: Vec<&str> = families
+            .split(',')
+            .map(|f| f.trim().trim_matches(|c| c == '"' || c == '\''))
+            .filter(|f| !f.is_empty())
+            .collect();
+        let mut list = self.fc_family_names(&names);
+        if !list.contains(&DEFAULT_FONT_FAMILY) {
+            list.push(DEFAULT_FONT_FAMILY);
+        }
+        list.join(",")
+    }
+
     /// Font file bytes for a fontconfig match, served from the cache when possible.
     fn blob_for_path(&self, path: &str, index: u32) -> Result<FontBlob, FontError> {
         use std::collections::hash_map::Entry;
@@ -372,10 +391,17 @@ impl PangoFontSystem {
         let layout = create_layout(&cr);
         // 96 DPI matches the browser/CSS convention, same as the rasterizer.
         context_set_resolution(&layout.context(), 96.0);
+        // CSS sets the paragraph direction, rather than Pango guessing it from the text. With
+        // `auto_dir` off Pango also mirrors `Left`/`Right` alignment in an RTL layout.
+        layout.context().set_base_dir(match style.direction {
+            TextDirection::Ltr => pango::Direction::Ltr,
+            TextDirection::Rtl => pango::Direction::Rtl,
+        });
+        layout.set_auto_dir(false);
+        layout.context_changed();
 
-        let family = self.find_available_font(&style.family, &layout.context());
         let mut font_desc = pango::FontDescription::new();
-        font_desc.set_family(&family);
+        font_desc.set_family(&self.pango_family_list(&style.family));
         // CSS px → pt (× 72/96), then to Pango units (× SCALE).
         font_desc
             .set_size((style.size as f64 * style.display_scale as f64 * pango::SCALE as f64 * (72.0 / 96.0)) as i32);
@@ -383,11 +409,30 @@ impl PangoFontSystem {
         if style.style != FontStyle::Normal {
             font_desc.set_style(pango::Style::Italic);
         }
+        let axes = style.variation_axes();
+        if !axes.is_empty() {
+            // `FontDescription::from_string` parses the `@axis=value,…` variations suffix; merging
+            // that description in sets only the variations.
+            let spec = axes
+                .iter()
+                .map(|axis| format!("{}={}", axis.tag_str(), axis.value))
+                .collect::<Vec<_>>()
+                .join(",");
+            font_desc.merge(Some(&pango::FontDescription::from_string(&format!("@{spec}"))), true);
+        }
         layout.set_font_description(Some(&font_desc));
         layout.set_text(text);
+        let features = style.opentype_features();
+        if !features.is_empty() {
+            // Pango takes `font-feature-settings` syntax verbatim.
+            let css = features.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+            let attrs = pango::AttrList::new();
+            attrs.insert(pango::AttrFontFeatures::new(&css));
+            layout.set_attributes(Some(&attrs));
+        }
         layout.set_wrap(pango::WrapMode::Word);
         match style.align {
-            TextAlign::Start => {} // pango's default (left for LTR)
+            TextAlign::Start => {} // pango's default (the start edge)
             TextAlign::Center => layout.set_alignment(pango::Alignment::Center),
             TextAlign::End => layout.set_alignment(pango::Alignment::Right),
             TextAlign::Justify => layout.set_justify(true),
@@ -437,14 +482,18 @@ impl PangoFontSystem {
 
                 let glyph_string = run.glyph_string();
                 let infos = glyph_string.glyph_info();
+                // Log clusters are byte offsets into the run's item.
+                let item_offset = run.item().offset().max(0) as u32;
+                let clusters = glyph_string.log_clusters();
                 let mut glyphs = Vec::with_capacity(infos.len());
                 let mut pen_x = 0.0f32;
-                for info in infos {
+                for (i, info) in infos.iter().enumerate() {
                     let geometry = info.geometry();
                     glyphs.push(ShapedGlyph {
                         id: info.glyph(),
                         x: run_x + pen_x + geometry.x_offset() as f32 / scale,
                         y: baseline + geometry.y_offset() as f32 / scale,
+                        cluster: item_offset + clusters.get(i).map_or(0, |&c| c.max(0) as u32),
                     });
                     pen_x += geometry.width() as f32 / scale;
                 }
@@ -471,6 +520,7 @@ impl PangoFontSystem {
                         style: style.style,
                         weight: style.weight,
                         stretch: style.stretch,
+                        codepoint: None,
                     };
                     if let Ok(font) = self.resolve(&query) {
                         runs.push(ShapedRun {
@@ -481,6 +531,7 @@ impl PangoFontSystem {
                             width: pen_x,
                             metrics,
                             glyphs,
+                            rotation: GlyphRotation::None,
                         });
                     }
                 }
@@ -560,6 +611,12 @@ impl FontSystem for PangoFontSystem {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        if style.writing_mode.is_vertical() {
+            return shape_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return shape_broken(self, text, style);
+        }
         let Some(layout) = self.build_layout(text, style) else {
             return ShapedText::empty();
         };
@@ -570,6 +627,12 @@ impl FontSystem for PangoFontSystem {
         if text.is_empty() {
             return (0.0, 0.0);
         }
+        if style.writing_mode.is_vertical() {
+            return measure_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return measure_broken(self, text, style);
+        }
         self.measure_inner(text, style)
             .unwrap_or_else(|| (text.chars().count() as f32 * style.size * 0.5, style.size * 1.2))
     }
@@ -655,6 +718,7 @@ fn get_system_ui_font_from_gsettings() -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gosub_interface::font_system::{FontFeature, FontKerning};
 
     /// Exercises the full registration path end-to-end: temp-file write plus the fontconfig
     /// FFI (`FcConfigGetCurrent` / `FcConfigAppFontAddFile` / `FcConfigBuildFonts`). Uses the
@@ -705,4 +769,27 @@ mod tests {
             shaped.height
         );
     }
+
+    #[test]
+    fn family_list_keeps_every_family() {
+        let fs = PangoFontSystem::new();
+        assert_eq!(
+            fs.pango_family_list("\"Source Serif 4\", 'Noto Serif CJK JP', system-ui, serif"),
+            "Source Serif 4,Noto Serif CJK JP,serif,sans"
+        );
+    }
+
+    #[test]
+    fn shapes_with_features_and_variations() {
+        let mut fs = PangoFontSystem::new();
+        let mut style = TextStyle::new("sans-serif", 16.0);
+        style.kerning = FontKerning::None;
+        style.features = FontFeature::from_variant("small-caps tabular-nums");
+        style.variations = vec![gosub_interface::font_system::FontVariation::new(b"wght", 650.0)];
+        let shaped = fs.shape("Hello 123", &style);
+        assert!(
+            !shaped.runs.is_empty(),
+            "feature/variation settings must not break shaping"
+        );
+    }
 }
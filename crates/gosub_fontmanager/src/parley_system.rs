@@ -1,12 +1,13 @@
 use cow_utils::CowUtils;
 use gosub_interface::font::{FontBlob, FontError, FontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontStretch, FontSystem, FontWeight, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText,
-    TextAlign, TextStyle,
+    measure_broken, measure_vertical, shape_broken, shape_vertical, FontQuery, FontStretch, FontSystem, FontWeight,
+    GlyphRotation, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextStyle, UnicodeRange,
 };
-use parley::fontique::{Attributes, FontWidth, GenericFamily, QueryFamily, QueryStatus, SourceCache};
-use parley::style::{FontStyle as ParleyStyle, FontWeight as ParleyWeight};
+use parley::fontique::{Attributes, FontInfoOverride, FontWidth, GenericFamily, QueryFamily, QueryStatus, SourceCache};
+use parley::style::{FontFeatures, FontStyle as ParleyStyle, FontVariations, FontWeight as ParleyWeight};
 use parley::{Alignment, AlignmentOptions, FontContext, LayoutContext, PositionedLayoutItem};
+use std::collections::HashMap;
 
 /// A [`FontSystem`] implementation backed by Parley + Fontique.
 ///
@@ -20,6 +21,16 @@ pub struct ParleyFontSystem {
     font_cx: FontContext,
     layout_cx: LayoutContext<()>,
     source_cache: SourceCache,
+    /// `@font-face` subsets per web font family (keyed lowercase), in registration order.
+    web_faces: HashMap<String, Vec<WebFace>>,
+}
+
+/// One `unicode-range` subset of a web font family. Fontique picks a single best face per
+/// family, so each distinct range is registered under its own alias family; resolution maps the
+/// CSS family to the alias whose range covers the queried code point.
+struct WebFace {
+    range: UnicodeRange,
+    alias: String,
 }
 
 impl std::fmt::Debug for ParleyFontSystem {
@@ -50,6 +61,7 @@ impl ParleyFontSystem {
             font_cx,
             layout_cx: LayoutContext::new(),
             source_cache: SourceCache::new_shared(),
+            web_faces: HashMap::new(),
         }
     }
 }
@@ -62,19 +74,150 @@ impl ParleyFontSystem {
     pub fn font_cx_mut(&mut self) -> &mut FontContext {
         &mut self.font_cx
     }
+
+    fn register_as(&mut self, data: Vec<u8>, family: &str) {
+        let info = FontInfoOverride {
+            family_name: Some(family),
+            ..Default::default()
+        };
+        self.font_cx.collection.register_fonts(data.into(), Some(info));
+    }
+
+    /// Expand web font families in `families` into their subset aliases. With a `codepoint`,
+    /// only the subsets covering it are kept (a family with none is skipped, so resolution moves
+    /// on to the next family); without one, every subset is kept in registration order.
+    fn expand_web_families<'a>(
+        web_faces: &'a HashMap<String, Vec<WebFace>>,
+        families: &[&'a str],
+        codepoint: Option<char>,
+    ) -> Vec<&'a str> {
+        let mut out = Vec::with_capacity(families.len());
+        for &family in families {
+            let Some(faces) = web_faces.get(family.cow_to_lowercase().as_ref()) else {
+                out.push(family);
+                continue;
+            };
+            out.extend(
+                faces
+                    .iter()
+                    .filter(|face| codepoint.is_none_or(|c| face.range.contains(c)))
+                    .map(|face| face.alias.as_str()),
+            );
+        }
+        out
+    }
+
+    /// The CSS family list to shape with: every family of the `font-family` value in order,
+    /// web fonts expanded into all their subsets, then the `emoji` generic. Parley falls back
+    /// per cluster along this list, so a glyph missing from the first family comes from the next
+    /// family that has it before the platform fallback (CJK, symbols) is consulted.
+    fn shaping_families(&self, css_families: &str) -> String {
+        let mut families = split_css_families(css_families);
+        if !families.iter().any(|f| f.eq_ignore_ascii_case("emoji")) {
+            families.push("emoji");
+        }
+        Self::expand_web_families(&self.web_faces, &families, None)
+            .into_iter()
+            .map(|f| match css_family_to_query(f) {
+                QueryFamily::Generic(_) => f.to_string(),
+                _ => format!("\"{f}\""),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Lay out `text` in `style` with the face selectors `weight`/`font_style`. Shared by
+    /// measuring and shaping so the two can't disagree.
+    fn build_layout(
+        &mut self,
+        text: &str,
+        style: &TextStyle,
+        weight: FontWeight,
+        font_style: FontStyle,
+    ) -> parley::Layout<()> {
+        // Parley takes the paragraph direction from the text itself; a leading mark pins it.
+        let text = style.direction.with_base_mark(text);
+        let text = text.as_ref();
+        let shaping_families = self.shaping_families(&style.family);
+        let features = css_list(&style.opentype_features());
+        let variations = css_list(&style.variation_axes());
+
+        let mut builder = self
+            .layout_cx
+            .ranged_builder(&mut self.font_cx, text, style.display_scale, false);
+        builder.push_default(parley::StyleProperty::FontSize(style.size));
+        builder.push_default(parley::StyleProperty::FontFamily(parley::FontFamily::Source(
+            shaping_families.into(),
+        )));
+        builder.push_default(parley::StyleProperty::FontWeight(ParleyWeight::new(weight.0 as f32)));
+        builder.push_default(parley::StyleProperty::FontStyle(style_to_parley(font_style)));
+        if !features.is_empty() {
+            builder.push_default(parley::StyleProperty::FontFeatures(FontFeatures::Source(
+                features.into(),
+            )));
+        }
+        if !variations.is_empty() {
+            builder.push_default(parley::StyleProperty::FontVariations(FontVariations::Source(
+                variations.into(),
+            )));
+        }
+        if let Some(lh) = style.line_height {
+            builder.push_default(parley::StyleProperty::LineHeight(parley::LineHeight::Absolute(lh)));
+        }
+        // Applied during measurement too - shaping without it would draw narrower than the
+        // layout box that measurement reserved.
+        if style.letter_spacing != 0.0 {
+            builder.push_default(parley::StyleProperty::LetterSpacing(style.letter_spacing));
+        }
+        builder.push_default(parley::StyleProperty::Brush(()));
+
+        let mut layout = builder.build(text);
+        layout.break_all_lines(Some(style.max_width.unwrap_or(f32::INFINITY)));
+        layout
+    }
 }
 
 impl FontSystem for ParleyFontSystem {
-    fn register_font(&mut self, data: Vec<u8>, _family_override: Option<&str>) -> Result<(), FontError> {
-        // fontique derives the family name from the font's own `name` table;
-        // custom name overrides are not yet supported here.
-        self.font_cx.collection.register_fonts(data.into(), None);
+    fn register_font(&mut self, data: Vec<u8>, family_override: Option<&str>) -> Result<(), FontError> {
+        match family_override {
+            Some(family) => self.register_as(data, family),
+            // fontique derives the family name from the font's own `name` table.
+            None => {
+                self.font_cx.collection.register_fonts(data.into(), None);
+            }
+        }
         Ok(())
     }
 
-    /// Resolve a CSS font query to a concrete font + its bytes via fontique.
+    /// Register a web font subset under the alias of its `(family, range)`. Faces of one family
+    /// that share a range (weights, styles) share an alias, so fontique still matches between them.
+    fn register_font_face(&mut self, data: Vec<u8>, family: &str, range: &UnicodeRange) -> Result<(), FontError> {
+        let next_alias = format!("gosub-webfont-{}", self.web_faces.values().map(Vec::len).sum::<usize>());
+        let faces = self
+            .web_faces
+            .entry(family.cow_to_lowercase().into_owned())
+            .or_default();
+        let alias = match faces.iter().find(|face| face.range == *range) {
+            Some(face) => face.alias.clone(),
+            None => {
+                faces.push(WebFace {
+                    range: range.clone(),
+                    alias: next_alias.clone(),
+                });
+                next_alias
+            }
+        };
+        self.register_as(data, &alias);
+        Ok(())
+    }
+
+    /// Resolve a CSS font query to a concrete font + its bytes via fontique. Web font families
+    /// resolve to the subset covering `query.codepoint`.
     fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
-        let families: Vec<QueryFamily> = query.families.iter().map(|&name| css_family_to_query(name)).collect();
+        let families: Vec<QueryFamily> = Self::expand_web_families(&self.web_faces, query.families, query.codepoint)
+            .into_iter()
+            .map(css_family_to_query)
+            .collect();
 
         let attrs = Attributes::new(
             stretch_to_width(query.stretch),
@@ -121,17 +264,25 @@ impl FontSystem for ParleyFontSystem {
     }
 
     /// Shape `text` into positioned glyph runs, resolving `style.family` first so shaping starts
-    /// from the same concrete font that [`FontSystem::measure`] used.
+    /// from the same concrete font that [`FontSystem::measure`] used. Clusters that font can't
+    /// render fall back along the rest of the `font-family` list, then to emoji and platform fonts.
     fn shape(&mut self, text: &str, style: &TextStyle) -> ShapedText {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        if style.writing_mode.is_vertical() {
+            return shape_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return shape_broken(self, text, style);
+        }
         let families = split_css_families(&style.family);
         let query = FontQuery {
             families: &families,
             style: style.style,
             weight: style.weight,
             stretch: style.stretch,
+            codepoint: text.chars().find(|c| !c.is_whitespace()),
         };
         let Ok(font) = self.resolve(&query) else {
             return ShapedText::empty();
@@ -147,38 +298,25 @@ impl FontSystem for ParleyFontSystem {
         if text.is_empty() {
             return (0.0, 0.0);
         }
+        if style.writing_mode.is_vertical() {
+            return measure_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return measure_broken(self, text, style);
+        }
         let families = split_css_families(&style.family);
         let query = FontQuery {
             families: &families,
             style: style.style,
             weight: style.weight,
             stretch: style.stretch,
+            codepoint: text.chars().find(|c| !c.is_whitespace()),
         };
         let Ok(resolved) = self.resolve(&query) else {
             return (text.chars().count() as f32 * style.size * 0.5, style.size * 1.2);
         };
 
-        let mut builder = self
-            .layout_cx
-            .ranged_builder(&mut self.font_cx, text, style.display_scale, false);
-        builder.push_default(parley::StyleProperty::FontSize(style.size));
-        builder.push_default(parley::StyleProperty::FontFamily(parley::FontFamily::Source(
-            resolved.family.as_str().into(),
-        )));
-        builder.push_default(parley::StyleProperty::FontWeight(ParleyWeight::new(
-            style.weight.0 as f32,
-        )));
-        builder.push_default(parley::StyleProperty::FontStyle(style_to_parley(style.style)));
-        if let Some(lh) = style.line_height {
-            builder.push_default(parley::StyleProperty::LineHeight(parley::LineHeight::Absolute(lh)));
-        }
-        if style.letter_spacing != 0.0 {
-            builder.push_default(parley::StyleProperty::LetterSpacing(style.letter_spacing));
-        }
-        builder.push_default(parley::StyleProperty::Brush(()));
-
-        let mut layout = builder.build(text);
-        layout.break_all_lines(Some(style.max_width.unwrap_or(f32::INFINITY)));
+        let layout = self.build_layout(text, style, resolved.weight, resolved.style);
 
         let mut width = 0.0f32;
         let mut height = 0.0f32;
@@ -197,37 +335,18 @@ impl FontSystem for ParleyFontSystem {
 
 impl ParleyFontSystem {
     /// Shape `text` with an already-resolved font. Layout parameters (size, line height, wrap
-    /// width, letter spacing, display scale) come from `style`; the font identity comes from
-    /// `font` - which is why measurement and drawing agree when both go through this path.
+    /// width, letter spacing, features, display scale) and the fallback family list come from
+    /// `style`; the face selectors come from `font` - which is why measurement and drawing agree
+    /// when both go through this path.
     fn shape_resolved(&mut self, text: &str, font: &ResolvedFont, style: &TextStyle) -> ShapedText {
         if text.is_empty() {
             return ShapedText::empty();
         }
 
-        let mut builder = self
-            .layout_cx
-            .ranged_builder(&mut self.font_cx, text, style.display_scale, false);
-        builder.push_default(parley::StyleProperty::FontSize(style.size));
-        builder.push_default(parley::StyleProperty::FontFamily(parley::FontFamily::Source(
-            font.family.as_str().into(),
-        )));
-        builder.push_default(parley::StyleProperty::FontWeight(ParleyWeight::new(
-            font.weight.0 as f32,
-        )));
-        builder.push_default(parley::StyleProperty::FontStyle(style_to_parley(font.style)));
-        if let Some(lh) = style.line_height {
-            builder.push_default(parley::StyleProperty::LineHeight(parley::LineHeight::Absolute(lh)));
-        }
-        // Applied during measurement too - shaping without it would draw narrower than the
-        // layout box that measurement reserved.
-        if style.letter_spacing != 0.0 {
-            builder.push_default(parley::StyleProperty::LetterSpacing(style.letter_spacing));
-        }
-        builder.push_default(parley::StyleProperty::Brush(()));
-
-        let mut layout = builder.build(text);
-        layout.break_all_lines(Some(style.max_width.unwrap_or(f32::INFINITY)));
+        let mut layout = self.build_layout(text, style, font.weight, font.style);
         layout.align(to_parley_alignment(style.align), AlignmentOptions::default());
+        // Cluster ranges index the laid-out text, which may start with a base mark.
+        let mark = style.direction.with_base_mark(text).len() - text.len();
 
         let mut runs: Vec<ShapedRun> = Vec::new();
         let mut pen_y = 0.0f32;
@@ -252,13 +371,25 @@ impl ParleyFontSystem {
                     let run_x = run.offset();
                     let mut pen_x = 0.0f32;
 
+                    // The layout has a single style, so every glyph run spans its whole `Run`,
+                    // whose visual clusters give `GlyphRun::glyphs` along with their text.
                     let glyphs: Vec<ShapedGlyph> = run
-                        .glyphs()
-                        .map(|g| {
+                        .run()
+                        .visual_clusters()
+                        .flat_map(|cluster| {
+                            let start = cluster.text_range().start.saturating_sub(mark) as u32;
+                            cluster.glyphs().map(move |g| (g, start))
+                        })
+                        .map(|(g, cluster)| {
                             let x = run_x + pen_x + g.x;
                             let y = pen_y + baseline + g.y;
                             pen_x += g.advance;
-                            ShapedGlyph { id: g.id, x, y }
+                            ShapedGlyph {
+                                id: g.id,
+                                x,
+                                y,
+                                cluster,
+                            }
                         })
                         .collect();
 
@@ -293,6 +424,7 @@ impl ParleyFontSystem {
                                 strikethrough_size: pm.strikethrough_size,
                             },
                             glyphs,
+                            rotation: GlyphRotation::None,
                         });
                     }
                 }
@@ -333,6 +465,11 @@ pub fn split_css_families(families: &str) -> Vec<&str> {
 
 // Conversion helpers
 
+/// A comma-separated CSS settings list (`"liga" 0, "smcp" 1`), the syntax parley parses.
+fn css_list<T: std::fmt::Display>(settings: &[T]) -> String {
+    settings.iter().map(T::to_string).collect::<Vec<_>>().join(", ")
+}
+
 fn css_family_to_query(name: &str) -> QueryFamily<'_> {
     match name.cow_to_lowercase().as_ref() {
         "sans-serif" => GenericFamily::SansSerif.into(),
@@ -345,6 +482,7 @@ fn css_family_to_query(name: &str) -> QueryFamily<'_> {
         "ui-serif" => GenericFamily::UiSerif.into(),
         "ui-monospace" => GenericFamily::UiMonospace.into(),
         "ui-rounded" => GenericFamily::UiRounded.into(),
+        "emoji" => GenericFamily::Emoji.into(),
         _ => QueryFamily::Named(name),
     }
 }
@@ -385,6 +523,7 @@ fn to_parley_alignment(align: TextAlign) -> Alignment {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gosub_interface::font_system::{FontKerning, TextDirection};
 
     /// `families()` must list every resolvable family: the bundled Roboto (registered in
     /// `new()`) proves registered fonts are included, sortedness proves the ordering contract.
@@ -435,4 +574,79 @@ mod tests {
             "letter-spacing should widen the measurement: {base_width} -> {spaced_width}"
         );
     }
+
+    /// Subsets of one web font family are told apart by `unicode-range`: a code point inside a
+    /// subset's range resolves to that subset, one outside every range falls through to the next
+    /// family in the list.
+    #[test]
+    fn web_font_subsets_resolve_per_codepoint() {
+        let mut fs = ParleyFontSystem::new();
+        let latin = UnicodeRange::parse("U+0000-00FF").unwrap();
+        let cyrillic = UnicodeRange::parse("U+0400-04FF").unwrap();
+        fs.register_font_face(gosub_shared::ROBOTO_FONT.to_vec(), "Web Sans", &latin)
+            .unwrap();
+        fs.register_font_face(gosub_shared::ROBOTO_FONT.to_vec(), "Web Sans", &cyrillic)
+            .unwrap();
+
+        let families = ["Web Sans", "sans-serif"];
+        let mut query = FontQuery::new(&families);
+        query.codepoint = Some('A');
+        let latin_face = fs.resolve(&query).unwrap().family;
+        query.codepoint = Some('Ж');
+        let cyrillic_face = fs.resolve(&query).unwrap().family;
+        assert_ne!(latin_face, cyrillic_face, "each subset is its own face");
+
+        query.codepoint = Some('Ω');
+        let greek_face = fs.resolve(&query).unwrap().family;
+        assert!(
+            greek_face != latin_face && greek_face != cyrillic_face,
+            "no subset covers Greek, so the generic fallback is used"
+        );
+    }
+
+    /// `font-kerning: none` must reach the shaper: Roboto kerns the `AV` pair tighter.
+    #[test]
+    fn font_kerning_none_disables_kerning() {
+        let mut fs = ParleyFontSystem::new();
+        let mut style = TextStyle::new("Roboto", 32.0);
+        let (kerned, _) = fs.measure("AVAVAV", &style);
+        style.kerning = FontKerning::None;
+        let (unkerned, _) = fs.measure("AVAVAV", &style);
+        assert!(
+            unkerned > kerned,
+            "kerning off must widen AV pairs: {kerned} -> {unkerned}"
+        );
+    }
+
+    #[test]
+    fn shaping_stack_walks_the_whole_family_list() {
+        let mut fs = ParleyFontSystem::new();
+        fs.register_font_face(gosub_shared::ROBOTO_FONT.to_vec(), "Web Sans", &UnicodeRange::all())
+            .unwrap();
+        assert_eq!(
+            fs.shaping_families("'Web Sans', Noto Sans CJK JP, serif"),
+            "\"gosub-webfont-0\", \"Noto Sans CJK JP\", serif, sans-serif, emoji"
+        );
+    }
+
+    /// `direction: rtl` puts a start-aligned line against the right edge of its wrap width, even
+    /// when the text itself has no RTL characters to go by.
+    #[test]
+    fn rtl_direction_starts_lines_at_the_right_edge() {
+        let mut fs = ParleyFontSystem::new();
+        let mut style = TextStyle::new("Roboto", 16.0);
+        style.max_width = Some(400.0);
+        let left_edge = |shaped: &ShapedText| shaped.runs.iter().map(|r| r.x).fold(f32::INFINITY, f32::min);
+
+        let ltr = fs.shape("Hello", &style);
+        style.direction = TextDirection::Rtl;
+        let rtl = fs.shape("Hello", &style);
+        assert!(left_edge(&ltr) < 1.0, "LTR starts at the left: {}", left_edge(&ltr));
+        assert!(
+            left_edge(&rtl) > 399.0 - ltr.width,
+            "RTL ends at the right: {} + {}",
+            left_edge(&rtl),
+            ltr.width
+        );
+    }
 }
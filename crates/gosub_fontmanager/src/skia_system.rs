@@ -6,12 +6,14 @@
 
 use gosub_interface::font::{FontBlob, FontError, FontStyle as CssFontStyle};
 use gosub_interface::font_system::{
-    FontQuery, FontSystem, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign as GosubTextAlign,
-    TextStyle as GosubTextStyle,
+    measure_broken, measure_vertical, shape_broken, shape_vertical, FontQuery, FontSystem, FontVariation,
+    GlyphRotation, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun, ShapedText, TextAlign as GosubTextAlign,
+    TextDirection as GosubTextDirection, TextStyle as GosubTextStyle,
 };
 use parking_lot::Mutex;
 use skia_safe::textlayout::{
-    FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextAlign, TextStyle, TypefaceFontProvider,
+    FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextAlign, TextDirection, TextStyle,
+    TypefaceFontProvider,
 };
 use skia_safe::{FontMgr, FontStyle};
 use std::cell::RefCell;
@@ -315,6 +317,28 @@ fn to_skia_slant(style: CssFontStyle) -> skia_safe::font_style::Slant {
     }
 }
 
+/// The variable-font axis positions for `style`. An explicit position replaces the whole
+/// instance, so `wght` is pinned to `font-weight` unless the style sets it - otherwise a
+/// registered per-weight instance (see [`weight_instances`]) would reset to the default weight.
+fn variation_coordinates(style: &GosubTextStyle) -> Vec<skia_safe::font_arguments::variation_position::Coordinate> {
+    use skia_safe::font_arguments::variation_position::Coordinate;
+    use skia_safe::FourByteTag;
+
+    let mut axes = style.variation_axes();
+    if axes.is_empty() {
+        return Vec::new();
+    }
+    if !axes.iter().any(|axis| &axis.tag == b"wght") {
+        axes.push(FontVariation::new(b"wght", style.weight.0 as f32));
+    }
+    axes.iter()
+        .map(|axis| Coordinate {
+            axis: FourByteTag::new(u32::from_be_bytes(axis.tag)),
+            value: axis.value,
+        })
+        .collect()
+}
+
 /// Build and lay out the measurement/shaping paragraph for `text` in `style`.
 ///
 /// This is the single source of truth for how a [`GosubTextStyle`] maps onto Skia's textlayout -
@@ -328,6 +352,10 @@ fn build_style_paragraph(fc: &FontCollection, text: &str, style: &GosubTextStyle
         GosubTextAlign::End => TextAlign::End,
         GosubTextAlign::Justify => TextAlign::Justify,
     });
+    paragraph_style.set_text_direction(match style.direction {
+        GosubTextDirection::Ltr => TextDirection::LTR,
+        GosubTextDirection::Rtl => TextDirection::RTL,
+    });
     let mut builder = ParagraphBuilder::new(&paragraph_style, fc.clone());
 
     let mut ts = TextStyle::new();
@@ -347,13 +375,24 @@ fn build_style_paragraph(fc: &FontCollection, text: &str, style: &GosubTextStyle
         ts.set_letter_spacing(style.letter_spacing);
     }
     // Pass the pruned family list so Skia's FontCollection reaches the real generic instead
-    // of letting an unavailable leading family capture the platform default.
+    // of letting an unavailable leading family capture the platform default. textlayout falls
+    // back per glyph along this list, then to the font manager's per-character match (emoji, CJK).
     ts.set_font_families(&resolve_family_list(&style.family));
     ts.set_font_style(FontStyle::new(
         skia_safe::font_style::Weight::from(style.weight.0 as i32),
         skia_safe::font_style::Width::NORMAL,
         to_skia_slant(style.style),
     ));
+    for feature in style.opentype_features() {
+        ts.add_font_feature(feature.tag_str(), feature.value as i32);
+    }
+    let coordinates = variation_coordinates(style);
+    if !coordinates.is_empty() {
+        let position = skia_safe::font_arguments::VariationPosition {
+            coordinates: &coordinates,
+        };
+        ts.set_font_arguments(&skia_safe::FontArguments::new().set_variation_design_position(position));
+    }
     builder.push_style(&ts);
     builder.add_text(text);
 
@@ -445,6 +484,12 @@ impl FontSystem for SkiaFontSystem {
         if text.is_empty() {
             return ShapedText::empty();
         }
+        if style.writing_mode.is_vertical() {
+            return shape_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return shape_broken(self, text, style);
+        }
         with_font_collection(|fc| {
             let mut paragraph = build_style_paragraph(fc, text, style);
             let width = paragraph.longest_line();
@@ -466,10 +511,12 @@ impl FontSystem for SkiaFontSystem {
                     .glyphs()
                     .iter()
                     .zip(info.positions())
-                    .map(|(glyph, pos)| ShapedGlyph {
+                    .zip(info.utf8_starts())
+                    .map(|((glyph, pos), &cluster)| ShapedGlyph {
                         id: u32::from(*glyph),
                         x: origin.x + pos.x,
                         y: origin.y + pos.y,
+                        cluster,
                     })
                     .collect();
                 if glyphs.is_empty() {
@@ -499,6 +546,7 @@ impl FontSystem for SkiaFontSystem {
                     width: info.advance_x(),
                     metrics,
                     glyphs,
+                    rotation: GlyphRotation::None,
                 });
             });
 
@@ -516,6 +564,12 @@ impl FontSystem for SkiaFontSystem {
         if text.is_empty() {
             return (0.0, 0.0);
         }
+        if style.writing_mode.is_vertical() {
+            return measure_vertical(self, text, style);
+        }
+        if style.needs_line_breaker(text) {
+            return measure_broken(self, text, style);
+        }
         with_font_collection(|fc| {
             let paragraph = build_style_paragraph(fc, text, style);
             (paragraph.longest_line(), paragraph.height())
@@ -545,6 +599,7 @@ fn width_from_css_percent(pct: i32) -> skia_safe::font_style::Width {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gosub_interface::font_system::FontKerning;
 
     /// `families()` must merge both sources: the platform font manager (non-empty on any
     /// machine with fonts) and the process-global web-font registry.
@@ -649,4 +704,30 @@ mod tests {
             skia_safe::font_style::Width::ULTRA_EXPANDED
         );
     }
+
+    #[test]
+    fn font_kerning_none_disables_kerning() {
+        let mut fs = SkiaFontSystem;
+        fs.register_font(gosub_shared::ROBOTO_FONT.to_vec(), Some("Gosub Kerning Test"))
+            .expect("bundled Roboto must register");
+        let mut style = GosubTextStyle::new("Gosub Kerning Test", 32.0);
+        let (kerned, _) = fs.measure("AVAVAV", &style);
+        style.kerning = FontKerning::None;
+        let (unkerned, _) = fs.measure("AVAVAV", &style);
+        assert!(
+            unkerned > kerned,
+            "kerning off must widen AV pairs: {kerned} -> {unkerned}"
+        );
+    }
+
+    #[test]
+    fn variable_axes_pin_the_css_weight() {
+        let mut style = GosubTextStyle::new("serif", 20.0);
+        style.weight = gosub_interface::font_system::FontWeight::BOLD;
+        let axes: Vec<_> = variation_coordinates(&style).iter().map(|c| c.value).collect();
+        assert_eq!(axes, vec![20.0, 700.0], "opsz from the size, wght from font-weight");
+
+        style.optical_sizing = false;
+        assert!(variation_coordinates(&style).is_empty());
+    }
 }
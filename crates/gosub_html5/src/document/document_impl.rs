@@ -1,12 +1,13 @@
 use core::fmt::Debug;
 use gosub_interface::css3::CssSystem;
-use gosub_interface::document::{Document, DocumentType};
+use gosub_interface::document::{Document, DocumentType, DomMutation};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use url::Url;
 
+use crate::document::query::{parse_selector, select, SelectorIndex};
 use crate::document::task_queue::is_valid_id_attribute_value;
 use crate::node::arena::NodeArena;
 use crate::node::data::comment::CommentData;
@@ -28,10 +29,34 @@ pub struct DocumentImpl<C: HasDocument> {
     /// Reverse index of `named_id_elements`: which ids each node is registered under.
     /// Kept in sync so unregistering a node does not require scanning the whole map.
     named_ids_by_node: HashMap<NodeId, Vec<String>>,
+    /// Elements by id and class, used to speed up selector queries
+    selector_index: SelectorIndex,
     pub doctype: DocumentType,
     pub quirks_mode: QuirksMode,
     pub stylesheets: Vec<<C::CssSystem as CssSystem>::Stylesheet>,
     hovered_nodes: parking_lot::RwLock<std::collections::HashSet<NodeId>>,
+    /// Mutations recorded since the last `take_mutations()`, or `None` while not recording
+    mutations: Option<Vec<DomMutation>>,
+}
+
+impl<C: HasDocument> Clone for DocumentImpl<C>
+where
+    <C::CssSystem as CssSystem>::Stylesheet: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            arena: self.arena.clone(),
+            named_id_elements: self.named_id_elements.clone(),
+            named_ids_by_node: self.named_ids_by_node.clone(),
+            selector_index: self.selector_index.clone(),
+            doctype: self.doctype,
+            quirks_mode: self.quirks_mode,
+            stylesheets: self.stylesheets.clone(),
+            hovered_nodes: parking_lot::RwLock::new(self.hovered_nodes.read().clone()),
+            mutations: self.mutations.clone(),
+        }
+    }
 }
 
 impl<C: HasDocument> PartialEq for DocumentImpl<C> {
@@ -54,10 +79,12 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             arena: NodeArena::new(),
             named_id_elements: HashMap::new(),
             named_ids_by_node: HashMap::new(),
+            selector_index: SelectorIndex::default(),
             doctype: document_type,
             quirks_mode: QuirksMode::NoQuirks,
             stylesheets: Vec::new(),
             hovered_nodes: parking_lot::RwLock::new(std::collections::HashSet::new()),
+            mutations: None,
         };
         let root = NodeImpl::new_document(Location::default(), QuirksMode::NoQuirks);
         doc.arena.register_node(root);
@@ -139,19 +166,30 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
 
     fn attach(&mut self, node: NodeId, parent: NodeId, position: Option<usize>) {
         self.attach_node(node, parent, position);
+        self.record(DomMutation::ChildList { parent });
     }
 
     fn detach(&mut self, node: NodeId) {
+        if let Some(parent) = self.parent(node) {
+            self.record(DomMutation::ChildList { parent });
+        }
         self.detach_node(node);
     }
 
     fn remove(&mut self, node: NodeId) {
+        if let Some(parent) = self.parent(node) {
+            self.record(DomMutation::ChildList { parent });
+        }
         self.delete_node_by_id(node);
     }
 
     fn relocate_node(&mut self, node: NodeId, parent: NodeId) {
+        if let Some(old_parent) = self.parent(node) {
+            self.record(DomMutation::ChildList { parent: old_parent });
+        }
         self.detach_node(node);
         self.attach_node(node, parent, None);
+        self.record(DomMutation::ChildList { parent });
     }
 
     // ── node type ──────────────────────────────────────────────────────────
@@ -191,6 +229,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
     }
 
     fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) {
+        let old_value = self.attribute(id, name).map(str::to_owned);
         let is_element = if let Some(node) = self.arena.node_ref_mut(id) {
             if let NodeDataTypeInternal::Element(ref mut e) = node.data {
                 e.add_attribute(name, value);
@@ -202,6 +241,21 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             false
         };
 
+        if is_element {
+            self.record(DomMutation::Attribute {
+                node: id,
+                name: name.to_owned(),
+                old_value,
+            });
+            match name {
+                "id" => self.selector_index.record_id(id, value),
+                "class" => value
+                    .split_whitespace()
+                    .for_each(|class| self.selector_index.record_class(id, class)),
+                _ => {}
+            }
+        }
+
         if is_element && name == "id" && is_valid_id_attribute_value(value) {
             if let Entry::Vacant(e) = self.named_id_elements.entry(value.to_string()) {
                 e.insert(id);
@@ -215,7 +269,15 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             return;
         };
         if let NodeDataTypeInternal::Element(ref mut e) = node.data {
+            let old_value = e.attributes.get(name).cloned();
             e.remove_attribute(name);
+            if old_value.is_some() {
+                self.record(DomMutation::Attribute {
+                    node: id,
+                    name: name.to_owned(),
+                    old_value,
+                });
+            }
         }
     }
 
@@ -225,6 +287,11 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         };
         if let NodeDataTypeInternal::Element(ref mut e) = node.data {
             e.add_class(class);
+            self.selector_index.record_class(id, class);
+            self.record(DomMutation::Class {
+                node: id,
+                name: class.to_owned(),
+            });
         }
     }
 
@@ -269,6 +336,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         };
         if let NodeDataTypeInternal::Text(ref mut t) = node.data {
             t.value = value.to_owned();
+            self.record(DomMutation::Text { node: id });
         }
     }
 
@@ -280,6 +348,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
             // In-place append: the backing String grows geometrically, so merging N adjacent
             // text runs into this node stays amortized O(total length) instead of O(N^2).
             t.value.push_str(value);
+            self.record(DomMutation::Text { node: id });
             true
         } else {
             false
@@ -356,6 +425,7 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
 
     fn add_stylesheet(&mut self, sheet: <C::CssSystem as CssSystem>::Stylesheet) {
         self.stylesheets.push(sheet);
+        self.record(DomMutation::Stylesheets);
     }
 
     // ── serialisation ──────────────────────────────────────────────────────
@@ -368,14 +438,58 @@ impl<C: HasDocument<Document = Self>> Document<C> for DocumentImpl<C> {
         crate::writer::DocumentWriter::write_from_node::<C>(node_id, self)
     }
 
+    fn inner_html(&self, node_id: NodeId) -> String {
+        crate::writer::DocumentWriter::html().inner::<C>(node_id, self)
+    }
+
+    fn outer_html(&self, node_id: NodeId) -> String {
+        crate::writer::DocumentWriter::html().outer::<C>(node_id, self)
+    }
+
+    fn outer_xml(&self, node_id: NodeId) -> String {
+        crate::writer::DocumentWriter::xml().outer::<C>(node_id, self)
+    }
+
     fn is_hovered(&self, id: NodeId) -> bool {
         self.hovered_nodes.read().contains(&id)
     }
+
+    // ── mutation records ───────────────────────────────────────────────────
+
+    fn record_mutations(&mut self, enabled: bool) {
+        if enabled {
+            self.mutations.get_or_insert_with(Vec::new);
+        } else {
+            self.mutations = None;
+        }
+    }
+
+    fn take_mutations(&mut self) -> Vec<DomMutation> {
+        self.mutations.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // ── selector queries ───────────────────────────────────────────────────
+
+    fn query_selector(&self, scope: NodeId, selectors: &str) -> gosub_shared::types::Result<Option<NodeId>> {
+        let selector = parse_selector(selectors)?;
+        Ok(select::<C>(self, scope, &selector, true).into_iter().next())
+    }
+
+    fn query_selector_all(&self, scope: NodeId, selectors: &str) -> gosub_shared::types::Result<Vec<NodeId>> {
+        let selector = parse_selector(selectors)?;
+        Ok(select::<C>(self, scope, &selector, false))
+    }
 }
 
 // ── Internal helpers (not part of Document trait) ───────────────────────────
 
 impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
+    fn record(&mut self, mutation: DomMutation) {
+        if let Some(mutations) = &mut self.mutations {
+            mutations.push(mutation);
+        }
+    }
+
     /// Update the set of hovered nodes to the ancestor chain of `leaf` (inclusive).
     /// Pass `None` to clear hover state. Uses interior mutability so it works through Arc.
     pub fn set_hovered_nodes(&self, leaf: Option<NodeId>) {
@@ -393,14 +507,24 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
     }
 
     fn on_document_node_mutation(&mut self, node: &NodeImpl) {
+        if let Some(data) = node.get_element_data() {
+            self.selector_index.record(node.id(), data);
+        }
         self.on_document_node_mutation_update_named_id(node);
     }
 
+    pub(crate) fn selector_index(&self) -> &SelectorIndex {
+        &self.selector_index
+    }
+
     /// Same as [`Self::on_document_node_mutation_update_named_id`], but looks the node up in the
     /// arena by id so callers that mutate nodes in place don't need a cloned `NodeImpl`.
     fn on_document_node_mutation_by_id(&mut self, node_id: NodeId) {
         let id_attr = match self.arena.node_ref(node_id).and_then(NodeImpl::get_element_data) {
-            Some(data) => data.attributes.get("id").cloned(),
+            Some(data) => {
+                self.selector_index.record(node_id, data);
+                data.attributes.get("id").cloned()
+            }
             None => return, // not an element
         };
         match id_attr {
@@ -568,6 +692,9 @@ impl<C: HasDocument<Document = Self>> DocumentImpl<C> {
                 self.on_document_node_mutation_by_id(parent_id);
             }
         }
+        if let Some(data) = self.arena.node_ref(node_id).and_then(NodeImpl::get_element_data) {
+            self.selector_index.forget(node_id, data);
+        }
         self.arena.delete_node(node_id);
     }
 
@@ -771,3 +898,74 @@ fn internal_visit<C: HasDocument<Document = DocumentImpl<C>>>(
     }
     visitor.document_leave(node);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Html5Parser;
+    use gosub_css3::system::Css3System;
+    use gosub_interface::config::ModuleConfiguration;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Config;
+
+    impl ModuleConfiguration for Config {
+        type CssSystem = Css3System;
+        type Document = DocumentImpl<Self>;
+        type HtmlParser = Html5Parser<'static, Self>;
+    }
+
+    #[test]
+    fn records_mutations_only_while_enabled() {
+        let mut doc = crate::html_compile::<Config>(r#"<div id="a" class="x"><p>text</p></div>"#);
+        let div = doc.node_by_named_id("a").expect("div");
+        let p = doc.children(div)[0];
+        let text = doc.children(p)[0];
+
+        doc.set_attribute(div, "title", "ignored");
+        assert!(doc.take_mutations().is_empty());
+
+        doc.record_mutations(true);
+        doc.set_attribute(div, "class", "y");
+        doc.remove_attribute(div, "title");
+        doc.remove_attribute(div, "missing");
+        doc.add_class(p, "late");
+        doc.set_text_value(text, "changed");
+        let root = doc.root();
+        Document::<Config>::relocate_node(&mut doc, p, root);
+        assert_eq!(
+            doc.take_mutations(),
+            vec![
+                DomMutation::Attribute {
+                    node: div,
+                    name: "class".into(),
+                    old_value: Some("x".into()),
+                },
+                DomMutation::Attribute {
+                    node: div,
+                    name: "title".into(),
+                    old_value: Some("ignored".into()),
+                },
+                DomMutation::Class {
+                    node: p,
+                    name: "late".into(),
+                },
+                DomMutation::Text { node: text },
+                DomMutation::ChildList { parent: div },
+                DomMutation::ChildList { parent: root },
+            ]
+        );
+        assert!(doc.take_mutations().is_empty());
+
+        // A clone keeps the document and its recording state
+        let mut copy = doc.clone();
+        assert!(copy == doc);
+        copy.remove(text);
+        assert_eq!(copy.take_mutations(), vec![DomMutation::ChildList { parent: p }]);
+        assert!(doc.take_mutations().is_empty());
+
+        doc.record_mutations(false);
+        doc.set_text_value(text, "again");
+        assert!(doc.take_mutations().is_empty());
+    }
+}
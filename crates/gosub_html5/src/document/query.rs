@@ -1,16 +1,167 @@
-use crate::document::document_impl::TreeIterator;
+//! CSS selector queries against a document (`querySelector()` and `querySelectorAll()`).
+//!
+//! Selectors are parsed by `gosub_css3` and matched with the same matcher the styling code uses. To
+//! avoid matching every element on a large document, the document keeps an index of the elements
+//! carrying each id and class. When every alternative of a selector list names an id or class in its
+//! rightmost compound, only the indexed elements are checked.
+
+use crate::document::document_impl::DocumentImpl;
 use crate::errors::Error;
+use crate::node::data::element::ElementData;
 use crate::parser::query::{Condition, Query, SearchType};
+use gosub_css3::matcher::styling::matches_selector;
+use gosub_css3::stylesheet::{Combinator, CssSelector, CssSelectorPart, PseudoFunction};
+use gosub_css3::Css3;
 use gosub_interface::config::HasDocument;
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
 use gosub_shared::node::NodeId;
+use std::collections::{HashMap, HashSet};
+
+/// Elements by id and by class, used to narrow down selector queries.
+///
+/// Entries are added whenever an element gains an id or class, but are not removed when it loses
+/// one, so a lookup returns a superset of the elements that currently match. Queries check every
+/// candidate against the selector anyway.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SelectorIndex {
+    ids: HashMap<String, HashSet<NodeId>>,
+    classes: HashMap<String, HashSet<NodeId>>,
+}
+
+impl SelectorIndex {
+    /// Records the id and classes an element currently has
+    pub(crate) fn record(&mut self, node_id: NodeId, element: &ElementData) {
+        if let Some(id) = element.attribute("id") {
+            self.record_id(node_id, id);
+        }
+        for class in element.active_class_names() {
+            self.record_class(node_id, &class);
+        }
+    }
+
+    pub(crate) fn record_id(&mut self, node_id: NodeId, id: &str) {
+        self.ids.entry(id.to_string()).or_default().insert(node_id);
+    }
+
+    pub(crate) fn record_class(&mut self, node_id: NodeId, class: &str) {
+        self.classes.entry(class.to_string()).or_default().insert(node_id);
+    }
+
+    /// Drops a deleted element from the index
+    pub(crate) fn forget(&mut self, node_id: NodeId, element: &ElementData) {
+        if let Some(nodes) = element.attribute("id").and_then(|id| self.ids.get_mut(id)) {
+            nodes.remove(&node_id);
+        }
+        for class in element.active_class_names() {
+            if let Some(nodes) = self.classes.get_mut(&class) {
+                nodes.remove(&node_id);
+            }
+        }
+    }
+
+    /// Candidates for the alternatives of a selector list, or `None` when an alternative cannot be
+    /// narrowed down by id or class
+    fn candidates(&self, selector: &CssSelector) -> Option<HashSet<NodeId>> {
+        let mut candidates = HashSet::new();
+        for parts in &selector.parts {
+            let subject = parts
+                .iter()
+                .rposition(|p| matches!(p, CssSelectorPart::Combinator(c) if *c != Combinator::Namespace))
+                .map_or(parts.as_slice(), |idx| &parts[idx + 1..]);
+
+            let by_id = subject.iter().find_map(|p| match p {
+                CssSelectorPart::Id(id) => Some(self.ids.get(id)),
+                _ => None,
+            });
+            let by_class = || {
+                subject.iter().find_map(|p| match p {
+                    CssSelectorPart::Class(class) => Some(self.classes.get(class)),
+                    _ => None,
+                })
+            };
+            let nodes = by_id.or_else(by_class)?;
+            candidates.extend(nodes.into_iter().flatten().copied());
+        }
+        Some(candidates)
+    }
+}
+
+/// Parses a selector list for a query, reporting syntax errors as query errors
+pub fn parse_selector(selectors: &str) -> gosub_shared::types::Result<CssSelector> {
+    Css3::parse_selector_str(selectors)
+        .map_err(|e| Error::Query(format!("invalid selector {selectors:?}: {}", e.message)).into())
+}
+
+/// Returns the elements below `scope` that match the selector, in tree order. With `first_only` the
+/// search stops at the first match.
+pub fn select<C: HasDocument<Document = DocumentImpl<C>>>(
+    doc: &DocumentImpl<C>,
+    scope: NodeId,
+    selector: &CssSelector,
+    first_only: bool,
+) -> Vec<NodeId> {
+    let is_match = |id: NodeId| matches_selector::<C>(doc, id, selector, Some(scope));
+
+    let Some(candidates) = doc.selector_index().candidates(selector) else {
+        let mut found = Vec::new();
+        for id in descendants::<C>(doc, scope) {
+            if is_match(id) {
+                found.push(id);
+                if first_only {
+                    break;
+                }
+            }
+        }
+        return found;
+    };
+
+    let mut found: Vec<(Vec<usize>, NodeId)> = candidates
+        .into_iter()
+        .filter(|&id| doc.node_by_id(id).is_some() && is_match(id))
+        .filter_map(|id| Some((tree_position::<C>(doc, scope, id)?, id)))
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    if first_only {
+        found.truncate(1);
+    }
+    found.into_iter().map(|(_, id)| id).collect()
+}
+
+/// The element descendants of a node in tree order
+fn descendants<C: HasDocument>(doc: &C::Document, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    let mut stack: Vec<NodeId> = doc.children(node_id).iter().rev().copied().collect();
+    std::iter::from_fn(move || loop {
+        let id = stack.pop()?;
+        stack.extend(doc.children(id).iter().rev().copied());
+        if doc.node_type(id) == NodeType::ElementNode {
+            return Some(id);
+        }
+    })
+}
+
+/// Child indices leading from `scope` down to `node_id`, which sort in tree order. `None` when the
+/// node is not below `scope`.
+fn tree_position<C: HasDocument>(doc: &C::Document, scope: NodeId, node_id: NodeId) -> Option<Vec<usize>> {
+    let mut position = Vec::new();
+    let mut current = node_id;
+    while current != scope {
+        let parent = doc.parent(current)?;
+        position.push(doc.children(parent).iter().position(|&id| id == current)?);
+        current = parent;
+    }
+    if position.is_empty() {
+        return None;
+    }
+    position.reverse();
+    Some(position)
+}
 
 pub struct DocumentQuery<C: HasDocument> {
     _phantom: std::marker::PhantomData<C>,
 }
 
-impl<C: HasDocument> DocumentQuery<C> {
+impl<C: HasDocument<Document = DocumentImpl<C>>> DocumentQuery<C> {
     /// Perform a single query against the document.
     /// If query search type is uninitialized, returns an error.
     /// Otherwise, returns a vector of `NodeIds` that match the predicate in tree order (preorder depth-first.)
@@ -19,27 +170,11 @@ impl<C: HasDocument> DocumentQuery<C> {
             return Err(Error::Query("Query predicate is uninitialized".to_owned()).into());
         }
 
-        let tree_iterator = TreeIterator::<C>::new(doc);
-
-        let mut found_ids = Vec::new();
-        for current_node_id in tree_iterator {
-            let mut predicate_result: bool = true;
-            for condition in &query.conditions {
-                if !Self::matches_query_condition(doc, &current_node_id, condition) {
-                    predicate_result = false;
-                    break;
-                }
-            }
-
-            if predicate_result {
-                found_ids.push(current_node_id);
-                if query.search_type == SearchType::FindFirst {
-                    return Ok(found_ids);
-                }
-            }
-        }
+        let selector = Self::to_selector(query);
+        let first_only = query.search_type == SearchType::FindFirst;
 
-        Ok(found_ids)
+        // The document element itself can match too, so search from the document node
+        Ok(select::<C>(doc, doc.root(), &selector, first_only))
     }
 
     /// Check if a given node's children contain a certain tag name
@@ -52,30 +187,183 @@ impl<C: HasDocument> DocumentQuery<C> {
         false
     }
 
-    fn matches_query_condition(doc: &C::Document, current_node_id: &NodeId, condition: &Condition) -> bool {
-        if doc.node_type(*current_node_id) != NodeType::ElementNode {
-            return false;
-        }
+    /// Translates the conditions of a query into a selector: a compound for the conditions on the
+    /// element itself, `:has(> tag)` for child tags, and a parent compound joined with `>`.
+    fn to_selector(query: &Query) -> CssSelector {
+        let mut parent = Vec::new();
+        let mut subject = Vec::new();
 
-        match condition {
-            Condition::EqualsTag(tag) => doc.tag_name(*current_node_id) == Some(tag.as_str()),
-            Condition::EqualsId(id) => doc.attribute(*current_node_id, "id") == Some(id.as_str()),
-            Condition::ContainsClass(class_name) => {
-                if let Some(classes) = doc.attribute(*current_node_id, "class") {
-                    classes.split_whitespace().any(|c| c == class_name.as_str())
-                } else {
-                    false
+        for condition in &query.conditions {
+            match condition {
+                Condition::EqualsTag(tag) => subject.push(CssSelectorPart::Type(tag.clone())),
+                Condition::EqualsId(id) => subject.push(CssSelectorPart::Id(id.clone())),
+                Condition::ContainsClass(class) => subject.push(CssSelectorPart::Class(class.clone())),
+                Condition::ContainsAttribute(attribute) => {
+                    subject.push(CssSelectorPart::Attribute(Box::new(
+                        gosub_css3::stylesheet::AttributeSelector {
+                            name: attribute.clone(),
+                            ..Default::default()
+                        },
+                    )));
                 }
-            }
-            Condition::ContainsAttribute(attribute) => doc.attribute(*current_node_id, attribute).is_some(),
-            Condition::ContainsChildTag(child_tag) => Self::contains_child_tag(doc, *current_node_id, child_tag),
-            Condition::HasParentTag(parent_tag) => {
-                if let Some(parent_id) = doc.parent(*current_node_id) {
-                    doc.tag_name(parent_id) == Some(parent_tag.as_str())
-                } else {
-                    false
+                Condition::ContainsChildTag(tag) => {
+                    let child = CssSelector {
+                        parts: vec![vec![
+                            CssSelectorPart::Combinator(Combinator::Child),
+                            CssSelectorPart::Type(tag.clone()),
+                        ]],
+                    };
+                    subject.push(CssSelectorPart::PseudoFunction(Box::new(PseudoFunction::Selector(
+                        "has".to_string(),
+                        child,
+                    ))));
                 }
+                Condition::HasParentTag(tag) => parent.push(CssSelectorPart::Type(tag.clone())),
             }
         }
+
+        if subject.is_empty() {
+            subject.push(CssSelectorPart::Universal);
+        }
+        let parts = if parent.is_empty() {
+            subject
+        } else {
+            parent.push(CssSelectorPart::Combinator(Combinator::Child));
+            parent.extend(subject);
+            parent
+        };
+
+        CssSelector { parts: vec![parts] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Html5Parser;
+    use gosub_css3::system::Css3System;
+    use gosub_interface::config::ModuleConfiguration;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Config;
+
+    impl ModuleConfiguration for Config {
+        type CssSystem = Css3System;
+        type Document = DocumentImpl<Self>;
+        type HtmlParser = Html5Parser<'static, Self>;
+    }
+
+    const HTML: &str = r#"<!DOCTYPE html>
+        <div id="main" class="box">
+            <ul><li class="item">1</li><li class="item odd">2</li><li>3</li><li class="item">4</li></ul>
+            <p class="note">x</p><span data-k="a b"></span><p></p>
+        </div>
+        <div class="box"><p class="note">y</p></div>"#;
+
+    fn ids(doc: &DocumentImpl<Config>, found: &[NodeId]) -> Vec<String> {
+        found
+            .iter()
+            .map(|&id| {
+                let tag = doc.tag_name(id).unwrap_or_default();
+                match doc.attribute(id, "class") {
+                    Some(class) => format!("{tag}.{class}"),
+                    None => tag.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    fn all(doc: &DocumentImpl<Config>, selectors: &str) -> Vec<String> {
+        ids(doc, &doc.query_selector_all(doc.root(), selectors).expect("selector"))
+    }
+
+    #[test]
+    fn combinators() {
+        let doc = crate::html_compile::<Config>(HTML);
+        assert_eq!(all(&doc, "#main > p"), vec!["p.note", "p"]);
+        assert_eq!(all(&doc, "body p.note"), vec!["p.note", "p.note"]);
+        assert_eq!(all(&doc, "li.odd + li"), vec!["li"]);
+        assert_eq!(all(&doc, "li.odd ~ li"), vec!["li", "li.item"]);
+        assert_eq!(all(&doc, "ul ~ span"), vec!["span"]);
+        // Backtracking: the first `div` ancestor has no `.box` parent, but the match must keep looking
+        assert_eq!(all(&doc, ".box li.odd"), vec!["li.item odd"]);
+        assert_eq!(all(&doc, "html > body > div.box > p"), vec!["p.note", "p", "p.note"]);
+    }
+
+    #[test]
+    fn pseudo_classes_and_attributes() {
+        let doc = crate::html_compile::<Config>(HTML);
+        assert_eq!(all(&doc, "li:first-child, li:last-child"), vec!["li.item", "li.item"]);
+        assert_eq!(all(&doc, "li:nth-child(2n+1)"), vec!["li.item", "li"]);
+        assert_eq!(all(&doc, "li:nth-child(2 of .item)"), vec!["li.item odd"]);
+        assert_eq!(all(&doc, "li:not(.item)"), vec!["li"]);
+        assert_eq!(all(&doc, "div:has(> ul)"), vec!["div.box"]);
+        assert_eq!(all(&doc, "p:empty"), vec!["p"]);
+        assert_eq!(all(&doc, "span[data-k~=b]"), vec!["span"]);
+        assert_eq!(all(&doc, ":is(ul, span)"), vec!["ul", "span"]);
+    }
+
+    #[test]
+    fn scope_and_first_match() {
+        let doc = crate::html_compile::<Config>(HTML);
+        let main = doc.query_selector(doc.root(), "#main").unwrap().unwrap();
+        assert_eq!(doc.node_by_named_id("main"), Some(main));
+
+        let notes = doc.query_selector_all(main, ".note").unwrap();
+        assert_eq!(notes.len(), 1);
+        // `:scope` is the element the query runs on
+        assert_eq!(
+            ids(&doc, &doc.query_selector_all(main, ":scope > *").unwrap()),
+            vec!["ul", "p.note", "span", "p"]
+        );
+        // The scope itself is never part of the result
+        assert_eq!(doc.query_selector(main, "div").unwrap(), None);
+
+        let first = doc.query_selector(doc.root(), "li").unwrap();
+        assert_eq!(first, doc.query_selector(doc.root(), ".item").unwrap());
+        assert!(doc.query_selector(doc.root(), "li >").is_err());
+        assert!(doc.query_selector(doc.root(), "").is_err());
+    }
+
+    #[test]
+    fn index_follows_mutations() {
+        let mut doc = crate::html_compile::<Config>(HTML);
+        let span = doc.query_selector(doc.root(), "span").unwrap().unwrap();
+        doc.set_attribute(span, "class", "fresh");
+        doc.add_class(span, "late");
+        doc.set_attribute(span, "id", "dup");
+        let p = doc.query_selector(doc.root(), "p:empty").unwrap().unwrap();
+        doc.set_attribute(p, "id", "dup");
+
+        assert_eq!(doc.query_selector_all(doc.root(), ".fresh.late").unwrap(), vec![span]);
+        // Duplicate ids all match, in tree order
+        assert_eq!(doc.query_selector_all(doc.root(), "#dup").unwrap(), vec![span, p]);
+
+        // Stale index entries are filtered out
+        doc.set_attribute(span, "class", "other");
+        assert!(doc.query_selector_all(doc.root(), ".fresh").unwrap().is_empty());
+        doc.delete_node_by_id(p);
+        assert_eq!(doc.query_selector_all(doc.root(), "#dup").unwrap(), vec![span]);
+    }
+
+    #[test]
+    fn query_builder() {
+        let doc = crate::html_compile::<Config>(HTML);
+        let query = Query::new().equals_tag("p").contains_class("note").find_all();
+        let found = DocumentQuery::<Config>::query(&doc, &query).unwrap();
+        assert_eq!(ids(&doc, &found), vec!["p.note", "p.note"]);
+
+        let query = Query::new()
+            .has_parent_tag("ul")
+            .contains_attribute("class")
+            .find_first();
+        let found = DocumentQuery::<Config>::query(&doc, &query).unwrap();
+        assert_eq!(ids(&doc, &found), vec!["li.item"]);
+
+        let query = Query::new().contains_child_tag("li").find_all();
+        let found = DocumentQuery::<Config>::query(&doc, &query).unwrap();
+        assert_eq!(ids(&doc, &found), vec!["ul"]);
+
+        assert!(DocumentQuery::<Config>::query(&doc, &Query::new()).is_err());
     }
 }
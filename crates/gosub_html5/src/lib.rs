@@ -17,6 +17,7 @@ pub mod testing;
 pub mod tokenizer;
 #[allow(dead_code)]
 pub mod writer;
+pub mod xml;
 
 /// Parses the given HTML string and returns a handle to the resulting DOM tree.
 ///
@@ -4050,8 +4050,16 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
     ) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
         // Must be a text node
         let text = self.document.text_value(node_id)?;
+        Self::parse_inline_stylesheet(origin, text, self.document.url())
+    }
 
-        let source_url = match self.document.url() {
+    /// Parse the text of a `<style>` element of the document at `url`
+    pub(crate) fn parse_inline_stylesheet(
+        origin: CssOrigin,
+        text: &str,
+        url: Option<Url>,
+    ) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
+        let source_url = match url {
             Some(url) => format!("{url}#inline"),
             None => "<unknown>#inline".into(),
         };
@@ -4075,8 +4083,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
 
     /// Load and parse an external stylesheet by URL
     #[cfg(target_arch = "wasm32")]
-    fn load_external_stylesheet(
-        &self,
+    pub(crate) fn load_external_stylesheet(
         _origin: CssOrigin,
         _url: Url,
     ) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
@@ -4084,7 +4091,10 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    fn load_external_stylesheet(&self, origin: CssOrigin, url: Url) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
+    pub(crate) fn load_external_stylesheet(
+        origin: CssOrigin,
+        url: Url,
+    ) -> Option<<C::CssSystem as CssSystem>::Stylesheet> {
         let css = if url.scheme() == "http" || url.scheme() == "https" {
             let response = match gosub_sonar::net::simple::sync_fetch(&url) {
                 Ok(r) => r,
@@ -4205,7 +4215,7 @@ impl<'a, C: HasDocument> Html5Parser<'a, C> {
                         }
                     }
                 };
-                if let Some(stylesheet) = self.load_external_stylesheet(CssOrigin::Author, css_url) {
+                if let Some(stylesheet) = Self::load_external_stylesheet(CssOrigin::Author, css_url) {
                     self.document.add_stylesheet(stylesheet);
                 } else {
                     self.parse_error("failed to load external stylesheet");
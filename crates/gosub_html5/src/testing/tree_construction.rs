@@ -54,6 +54,11 @@ impl Test {
     }
 }
 
+/// Returns the tree below `root` in the format of the `#document` section of the test files
+pub fn tree_lines<C: HasDocument>(document: C::Document, root: gosub_shared::node::NodeId) -> Vec<String> {
+    TreeOutputGenerator::<C>::new(document).generate_from(root)
+}
+
 /// Harness is a wrapper to run tree-construction tests
 #[derive(Debug)]
 pub struct Harness {
@@ -94,6 +99,20 @@ impl Harness {
         Ok(result)
     }
 
+    /// Parses the input of a test the same way `run_test` does, and returns the document together with
+    /// the node its tree starts from (the document root, or the html element for fragment tests)
+    pub fn parse_test<C: HasHtmlParser + HasDocument>(
+        &mut self,
+        test: Test,
+        scripting_enabled: bool,
+    ) -> Result<(C::Document, gosub_shared::node::NodeId)> {
+        self.test = test;
+        self.next_document_line = 0;
+
+        let (document, _, tree_root) = self.do_parse::<C>(scripting_enabled)?;
+        Ok((document, tree_root))
+    }
+
     /// Run the html5 parser and return the document tree, errors, and the NodeId to start tree
     /// generation from (document root for full documents, the html element for fragment parses).
     fn do_parse<C: HasHtmlParser + HasDocument>(
@@ -1,81 +1,632 @@
+//! Serialization of a document (or a part of it) back into markup.
+//!
+//! Two modes are supported:
+//!
+//! * [`SerializationMode::Html`] implements the HTML fragment serialization algorithm
+//!   (<https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments>). This is what
+//!   `innerHTML` and `outerHTML` return: text and attribute values are escaped, void elements have
+//!   no end tag, and the contents of raw text elements such as `<script>` and `<style>` are written
+//!   verbatim. Parsing the output again yields the same tree, apart from the cases the spec lists as
+//!   not round-tripping.
+//! * [`SerializationMode::Xml`] produces well-formed XML following the DOM Parsing and Serialization
+//!   spec (<https://w3c.github.io/DOM-Parsing/#dfn-xml-serialization>). Namespace declarations are
+//!   emitted wherever an element's namespace differs from its parent's, so an inline `<svg>` subtree
+//!   comes out as a standalone SVG document that XML consumers like usvg accept.
+
+use crate::node::{HTML_NAMESPACE, XLINK_NAMESPACE};
 use gosub_interface::config::HasDocument;
 use gosub_interface::document::Document;
 use gosub_interface::node::NodeType;
 use gosub_shared::node::NodeId;
 
-pub struct DocumentWriter;
+/// HTML elements that never have contents and are serialized without an end tag
+const VOID_ELEMENTS: [&str; 18] = [
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input", "keygen", "link",
+    "meta", "param", "source", "track", "wbr",
+];
+
+/// HTML elements whose text children are serialized without escaping
+const RAW_TEXT_ELEMENTS: [&str; 7] = ["style", "script", "xmp", "iframe", "noembed", "noframes", "plaintext"];
+
+/// The markup flavour the writer produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SerializationMode {
+    /// HTML fragment serialization, as used by `innerHTML` and `outerHTML`
+    #[default]
+    Html,
+    /// Well-formed XML with explicit namespace declarations
+    Xml,
+}
+
+/// Serializes nodes of a document into HTML or XML markup
+#[derive(Debug, Clone, Copy)]
+pub struct DocumentWriter {
+    mode: SerializationMode,
+    /// Whether scripting is enabled, which makes the contents of `<noscript>` raw text
+    scripting: bool,
+}
+
+impl Default for DocumentWriter {
+    fn default() -> Self {
+        Self::html()
+    }
+}
 
 impl DocumentWriter {
+    /// Returns a writer that produces HTML, with scripting enabled like the parser's default
+    #[must_use]
+    pub fn html() -> Self {
+        Self {
+            mode: SerializationMode::Html,
+            scripting: true,
+        }
+    }
+
+    /// Returns a writer that produces XML
+    #[must_use]
+    pub fn xml() -> Self {
+        Self {
+            mode: SerializationMode::Xml,
+            scripting: true,
+        }
+    }
+
+    /// Sets whether scripting is enabled. This should match the setting the document was parsed
+    /// with, as it decides whether the contents of `<noscript>` are written as raw text.
+    #[must_use]
+    pub fn with_scripting(mut self, scripting: bool) -> Self {
+        self.scripting = scripting;
+        self
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> SerializationMode {
+        self.mode
+    }
+
+    /// Serializes the given node including the node itself as HTML (`outerHTML`)
     pub fn write_from_node<C: HasDocument>(node_id: NodeId, doc: &C::Document) -> String {
-        let mut buffer = String::new();
-        write_node::<C>(node_id, doc, &mut buffer);
-        buffer
+        Self::html().outer::<C>(node_id, doc)
     }
-}
 
-fn write_node<C: HasDocument>(id: NodeId, doc: &C::Document, buf: &mut String) {
-    match doc.node_type(id) {
-        NodeType::DocumentNode => {
-            let children: Vec<NodeId> = doc.children(id).to_vec();
-            for child in children {
-                write_node::<C>(child, doc, buf);
+    /// Serializes the children of the given node (`innerHTML`). For a `<template>` element these are
+    /// the children of its template contents.
+    pub fn inner<C: HasDocument>(&self, node_id: NodeId, doc: &C::Document) -> String {
+        let mut serializer = Serializer::<C>::new(*self, doc);
+        match self.mode {
+            SerializationMode::Html => serializer.html_children(node_id),
+            SerializationMode::Xml => {
+                let default_ns = inherited_namespace::<C>(doc, node_id);
+                serializer.xml_children(node_id, default_ns, &[]);
             }
         }
-        NodeType::DocTypeNode => {
-            if let Some(name) = doc.doctype_name(id) {
-                buf.push_str("<!DOCTYPE ");
-                buf.push_str(name);
-                buf.push('>');
-            }
-            let children: Vec<NodeId> = doc.children(id).to_vec();
-            for child in children {
-                write_node::<C>(child, doc, buf);
+        serializer.buf
+    }
+
+    /// Serializes the given node itself and its descendants (`outerHTML`). Serializing the document
+    /// node writes all of its children. In XML mode the output is a standalone fragment, so the
+    /// namespace of the node is always declared.
+    pub fn outer<C: HasDocument>(&self, node_id: NodeId, doc: &C::Document) -> String {
+        let mut serializer = Serializer::<C>::new(*self, doc);
+        match self.mode {
+            SerializationMode::Html => serializer.html_node(node_id, doc.parent(node_id)),
+            SerializationMode::Xml => {
+                serializer.xml_node(node_id, None, &[]);
             }
         }
-        NodeType::TextNode => {
-            if let Some(value) = doc.text_value(id) {
-                buf.push_str(value);
+        serializer.buf
+    }
+}
+
+/// Namespace of the nearest element at or above `id`, which is the default namespace its children
+/// inherit in XML output
+fn inherited_namespace<C: HasDocument>(doc: &C::Document, id: NodeId) -> Option<&str> {
+    let mut current = Some(id);
+    while let Some(node) = current {
+        if doc.node_type(node) == NodeType::ElementNode {
+            return Some(doc.namespace(node).unwrap_or(HTML_NAMESPACE));
+        }
+        current = doc.parent(node);
+    }
+    None
+}
+
+/// Turns the internal attribute name into its serialized form. Namespaced attributes on foreign
+/// elements are stored as "prefix local" (for instance "xlink href", or "xmlns " for a plain `xmlns`).
+fn attribute_name(name: &str) -> std::borrow::Cow<'_, str> {
+    match name.split_once(' ') {
+        Some((prefix, "")) => prefix.into(),
+        Some((prefix, local)) => format!("{prefix}:{local}").into(),
+        None => name.into(),
+    }
+}
+
+fn is_html<C: HasDocument>(doc: &C::Document, id: NodeId) -> bool {
+    doc.namespace(id).is_none_or(|ns| ns == HTML_NAMESPACE)
+}
+
+/// Attributes sorted by name, so output does not depend on hash map ordering
+fn sorted_attributes<C: HasDocument>(doc: &C::Document, id: NodeId) -> Vec<(&String, &String)> {
+    let mut attrs: Vec<_> = doc.attributes(id).map(|a| a.iter().collect()).unwrap_or_default();
+    attrs.sort();
+    attrs
+}
+
+struct Serializer<'a, C: HasDocument> {
+    writer: DocumentWriter,
+    doc: &'a C::Document,
+    buf: String,
+}
+
+impl<'a, C: HasDocument> Serializer<'a, C> {
+    fn new(writer: DocumentWriter, doc: &'a C::Document) -> Self {
+        Self {
+            writer,
+            doc,
+            buf: String::new(),
+        }
+    }
+
+    /// The node whose children are serialized for `id`: template elements serialize their contents
+    fn content_node(&self, id: NodeId) -> NodeId {
+        if self.doc.tag_name(id) == Some("template") && is_html::<C>(self.doc, id) {
+            return self.doc.template_contents(id).unwrap_or(id);
+        }
+        id
+    }
+
+    fn html_children(&mut self, id: NodeId) {
+        let doc = self.doc;
+        let content = self.content_node(id);
+        for &child in doc.children(content) {
+            self.html_node(child, Some(id));
+        }
+    }
+
+    fn html_node(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let doc = self.doc;
+        match doc.node_type(id) {
+            NodeType::DocumentNode => self.html_children(id),
+            NodeType::DocTypeNode => {
+                self.buf.push_str("<!DOCTYPE ");
+                self.buf.push_str(doc.doctype_name(id).unwrap_or_default());
+                self.buf.push('>');
             }
-            let children: Vec<NodeId> = doc.children(id).to_vec();
-            for child in children {
-                write_node::<C>(child, doc, buf);
+            NodeType::CommentNode => {
+                self.buf.push_str("<!--");
+                self.buf.push_str(doc.comment_value(id).unwrap_or_default());
+                self.buf.push_str("-->");
             }
-        }
-        NodeType::CommentNode => {
-            if let Some(value) = doc.comment_value(id) {
-                buf.push_str("<!--");
-                buf.push_str(value);
-                buf.push_str("-->");
+            NodeType::TextNode => {
+                let value = doc.text_value(id).unwrap_or_default();
+                if parent.is_some_and(|parent| self.is_raw_text_parent(parent)) {
+                    self.buf.push_str(value);
+                } else {
+                    escape_html(value, false, &mut self.buf);
+                }
             }
-            let children: Vec<NodeId> = doc.children(id).to_vec();
-            for child in children {
-                write_node::<C>(child, doc, buf);
+            NodeType::ElementNode => {
+                let Some(name) = doc.tag_name(id) else {
+                    return;
+                };
+                self.buf.push('<');
+                self.buf.push_str(name);
+                for (attr_name, attr_value) in sorted_attributes::<C>(doc, id) {
+                    self.buf.push(' ');
+                    self.buf.push_str(&attribute_name(attr_name));
+                    self.buf.push_str("=\"");
+                    escape_html(attr_value, true, &mut self.buf);
+                    self.buf.push('"');
+                }
+                self.buf.push('>');
+
+                if is_html::<C>(doc, id) && VOID_ELEMENTS.contains(&name) {
+                    return;
+                }
+
+                self.html_children(id);
+
+                self.buf.push_str("</");
+                self.buf.push_str(name);
+                self.buf.push('>');
             }
         }
-        NodeType::ElementNode => {
-            if let Some(name) = doc.tag_name(id) {
-                buf.push('<');
-                buf.push_str(name);
-                if let Some(attrs) = doc.attributes(id) {
-                    for (attr_name, attr_value) in attrs {
-                        buf.push(' ');
-                        buf.push_str(attr_name);
-                        buf.push_str("=\"");
-                        buf.push_str(attr_value);
-                        buf.push('"');
+    }
+
+    fn is_raw_text_parent(&self, parent: NodeId) -> bool {
+        let Some(name) = self.doc.tag_name(parent) else {
+            return false;
+        };
+        is_html::<C>(self.doc, parent)
+            && (RAW_TEXT_ELEMENTS.contains(&name) || (name == "noscript" && self.writer.scripting))
+    }
+
+    fn xml_children(&mut self, id: NodeId, default_ns: Option<&'a str>, prefixes: &[&'a str]) {
+        let doc = self.doc;
+        let content = self.content_node(id);
+        for &child in doc.children(content) {
+            self.xml_node(child, default_ns, prefixes);
+        }
+    }
+
+    /// Writes a node as XML. `default_ns` is the namespace in scope for unprefixed element names and
+    /// `prefixes` the attribute prefixes declared by ancestors within the output.
+    fn xml_node(&mut self, id: NodeId, default_ns: Option<&'a str>, prefixes: &[&'a str]) {
+        let doc = self.doc;
+        match doc.node_type(id) {
+            NodeType::DocumentNode => self.xml_children(id, None, prefixes),
+            NodeType::DocTypeNode => {
+                self.buf.push_str("<!DOCTYPE ");
+                self.buf.push_str(doc.doctype_name(id).unwrap_or_default());
+                let public_id = doc.doctype_public_id(id).unwrap_or_default();
+                let system_id = doc.doctype_system_id(id).unwrap_or_default();
+                if !public_id.is_empty() {
+                    self.buf.push_str(" PUBLIC \"");
+                    self.buf.push_str(public_id);
+                    self.buf.push('"');
+                } else if !system_id.is_empty() {
+                    self.buf.push_str(" SYSTEM");
+                }
+                if !system_id.is_empty() {
+                    self.buf.push_str(" \"");
+                    self.buf.push_str(system_id);
+                    self.buf.push('"');
+                }
+                self.buf.push('>');
+            }
+            NodeType::CommentNode => {
+                self.buf.push_str("<!--");
+                self.buf.push_str(doc.comment_value(id).unwrap_or_default());
+                self.buf.push_str("-->");
+            }
+            NodeType::TextNode => escape_xml(doc.text_value(id).unwrap_or_default(), false, &mut self.buf),
+            NodeType::ElementNode => {
+                let Some(name) = doc.tag_name(id) else {
+                    return;
+                };
+                let namespace = doc.namespace(id).unwrap_or(HTML_NAMESPACE);
+
+                self.buf.push('<');
+                self.buf.push_str(name);
+                if default_ns != Some(namespace) {
+                    self.buf.push_str(" xmlns=\"");
+                    escape_xml(namespace, true, &mut self.buf);
+                    self.buf.push('"');
+                }
+
+                let attrs = sorted_attributes::<C>(doc, id);
+                let mut in_scope = prefixes.to_vec();
+                // Explicit prefix declarations first, so we don't declare them a second time below
+                for (attr_name, attr_value) in &attrs {
+                    if let Some(("xmlns", prefix)) = attr_name.split_once(' ') {
+                        if !prefix.is_empty() && !in_scope.contains(&prefix) {
+                            self.push_xml_attribute(&attribute_name(attr_name), attr_value);
+                            in_scope.push(prefix);
+                        }
+                    }
+                }
+                for (attr_name, attr_value) in &attrs {
+                    match attr_name.split_once(' ') {
+                        // Namespace declarations have been handled above
+                        Some(("xmlns", _)) => continue,
+                        Some(("xlink", _)) if !in_scope.contains(&"xlink") => {
+                            self.push_xml_attribute("xmlns:xlink", XLINK_NAMESPACE);
+                            in_scope.push("xlink");
+                        }
+                        _ => {}
                     }
+                    // A plain `xmlns` attribute is replaced by the declaration written above
+                    if attr_name.as_str() == "xmlns" {
+                        continue;
+                    }
+                    self.push_xml_attribute(&attribute_name(attr_name), attr_value);
                 }
-                buf.push('>');
 
-                let children: Vec<NodeId> = doc.children(id).to_vec();
-                for child in children {
-                    write_node::<C>(child, doc, buf);
+                let content = self.content_node(id);
+                if doc.children(content).is_empty() {
+                    if namespace == HTML_NAMESPACE && !VOID_ELEMENTS.contains(&name) {
+                        self.buf.push_str("></");
+                        self.buf.push_str(name);
+                        self.buf.push('>');
+                    } else if namespace == HTML_NAMESPACE {
+                        self.buf.push_str(" />");
+                    } else {
+                        self.buf.push_str("/>");
+                    }
+                    return;
                 }
 
-                buf.push_str("</");
-                buf.push_str(name);
-                buf.push('>');
+                self.buf.push('>');
+                self.xml_children(id, Some(namespace), &in_scope);
+                self.buf.push_str("</");
+                self.buf.push_str(name);
+                self.buf.push('>');
             }
         }
     }
+
+    fn push_xml_attribute(&mut self, name: &str, value: &str) {
+        self.buf.push(' ');
+        self.buf.push_str(name);
+        self.buf.push_str("=\"");
+        escape_xml(value, true, &mut self.buf);
+        self.buf.push('"');
+    }
+}
+
+/// Escapes a string as described in the HTML fragment serialization algorithm
+fn escape_html(value: &str, attribute_mode: bool, buf: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '\u{00A0}' => buf.push_str("&nbsp;"),
+            '"' if attribute_mode => buf.push_str("&quot;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            _ => buf.push(c),
+        }
+    }
+}
+
+/// Escapes a string for XML text or attribute values
+fn escape_xml(value: &str, attribute_mode: bool, buf: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '"' if attribute_mode => buf.push_str("&quot;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            _ => buf.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::parser::Html5Parser;
+    use crate::testing::tree_construction::fixture::read_fixtures;
+    use crate::testing::tree_construction::{tree_lines, Harness};
+    use gosub_css3::system::Css3System;
+    use gosub_interface::config::ModuleConfiguration;
+    use gosub_interface::node::QuirksMode;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Config;
+
+    impl ModuleConfiguration for Config {
+        type CssSystem = Css3System;
+        type Document = DocumentImpl<Self>;
+        type HtmlParser = Html5Parser<'static, Self>;
+    }
+
+    fn body_html(html: &str) -> String {
+        let doc = crate::html_compile::<Config>(html);
+        let body = doc.node_by_named_id("body").expect("body");
+        doc.inner_html(body)
+    }
+
+    #[test]
+    fn escapes_text_and_attributes() {
+        assert_eq!(
+            body_html(r#"<body id=body><p title='a "b" & <c>'>x &amp; y &lt; z&nbsp;"q"</p>"#),
+            r#"<p title="a &quot;b&quot; &amp; &lt;c&gt;">x &amp; y &lt; z&nbsp;"q"</p>"#
+        );
+    }
+
+    #[test]
+    fn attributes_are_sorted() {
+        assert_eq!(
+            body_html(r#"<body id=body><a href=x class=c data-z=1 alt="">t</a>"#),
+            r#"<a alt="" class="c" data-z="1" href="x">t</a>"#
+        );
+    }
+
+    #[test]
+    fn void_elements_have_no_end_tag() {
+        assert_eq!(
+            body_html("<body id=body><p>a<br>b<img src=x><input></p><hr>"),
+            r#"<p>a<br>b<img src="x"><input></p><hr>"#
+        );
+    }
+
+    #[test]
+    fn raw_text_is_not_escaped() {
+        assert_eq!(
+            body_html("<body id=body><script>if (a < b && c) {}</script><style>a > b {}</style><xmp><&></xmp>"),
+            "<script>if (a < b && c) {}</script><style>a > b {}</style><xmp><&></xmp>"
+        );
+    }
+
+    #[test]
+    fn noscript_depends_on_scripting() {
+        let doc = crate::html_compile::<Config>("<body id=body><noscript><b>x</b></noscript>");
+        let body = doc.node_by_named_id("body").expect("body");
+
+        let scripting = DocumentWriter::html().inner::<Config>(body, &doc);
+        assert_eq!(scripting, "<noscript><b>x</b></noscript>");
+
+        let no_scripting = DocumentWriter::html().with_scripting(false).inner::<Config>(body, &doc);
+        assert_eq!(no_scripting, "<noscript>&lt;b&gt;x&lt;/b&gt;</noscript>");
+    }
+
+    #[test]
+    fn whole_document() {
+        let doc = crate::html_compile::<Config>("<!DOCTYPE html><!--c--><title>a&b</title><p>x");
+        assert_eq!(
+            doc.write(),
+            "<!DOCTYPE html><!--c--><html><head><title>a&amp;b</title></head><body><p>x</p></body></html>"
+        );
+    }
+
+    #[test]
+    fn inner_and_outer_html() {
+        let doc = crate::html_compile::<Config>("<div id=d><template><p>x</p></template>y</div>");
+        let div = doc.node_by_named_id("d").expect("div");
+
+        assert_eq!(doc.inner_html(div), "<template><p>x</p></template>y");
+        assert_eq!(
+            doc.outer_html(div),
+            r#"<div id="d"><template><p>x</p></template>y</div>"#
+        );
+    }
+
+    const SVG: &str = r##"<body><svg id=s viewBox="0 0 1 1"><foreignObject><br></foreignObject><use xlink:href="#a"/><text>a&lt;b</text></svg>"##;
+
+    #[test]
+    fn svg_as_html() {
+        let doc = crate::html_compile::<Config>(SVG);
+        let svg = doc.node_by_named_id("s").expect("svg");
+
+        assert_eq!(
+            doc.outer_html(svg),
+            r##"<svg id="s" viewBox="0 0 1 1"><foreignObject><br></foreignObject><use xlink:href="#a"></use><text>a&lt;b</text></svg>"##
+        );
+    }
+
+    #[test]
+    fn svg_as_xml() {
+        let doc = crate::html_compile::<Config>(SVG);
+        let svg = doc.node_by_named_id("s").expect("svg");
+
+        assert_eq!(
+            doc.outer_xml(svg),
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" id="s" viewBox="0 0 1 1">"#,
+                r#"<foreignObject><br xmlns="http://www.w3.org/1999/xhtml" /></foreignObject>"#,
+                r##"<use xmlns:xlink="http://www.w3.org/1999/xlink" xlink:href="#a"/>"##,
+                r#"<text>a&lt;b</text></svg>"#
+            )
+        );
+    }
+
+    #[test]
+    fn xml_mode_on_html() {
+        let doc =
+            crate::html_compile::<Config>(r#"<body id=body><p>a<br><script>a < b</script><span class="x"></span></p>"#);
+        let body = doc.node_by_named_id("body").expect("body");
+
+        assert_eq!(
+            DocumentWriter::xml().inner::<Config>(body, &doc),
+            r#"<p>a<br /><script>a &lt; b</script><span class="x"></span></p>"#
+        );
+        assert_eq!(
+            DocumentWriter::xml().outer::<Config>(body, &doc),
+            concat!(
+                r#"<body xmlns="http://www.w3.org/1999/xhtml" id="body">"#,
+                r#"<p>a<br /><script>a &lt; b</script><span class="x"></span></p></body>"#
+            )
+        );
+    }
+
+    /// Inputs the adoption agency or the form element pointer turn into trees that nested markup
+    /// cannot describe, so writing them out and parsing again gives a different shape
+    const MISNESTED_CASES: &[&str] = &[
+        // tests1.dat
+        "<a><table><td><a><table></table><a></tr><a></table><b>X</b>C<a>Y",
+        "<a href=\"blah\">aba<table><a href=\"foo\">br<tr><td></td></tr>x</table>aoe",
+        "<a><table><a></table><p><a><div><a>",
+        "<a><table><td><a><table></table><a></tr><a></table><a>",
+        // template.dat
+        "<template><a><table><a>",
+        // tests16.dat
+        "<!doctype html><form><table></form><form></table></form>",
+        // tests26.dat
+        "<!DOCTYPE html><body><b><nobr>1<table><nobr></b><i><nobr>2<nobr></i>3",
+    ];
+
+    /// Fragment contexts whose contents are raw text. Serializing the fragment's children on their own
+    /// loses the context element, so the text gets escaped.
+    const RAW_TEXT_CONTEXTS: &[&str] = &["style", "script", "xmp", "iframe", "noembed", "noframes", "plaintext"];
+
+    /// Returns why a parsed tree is known not to survive a round trip through the serializer, following
+    /// the list of caveats in the spec's fragment serialization section
+    fn not_round_trippable(doc: &DocumentImpl<Config>, id: NodeId) -> Option<&'static str> {
+        if doc.quirks_mode() != QuirksMode::NoQuirks {
+            return Some("the doctype that selected quirks mode is not serialized in full");
+        }
+        match doc.node_type(id) {
+            NodeType::DocTypeNode
+                if doc.doctype_public_id(id).is_some_and(|s| !s.is_empty())
+                    || doc.doctype_system_id(id).is_some_and(|s| !s.is_empty()) =>
+            {
+                return Some("doctype identifiers are not serialized");
+            }
+            NodeType::TextNode => {
+                let text = doc.text_value(id).unwrap_or_default();
+                if text.contains(['\r', '\0']) {
+                    return Some("carriage returns and NULs are normalized by the parser");
+                }
+                let parent = doc.parent(id).and_then(|parent| doc.tag_name(parent));
+                if parent == Some("script") && text.contains("<!--") {
+                    return Some("script data with escaped sections can swallow the end tag");
+                }
+                let first_child = doc
+                    .parent(id)
+                    .is_some_and(|parent| doc.children(parent).first() == Some(&id));
+                if matches!(parent, Some("pre" | "textarea" | "listing")) && first_child && text.starts_with('\n') {
+                    return Some("a leading newline in pre, textarea and listing is dropped by the parser");
+                }
+            }
+            NodeType::ElementNode if doc.tag_name(id) == Some("plaintext") => {
+                return Some("nothing after a plaintext start tag is parsed as markup");
+            }
+            _ => {}
+        }
+        doc.children(id)
+            .iter()
+            .find_map(|&child| not_round_trippable(doc, child))
+    }
+
+    /// Serializing the result of every tree-construction test and parsing that again must give the
+    /// same tree, except for the inputs the spec acknowledges do not round-trip.
+    #[test]
+    fn html5lib_round_trip() {
+        let mut failures = Vec::new();
+        let mut harness = Harness::new();
+
+        for fixture in read_fixtures(None).expect("fixtures") {
+            for test in fixture.tests {
+                if MISNESTED_CASES.contains(&test.spec_data())
+                    || test
+                        .spec
+                        .document_fragment
+                        .as_deref()
+                        .is_some_and(|context| RAW_TEXT_CONTEXTS.contains(&context))
+                {
+                    continue;
+                }
+
+                for &scripting_enabled in test.script_modes() {
+                    let (document, root) = harness
+                        .parse_test::<Config>(test.clone(), scripting_enabled)
+                        .expect("problem parsing");
+                    if not_round_trippable(&document, root).is_some() {
+                        continue;
+                    }
+                    let writer = DocumentWriter::html().with_scripting(scripting_enabled);
+                    let serialized = if test.spec.document_fragment.is_some() {
+                        writer.inner::<Config>(root, &document)
+                    } else {
+                        writer.outer::<Config>(root, &document)
+                    };
+                    let expected = tree_lines::<Config>(document, root);
+
+                    let mut reparse = test.clone();
+                    reparse.spec.data = serialized.clone();
+                    let (document, root) = harness
+                        .parse_test::<Config>(reparse, scripting_enabled)
+                        .expect("problem parsing");
+                    let actual = tree_lines::<Config>(document, root);
+
+                    if actual != expected {
+                        failures.push(format!("{}:{} {:?}", test.file_path, test.line, serialized));
+                    }
+                }
+            }
+        }
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
 }
@@ -0,0 +1,588 @@
+//! XML parser frontend
+//!
+//! Parses XML documents (XHTML, standalone SVG, feeds and other XML) into the same document arena as
+//! the HTML parser. Unlike HTML parsing, XML parsing is strict: a document that is not well-formed
+//! produces a single [`ParseError`] and no tree, so callers can show an error page instead.
+//!
+//! Elements keep their namespace. Elements in no namespace get an empty namespace, as the arena
+//! defaults a missing namespace to HTML. Attributes follow the convention of the HTML parser:
+//! namespaced attributes are stored as "prefix local" (for instance "xlink href"), and namespace
+//! declarations as "xmlns prefix", or "xmlns" for the default namespace.
+//!
+//! XHTML documents load the stylesheets of their `<style>` and `<link rel="stylesheet">` elements,
+//! as the HTML parser does.
+//!
+//! Processing instructions have no node type in the arena and are dropped. Only the predefined XML
+//! entities and those declared in the internal DTD subset are known: named HTML entities such as
+//! `&nbsp;` are well-formedness errors unless the document declares them.
+
+use crate::document::builder::DocumentBuilderImpl;
+use crate::errors::ParseError;
+use crate::node::HTML_NAMESPACE;
+use crate::parser::Html5Parser;
+use gosub_interface::config::HasDocument;
+use gosub_interface::css3::CssOrigin;
+use gosub_interface::document::Document;
+use gosub_shared::byte_stream::Location;
+use gosub_shared::node::NodeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use url::Url;
+
+/// Nodes a document may hold before parsing is abandoned, as a guard against hostile input
+const NODES_LIMIT: u32 = 10_000_000;
+
+/// Deepest element nesting accepted (libxml2's default). roxmltree parses elements recursively, so
+/// without a limit a hostile document could overflow the stack.
+pub const MAX_DEPTH: usize = 256;
+
+/// Stack size of the thread roxmltree runs on, enough for [`MAX_DEPTH`] levels in unoptimized builds
+const PARSE_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+pub struct XmlParser<C: HasDocument> {
+    _phantom: PhantomData<C>,
+}
+
+impl<C: HasDocument> XmlParser<C> {
+    /// Parses a complete XML document into `document`, which should be empty. When the input is not
+    /// well-formed, the first well-formedness error is returned and nothing is added to the document.
+    pub fn parse_document(input: &str, document: &mut C::Document) -> Result<(), ParseError> {
+        if let Some(offset) = exceeds_max_depth(input) {
+            return Err(ParseError {
+                message: format!("elements are nested more than {MAX_DEPTH} levels deep"),
+                location: location_of(input, offset),
+            });
+        }
+        let xml = parse_xml(input).map_err(|e| parse_error(input, &e))?;
+
+        let root = document.root();
+        if let Some((name, public_id, system_id, offset)) = doctype(input, xml.root_element().range().start) {
+            let location = location_at(&xml, offset);
+            let doctype = document.create_doctype(name, public_id, system_id, location);
+            document.attach(doctype, root, None);
+        }
+
+        // Built with an explicit stack, as documents can nest deeper than the call stack allows
+        let mut stack: Vec<(roxmltree::Node, NodeId)> = xml.root().children().rev().map(|n| (n, root)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let location = location_at(&xml, node.range().start);
+            let id = match node.node_type() {
+                roxmltree::NodeType::Element => {
+                    let name = node.tag_name();
+                    let namespace = name.namespace().unwrap_or("");
+                    document.create_element(name.name(), Some(namespace), attributes(node), location)
+                }
+                roxmltree::NodeType::Text => document.create_text(node.text().unwrap_or_default(), location),
+                roxmltree::NodeType::Comment => document.create_comment(node.text().unwrap_or_default(), location),
+                roxmltree::NodeType::PI | roxmltree::NodeType::Root => continue,
+            };
+            document.attach(id, parent, None);
+            stack.extend(node.children().rev().map(|child| (child, id)));
+        }
+
+        // XHTML is styled like HTML, by its style elements and stylesheet links
+        for node in xml
+            .descendants()
+            .filter(|n| n.tag_name().namespace() == Some(HTML_NAMESPACE))
+        {
+            let stylesheet = match node.tag_name().name() {
+                "style" => {
+                    let text: String = node.children().filter_map(|child| child.text()).collect();
+                    Html5Parser::<C>::parse_inline_stylesheet(CssOrigin::Author, &text, document.url())
+                }
+                "link" if node.attribute("rel").is_some_and(is_stylesheet_link) => {
+                    let href = node.attribute("href").unwrap_or_default();
+                    let url = match document.url() {
+                        Some(base) => base.join(href),
+                        None => Url::parse(href),
+                    };
+                    match url {
+                        Ok(url) => Html5Parser::<C>::load_external_stylesheet(CssOrigin::Author, url),
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some(stylesheet) = stylesheet {
+                document.add_stylesheet(stylesheet);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_stylesheet_link(rel: &str) -> bool {
+    rel.split_ascii_whitespace()
+        .any(|r| r.eq_ignore_ascii_case("stylesheet"))
+}
+
+/// Runs roxmltree on a thread with a stack large enough for [`MAX_DEPTH`] levels of elements, or on
+/// the current thread where no thread can be started
+fn parse_xml(input: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let parse = || {
+        let options = roxmltree::ParsingOptions {
+            allow_dtd: true,
+            nodes_limit: NODES_LIMIT,
+            ..Default::default()
+        };
+        roxmltree::Document::parse_with_options(input, options)
+    };
+
+    std::thread::scope(|scope| {
+        let thread = std::thread::Builder::new()
+            .name("xml-parser".to_string())
+            .stack_size(PARSE_STACK_SIZE)
+            .spawn_scoped(scope, parse);
+        match thread {
+            Ok(handle) => handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)),
+            Err(_) => parse(),
+        }
+    })
+}
+
+/// Returns the offset of the first start tag nested deeper than [`MAX_DEPTH`], if any. This is a
+/// quick scan rather than a parse, so for a document that is not well-formed the answer may be off
+/// and the parser reports a different error. Markup in entity values counts as if every entity
+/// were referenced at the deepest point, which overestimates but never misses deep nesting.
+fn exceeds_max_depth(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let find = |from: usize, needle: &[u8]| {
+        bytes
+            .get(from..)?
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|p| from + p + needle.len())
+    };
+    // End of a tag or declaration starting at `from`, skipping quoted strings
+    let tag_end = |from: usize| {
+        let mut quote = None;
+        for (i, &b) in bytes.iter().enumerate().skip(from) {
+            match (quote, b) {
+                (Some(q), _) if b == q => quote = None,
+                (None, b'"' | b'\'') => quote = Some(b),
+                (None, b'>') => return Some(i + 1),
+                _ => {}
+            }
+        }
+        None
+    };
+
+    let mut depth = 0;
+    let mut entity_depth = 0;
+    let mut i = 0;
+    while let Some(start) = bytes.get(i..)?.iter().position(|&b| b == b'<').map(|p| i + p) {
+        let rest = &bytes[start..];
+        i = if rest.starts_with(b"<!--") {
+            find(start + 4, b"-->")?
+        } else if rest.starts_with(b"<![CDATA[") {
+            find(start + 9, b"]]>")?
+        } else if rest.starts_with(b"<?") {
+            find(start + 2, b"?>")?
+        } else if rest.starts_with(b"<!DOCTYPE") {
+            let (end, depth_in_entities) = doctype_extent(input, start)?;
+            entity_depth += depth_in_entities;
+            end
+        } else if rest.starts_with(b"</") {
+            depth = usize::saturating_sub(depth, 1);
+            tag_end(start)?
+        } else {
+            let end = tag_end(start)?;
+            if bytes[end - 2] != b'/' {
+                depth += 1;
+                if depth + entity_depth > MAX_DEPTH {
+                    return Some(start);
+                }
+            }
+            end
+        };
+    }
+    None
+}
+
+/// End of the document type declaration starting at `start`, and the total element nesting of the
+/// entity values declared in its internal subset
+fn doctype_extent(input: &str, start: usize) -> Option<(usize, usize)> {
+    let bytes = input.as_bytes();
+    let mut entity_depth = 0;
+    let mut in_subset = false;
+    let mut i = start + "<!DOCTYPE".len();
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'"' | b'\'' => {
+                let len = bytes[i + 1..].iter().position(|&c| c == b)?;
+                let value = input.get(i + 1..i + 1 + len)?;
+                if in_subset && value.contains('<') {
+                    entity_depth += markup_depth(value);
+                }
+                i += len + 2;
+                continue;
+            }
+            b'<' if in_subset && bytes[i..].starts_with(b"<!--") => {
+                let len = input.get(i..)?.find("-->")?;
+                i += len + 3;
+                continue;
+            }
+            b'[' => in_subset = true,
+            b']' => in_subset = false,
+            b'>' if !in_subset => return Some((i + 1, entity_depth)),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Deepest element nesting within an entity value
+fn markup_depth(value: &str) -> usize {
+    let mut depth = 0usize;
+    let mut deepest = 0;
+    for (i, _) in value.match_indices('<') {
+        let rest = &value[i..];
+        if rest.starts_with("</") {
+            depth = depth.saturating_sub(1);
+        } else if !rest.starts_with("<!") && !rest.starts_with("<?") {
+            let self_closing = rest.find('>').is_some_and(|end| rest[..end].ends_with('/'));
+            if !self_closing {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+        }
+    }
+    deepest
+}
+
+/// Parses the given XML string into a new document
+pub fn xml_compile<C: HasDocument>(xml: &str) -> Result<C::Document, ParseError> {
+    let mut doc = DocumentBuilderImpl::new_document::<C>(None);
+    XmlParser::<C>::parse_document(xml, &mut doc)?;
+    Ok(doc)
+}
+
+/// Decodes the bytes of an XML document. A byte order mark selects UTF-8 or UTF-16, and without
+/// one an encoding declaration may select Latin-1 (or its subset ASCII). Everything else must be
+/// UTF-8, and invalid UTF-8 is reported as a well-formedness error.
+pub fn decode(bytes: &[u8]) -> Result<String, ParseError> {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units = bytes.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect::<String>()
+    };
+
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return utf8(rest);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return Ok(utf16(rest, u16::from_le_bytes));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return Ok(utf16(rest, u16::from_be_bytes));
+    }
+
+    match declared_encoding(bytes) {
+        Some(encoding) if is_latin1(&encoding) => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+        _ => utf8(bytes),
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, ParseError> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| {
+        let valid = &bytes[..e.utf8_error().valid_up_to()];
+        // Valid up to here, so the conversion cannot fail
+        let text = std::str::from_utf8(valid).unwrap_or_default();
+        ParseError {
+            message: "the document is not valid UTF-8".to_string(),
+            location: location_of(text, text.len()),
+        }
+    })
+}
+
+/// The encoding named by the XML declaration (`<?xml version="1.0" encoding="..."?>`), if any
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    let declaration = bytes.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = String::from_utf8_lossy(&declaration[..end]);
+    let (_, rest) = declaration.split_once("encoding")?;
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let value = rest[1..].split(quote).next()?;
+    Some(value.to_string())
+}
+
+fn is_latin1(encoding: &str) -> bool {
+    ["iso-8859-1", "latin1", "l1", "us-ascii", "ascii", "iso-ir-100", "cp819"]
+        .iter()
+        .any(|name| encoding.eq_ignore_ascii_case(name))
+}
+
+/// The attributes of an element as stored in the arena, including the namespace declarations it makes
+fn attributes(node: roxmltree::Node) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+
+    // roxmltree lists the namespaces in scope rather than the declarations, so compare with the parent
+    let inherited: Vec<&roxmltree::Namespace> = node
+        .parent_element()
+        .map(|p| p.namespaces().collect())
+        .unwrap_or_default();
+    for namespace in node.namespaces() {
+        if inherited.contains(&namespace) {
+            continue;
+        }
+        let name = match namespace.name() {
+            Some(prefix) => format!("xmlns {prefix}"),
+            None => "xmlns".to_string(),
+        };
+        attributes.insert(name, namespace.uri().to_string());
+    }
+
+    for attribute in node.attributes() {
+        let name = match attribute.namespace().and_then(|ns| node.lookup_prefix(ns)) {
+            Some(prefix) => format!("{prefix} {}", attribute.name()),
+            None => attribute.name().to_string(),
+        };
+        attributes.insert(name, attribute.value().to_string());
+    }
+
+    attributes
+}
+
+/// Finds the document type declaration in the prolog (before the root element at `end`), returning
+/// its name, public and system identifiers and offset. roxmltree checks it but does not expose it.
+fn doctype(input: &str, end: usize) -> Option<(&str, Option<&str>, Option<&str>, usize)> {
+    let offset = input.get(..end)?.find("<!DOCTYPE")?;
+    let rest = input[offset + "<!DOCTYPE".len()..end].trim_start();
+
+    let name_end = rest
+        .find(|c: char| c.is_whitespace() || c == '[' || c == '>')
+        .unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_end);
+
+    let literal = |s: &str| -> Option<(usize, usize)> {
+        let s_trimmed = s.trim_start();
+        let skipped = s.len() - s_trimmed.len();
+        let quote = s_trimmed.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let len = s_trimmed[1..].find(quote)?;
+        Some((skipped + 1, skipped + 1 + len))
+    };
+
+    let rest = rest.trim_start();
+    let (public_id, system_id) = if let Some(after) = rest.strip_prefix("PUBLIC") {
+        let (start, end) = literal(after)?;
+        let system = literal(&after[end + 1..]).map(|(s, e)| &after[end + 1 + s..end + 1 + e]);
+        (Some(&after[start..end]), system)
+    } else if let Some(after) = rest.strip_prefix("SYSTEM") {
+        let (start, end) = literal(after)?;
+        (None, Some(&after[start..end]))
+    } else {
+        (None, None)
+    };
+
+    Some((name, public_id, system_id, offset))
+}
+
+fn location_at(xml: &roxmltree::Document, offset: usize) -> Location {
+    let pos = xml.text_pos_at(offset);
+    Location::new(pos.row as usize, pos.col as usize, offset)
+}
+
+/// Location of a byte offset in `input`, with columns counted in characters
+fn location_of(input: &str, offset: usize) -> Location {
+    let before = input.get(..offset).unwrap_or(input);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    Location::new(line, column, before.len())
+}
+
+/// Converts a roxmltree error into a parse error. Its message ends in the position, which the
+/// location carries instead.
+fn parse_error(input: &str, error: &roxmltree::Error) -> ParseError {
+    let location = match error {
+        // Reported at the start, though the input ended too early
+        roxmltree::Error::UnexpectedEndOfStream | roxmltree::Error::UnclosedRootNode => location_of(input, input.len()),
+        _ => {
+            let pos = error.pos();
+            let (line, column) = (pos.row as usize, pos.col as usize);
+            let offset = input.split_inclusive('\n').take(line - 1).map(str::len).sum::<usize>()
+                + input
+                    .split_inclusive('\n')
+                    .nth(line - 1)
+                    .map_or(0, |l| l.chars().take(column - 1).map(char::len_utf8).sum());
+            Location::new(line, column, offset)
+        }
+    };
+
+    let message = error.to_string();
+    let message = match message.strip_suffix(&format!(" at {}", error.pos())) {
+        Some(stripped) => stripped.to_string(),
+        None => message,
+    };
+
+    ParseError { message, location }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document_impl::DocumentImpl;
+    use crate::node::SVG_NAMESPACE;
+    use crate::testing::tree_construction::tree_lines;
+    use gosub_css3::system::Css3System;
+    use gosub_interface::config::ModuleConfiguration;
+    use gosub_interface::node::NodeType;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Config;
+
+    impl ModuleConfiguration for Config {
+        type CssSystem = Css3System;
+        type Document = DocumentImpl<Self>;
+        type HtmlParser = Html5Parser<'static, Self>;
+    }
+
+    fn tree(xml: &str) -> Vec<String> {
+        let doc = xml_compile::<Config>(xml).expect("well-formed");
+        let root = doc.root();
+        tree_lines::<Config>(doc, root)
+    }
+
+    #[test]
+    fn namespaces() {
+        let doc = xml_compile::<Config>(
+            r##"<html xmlns="http://www.w3.org/1999/xhtml"><body><svg:svg xmlns:svg="http://www.w3.org/2000/svg"
+                xmlns:xlink="http://www.w3.org/1999/xlink"><svg:use xlink:href="#a"/></svg:svg><raw xmlns=""/></body></html>"##,
+        )
+        .expect("well-formed");
+
+        let html = doc.children(doc.root())[0];
+        assert_eq!(doc.tag_name(html), Some("html"));
+        assert_eq!(doc.namespace(html), Some(HTML_NAMESPACE));
+        assert_eq!(doc.attribute(html, "xmlns"), Some(HTML_NAMESPACE));
+
+        let body = doc.children(html)[0];
+        let svg = doc.children(body)[0];
+        assert_eq!(doc.tag_name(svg), Some("svg"));
+        assert_eq!(doc.namespace(svg), Some(SVG_NAMESPACE));
+        assert_eq!(doc.attribute(svg, "xmlns svg"), Some(SVG_NAMESPACE));
+
+        let use_element = doc.children(svg)[0];
+        assert_eq!(doc.attribute(use_element, "xlink href"), Some("#a"));
+        // Declarations are only recorded where they are made
+        assert_eq!(doc.attribute(use_element, "xmlns svg"), None);
+
+        let raw = doc.children(body)[1];
+        assert_eq!(doc.namespace(raw), Some(""));
+
+        assert_eq!(
+            doc.outer_xml(svg),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:svg="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><use xlink:href="#a"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn xhtml_stylesheets() {
+        let doc = xml_compile::<Config>(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><head><style>p { color: red }</style></head>
+                <body><svg xmlns="http://www.w3.org/2000/svg"><style>rect { fill: red }</style></svg></body></html>"#,
+        )
+        .expect("well-formed");
+        // The SVG style element is not an HTML one
+        assert_eq!(doc.stylesheets().len(), 1);
+    }
+
+    #[test]
+    fn content() {
+        let lines = tree(
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE note SYSTEM \"note.dtd\" [<!ENTITY who \"World\">]>\n\
+             <!--c--><note a='1 &amp; 2'>Hello &who;<![CDATA[ <raw> ]]><?pi x?></note>",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "| <!DOCTYPE note \"\" \"note.dtd\">",
+                "| <!-- c -->",
+                "| <note>",
+                "|   a=\"1 & 2\"",
+                "|   \"Hello World <raw> \"",
+            ]
+        );
+    }
+
+    #[test]
+    fn doctype_identifiers() {
+        let input = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" 'http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd'><html/>"#;
+        assert_eq!(
+            doctype(input, input.find("<html").unwrap_or_default()),
+            Some((
+                "html",
+                Some("-//W3C//DTD XHTML 1.0 Strict//EN"),
+                Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"),
+                0
+            ))
+        );
+        assert_eq!(doctype("<!DOCTYPE svg><svg/>", 14), Some(("svg", None, None, 0)));
+        assert_eq!(doctype("<svg/>", 0), None);
+    }
+
+    #[test]
+    fn well_formedness_errors() {
+        let error = xml_compile::<Config>("<a>\n  <b></a>").expect_err("mismatched tags");
+        assert_eq!(error.message, "expected 'b' tag, not 'a'");
+        assert_eq!((error.location.line, error.location.column), (2, 6));
+        assert_eq!(error.location.offset, 9);
+
+        let error = xml_compile::<Config>("<a>\n<b>").expect_err("unclosed");
+        assert_eq!((error.location.line, error.location.column), (2, 4));
+
+        for input in ["", "<a>&nbsp;</a>", "<a/><b/>", "<a x='1' x='2'/>", "<p:a/>"] {
+            assert!(
+                xml_compile::<Config>(input).is_err(),
+                "{input:?} should not be well-formed"
+            );
+        }
+
+        // Nothing is added for a document that is not well-formed
+        let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+        assert!(XmlParser::<Config>::parse_document("<a><b></a>", &mut doc).is_err());
+        assert!(doc.children(doc.root()).is_empty());
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(decode(b"\xEF\xBB\xBF<a>\xC3\xA9</a>").ok().as_deref(), Some("<a>é</a>"));
+        assert_eq!(decode(b"\xFF\xFE<\0a\0/\0>\0").ok().as_deref(), Some("<a/>"));
+        assert_eq!(decode(b"\xFE\xFF\0<\0a\0/\0>").ok().as_deref(), Some("<a/>"));
+        assert_eq!(
+            decode(b"<?xml version='1.0' encoding='ISO-8859-1'?><a>\xE9</a>")
+                .ok()
+                .as_deref(),
+            Some("<?xml version='1.0' encoding='ISO-8859-1'?><a>é</a>")
+        );
+
+        let error = decode(b"<a>\n ok \xE9</a>").expect_err("invalid UTF-8");
+        assert_eq!((error.location.line, error.location.column), (2, 5));
+    }
+
+    #[test]
+    fn deep_documents() {
+        let nested = |depth: usize| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+
+        let doc = xml_compile::<Config>(&nested(MAX_DEPTH)).expect("well-formed");
+        let mut node = doc.root();
+        let mut levels = 0;
+        while let Some(&child) = doc.children(node).first() {
+            assert_eq!(doc.node_type(child), NodeType::ElementNode);
+            node = child;
+            levels += 1;
+        }
+        assert_eq!(levels, MAX_DEPTH);
+
+        let err = xml_compile::<Config>(&nested(50_000)).expect_err("too deep");
+        assert_eq!(err.message, "elements are nested more than 256 levels deep");
+        assert_eq!(err.location.offset, MAX_DEPTH * 3);
+
+        // Nesting through entities is counted too
+        let xml = format!("<!DOCTYPE a [<!ENTITY e '{}'>]><a>&e;</a>", nested(MAX_DEPTH));
+        assert!(xml_compile::<Config>(&xml).is_err());
+    }
+}
@@ -35,6 +35,60 @@ pub struct HoverFingerprints {
     pub ids: std::collections::HashSet<String>,
 }
 
+/// How far a change to an element's class, id or attribute can reach when matching selectors.
+/// Ordered from narrowest to widest so scopes can be combined with `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InvalidationScope {
+    /// Only the element itself (the feature appears in a selector's subject compound)
+    Element,
+    /// The element and its descendants (the feature appears left of a descendant or child combinator)
+    Subtree,
+    /// The element's parent and everything below it (the feature appears left of a sibling combinator
+    /// or in the `of` list of `:nth-child()`)
+    Siblings,
+    /// Anything in the document (the feature appears inside `:has()`)
+    Document,
+}
+
+/// Selector invalidation sets for a set of stylesheets.
+///
+/// Maps every class, id and attribute name that any selector tests to the widest
+/// [`InvalidationScope`] it is used with, so a DOM change only restyles the elements whose
+/// selector matches could have changed. Features missing from the sets cannot affect matching
+/// at all. Like [`HoverFingerprints`], this is computed by the [`CssSystem`] through
+/// [`CssSystem::invalidation_sets`].
+#[derive(Default, Debug, Clone)]
+pub struct InvalidationSets {
+    /// Class names tested by `.class` selectors
+    pub classes: std::collections::HashMap<String, InvalidationScope>,
+    /// Element ids tested by `#id` selectors
+    pub ids: std::collections::HashMap<String, InvalidationScope>,
+    /// Attribute names (lowercase) tested by `[attr]` selectors
+    pub attributes: std::collections::HashMap<String, InvalidationScope>,
+    /// Some selector uses `:has()`, so a change anywhere can restyle an ancestor
+    pub relational: bool,
+}
+
+impl InvalidationSets {
+    /// Scope of a change to the class `name`, if any selector tests it
+    #[must_use]
+    pub fn class_scope(&self, name: &str) -> Option<InvalidationScope> {
+        self.classes.get(name).copied().max(self.attribute_scope("class"))
+    }
+
+    /// Scope of a change to the id `name`, if any selector tests it
+    #[must_use]
+    pub fn id_scope(&self, name: &str) -> Option<InvalidationScope> {
+        self.ids.get(name).copied().max(self.attribute_scope("id"))
+    }
+
+    /// Scope of a change to the attribute `name` as tested by `[attr]` selectors
+    #[must_use]
+    pub fn attribute_scope(&self, name: &str) -> Option<InvalidationScope> {
+        self.attributes.get(name).copied()
+    }
+}
+
 /// The `CssSystem` trait is a trait that defines all things CSS3 that are used by other non-css3 crates. This is the main trait that
 /// is used to parse CSS3 files. It contains sub elements like the Stylesheet trait that is used in for instance the Document trait.
 pub trait CssSystem: Clone + Debug + 'static {
@@ -56,10 +110,12 @@ pub trait CssSystem: Clone + Debug + 'static {
         sheets: &[Self::Stylesheet],
     ) -> Option<Self::PropertyMap>;
 
-    /// Returns the properties that apply to the `::before` / `::after` pseudo-element of `id`.
-    /// `pseudo` is the pseudo-element name without colons (`"before"` or `"after"`). Returns
-    /// `None` when no rule targets that pseudo-element (so no generated box should be created).
-    /// The default implementation reports no pseudo-element styling.
+    /// Returns the properties that apply to the `::before` / `::after` / `::marker` / `::selection`
+    /// pseudo-element of `id`. `pseudo` is the pseudo-element name without colons (`"before"`,
+    /// `"after"`, `"marker"` or `"selection"`). Returns `None` when no rule targets that
+    /// pseudo-element (so no generated box should be created, or the default selection highlight
+    /// applies); `::marker` always yields a (possibly empty) map since list items get a marker
+    /// without any rule. The default implementation reports no pseudo-element styling.
     fn pseudo_properties_from_node<C: HasDocument<CssSystem = Self>>(
         _doc: &C::Document,
         _id: NodeId,
@@ -75,6 +131,10 @@ pub trait CssSystem: Clone + Debug + 'static {
     /// are the subject of a `:hover` rule. Lets the engine cheaply decide whether a hover change
     /// can affect styling without re-running selector matching.
     fn hover_fingerprints(sheets: &[Self::Stylesheet]) -> HoverFingerprints;
+
+    /// Scan `sheets` and collect the [`InvalidationSets`] - the classes, ids and attributes any
+    /// selector depends on, with how far a change to each can reach.
+    fn invalidation_sets(sheets: &[Self::Stylesheet]) -> InvalidationSets;
 }
 
 pub trait CssStylesheet: PartialEq + Debug {
@@ -84,13 +144,131 @@ pub trait CssStylesheet: PartialEq + Debug {
     /// Returns the source URL of the stylesheet
     fn url(&self) -> &str;
 
-    /// `@font-face` web fonts declared in this stylesheet, as
-    /// `(family, source_urls, unicode_range)` tuples. The source URLs are unresolved
-    /// (relative to the stylesheet's own URL); `unicode_range` is the raw descriptor or
-    /// `None` when the face covers all code points.
-    fn font_faces(&self) -> Vec<(String, Vec<String>, Option<String>)> {
+    /// `@font-face` web fonts declared in this stylesheet, in source order.
+    fn font_faces(&self) -> Vec<FontFaceRule> {
+        Vec::new()
+    }
+
+    /// `@counter-style` rules declared in this stylesheet, in source order.
+    fn counter_styles(&self) -> Vec<CounterStyleRule> {
         Vec::new()
     }
+
+    /// `@keyframes` rules declared in this stylesheet, in source order.
+    fn keyframes(&self) -> Vec<KeyframesRule> {
+        Vec::new()
+    }
+
+    /// `@page` rules declared in this stylesheet, in source order.
+    fn page_rules(&self) -> Vec<PageRule> {
+        Vec::new()
+    }
+}
+
+/// The `font-display` descriptor of an `@font-face` rule: how text using the face renders
+/// while the face is still downloading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FontDisplay {
+    /// The user agent's choice; behaves as `block`.
+    #[default]
+    Auto,
+    /// Invisible text for a short block period, then a fallback until the face arrives.
+    Block,
+    /// A fallback immediately; the face swaps in whenever it arrives.
+    Swap,
+    /// A very short block period, then a short window in which the face may still swap in.
+    Fallback,
+    /// A very short block period; a face arriving later is only used on the next load.
+    Optional,
+}
+
+impl FontDisplay {
+    /// Parse a `font-display` keyword (case-insensitive).
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        [
+            ("auto", Self::Auto),
+            ("block", Self::Block),
+            ("swap", Self::Swap),
+            ("fallback", Self::Fallback),
+            ("optional", Self::Optional),
+        ]
+        .into_iter()
+        .find_map(|(name, display)| keyword.eq_ignore_ascii_case(name).then_some(display))
+    }
+}
+
+/// A parsed `@font-face` rule: a logical font family and the (unresolved) URLs that provide it.
+/// URLs are relative to the stylesheet's own URL until resolved by the consumer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontFaceRule {
+    /// The `font-family` name this face provides (unquoted).
+    pub family: String,
+    /// Candidate `src: url(...)` targets in declared order.
+    pub sources: Vec<String>,
+    /// The raw `unicode-range` descriptor, if any (e.g. `"U+0000-00FF, U+0131"`); `None` means
+    /// the face covers all code points.
+    pub unicode_range: Option<String>,
+    pub display: FontDisplay,
+}
+
+/// A parsed `@counter-style` rule. Descriptors that were not declared are left empty/`None` so
+/// the consumer can apply the spec defaults (and resolve `extends`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CounterStyleRule {
+    /// The counter style name (the rule's prelude).
+    pub name: String,
+    /// `system` keyword (`cyclic`, `numeric`, `alphabetic`, `symbolic`, `additive`, `fixed`,
+    /// `extends`), with its argument for `fixed <n>` / `extends <name>`.
+    pub system: Option<String>,
+    /// `symbols`, unquoted.
+    pub symbols: Vec<String>,
+    /// `additive-symbols` as `(weight, symbol)` pairs.
+    pub additive_symbols: Vec<(u32, String)>,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    /// `negative` sign prefix.
+    pub negative: Option<String>,
+    /// `fallback` counter style name.
+    pub fallback: Option<String>,
+}
+
+/// A parsed `@keyframes` rule: the named animation's keyframes in source order. A later rule
+/// with the same name replaces an earlier one, which is up to the consumer to resolve.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyframesRule {
+    /// The animation name (the rule's prelude), as `animation-name` refers to it.
+    pub name: String,
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// One keyframe block of a `@keyframes` rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keyframe {
+    /// The keyframe selectors as offsets in `0.0..=1.0` (`from` is 0, `to` is 1, `50%` is 0.5).
+    pub offsets: Vec<f32>,
+    /// The declarations as `(property, value)` text pairs, e.g. `("opacity", "0.5")`.
+    pub declarations: Vec<(String, String)>,
+}
+
+/// A parsed `@page` rule. Declarations are kept as CSS text, like [`Keyframe::declarations`]; the
+/// paged-media layout resolves `size`, `margin` and the margin boxes' `content` itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageRule {
+    /// The page selectors, one entry per comma separated alternative: `""` for a bare `@page`,
+    /// `":first"`, `":left"`, `":right"` or `"chapter:first"` for a named page.
+    pub selectors: Vec<String>,
+    /// The page's own declarations as `(property, value)` text pairs, e.g. `("size", "A4")`.
+    pub declarations: Vec<(String, String)>,
+    /// Nested page-margin boxes (`@top-center`, `@bottom-right`, ...).
+    pub margin_boxes: Vec<PageMarginRule>,
+}
+
+/// A page-margin box of an `@page` rule, such as `@bottom-center { content: counter(page) }`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMarginRule {
+    /// The margin box name without the `@`, e.g. `"bottom-center"`.
+    pub name: String,
+    pub declarations: Vec<(String, String)>,
 }
 
 pub trait CssPropertyMap<S: CssSystem>: Default + Debug + WasmNotSend {
@@ -130,6 +308,13 @@ pub trait CssProperty<S: CssSystem>: Debug + Display + Sized + From<S::Value> {
     fn as_function(&self) -> Option<(&str, &[S::Value])>;
 
     fn is_none(&self) -> bool;
+
+    /// Origin and location (the stylesheet it came from, or empty) of the declaration that won
+    /// the cascade, or `None` when nothing declared the property. Used by inspection tooling; the
+    /// default implementation keeps no declarations.
+    fn winning_declaration(&self) -> Option<(CssOrigin, &str)> {
+        None
+    }
 }
 
 pub trait CssValue: Sized {
@@ -14,6 +14,28 @@ pub enum DocumentType {
     IframeSrcDoc,
 }
 
+/// A change made to a document after it was built, as recorded by
+/// [`Document::record_mutations`]. Consumers use these to work out which parts of the
+/// styled and laid-out tree must be recomputed instead of rebuilding everything.
+#[derive(PartialEq, Debug, Clone)]
+pub enum DomMutation {
+    /// An attribute (including `class`, `id` and `style`) was set or removed. `old_value` is
+    /// the value before the change, `None` when the attribute did not exist.
+    Attribute {
+        node: NodeId,
+        name: String,
+        old_value: Option<String>,
+    },
+    /// A class was added to an element's class list without going through its `class` attribute
+    Class { node: NodeId, name: String },
+    /// The data of a text node changed
+    Text { node: NodeId },
+    /// A child was attached to or detached from `parent`
+    ChildList { parent: NodeId },
+    /// A stylesheet was added to the document
+    Stylesheets,
+}
+
 /// Storage-agnostic document interface.
 ///
 /// All node data is accessed through `NodeId` handles. The concrete storage
@@ -138,7 +160,35 @@ pub trait Document<C: HasCssSystem>: Sized + Display + Debug + PartialEq + 'stat
     fn write(&self) -> String;
     fn write_from_node(&self, id: NodeId) -> String;
 
+    /// HTML serialization of the children of a node, like the DOM's `innerHTML`
+    fn inner_html(&self, id: NodeId) -> String;
+    /// HTML serialization of a node and its descendants, like the DOM's `outerHTML`
+    fn outer_html(&self, id: NodeId) -> String;
+    /// XML serialization of a node and its descendants, including the namespace declarations
+    /// needed to read it as a standalone XML document (for instance an inline `<svg>`)
+    fn outer_xml(&self, id: NodeId) -> String;
+
+    // Selector queries
+
+    /// The first element below `scope` matching a CSS selector list, like the DOM's `querySelector()`.
+    /// Returns an error when the selector cannot be parsed.
+    fn query_selector(&self, scope: NodeId, selectors: &str) -> gosub_shared::types::Result<Option<NodeId>>;
+    /// All elements below `scope` matching a CSS selector list in tree order, like the DOM's
+    /// `querySelectorAll()`
+    fn query_selector_all(&self, scope: NodeId, selectors: &str) -> gosub_shared::types::Result<Vec<NodeId>>;
+
     fn is_hovered(&self, _id: NodeId) -> bool {
         false
     }
+
+    // Mutation records
+
+    /// Start (`true`) or stop (`false`) recording [`DomMutation`]s. Stopping discards any
+    /// records that were not taken yet. Documents that do not track changes ignore this.
+    fn record_mutations(&mut self, _enabled: bool) {}
+
+    /// Drain the mutations recorded since the last call, oldest first
+    fn take_mutations(&mut self) -> Vec<DomMutation> {
+        Vec::new()
+    }
 }
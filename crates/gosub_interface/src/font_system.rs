@@ -1,8 +1,15 @@
 use parking_lot::Mutex;
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use crate::font::{FontBlob, FontError, FontStyle};
 
+mod line_break;
+mod vertical;
+
+pub use line_break::{measure_broken, shape_broken};
+pub use vertical::{measure_vertical, shape_vertical};
+
 // Value types
 
 /// CSS font-weight (100–900). Common constants provided for convenience.
@@ -45,6 +52,77 @@ impl Default for FontStretch {
     }
 }
 
+/// The code points an `@font-face` subset covers (CSS `unicode-range`), as inclusive
+/// `(start, end)` intervals. The default covers every code point.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnicodeRange(Vec<(u32, u32)>);
+
+impl UnicodeRange {
+    /// The largest Unicode scalar value.
+    const MAX: u32 = 0x10_FFFF;
+
+    /// Every code point (`U+0-10FFFF`, the descriptor's initial value).
+    pub fn all() -> Self {
+        Self(vec![(0, Self::MAX)])
+    }
+
+    /// Parse a `unicode-range` descriptor: comma- or whitespace-separated `U+XXXX`,
+    /// `U+XXXX-YYYY` and wildcard `U+4??` entries. Invalid entries are skipped (as the spec
+    /// drops them); `None` when no entry is valid.
+    pub fn parse(descriptor: &str) -> Option<Self> {
+        let intervals: Vec<(u32, u32)> = descriptor
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(Self::parse_entry)
+            .collect();
+        (!intervals.is_empty()).then_some(Self(intervals))
+    }
+
+    fn parse_entry(entry: &str) -> Option<(u32, u32)> {
+        let hex = entry.strip_prefix("U+").or_else(|| entry.strip_prefix("u+"))?;
+        let (start, end) = match hex.split_once('-') {
+            Some((start, end)) => (parse_hex(start)?, parse_hex(end)?),
+            None if hex.contains('?') => {
+                let digits = hex.trim_end_matches('?');
+                let wildcards = (hex.len() - digits.len()) as u32;
+                if digits.contains('?') || hex.len() > 6 {
+                    return None;
+                }
+                let base = if digits.is_empty() { 0 } else { parse_hex(digits)? };
+                let base = base << (4 * wildcards);
+                (base, base | ((1 << (4 * wildcards)) - 1))
+            }
+            None => {
+                let point = parse_hex(hex)?;
+                (point, point)
+            }
+        };
+        (start <= end && start <= Self::MAX).then_some((start, end.min(Self::MAX)))
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        self.0.iter().any(|&(start, end)| (start..=end).contains(&c))
+    }
+
+    /// The inclusive intervals of the range.
+    pub fn intervals(&self) -> &[(u32, u32)] {
+        &self.0
+    }
+}
+
+impl Default for UnicodeRange {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+fn parse_hex(digits: &str) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 6 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
 /// A CSS font-family query with full property set.
 ///
 /// `families` is a priority-ordered slice of family names, exactly as they appear
@@ -55,6 +133,10 @@ pub struct FontQuery<'a> {
     pub style: FontStyle,
     pub weight: FontWeight,
     pub stretch: FontStretch,
+    /// The code point the font must cover, if any. Selects between `@font-face` subsets of one
+    /// family registered with [`FontSystem::register_font_face`], in the font systems that keep
+    /// their ranges; `None` accepts any subset.
+    pub codepoint: Option<char>,
 }
 
 impl<'a> FontQuery<'a> {
@@ -64,6 +146,7 @@ impl<'a> FontQuery<'a> {
             style: FontStyle::Normal,
             weight: FontWeight::NORMAL,
             stretch: FontStretch::NORMAL,
+            codepoint: None,
         }
     }
 }
@@ -95,6 +178,9 @@ pub struct ShapedGlyph {
     pub id: u32,
     pub x: f32,
     pub y: f32,
+    /// Byte offset in the shaped text where the glyph's cluster starts. Glyphs an engine adds
+    /// itself (a hyphen at a break, an ellipsis) carry the offset of the text they stand after.
+    pub cluster: u32,
 }
 
 /// Decoration metrics for a shaped run, in pixels.
@@ -128,6 +214,32 @@ pub struct ShapedRun {
     /// Decoration metrics of the run's font, for underline/strikethrough painting.
     pub metrics: RunMetrics,
     pub glyphs: Vec<ShapedGlyph>,
+    /// Sideways text in a vertical writing mode: everything above is in a frame turned a quarter
+    /// turn about the shaped block's origin, so a rasterizer rotates by this before drawing the
+    /// run as it would a horizontal one.
+    pub rotation: GlyphRotation,
+}
+
+/// How a [`ShapedRun`]'s frame is turned relative to the shaped block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlyphRotation {
+    #[default]
+    None,
+    /// A quarter turn clockwise: glyph tops face right (`vertical-*`, `sideways-rl`).
+    Clockwise,
+    /// A quarter turn counter-clockwise: glyph tops face left (`sideways-lr`).
+    CounterClockwise,
+}
+
+impl GlyphRotation {
+    /// The rotation in radians, clockwise positive as on a y-down canvas.
+    pub fn radians(self) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Clockwise => std::f64::consts::FRAC_PI_2,
+            Self::CounterClockwise => -std::f64::consts::FRAC_PI_2,
+        }
+    }
 }
 
 /// The complete result of shaping a string: positioned glyph runs plus metrics.
@@ -162,7 +274,8 @@ impl ShapedText {
 
 // Text style for measurement
 
-/// CSS `text-align`, applied during shaping within [`TextStyle::max_width`].
+/// CSS `text-align`, applied during shaping within [`TextStyle::max_width`]. `Start` and `End`
+/// follow [`TextStyle::direction`]: `Start` is the left edge for LTR text and the right for RTL.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextAlign {
     #[default]
@@ -172,6 +285,401 @@ pub enum TextAlign {
     Justify,
 }
 
+/// CSS `direction`: the base direction of a paragraph, which the engine's Unicode Bidirectional
+/// Algorithm resolves mixed-direction runs against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl TextDirection {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [("ltr", Self::Ltr), ("rtl", Self::Rtl)]
+            .into_iter()
+            .find_map(|(name, direction)| name.eq_ignore_ascii_case(keyword).then_some(direction))
+    }
+
+    pub fn is_rtl(self) -> bool {
+        self == Self::Rtl
+    }
+
+    /// `text` led by this direction's mark (U+200E LRM / U+200F RLM) whenever an engine that takes
+    /// the paragraph direction from the first strong character (UAX #9 rules P2/P3) could pick
+    /// the other one. The mark is invisible and zero-width, so engines without a base-direction
+    /// setting shape this instead of `text`.
+    pub fn with_base_mark(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Rtl => Cow::Owned(format!("\u{200F}{text}")),
+            Self::Ltr if text.chars().any(is_rtl_char) => Cow::Owned(format!("\u{200E}{text}")),
+            Self::Ltr => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Whether `c` sits in a right-to-left script block (Hebrew, Arabic, Syriac, Thaana, NKo,
+/// Samaritan, Mandaic, their presentation forms and the historic RTL scripts of plane 1).
+fn is_rtl_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}' | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+/// CSS `writing-mode`: whether lines run horizontally or vertically, and which way they stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WritingMode {
+    #[default]
+    HorizontalTb,
+    VerticalRl,
+    VerticalLr,
+    SidewaysRl,
+    SidewaysLr,
+}
+
+impl WritingMode {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [
+            ("horizontal-tb", Self::HorizontalTb),
+            ("vertical-rl", Self::VerticalRl),
+            ("vertical-lr", Self::VerticalLr),
+            ("sideways-rl", Self::SidewaysRl),
+            ("sideways-lr", Self::SidewaysLr),
+        ]
+        .into_iter()
+        .find_map(|(name, mode)| name.eq_ignore_ascii_case(keyword).then_some(mode))
+    }
+
+    pub fn is_vertical(self) -> bool {
+        self != Self::HorizontalTb
+    }
+
+    /// Whether successive lines (and blocks) stack from right to left.
+    pub fn is_block_rtl(self) -> bool {
+        matches!(self, Self::VerticalRl | Self::SidewaysRl)
+    }
+}
+
+/// CSS `text-orientation`: how glyphs sit in a vertical line. `Mixed` sets CJK characters upright
+/// and turns the rest sideways; the `sideways-*` writing modes turn everything regardless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextOrientation {
+    #[default]
+    Mixed,
+    Upright,
+    Sideways,
+}
+
+impl TextOrientation {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [
+            ("mixed", Self::Mixed),
+            ("upright", Self::Upright),
+            ("sideways", Self::Sideways),
+            // The legacy name of `sideways`.
+            ("sideways-right", Self::Sideways),
+        ]
+        .into_iter()
+        .find_map(|(name, orientation)| name.eq_ignore_ascii_case(keyword).then_some(orientation))
+    }
+}
+
+/// CSS `word-break`: where lines may break inside words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WordBreak {
+    #[default]
+    Normal,
+    /// Between any two letters, which also narrows the min-content width.
+    BreakAll,
+    /// Never between CJK letters, which then only break at spaces like other words.
+    KeepAll,
+}
+
+impl WordBreak {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [
+            ("normal", Self::Normal),
+            ("break-all", Self::BreakAll),
+            ("keep-all", Self::KeepAll),
+            // Deprecated; behaves as `overflow-wrap: anywhere` would, which is close enough.
+            ("break-word", Self::BreakAll),
+        ]
+        .into_iter()
+        .find_map(|(name, word_break)| name.eq_ignore_ascii_case(keyword).then_some(word_break))
+    }
+}
+
+/// CSS `overflow-wrap` (legacy name `word-wrap`): whether a word too long for its line may break
+/// at an arbitrary point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OverflowWrap {
+    #[default]
+    Normal,
+    /// Breaks overflowing words, and the break points count towards the min-content width.
+    Anywhere,
+    /// Breaks overflowing words without narrowing the min-content width; the layouter measures
+    /// min-content as `Normal`.
+    BreakWord,
+}
+
+impl OverflowWrap {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [
+            ("normal", Self::Normal),
+            ("anywhere", Self::Anywhere),
+            ("break-word", Self::BreakWord),
+        ]
+        .into_iter()
+        .find_map(|(name, wrap)| name.eq_ignore_ascii_case(keyword).then_some(wrap))
+    }
+}
+
+/// CSS `hyphens`. Soft hyphens (U+00AD) in the text are the hyphenation points: `Auto` expects
+/// the caller to have inserted them from a dictionary, and `None` to have removed them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Hyphens {
+    None,
+    #[default]
+    Manual,
+    Auto,
+}
+
+impl Hyphens {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [("none", Self::None), ("manual", Self::Manual), ("auto", Self::Auto)]
+            .into_iter()
+            .find_map(|(name, hyphens)| name.eq_ignore_ascii_case(keyword).then_some(hyphens))
+    }
+}
+
+/// CSS `text-overflow`: how a line that overflows [`TextStyle::max_width`] ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextOverflow {
+    #[default]
+    Clip,
+    /// Truncated to fit, ending in `…`.
+    Ellipsis,
+}
+
+impl TextOverflow {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [("clip", Self::Clip), ("ellipsis", Self::Ellipsis)]
+            .into_iter()
+            .find_map(|(name, overflow)| name.eq_ignore_ascii_case(keyword).then_some(overflow))
+    }
+}
+
+/// The line-breaking controls text is shaped with. None of the font systems implements them, so
+/// text that uses them is shaped by [`shape_broken`] on top of the engine's shaping (see
+/// [`TextStyle::needs_line_breaker`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LineBreaking {
+    pub word_break: WordBreak,
+    pub overflow_wrap: OverflowWrap,
+    pub hyphens: Hyphens,
+    pub text_overflow: TextOverflow,
+    /// CSS `line-clamp`: at most this many lines, the last one ending in `…` when text was cut.
+    pub line_clamp: Option<u32>,
+    /// CSS `white-space: nowrap`: each paragraph stays on one line, and `max_width` only says
+    /// where `text_overflow` cuts it.
+    pub nowrap: bool,
+}
+
+/// One OpenType feature setting, e.g. `liga` off or `ss01` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontFeature {
+    pub tag: [u8; 4],
+    /// `0` disables the feature, `1` enables it; larger values pick an alternate.
+    pub value: u32,
+}
+
+impl FontFeature {
+    pub const fn new(tag: &[u8; 4], value: u32) -> Self {
+        Self { tag: *tag, value }
+    }
+
+    /// The tag as text (always ASCII once parsed).
+    pub fn tag_str(&self) -> &str {
+        std::str::from_utf8(&self.tag).unwrap_or("????")
+    }
+
+    /// Parse a CSS `font-feature-settings` value: `normal`, or a comma-separated list of
+    /// `"tag"` entries each optionally followed by an integer, `on` or `off`. A malformed
+    /// entry invalidates the whole declaration, as in CSS.
+    pub fn parse_settings(value: &str) -> Option<Vec<Self>> {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("normal") {
+            return Some(Vec::new());
+        }
+        value
+            .split(',')
+            .map(|entry| {
+                let (tag, rest) = parse_tag(entry)?;
+                let value = match rest {
+                    "" => 1,
+                    on if on.eq_ignore_ascii_case("on") => 1,
+                    off if off.eq_ignore_ascii_case("off") => 0,
+                    n => n.parse().ok()?,
+                };
+                Some(Self { tag, value })
+            })
+            .collect()
+    }
+
+    /// The features a `font-variant-*` value turns on or off, for any of the `ligatures`,
+    /// `caps`, `numeric`, `position`, `east-asian` and `alternates` longhands (their keywords
+    /// don't overlap). `normal` and unknown keywords contribute nothing.
+    pub fn from_variant(value: &str) -> Vec<Self> {
+        value
+            .split_whitespace()
+            .filter_map(|keyword| VARIANT_FEATURES.iter().find(|(k, _)| k.eq_ignore_ascii_case(keyword)))
+            .flat_map(|(_, features)| features.iter().map(|&(tag, value)| Self::new(tag, value)))
+            .collect()
+    }
+}
+
+/// `font-variant-*` keywords and the OpenType features they set (CSS Fonts 4 §6).
+type VariantFeatures = (&'static str, &'static [(&'static [u8; 4], u32)]);
+const VARIANT_FEATURES: &[VariantFeatures] = &[
+    // font-variant-ligatures
+    (
+        "none",
+        &[(b"liga", 0), (b"clig", 0), (b"dlig", 0), (b"hlig", 0), (b"calt", 0)],
+    ),
+    ("common-ligatures", &[(b"liga", 1), (b"clig", 1)]),
+    ("no-common-ligatures", &[(b"liga", 0), (b"clig", 0)]),
+    ("discretionary-ligatures", &[(b"dlig", 1)]),
+    ("no-discretionary-ligatures", &[(b"dlig", 0)]),
+    ("historical-ligatures", &[(b"hlig", 1)]),
+    ("no-historical-ligatures", &[(b"hlig", 0)]),
+    ("contextual", &[(b"calt", 1)]),
+    ("no-contextual", &[(b"calt", 0)]),
+    // font-variant-caps
+    ("small-caps", &[(b"smcp", 1)]),
+    ("all-small-caps", &[(b"c2sc", 1), (b"smcp", 1)]),
+    ("petite-caps", &[(b"pcap", 1)]),
+    ("all-petite-caps", &[(b"c2pc", 1), (b"pcap", 1)]),
+    ("unicase", &[(b"unic", 1)]),
+    ("titling-caps", &[(b"titl", 1)]),
+    // font-variant-numeric
+    ("lining-nums", &[(b"lnum", 1)]),
+    ("oldstyle-nums", &[(b"onum", 1)]),
+    ("proportional-nums", &[(b"pnum", 1)]),
+    ("tabular-nums", &[(b"tnum", 1)]),
+    ("diagonal-fractions", &[(b"frac", 1)]),
+    ("stacked-fractions", &[(b"afrc", 1)]),
+    ("ordinal", &[(b"ordn", 1)]),
+    ("slashed-zero", &[(b"zero", 1)]),
+    // font-variant-position
+    ("sub", &[(b"subs", 1)]),
+    ("super", &[(b"sups", 1)]),
+    // font-variant-east-asian
+    ("jis78", &[(b"jp78", 1)]),
+    ("jis83", &[(b"jp83", 1)]),
+    ("jis90", &[(b"jp90", 1)]),
+    ("jis04", &[(b"jp04", 1)]),
+    ("simplified", &[(b"smpl", 1)]),
+    ("traditional", &[(b"trad", 1)]),
+    ("full-width", &[(b"fwid", 1)]),
+    ("proportional-width", &[(b"pwid", 1)]),
+    ("ruby", &[(b"ruby", 1)]),
+    // font-variant-alternates
+    ("historical-forms", &[(b"hist", 1)]),
+];
+
+/// The CSS form, `"liga" 0`, as accepted back by `font-feature-settings`.
+impl std::fmt::Display for FontFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" {}", self.tag_str(), self.value)
+    }
+}
+
+/// One variable-font axis position, e.g. `wght` at 650.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontVariation {
+    pub tag: [u8; 4],
+    pub value: f32,
+}
+
+impl FontVariation {
+    pub const fn new(tag: &[u8; 4], value: f32) -> Self {
+        Self { tag: *tag, value }
+    }
+
+    pub fn tag_str(&self) -> &str {
+        std::str::from_utf8(&self.tag).unwrap_or("????")
+    }
+
+    /// Parse a CSS `font-variation-settings` value: `normal`, or a comma-separated list of
+    /// `"axis" <number>` entries. A malformed entry invalidates the whole declaration.
+    pub fn parse_settings(value: &str) -> Option<Vec<Self>> {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("normal") {
+            return Some(Vec::new());
+        }
+        value
+            .split(',')
+            .map(|entry| {
+                let (tag, rest) = parse_tag(entry)?;
+                let value: f32 = rest.parse().ok()?;
+                value.is_finite().then_some(Self { tag, value })
+            })
+            .collect()
+    }
+}
+
+/// The CSS form, `"wght" 650`, as accepted back by `font-variation-settings`.
+impl std::fmt::Display for FontVariation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" {}", self.tag_str(), self.value)
+    }
+}
+
+/// Split a settings entry into its quoted (or bare) four-character ASCII tag and the rest.
+fn parse_tag(entry: &str) -> Option<([u8; 4], &str)> {
+    let entry = entry.trim();
+    let (tag, rest) = match entry.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let body = &entry[1..];
+            let end = body.find(quote)?;
+            (&body[..end], &body[end + 1..])
+        }
+        _ => entry.split_at(entry.find(char::is_whitespace).unwrap_or(entry.len())),
+    };
+    let tag: [u8; 4] = tag.as_bytes().try_into().ok()?;
+    tag.iter()
+        .all(|b| (0x20..=0x7E).contains(b))
+        .then_some((tag, rest.trim()))
+}
+
+/// CSS `font-kerning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontKerning {
+    /// The engine decides; every engine here kerns.
+    #[default]
+    Auto,
+    Normal,
+    None,
+}
+
+impl FontKerning {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.trim();
+        [("auto", Self::Auto), ("normal", Self::Normal), ("none", Self::None)]
+            .into_iter()
+            .find_map(|(name, kerning)| name.eq_ignore_ascii_case(keyword).then_some(kerning))
+    }
+}
+
 /// CSS-resolved text style passed to [`FontSystem::measure`].
 ///
 /// Carries everything an engine needs to lay out a run of text: the family (the implementation
@@ -191,12 +699,29 @@ pub struct TextStyle {
     /// Extra spacing between characters in px (CSS `letter-spacing`; 0 = `normal`). Affects the
     /// measured width, so it must match what the renderer draws.
     pub letter_spacing: f32,
-    /// `Some(px)` soft-wraps at that width; `None` = a single unbroken line.
+    /// `Some(px)` soft-wraps at that width; `None` = a single unbroken line. In a vertical
+    /// `writing_mode` this limits the line length, which runs down the page.
     pub max_width: Option<f32>,
     /// Alignment of the shaped lines within `max_width` (no-op when `max_width` is `None`).
     pub align: TextAlign,
+    /// Base direction of the paragraph; `align` is relative to it.
+    pub direction: TextDirection,
+    /// Vertical modes are shaped by [`shape_vertical`] on top of the engine's horizontal shaping.
+    pub writing_mode: WritingMode,
+    /// Glyph orientation in a vertical `writing_mode`.
+    pub orientation: TextOrientation,
     /// Device-pixel scale (DPI). `1.0` = CSS pixels.
     pub display_scale: f32,
+    /// OpenType features from the `font-variant-*` longhands followed by `font-feature-settings`,
+    /// so later entries override earlier ones for the same tag.
+    pub features: Vec<FontFeature>,
+    /// Variable-font axis positions (`font-variation-settings`).
+    pub variations: Vec<FontVariation>,
+    pub kerning: FontKerning,
+    /// CSS `font-optical-sizing: auto`: set the `opsz` axis to the font size.
+    pub optical_sizing: bool,
+    /// `word-break`, `overflow-wrap`, `hyphens`, `text-overflow` and `line-clamp`.
+    pub line_breaking: LineBreaking,
 }
 
 impl TextStyle {
@@ -212,8 +737,64 @@ impl TextStyle {
             letter_spacing: 0.0,
             max_width: None,
             align: TextAlign::Start,
+            direction: TextDirection::Ltr,
+            writing_mode: WritingMode::HorizontalTb,
+            orientation: TextOrientation::Mixed,
             display_scale: 1.0,
+            features: Vec::new(),
+            variations: Vec::new(),
+            kerning: FontKerning::Auto,
+            optical_sizing: true,
+            line_breaking: LineBreaking::default(),
+        }
+    }
+
+    /// Whether `text` must be shaped by [`shape_broken`]: a wrap width is set and the text uses a
+    /// line-breaking control the engines lack, including a soft hyphen that may need drawing.
+    /// `nowrap` alone is left to the engine, which a wide enough `max_width` keeps on one line.
+    pub fn needs_line_breaker(&self, text: &str) -> bool {
+        let breaking = &self.line_breaking;
+        self.max_width.is_some()
+            && (breaking.word_break != WordBreak::Normal
+                || breaking.overflow_wrap != OverflowWrap::Normal
+                || breaking.text_overflow != TextOverflow::Clip
+                || breaking.line_clamp.is_some()
+                || (breaking.hyphens != Hyphens::None && text.contains('\u{AD}')))
+    }
+
+    /// The OpenType features to shape with: `kern` as `font-kerning` asks, then
+    /// [`TextStyle::features`], one entry per tag with the last setting winning.
+    pub fn opentype_features(&self) -> Vec<FontFeature> {
+        let kern = match self.kerning {
+            FontKerning::Auto => None,
+            FontKerning::Normal => Some(FontFeature::new(b"kern", 1)),
+            FontKerning::None => Some(FontFeature::new(b"kern", 0)),
+        };
+        let mut out: Vec<FontFeature> = Vec::with_capacity(self.features.len() + 1);
+        for feature in kern.iter().chain(&self.features) {
+            match out.iter_mut().find(|f| f.tag == feature.tag) {
+                Some(existing) => existing.value = feature.value,
+                None => out.push(*feature),
+            }
+        }
+        out
+    }
+
+    /// The variation axes to shape with: `opsz` at the font size when optical sizing is on and
+    /// no explicit `opsz` is given, then [`TextStyle::variations`] (last setting per axis wins).
+    /// Axes a font lacks are ignored by the engines.
+    pub fn variation_axes(&self) -> Vec<FontVariation> {
+        let mut out: Vec<FontVariation> = Vec::with_capacity(self.variations.len() + 1);
+        if self.optical_sizing && !self.variations.iter().any(|v| &v.tag == b"opsz") {
+            out.push(FontVariation::new(b"opsz", self.size));
+        }
+        for variation in &self.variations {
+            match out.iter_mut().find(|v| v.tag == variation.tag) {
+                Some(existing) => existing.value = variation.value,
+                None => out.push(*variation),
+            }
         }
+        out
     }
 }
 
@@ -240,6 +821,17 @@ pub trait FontSystem: Send + Sync + 'static {
     /// `family_override` assigns a logical name CSS can reference; `None` uses the font's own name.
     fn register_font(&mut self, data: Vec<u8>, family_override: Option<&str>) -> Result<(), FontError>;
 
+    /// Register one `@font-face` subset of the web font `family`, covering `range`.
+    ///
+    /// Every subset of a family is registered under that same family name; [`FontSystem::resolve`]
+    /// then picks the subset covering [`FontQuery::codepoint`]. Only the Parley font system
+    /// keeps the ranges. The default, which the Skia, Pango and cosmic-text systems use, ignores
+    /// `range` and registers the face like [`FontSystem::register_font`] would: the subsets are
+    /// then faces of one family, chosen between by the font system's own fallback, if at all.
+    fn register_font_face(&mut self, data: Vec<u8>, family: &str, _range: &UnicodeRange) -> Result<(), FontError> {
+        self.register_font(data, Some(family))
+    }
+
     /// Resolve a CSS font query to a concrete font, including its raw bytes.
     ///
     /// Walks `query.families` in priority order (generic keywords like `sans-serif` map to the
@@ -295,3 +887,126 @@ pub trait FontSystem: Send + Sync + 'static {
 pub trait HasFontSystem {
     fn font_system(&self) -> Arc<Mutex<dyn FontSystem>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_range_parses_points_intervals_and_wildcards() {
+        let range = UnicodeRange::parse("U+0000-00FF, U+0131, U+4??").unwrap();
+        assert_eq!(range.intervals(), &[(0, 0xFF), (0x131, 0x131), (0x400, 0x4FF)]);
+        assert!(range.contains('A'));
+        assert!(range.contains('\u{0131}'));
+        assert!(range.contains('Ж'), "U+0416 is covered by U+4??");
+        assert!(!range.contains('Ω'));
+    }
+
+    #[test]
+    fn unicode_range_skips_invalid_entries() {
+        assert_eq!(UnicodeRange::parse("bogus, U+20-10"), None);
+        let range = UnicodeRange::parse("U+30-39 garbage u+ff").unwrap();
+        assert_eq!(range.intervals(), &[(0x30, 0x39), (0xFF, 0xFF)]);
+        assert!(UnicodeRange::default().contains('\u{10FFFF}'));
+    }
+
+    #[test]
+    fn default_font_face_registration_ignores_the_range() {
+        /// Records the fonts registered through it.
+        #[derive(Default)]
+        struct Registry(Vec<(Vec<u8>, Option<String>)>);
+
+        impl FontSystem for Registry {
+            fn register_font(&mut self, data: Vec<u8>, family_override: Option<&str>) -> Result<(), FontError> {
+                self.0.push((data, family_override.map(str::to_string)));
+                Ok(())
+            }
+
+            fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
+                Err(FontError::FontNotFound(query.families.join(", ")))
+            }
+
+            fn families(&mut self) -> Vec<String> {
+                Vec::new()
+            }
+
+            fn shape(&mut self, _text: &str, _style: &TextStyle) -> ShapedText {
+                ShapedText::empty()
+            }
+        }
+
+        let mut fonts = Registry::default();
+        let latin = UnicodeRange::parse("U+0000-00FF").unwrap();
+        let cyrillic = UnicodeRange::parse("U+0400-04FF").unwrap();
+        fonts.register_font_face(vec![1], "Web Sans", &latin).unwrap();
+        fonts.register_font_face(vec![2], "Web Sans", &cyrillic).unwrap();
+        assert_eq!(
+            fonts.0,
+            vec![
+                (vec![1], Some("Web Sans".to_string())),
+                (vec![2], Some("Web Sans".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn feature_and_variation_settings_parse() {
+        let features = FontFeature::parse_settings("\"liga\" off, 'ss01', \"salt\" 3").unwrap();
+        assert_eq!(
+            features,
+            vec![
+                FontFeature::new(b"liga", 0),
+                FontFeature::new(b"ss01", 1),
+                FontFeature::new(b"salt", 3)
+            ]
+        );
+        assert_eq!(features[2].to_string(), "\"salt\" 3");
+        assert_eq!(FontFeature::parse_settings("normal"), Some(vec![]));
+        assert_eq!(FontFeature::parse_settings("\"toolong\" 1"), None);
+
+        let axes = FontVariation::parse_settings("\"wght\" 650, \"wdth\" 80.5").unwrap();
+        assert_eq!(
+            axes,
+            vec![FontVariation::new(b"wght", 650.0), FontVariation::new(b"wdth", 80.5)]
+        );
+        assert_eq!(FontVariation::parse_settings("\"wght\""), None);
+    }
+
+    #[test]
+    fn text_style_merges_variants_kerning_and_optical_size() {
+        let mut style = TextStyle::new("serif", 18.0);
+        style.kerning = FontKerning::None;
+        style.features = FontFeature::from_variant("small-caps no-common-ligatures");
+        style.features.push(FontFeature::new(b"liga", 1));
+        assert_eq!(
+            style.opentype_features(),
+            vec![
+                FontFeature::new(b"kern", 0),
+                FontFeature::new(b"smcp", 1),
+                FontFeature::new(b"liga", 1),
+                FontFeature::new(b"clig", 0)
+            ],
+            "font-feature-settings overrides the variant's liga"
+        );
+
+        assert_eq!(style.variation_axes(), vec![FontVariation::new(b"opsz", 18.0)]);
+        style.variations = vec![FontVariation::new(b"opsz", 12.0)];
+        assert_eq!(style.variation_axes(), vec![FontVariation::new(b"opsz", 12.0)]);
+        style.variations.clear();
+        style.optical_sizing = false;
+        assert!(style.variation_axes().is_empty());
+    }
+
+    #[test]
+    fn base_mark_only_when_the_engine_could_guess_wrong() {
+        assert_eq!(TextDirection::from_keyword(" RTL "), Some(TextDirection::Rtl));
+        assert_eq!(TextDirection::from_keyword("auto"), None);
+
+        assert!(matches!(
+            TextDirection::Ltr.with_base_mark("hello"),
+            Cow::Borrowed("hello")
+        ));
+        assert_eq!(TextDirection::Ltr.with_base_mark("שלום!"), "\u{200E}שלום!");
+        assert_eq!(TextDirection::Rtl.with_base_mark("hello"), "\u{200F}hello");
+    }
+}
@@ -0,0 +1,421 @@
+//! Line-breaking controls, laid out on top of an engine's shaping.
+//!
+//! The font systems wrap at spaces and between CJK characters by themselves, but know nothing of
+//! `word-break`, `overflow-wrap`, hyphenation, `text-overflow` or `line-clamp`. So
+//! [`shape_broken`] fills the lines itself, greedily, measuring each unbreakable piece of text
+//! through the same [`FontSystem`], then shapes every line unwrapped and stacks them:
+//!
+//! - a line may break after whitespace, between two CJK characters (not under `keep-all`), after
+//!   a soft hyphen, between any two letters under `word-break: break-all`, and anywhere in a word
+//!   too long for its line under `overflow-wrap: anywhere`/`break-word`;
+//! - a line broken at a soft hyphen ends in a visible `-`;
+//! - `line-clamp` drops the lines past the limit and ends the last one kept in `…`, as does
+//!   `text-overflow: ellipsis` for every line wider than `max_width`.
+//!
+//! Lines are aligned like the engines align them, except that `justify` sets them start-aligned.
+
+use super::vertical::{map_clusters, split_inclusive_ranges};
+use super::{
+    FontSystem, Hyphens, LineBreaking, OverflowWrap, ShapedGlyph, ShapedRun, ShapedText, TextAlign, TextOverflow,
+    TextStyle, WordBreak,
+};
+use std::borrow::Cow;
+use std::ops::Range;
+
+const SOFT_HYPHEN: char = '\u{AD}';
+/// Drawn where a line breaks at a soft hyphen.
+const HYPHEN: &str = "-";
+const ELLIPSIS: &str = "\u{2026}";
+
+/// One line of the broken text, as a byte range.
+struct Line {
+    range: Range<usize>,
+    /// Broken at a soft hyphen, which is drawn.
+    hyphen: bool,
+    /// The last line `line-clamp` keeps, ending in an ellipsis.
+    clamped: bool,
+}
+
+/// Shape `text` with `style`'s line-breaking controls. Font systems call this from
+/// [`FontSystem::shape`] whenever [`TextStyle::needs_line_breaker`]; it shapes through `fs` with
+/// unwrapped copies of `style`.
+pub fn shape_broken(fs: &mut dyn FontSystem, text: &str, style: &TextStyle) -> ShapedText {
+    let mut plain = style.clone();
+    plain.line_breaking = LineBreaking::default();
+    plain.max_width = None;
+    plain.align = TextAlign::Start;
+    let breaking = style.line_breaking;
+    let limit = style.max_width.unwrap_or(f32::INFINITY);
+
+    let mut lines = break_lines(fs, &plain, text, &breaking, limit);
+    if let Some(clamp) = breaking.line_clamp.map(|n| n.max(1) as usize) {
+        if lines.len() > clamp {
+            lines.truncate(clamp);
+            if let Some(last) = lines.last_mut() {
+                last.clamped = true;
+                last.hyphen = false;
+            }
+        }
+    }
+
+    let shaped_lines: Vec<ShapedText> = lines
+        .iter()
+        .map(|line| {
+            let source = text[line.range.clone()].trim_end_matches('\n');
+            let mut content = visible(source).into_owned();
+            if line.hyphen {
+                content.push_str(HYPHEN);
+            }
+            let overflows = breaking.text_overflow == TextOverflow::Ellipsis && fs.measure(&content, &plain).0 > limit;
+            if line.clamped || overflows {
+                content = ellipsize(fs, &plain, &content, limit);
+            }
+            let mut shaped = fs.shape(&content, &plain);
+            // Map offsets in `content` back to `text`, past the soft hyphens `visible` dropped.
+            let offsets: Vec<u32> = source
+                .char_indices()
+                .filter(|&(_, c)| c != SOFT_HYPHEN)
+                .flat_map(|(i, c)| std::iter::repeat_n((line.range.start + i) as u32, c.len_utf8()))
+                .collect();
+            let end = (line.range.start + source.len()) as u32;
+            map_clusters(&mut shaped, |cluster| {
+                offsets.get(cluster as usize).copied().unwrap_or(end)
+            });
+            shaped
+        })
+        .collect();
+
+    let Some((first_index, first)) = shaped_lines.iter().enumerate().find(|(_, s)| !s.is_empty()) else {
+        return ShapedText::empty();
+    };
+    let line_height = style.line_height.unwrap_or(first.line_height);
+    let ascent = first_index as f32 * line_height + first.ascent;
+    let longest = shaped_lines.iter().map(|s| s.width).fold(0.0, f32::max);
+    let extent = if limit.is_finite() { limit.max(longest) } else { longest };
+    let height = line_height * shaped_lines.len() as f32;
+
+    let mut runs = Vec::new();
+    for (k, shaped) in shaped_lines.into_iter().enumerate() {
+        let dx = match (style.align, style.direction.is_rtl()) {
+            (TextAlign::Center, _) => (extent - shaped.width) / 2.0,
+            (TextAlign::End, false) | (TextAlign::Start | TextAlign::Justify, true) => extent - shaped.width,
+            _ => 0.0,
+        };
+        let dy = k as f32 * line_height;
+        runs.extend(shaped.runs.into_iter().map(|run| offset_run(run, dx, dy)));
+    }
+
+    ShapedText {
+        runs,
+        width: longest,
+        height,
+        line_height,
+        ascent,
+    }
+}
+
+/// [`shape_broken`]'s bounding box, for [`FontSystem::measure`].
+pub fn measure_broken(fs: &mut dyn FontSystem, text: &str, style: &TextStyle) -> (f32, f32) {
+    let shaped = shape_broken(fs, text, style);
+    (shaped.width, shaped.height)
+}
+
+/// Fill lines no wider than `limit` greedily, piece by piece, measuring with `plain`.
+fn break_lines(
+    fs: &mut dyn FontSystem,
+    plain: &TextStyle,
+    text: &str,
+    breaking: &LineBreaking,
+    limit: f32,
+) -> Vec<Line> {
+    let mut width = |s: &str| {
+        if s.is_empty() {
+            0.0
+        } else {
+            fs.measure(&visible(s), plain).0
+        }
+    };
+    let hyphenates = breaking.hyphens != Hyphens::None;
+    let hyphen_width = if hyphenates { width(HYPHEN) } else { 0.0 };
+    let breaks_overflow = breaking.overflow_wrap != OverflowWrap::Normal;
+    let ends_hyphenated = |range: &Range<usize>| hyphenates && text[range.clone()].ends_with(SOFT_HYPHEN);
+
+    let mut lines = Vec::new();
+    for paragraph in split_inclusive_ranges(text, '\n') {
+        if breaking.nowrap {
+            lines.push(Line {
+                range: paragraph,
+                hyphen: false,
+                clamped: false,
+            });
+            continue;
+        }
+        let mut line = paragraph.start..paragraph.start;
+        let mut advance = 0.0;
+        for piece in break_pieces(&text[paragraph.clone()], paragraph.start, breaking) {
+            let piece_text = text[piece.clone()].trim_end_matches('\n');
+            // Trailing spaces hang past the line's end; a soft hyphen taken as a break is drawn.
+            let body = width(piece_text.trim_end_matches(is_break_space))
+                + if ends_hyphenated(&piece) { hyphen_width } else { 0.0 };
+            if !line.is_empty() && advance + body > limit {
+                lines.push(Line {
+                    hyphen: ends_hyphenated(&line),
+                    range: line.clone(),
+                    clamped: false,
+                });
+                line = piece.start..piece.start;
+                advance = 0.0;
+            }
+            if line.is_empty() && breaks_overflow && body > limit {
+                // The piece overflows a line of its own, so break it between any two clusters.
+                for cluster in clusters(piece_text.trim_end_matches(is_break_space), piece.start) {
+                    let cluster_width = width(&text[cluster.clone()]);
+                    if !line.is_empty() && advance + cluster_width > limit {
+                        lines.push(Line {
+                            range: line.clone(),
+                            hyphen: false,
+                            clamped: false,
+                        });
+                        line = cluster.start..cluster.start;
+                        advance = 0.0;
+                    }
+                    line.end = cluster.end;
+                    advance += cluster_width;
+                }
+                line.end = piece.end;
+                continue;
+            }
+            line.end = piece.end;
+            advance += width(piece_text);
+        }
+        lines.push(Line {
+            range: line,
+            hyphen: false,
+            clamped: false,
+        });
+    }
+    lines
+}
+
+/// `content` cut to the longest run of whole clusters that fits `limit` with an ellipsis after it.
+fn ellipsize(fs: &mut dyn FontSystem, plain: &TextStyle, content: &str, limit: f32) -> String {
+    let with_ellipsis = |end: usize| format!("{}{ELLIPSIS}", content[..end].trim_end());
+    let ends: Vec<usize> = std::iter::once(0)
+        .chain(clusters(content, 0).map(|cluster| cluster.end))
+        .collect();
+    // Binary search for the most clusters that still fit; none at all leaves the bare ellipsis.
+    let (mut lo, mut hi) = (0, ends.len() - 1);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if fs.measure(&with_ellipsis(ends[mid]), plain).0 <= limit {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    with_ellipsis(ends[lo])
+}
+
+/// `text` without its soft hyphens, which only mark where a word may break.
+fn visible(text: &str) -> Cow<'_, str> {
+    if text.contains(SOFT_HYPHEN) {
+        Cow::Owned(text.chars().filter(|&c| c != SOFT_HYPHEN).collect())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// The unbreakable pieces of `text` (byte ranges shifted by `base`), each carrying its trailing
+/// whitespace.
+fn break_pieces(text: &str, base: usize, breaking: &LineBreaking) -> Vec<Range<usize>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if let Some(p) = prev {
+            let breaks = if is_break_space(c) || is_mark(c) {
+                false
+            } else if is_break_space(p) || (p == SOFT_HYPHEN && breaking.hyphens != Hyphens::None) {
+                true
+            } else {
+                match breaking.word_break {
+                    WordBreak::BreakAll => true,
+                    WordBreak::Normal => is_cjk(p) && is_cjk(c),
+                    WordBreak::KeepAll => false,
+                }
+            };
+            if breaks {
+                pieces.push(base + start..base + i);
+                start = i;
+            }
+        }
+        prev = Some(c);
+    }
+    if start < text.len() {
+        pieces.push(base + start..base + text.len());
+    }
+    pieces
+}
+
+/// `text`'s characters with the combining marks that follow them, as byte ranges shifted by
+/// `base`: the points an overflowing word may break at, short of real grapheme clusters.
+fn clusters(text: &str, base: usize) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, c) = chars.next()?;
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_mark(c) {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        Some(base + start..base + end)
+    })
+}
+
+/// Whitespace a line may break after; the no-break spaces hold their neighbours together.
+fn is_break_space(c: char) -> bool {
+    c.is_whitespace() && !matches!(c, '\u{A0}' | '\u{2007}' | '\u{202F}')
+}
+
+/// Combining marks, joiners and variation selectors, which stay with the character before them.
+fn is_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Whether `c` is a CJK letter, which a line may break on either side of: the Han, kana, Hangul
+/// and fullwidth blocks.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1100}'..='\u{11FF}'
+            | '\u{2E80}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF00}'..='\u{FFEF}'
+            | '\u{20000}'..='\u{3FFFD}'
+    )
+}
+
+/// Move a shaped run by `(dx, dy)`.
+fn offset_run(run: ShapedRun, dx: f32, dy: f32) -> ShapedRun {
+    ShapedRun {
+        glyphs: run
+            .glyphs
+            .iter()
+            .map(|g| ShapedGlyph {
+                x: g.x + dx,
+                y: g.y + dy,
+                ..*g
+            })
+            .collect(),
+        x: run.x + dx,
+        baseline: run.baseline + dy,
+        ..run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font_system::vertical::tests::Mono;
+
+    fn style(max: f32, breaking: LineBreaking) -> TextStyle {
+        let mut style = TextStyle::new("mono", 10.0);
+        style.max_width = Some(max);
+        style.line_breaking = breaking;
+        style
+    }
+
+    fn glyph_counts(shaped: &ShapedText) -> Vec<usize> {
+        shaped.runs.iter().map(|run| run.glyphs.len()).collect()
+    }
+
+    #[test]
+    fn overflowing_words_break_only_when_allowed() {
+        let normal = style(35.0, LineBreaking::default());
+        assert!(!normal.needs_line_breaker("abcdefgh"));
+
+        let anywhere = style(
+            35.0,
+            LineBreaking {
+                overflow_wrap: OverflowWrap::Anywhere,
+                ..Default::default()
+            },
+        );
+        assert!(anywhere.needs_line_breaker("abcdefgh"));
+        let shaped = shape_broken(&mut Mono, "ab abcdefgh", &anywhere);
+        assert_eq!(
+            glyph_counts(&shaped),
+            vec![3, 3, 3, 2],
+            "\"ab \", then the word cut to fit"
+        );
+        assert_eq!((shaped.width, shaped.height), (30.0, 80.0));
+        let baselines: Vec<f32> = shaped.runs.iter().map(|run| run.baseline).collect();
+        assert_eq!(baselines, vec![16.0, 36.0, 56.0, 76.0]);
+
+        let break_all = style(
+            35.0,
+            LineBreaking {
+                word_break: WordBreak::BreakAll,
+                ..Default::default()
+            },
+        );
+        assert_eq!(measure_broken(&mut Mono, "ab abcdefgh", &break_all), (30.0, 80.0));
+    }
+
+    #[test]
+    fn soft_hyphens_break_with_a_visible_hyphen() {
+        let hyphenated = style(65.0, LineBreaking::default());
+        let text = "hy\u{AD}phen\u{AD}ation";
+        assert!(hyphenated.needs_line_breaker(text));
+        let shaped = shape_broken(&mut Mono, text, &hyphenated);
+        assert_eq!(glyph_counts(&shaped), vec![3, 5, 5], "\"hy-\", \"phen-\", \"ation\"");
+
+        let none = style(
+            65.0,
+            LineBreaking {
+                hyphens: Hyphens::None,
+                ..Default::default()
+            },
+        );
+        assert!(!none.needs_line_breaker(text));
+    }
+
+    #[test]
+    fn ellipsis_and_line_clamp_cut_the_text() {
+        let ellipsis = style(
+            45.0,
+            LineBreaking {
+                text_overflow: TextOverflow::Ellipsis,
+                nowrap: true,
+                ..Default::default()
+            },
+        );
+        let shaped = shape_broken(&mut Mono, "abcdefgh", &ellipsis);
+        assert_eq!(glyph_counts(&shaped), vec![4], "\"abc…\"");
+        assert_eq!(shaped.height, 20.0);
+
+        let clamp = style(
+            25.0,
+            LineBreaking {
+                line_clamp: Some(2),
+                ..Default::default()
+            },
+        );
+        let shaped = shape_broken(&mut Mono, "aa bb cc", &clamp);
+        assert_eq!(glyph_counts(&shaped), vec![3, 2], "\"aa \" and \"b…\"");
+        assert_eq!(shaped.height, 40.0);
+    }
+}
@@ -0,0 +1,378 @@
+//! Vertical writing modes, laid out on top of an engine's horizontal shaping.
+//!
+//! None of the font systems sets vertical lines itself, so [`shape_vertical`] breaks the text
+//! into lines, shapes each orientation segment of a line horizontally through the same
+//! [`FontSystem`], and places the results in columns:
+//!
+//! - upright segments (CJK under `text-orientation: mixed`, everything under `upright`) are shaped
+//!   with the `vert`/`vrt2` alternates, and their glyphs are stacked down the column one
+//!   horizontal advance apart;
+//! - sideways segments keep their horizontal layout in a frame turned a quarter turn (see
+//!   [`GlyphRotation`]), which the rasterizer applies.
+//!
+//! Upright glyphs are centred as 1em squares, which holds for CJK fonts. Lines break after
+//! whitespace and between CJK characters, without the line-breaking (kinsoku) rules.
+
+use super::{
+    FontFeature, FontSystem, GlyphRotation, LineBreaking, ShapedGlyph, ShapedRun, ShapedText, TextAlign,
+    TextOrientation, TextStyle, WritingMode,
+};
+use std::ops::Range;
+
+/// One orientation segment of a line, shaped horizontally.
+struct Segment {
+    shaped: ShapedText,
+    upright: bool,
+    /// Offset along the line, px from its start.
+    offset: f32,
+}
+
+/// Shape `text` in `style`'s vertical writing mode. Font systems call this from
+/// [`FontSystem::shape`] whenever `style.writing_mode` is vertical; it shapes through `fs` with
+/// horizontal copies of `style`. `style.max_width` limits the length of the lines.
+pub fn shape_vertical(fs: &mut dyn FontSystem, text: &str, style: &TextStyle) -> ShapedText {
+    let mut sideways = style.clone();
+    sideways.writing_mode = WritingMode::HorizontalTb;
+    sideways.max_width = None;
+    sideways.align = TextAlign::Start;
+    sideways.line_breaking = LineBreaking::default();
+    let mut upright = sideways.clone();
+    upright
+        .features
+        .extend([FontFeature::new(b"vert", 1), FontFeature::new(b"vrt2", 1)]);
+
+    let is_upright = |c: char| match (style.writing_mode, style.orientation) {
+        (WritingMode::SidewaysRl | WritingMode::SidewaysLr, _) | (_, TextOrientation::Sideways) => false,
+        (_, TextOrientation::Upright) => true,
+        (_, TextOrientation::Mixed) => is_upright_char(c),
+    };
+    let limit = style.max_width.unwrap_or(f32::INFINITY);
+
+    // Fill lines greedily, piece by piece.
+    let mut lines: Vec<Range<usize>> = Vec::new();
+    for paragraph in split_inclusive_ranges(text, '\n') {
+        let mut line = paragraph.start..paragraph.start;
+        let mut advance = 0.0;
+        for piece in break_pieces(&text[paragraph.clone()], paragraph.start) {
+            let piece_advance: f32 = orientation_segments(&text[piece.clone()], &is_upright)
+                .map(|(segment, up)| {
+                    let segment = &text[piece.start + segment.start..piece.start + segment.end];
+                    fs.measure(segment, if up { &upright } else { &sideways }).0
+                })
+                .sum();
+            if !line.is_empty() && advance + piece_advance > limit {
+                lines.push(line.clone());
+                line = piece.start..piece.start;
+                advance = 0.0;
+            }
+            line.end = piece.end;
+            advance += piece_advance;
+        }
+        lines.push(line);
+    }
+
+    // Shape each line's segments.
+    let shaped_lines: Vec<(Vec<Segment>, f32)> = lines
+        .iter()
+        .map(|line| {
+            let line_text = text[line.clone()].trim_end_matches('\n');
+            let mut offset = 0.0;
+            let segments = orientation_segments(line_text, &is_upright)
+                .map(|(segment, up)| {
+                    let mut shaped = fs.shape(&line_text[segment.clone()], if up { &upright } else { &sideways });
+                    let base = (line.start + segment.start) as u32;
+                    map_clusters(&mut shaped, |cluster| base + cluster);
+                    let start = offset;
+                    offset += shaped.width;
+                    Segment {
+                        shaped,
+                        upright: up,
+                        offset: start,
+                    }
+                })
+                .collect();
+            (segments, offset)
+        })
+        .collect();
+
+    let Some(first) = shaped_lines
+        .iter()
+        .flat_map(|(segments, _)| segments)
+        .find(|s| !s.shaped.is_empty())
+    else {
+        return ShapedText::empty();
+    };
+    let line_height = style.line_height.unwrap_or(first.shaped.line_height);
+    let longest = shaped_lines.iter().map(|(_, advance)| *advance).fold(0.0, f32::max);
+    let extent = match (style.align, style.max_width) {
+        (TextAlign::Center | TextAlign::End, Some(max)) => longest.max(max),
+        _ => longest,
+    };
+    let width = line_height * shaped_lines.len() as f32;
+
+    let mut runs = Vec::new();
+    for (k, (segments, advance)) in shaped_lines.into_iter().enumerate() {
+        let column_x = if style.writing_mode.is_block_rtl() {
+            width - (k + 1) as f32 * line_height
+        } else {
+            k as f32 * line_height
+        };
+        let shift = match style.align {
+            TextAlign::Center => (extent - advance) / 2.0,
+            TextAlign::End => extent - advance,
+            TextAlign::Start | TextAlign::Justify => 0.0,
+        };
+        for segment in segments {
+            let offset = segment.offset + shift;
+            let half_leading = (segment.shaped.line_height - style.size) / 2.0;
+            for run in segment.shaped.runs {
+                runs.push(if segment.upright {
+                    upright_run(run, column_x + (line_height - style.size) / 2.0, offset - half_leading)
+                } else if style.writing_mode == WritingMode::SidewaysLr {
+                    // Reads bottom to top: frame (u, v) lands at (v, -u) on the page.
+                    frame_run(run, GlyphRotation::CounterClockwise, offset - extent, column_x)
+                } else {
+                    // Frame (u, v) lands at (-v, u): the line's top edge is the column's right.
+                    frame_run(run, GlyphRotation::Clockwise, offset, -(column_x + line_height))
+                });
+            }
+        }
+    }
+
+    ShapedText {
+        runs,
+        width,
+        height: extent,
+        line_height,
+        // Vertical lines share a central baseline, half a line into the first column.
+        ascent: line_height / 2.0,
+    }
+}
+
+/// [`shape_vertical`]'s bounding box, for [`FontSystem::measure`].
+pub fn measure_vertical(fs: &mut dyn FontSystem, text: &str, style: &TextStyle) -> (f32, f32) {
+    let shaped = shape_vertical(fs, text, style);
+    (shaped.width, shaped.height)
+}
+
+/// Stack a horizontally shaped upright run down the column whose glyph cells start at `x`: a
+/// glyph's horizontal pen position becomes its offset down the line. The run has no horizontal
+/// extent, so rasterizers draw no horizontal decoration for it.
+fn upright_run(run: ShapedRun, x: f32, down: f32) -> ShapedRun {
+    ShapedRun {
+        glyphs: run
+            .glyphs
+            .iter()
+            .map(|g| ShapedGlyph {
+                x,
+                y: down + g.x + g.y,
+                ..*g
+            })
+            .collect(),
+        x,
+        baseline: down + run.x + run.baseline,
+        width: 0.0,
+        ..run
+    }
+}
+
+/// Move a horizontally shaped run by `(du, dv)` within its rotated frame.
+fn frame_run(run: ShapedRun, rotation: GlyphRotation, du: f32, dv: f32) -> ShapedRun {
+    ShapedRun {
+        glyphs: run
+            .glyphs
+            .iter()
+            .map(|g| ShapedGlyph {
+                x: g.x + du,
+                y: g.y + dv,
+                ..*g
+            })
+            .collect(),
+        x: run.x + du,
+        baseline: run.baseline + dv,
+        rotation,
+        ..run
+    }
+}
+
+/// Rewrite the cluster offsets of `shaped`'s glyphs, for text shaped as part of a larger string.
+pub(super) fn map_clusters(shaped: &mut ShapedText, map: impl Fn(u32) -> u32) {
+    for glyph in shaped.runs.iter_mut().flat_map(|run| run.glyphs.iter_mut()) {
+        glyph.cluster = map(glyph.cluster);
+    }
+}
+
+/// The byte ranges of `text`'s lines, each including its terminating `sep`.
+pub(super) fn split_inclusive_ranges(text: &str, sep: char) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = 0;
+    text.split_inclusive(sep).map(move |part| {
+        let range = start..start + part.len();
+        start = range.end;
+        range
+    })
+}
+
+/// The unbreakable pieces of `text` (byte ranges shifted by `base`): a line may break after
+/// whitespace and between two CJK characters.
+fn break_pieces(text: &str, base: usize) -> Vec<Range<usize>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if let Some(p) = prev {
+            let after_space = p.is_whitespace() && !c.is_whitespace();
+            if after_space || (is_upright_char(p) && is_upright_char(c)) {
+                pieces.push(base + start..base + i);
+                start = i;
+            }
+        }
+        prev = Some(c);
+    }
+    if start < text.len() {
+        pieces.push(base + start..base + text.len());
+    }
+    pieces
+}
+
+/// Split `text` into maximal runs of one orientation, as byte ranges with `true` for upright.
+fn orientation_segments<'a>(
+    text: &'a str,
+    is_upright: &'a dyn Fn(char) -> bool,
+) -> impl Iterator<Item = (Range<usize>, bool)> + 'a {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, c) = chars.next()?;
+        let up = is_upright(c);
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if is_upright(c) != up {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        Some((start..end, up))
+    })
+}
+
+/// Whether `c` stands upright in `text-orientation: mixed`: the CJK, kana, Hangul, Yi and
+/// fullwidth blocks and the emoji planes, approximating Unicode's `Vertical_Orientation=U/Tu`.
+fn is_upright_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1100}'..='\u{11FF}'
+            | '\u{2E80}'..='\u{A4CF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE10}'..='\u{FE1F}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF01}'..='\u{FF60}'
+            | '\u{FFE0}'..='\u{FFE7}'
+            | '\u{1F000}'..='\u{1FAFF}'
+            | '\u{20000}'..='\u{3FFFD}'
+    )
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::font::{FontBlob, FontError, FontStyle};
+    use crate::font_system::{FontQuery, FontStretch, FontWeight, ResolvedFont, RunMetrics};
+    use std::sync::Arc;
+
+    /// Every character advances 10px on a 20px line, with the baseline at 16px.
+    pub(crate) struct Mono;
+
+    impl FontSystem for Mono {
+        fn register_font(&mut self, _data: Vec<u8>, _family_override: Option<&str>) -> Result<(), FontError> {
+            Ok(())
+        }
+
+        fn resolve(&mut self, query: &FontQuery<'_>) -> Result<ResolvedFont, FontError> {
+            Err(FontError::FontNotFound(query.families.join(", ")))
+        }
+
+        fn families(&mut self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn shape(&mut self, text: &str, style: &TextStyle) -> ShapedText {
+            assert!(!style.writing_mode.is_vertical());
+            let glyphs: Vec<ShapedGlyph> = (text.char_indices().enumerate())
+                .map(|(i, (cluster, _))| ShapedGlyph {
+                    id: i as u32,
+                    x: i as f32 * 10.0,
+                    y: 16.0,
+                    cluster: cluster as u32,
+                })
+                .collect();
+            let width = glyphs.len() as f32 * 10.0;
+            ShapedText {
+                runs: vec![ShapedRun {
+                    font: ResolvedFont {
+                        family: style.family.clone(),
+                        style: FontStyle::Normal,
+                        weight: FontWeight::NORMAL,
+                        stretch: FontStretch::NORMAL,
+                        blob: FontBlob::new(Arc::new(Vec::<u8>::new()), 0),
+                    },
+                    font_size: style.size,
+                    x: 0.0,
+                    baseline: 16.0,
+                    width,
+                    metrics: RunMetrics::default(),
+                    glyphs,
+                    rotation: GlyphRotation::None,
+                }],
+                width,
+                height: 20.0,
+                line_height: 20.0,
+                ascent: 16.0,
+            }
+        }
+    }
+
+    fn style(mode: WritingMode, max: Option<f32>) -> TextStyle {
+        let mut style = TextStyle::new("mono", 10.0);
+        style.writing_mode = mode;
+        style.max_width = max;
+        style
+    }
+
+    #[test]
+    fn lines_become_columns_stacked_in_block_order() {
+        let (w, h) = measure_vertical(&mut Mono, "ab cd", &style(WritingMode::VerticalRl, Some(35.0)));
+        assert_eq!((w, h), (40.0, 30.0), "two 20px columns, the longest line \"ab \" 30px");
+
+        let shaped = shape_vertical(&mut Mono, "ab cd", &style(WritingMode::VerticalRl, Some(35.0)));
+        assert!(shaped.runs.iter().all(|run| run.rotation == GlyphRotation::Clockwise));
+        // The first line's frame sits at v = -40 (the right column), the second at v = -20.
+        let baselines: Vec<f32> = shaped.runs.iter().map(|run| run.baseline).collect();
+        assert_eq!(baselines, vec![16.0 - 40.0, 16.0 - 20.0]);
+
+        let shaped = shape_vertical(&mut Mono, "ab cd", &style(WritingMode::VerticalLr, Some(35.0)));
+        let baselines: Vec<f32> = shaped.runs.iter().map(|run| run.baseline).collect();
+        assert_eq!(baselines, vec![16.0 - 20.0, 16.0 - 40.0]);
+    }
+
+    #[test]
+    fn cjk_stands_upright_and_breaks_anywhere() {
+        let shaped = shape_vertical(&mut Mono, "日本a", &style(WritingMode::VerticalRl, None));
+        assert_eq!(shaped.runs.len(), 2);
+        let (cjk, latin) = (&shaped.runs[0], &shaped.runs[1]);
+        assert_eq!(cjk.rotation, GlyphRotation::None);
+        // Centred in the 20px column, stacked 10px apart; the sideways "a" follows them.
+        let ys: Vec<f32> = cjk.glyphs.iter().map(|g| g.y).collect();
+        assert_eq!((cjk.glyphs[0].x, ys), (5.0, vec![11.0, 21.0]));
+        assert_eq!((latin.rotation, latin.glyphs[0].x), (GlyphRotation::Clockwise, 20.0));
+
+        let (w, h) = measure_vertical(&mut Mono, "日本語", &style(WritingMode::VerticalRl, Some(20.0)));
+        assert_eq!((w, h), (40.0, 20.0));
+
+        let sideways = shape_vertical(&mut Mono, "日本", &style(WritingMode::SidewaysLr, None));
+        assert!(sideways
+            .runs
+            .iter()
+            .all(|run| run.rotation == GlyphRotation::CounterClockwise));
+    }
+}
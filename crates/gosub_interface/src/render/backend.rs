@@ -164,6 +164,197 @@ fn mul_div255_pair(pair: u32, factor: u32) -> u32 {
     ((t + ((t >> 8) & 0x00FF_00FF)) >> 8) & 0x00FF_00FF
 }
 
+/// CSS `mix-blend-mode`: how a box's pixels combine with the backdrop beneath it.
+/// `Normal` is plain source-over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl BlendMode {
+    /// Parse a CSS `mix-blend-mode` keyword; unknown keywords fall back to `Normal`.
+    pub fn from_css_keyword(keyword: &str) -> Self {
+        match keyword {
+            "multiply" => BlendMode::Multiply,
+            "screen" => BlendMode::Screen,
+            "overlay" => BlendMode::Overlay,
+            "darken" => BlendMode::Darken,
+            "lighten" => BlendMode::Lighten,
+            "color-dodge" => BlendMode::ColorDodge,
+            "color-burn" => BlendMode::ColorBurn,
+            "hard-light" => BlendMode::HardLight,
+            "soft-light" => BlendMode::SoftLight,
+            "difference" => BlendMode::Difference,
+            "exclusion" => BlendMode::Exclusion,
+            "hue" => BlendMode::Hue,
+            "saturation" => BlendMode::Saturation,
+            "color" => BlendMode::Color,
+            "luminosity" => BlendMode::Luminosity,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    /// Stable small integer for content hashing.
+    pub fn id(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The blend function `B(Cb, Cs)` on straight (unpremultiplied) RGB in `0.0..=1.0`, with
+    /// `cb` the backdrop and `cs` the source colour (Compositing and Blending Level 1, §10).
+    pub fn blend_rgb(self, cb: [f32; 3], cs: [f32; 3]) -> [f32; 3] {
+        let separable = |f: fn(f32, f32) -> f32| [f(cb[0], cs[0]), f(cb[1], cs[1]), f(cb[2], cs[2])];
+        match self {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => separable(|b, s| b * s),
+            BlendMode::Screen => separable(screen),
+            BlendMode::Overlay => separable(|b, s| hard_light(s, b)),
+            BlendMode::Darken => separable(f32::min),
+            BlendMode::Lighten => separable(f32::max),
+            BlendMode::ColorDodge => separable(|b, s| {
+                if b <= 0.0 {
+                    0.0
+                } else if s >= 1.0 {
+                    1.0
+                } else {
+                    (b / (1.0 - s)).min(1.0)
+                }
+            }),
+            BlendMode::ColorBurn => separable(|b, s| {
+                if b >= 1.0 {
+                    1.0
+                } else if s <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - b) / s).min(1.0)
+                }
+            }),
+            BlendMode::HardLight => separable(hard_light),
+            BlendMode::SoftLight => separable(|b, s| {
+                if s <= 0.5 {
+                    b - (1.0 - 2.0 * s) * b * (1.0 - b)
+                } else {
+                    let d = if b <= 0.25 {
+                        ((16.0 * b - 12.0) * b + 4.0) * b
+                    } else {
+                        b.sqrt()
+                    };
+                    b + (2.0 * s - 1.0) * (d - b)
+                }
+            }),
+            BlendMode::Difference => separable(|b, s| (b - s).abs()),
+            BlendMode::Exclusion => separable(|b, s| b + s - 2.0 * b * s),
+            BlendMode::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+            BlendMode::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+            BlendMode::Color => set_lum(cs, lum(cb)),
+            BlendMode::Luminosity => set_lum(cb, lum(cs)),
+        }
+    }
+}
+
+fn screen(b: f32, s: f32) -> f32 {
+    b + s - b * s
+}
+
+fn hard_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b * 2.0 * s
+    } else {
+        screen(b, 2.0 * s - 1.0)
+    }
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+/// Shifts `c` to luminosity `l`, pulling out-of-gamut channels back towards grey.
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    let c = c.map(|v| v + d);
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    c.map(|v| {
+        let mut v = v;
+        if n < 0.0 {
+            v = l + (v - l) * l / (l - n);
+        }
+        if x > 1.0 {
+            v = l + (v - l) * (1.0 - l) / (x - l);
+        }
+        v
+    })
+}
+
+/// Rescales `c` to saturation `s`, keeping the order of its channels.
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let mut idx = [0usize, 1, 2];
+    idx.sort_by(|&a, &b| c[a].total_cmp(&c[b]));
+    let [min, mid, max] = idx;
+    let mut out = [0.0; 3];
+    if c[max] > c[min] {
+        out[mid] = (c[mid] - c[min]) * s / (c[max] - c[min]);
+        out[max] = s;
+    }
+    out
+}
+
+/// Composite a premultiplied source pixel onto a premultiplied destination with a CSS
+/// `mix-blend-mode`, both packed as `0xAARRGGBB`: `co = cs·(1 − αb) + cb·(1 − αs) + αs·αb·B(Cb, Cs)`.
+/// `Normal` is exactly [`blend_over_argb_u32`].
+pub fn blend_mode_argb_u32(src: u32, dst: u32, mode: BlendMode) -> u32 {
+    let sa = src >> 24;
+    let da = dst >> 24;
+    if mode == BlendMode::Normal || sa == 0 || da == 0 {
+        return blend_over_argb_u32(src, dst);
+    }
+    let channels = |px: u32| [(px >> 16) & 0xFF, (px >> 8) & 0xFF, px & 0xFF].map(|c| c as f32 / 255.0);
+    let (s, d) = (channels(src), channels(dst));
+    let (sa, da) = (sa as f32 / 255.0, da as f32 / 255.0);
+    let cs = s.map(|c| (c / sa).min(1.0));
+    let cb = d.map(|c| (c / da).min(1.0));
+    let b = mode.blend_rgb(cb, cs);
+
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+    let ao = to_u8(sa + da - sa * da);
+    let co: [u32; 3] = std::array::from_fn(|i| to_u8(s[i] * (1.0 - da) + d[i] * (1.0 - sa) + sa * da * b[i]));
+    // Rounding must never leave a colour channel above alpha, or the pixel stops being premultiplied.
+    let co = co.map(|c| c.min(ao));
+    (ao << 24) | (co[0] << 16) | (co[1] << 8) | co[2]
+}
+
+/// One isolated compositing group a tile's layer composites through: a stacking context with
+/// `opacity < 1`, a `mix-blend-mode`, or `isolation: isolate`. Everything in the group is
+/// composited together offscreen, then faded by `opacity` and blended into the enclosing group as
+/// one image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompositeGroup {
+    /// Identifies the group across tiles (the promoting layer's id).
+    pub id: u64,
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum GpuPixelFormat {
     Bgra8UnormSrgb,
@@ -268,12 +459,16 @@ pub struct CachedTile {
     pub data: bytes::Bytes,
     /// In-memory byte order of `data`, set by the rasterizer that produced it.
     pub format: PixelFormat,
-    /// Group opacity (1.0 = opaque) of the tile's layer. The compositor scales the tile's
-    /// premultiplied pixels by this before the source-over blend, fading opacity-promoted layers
-    /// (e.g. a translucent fixed navbar) as a whole.
+    /// Group opacity (1.0 = opaque) of the tile's layer, multiplied through its enclosing groups.
+    /// A compositor without group support scales the tile's premultiplied pixels by this before
+    /// the source-over blend, fading opacity-promoted layers (e.g. a translucent fixed navbar) as a
+    /// whole. Ignored when `groups` is non-empty: the groups carry the opacity instead.
     pub opacity: f32,
     /// How this tile's layer responds to scroll (normal flow vs. `position: fixed`).
     pub anchor: TileAnchor,
+    /// The isolated groups the tile composites through, outermost first. Empty when it lands
+    /// straight on the page. Tiles of one group are contiguous in composite order.
+    pub groups: Arc<[CompositeGroup]>,
     /// True when every pixel is fully opaque (alpha == 255). Computed once when the tile is cached;
     /// lets a CPU compositor blit the tile with a plain row copy instead of a per-pixel source-over.
     pub opaque: bool,
@@ -592,6 +787,36 @@ mod tests {
         assert!((126..=129).contains(&r), "expected ~half grey, got {r}");
     }
 
+    #[test]
+    fn blend_modes_against_opaque_backdrop() {
+        const GREY: u32 = 0xFF80_8080;
+        // Multiply by white is identity, by black is black; screen is its inverse.
+        assert_eq!(blend_mode_argb_u32(WHITE, GREY, BlendMode::Multiply), GREY);
+        assert_eq!(blend_mode_argb_u32(BLACK, GREY, BlendMode::Multiply), BLACK);
+        assert_eq!(blend_mode_argb_u32(BLACK, GREY, BlendMode::Screen), GREY);
+        assert_eq!(blend_mode_argb_u32(WHITE, GREY, BlendMode::Difference), 0xFF7F_7F7F);
+        // Luminosity keeps the backdrop's (zero) chroma and takes the source's brightness.
+        assert_eq!(blend_mode_argb_u32(WHITE, BLACK, BlendMode::Luminosity), WHITE);
+        // A transparent backdrop shows the source unblended.
+        assert_eq!(blend_mode_argb_u32(0xFFFF_0000, 0, BlendMode::Multiply), 0xFFFF_0000);
+    }
+
+    #[test]
+    fn blend_mode_stays_premultiplied() {
+        // Half-transparent red multiplied over opaque blue: red·blue = black where they overlap.
+        let out = blend_mode_argb_u32(0x8080_0000, 0xFF00_00FF, BlendMode::Multiply);
+        assert_eq!(out >> 24, 0xFF);
+        let [r, g, b] = [(out >> 16) & 0xFF, (out >> 8) & 0xFF, out & 0xFF];
+        assert_eq!((r, g), (0, 0));
+        assert!((126..=129).contains(&b), "half the blue backdrop remains, got {b}");
+    }
+
+    #[test]
+    fn css_blend_keywords() {
+        assert_eq!(BlendMode::from_css_keyword("color-dodge"), BlendMode::ColorDodge);
+        assert_eq!(BlendMode::from_css_keyword("plus-lighter"), BlendMode::Normal);
+    }
+
     #[test]
     fn rgba8_pixel_normalizes_to_argb() {
         // Rgba8 little-endian bytes [R,G,B,A] = [0x11,0x22,0x33,0xFF] read as 0xFF332211.
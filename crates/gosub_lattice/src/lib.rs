@@ -3,11 +3,13 @@ pub mod geo;
 pub mod grid;
 pub mod mock;
 pub mod model;
+pub mod multicol;
 pub mod sizing;
 mod tests;
 pub mod types;
 
 pub use compute::compute_table_layout;
+pub use multicol::{compute_multicol_layout, ColumnTree};
 pub use types::{BorderCollapse, BoxEdges, CellLayout, CssLength, CssProp, TableRole, TableSizing};
 
 use std::fmt::Debug;
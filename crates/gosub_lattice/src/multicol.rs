@@ -0,0 +1,130 @@
+//! CSS multi-column layout (CSS Multi-column Layout Module Level 1).
+//!
+//! Like the table algorithm, this is a standalone pass that runs *after* the host has measured
+//! the container's children: it decides how many columns fit, balances the children across
+//! them (breaking a child between its line boxes where the host allows it), stacks
+//! `column-span: all` spanners between column rows, and reports where `column-rule`s go.
+//! It talks to the host exclusively through the [`ColumnTree`] adapter trait.
+
+pub mod compute;
+pub mod mock;
+mod tests;
+
+pub use compute::{compute_multicol_layout, resolve_columns};
+
+use crate::geo::{Point, Size};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Adapter trait that the multi-column algorithm uses to read from and write to an external
+/// layout tree.
+pub trait ColumnTree {
+    type NodeId: Copy + Clone + Eq + Hash + Debug;
+
+    /// Returns the in-flow children of the multi-column container `id` in document order.
+    fn children(&self, id: Self::NodeId) -> Vec<Self::NodeId>;
+
+    /// The multi-column properties of the container `id`.
+    fn column_style(&self, id: Self::NodeId) -> ColumnStyle;
+
+    /// `column-span` of a child.
+    fn column_span(&self, id: Self::NodeId) -> ColumnSpan;
+
+    /// Outer (margin-box) height of child `id` when laid out at `inline_size`.
+    ///
+    /// Hosts that have already laid the child out at the column width (or, for spanners, at the
+    /// container width) may simply return that height.
+    fn block_size(&mut self, id: Self::NodeId, inline_size: f32) -> f32;
+
+    /// Offsets from the top of child `id` at which its content may be split across columns,
+    /// typically the boundaries between its line boxes. The default - no offsets - makes every
+    /// child monolithic: it moves to the next column as a whole.
+    fn break_offsets(&self, _id: Self::NodeId) -> Vec<f32> {
+        Vec::new()
+    }
+
+    /// Writes the computed fragments for child `id` back to the tree. A child that was not
+    /// broken gets exactly one fragment.
+    fn set_fragments(&mut self, id: Self::NodeId, fragments: Vec<ColumnFragment>);
+}
+
+/// The multi-column properties of a container, resolved to pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ColumnStyle {
+    /// `column-count`; `None` for `auto`.
+    pub count: Option<u32>,
+    /// `column-width`; `None` for `auto`.
+    pub width: Option<f32>,
+    /// `column-gap` (`normal` is resolved to `1em` by the host).
+    pub gap: f32,
+    pub fill: ColumnFill,
+    /// Definite `height` of the container's content box, if any. Columns are balanced within it
+    /// and content that does not fit spills into overflow columns.
+    pub height: Option<f32>,
+}
+
+impl ColumnStyle {
+    /// A box is a multi-column container when either `column-count` or `column-width` is not
+    /// `auto`.
+    pub fn is_multicol(&self) -> bool {
+        self.count.is_some() || self.width.is_some()
+    }
+}
+
+/// `column-fill` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnFill {
+    /// Balance content equally between columns.
+    #[default]
+    Balance,
+    /// Fill columns sequentially. Only differs from `balance` when the container has a definite
+    /// height.
+    Auto,
+}
+
+/// `column-span` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnSpan {
+    #[default]
+    None,
+    /// The element spans all columns and splits the column rows around it.
+    All,
+}
+
+/// One piece of a child placed in a column. All coordinates are relative to the container's
+/// content box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnFragment {
+    /// Index of the column the piece landed in (0 for spanners). Indexes at or above the used
+    /// column count are overflow columns.
+    pub column: usize,
+    /// Where the top of the piece is placed.
+    pub position: Point,
+    /// Distance from the child's top edge to the start of this piece (0 for the first fragment).
+    pub offset: f32,
+    /// Width of the column (or of the container, for spanners) and height of the piece.
+    pub size: Size,
+}
+
+/// A vertical `column-rule` segment between two adjacent columns of one column row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnRule {
+    /// Horizontal centre of the gap, relative to the container's content box.
+    pub x: f32,
+    pub y: f32,
+    pub height: f32,
+}
+
+/// Result of [`compute_multicol_layout`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MultiColLayout {
+    /// Used column count (excluding overflow columns).
+    pub column_count: usize,
+    /// Used column width.
+    pub column_width: f32,
+    /// Height of the container's content box: every column row and spanner, stacked.
+    pub height: f32,
+    /// Rule segments, in paint order. The host decides whether (and how) to paint them from the
+    /// container's `column-rule-*` properties.
+    pub rules: Vec<ColumnRule>,
+}
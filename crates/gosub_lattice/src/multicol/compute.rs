@@ -0,0 +1,278 @@
+use crate::geo::{Point, Size};
+use anyhow::Result;
+
+use crate::multicol::{ColumnFill, ColumnFragment, ColumnRule, ColumnSpan, ColumnStyle, ColumnTree, MultiColLayout};
+
+/// Tolerance used when comparing accumulated heights against a column height.
+const EPSILON: f32 = 0.01;
+
+/// Bisection steps when searching for the balanced column height. Each step halves the
+/// interval, so this is far below a pixel for any realistic page.
+const BALANCE_STEPS: usize = 32;
+
+/// Entry point for the multi-column layout algorithm.
+///
+/// Reads the children of `container` from `tree`, distributes them over columns of
+/// `available_width` (the container's content-box width), and writes one or more
+/// [`ColumnFragment`]s per child back via [`ColumnTree::set_fragments`].
+///
+/// Children are grouped into *column rows*: runs of ordinary children separated by
+/// `column-span: all` spanners. Each row is balanced on its own, and spanners are stacked
+/// between rows at the full container width. The caller is responsible for sizing the
+/// container itself from [`MultiColLayout::height`].
+pub fn compute_multicol_layout<T: ColumnTree>(
+    tree: &mut T,
+    container: T::NodeId,
+    available_width: f32,
+) -> Result<MultiColLayout> {
+    let style = tree.column_style(container);
+    let (count, width) = resolve_columns(available_width, &style);
+    let columns = Columns {
+        count,
+        width,
+        gap: style.gap.max(0.0),
+        fill: style.fill,
+        height: style.height,
+    };
+
+    let mut layout = MultiColLayout {
+        column_count: count,
+        column_width: width,
+        ..Default::default()
+    };
+
+    let mut y = 0.0;
+    let mut pieces: Vec<Piece<T::NodeId>> = Vec::new();
+    for child in tree.children(container) {
+        if tree.column_span(child) == ColumnSpan::All {
+            y += lay_out_row(tree, &pieces, &columns, y, &mut layout.rules);
+            pieces.clear();
+
+            let height = tree.block_size(child, available_width).max(0.0);
+            let fragment = ColumnFragment {
+                column: 0,
+                position: Point::new(0.0, y),
+                offset: 0.0,
+                size: Size::new(available_width, height),
+            };
+            tree.set_fragments(child, vec![fragment]);
+            y += height;
+            continue;
+        }
+
+        let height = tree.block_size(child, width).max(0.0);
+        let mut offsets = tree.break_offsets(child);
+        offsets.sort_by(f32::total_cmp);
+
+        // Cut the child into unbreakable pieces at its break offsets. Offsets at (or beyond) the
+        // child's edges would only produce empty pieces.
+        let mut start = 0.0;
+        for offset in offsets {
+            if offset - start > EPSILON && height - offset > EPSILON {
+                pieces.push(Piece {
+                    node: child,
+                    start,
+                    end: offset,
+                });
+                start = offset;
+            }
+        }
+        pieces.push(Piece {
+            node: child,
+            start,
+            end: height,
+        });
+    }
+    y += lay_out_row(tree, &pieces, &columns, y, &mut layout.rules);
+
+    layout.height = y;
+    Ok(layout)
+}
+
+/// Resolves the used column count and width for a container whose content box is
+/// `available_width` wide (CSS Multi-column §3.4 pseudo-algorithm).
+///
+/// Returns `(1, available_width)` when neither `column-count` nor `column-width` is set.
+pub fn resolve_columns(available_width: f32, style: &ColumnStyle) -> (usize, f32) {
+    let available = available_width.max(0.0);
+    let gap = style.gap.max(0.0);
+
+    // How many columns of at least `width` fit, with gaps between them.
+    let fit = |width: f32| {
+        let pitch = width.max(0.0) + gap;
+        if pitch <= 0.0 {
+            1
+        } else {
+            (((available + gap) / pitch).floor() as usize).max(1)
+        }
+    };
+
+    let count = match (style.count, style.width) {
+        (None, None) => return (1, available),
+        (Some(count), None) => {
+            let count = count.max(1) as usize;
+            let width = (available - (count as f32 - 1.0) * gap) / count as f32;
+            return (count, width.max(0.0));
+        }
+        (None, Some(width)) => fit(width),
+        (Some(count), Some(width)) => (count.max(1) as usize).min(fit(width)),
+    };
+
+    (count, ((available + gap) / count as f32 - gap).max(0.0))
+}
+
+/// Resolved geometry shared by every column row of one container.
+struct Columns {
+    count: usize,
+    width: f32,
+    gap: f32,
+    fill: ColumnFill,
+    height: Option<f32>,
+}
+
+impl Columns {
+    /// x of the left edge of column `index`.
+    fn x(&self, index: usize) -> f32 {
+        index as f32 * (self.width + self.gap)
+    }
+}
+
+/// An unbreakable slice `[start, end)` of a child, measured from the child's top.
+#[derive(Debug, Clone, Copy)]
+struct Piece<Id> {
+    node: Id,
+    start: f32,
+    end: f32,
+}
+
+impl<Id> Piece<Id> {
+    fn height(&self) -> f32 {
+        self.end - self.start
+    }
+}
+
+/// Lay out one column row starting at `top`, write the fragments of its children and the rules
+/// between its columns, and return the row's height.
+fn lay_out_row<T: ColumnTree>(
+    tree: &mut T,
+    pieces: &[Piece<T::NodeId>],
+    columns: &Columns,
+    top: f32,
+    rules: &mut Vec<ColumnRule>,
+) -> f32 {
+    if pieces.is_empty() {
+        return 0.0;
+    }
+
+    // A definite container height constrains every row after the rows above it.
+    let remaining = columns.height.map(|h| (h - top).max(0.0));
+    let limit = match (columns.fill, remaining) {
+        (ColumnFill::Auto, Some(h)) => h,
+        (ColumnFill::Balance, Some(h)) => balanced_height(pieces, columns.count).min(h),
+        (_, None) => balanced_height(pieces, columns.count),
+    };
+
+    let slots = pack(pieces, limit);
+    let used = slots.last().map(|&(column, _)| column + 1).unwrap_or(0);
+
+    let mut column_heights = vec![0.0f32; used];
+    for (piece, &(column, y)) in pieces.iter().zip(slots.iter()) {
+        column_heights[column] = column_heights[column].max(y + piece.height());
+    }
+    let row_height = column_heights.iter().copied().fold(0.0, f32::max);
+
+    // Consecutive pieces of the same child in the same column form one fragment. Pieces of a
+    // child are contiguous, so each child's fragments are complete once the next child starts.
+    let mut current: Option<(T::NodeId, Vec<ColumnFragment>)> = None;
+    for (piece, &(column, y)) in pieces.iter().zip(slots.iter()) {
+        if let Some((node, fragments)) = current.as_mut() {
+            if *node == piece.node {
+                match fragments.last_mut() {
+                    Some(last) if last.column == column => last.size.height += piece.height(),
+                    _ => fragments.push(fragment_for(piece, column, y, top, columns)),
+                }
+                continue;
+            }
+        }
+        if let Some((node, fragments)) = current.take() {
+            tree.set_fragments(node, fragments);
+        }
+        current = Some((piece.node, vec![fragment_for(piece, column, y, top, columns)]));
+    }
+    if let Some((node, fragments)) = current {
+        tree.set_fragments(node, fragments);
+    }
+
+    // Rules sit in the middle of each gap between two columns that both have content. Greedy
+    // packing never leaves a column empty before a used one, so that is every gap up to `used`.
+    for column in 1..used {
+        rules.push(ColumnRule {
+            x: columns.x(column) - columns.gap / 2.0,
+            y: top,
+            height: row_height,
+        });
+    }
+
+    row_height
+}
+
+fn fragment_for<Id>(piece: &Piece<Id>, column: usize, y: f32, top: f32, columns: &Columns) -> ColumnFragment {
+    ColumnFragment {
+        column,
+        position: Point::new(columns.x(column), top + y),
+        offset: piece.start,
+        size: Size::new(columns.width, piece.height()),
+    }
+}
+
+/// Fill columns of height `limit` in order, moving to the next column whenever a piece does not
+/// fit. Returns the `(column, y)` slot of every piece. A piece taller than `limit` still gets a
+/// column of its own rather than being dropped.
+fn pack<Id>(pieces: &[Piece<Id>], limit: f32) -> Vec<(usize, f32)> {
+    let mut column = 0;
+    let mut y = 0.0;
+    pieces
+        .iter()
+        .map(|piece| {
+            if y > 0.0 && y + piece.height() > limit + EPSILON {
+                column += 1;
+                y = 0.0;
+            }
+            let slot = (column, y);
+            y += piece.height();
+            slot
+        })
+        .collect()
+}
+
+/// The smallest column height at which `pieces` fit in `count` columns.
+///
+/// The number of columns `pack` uses only shrinks as the limit grows, so the answer is found by
+/// bisecting between the lower bound (the tallest piece, or an even share of the total) and
+/// the total height (everything in one column).
+fn balanced_height<Id>(pieces: &[Piece<Id>], count: usize) -> f32 {
+    let total: f32 = pieces.iter().map(Piece::height).sum();
+    if count <= 1 {
+        return total;
+    }
+    let tallest = pieces.iter().map(Piece::height).fold(0.0, f32::max);
+    let fits = |limit: f32| pack(pieces, limit).last().map_or(0, |&(column, _)| column + 1) <= count;
+
+    let mut lo = tallest.max(total / count as f32);
+    let mut hi = total;
+    if fits(lo) {
+        return lo;
+    }
+    for _ in 0..BALANCE_STEPS {
+        if hi - lo <= EPSILON {
+            break;
+        }
+        let mid = (lo + hi) / 2.0;
+        if fits(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
@@ -0,0 +1,190 @@
+/// A simple in-memory multi-column tree used for testing.
+///
+/// Build a container with [`MockColumns`], then call [`MockColumns::into_tree`] to get the raw
+/// [`MockColumnTree`] and run `compute_multicol_layout` yourself, or [`MockColumns::layout`] to
+/// do both in one go.
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::multicol::compute::compute_multicol_layout;
+use crate::multicol::{ColumnFill, ColumnFragment, ColumnSpan, ColumnStyle, ColumnTree, MultiColLayout};
+
+// MockBlock - a single child specification used by the builder
+
+/// Child specification used with [`MockColumns`].
+#[derive(Clone)]
+pub struct MockBlock {
+    pub label: String,
+    /// Outer height reported by `block_size`, whatever the column width.
+    pub height: f32,
+    /// Line height; when set, the block may break between its lines.
+    pub line_height: Option<f32>,
+    pub span: ColumnSpan,
+}
+
+impl MockBlock {
+    pub fn new(label: impl Into<String>, height: f32) -> Self {
+        Self {
+            label: label.into(),
+            height,
+            line_height: None,
+            span: ColumnSpan::None,
+        }
+    }
+
+    pub fn lines(mut self, line_height: f32) -> Self {
+        self.line_height = Some(line_height);
+        self
+    }
+    pub fn span_all(mut self) -> Self {
+        self.span = ColumnSpan::All;
+        self
+    }
+}
+
+/// Shorthand: `block("label", height)`.
+pub fn block(label: impl Into<String>, height: f32) -> MockBlock {
+    MockBlock::new(label, height)
+}
+
+// MockColumns - fluent builder
+
+#[derive(Default)]
+pub struct MockColumns {
+    available_width: f32,
+    style: ColumnStyle,
+    children: Vec<MockBlock>,
+}
+
+impl MockColumns {
+    pub fn new(available_width: f32) -> Self {
+        Self {
+            available_width,
+            ..Default::default()
+        }
+    }
+
+    pub fn count(mut self, count: u32) -> Self {
+        self.style.count = Some(count);
+        self
+    }
+    pub fn width(mut self, width: f32) -> Self {
+        self.style.width = Some(width);
+        self
+    }
+    pub fn gap(mut self, gap: f32) -> Self {
+        self.style.gap = gap;
+        self
+    }
+    pub fn fill(mut self, fill: ColumnFill) -> Self {
+        self.style.fill = fill;
+        self
+    }
+    pub fn height(mut self, height: f32) -> Self {
+        self.style.height = Some(height);
+        self
+    }
+    pub fn child(mut self, child: MockBlock) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Build a [`MockColumnTree`] and run the layout on it.
+    pub fn layout(self) -> Result<(MockColumnTree, MultiColLayout)> {
+        let available_width = self.available_width;
+        let (mut tree, root) = self.into_tree();
+        let layout = compute_multicol_layout(&mut tree, root, available_width)?;
+        Ok((tree, layout))
+    }
+
+    /// Convert into a raw [`MockColumnTree`] (root NodeId is returned alongside). Children get
+    /// the ids `1..=n` in the order they were added.
+    pub fn into_tree(self) -> (MockColumnTree, u32) {
+        let mut tree = MockColumnTree {
+            nodes: HashMap::new(),
+            children: Vec::new(),
+            style: self.style,
+        };
+        for (index, spec) in self.children.into_iter().enumerate() {
+            let id = index as u32 + 1;
+            tree.children.push(id);
+            tree.nodes.insert(
+                id,
+                MockColumnNode {
+                    spec,
+                    fragments: Vec::new(),
+                },
+            );
+        }
+        (tree, MOCK_ROOT)
+    }
+}
+
+/// NodeId of the container in every [`MockColumnTree`].
+pub const MOCK_ROOT: u32 = 0;
+
+// MockColumnTree - the in-memory node tree
+
+struct MockColumnNode {
+    spec: MockBlock,
+    fragments: Vec<ColumnFragment>,
+}
+
+pub struct MockColumnTree {
+    nodes: HashMap<u32, MockColumnNode>,
+    children: Vec<u32>,
+    style: ColumnStyle,
+}
+
+impl MockColumnTree {
+    /// The fragments written for `id`; empty before layout.
+    pub fn fragments(&self, id: u32) -> &[ColumnFragment] {
+        self.nodes.get(&id).map(|n| n.fragments.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn label(&self, id: u32) -> Option<&str> {
+        self.nodes.get(&id).map(|n| n.spec.label.as_str())
+    }
+}
+
+impl ColumnTree for MockColumnTree {
+    type NodeId = u32;
+
+    fn children(&self, id: u32) -> Vec<u32> {
+        if id == MOCK_ROOT {
+            self.children.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn column_style(&self, _id: u32) -> ColumnStyle {
+        self.style
+    }
+
+    fn column_span(&self, id: u32) -> ColumnSpan {
+        self.nodes.get(&id).map(|n| n.spec.span).unwrap_or_default()
+    }
+
+    fn block_size(&mut self, id: u32, _inline_size: f32) -> f32 {
+        self.nodes.get(&id).map(|n| n.spec.height).unwrap_or(0.0)
+    }
+
+    fn break_offsets(&self, id: u32) -> Vec<f32> {
+        let Some(node) = self.nodes.get(&id) else {
+            return Vec::new();
+        };
+        let Some(line_height) = node.spec.line_height.filter(|&h| h > 0.0) else {
+            return Vec::new();
+        };
+        let lines = (node.spec.height / line_height).ceil() as usize;
+        (1..lines).map(|line| line as f32 * line_height).collect()
+    }
+
+    fn set_fragments(&mut self, id: u32, fragments: Vec<ColumnFragment>) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.fragments = fragments;
+        }
+    }
+}
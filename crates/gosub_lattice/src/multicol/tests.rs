@@ -0,0 +1,215 @@
+//! Tests for `compute_multicol_layout`.
+//!
+//! Each test builds a container via `MockColumns`, runs the algorithm, then asserts exact
+//! fragment positions. All coordinates are relative to the container's content box. Children
+//! get NodeIds `1..=n` in the order they were added.
+
+#[cfg(test)]
+mod multicol_tests {
+    use crate::multicol::compute::resolve_columns;
+    use crate::multicol::mock::{block, MockColumns};
+    use crate::multicol::{ColumnFill, ColumnStyle};
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    macro_rules! assert_approx {
+        ($a:expr, $b:expr, $msg:literal) => {
+            assert!(approx($a, $b), "{}: expected {}, got {}", $msg, $b, $a);
+        };
+    }
+
+    // 1. Column count / width resolution (§3.4 pseudo-algorithm)
+    #[test]
+    fn resolve_count_and_width() {
+        let style = |count: Option<u32>, width: Option<f32>, gap: f32| ColumnStyle {
+            count,
+            width,
+            gap,
+            ..Default::default()
+        };
+
+        // Neither set: not a multi-column container.
+        assert_eq!(resolve_columns(300.0, &style(None, None, 10.0)), (1, 300.0));
+
+        // column-count only: the gaps come off the available width first.
+        let (n, w) = resolve_columns(300.0, &style(Some(3), None, 30.0));
+        assert_eq!(n, 3);
+        assert_approx!(w, 80.0, "(300 - 2*30) / 3");
+
+        // column-width only: as many columns as fit, then stretched to fill.
+        let (n, w) = resolve_columns(300.0, &style(None, Some(100.0), 20.0));
+        assert_eq!(n, 2, "floor((300+20) / (100+20))");
+        assert_approx!(w, 140.0, "(300+20)/2 - 20");
+
+        // Both set: column-count is a maximum.
+        let (n, _) = resolve_columns(300.0, &style(Some(4), Some(100.0), 20.0));
+        assert_eq!(n, 2);
+        let (n, w) = resolve_columns(300.0, &style(Some(2), Some(50.0), 0.0));
+        assert_eq!(n, 2);
+        assert_approx!(w, 150.0, "two columns share the width");
+
+        // A column-width wider than the container still yields one column.
+        let (n, w) = resolve_columns(80.0, &style(None, Some(100.0), 10.0));
+        assert_eq!(n, 1);
+        assert_approx!(w, 80.0, "single column fills the container");
+    }
+
+    // 2. Balancing monolithic children
+    #[test]
+    fn balances_equal_blocks() {
+        let mut builder = MockColumns::new(320.0).count(3).gap(10.0);
+        for i in 0..6 {
+            builder = builder.child(block(format!("b{i}"), 30.0));
+        }
+        let (tree, layout) = builder.layout().expect("layout must succeed");
+
+        assert_eq!(layout.column_count, 3);
+        assert_approx!(layout.column_width, 100.0, "(320 - 2*10) / 3");
+        assert_approx!(layout.height, 60.0, "two blocks per column");
+
+        let expected = [
+            (0.0, 0.0),
+            (0.0, 30.0),
+            (110.0, 0.0),
+            (110.0, 30.0),
+            (220.0, 0.0),
+            (220.0, 30.0),
+        ];
+        for (i, (x, y)) in expected.into_iter().enumerate() {
+            let fragments = tree.fragments(i as u32 + 1);
+            assert_eq!(fragments.len(), 1, "block {i} is not broken");
+            assert_approx!(fragments[0].position.x, x, "block x");
+            assert_approx!(fragments[0].position.y, y, "block y");
+            assert_approx!(fragments[0].size.width, 100.0, "fragment width = column width");
+        }
+
+        // One rule in the middle of each gap, spanning the row.
+        assert_eq!(layout.rules.len(), 2);
+        assert_approx!(layout.rules[0].x, 105.0, "first rule centred in gap");
+        assert_approx!(layout.rules[1].x, 215.0, "second rule centred in gap");
+        assert_approx!(layout.rules[0].height, 60.0, "rule height = row height");
+    }
+
+    #[test]
+    fn tall_monolithic_block_sets_the_height() {
+        let (tree, layout) = MockColumns::new(300.0)
+            .count(3)
+            .child(block("tall", 100.0))
+            .child(block("a", 10.0))
+            .child(block("b", 10.0))
+            .layout()
+            .expect("layout must succeed");
+
+        assert_approx!(layout.height, 100.0, "no column can be shorter than the tall block");
+        assert_eq!(tree.fragments(2)[0].column, 1);
+        assert_eq!(
+            tree.fragments(3)[0].column,
+            1,
+            "both small blocks share the second column"
+        );
+        assert_approx!(tree.fragments(3)[0].position.y, 10.0, "b stacks under a");
+        assert_eq!(layout.rules.len(), 1, "no rule next to the empty third column");
+    }
+
+    // 3. Fragmentation between line boxes
+    #[test]
+    fn breaks_block_between_lines() {
+        let (tree, layout) = MockColumns::new(200.0)
+            .count(2)
+            .child(block("para", 100.0).lines(20.0))
+            .layout()
+            .expect("layout must succeed");
+
+        // Five 20px lines over two columns: three lines, then two.
+        assert_approx!(layout.height, 60.0, "balanced height");
+        let fragments = tree.fragments(1);
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].column, 0);
+        assert_approx!(fragments[0].offset, 0.0, "first fragment starts at the top");
+        assert_approx!(fragments[0].size.height, 60.0, "first fragment height");
+        assert_eq!(fragments[1].column, 1);
+        assert_approx!(fragments[1].position.x, 100.0, "second column x");
+        assert_approx!(fragments[1].position.y, 0.0, "continues at the column top");
+        assert_approx!(fragments[1].offset, 60.0, "second fragment starts after three lines");
+        assert_approx!(fragments[1].size.height, 40.0, "second fragment height");
+    }
+
+    // 4. column-span: all
+    #[test]
+    fn spanner_splits_column_rows() {
+        let (tree, layout) = MockColumns::new(200.0)
+            .count(2)
+            .child(block("a", 40.0))
+            .child(block("b", 40.0))
+            .child(block("heading", 25.0).span_all())
+            .child(block("c", 20.0))
+            .child(block("d", 20.0))
+            .layout()
+            .expect("layout must succeed");
+
+        assert_approx!(layout.height, 85.0, "40 (row) + 25 (spanner) + 20 (row)");
+
+        let heading = tree.fragments(3);
+        assert_eq!(heading.len(), 1);
+        assert_approx!(heading[0].position.y, 40.0, "spanner below the first row");
+        assert_approx!(heading[0].size.width, 200.0, "spanner spans the container");
+
+        let c = tree.fragments(4)[0];
+        let d = tree.fragments(5)[0];
+        assert_approx!(c.position.y, 65.0, "second row starts below the spanner");
+        assert_approx!(d.position.x, 100.0, "d in the second column");
+        assert_approx!(d.position.y, 65.0, "d at the top of its column");
+
+        // Each row has its own rule; none crosses the spanner.
+        assert_eq!(layout.rules.len(), 2);
+        assert_approx!(layout.rules[0].height, 40.0, "first row rule");
+        assert_approx!(layout.rules[1].y, 65.0, "second row rule starts below spanner");
+        assert_approx!(layout.rules[1].height, 20.0, "second row rule");
+    }
+
+    // 5. Definite heights and column-fill
+    #[test]
+    fn fill_auto_spills_into_overflow_columns() {
+        let (tree, layout) = MockColumns::new(200.0)
+            .count(2)
+            .height(50.0)
+            .fill(ColumnFill::Auto)
+            .child(block("a", 30.0))
+            .child(block("b", 30.0))
+            .child(block("c", 30.0))
+            .layout()
+            .expect("layout must succeed");
+
+        assert_eq!(layout.column_count, 2);
+        assert_eq!(tree.fragments(2)[0].column, 1);
+        let c = tree.fragments(3)[0];
+        assert_eq!(c.column, 2, "third block overflows to an extra column");
+        assert_approx!(c.position.x, 200.0, "overflow column continues inline");
+    }
+
+    #[test]
+    fn balance_within_definite_height() {
+        let (tree, layout) = MockColumns::new(200.0)
+            .count(2)
+            .height(100.0)
+            .child(block("a", 30.0))
+            .child(block("b", 30.0))
+            .child(block("c", 30.0))
+            .layout()
+            .expect("layout must succeed");
+
+        // Balancing still wins while it fits inside the height.
+        assert_approx!(layout.height, 60.0, "balanced, not filled to 100");
+        assert_eq!(tree.fragments(2)[0].column, 0);
+        assert_eq!(tree.fragments(3)[0].column, 1);
+    }
+
+    #[test]
+    fn empty_container_has_no_height() {
+        let (_, layout) = MockColumns::new(200.0).count(3).layout().expect("layout must succeed");
+        assert_approx!(layout.height, 0.0, "no content");
+        assert!(layout.rules.is_empty());
+    }
+}
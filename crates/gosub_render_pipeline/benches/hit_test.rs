@@ -0,0 +1,107 @@
+//! Hit-test latency on a laid-out Wikipedia main page.
+//!
+//! Run: cargo bench -p gosub_render_pipeline --bench hit_test
+//!
+//! The page is parsed, laid out and layered once; each iteration then hit-tests a grid of
+//! points spread over the whole page, scrolled so every point lands on content.
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use gosub_css3::system::Css3System;
+use gosub_html5::document::builder::DocumentBuilderImpl;
+use gosub_html5::document::document_impl::DocumentImpl;
+use gosub_html5::parser::Html5Parser;
+use gosub_interface::config::ModuleConfiguration;
+use gosub_interface::css3::CssSystem as _;
+use gosub_interface::document::Document as _;
+use gosub_shared::byte_stream::{ByteStream, Encoding};
+
+use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
+use gosub_render_pipeline::common::geo::Dimension;
+use gosub_render_pipeline::layering::layer::LayerList;
+use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
+use gosub_render_pipeline::layouter::CanLayout;
+use gosub_render_pipeline::rendertree_builder::RenderTree;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Config;
+
+impl ModuleConfiguration for Config {
+    type CssSystem = Css3System;
+    type Document = DocumentImpl<Self>;
+    type HtmlParser = Html5Parser<'static, Self>;
+}
+
+const VIEWPORT_W: f64 = 1280.0;
+const VIEWPORT_H: f64 = 800.0;
+/// Points per row and per column of the hit-test grid.
+const GRID: usize = 32;
+
+fn layer_wikipedia(layouter: &mut TaffyLayouter) -> LayerList {
+    let path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/data/tree_iterator/wikipedia_main.html");
+    let html = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()));
+
+    let mut stream = ByteStream::from_str(&html, Encoding::UTF8);
+    let mut doc = DocumentBuilderImpl::new_document::<Config>(None);
+    let _ = Html5Parser::<Config>::parse_document(&mut stream, &mut doc, None);
+    doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+
+    let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+    let mut render_tree = RenderTree::new(Arc::new(adapter));
+    render_tree.parse().expect("failed to build render tree");
+    let layout_tree = layouter.layout(render_tree, Some(Dimension::new(VIEWPORT_W, VIEWPORT_H)), 1.0);
+    LayerList::new(layout_tree)
+}
+
+/// `(vp_x, vp_y, scroll_y)` for a `GRID` x `GRID` grid over the whole page: each point is
+/// reached by scrolling its row to the top half of the viewport.
+fn grid_points(page_height: f64) -> Vec<(f64, f64, f64)> {
+    let page_height = page_height.max(VIEWPORT_H);
+    let mut points = Vec::with_capacity(GRID * GRID);
+    for row in 0..GRID {
+        let page_y = (row as f64 + 0.5) * page_height / GRID as f64;
+        let scroll_y = (page_y - VIEWPORT_H / 2.0).clamp(0.0, page_height - VIEWPORT_H);
+        for col in 0..GRID {
+            let vp_x = (col as f64 + 0.5) * VIEWPORT_W / GRID as f64;
+            points.push((vp_x, page_y - scroll_y, scroll_y));
+        }
+    }
+    points
+}
+
+fn bench_hit_test(c: &mut Criterion) {
+    let mut layouter = TaffyLayouter::new();
+    let layer_list = layer_wikipedia(&mut layouter);
+    let font_system = layouter.font_system();
+    let points = grid_points(layer_list.layout_tree.root_dimension.height);
+
+    let mut group = c.benchmark_group("hit_test/wikipedia");
+    group.measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Elements(points.len() as u64));
+
+    group.bench_function("find_element_at", |b| {
+        b.iter(|| {
+            for &(x, y, scroll_y) in &points {
+                black_box(layer_list.find_element_at(black_box(x), black_box(y), 0.0, scroll_y));
+            }
+        });
+    });
+
+    group.bench_function("hit_test", |b| {
+        b.iter(|| {
+            for &(x, y, scroll_y) in &points {
+                black_box(layer_list.hit_test(black_box(x), black_box(y), 0.0, scroll_y, Some(&font_system)));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_hit_test);
+criterion_main!(benches);
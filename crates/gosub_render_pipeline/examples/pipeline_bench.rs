@@ -27,11 +27,13 @@ use gosub_shared::byte_stream::{ByteStream, Encoding};
 use gosub_render_pipeline::common::browser_state::{BrowserState, WireframeState};
 use gosub_render_pipeline::common::document::pipeline_doc::GosubDocumentAdapter;
 use gosub_render_pipeline::common::geo::{Dimension, Rect};
+use gosub_render_pipeline::find::FindMatches;
 use gosub_render_pipeline::layering::layer::LayerList;
 use gosub_render_pipeline::layouter::taffy::TaffyLayouter;
 use gosub_render_pipeline::layouter::CanLayout;
 use gosub_render_pipeline::painter::Painter;
 use gosub_render_pipeline::rendertree_builder::RenderTree;
+use gosub_render_pipeline::selection::TextSelection;
 use gosub_render_pipeline::tiler::{TileList, TileState};
 
 // ── Config ─────────────────────────────────────────────────────────────────
@@ -166,6 +168,12 @@ fn run_pipeline_once(html: &str, layouter: &mut TaffyLayouter) -> StageTimes {
         wireframed: WireframeState::None,
         debug_hover: false,
         current_hovered_element: None,
+        selection: TextSelection::default(),
+        find: FindMatches::default(),
+        frames: Default::default(),
+        media: Default::default(),
+        image_frames: Default::default(),
+        blocked_fonts: Vec::new(),
         show_tilegrid: false,
         debug_table_cells: false,
         viewport: full_rect,
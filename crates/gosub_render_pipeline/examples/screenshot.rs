@@ -131,7 +131,8 @@ fn main() {
                     );
                 }
             }
-            ElementContext::Image(_) | ElementContext::Svg(_) => {}
+            ElementContext::Image(_) | ElementContext::Svg(_) | ElementContext::Frame(_) | ElementContext::Media(_) => {
+            }
         }
 
         for &child_id in el.children.iter().rev() {
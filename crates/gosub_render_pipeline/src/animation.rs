@@ -0,0 +1,1252 @@
+//! CSS transitions and `@keyframes` animations.
+//!
+//! An [`AnimationTimeline`] follows the animated elements of a laid-out page. Each tick it starts
+//! a transition for every transitioned property whose value changed, samples the running
+//! transitions and animations, and publishes the animated values in [`AnimatedStyles`], which the
+//! document adapter answers ahead of the cascade. The returned [`AnimationUpdate`] tells how far
+//! down the pipeline the new values reach: `opacity` and `transform` only re-composite the
+//! element's layer, colours repaint its tiles, and every other property lays the page out again.
+//!
+//! Timing functions evaluate as [`Easing`] curves. Only the translation of a `transform` is
+//! rendered (as a composite-time layer offset); scales and rotations are parsed and interpolated
+//! but not drawn yet.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+use cow_utils::CowUtils;
+use gosub_interface::css3::KeyframesRule;
+use gosub_interface::font_system::WritingMode;
+use gosub_shared::animation::{Easing, StepPosition};
+use gosub_shared::node::NodeId;
+use parking_lot::RwLock;
+
+use crate::common::document::inline_style::parse_inline_style_attr;
+use crate::common::document::pipeline_doc::{PipelineDocument, PipelineNodeKind};
+use crate::common::document::style::{lookup, NodeStyle, StyleProperty, Unit, Value};
+use crate::layouter::{LayoutElementId, LayoutTree};
+
+/// Properties that interpolate smoothly; `transition-property: all` covers these. Others still
+/// animate from keyframes, flipping halfway like any value that can't be interpolated.
+const ANIMATABLE: [StyleProperty; 36] = [
+    StyleProperty::Opacity,
+    StyleProperty::Transform,
+    StyleProperty::Color,
+    StyleProperty::BackgroundColor,
+    StyleProperty::BorderTopColor,
+    StyleProperty::BorderRightColor,
+    StyleProperty::BorderBottomColor,
+    StyleProperty::BorderLeftColor,
+    StyleProperty::BorderTopLeftRadius,
+    StyleProperty::BorderTopRightRadius,
+    StyleProperty::BorderBottomRightRadius,
+    StyleProperty::BorderBottomLeftRadius,
+    StyleProperty::Width,
+    StyleProperty::Height,
+    StyleProperty::MinWidth,
+    StyleProperty::MinHeight,
+    StyleProperty::MaxWidth,
+    StyleProperty::MaxHeight,
+    StyleProperty::MarginTop,
+    StyleProperty::MarginRight,
+    StyleProperty::MarginBottom,
+    StyleProperty::MarginLeft,
+    StyleProperty::PaddingTop,
+    StyleProperty::PaddingRight,
+    StyleProperty::PaddingBottom,
+    StyleProperty::PaddingLeft,
+    StyleProperty::BorderTopWidth,
+    StyleProperty::BorderRightWidth,
+    StyleProperty::BorderBottomWidth,
+    StyleProperty::BorderLeftWidth,
+    StyleProperty::InsetBlockStart,
+    StyleProperty::InsetBlockEnd,
+    StyleProperty::InsetInlineStart,
+    StyleProperty::InsetInlineEnd,
+    StyleProperty::FontSize,
+    StyleProperty::Gap,
+];
+
+// ── Parsing ──────────────────────────────────────────────────────────────────
+
+/// Splits `text` at `sep` outside parentheses, trimming the parts and dropping empty ones. A
+/// `' '` separator splits at any whitespace.
+fn split_top_level(text: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && (c == sep || (sep == ' ' && c.is_whitespace())) => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts.into_iter().map(str::trim).filter(|p| !p.is_empty()).collect()
+}
+
+/// `name(args)` split into its name and argument text.
+fn function(text: &str) -> Option<(&str, &str)> {
+    let open = text.find('(')?;
+    let args = text[open + 1..].strip_suffix(')')?;
+    Some((text[..open].trim(), args))
+}
+
+/// Function arguments, separated by commas and/or whitespace.
+fn split_args(args: &str) -> Vec<&str> {
+    args.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+/// A CSS `<time>` (`250ms`, `1.5s`) in seconds.
+pub fn parse_time(text: &str) -> Option<f32> {
+    let text = text.trim().cow_to_ascii_lowercase();
+    if let Some(ms) = text.strip_suffix("ms") {
+        return ms.parse::<f32>().ok().map(|ms| ms / 1000.0);
+    }
+    text.strip_suffix('s')?.parse().ok()
+}
+
+/// A CSS `<easing-function>`: a named curve, `cubic-bezier()`, `steps()` or `step-start`/`-end`.
+pub fn parse_easing(text: &str) -> Option<Easing> {
+    let text = text.trim().cow_to_ascii_lowercase();
+    Some(match text.as_ref() {
+        "linear" => Easing::Linear,
+        "ease" => Easing::Ease,
+        "ease-in" => Easing::EaseIn,
+        "ease-out" => Easing::EaseOut,
+        "ease-in-out" => Easing::EaseInOut,
+        "step-start" => Easing::Steps(1, StepPosition::JumpStart),
+        "step-end" => Easing::Steps(1, StepPosition::JumpEnd),
+        other => {
+            let (name, args) = function(other)?;
+            let args = split_args(args);
+            match name {
+                "cubic-bezier" => {
+                    let n = args
+                        .iter()
+                        .map(|a| a.parse::<f32>().ok())
+                        .collect::<Option<Vec<f32>>>()?;
+                    let [x1, y1, x2, y2] = n[..] else {
+                        return None;
+                    };
+                    Easing::CubicBezier(x1.clamp(0.0, 1.0), y1, x2.clamp(0.0, 1.0), y2)
+                }
+                "steps" => {
+                    let count = args.first()?.parse::<u32>().ok()?;
+                    let position = match args.get(1).copied() {
+                        None | Some("end" | "jump-end") => StepPosition::JumpEnd,
+                        Some("start" | "jump-start") => StepPosition::JumpStart,
+                        Some("jump-none") => StepPosition::JumpNone,
+                        Some("jump-both") => StepPosition::JumpBoth,
+                        Some(_) => return None,
+                    };
+                    Easing::Steps(count.max(1), position)
+                }
+                _ => return None,
+            }
+        }
+    })
+}
+
+/// The value the `transition` or `animation` shorthand `text` gives `longhand`, as a list with
+/// one entry per comma-separated item. `None` for other properties. Components are told apart
+/// by their syntax: the first time is the duration and the second the delay; in `animation` the
+/// first token no other component takes is the name.
+pub fn shorthand_component(text: &str, longhand: &StyleProperty) -> Option<String> {
+    if !TRANSITION_LONGHANDS.contains(longhand) && !ANIMATION_LONGHANDS.contains(longhand) {
+        return None;
+    }
+
+    let items: Vec<String> = split_top_level(text, ',')
+        .into_iter()
+        .map(|item| {
+            let mut times = Vec::new();
+            let mut easing = None;
+            let mut other = Vec::new();
+            for token in split_top_level(item, ' ') {
+                if parse_time(token).is_some() {
+                    times.push(token);
+                } else if parse_easing(token).is_some() {
+                    easing = Some(token);
+                } else {
+                    other.push(token);
+                }
+            }
+            let keyword = |keywords: &[&str]| {
+                other
+                    .iter()
+                    .copied()
+                    .find(|t| keywords.iter().any(|k| t.eq_ignore_ascii_case(k)))
+            };
+            let value = match longhand {
+                StyleProperty::TransitionProperty => other.first().copied().unwrap_or("all"),
+                StyleProperty::TransitionDuration | StyleProperty::AnimationDuration => {
+                    times.first().copied().unwrap_or("0s")
+                }
+                StyleProperty::TransitionDelay | StyleProperty::AnimationDelay => times.get(1).copied().unwrap_or("0s"),
+                StyleProperty::TransitionTimingFunction | StyleProperty::AnimationTimingFunction => {
+                    easing.unwrap_or("ease")
+                }
+                StyleProperty::AnimationIterationCount => other
+                    .iter()
+                    .copied()
+                    .find(|t| t.eq_ignore_ascii_case("infinite") || t.parse::<f32>().is_ok())
+                    .unwrap_or("1"),
+                StyleProperty::AnimationDirection => keyword(&DIRECTIONS[..]).unwrap_or("normal"),
+                StyleProperty::AnimationFillMode => keyword(&FILL_MODES[..]).unwrap_or("none"),
+                StyleProperty::AnimationPlayState => keyword(&PLAY_STATES[..]).unwrap_or("running"),
+                _ => other
+                    .iter()
+                    .copied()
+                    .find(|t| {
+                        !t.eq_ignore_ascii_case("infinite")
+                            && t.parse::<f32>().is_err()
+                            && !DIRECTIONS
+                                .iter()
+                                .chain(&FILL_MODES)
+                                .chain(&PLAY_STATES)
+                                .any(|k| t.eq_ignore_ascii_case(k))
+                    })
+                    .unwrap_or("none"),
+            };
+            value.to_string()
+        })
+        .collect();
+    (!items.is_empty()).then(|| items.join(", "))
+}
+
+/// The longhands of the `transition` shorthand.
+pub const TRANSITION_LONGHANDS: [StyleProperty; 4] = [
+    StyleProperty::TransitionProperty,
+    StyleProperty::TransitionDuration,
+    StyleProperty::TransitionTimingFunction,
+    StyleProperty::TransitionDelay,
+];
+/// The longhands of the `animation` shorthand.
+pub const ANIMATION_LONGHANDS: [StyleProperty; 8] = [
+    StyleProperty::AnimationName,
+    StyleProperty::AnimationDuration,
+    StyleProperty::AnimationTimingFunction,
+    StyleProperty::AnimationDelay,
+    StyleProperty::AnimationIterationCount,
+    StyleProperty::AnimationDirection,
+    StyleProperty::AnimationFillMode,
+    StyleProperty::AnimationPlayState,
+];
+const DIRECTIONS: [&str; 4] = ["normal", "reverse", "alternate", "alternate-reverse"];
+const FILL_MODES: [&str; 4] = ["none", "forwards", "backwards", "both"];
+const PLAY_STATES: [&str; 2] = ["running", "paused"];
+
+/// The comma-separated entries of a list-valued property of `id`, as declared.
+fn style_list(doc: &dyn PipelineDocument, id: NodeId, prop: &StyleProperty) -> Vec<String> {
+    match doc.get_style(id, prop) {
+        Value::Keyword(kw) => split_top_level(&lookup(kw), ',')
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Entry `i` of a list property, which repeats when shorter than the list it pairs with.
+fn cycled<'a>(list: &'a [String], i: usize, default: &'a str) -> &'a str {
+    if list.is_empty() {
+        default
+    } else {
+        &list[i % list.len()]
+    }
+}
+
+// ── Transitions and animations of an element ────────────────────────────────
+
+/// One entry of an element's `transition-*` lists.
+#[derive(Clone, Debug)]
+pub struct TransitionSpec {
+    /// The lowercased `transition-property` entry: a property, a shorthand or `all`.
+    pub property: String,
+    /// In seconds.
+    pub duration: f32,
+    /// In seconds; negative starts the transition part-way.
+    pub delay: f32,
+    pub easing: Easing,
+}
+
+impl TransitionSpec {
+    /// The animatable properties the entry covers.
+    pub fn properties(&self) -> Vec<StyleProperty> {
+        let sides = |names: [&str; 4]| {
+            names
+                .iter()
+                .filter_map(|name| property_by_name(name))
+                .collect::<Vec<_>>()
+        };
+        match self.property.as_str() {
+            "all" => ANIMATABLE.to_vec(),
+            "margin" => sides(["margin-top", "margin-right", "margin-bottom", "margin-left"]),
+            "padding" => sides(["padding-top", "padding-right", "padding-bottom", "padding-left"]),
+            "inset" => sides(["top", "right", "bottom", "left"]),
+            "border-color" => sides([
+                "border-top-color",
+                "border-right-color",
+                "border-bottom-color",
+                "border-left-color",
+            ]),
+            "border-width" => sides([
+                "border-top-width",
+                "border-right-width",
+                "border-bottom-width",
+                "border-left-width",
+            ]),
+            "border-radius" => sides([
+                "border-top-left-radius",
+                "border-top-right-radius",
+                "border-bottom-right-radius",
+                "border-bottom-left-radius",
+            ]),
+            "background" => vec![StyleProperty::BackgroundColor],
+            name => property_by_name(name).into_iter().collect(),
+        }
+    }
+}
+
+/// The animatable property called `name`. The physical insets are the logical properties they
+/// land on in horizontal left-to-right text, as the inline-style parser files them.
+fn property_by_name(name: &str) -> Option<StyleProperty> {
+    if matches!(name, "top" | "right" | "bottom" | "left") {
+        return StyleProperty::inset_on_side(name, WritingMode::HorizontalTb, false);
+    }
+    ANIMATABLE.iter().find(|p| p.css_name() == name).cloned()
+}
+
+/// The transitions declared on `id`, one per `transition-property` entry; entries without a
+/// duration never run and are left out.
+pub fn transition_specs(doc: &dyn PipelineDocument, id: NodeId) -> Vec<TransitionSpec> {
+    let properties = style_list(doc, id, &StyleProperty::TransitionProperty);
+    let durations = style_list(doc, id, &StyleProperty::TransitionDuration);
+    let easings = style_list(doc, id, &StyleProperty::TransitionTimingFunction);
+    let delays = style_list(doc, id, &StyleProperty::TransitionDelay);
+    properties
+        .iter()
+        .enumerate()
+        .map(|(i, property)| TransitionSpec {
+            property: property.cow_to_ascii_lowercase().into_owned(),
+            duration: parse_time(cycled(&durations, i, "0s")).unwrap_or(0.0),
+            delay: parse_time(cycled(&delays, i, "0s")).unwrap_or(0.0),
+            easing: parse_easing(cycled(&easings, i, "ease")).unwrap_or(Easing::Ease),
+        })
+        .filter(|spec| spec.property != "none" && spec.duration > 0.0)
+        .collect()
+}
+
+/// Which way the iterations of an animation run (`animation-direction`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationDirection {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+/// Whether an animation's values apply before it starts and after it ends (`animation-fill-mode`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillMode {
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+/// One entry of an element's `animation-*` lists.
+#[derive(Clone, Debug)]
+pub struct AnimationSpec {
+    /// The `@keyframes` rule the animation runs.
+    pub name: String,
+    /// Of one iteration, in seconds.
+    pub duration: f32,
+    /// In seconds; negative starts the animation part-way.
+    pub delay: f32,
+    /// Applied between each pair of keyframes.
+    pub easing: Easing,
+    /// `f32::INFINITY` for `infinite`.
+    pub iterations: f32,
+    pub direction: AnimationDirection,
+    pub fill_mode: FillMode,
+    pub paused: bool,
+}
+
+impl AnimationSpec {
+    /// Progress through the keyframes (0 is the first, 1 the last) `elapsed` seconds after the
+    /// animation started, or `None` while the fill mode leaves the element unanimated.
+    pub fn progress(&self, elapsed: f32) -> Option<f32> {
+        let local = elapsed - self.delay;
+        let fills = |modes: [FillMode; 2]| modes.contains(&self.fill_mode);
+        if local < 0.0 {
+            return fills([FillMode::Backwards, FillMode::Both]).then(|| self.directed(0, 0.0));
+        }
+        if self.is_finished(elapsed) {
+            if !fills([FillMode::Forwards, FillMode::Both]) {
+                return None;
+            }
+            // An animation ending on a whole iteration shows that iteration's last frame
+            let iterations = if self.iterations.is_finite() {
+                self.iterations.max(0.0)
+            } else {
+                1.0
+            };
+            return Some(if iterations > 0.0 && iterations.fract() == 0.0 {
+                self.directed(iterations as u32 - 1, 1.0)
+            } else {
+                self.directed(iterations as u32, iterations.fract())
+            });
+        }
+        let done = local / self.duration;
+        Some(self.directed(done as u32, done.fract()))
+    }
+
+    /// True once every iteration has played; never for an infinite animation with a duration.
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        let local = elapsed - self.delay;
+        self.duration <= 0.0 || (self.iterations.is_finite() && local >= self.duration * self.iterations)
+    }
+
+    fn directed(&self, iteration: u32, fraction: f32) -> f32 {
+        let reversed = match self.direction {
+            AnimationDirection::Normal => false,
+            AnimationDirection::Reverse => true,
+            AnimationDirection::Alternate => iteration % 2 == 1,
+            AnimationDirection::AlternateReverse => iteration.is_multiple_of(2),
+        };
+        if reversed {
+            1.0 - fraction
+        } else {
+            fraction
+        }
+    }
+}
+
+/// The animations declared on `id`, one per `animation-name` entry other than `none`.
+pub fn animation_specs(doc: &dyn PipelineDocument, id: NodeId) -> Vec<AnimationSpec> {
+    let names = style_list(doc, id, &StyleProperty::AnimationName);
+    if names.iter().all(|name| name.eq_ignore_ascii_case("none")) {
+        return Vec::new();
+    }
+    let list = |prop: StyleProperty| style_list(doc, id, &prop);
+    let durations = list(StyleProperty::AnimationDuration);
+    let easings = list(StyleProperty::AnimationTimingFunction);
+    let delays = list(StyleProperty::AnimationDelay);
+    let counts = list(StyleProperty::AnimationIterationCount);
+    let directions = list(StyleProperty::AnimationDirection);
+    let fill_modes = list(StyleProperty::AnimationFillMode);
+    let play_states = list(StyleProperty::AnimationPlayState);
+    names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !name.eq_ignore_ascii_case("none"))
+        .map(|(i, name)| {
+            let count = cycled(&counts, i, "1");
+            AnimationSpec {
+                name: name.trim_matches(['"', '\'']).to_string(),
+                duration: parse_time(cycled(&durations, i, "0s")).unwrap_or(0.0),
+                delay: parse_time(cycled(&delays, i, "0s")).unwrap_or(0.0),
+                easing: parse_easing(cycled(&easings, i, "ease")).unwrap_or(Easing::Ease),
+                iterations: if count.eq_ignore_ascii_case("infinite") {
+                    f32::INFINITY
+                } else {
+                    count.parse::<f32>().map(|n| n.max(0.0)).unwrap_or(1.0)
+                },
+                direction: match cycled(&directions, i, "normal").cow_to_ascii_lowercase().as_ref() {
+                    "reverse" => AnimationDirection::Reverse,
+                    "alternate" => AnimationDirection::Alternate,
+                    "alternate-reverse" => AnimationDirection::AlternateReverse,
+                    _ => AnimationDirection::Normal,
+                },
+                fill_mode: match cycled(&fill_modes, i, "none").cow_to_ascii_lowercase().as_ref() {
+                    "forwards" => FillMode::Forwards,
+                    "backwards" => FillMode::Backwards,
+                    "both" => FillMode::Both,
+                    _ => FillMode::None,
+                },
+                paused: cycled(&play_states, i, "running").eq_ignore_ascii_case("paused"),
+            }
+        })
+        .collect()
+}
+
+/// The keyframes of `rule` as styles by offset, in offset order. A keyframe listing several
+/// offsets appears once for each.
+fn keyframe_styles(rule: &KeyframesRule) -> Vec<(f32, NodeStyle)> {
+    let mut frames = Vec::new();
+    for keyframe in &rule.keyframes {
+        let text: String = keyframe
+            .declarations
+            .iter()
+            .map(|(property, value)| format!("{property}: {value};"))
+            .collect();
+        let style = parse_inline_style_attr(&text);
+        for &offset in &keyframe.offsets {
+            frames.push((offset, style.clone()));
+        }
+    }
+    frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+    frames
+}
+
+// ── Values ───────────────────────────────────────────────────────────────────
+
+/// A function of a `transform` list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformFunction {
+    /// In CSS px.
+    Translate(f32, f32),
+    Scale(f32, f32),
+    /// Clockwise, in degrees.
+    Rotate(f32),
+}
+
+impl TransformFunction {
+    /// The function of the same kind that leaves the element as it is.
+    fn identity(self) -> Self {
+        match self {
+            TransformFunction::Translate(..) => TransformFunction::Translate(0.0, 0.0),
+            TransformFunction::Scale(..) => TransformFunction::Scale(1.0, 1.0),
+            TransformFunction::Rotate(_) => TransformFunction::Rotate(0.0),
+        }
+    }
+}
+
+/// A length in CSS px; font-relative units assume the 16px default. `None` for percentages.
+fn parse_length(text: &str) -> Option<f32> {
+    if let Some(px) = text.strip_suffix("px") {
+        return px.parse().ok();
+    }
+    if let Some(em) = text.strip_suffix("rem").or_else(|| text.strip_suffix("em")) {
+        return em.parse::<f32>().ok().map(|em| em * 16.0);
+    }
+    text.parse::<f32>().ok().filter(|n| *n == 0.0)
+}
+
+/// An angle in degrees.
+fn parse_angle(text: &str) -> Option<f32> {
+    if let Some(deg) = text.strip_suffix("deg") {
+        return deg.parse().ok();
+    }
+    if let Some(grad) = text.strip_suffix("grad") {
+        return grad.parse::<f32>().ok().map(|grad| grad * 0.9);
+    }
+    if let Some(rad) = text.strip_suffix("rad") {
+        return rad.parse::<f32>().ok().map(f32::to_degrees);
+    }
+    if let Some(turn) = text.strip_suffix("turn") {
+        return turn.parse::<f32>().ok().map(|turn| turn * 360.0);
+    }
+    text.parse::<f32>().ok().filter(|n| *n == 0.0)
+}
+
+/// The 2D translate, scale and rotate functions of a `transform` value, in order. `none` and
+/// unsupported functions (`matrix()`, `skew()`, 3D) contribute nothing.
+pub fn parse_transform(text: &str) -> Vec<TransformFunction> {
+    let text = text.trim().cow_to_ascii_lowercase();
+    let mut out = Vec::new();
+    for part in split_top_level(&text, ' ') {
+        let Some((name, args)) = function(part) else {
+            continue;
+        };
+        let args = split_args(args);
+        let length = |i: usize| args.get(i).and_then(|a| parse_length(a));
+        let number = |i: usize| args.get(i).and_then(|a| a.parse::<f32>().ok());
+        out.push(match name {
+            "translate" | "translate3d" => {
+                TransformFunction::Translate(length(0).unwrap_or(0.0), length(1).unwrap_or(0.0))
+            }
+            "translatex" => TransformFunction::Translate(length(0).unwrap_or(0.0), 0.0),
+            "translatey" => TransformFunction::Translate(0.0, length(0).unwrap_or(0.0)),
+            "scale" | "scale3d" => {
+                let x = number(0).unwrap_or(1.0);
+                TransformFunction::Scale(x, number(1).unwrap_or(x))
+            }
+            "scalex" => TransformFunction::Scale(number(0).unwrap_or(1.0), 1.0),
+            "scaley" => TransformFunction::Scale(1.0, number(0).unwrap_or(1.0)),
+            "rotate" | "rotatez" => TransformFunction::Rotate(args.first().and_then(|a| parse_angle(a)).unwrap_or(0.0)),
+            _ => continue,
+        });
+    }
+    out
+}
+
+/// CSS text for a list of transform functions.
+fn transform_text(functions: &[TransformFunction]) -> String {
+    if functions.is_empty() {
+        return "none".to_string();
+    }
+    functions
+        .iter()
+        .map(|f| match f {
+            TransformFunction::Translate(x, y) => format!("translate({x}px, {y}px)"),
+            TransformFunction::Scale(x, y) => format!("scale({x}, {y})"),
+            TransformFunction::Rotate(a) => format!("rotate({a}deg)"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The total offset, in CSS px, the translations of a `transform` value move the element by.
+pub fn transform_translation(text: &str) -> (f64, f64) {
+    parse_transform(text).into_iter().fold((0.0, 0.0), |(x, y), f| match f {
+        TransformFunction::Translate(dx, dy) => (x + dx as f64, y + dy as f64),
+        _ => (x, y),
+    })
+}
+
+/// Two transform lists interpolate function by function when they are the same kinds in the
+/// same order (`none` standing for identities); other pairs flip halfway.
+fn interpolate_transforms(from: &str, to: &str, t: f32) -> String {
+    let mut a = parse_transform(from);
+    let mut b = parse_transform(to);
+    if a.is_empty() {
+        a = b.iter().map(|f| f.identity()).collect();
+    } else if b.is_empty() {
+        b = a.iter().map(|f| f.identity()).collect();
+    }
+    if a.len() != b.len() {
+        return (if t < 0.5 { from } else { to }).to_string();
+    }
+    let lerp = |x: f32, y: f32| x + (y - x) * t;
+    let mut out = Vec::with_capacity(a.len());
+    for (from_fn, to_fn) in a.iter().zip(&b) {
+        out.push(match (from_fn, to_fn) {
+            (TransformFunction::Translate(x1, y1), TransformFunction::Translate(x2, y2)) => {
+                TransformFunction::Translate(lerp(*x1, *x2), lerp(*y1, *y2))
+            }
+            (TransformFunction::Scale(x1, y1), TransformFunction::Scale(x2, y2)) => {
+                TransformFunction::Scale(lerp(*x1, *x2), lerp(*y1, *y2))
+            }
+            (TransformFunction::Rotate(a1), TransformFunction::Rotate(a2)) => TransformFunction::Rotate(lerp(*a1, *a2)),
+            _ => return (if t < 0.5 { from } else { to }).to_string(),
+        });
+    }
+    transform_text(&out)
+}
+
+/// The value of `prop` a fraction `t` of the way from `from` to `to`. Colours, lengths in the
+/// same unit, numbers, percentages and transforms interpolate; anything else flips halfway.
+/// `t` may leave `[0, 1]` for overshooting timing functions.
+pub fn interpolate(prop: &StyleProperty, from: &Value, to: &Value, t: f32) -> Value {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let channel = |a: u8, b: u8| lerp(a as f32, b as f32).round().clamp(0.0, 255.0) as u8;
+    match (from, to) {
+        (Value::Color(r1, g1, b1, a1), Value::Color(r2, g2, b2, a2)) => Value::Color(
+            channel(*r1, *r2),
+            channel(*g1, *g2),
+            channel(*b1, *b2),
+            channel(*a1, *a2),
+        ),
+        (Value::Unit(a, unit), Value::Unit(b, other)) if unit == other => Value::Unit(lerp(*a, *b), unit.clone()),
+        (Value::Number(a), Value::Number(b)) => Value::Number(lerp(*a, *b)),
+        (Value::Percentage(a), Value::Percentage(b)) => Value::Percentage(lerp(*a, *b)),
+        (Value::Keyword(a), Value::Keyword(b)) if *prop == StyleProperty::Transform => {
+            Value::keyword(&interpolate_transforms(&lookup(*a), &lookup(*b), t))
+        }
+        _ if t < 0.5 => from.clone(),
+        _ => to.clone(),
+    }
+}
+
+/// `value` of `prop` on `id` with font-relative lengths resolved to px, as `get_style` would,
+/// and `opacity` as a plain number, so values from different sources interpolate.
+fn resolve(doc: &dyn PipelineDocument, id: NodeId, prop: &StyleProperty, value: Value) -> Value {
+    match value {
+        Value::Unit(n, _) if *prop == StyleProperty::Opacity => Value::Number(n),
+        Value::Unit(v, Unit::Rem) => Value::Unit(v * 16.0, Unit::Px),
+        Value::Unit(v, Unit::Em) => {
+            let basis = match (prop, doc.parent(id)) {
+                (StyleProperty::FontSize, Some(parent)) => doc.font_size_px(parent),
+                (StyleProperty::FontSize, None) => 16.0,
+                _ => doc.font_size_px(id),
+            };
+            Value::Unit(v * basis, Unit::Px)
+        }
+        other => other,
+    }
+}
+
+/// The value of `prop` on `id` that animations and transitions of it start from and settle on:
+/// the cascaded value with its own animations left out.
+fn base_value(doc: &dyn PipelineDocument, id: NodeId, prop: &StyleProperty) -> Value {
+    let value = doc.get_own_base_style(id, prop).unwrap_or_else(|| {
+        let meta = prop.meta();
+        match doc.parent(id) {
+            Some(parent) if meta.inherited => doc.get_style(parent, prop),
+            _ => meta.initial_value(),
+        }
+    });
+    resolve(doc, id, prop, value)
+}
+
+/// How far down the pipeline a change of `prop` reaches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Invalidation {
+    /// The element's layer is faded or moved at composite time.
+    Composite,
+    /// The element's tiles are painted again on the current layout.
+    Paint,
+    /// The page is laid out again.
+    Layout,
+}
+
+fn invalidation(prop: &StyleProperty) -> Invalidation {
+    match prop {
+        StyleProperty::Opacity | StyleProperty::Transform => Invalidation::Composite,
+        StyleProperty::Color
+        | StyleProperty::BackgroundColor
+        | StyleProperty::BorderTopColor
+        | StyleProperty::BorderRightColor
+        | StyleProperty::BorderBottomColor
+        | StyleProperty::BorderLeftColor
+        | StyleProperty::BorderTopLeftRadius
+        | StyleProperty::BorderTopRightRadius
+        | StyleProperty::BorderBottomRightRadius
+        | StyleProperty::BorderBottomLeftRadius
+        | StyleProperty::ColumnRuleColor
+        | StyleProperty::BoxShadow
+        | StyleProperty::TextShadow => Invalidation::Paint,
+        _ => Invalidation::Layout,
+    }
+}
+
+// ── Timeline ─────────────────────────────────────────────────────────────────
+
+/// The animated values of a page, by element, shared between the [`AnimationTimeline`] that
+/// writes them and the document adapters that answer them ahead of the cascade.
+#[derive(Debug, Default)]
+pub struct AnimatedStyles {
+    styles: RwLock<HashMap<NodeId, NodeStyle>>,
+    /// Elements with an `opacity` or `transform` animation or transition in flight.
+    composited: RwLock<HashSet<NodeId>>,
+}
+
+impl AnimatedStyles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The animated value of `prop` on `id`, if it is being animated.
+    pub fn get(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
+        self.styles.read().get(&id)?.get_own(prop).cloned()
+    }
+
+    /// True while `id` animates `opacity` or `transform`.
+    pub fn is_composited(&self, id: NodeId) -> bool {
+        self.composited.read().contains(&id)
+    }
+
+    fn clear(&self) {
+        self.styles.write().clear();
+        self.composited.write().clear();
+    }
+}
+
+/// What the values of an [`AnimationTimeline::tick`] invalidate.
+#[derive(Debug, Default, PartialEq)]
+pub struct AnimationUpdate {
+    /// A property that affects layout changed: the page must be laid out again.
+    pub relayout: bool,
+    /// Layout elements to repaint on the current layout, for changed colours.
+    pub repaint: Vec<LayoutElementId>,
+    /// Elements whose `opacity` or `transform` changed, for their layers to be updated in place.
+    pub composite: Vec<(NodeId, LayoutElementId)>,
+}
+
+impl AnimationUpdate {
+    pub fn is_empty(&self) -> bool {
+        !self.relayout && self.repaint.is_empty() && self.composite.is_empty()
+    }
+}
+
+/// An element with transitions or animations, and the layout elements its values can show in.
+struct AnimatedElement {
+    node: NodeId,
+    element: LayoutElementId,
+    /// The element's own layout elements and those of its subtree, which inherit its colours.
+    subtree: Vec<LayoutElementId>,
+}
+
+struct RunningTransition {
+    from: Value,
+    to: Value,
+    start: Instant,
+    delay: f32,
+    duration: f32,
+    easing: Easing,
+}
+
+impl RunningTransition {
+    /// The value at `now`, or `None` once the transition has finished.
+    fn value_at(&self, prop: &StyleProperty, now: Instant) -> Option<Value> {
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f32() - self.delay;
+        if elapsed >= self.duration {
+            return None;
+        }
+        let t = (elapsed / self.duration).max(0.0);
+        Some(interpolate(prop, &self.from, &self.to, self.easing.eval(t)))
+    }
+}
+
+struct RunningAnimation {
+    spec: AnimationSpec,
+    start: Instant,
+    /// When a paused animation was paused, to resume it where it stopped.
+    paused_at: Option<Instant>,
+    frames: Vec<(f32, NodeStyle)>,
+}
+
+/// The transitions and keyframe animations of one page. [`Self::scan`] picks up the animated
+/// elements of a new layout; [`Self::tick`] advances everything to a point in time.
+pub struct AnimationTimeline {
+    styles: Arc<AnimatedStyles>,
+    elements: Vec<AnimatedElement>,
+    /// The value each transitioned property last settled on, for spotting changes.
+    settled: HashMap<(NodeId, StyleProperty), Value>,
+    transitions: HashMap<(NodeId, StyleProperty), RunningTransition>,
+    animations: HashMap<NodeId, Vec<RunningAnimation>>,
+}
+
+impl AnimationTimeline {
+    /// A timeline publishing its values to `styles`.
+    pub fn new(styles: Arc<AnimatedStyles>) -> Self {
+        Self {
+            styles,
+            elements: Vec::new(),
+            settled: HashMap::new(),
+            transitions: HashMap::new(),
+            animations: HashMap::new(),
+        }
+    }
+
+    /// True when nothing is declared to animate, so ticks can be skipped.
+    pub fn is_idle(&self) -> bool {
+        self.elements.is_empty() && self.transitions.is_empty()
+    }
+
+    /// Drops every animation and transition, e.g. for a new document.
+    pub fn reset(&mut self) {
+        self.elements.clear();
+        self.settled.clear();
+        self.transitions.clear();
+        self.animations.clear();
+        self.styles.clear();
+    }
+
+    /// Finds the elements of `layout_tree` that declare transitions or animations. Animations
+    /// still listed carry on, newly listed ones start at `now` and the others stop. Properties
+    /// seen for the first time settle without transitioning.
+    pub fn scan(&mut self, layout_tree: &LayoutTree, now: Instant) {
+        let doc = &*layout_tree.render_tree.doc;
+        let mut elements = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![layout_tree.root_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = layout_tree.get_node_by_id(id) else {
+                continue;
+            };
+            stack.extend(node.children.iter().rev());
+            let node_id = node.dom_node_id;
+            if doc.node_kind(node_id) != PipelineNodeKind::Element || !seen.insert(node_id) {
+                continue;
+            }
+            let transitions = transition_specs(doc, node_id);
+            let animations = animation_specs(doc, node_id);
+            if transitions.is_empty() && animations.is_empty() {
+                continue;
+            }
+
+            let mut running = self.animations.remove(&node_id).unwrap_or_default();
+            let mut kept = Vec::new();
+            for spec in animations {
+                if let Some(i) = running.iter().position(|r| r.spec.name == spec.name) {
+                    let mut animation = running.swap_remove(i);
+                    animation.spec = spec;
+                    kept.push(animation);
+                } else if let Some(rule) = doc.keyframes(&spec.name) {
+                    kept.push(RunningAnimation {
+                        spec,
+                        start: now,
+                        paused_at: None,
+                        frames: keyframe_styles(&rule),
+                    });
+                }
+            }
+            if !kept.is_empty() {
+                self.animations.insert(node_id, kept);
+            }
+            for spec in &transitions {
+                for prop in spec.properties() {
+                    self.settled
+                        .entry((node_id, prop.clone()))
+                        .or_insert_with(|| base_value(doc, node_id, &prop));
+                }
+            }
+
+            let mut subtree = Vec::new();
+            let mut below = vec![id];
+            while let Some(child) = below.pop() {
+                subtree.push(child);
+                if let Some(child) = layout_tree.get_node_by_id(child) {
+                    below.extend(&child.children);
+                }
+            }
+            elements.push(AnimatedElement {
+                node: node_id,
+                element: id,
+                subtree,
+            });
+        }
+
+        self.animations.retain(|id, _| seen.contains(id));
+        self.settled.retain(|(id, _), _| seen.contains(id));
+        self.transitions.retain(|(id, _), _| seen.contains(id));
+        self.elements = elements;
+    }
+
+    /// Advances every transition and animation to `now` and publishes the values. A property
+    /// whose value changed since the last tick transitions from what was shown towards the new
+    /// value. With `reduced_motion`, transitions jump to their end and animations don't run.
+    pub fn tick(&mut self, doc: &dyn PipelineDocument, now: Instant, reduced_motion: bool) -> AnimationUpdate {
+        for element in &self.elements {
+            let id = element.node;
+            // A property listed twice transitions as its last entry says
+            let mut by_property: HashMap<StyleProperty, &TransitionSpec> = HashMap::new();
+            let specs = transition_specs(doc, id);
+            for spec in &specs {
+                for prop in spec.properties() {
+                    by_property.insert(prop, spec);
+                }
+            }
+            for (prop, spec) in by_property {
+                let target = base_value(doc, id, &prop);
+                let key = (id, prop);
+                let Some(settled) = self.settled.get(&key) else {
+                    self.settled.insert(key, target);
+                    continue;
+                };
+                if *settled == target {
+                    continue;
+                }
+                let from = self
+                    .transitions
+                    .get(&key)
+                    .and_then(|running| running.value_at(&key.1, now))
+                    .unwrap_or_else(|| settled.clone());
+                self.settled.insert(key.clone(), target.clone());
+                if reduced_motion {
+                    self.transitions.remove(&key);
+                    continue;
+                }
+                self.transitions.insert(
+                    key,
+                    RunningTransition {
+                        from,
+                        to: target,
+                        start: now,
+                        delay: spec.delay,
+                        duration: spec.duration,
+                        easing: spec.easing.clone(),
+                    },
+                );
+            }
+        }
+
+        // Transitions sit above animations in the cascade, so they are applied last
+        let mut styles: HashMap<NodeId, NodeStyle> = HashMap::new();
+        if reduced_motion {
+            self.transitions.clear();
+        } else {
+            for (&id, running) in &mut self.animations {
+                for animation in running {
+                    match (animation.spec.paused, animation.paused_at) {
+                        (true, None) => animation.paused_at = Some(now),
+                        (false, Some(paused_at)) => {
+                            animation.start += now.saturating_duration_since(paused_at);
+                            animation.paused_at = None;
+                        }
+                        _ => {}
+                    }
+                    let at = animation.paused_at.unwrap_or(now);
+                    let elapsed = at.saturating_duration_since(animation.start).as_secs_f32();
+                    let Some(progress) = animation.spec.progress(elapsed) else {
+                        continue;
+                    };
+                    let style = styles.entry(id).or_default();
+                    sample(doc, id, &animation.frames, progress, &animation.spec.easing, style);
+                }
+            }
+        }
+        self.transitions.retain(|(id, prop), running| {
+            let Some(value) = running.value_at(prop, now) else {
+                return false;
+            };
+            styles.entry(*id).or_default().set(prop.clone(), value);
+            true
+        });
+
+        let update = self.diff(&styles);
+        let composited: HashSet<NodeId> = styles
+            .iter()
+            .filter(|(_, style)| {
+                style
+                    .iter()
+                    .any(|(prop, _)| invalidation(&prop) == Invalidation::Composite)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        *self.styles.styles.write() = styles;
+        *self.styles.composited.write() = composited;
+        update
+    }
+
+    /// Pauses every animation and transition at `paused_at`, to carry on from there at `now`.
+    pub fn shift(&mut self, paused_at: Instant, now: Instant) {
+        let paused_for = now.saturating_duration_since(paused_at);
+        for animation in self.animations.values_mut().flatten() {
+            animation.start += paused_for;
+        }
+        for transition in self.transitions.values_mut() {
+            transition.start += paused_for;
+        }
+    }
+
+    /// What changed between the published values and `styles`.
+    fn diff(&self, styles: &HashMap<NodeId, NodeStyle>) -> AnimationUpdate {
+        let previous = self.styles.styles.read();
+        let mut update = AnimationUpdate::default();
+        let mut repaint = HashSet::new();
+        let empty = NodeStyle::new();
+        let ids: HashSet<NodeId> = previous.keys().chain(styles.keys()).copied().collect();
+        for id in ids {
+            let old = previous.get(&id).unwrap_or(&empty);
+            let new = styles.get(&id).unwrap_or(&empty);
+            let changed: Vec<Invalidation> = old
+                .iter()
+                .chain(new.iter())
+                .filter(|(prop, _)| old.get_own(prop) != new.get_own(prop))
+                .map(|(prop, _)| invalidation(&prop))
+                .collect();
+            let Some(element) = self.elements.iter().find(|e| e.node == id) else {
+                // Its element is gone from the layout; a layout is already on its way
+                continue;
+            };
+            if changed.contains(&Invalidation::Composite) {
+                update.composite.push((id, element.element));
+            }
+            if changed.contains(&Invalidation::Paint) {
+                repaint.extend(element.subtree.iter().copied());
+            }
+            update.relayout |= changed.contains(&Invalidation::Layout);
+        }
+        update.repaint = repaint.into_iter().collect();
+        update
+    }
+}
+
+/// Samples the keyframes at `progress` into `out`. Each property interpolates between the
+/// nearest keyframes that set it, with the element's own value standing in for a missing first
+/// or last keyframe.
+fn sample(
+    doc: &dyn PipelineDocument,
+    id: NodeId,
+    frames: &[(f32, NodeStyle)],
+    progress: f32,
+    easing: &Easing,
+    out: &mut NodeStyle,
+) {
+    let mut properties: Vec<StyleProperty> = Vec::new();
+    for (_, style) in frames {
+        for (prop, _) in style.iter() {
+            if !properties.contains(&prop) {
+                properties.push(prop);
+            }
+        }
+    }
+    for prop in properties {
+        let value_at = |(offset, style): &(f32, NodeStyle)| Some((*offset, style.get_own(&prop)?.clone()));
+        let before = frames.iter().rev().filter(|f| f.0 <= progress).find_map(value_at);
+        let after = frames.iter().filter(|f| f.0 >= progress).find_map(value_at);
+        let (from_offset, from) = before.unwrap_or_else(|| (0.0, base_value(doc, id, &prop)));
+        let (to_offset, to) = after.unwrap_or_else(|| (1.0, base_value(doc, id, &prop)));
+        let t = if to_offset > from_offset {
+            (progress - from_offset) / (to_offset - from_offset)
+        } else {
+            1.0
+        };
+        let from = resolve(doc, id, &prop, from);
+        let to = resolve(doc, id, &prop, to);
+        out.set(prop.clone(), interpolate(&prop, &from, &to, easing.eval(t)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(iterations: f32, direction: AnimationDirection, fill_mode: FillMode) -> AnimationSpec {
+        AnimationSpec {
+            name: "move".to_string(),
+            duration: 2.0,
+            delay: 1.0,
+            easing: Easing::Linear,
+            iterations,
+            direction,
+            fill_mode,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn times_and_easings_parse() {
+        assert_eq!(parse_time("250ms"), Some(0.25));
+        assert_eq!(parse_time("1.5s"), Some(1.5));
+        assert_eq!(parse_time("ease"), None);
+
+        let eval = |text: &str, t: f32| parse_easing(text).map(|e| e.eval(t));
+        assert_eq!(eval("linear", 0.3), Some(0.3));
+        assert_eq!(eval("steps(4, end)", 0.3), Some(0.25));
+        assert_eq!(eval("step-start", 0.1), Some(1.0));
+        assert!(eval("cubic-bezier(0.25, 0.1, 0.25, 1)", 0.5).is_some());
+        assert!(parse_easing("cubic-bezier(1, 2)").is_none());
+        assert!(parse_easing("opacity").is_none());
+    }
+
+    #[test]
+    fn shorthands_split_into_longhands() {
+        let transition = "opacity 0.3s ease-in, transform 1s 200ms";
+        let component = |prop| shorthand_component(transition, &prop);
+        assert_eq!(
+            component(StyleProperty::TransitionProperty).as_deref(),
+            Some("opacity, transform")
+        );
+        assert_eq!(
+            component(StyleProperty::TransitionDuration).as_deref(),
+            Some("0.3s, 1s")
+        );
+        assert_eq!(component(StyleProperty::TransitionDelay).as_deref(), Some("0s, 200ms"));
+        assert_eq!(
+            component(StyleProperty::TransitionTimingFunction).as_deref(),
+            Some("ease-in, ease")
+        );
+
+        let animation = "spin 2s linear infinite alternate both";
+        let component = |prop| shorthand_component(animation, &prop);
+        assert_eq!(component(StyleProperty::AnimationName).as_deref(), Some("spin"));
+        assert_eq!(
+            component(StyleProperty::AnimationIterationCount).as_deref(),
+            Some("infinite")
+        );
+        assert_eq!(
+            component(StyleProperty::AnimationDirection).as_deref(),
+            Some("alternate")
+        );
+        assert_eq!(component(StyleProperty::AnimationFillMode).as_deref(), Some("both"));
+        assert_eq!(component(StyleProperty::AnimationPlayState).as_deref(), Some("running"));
+        assert_eq!(shorthand_component(animation, &StyleProperty::Opacity), None);
+    }
+
+    #[test]
+    fn animation_progress_follows_direction_and_fill() {
+        let normal = spec(2.0, AnimationDirection::Normal, FillMode::None);
+        assert_eq!(normal.progress(0.5), None);
+        assert_eq!(normal.progress(2.0), Some(0.5));
+        assert_eq!(normal.progress(3.5), Some(0.25));
+        assert_eq!(normal.progress(5.0), None);
+        assert!(normal.is_finished(5.0));
+
+        let alternate = spec(2.0, AnimationDirection::Alternate, FillMode::Both);
+        assert_eq!(alternate.progress(0.0), Some(0.0));
+        assert_eq!(alternate.progress(3.5), Some(0.75));
+        assert_eq!(alternate.progress(9.0), Some(0.0));
+
+        let forwards = spec(1.5, AnimationDirection::Normal, FillMode::Forwards);
+        assert_eq!(forwards.progress(10.0), Some(0.5));
+
+        let infinite = spec(f32::INFINITY, AnimationDirection::Reverse, FillMode::None);
+        assert!(!infinite.is_finished(1000.0));
+        assert_eq!(infinite.progress(1000.5), Some(0.25));
+    }
+
+    #[test]
+    fn values_interpolate() {
+        let opacity = StyleProperty::Opacity;
+        assert_eq!(
+            interpolate(&opacity, &Value::Number(0.0), &Value::Number(1.0), 0.25),
+            Value::Number(0.25)
+        );
+        assert_eq!(
+            interpolate(
+                &StyleProperty::BackgroundColor,
+                &Value::Color(0, 0, 0, 255),
+                &Value::Color(255, 100, 0, 255),
+                0.5
+            ),
+            Value::Color(128, 50, 0, 255)
+        );
+        assert_eq!(
+            interpolate(
+                &StyleProperty::Width,
+                &Value::Unit(10.0, Unit::Px),
+                &Value::Unit(20.0, Unit::Px),
+                0.5
+            ),
+            Value::Unit(15.0, Unit::Px)
+        );
+        // Mixed units can't interpolate, so they flip halfway
+        let (px, pct) = (Value::Unit(10.0, Unit::Px), Value::Unit(50.0, Unit::Percent));
+        assert_eq!(interpolate(&StyleProperty::Width, &px, &pct, 0.4), px);
+        assert_eq!(interpolate(&StyleProperty::Width, &px, &pct, 0.6), pct);
+    }
+
+    #[test]
+    fn transforms_interpolate_and_translate() {
+        assert_eq!(
+            parse_transform("translateX(10px) scale(2) rotate(0.5turn)"),
+            vec![
+                TransformFunction::Translate(10.0, 0.0),
+                TransformFunction::Scale(2.0, 2.0),
+                TransformFunction::Rotate(180.0),
+            ]
+        );
+        assert_eq!(
+            interpolate_transforms("none", "translate(20px, 40px)", 0.5),
+            "translate(10px, 20px)"
+        );
+        assert_eq!(
+            interpolate_transforms("translate(0, 0)", "rotate(90deg)", 0.25),
+            "translate(0, 0)"
+        );
+        assert_eq!(
+            transform_translation("translate(5px, 6px) translateY(4px)"),
+            (5.0, 10.0)
+        );
+        assert_eq!(transform_translation("none"), (0.0, 0.0));
+    }
+
+    #[test]
+    fn transitions_cover_shorthands_and_all() {
+        let spec = |property: &str| TransitionSpec {
+            property: property.to_string(),
+            duration: 1.0,
+            delay: 0.0,
+            easing: Easing::Linear,
+        };
+        assert_eq!(spec("all").properties().len(), ANIMATABLE.len());
+        assert_eq!(spec("margin").properties().len(), 4);
+        assert_eq!(spec("left").properties(), vec![StyleProperty::InsetInlineStart]);
+        assert_eq!(spec("opacity").properties(), vec![StyleProperty::Opacity]);
+        assert!(spec("display").properties().is_empty());
+    }
+}
@@ -1,9 +1,23 @@
+use crate::common::document::node::NodeId;
 use crate::common::geo::Rect;
+use crate::common::media::MediaId;
+use crate::find::FindMatches;
 use crate::layouter::LayoutElementId;
+use crate::media_element::MediaPresentation;
+use crate::selection::TextSelection;
 use crate::tiler::TileList;
+use gosub_interface::font_system::UnicodeRange;
 use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+/// An `@font-face` subset whose fetch is in flight during its block period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedFont {
+    pub family: String,
+    pub range: UnicodeRange,
+}
+
 #[derive(Debug)]
 pub enum WireframeState {
     None,
@@ -23,6 +37,22 @@ pub struct BrowserState {
     /// Draw a 1px red border around every table-cell element (set via GOSUB_DEBUG_TABLE_CELLS=1)
     pub debug_table_cells: bool,
     pub current_hovered_element: Option<LayoutElementId>,
+    /// Selected text, highlighted with the `::selection` colours.
+    pub selection: TextSelection,
+    /// Find-in-page matches, overlaid on the text they cover.
+    pub find: FindMatches,
+    /// The latest rendering of each `<iframe>`'s nested document, by iframe element. A frame
+    /// without one yet paints as an empty box.
+    pub frames: HashMap<NodeId, MediaId>,
+    /// The playback state of each `<video>`/`<audio>` element, by element. One without an entry
+    /// shows its poster, paused at the start.
+    pub media: HashMap<NodeId, MediaPresentation>,
+    /// The frame each animated image shows, by the image's media id. An animated image without
+    /// an entry shows its first frame.
+    pub image_frames: HashMap<MediaId, MediaId>,
+    /// Web font faces still in their `font-display` block period. Text set in one of them is
+    /// left out rather than flashed in a fallback font; everything else paints as usual.
+    pub blocked_fonts: Vec<BlockedFont>,
     /// Current viewport offset + size
     pub viewport: Rect,
     pub tile_list: Option<RwLock<TileList>>,
@@ -38,8 +68,35 @@ impl Debug for BrowserState {
             .field("show_tilegrid", &self.show_tilegrid)
             .field("debug_table_cells", &self.debug_table_cells)
             .field("current_hovered_element", &self.current_hovered_element)
+            .field("selection", &self.selection)
+            .field("find", &self.find)
+            .field("frames", &self.frames)
+            .field("media", &self.media)
+            .field("image_frames", &self.image_frames)
+            .field("blocked_fonts", &self.blocked_fonts)
             .field("viewport", &self.viewport)
             .field("dpi_scale_factor", &self.dpi_scale_factor)
             .finish()
     }
 }
+
+impl BrowserState {
+    /// The media id to draw for image `media_id`: its current frame when it is animated.
+    pub fn image_frame(&self, media_id: MediaId) -> MediaId {
+        self.image_frames.get(&media_id).copied().unwrap_or(media_id)
+    }
+
+    /// Whether `text` set in `family` (a CSS family list) waits for a blocked web font: the
+    /// first family is blocked and some of the text falls in that face's range.
+    pub fn font_blocked(&self, family: &str, text: &str) -> bool {
+        if self.blocked_fonts.is_empty() {
+            return false;
+        }
+        let first = family.split(',').next().unwrap_or_default();
+        let first = first.trim().trim_matches(|c| c == '"' || c == '\'');
+        self.blocked_fonts
+            .iter()
+            .filter(|font| font.family.eq_ignore_ascii_case(first))
+            .any(|font| text.chars().any(|c| font.range.contains(c)))
+    }
+}
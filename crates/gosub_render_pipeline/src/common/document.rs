@@ -1,3 +1,4 @@
+pub mod counters;
 pub mod inline_style;
 pub mod node;
 pub mod pipeline_doc;
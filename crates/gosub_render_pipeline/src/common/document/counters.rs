@@ -0,0 +1,480 @@
+//! CSS counters (`counter-reset` / `counter-increment` / `counter-set`) and counter styles.
+//!
+//! The adapter walks the document once in tree order, feeding each element's counter properties
+//! into a [`CounterStack`] and keeping a [`CounterSnapshot`] for every box that can display a
+//! counter (list-item markers and `::before`/`::after` content). [`CounterStyles`] then turns
+//! the numbers into text: the predefined styles plus any `@counter-style` rules.
+
+use gosub_interface::css3::CounterStyleRule;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The counters in scope while walking the tree. Each instance remembers the depth of the
+/// sibling list it was created in: it stays visible to following siblings and their
+/// descendants, and goes out of scope once that sibling list is left.
+#[derive(Debug, Default)]
+pub struct CounterStack {
+    /// `(name, value, depth)`, outermost first.
+    entries: Vec<(String, i32, usize)>,
+}
+
+impl CounterStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `counter-reset`: a new instance, unless a sibling already created one at this depth.
+    pub fn reset(&mut self, name: &str, value: i32, depth: usize) {
+        if let Some(entry) = self.entries.iter_mut().rev().find(|e| e.0 == name) {
+            if entry.2 == depth {
+                entry.1 = value;
+                return;
+            }
+        }
+        self.entries.push((name.to_string(), value, depth));
+    }
+
+    /// `counter-increment`; a counter not in scope is instantiated at 0 first.
+    pub fn increment(&mut self, name: &str, by: i32, depth: usize) {
+        let value = self.innermost(name, depth);
+        *value = value.saturating_add(by);
+    }
+
+    /// `counter-set`; a counter not in scope is instantiated first.
+    pub fn set(&mut self, name: &str, value: i32, depth: usize) {
+        *self.innermost(name, depth) = value;
+    }
+
+    /// Drops the counters created in sibling lists at `depth` or deeper.
+    pub fn leave(&mut self, depth: usize) {
+        self.entries.retain(|e| e.2 < depth);
+    }
+
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot(
+            self.entries
+                .iter()
+                .map(|(name, value, _)| (name.clone(), *value))
+                .collect(),
+        )
+    }
+
+    fn innermost(&mut self, name: &str, depth: usize) -> &mut i32 {
+        let idx = match self.entries.iter().rposition(|e| e.0 == name) {
+            Some(idx) => idx,
+            None => {
+                self.entries.push((name.to_string(), 0, depth));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[idx].1
+    }
+}
+
+/// The counters in scope at one box, outermost first.
+#[derive(Debug, Clone, Default)]
+pub struct CounterSnapshot(Arc<[(String, i32)]>);
+
+impl CounterSnapshot {
+    /// `counter(name)`: the innermost instance, 0 when there is none.
+    pub fn value(&self, name: &str) -> i32 {
+        self.0.iter().rev().find(|e| e.0 == name).map_or(0, |e| e.1)
+    }
+
+    /// `counters(name, …)`: every instance in scope, outermost first.
+    pub fn values(&self, name: &str) -> Vec<i32> {
+        let values: Vec<i32> = self.0.iter().filter(|e| e.0 == name).map(|e| e.1).collect();
+        if values.is_empty() {
+            vec![0]
+        } else {
+            values
+        }
+    }
+}
+
+/// Parses a `counter-reset` / `counter-increment` / `counter-set` value (`a 2 b`, `none`) into
+/// `(name, value)` pairs; a name without an integer gets `default`.
+pub fn parse_counter_list(text: &str, default: i32) -> Vec<(String, i32)> {
+    let mut out: Vec<(String, i32)> = Vec::new();
+    for token in text.split_whitespace() {
+        if let Ok(n) = token.parse::<f32>() {
+            if let Some(last) = out.last_mut() {
+                last.1 = n as i32;
+            }
+            continue;
+        }
+        if matches!(token, "none" | "initial" | "inherit" | "unset") {
+            continue;
+        }
+        out.push((token.to_string(), default));
+    }
+    out
+}
+
+const ROMAN_WEIGHTS: [u32; 13] = [1000, 900, 500, 400, 100, 90, 50, 40, 10, 9, 5, 4, 1];
+const LOWER_ROMAN: [&str; 13] = ["m", "cm", "d", "cd", "c", "xc", "l", "xl", "x", "ix", "v", "iv", "i"];
+const UPPER_ROMAN: [&str; 13] = ["M", "CM", "D", "CD", "C", "XC", "L", "XL", "X", "IX", "V", "IV", "I"];
+
+const GREEK: &str = "αβγδεζηθικλμνξοπρστυφχψω";
+
+/// How a counter style maps an integer to symbols.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum System {
+    Cyclic,
+    /// Symbols stand for consecutive values from the given first value.
+    Fixed(i32),
+    Symbolic,
+    Alphabetic,
+    Numeric,
+    Additive,
+}
+
+/// A counter style with `extends` resolved and every descriptor filled in.
+#[derive(Debug, Clone)]
+struct ResolvedStyle {
+    system: System,
+    symbols: Vec<String>,
+    additive: Vec<(u32, String)>,
+    prefix: String,
+    suffix: String,
+    negative: String,
+    fallback: String,
+    /// Inclusive range the style can represent; values outside use the fallback.
+    range: (i32, i32),
+    /// Minimum symbol count, padded with the first symbol (`decimal-leading-zero`).
+    pad: usize,
+}
+
+impl ResolvedStyle {
+    fn new(system: System, symbols: Vec<String>, suffix: &str) -> Self {
+        let range = match system {
+            System::Symbolic | System::Alphabetic => (1, i32::MAX),
+            System::Additive => (0, i32::MAX),
+            _ => (i32::MIN, i32::MAX),
+        };
+        Self {
+            system,
+            symbols,
+            additive: Vec::new(),
+            prefix: String::new(),
+            suffix: suffix.to_string(),
+            negative: "-".to_string(),
+            fallback: "decimal".to_string(),
+            range,
+            pad: 0,
+        }
+    }
+
+    fn chars(system: System, chars: &str, suffix: &str) -> Self {
+        Self::new(system, chars.chars().map(String::from).collect(), suffix)
+    }
+
+    /// The predefined styles this engine knows.
+    fn predefined(name: &str) -> Option<Self> {
+        Some(match name {
+            "decimal" => Self::chars(System::Numeric, "0123456789", ". "),
+            "decimal-leading-zero" => {
+                let mut style = Self::chars(System::Numeric, "0123456789", ". ");
+                style.pad = 2;
+                style
+            }
+            "lower-roman" | "upper-roman" => {
+                let symbols = if name == "upper-roman" {
+                    UPPER_ROMAN
+                } else {
+                    LOWER_ROMAN
+                };
+                let mut style = Self::new(System::Additive, Vec::new(), ". ");
+                style.additive = ROMAN_WEIGHTS
+                    .iter()
+                    .zip(symbols)
+                    .map(|(w, s)| (*w, s.to_string()))
+                    .collect();
+                style.range = (1, 3999);
+                style
+            }
+            "lower-alpha" | "lower-latin" => Self::chars(System::Alphabetic, "abcdefghijklmnopqrstuvwxyz", ". "),
+            "upper-alpha" | "upper-latin" => Self::chars(System::Alphabetic, "ABCDEFGHIJKLMNOPQRSTUVWXYZ", ". "),
+            "lower-greek" => Self::chars(System::Alphabetic, GREEK, ". "),
+            "disc" => Self::chars(System::Cyclic, "•", " "),
+            "circle" => Self::chars(System::Cyclic, "◦", " "),
+            "square" => Self::chars(System::Cyclic, "▪", " "),
+            "disclosure-open" => Self::chars(System::Cyclic, "▾", " "),
+            "disclosure-closed" => Self::chars(System::Cyclic, "▸", " "),
+            _ => return None,
+        })
+    }
+
+    /// The representation without prefix/suffix, or `None` when the value is out of range.
+    fn represent(&self, value: i32) -> Option<String> {
+        if value < self.range.0 || value > self.range.1 {
+            return None;
+        }
+        let n = self.symbols.len();
+        match self.system {
+            System::Cyclic => {
+                let idx = (i64::from(value) - 1).rem_euclid(n.max(1) as i64) as usize;
+                self.symbols.get(idx).cloned()
+            }
+            System::Fixed(first) => {
+                let idx = usize::try_from(i64::from(value) - i64::from(first)).ok()?;
+                self.symbols.get(idx).cloned()
+            }
+            System::Symbolic => {
+                if n == 0 || value < 1 {
+                    return None;
+                }
+                let v = value as usize;
+                Some(self.symbols[(v - 1) % n].repeat((v - 1) / n + 1))
+            }
+            System::Alphabetic => {
+                if n < 2 || value < 1 {
+                    return None;
+                }
+                let mut v = value as usize;
+                let mut digits = Vec::new();
+                while v > 0 {
+                    v -= 1;
+                    digits.push(self.symbols[v % n].as_str());
+                    v /= n;
+                }
+                Some(digits.into_iter().rev().collect())
+            }
+            System::Numeric => {
+                if n < 2 {
+                    return None;
+                }
+                let mut v = value.unsigned_abs() as usize;
+                let mut digits = Vec::new();
+                loop {
+                    digits.push(self.symbols[v % n].as_str());
+                    v /= n;
+                    if v == 0 {
+                        break;
+                    }
+                }
+                while digits.len() < self.pad {
+                    digits.push(self.symbols[0].as_str());
+                }
+                let digits: String = digits.into_iter().rev().collect();
+                Some(if value < 0 {
+                    format!("{}{digits}", self.negative)
+                } else {
+                    digits
+                })
+            }
+            System::Additive => {
+                let mut rest = u32::try_from(value).ok()?;
+                if rest == 0 {
+                    return self.additive.iter().find(|(w, _)| *w == 0).map(|(_, s)| s.clone());
+                }
+                let mut out = String::new();
+                for (weight, symbol) in &self.additive {
+                    if *weight == 0 {
+                        continue;
+                    }
+                    while rest >= *weight {
+                        out.push_str(symbol);
+                        rest -= weight;
+                    }
+                }
+                (rest == 0).then_some(out)
+            }
+        }
+    }
+}
+
+/// Counter-style lookup: the predefined styles overlaid with the document's `@counter-style`
+/// rules (later rules win).
+#[derive(Debug, Default)]
+pub struct CounterStyles {
+    rules: HashMap<String, CounterStyleRule>,
+}
+
+/// Guards `extends` / `fallback` chains against cycles.
+const MAX_STYLE_DEPTH: usize = 8;
+
+impl CounterStyles {
+    pub fn new(rules: impl IntoIterator<Item = CounterStyleRule>) -> Self {
+        Self {
+            rules: rules.into_iter().map(|r| (r.name.clone(), r)).collect(),
+        }
+    }
+
+    /// True when `name` is a predefined style or an `@counter-style` rule.
+    pub fn is_known(&self, name: &str) -> bool {
+        name == "none" || self.rules.contains_key(name) || ResolvedStyle::predefined(name).is_some()
+    }
+
+    /// `counter(x, style)` text: the bare representation, without prefix or suffix.
+    pub fn format(&self, value: i32, style: &str) -> String {
+        if style == "none" {
+            return String::new();
+        }
+        self.represent(value, style, 0)
+            .map(|(text, _)| text)
+            .unwrap_or_else(|| value.to_string())
+    }
+
+    /// `::marker` text for a list item: the representation wrapped in the style's prefix/suffix.
+    pub fn marker(&self, value: i32, style: &str) -> String {
+        if style == "none" {
+            return String::new();
+        }
+        match self.represent(value, style, 0) {
+            Some((text, resolved)) => format!("{}{text}{}", resolved.prefix, resolved.suffix),
+            None => format!("{value}. "),
+        }
+    }
+
+    fn represent(&self, value: i32, style: &str, depth: usize) -> Option<(String, ResolvedStyle)> {
+        let resolved = self.resolve(style, depth)?;
+        match resolved.represent(value) {
+            Some(text) => Some((text, resolved)),
+            None if depth < MAX_STYLE_DEPTH && resolved.fallback != style => {
+                // The fallback's own representation, but this style's prefix/suffix.
+                let (text, _) = self.represent(value, &resolved.fallback, depth + 1)?;
+                Some((text, resolved))
+            }
+            None => None,
+        }
+    }
+
+    fn resolve(&self, name: &str, depth: usize) -> Option<ResolvedStyle> {
+        let Some(rule) = self.rules.get(name) else {
+            return ResolvedStyle::predefined(name);
+        };
+        let system = rule.system.as_deref().unwrap_or("symbolic");
+        let mut words = system.split_whitespace();
+        let mut style = match words.next().unwrap_or("symbolic") {
+            "extends" => {
+                let base = words.next().unwrap_or("decimal");
+                if depth >= MAX_STYLE_DEPTH || base == name {
+                    return None;
+                }
+                self.resolve(base, depth + 1)
+                    .or_else(|| ResolvedStyle::predefined("decimal"))?
+            }
+            keyword => {
+                let system = match keyword {
+                    "cyclic" => System::Cyclic,
+                    "fixed" => System::Fixed(words.next().and_then(|n| n.parse().ok()).unwrap_or(1)),
+                    "alphabetic" => System::Alphabetic,
+                    "numeric" => System::Numeric,
+                    "additive" => System::Additive,
+                    _ => System::Symbolic,
+                };
+                let mut style = ResolvedStyle::new(system, rule.symbols.clone(), ". ");
+                let mut additive = rule.additive_symbols.clone();
+                additive.sort_by_key(|b| std::cmp::Reverse(b.0));
+                style.additive = additive;
+                style
+            }
+        };
+        if let Some(prefix) = &rule.prefix {
+            style.prefix.clone_from(prefix);
+        }
+        if let Some(suffix) = &rule.suffix {
+            style.suffix.clone_from(suffix);
+        }
+        if let Some(negative) = &rule.negative {
+            style.negative.clone_from(negative);
+        }
+        if let Some(fallback) = &rule.fallback {
+            style.fallback.clone_from(fallback);
+        }
+        Some(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sibling_resets_share_a_scope_and_nesting_stacks() {
+        let mut stack = CounterStack::new();
+        stack.reset("item", 0, 1);
+        stack.increment("item", 1, 1);
+        // A nested list instantiates a second counter of the same name.
+        stack.reset("item", 0, 2);
+        stack.increment("item", 1, 2);
+        stack.increment("item", 1, 2);
+        assert_eq!(stack.snapshot().values("item"), vec![1, 2]);
+        assert_eq!(stack.snapshot().value("item"), 2);
+        stack.leave(2);
+        assert_eq!(stack.snapshot().value("item"), 1);
+        // A sibling reset replaces the instance instead of nesting.
+        stack.reset("item", 5, 1);
+        assert_eq!(stack.snapshot().values("item"), vec![5]);
+        // Unknown counters are instantiated on use.
+        stack.increment("other", 3, 1);
+        assert_eq!(stack.snapshot().value("other"), 3);
+        assert_eq!(stack.snapshot().value("missing"), 0);
+    }
+
+    #[test]
+    fn counter_lists_parse_names_and_values() {
+        assert_eq!(
+            parse_counter_list("chapter section 2", 1),
+            vec![("chapter".to_string(), 1), ("section".to_string(), 2)]
+        );
+        assert!(parse_counter_list("none", 0).is_empty());
+    }
+
+    #[test]
+    fn predefined_styles() {
+        let styles = CounterStyles::default();
+        assert_eq!(styles.format(42, "decimal"), "42");
+        assert_eq!(styles.format(-3, "decimal"), "-3");
+        assert_eq!(styles.format(7, "decimal-leading-zero"), "07");
+        assert_eq!(styles.format(1994, "upper-roman"), "MCMXCIV");
+        assert_eq!(styles.format(4, "lower-roman"), "iv");
+        assert_eq!(styles.format(28, "lower-alpha"), "ab");
+        assert_eq!(styles.format(2, "lower-greek"), "β");
+        // Out of range falls back to decimal.
+        assert_eq!(styles.format(0, "lower-alpha"), "0");
+        assert_eq!(styles.marker(3, "decimal"), "3. ");
+        assert_eq!(styles.marker(3, "square"), "▪ ");
+        assert_eq!(styles.marker(3, "none"), "");
+    }
+
+    #[test]
+    fn counter_style_rules() {
+        let styles = CounterStyles::new([
+            CounterStyleRule {
+                name: "thumbs".into(),
+                system: Some("cyclic".into()),
+                symbols: vec!["👍".into(), "👎".into()],
+                suffix: Some(" ".into()),
+                ..Default::default()
+            },
+            CounterStyleRule {
+                name: "paren".into(),
+                system: Some("extends lower-roman".into()),
+                prefix: Some("(".into()),
+                suffix: Some(") ".into()),
+                ..Default::default()
+            },
+            CounterStyleRule {
+                name: "dice".into(),
+                system: Some("additive".into()),
+                additive_symbols: vec![(1, "⚀".into()), (6, "⚅".into())],
+                ..Default::default()
+            },
+            CounterStyleRule {
+                name: "abc".into(),
+                system: Some("fixed".into()),
+                symbols: vec!["a".into(), "b".into(), "c".into()],
+                ..Default::default()
+            },
+        ]);
+        assert_eq!(styles.marker(3, "thumbs"), "👍 ");
+        assert_eq!(styles.marker(4, "paren"), "(iv) ");
+        assert_eq!(styles.format(8, "dice"), "⚅⚀⚀");
+        assert_eq!(styles.format(2, "abc"), "b");
+        assert_eq!(styles.format(4, "abc"), "4");
+        assert!(styles.is_known("thumbs"));
+        assert!(!styles.is_known("→"));
+    }
+}
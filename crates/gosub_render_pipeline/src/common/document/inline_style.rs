@@ -1,5 +1,7 @@
+use crate::animation::{shorthand_component, ANIMATION_LONGHANDS, TRANSITION_LONGHANDS};
 use crate::common::document::style::{
-    intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap, Unit, Value,
+    font_variant_longhand, intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap,
+    Unit, Value,
 };
 use cow_utils::CowUtils;
 use gosub_shared::css_colors::named_color_hex;
@@ -294,7 +296,13 @@ fn apply_style_kv(style: &mut NodeStyle, key: &str, value: &str) {
         "grid-row" => style.set(StyleProperty::GridRow, Value::Keyword(intern(value))),
 
         "aspect-ratio" => style.set(StyleProperty::AspectRatio, parse_style_num(value)),
-        "gap" => style.set(StyleProperty::Gap, parse_style_value(value)),
+        "gap" => {
+            style.set(StyleProperty::Gap, parse_style_value(value));
+            // `gap: <row> <column>` - the last value is the column gap multi-column layout uses.
+            if let Some(column) = value.split_whitespace().last() {
+                style.set(StyleProperty::ColumnGap, parse_style_value(column));
+            }
+        }
         "align-items" => style.set(StyleProperty::AlignItems, parse_style_str(value)),
         "align-self" => style.set(StyleProperty::AlignSelf, parse_style_str(value)),
         "align-content" => style.set(StyleProperty::AlignContent, parse_style_str(value)),
@@ -324,6 +332,73 @@ fn apply_style_kv(style: &mut NodeStyle, key: &str, value: &str) {
         "white-space" => style.set(StyleProperty::WhiteSpace, parse_style_str(value)),
         "text-transform" => style.set(StyleProperty::TextTransform, parse_style_str(value)),
         "mix-blend-mode" => style.set(StyleProperty::MixBlendMode, parse_style_str(value)),
+        "isolation" => style.set(StyleProperty::Isolation, parse_style_str(value)),
+        // Kept as text and parsed at paint time, like the stylesheet path.
+        "box-shadow" => style.set(StyleProperty::BoxShadow, parse_style_str(value)),
+        "text-shadow" => style.set(StyleProperty::TextShadow, parse_style_str(value)),
+        "filter" => style.set(StyleProperty::Filter, parse_style_str(value)),
+        "opacity" => style.set(StyleProperty::Opacity, parse_style_num(value)),
+        // Transforms, transitions and animations stay as text; `animation` parses them per tick.
+        "transform" => style.set(StyleProperty::Transform, parse_style_str(value)),
+        "transition" => apply_animation_shorthand(style, value, &TRANSITION_LONGHANDS),
+        "transition-property" => style.set(StyleProperty::TransitionProperty, parse_style_str(value)),
+        "transition-duration" => style.set(StyleProperty::TransitionDuration, parse_style_str(value)),
+        "transition-timing-function" => style.set(StyleProperty::TransitionTimingFunction, parse_style_str(value)),
+        "transition-delay" => style.set(StyleProperty::TransitionDelay, parse_style_str(value)),
+        "animation" => apply_animation_shorthand(style, value, &ANIMATION_LONGHANDS),
+        "animation-name" => style.set(StyleProperty::AnimationName, parse_style_str(value)),
+        "animation-duration" => style.set(StyleProperty::AnimationDuration, parse_style_str(value)),
+        "animation-timing-function" => style.set(StyleProperty::AnimationTimingFunction, parse_style_str(value)),
+        "animation-delay" => style.set(StyleProperty::AnimationDelay, parse_style_str(value)),
+        "animation-iteration-count" => style.set(StyleProperty::AnimationIterationCount, parse_style_str(value)),
+        "animation-direction" => style.set(StyleProperty::AnimationDirection, parse_style_str(value)),
+        "animation-fill-mode" => style.set(StyleProperty::AnimationFillMode, parse_style_str(value)),
+        "animation-play-state" => style.set(StyleProperty::AnimationPlayState, parse_style_str(value)),
+        "list-style" => apply_list_style_shorthand(style, value),
+        "list-style-type" => style.set(StyleProperty::ListStyleType, parse_list_style_type(value)),
+        "list-style-position" => style.set(StyleProperty::ListStylePosition, parse_style_str(value)),
+        "list-style-image" => match parse_css_url(value) {
+            Some(url) => style.set(StyleProperty::ListStyleImage, Value::Keyword(intern(&url))),
+            None => style.set(StyleProperty::ListStyleImage, parse_style_str("none")),
+        },
+        // Counter lists stay as text; the counter walk parses them.
+        "counter-reset" => style.set(StyleProperty::CounterReset, parse_style_str(value)),
+        "counter-increment" => style.set(StyleProperty::CounterIncrement, parse_style_str(value)),
+        "counter-set" => style.set(StyleProperty::CounterSet, parse_style_str(value)),
+        "columns" => apply_columns_shorthand(style, value),
+        "column-count" => style.set(StyleProperty::ColumnCount, parse_style_num(value)),
+        "column-width" => style.set(StyleProperty::ColumnWidth, parse_style_value(value)),
+        "column-gap" => style.set(StyleProperty::ColumnGap, parse_style_value(value)),
+        "column-rule" => apply_column_rule_shorthand(style, value),
+        "column-rule-width" => style.set(StyleProperty::ColumnRuleWidth, parse_rule_width(value)),
+        "column-rule-style" => style.set(StyleProperty::ColumnRuleStyle, parse_border_style(value)),
+        "column-rule-color" => style.set(StyleProperty::ColumnRuleColor, parse_named_color(value)),
+        "column-span" => style.set(StyleProperty::ColumnSpan, parse_style_str(value)),
+        "column-fill" => style.set(StyleProperty::ColumnFill, parse_style_str(value)),
+        // OpenType settings stay as text; the font system parses them when shaping.
+        "font-feature-settings" => style.set(StyleProperty::FontFeatureSettings, parse_style_str(value)),
+        "font-variation-settings" => style.set(StyleProperty::FontVariationSettings, parse_style_str(value)),
+        "font-variant" => apply_font_variant_shorthand(style, value),
+        "font-variant-ligatures" => style.set(StyleProperty::FontVariantLigatures, parse_style_str(value)),
+        "font-variant-caps" => style.set(StyleProperty::FontVariantCaps, parse_style_str(value)),
+        "font-variant-numeric" => style.set(StyleProperty::FontVariantNumeric, parse_style_str(value)),
+        "font-variant-position" => style.set(StyleProperty::FontVariantPosition, parse_style_str(value)),
+        "font-variant-east-asian" => style.set(StyleProperty::FontVariantEastAsian, parse_style_str(value)),
+        "font-variant-alternates" => style.set(StyleProperty::FontVariantAlternates, parse_style_str(value)),
+        "font-kerning" => style.set(StyleProperty::FontKerning, parse_style_str(value)),
+        "font-optical-sizing" => style.set(StyleProperty::FontOpticalSizing, parse_style_str(value)),
+        "direction" => style.set(StyleProperty::Direction, parse_style_str(value)),
+        "unicode-bidi" => style.set(StyleProperty::UnicodeBidi, parse_style_str(value)),
+        "writing-mode" => style.set(StyleProperty::WritingMode, parse_style_str(value)),
+        "text-orientation" => style.set(StyleProperty::TextOrientation, parse_style_str(value)),
+        "word-break" => style.set(StyleProperty::WordBreak, parse_style_str(value)),
+        "overflow-wrap" | "word-wrap" => style.set(StyleProperty::OverflowWrap, parse_style_str(value)),
+        "hyphens" => style.set(StyleProperty::Hyphens, parse_style_str(value)),
+        "text-overflow" => style.set(StyleProperty::TextOverflow, parse_style_str(value)),
+        "line-clamp" | "-webkit-line-clamp" => style.set(StyleProperty::LineClamp, parse_style_num(value)),
+        "break-before" | "page-break-before" => style.set(StyleProperty::BreakBefore, parse_style_str(value)),
+        "break-after" | "page-break-after" => style.set(StyleProperty::BreakAfter, parse_style_str(value)),
+        "break-inside" | "page-break-inside" => style.set(StyleProperty::BreakInside, parse_style_str(value)),
         "text-decoration" | "text-decoration-line" => {
             let has_underline = value.contains("underline");
             let has_line_through = value.contains("line-through");
@@ -343,6 +418,105 @@ fn apply_style_kv(style: &mut NodeStyle, key: &str, value: &str) {
     }
 }
 
+/// `transition` / `animation`: every longhand gets its component of each comma-separated item,
+/// or its initial value where an item leaves it out.
+fn apply_animation_shorthand(style: &mut NodeStyle, value: &str, longhands: &[StyleProperty]) {
+    for longhand in longhands {
+        if let Some(component) = shorthand_component(value, longhand) {
+            style.set(longhand.clone(), parse_style_str(&component));
+        }
+    }
+}
+
+/// `list-style: <position> || <image> || <type>`. A lone `none` clears both type and image.
+fn apply_list_style_shorthand(style: &mut NodeStyle, value: &str) {
+    let mut rest = value.to_string();
+    if let Some(url) = parse_css_url(value) {
+        style.set(StyleProperty::ListStyleImage, Value::Keyword(intern(&url)));
+        if let (Some(start), Some(end)) = (rest.find("url("), rest.find(')')) {
+            rest.replace_range(start..=end, " ");
+        }
+    }
+    for token in rest.split_whitespace() {
+        match token {
+            "inside" | "outside" => style.set(StyleProperty::ListStylePosition, parse_style_str(token)),
+            _ => style.set(StyleProperty::ListStyleType, parse_list_style_type(token)),
+        }
+    }
+}
+
+/// `font-variant`: each keyword goes to the longhand it belongs to; longhands without one reset
+/// to `normal`.
+fn apply_font_variant_shorthand(style: &mut NodeStyle, value: &str) {
+    for longhand in [
+        StyleProperty::FontVariantLigatures,
+        StyleProperty::FontVariantCaps,
+        StyleProperty::FontVariantNumeric,
+        StyleProperty::FontVariantPosition,
+        StyleProperty::FontVariantEastAsian,
+        StyleProperty::FontVariantAlternates,
+    ] {
+        let own: Vec<&str> = value
+            .split_whitespace()
+            .filter(|kw| font_variant_longhand(kw).as_ref() == Some(&longhand))
+            .collect();
+        let own = if own.is_empty() {
+            "normal".to_string()
+        } else {
+            own.join(" ")
+        };
+        style.set(longhand, parse_style_str(&own));
+    }
+}
+
+/// `columns: <column-width> || <column-count>`. A unitless number is the count; `auto` leaves
+/// its longhand at the initial value.
+fn apply_columns_shorthand(style: &mut NodeStyle, value: &str) {
+    for token in value.split_whitespace() {
+        if token == "auto" {
+            continue;
+        }
+        match token.parse::<f32>() {
+            Ok(count) => style.set(StyleProperty::ColumnCount, Value::Number(count)),
+            Err(_) => style.set(StyleProperty::ColumnWidth, parse_style_value(token)),
+        }
+    }
+}
+
+/// `column-rule: <width> || <style> || <color>`, parsed like the `border` shorthand.
+fn apply_column_rule_shorthand(style: &mut NodeStyle, value: &str) {
+    let mut width = Value::Unit(3.0, Unit::Px);
+    let mut rule_style = Value::BorderStyle(BorderStyle::None);
+    let mut color = Value::Color(0, 0, 0, 255);
+
+    for part in value.split_whitespace() {
+        match parse_rule_width(part) {
+            v @ Value::Unit(_, _) => width = v,
+            _ if is_border_style_keyword(part) => rule_style = parse_border_style(part),
+            _ => color = parse_named_color(part),
+        }
+    }
+
+    style.set(StyleProperty::ColumnRuleWidth, width);
+    style.set(StyleProperty::ColumnRuleStyle, rule_style);
+    style.set(StyleProperty::ColumnRuleColor, color);
+}
+
+/// A `<line-width>`: a length or `thin` / `medium` / `thick`.
+fn parse_rule_width(value: &str) -> Value {
+    match value {
+        "thin" => Value::Unit(1.0, Unit::Px),
+        "medium" => Value::Unit(3.0, Unit::Px),
+        "thick" => Value::Unit(5.0, Unit::Px),
+        _ => parse_style_value(value),
+    }
+}
+
+/// A counter-style name, or a quoted literal marker string (stored dequoted).
+fn parse_list_style_type(value: &str) -> Value {
+    parse_style_str(value.trim().trim_matches(['"', '\'']))
+}
+
 fn parse_text_wrap(value: &str) -> Value {
     match value {
         "wrap" => Value::TextWrap(TextWrap::Wrap),
@@ -369,8 +543,8 @@ fn parse_style_str(val: &str) -> Value {
 
 fn parse_text_align(val: &str) -> Value {
     match val {
-        "left" => Value::TextAlign(TextAlign::Start),
-        "right" => Value::TextAlign(TextAlign::End),
+        "left" => Value::TextAlign(TextAlign::Left),
+        "right" => Value::TextAlign(TextAlign::Right),
         "start" => Value::TextAlign(TextAlign::Start),
         "end" => Value::TextAlign(TextAlign::End),
         "center" => Value::TextAlign(TextAlign::Center),
@@ -404,6 +578,7 @@ fn parse_display(value: &str) -> Value {
         "table-header-group" => Value::Display(Display::TableHeaderGroup),
         "table-row" => Value::Display(Display::TableRow),
         "table-row-group" => Value::Display(Display::TableRowGroup),
+        "list-item" => Value::Display(Display::ListItem),
         _ => Value::Keyword(intern(value)),
     }
 }
@@ -549,6 +724,101 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn list_style_shorthand_sets_longhands() {
+        let style = parse_inline_style_attr("list-style: square inside url(dot.png); display: list-item");
+        assert_eq!(
+            style.get_own(&StyleProperty::ListStyleType),
+            Some(&Value::Keyword(intern("square")))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ListStylePosition),
+            Some(&Value::Keyword(intern("inside")))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ListStyleImage),
+            Some(&Value::Keyword(intern("dot.png")))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::Display),
+            Some(&Value::Display(Display::ListItem))
+        );
+    }
+
+    #[test]
+    fn column_shorthands_set_longhands() {
+        let style = parse_inline_style_attr("columns: 12em 3; column-rule: thin dashed #ff0000; gap: 4px 20px");
+        assert_eq!(style.get_own(&StyleProperty::ColumnCount), Some(&Value::Number(3.0)));
+        assert_eq!(
+            style.get_own(&StyleProperty::ColumnWidth),
+            Some(&Value::Unit(12.0, Unit::Em))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ColumnRuleWidth),
+            Some(&Value::Unit(1.0, Unit::Px))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ColumnRuleStyle),
+            Some(&Value::BorderStyle(BorderStyle::Dashed))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ColumnRuleColor),
+            Some(&Value::Color(255, 0, 0, 255))
+        );
+        assert_eq!(
+            style.get_own(&StyleProperty::ColumnGap),
+            Some(&Value::Unit(20.0, Unit::Px))
+        );
+    }
+
+    #[test]
+    fn font_variant_shorthand_splits_into_longhands() {
+        let style = parse_inline_style_attr("font-variant: small-caps tabular-nums no-contextual");
+        let kw = |prop| style.get_own(&prop).cloned();
+        assert_eq!(
+            kw(StyleProperty::FontVariantCaps),
+            Some(Value::Keyword(intern("small-caps")))
+        );
+        assert_eq!(
+            kw(StyleProperty::FontVariantNumeric),
+            Some(Value::Keyword(intern("tabular-nums")))
+        );
+        assert_eq!(
+            kw(StyleProperty::FontVariantLigatures),
+            Some(Value::Keyword(intern("no-contextual")))
+        );
+        assert_eq!(
+            kw(StyleProperty::FontVariantPosition),
+            Some(Value::Keyword(intern("normal")))
+        );
+    }
+
+    #[test]
+    fn animation_shorthands_set_longhands() {
+        let style = parse_inline_style_attr(
+            "opacity: 0.5; transform: translateX(4px); transition: opacity 0.3s, color 1s ease-in; animation: spin 2s infinite",
+        );
+        let kw = |prop| style.get_own(&prop).cloned();
+        assert_eq!(kw(StyleProperty::Opacity), Some(Value::Number(0.5)));
+        assert_eq!(
+            kw(StyleProperty::Transform),
+            Some(Value::Keyword(intern("translateX(4px)")))
+        );
+        assert_eq!(
+            kw(StyleProperty::TransitionProperty),
+            Some(Value::Keyword(intern("opacity, color")))
+        );
+        assert_eq!(
+            kw(StyleProperty::TransitionTimingFunction),
+            Some(Value::Keyword(intern("ease, ease-in")))
+        );
+        assert_eq!(kw(StyleProperty::AnimationName), Some(Value::Keyword(intern("spin"))));
+        assert_eq!(
+            kw(StyleProperty::AnimationIterationCount),
+            Some(Value::Keyword(intern("infinite")))
+        );
+    }
+
     #[test]
     fn background_image_longhand() {
         let style = parse_inline_style_attr("background-image: url(pic.png)");
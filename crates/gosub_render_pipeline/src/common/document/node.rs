@@ -107,7 +107,7 @@ impl Node {
             NodeType::Element(data) => {
                 matches!(
                     data.get_style(&StyleProperty::Display),
-                    Some(Value::Display(Display::Block))
+                    Some(Value::Display(Display::Block | Display::ListItem))
                 )
             }
             _ => false,
@@ -1,18 +1,31 @@
+use crate::animation::{shorthand_component, AnimatedStyles};
+use crate::common::document::counters::{parse_counter_list, CounterSnapshot, CounterStack, CounterStyles};
 use crate::common::document::node::{AttrMap, ElementData, Node, NodeType};
 use crate::common::document::style::{
-    intern, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign, TextWrap, Unit, Value,
+    font_variant_longhand, intern, lookup, BorderStyle, Display, FontWeight, NodeStyle, StyleProperty, TextAlign,
+    TextWrap, Unit, Value,
 };
+use crate::invalidation::Damage;
 use crate::painter::commands::color::Color;
-use crate::painter::commands::gradient::{ColorStop, Gradient, LinearGradient, Tiling};
+use crate::painter::commands::filter::{parse_filters, Filter};
+use crate::painter::commands::gradient::{
+    ColorInterpolation, ConicGradient, Gradient, GradientStop, HueInterpolation, LengthPercent, LinearGradient,
+    RadialExtent, RadialGradient, RadialShape, RadialSize,
+};
+use crate::painter::commands::shadow::{parse_box_shadows, parse_text_shadows, Shadow};
 use cow_utils::CowUtils;
 use gosub_interface::config::HasDocument;
-use gosub_interface::css3::{CssProperty, CssPropertyMap, CssSystem, CssValue};
+use gosub_interface::css3::{
+    CssProperty, CssPropertyMap, CssStylesheet as _, CssSystem, CssValue, KeyframesRule, PageRule,
+};
 use gosub_interface::document::Document as _;
+use gosub_interface::font_system::WritingMode;
 use gosub_interface::node::NodeType as GosubNodeType;
 use gosub_shared::node::NodeId;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
 use std::sync::Arc;
+use unicode_bidi::{get_base_direction, Direction as BidiDirection};
 
 // ── Bridge: CssProperty → Value ──────────────────────────────────────────────
 
@@ -25,7 +38,8 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
         | StyleProperty::BorderTopColor
         | StyleProperty::BorderRightColor
         | StyleProperty::BorderBottomColor
-        | StyleProperty::BorderLeftColor => {
+        | StyleProperty::BorderLeftColor
+        | StyleProperty::ColumnRuleColor => {
             if let Some(s) = p.as_string() {
                 if let Some((r, g, b, a)) = css_system_color(s) {
                     return Some(Value::Color(r, g, b, a));
@@ -55,6 +69,7 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
                 "table-header-group" => Display::TableHeaderGroup,
                 "table-row" => Display::TableRow,
                 "table-row-group" => Display::TableRowGroup,
+                "list-item" => Display::ListItem,
                 _ => Display::Block,
             };
             Some(Value::Display(d))
@@ -115,7 +130,8 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
         StyleProperty::BorderTopStyle
         | StyleProperty::BorderRightStyle
         | StyleProperty::BorderBottomStyle
-        | StyleProperty::BorderLeftStyle => {
+        | StyleProperty::BorderLeftStyle
+        | StyleProperty::ColumnRuleStyle => {
             let s = p.as_string()?;
             Some(Value::BorderStyle(str_to_border_style(s)))
         }
@@ -168,8 +184,8 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
             None
         }
 
-        // ── z-index: an integer (stacking order) or the `auto` keyword ─────
-        StyleProperty::ZIndex => {
+        // ── z-index / column-count / line-clamp: an integer or a keyword ───
+        StyleProperty::ZIndex | StyleProperty::ColumnCount | StyleProperty::LineClamp => {
             if let Some(n) = p.as_number() {
                 Some(Value::Number(n))
             } else {
@@ -200,6 +216,60 @@ fn css_property_to_value<S: CssSystem>(p: &S::Property, prop: &StyleProperty) ->
             Some(Value::Keyword(intern(&s)))
         }
 
+        // ── Shadows and filters: `2px 2px 4px #0008, inset 0 0 1em red`, `blur(4px)`, … ─
+        // Lists of lengths, colours and functions that no single accessor returns. Re-serialize
+        // to CSS text and let `painter::commands::{shadow, filter}` parse it at paint time, where
+        // `currentcolor` and font-relative lengths can be resolved against the element.
+        StyleProperty::BoxShadow | StyleProperty::TextShadow | StyleProperty::Filter => {
+            let s = css_property_to_text::<S>(p)?;
+            Some(Value::Keyword(intern(&s)))
+        }
+
+        // ── Counter lists: `item 2 section`, read by the counter walk ──────
+        StyleProperty::CounterReset | StyleProperty::CounterIncrement | StyleProperty::CounterSet => {
+            let s = css_property_to_text::<S>(p)?;
+            Some(Value::Keyword(intern(&s)))
+        }
+
+        // ── Transitions, animations and transforms: `opacity 0.3s, color 1s`, `rotate(5deg)` ─
+        // Comma-separated lists and function chains; `animation` parses them per tick.
+        StyleProperty::TransitionProperty
+        | StyleProperty::TransitionDuration
+        | StyleProperty::TransitionTimingFunction
+        | StyleProperty::TransitionDelay
+        | StyleProperty::AnimationName
+        | StyleProperty::AnimationDuration
+        | StyleProperty::AnimationTimingFunction
+        | StyleProperty::AnimationDelay
+        | StyleProperty::AnimationIterationCount
+        | StyleProperty::AnimationDirection
+        | StyleProperty::AnimationFillMode
+        | StyleProperty::AnimationPlayState
+        | StyleProperty::Transform => {
+            let s = css_property_to_text::<S>(p)?;
+            Some(Value::Keyword(intern(&s)))
+        }
+
+        // ── OpenType settings: `"liga" 0, "ss01"`, `small-caps`, `"wght" 650` ─
+        // Keyword lists and quoted tags; the font system parses the text when shaping.
+        StyleProperty::FontFeatureSettings
+        | StyleProperty::FontVariationSettings
+        | StyleProperty::FontVariantLigatures
+        | StyleProperty::FontVariantCaps
+        | StyleProperty::FontVariantNumeric
+        | StyleProperty::FontVariantPosition
+        | StyleProperty::FontVariantEastAsian
+        | StyleProperty::FontVariantAlternates => {
+            let s = css_property_to_text::<S>(p)?;
+            Some(Value::Keyword(intern(&s)))
+        }
+
+        // ── list-style-image: the (unresolved) url, or `none` ──────────────
+        StyleProperty::ListStyleImage => match css_property_url::<S>(p) {
+            Some(url) => Some(Value::Keyword(intern(&url))),
+            None => Some(Value::Keyword(intern(p.as_string()?))),
+        },
+
         // ── Default: unit-based or keyword ────────────────────────────────
         _ => {
             if let Some((v, unit)) = p.as_unit() {
@@ -277,6 +347,79 @@ fn join_grid_args<S: CssSystem>(args: &[S::Value]) -> String {
     out.trim().to_string()
 }
 
+/// Serializes a shadow/filter value back to CSS text. Unlike the grid serializer this keeps
+/// colours, which arrive already collapsed to 0..=255 components and are written as `rgba(...)`.
+fn css_property_to_text<S: CssSystem>(p: &S::Property) -> Option<String> {
+    if let Some(s) = p.as_string() {
+        return Some(s.to_string());
+    }
+    if let Some((r, g, b, a)) = p.as_color() {
+        return Some(rgba_text(r, g, b, a));
+    }
+    if let Some((name, args)) = p.as_function() {
+        return Some(format!("{name}({})", join_css_text::<S>(args)));
+    }
+    if let Some(list) = p.as_list() {
+        return Some(join_css_text::<S>(list));
+    }
+    if let Some((val, unit)) = p.as_unit() {
+        return Some(format!("{val}{unit}"));
+    }
+    if let Some(pct) = p.as_percentage() {
+        return Some(format!("{pct}%"));
+    }
+    p.as_number().map(|n| format!("{n}"))
+}
+
+fn css_value_to_text<S: CssSystem>(v: &S::Value) -> String {
+    if let Some(s) = v.as_string() {
+        return s.to_string();
+    }
+    if let Some((r, g, b, a)) = v.as_color() {
+        return rgba_text(r, g, b, a);
+    }
+    if let Some((name, args)) = v.as_function() {
+        return format!("{name}({})", join_css_text::<S>(args));
+    }
+    if let Some(list) = v.as_list() {
+        return join_css_text::<S>(list);
+    }
+    if let Some((val, unit)) = v.as_unit() {
+        return format!("{val}{unit}");
+    }
+    if let Some(pct) = v.as_percentage() {
+        return format!("{pct}%");
+    }
+    if let Some(n) = v.as_number() {
+        return format!("{n}");
+    }
+    String::new()
+}
+
+/// Space-joins values, rendering commas as `, ` (shadow layers, function arguments).
+fn join_css_text<S: CssSystem>(values: &[S::Value]) -> String {
+    let mut out = String::new();
+    for v in values {
+        if v.is_comma() {
+            out.push_str(", ");
+            continue;
+        }
+        let text = css_value_to_text::<S>(v);
+        if text.is_empty() {
+            continue;
+        }
+        if !out.is_empty() && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        out.push_str(&text);
+    }
+    out.trim().to_string()
+}
+
+fn rgba_text(r: f32, g: f32, b: f32, a: f32) -> String {
+    format!("rgba({}, {}, {}, {})", r as u8, g as u8, b as u8, a / 255.0)
+}
+
 /// Recursively search a CSS value tree for the first `url(...)` token and return its
 /// (unresolved) target, stripping any quotes. Used for `background-image`.
 fn css_value_url<S: CssSystem>(v: &S::Value) -> Option<String> {
@@ -309,6 +452,130 @@ fn css_property_url<S: CssSystem>(p: &S::Property) -> Option<String> {
     None
 }
 
+/// One longhand of a `list-style` shorthand (`square inside`, `url(dot.png) none`, …).
+fn list_style_component<S: CssSystem>(p: &S::Property, prop: &StyleProperty) -> Option<Value> {
+    if matches!(prop, StyleProperty::ListStyleImage) {
+        return css_property_url::<S>(p).map(|url| Value::Keyword(intern(&url)));
+    }
+    let tokens: Vec<&str> = match p.as_list() {
+        Some(list) => list.iter().filter_map(|v| v.as_string()).collect(),
+        None => p.as_string().into_iter().collect(),
+    };
+    let is_position = |t: &&str| matches!(*t, "inside" | "outside");
+    let token = if matches!(prop, StyleProperty::ListStylePosition) {
+        tokens.into_iter().find(is_position)
+    } else {
+        tokens.into_iter().find(|t| !is_position(t))
+    }?;
+    Some(Value::Keyword(intern(token)))
+}
+
+/// One token of a multi-column shorthand (`columns`, `column-rule`, `gap`).
+enum ColTok {
+    /// A unitless number (a `column-count`).
+    Num(f32),
+    /// A `<length>` resolved to px.
+    Len(f32),
+    /// A `<percentage>` (0..100).
+    Pct(f32),
+    Color(u8, u8, u8, u8),
+    /// A keyword (`auto`, `solid`, `thin`, …), lowercased.
+    Kw(String),
+}
+
+fn value_col_tok<S: CssSystem>(v: &S::Value) -> Option<ColTok> {
+    if v.as_unit().is_some() {
+        return Some(ColTok::Len(v.unit_to_px()));
+    }
+    if let Some(pct) = v.as_percentage() {
+        return Some(ColTok::Pct(pct));
+    }
+    if let Some(n) = v.as_number() {
+        return Some(ColTok::Num(n));
+    }
+    if let Some((r, g, b, a)) = v.as_color() {
+        return Some(ColTok::Color(r as u8, g as u8, b as u8, a as u8));
+    }
+    v.as_string().map(keyword_col_tok)
+}
+
+fn prop_col_tok<S: CssSystem>(p: &S::Property) -> Option<ColTok> {
+    if p.as_unit().is_some() {
+        return Some(ColTok::Len(p.unit_to_px()));
+    }
+    if let Some(pct) = p.as_percentage() {
+        return Some(ColTok::Pct(pct));
+    }
+    if let Some(n) = p.as_number() {
+        return Some(ColTok::Num(n));
+    }
+    if let Some((r, g, b, a)) = p.as_color() {
+        return Some(ColTok::Color(r as u8, g as u8, b as u8, a as u8));
+    }
+    p.as_string().map(keyword_col_tok)
+}
+
+/// A word of a multi-column shorthand: a system or named colour (`column-rule: 2px dashed red`
+/// keeps `red` as a word), or else a keyword.
+fn keyword_col_tok(s: &str) -> ColTok {
+    if let Some((r, g, b, a)) = css_system_color(s) {
+        return ColTok::Color(r, g, b, a);
+    }
+    match Color::try_from_css(s) {
+        Some(color) => ColTok::Color(color.r8(), color.g8(), color.b8(), color.a8()),
+        None => ColTok::Kw(s.cow_to_ascii_lowercase().into_owned()),
+    }
+}
+
+/// Picks one longhand out of a `columns`, `column-rule` or `gap` shorthand. The components
+/// may appear in any order, so each longhand takes the first token of its type; `gap` lists
+/// the row gap first, so `column-gap` takes the last length.
+fn multicol_component<S: CssSystem>(p: &S::Property, prop: &StyleProperty) -> Option<Value> {
+    let tokens: Vec<ColTok> = match p.as_list() {
+        Some(list) => list.iter().filter_map(value_col_tok::<S>).collect(),
+        None => prop_col_tok::<S>(p).into_iter().collect(),
+    };
+    let is_rule_style = |kw: &str| kw == "none" || str_to_border_style(kw) != BorderStyle::None;
+    match prop {
+        StyleProperty::ColumnCount => tokens.iter().find_map(|t| match t {
+            ColTok::Num(n) => Some(Value::Number(*n)),
+            _ => None,
+        }),
+        StyleProperty::ColumnWidth => tokens.iter().find_map(|t| match t {
+            ColTok::Len(px) => Some(Value::Unit(*px, Unit::Px)),
+            _ => None,
+        }),
+        StyleProperty::ColumnGap => tokens.iter().rev().find_map(|t| match t {
+            ColTok::Len(px) => Some(Value::Unit(*px, Unit::Px)),
+            ColTok::Num(n) if *n == 0.0 => Some(Value::Unit(0.0, Unit::Px)),
+            ColTok::Pct(pct) => Some(Value::Unit(*pct, Unit::Percent)),
+            _ => None,
+        }),
+        StyleProperty::ColumnRuleWidth => tokens.iter().find_map(|t| match t {
+            ColTok::Len(px) => Some(Value::Unit(*px, Unit::Px)),
+            ColTok::Kw(kw) => {
+                let px = match kw.as_str() {
+                    "thin" => 1.0,
+                    "medium" => 3.0,
+                    "thick" => 5.0,
+                    _ => return None,
+                };
+                Some(Value::Unit(px, Unit::Px))
+            }
+            _ => None,
+        }),
+        StyleProperty::ColumnRuleStyle => tokens.iter().find_map(|t| match t {
+            ColTok::Kw(kw) if is_rule_style(kw) => Some(Value::BorderStyle(str_to_border_style(kw))),
+            _ => None,
+        }),
+        StyleProperty::ColumnRuleColor => tokens.iter().find_map(|t| match t {
+            ColTok::Color(r, g, b, a) => Some(Value::Color(*r, *g, *b, *a)),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
 /// First colour token of a `background` shorthand (`#fff url(...) no-repeat`), components 0..=255.
 fn css_property_bg_color<S: CssSystem>(p: &S::Property) -> Option<(u8, u8, u8, u8)> {
     // Single-value shorthand: a bare `<color>` (hex/function collapse to a concrete colour at
@@ -337,9 +604,15 @@ fn css_property_bg_color<S: CssSystem>(p: &S::Property) -> Option<(u8, u8, u8, u
 
 // ── Gradient parsing ──────────────────────────────────────────────────────────
 
-/// Parses `linear-gradient(...)` args: an optional leading direction (`to <side>[ <side>]` or an
-/// `<angle>`) then two or more stops. Positionless stops are spread evenly between neighbours.
-fn parse_linear_gradient<S: CssSystem>(args: &[S::Value]) -> Option<Gradient> {
+/// Parses a `[repeating-]linear/radial/conic-gradient(...)` function: an optional prelude group
+/// (direction / shape / centre, plus `in <color-space>`) then two or more colour stops.
+fn parse_gradient<S: CssSystem>(name: &str, args: &[S::Value]) -> Option<Gradient> {
+    let name = name.cow_to_ascii_lowercase();
+    let (repeating, kind) = match name.strip_prefix("repeating-") {
+        Some(kind) => (true, kind),
+        None => (false, name.as_ref()),
+    };
+
     let mut groups: Vec<Vec<&S::Value>> = Vec::new();
     let mut current: Vec<&S::Value> = Vec::new();
     for a in args {
@@ -351,81 +624,232 @@ fn parse_linear_gradient<S: CssSystem>(args: &[S::Value]) -> Option<Gradient> {
     }
     groups.push(current);
 
-    // An optional direction occupies the first group when it carries no colour.
-    let mut angle_deg = 180.0_f32; // CSS default direction is `to bottom`.
-    let mut first_stop = 0;
-    if let Some(first) = groups.first() {
-        if let Some(angle) = parse_gradient_direction::<S>(first) {
-            angle_deg = angle;
-            first_stop = 1;
-        }
+    // The prelude occupies the first group when it carries no colour.
+    let mut prelude: Vec<&S::Value> = Vec::new();
+    if groups.first().is_some_and(|g| group_color::<S>(g).is_none()) {
+        prelude = groups.remove(0);
     }
+    let interpolation = take_color_interpolation::<S>(&mut prelude);
 
-    let mut colors: Vec<Color> = Vec::new();
-    let mut offsets: Vec<Option<f32>> = Vec::new();
-    for group in groups.iter().skip(first_stop) {
-        // Named colours and `transparent` tokenise as plain identifiers, so `as_color()` misses
-        // them - fall back to string parsing, which `#e6e6e6 25%, transparent 25%` relies on.
-        let color = group
-            .iter()
-            .find_map(|v| v.as_color())
-            .map(|(r, g, b, a)| Color::from_rgba(r / 255.0, g / 255.0, b / 255.0, a / 255.0))
-            .or_else(|| group.iter().find_map(|v| v.as_string()).and_then(Color::try_from_css));
-        let Some(color) = color else {
+    let conic = kind == "conic-gradient";
+    let mut stops: Vec<GradientStop> = Vec::new();
+    for group in &groups {
+        // Colour hints (a bare position) are not modelled; the midpoint stays halfway.
+        let Some(color) = group_color::<S>(group) else {
             continue;
         };
-        colors.push(color);
-        offsets.push(group.iter().find_map(|v| v.as_percentage()).map(|p| p / 100.0));
+        let positions: Vec<LengthPercent> = group
+            .iter()
+            .filter_map(|v| {
+                if conic {
+                    value_angle_percent::<S>(v)
+                } else {
+                    value_length_percent::<S>(v)
+                }
+            })
+            .take(2)
+            .collect();
+        if positions.is_empty() {
+            stops.push(GradientStop {
+                color: color.clone(),
+                position: None,
+            });
+        }
+        // A two-position stop (`red 10% 20%`) is the same colour at both positions.
+        for p in positions {
+            stops.push(GradientStop {
+                color: color.clone(),
+                position: Some(p),
+            });
+        }
     }
-    let n = colors.len();
-    if n < 2 {
+    if stops.len() < 2 {
         return None;
     }
 
-    // Anchor the endpoints, then linearly interpolate any interior gaps.
-    if offsets[0].is_none() {
-        offsets[0] = Some(0.0);
+    let center = (LengthPercent::CENTER, LengthPercent::CENTER);
+    match kind {
+        "linear-gradient" => Some(Gradient::Linear(LinearGradient {
+            // CSS default direction is `to bottom`.
+            angle_deg: parse_gradient_direction::<S>(&prelude).unwrap_or(180.0),
+            stops,
+            repeating,
+            interpolation,
+            tiling: None,
+        })),
+        "radial-gradient" => {
+            let (before_at, at) = split_at_keyword::<S>(&prelude, "at");
+            let words = group_words::<S>(before_at);
+            let has = |k: &str| words.iter().any(|w| w == k);
+            let lengths: Vec<LengthPercent> = before_at.iter().filter_map(|v| value_length_percent::<S>(v)).collect();
+            let extent = [
+                ("closest-side", RadialExtent::ClosestSide),
+                ("farthest-side", RadialExtent::FarthestSide),
+                ("closest-corner", RadialExtent::ClosestCorner),
+                ("farthest-corner", RadialExtent::FarthestCorner),
+            ]
+            .into_iter()
+            .find(|(k, _)| has(k))
+            .map(|(_, e)| e);
+            // One length means a circle, two an ellipse, unless the shape says otherwise.
+            let (shape, size) = match (lengths.as_slice(), extent) {
+                ([r], _) => (RadialShape::Circle, RadialSize::Explicit(*r, *r)),
+                ([rx, ry, ..], _) => (RadialShape::Ellipse, RadialSize::Explicit(*rx, *ry)),
+                (_, extent) => {
+                    let shape = if has("circle") {
+                        RadialShape::Circle
+                    } else {
+                        RadialShape::Ellipse
+                    };
+                    (shape, RadialSize::Extent(extent.unwrap_or_default()))
+                }
+            };
+            Some(Gradient::Radial(RadialGradient {
+                shape,
+                size,
+                center: at.map(value_bg_position::<S>).unwrap_or(center),
+                stops,
+                repeating,
+                interpolation,
+                tiling: None,
+            }))
+        }
+        "conic-gradient" => {
+            let (before_at, at) = split_at_keyword::<S>(&prelude, "at");
+            let (_, from) = split_at_keyword::<S>(before_at, "from");
+            let from_deg = from
+                .and_then(|f| f.first())
+                .and_then(|v| value_angle_percent::<S>(v))
+                .map(|a| a.percent * 3.6)
+                .unwrap_or(0.0);
+            Some(Gradient::Conic(ConicGradient {
+                from_deg,
+                center: at.map(value_bg_position::<S>).unwrap_or(center),
+                stops,
+                repeating,
+                interpolation,
+                tiling: None,
+            }))
+        }
+        _ => None,
     }
-    if offsets[n - 1].is_none() {
-        offsets[n - 1] = Some(1.0);
+}
+
+/// Colour of a stop group. Named colours and `transparent` tokenise as plain identifiers, so
+/// `as_color()` misses them - fall back to string parsing, which `#e6e6e6 25%, transparent 25%`
+/// relies on.
+fn group_color<S: CssSystem>(group: &[&S::Value]) -> Option<Color> {
+    group
+        .iter()
+        .find_map(|v| v.as_color())
+        .map(|(r, g, b, a)| Color::from_rgba(r / 255.0, g / 255.0, b / 255.0, a / 255.0))
+        .or_else(|| group.iter().find_map(|v| v.as_string()).and_then(Color::try_from_css))
+}
+
+/// Lowercased identifiers of a group, in order.
+fn group_words<S: CssSystem>(group: &[&S::Value]) -> Vec<String> {
+    group
+        .iter()
+        .filter_map(|v| v.as_string())
+        .map(|s| s.cow_to_ascii_lowercase().into_owned())
+        .collect()
+}
+
+/// Splits a prelude at the identifier `keyword`: the tokens before it, and those after it
+/// (`None` when the keyword is absent).
+fn split_at_keyword<'a, 'v, S: CssSystem>(
+    group: &'a [&'v S::Value],
+    keyword: &str,
+) -> (&'a [&'v S::Value], Option<&'a [&'v S::Value]>) {
+    match group
+        .iter()
+        .position(|v| v.as_string().is_some_and(|s| s.eq_ignore_ascii_case(keyword)))
+    {
+        Some(i) => (&group[..i], Some(&group[i + 1..])),
+        None => (group, None),
     }
-    let mut i = 0;
-    while i < n {
-        if offsets[i].is_some() {
-            i += 1;
-            continue;
+}
+
+/// Removes an `in <color-space> [<hue-method> hue]` clause from the prelude and returns it.
+/// Spaces without their own interpolation fall back to the closest supported one.
+fn take_color_interpolation<S: CssSystem>(prelude: &mut Vec<&S::Value>) -> ColorInterpolation {
+    let words = group_words::<S>(prelude);
+    let Some(at) = words.iter().position(|w| w == "in") else {
+        return ColorInterpolation::Srgb;
+    };
+    let mut hue = HueInterpolation::Shorter;
+    let mut consumed = 2;
+    if words.get(at + 3).map(String::as_str) == Some("hue") {
+        hue = match words.get(at + 2).map(String::as_str) {
+            Some("longer") => HueInterpolation::Longer,
+            Some("increasing") => HueInterpolation::Increasing,
+            Some("decreasing") => HueInterpolation::Decreasing,
+            _ => HueInterpolation::Shorter,
+        };
+        consumed = 4;
+    }
+    let interpolation = match words.get(at + 1).map(String::as_str) {
+        Some("srgb-linear") => ColorInterpolation::SrgbLinear,
+        Some("oklab" | "lab") => ColorInterpolation::Oklab,
+        Some("oklch" | "lch" | "hsl" | "hwb") => ColorInterpolation::Oklch(hue),
+        _ => ColorInterpolation::Srgb,
+    };
+
+    // `words` only holds identifiers, so map the word index back to the token index.
+    let mut seen = 0;
+    let mut start = None;
+    for (i, v) in prelude.iter().enumerate() {
+        if v.as_string().is_some() {
+            if seen == at {
+                start = Some(i);
+                break;
+            }
+            seen += 1;
         }
-        let start = i - 1; // resolved (endpoints are anchored)
-        let mut end = i;
-        while end < n && offsets[end].is_none() {
-            end += 1;
-        }
-        let a = offsets[start].unwrap_or(0.0);
-        let b = offsets.get(end).and_then(|o| *o).unwrap_or(1.0);
-        let steps = (end - start) as f32;
-        for (k, slot) in offsets.iter_mut().enumerate().take(end).skip(start + 1) {
-            *slot = Some(a + (b - a) * ((k - start) as f32) / steps);
-        }
-        i = end;
-    }
-
-    // Clamp to [0,1] and keep positions non-decreasing (CSS gradient rule).
-    let mut running = 0.0_f32;
-    let stops = colors
-        .into_iter()
-        .zip(offsets)
-        .map(|(color, off)| {
-            let off = off.unwrap_or(0.0).clamp(0.0, 1.0).max(running);
-            running = off;
-            ColorStop { offset: off, color }
-        })
-        .collect();
+    }
+    if let Some(start) = start {
+        let end = (start + consumed).min(prelude.len());
+        prelude.drain(start..end);
+    }
+    interpolation
+}
+
+/// A stop or size `<length-percentage>`: a percentage, a px length or a bare `0`.
+fn value_length_percent<S: CssSystem>(v: &S::Value) -> Option<LengthPercent> {
+    match value_bg_tok::<S>(v)? {
+        BgTok::Len(px) => Some(LengthPercent::px(px)),
+        BgTok::Pct(pct) => Some(LengthPercent::percent(pct)),
+        BgTok::Kw(_) => None,
+    }
+}
+
+/// A conic stop or `from` angle as a percentage of a full turn.
+fn value_angle_percent<S: CssSystem>(v: &S::Value) -> Option<LengthPercent> {
+    if let Some(pct) = v.as_percentage() {
+        return Some(LengthPercent::percent(pct));
+    }
+    if v.as_number() == Some(0.0) {
+        return Some(LengthPercent::percent(0.0));
+    }
+    let (value, unit) = v.as_unit()?;
+    let deg = angle_to_degrees(value, unit)?;
+    Some(LengthPercent::percent(deg / 3.6))
+}
+
+fn angle_to_degrees(value: f32, unit: &str) -> Option<f32> {
+    match unit {
+        "deg" => Some(value),
+        "grad" => Some(value * 0.9),
+        "rad" => Some(value.to_degrees()),
+        "turn" => Some(value * 360.0),
+        _ => None,
+    }
+}
 
-    Some(Gradient::Linear(LinearGradient {
-        angle_deg,
-        stops,
-        tiling: None,
-    }))
+/// A `<position>` written as gradient prelude tokens (`at left 10px top`).
+fn value_bg_position<S: CssSystem>(group: &[&S::Value]) -> (LengthPercent, LengthPercent) {
+    let toks: Vec<BgTok> = group.iter().filter_map(|v| value_bg_tok::<S>(v)).collect();
+    resolve_bg_position(&toks)
 }
 
 /// Gradient-line angle in CSS degrees, or `None` if the group is a colour stop rather than a
@@ -433,20 +857,10 @@ fn parse_linear_gradient<S: CssSystem>(args: &[S::Value]) -> Option<Gradient> {
 fn parse_gradient_direction<S: CssSystem>(group: &[&S::Value]) -> Option<f32> {
     // Angle form: `45deg`, `0.25turn`, `1.5rad`, `100grad`.
     if let Some((v, unit)) = group.first().and_then(|first| first.as_unit()) {
-        return match unit {
-            "deg" => Some(v),
-            "grad" => Some(v * 0.9),
-            "rad" => Some(v.to_degrees()),
-            "turn" => Some(v * 360.0),
-            _ => None,
-        };
+        return angle_to_degrees(v, unit);
     }
     // Keyword form: `to <side> [<side>]`.
-    let words: Vec<String> = group
-        .iter()
-        .filter_map(|v| v.as_string())
-        .map(|s| s.cow_to_lowercase().into_owned())
-        .collect();
+    let words = group_words::<S>(group);
     if words.first().map(String::as_str) != Some("to") {
         return None;
     }
@@ -464,38 +878,51 @@ fn parse_gradient_direction<S: CssSystem>(group: &[&S::Value]) -> Option<f32> {
     })
 }
 
-/// All `linear-gradient(...)` layers of a `background-image` property, in source order (the
-/// first listed layer paints on top). Non-gradient layers (`url()`, `none`) are skipped.
-fn property_gradient_layers<S: CssSystem>(p: &S::Property) -> Vec<LinearGradient> {
-    let mut out = Vec::new();
-    let mut push_fn = |name: &str, args: &[S::Value]| {
-        if name.eq_ignore_ascii_case("linear-gradient") {
-            if let Some(Gradient::Linear(g)) = parse_linear_gradient::<S>(args) {
-                out.push(g);
-            }
-        }
-    };
+/// The image of one `background-image` layer value: a `url()` or a gradient function.
+fn value_bg_image<S: CssSystem>(v: &S::Value) -> Option<BgImage> {
+    if let Some((name, args)) = v.as_function() {
+        return bg_image_function::<S>(name, args);
+    }
+    v.as_list()?.iter().find_map(value_bg_image::<S>)
+}
+
+fn bg_image_function<S: CssSystem>(name: &str, args: &[S::Value]) -> Option<BgImage> {
+    if name.eq_ignore_ascii_case("url") {
+        let url = args.iter().find_map(|a| a.as_string())?;
+        return Some(BgImage::Url(url.trim_matches(['"', '\'']).to_string()));
+    }
+    parse_gradient::<S>(name, args).map(BgImage::Gradient)
+}
+
+/// The images of a `background-image` (or `background` shorthand) property, one slot per
+/// comma-separated layer in source order. `none` and unparsable layers leave an empty slot, so
+/// slot indices still line up with the other per-layer longhands.
+fn property_bg_images<S: CssSystem>(p: &S::Property) -> Vec<Option<BgImage>> {
     if let Some((name, args)) = p.as_function() {
-        push_fn(name, args);
-        return out;
+        return vec![bg_image_function::<S>(name, args)];
     }
-    if let Some(list) = p.as_list() {
-        for v in list {
-            if let Some((name, args)) = v.as_function() {
-                push_fn(name, args);
+    let Some(list) = p.as_list() else {
+        return Vec::new();
+    };
+    let mut layers: Vec<Option<BgImage>> = vec![None];
+    for v in list {
+        if v.is_comma() {
+            layers.push(None);
+        } else if let Some(slot) = layers.last_mut() {
+            if slot.is_none() {
+                *slot = value_bg_image::<S>(v);
             }
         }
     }
-    out
+    layers
 }
 
 /// One resolved token from a `background-size`/`-position`/`-repeat` value.
+#[derive(Clone)]
 enum BgTok {
     /// A `<length>` in px (bare `0` included).
     Len(f32),
-    /// A `<percentage>` (0..100). The value is retained for future box-relative resolution;
-    /// today a percentage size/position falls back to "fill box" / zero offset.
-    #[allow(dead_code)]
+    /// A `<percentage>` (0..100).
     Pct(f32),
     /// A keyword (`cover`, `center`, `no-repeat`, …), lowercased.
     Kw(String),
@@ -561,40 +988,85 @@ fn bg_token_groups<S: CssSystem>(p: &S::Property) -> Vec<Vec<BgTok>> {
     }
 }
 
-/// `background-size` group → tile size in px, or `None` for `auto`/`cover`/`contain`/`%`
-/// (which mean "fill the box", i.e. no tiling).
-fn resolve_bg_size(group: &[BgTok]) -> Option<(f32, f32)> {
-    let mut dims = Vec::new();
+/// `background-size` group → [`BgSize`]. A single value sizes the width; the height is `auto`.
+fn resolve_bg_size(group: &[BgTok]) -> BgSize {
+    let mut dims: Vec<Option<LengthPercent>> = Vec::new();
     for t in group {
         match t {
-            BgTok::Len(v) => dims.push(*v),
-            // Percentage- and keyword-sized backgrounds need the box size to resolve; treat
-            // them as "fill the box" for now (no tiling).
-            BgTok::Pct(_) | BgTok::Kw(_) => return None,
+            BgTok::Len(v) => dims.push(Some(LengthPercent::px(*v))),
+            BgTok::Pct(p) => dims.push(Some(LengthPercent::percent(*p))),
+            BgTok::Kw(k) if k == "cover" => return BgSize::Cover,
+            BgTok::Kw(k) if k == "contain" => return BgSize::Contain,
+            BgTok::Kw(k) if k == "auto" => dims.push(None),
+            BgTok::Kw(_) => {}
         }
     }
     match dims.as_slice() {
-        [w] => Some((*w, *w)),
-        [w, h, ..] => Some((*w, *h)),
-        _ => None,
+        [] | [None] | [None, None, ..] => BgSize::Auto,
+        [w] => BgSize::Explicit(*w, None),
+        [w, h, ..] => BgSize::Explicit(*w, *h),
     }
 }
 
-/// `background-position` group → (x, y) px phase offset. Percentages and edge keywords need the
-/// box size, so they resolve to 0 for now; px offsets are exact.
-fn resolve_bg_position(group: &[BgTok]) -> (f32, f32) {
-    let lens: Vec<f32> = group
-        .iter()
-        .filter_map(|t| match t {
-            BgTok::Len(v) => Some(*v),
-            _ => None,
-        })
-        .collect();
-    match lens.as_slice() {
-        [x] => (*x, 0.0),
-        [x, y, ..] => (*x, *y),
-        _ => (0.0, 0.0),
+/// `background-position` (or gradient `at <position>`) group → per-axis offset, where the
+/// percentage part resolves against the free space (box minus image). Handles the one- and
+/// two-value forms and the three/four-value `<edge> <offset>` form.
+fn resolve_bg_position(group: &[BgTok]) -> (LengthPercent, LengthPercent) {
+    let lp = |t: &BgTok| match t {
+        BgTok::Len(v) => Some(LengthPercent::px(*v)),
+        BgTok::Pct(p) => Some(LengthPercent::percent(*p)),
+        BgTok::Kw(_) => None,
+    };
+    let edge = |k: &str, offset: LengthPercent| match k {
+        "left" | "top" => offset,
+        "right" | "bottom" => LengthPercent {
+            px: -offset.px,
+            percent: 100.0 - offset.percent,
+        },
+        _ => LengthPercent::CENTER,
+    };
+    let is_y = |k: &str| k == "top" || k == "bottom";
+    let is_x = |k: &str| k == "left" || k == "right";
+
+    // `center` leaves its axis unset, which defaults to the centre below.
+    let (mut x, mut y) = (None, None);
+    if group.len() >= 3 {
+        // `<edge> [<offset>]` pairs.
+        let mut i = 0;
+        while i < group.len() {
+            if let BgTok::Kw(k) = &group[i] {
+                let offset = group.get(i + 1).and_then(lp);
+                if offset.is_some() {
+                    i += 1;
+                }
+                let v = edge(k, offset.unwrap_or_default());
+                if is_y(k) {
+                    y = Some(v);
+                } else if is_x(k) {
+                    x = Some(v);
+                }
+            }
+            i += 1;
+        }
+    } else {
+        for (i, t) in group.iter().enumerate() {
+            match t {
+                BgTok::Kw(k) if is_y(k) => y = Some(edge(k, LengthPercent::default())),
+                BgTok::Kw(k) if is_x(k) => x = Some(edge(k, LengthPercent::default())),
+                BgTok::Kw(_) => {}
+                // A leading value is horizontal, a trailing one vertical.
+                t => {
+                    let v = lp(t);
+                    if i == 0 && x.is_none() {
+                        x = v;
+                    } else {
+                        y = v;
+                    }
+                }
+            }
+        }
     }
+    (x.unwrap_or(LengthPercent::CENTER), y.unwrap_or(LengthPercent::CENTER))
 }
 
 /// `background-repeat` group → (repeat_x, repeat_y). Defaults to repeating both axes.
@@ -616,6 +1088,43 @@ fn resolve_bg_repeat(group: &[BgTok]) -> (bool, bool) {
     }
 }
 
+const POSITION_KEYWORDS: [&str; 5] = ["left", "right", "top", "bottom", "center"];
+const SIZE_KEYWORDS: [&str; 3] = ["auto", "cover", "contain"];
+const REPEAT_KEYWORDS: [&str; 6] = ["repeat", "repeat-x", "repeat-y", "no-repeat", "space", "round"];
+const BOX_KEYWORDS: [&str; 5] = ["border-box", "padding-box", "content-box", "text", "border-area"];
+
+/// Layer `i`'s group of a per-layer longhand (cycled to the layer count), or else the `keep`
+/// keywords of the matching `background` shorthand layer. `None` when neither says anything.
+fn layer_tokens(groups: &[Vec<BgTok>], shorthand: Option<&Vec<BgTok>>, keep: &[&str], i: usize) -> Option<Vec<BgTok>> {
+    if !groups.is_empty() {
+        return Some(groups[i % groups.len()].clone());
+    }
+    let keywords: Vec<BgTok> = shorthand?
+        .iter()
+        .filter(|t| matches!(t, BgTok::Kw(k) if keep.contains(&k.as_str())))
+        .cloned()
+        .collect();
+    (!keywords.is_empty()).then_some(keywords)
+}
+
+/// `<visual-box>` keywords of a group, in order.
+fn bg_boxes(group: &[BgTok]) -> Vec<BgBox> {
+    group
+        .iter()
+        .filter_map(|t| match t {
+            BgTok::Kw(k) => match k.as_str() {
+                "border-box" => Some(BgBox::Border),
+                "padding-box" => Some(BgBox::Padding),
+                "content-box" => Some(BgBox::Content),
+                // `background-clip: text` / `border-area` are not modelled; clip to the border box.
+                "text" | "border-area" => Some(BgBox::Border),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PipelineNodeKind {
     Text,
@@ -625,42 +1134,80 @@ pub enum PipelineNodeKind {
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BgSize {
-    /// `auto` / absent - the image's intrinsic size.
+    /// `auto` / absent - the image's intrinsic size (the whole origin box for a gradient).
     Auto,
-    /// Explicit lengths in px.
-    Length(f32, f32),
+    /// Explicit width / height; `None` on an axis is `auto`. Percentages are of the origin box.
+    Explicit(Option<LengthPercent>, Option<LengthPercent>),
     /// `cover` - scale (preserving aspect) so the image fully covers the box, cropping overflow.
     Cover,
     /// `contain` - scale (preserving aspect) so the image fits inside the box, letterboxing.
     Contain,
 }
 
-/// Resolved `background-repeat`/`-size`/`-position` for an element's first background layer. Read
-/// from the `background` shorthand as well as the longhands, since pages write `background: url(x)
-/// repeat`. `cover`/`contain` need the box size, so final tile geometry is computed at paint time.
-#[derive(Debug, Clone, Copy)]
+impl BgSize {
+    /// The size in px when both axes are plain lengths.
+    pub fn lengths(&self) -> Option<(f32, f32)> {
+        match self {
+            BgSize::Explicit(Some(w), Some(h)) if w.percent == 0.0 && h.percent == 0.0 => Some((w.px, h.px)),
+            _ => None,
+        }
+    }
+}
+
+/// `background-origin` / `background-clip` reference box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BgBox {
+    Border,
+    Padding,
+    Content,
+}
+
+/// Resolved `background-repeat`/`-size`/`-position`/`-origin`/`-clip` of one background layer.
+/// Read from the `background` shorthand as well as the longhands, since pages write
+/// `background: url(x) no-repeat`. Sizes and positions need the box, so the final tile geometry
+/// is computed at paint time.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BgImageLayout {
     /// Whether the tile repeats on the x / y axis (`background-repeat`; default repeat both).
     pub repeat: (bool, bool),
-    /// Tile origin offset from the box origin, in px (`background-position`, length form).
-    pub position: (f32, f32),
-    /// Per-axis `center` keyword (`background-position: center`) - resolved against the box at paint.
-    pub center: (bool, bool),
+    /// Tile offset within the origin box; the percentage part is of the free space (box minus
+    /// tile), so `50%` centres.
+    pub position: (LengthPercent, LengthPercent),
     /// Resolved `background-size`.
     pub size: BgSize,
+    /// Box that `position` and `size` are relative to (default `padding-box`).
+    pub origin: BgBox,
+    /// Box the layer is painted within (default `border-box`).
+    pub clip: BgBox,
 }
 
 impl Default for BgImageLayout {
     fn default() -> Self {
         BgImageLayout {
             repeat: (true, true),
-            position: (0.0, 0.0),
-            center: (false, false),
+            position: (LengthPercent::default(), LengthPercent::default()),
             size: BgSize::Auto,
+            origin: BgBox::Padding,
+            clip: BgBox::Border,
         }
     }
 }
 
+/// The image of a background layer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BgImage {
+    Gradient(Gradient),
+    /// Unresolved `url()` target.
+    Url(String),
+}
+
+/// One `background-image` layer with its own layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BgLayer {
+    pub image: BgImage,
+    pub layout: BgImageLayout,
+}
+
 // ── PipelineDocument trait ────────────────────────────────────────────────────
 
 pub trait PipelineDocument: Send + Sync {
@@ -673,25 +1220,58 @@ pub trait PipelineDocument: Send + Sync {
     fn html_node_id(&self) -> Option<NodeId>;
     fn body_node_id(&self) -> Option<NodeId>;
     fn base_url(&self) -> String;
-    fn inner_html(&self, id: NodeId) -> String;
+    /// The node and its subtree serialized as XML, which is how inline `<svg>` is handed to the SVG parser
+    fn outer_xml(&self, id: NodeId) -> String;
     fn get_node_by_id(&self, _id: NodeId) -> Option<Node> {
         None
     }
 
+    /// The language `id` is in: the `lang` attribute of it or its nearest ancestor that has one,
+    /// lowercased (e.g. `en-gb`). `None` when no ancestor declares it.
+    fn language(&self, _id: NodeId) -> Option<String> {
+        None
+    }
+
     /// Returns the own (explicitly-set) value for `prop` on node `id`, without recursing.
     fn get_own_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value>;
 
-    /// `background-image` gradient layers in source order (first listed paints on top), each
-    /// carrying its resolved tiling (`None` tiling = fill the box). Empty for solid/image
-    /// backgrounds.
-    fn background_layers(&self, _id: NodeId) -> Vec<Gradient> {
+    /// As [`Self::get_own_style`], but leaving out running animations and transitions: the value
+    /// they start from and settle on.
+    fn get_own_base_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
+        self.get_own_style(id, prop)
+    }
+
+    /// The `@keyframes` rule called `name`, the last one declared when several are.
+    fn keyframes(&self, _name: &str) -> Option<KeyframesRule> {
+        None
+    }
+
+    /// The document's `@page` rules in cascade order, read when the document is laid out on pages.
+    fn page_rules(&self) -> Vec<PageRule> {
         Vec::new()
     }
 
-    /// Tiling for a raster/SVG `background-image`, read from both the `background` shorthand and
-    /// the longhands. Defaults to "repeat both axes, intrinsic size, no offset".
-    fn background_image_layout(&self, _id: NodeId) -> BgImageLayout {
-        BgImageLayout::default()
+    /// True while `id` animates `opacity` or `transform`. Layering gives such an element a layer
+    /// of its own, which the compositor fades and moves without repainting it.
+    fn has_composited_animation(&self, _id: NodeId) -> bool {
+        false
+    }
+
+    /// `background-image` layers in source order (first listed paints on top), each with its
+    /// own `background-position/-size/-repeat/-origin/-clip`. Empty when there is no image.
+    /// The default exposes only the single `url()` that `get_style` reports.
+    fn background_layers(&self, id: NodeId) -> Vec<BgLayer> {
+        let url = match self.get_style(id, &StyleProperty::BackgroundImage) {
+            Value::Keyword(url) => lookup(url),
+            _ => return Vec::new(),
+        };
+        if url.is_empty() || url.eq_ignore_ascii_case("none") {
+            return Vec::new();
+        }
+        vec![BgLayer {
+            image: BgImage::Url(url),
+            layout: BgImageLayout::default(),
+        }]
     }
 
     /// Forces the next `get_own_style` to re-evaluate CSS selectors (including `:hover`) from
@@ -766,47 +1346,173 @@ pub trait PipelineDocument: Send + Sync {
             _ => 0.0,
         }
     }
+
+    /// The computed `color`, which `currentcolor` in shadows and filters resolves to.
+    fn current_color(&self, id: NodeId) -> Color {
+        match self.get_style(id, &StyleProperty::Color) {
+            Value::Color(r, g, b, a) => Color::from_rgba8(r, g, b, a),
+            _ => Color::BLACK,
+        }
+    }
+
+    /// The `color` and `background-color` selected text in `id` paints with, from the `::selection`
+    /// rule on it or its nearest ancestor that has one. `None` keeps the text's own colour or the
+    /// default highlight.
+    fn selection_colors(&self, _id: NodeId) -> (Option<Color>, Option<Color>) {
+        (None, None)
+    }
+
+    /// `box-shadow` layers in source order (the first listed paints on top). Empty for `none`
+    /// or an invalid list.
+    fn box_shadows(&self, id: NodeId) -> Vec<Shadow> {
+        match self.get_style(id, &StyleProperty::BoxShadow) {
+            Value::Keyword(kw) => parse_box_shadows(&lookup(kw), &self.current_color(id), self.font_size_px(id)),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `text-shadow` layers in source order. Inherited, so text nodes pick up their parent's.
+    fn text_shadows(&self, id: NodeId) -> Vec<Shadow> {
+        match self.get_style(id, &StyleProperty::TextShadow) {
+            Value::Keyword(kw) => parse_text_shadows(&lookup(kw), &self.current_color(id), self.font_size_px(id)),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The element's own `filter` chain, applied in order. Not inherited: descendants are
+    /// filtered as part of the element's layer instead.
+    fn filters(&self, id: NodeId) -> Vec<Filter> {
+        match self.get_own_style(id, &StyleProperty::Filter) {
+            Some(Value::Keyword(kw)) => parse_filters(&lookup(kw), &self.current_color(id), self.font_size_px(id)),
+            _ => Vec::new(),
+        }
+    }
 }
 
-// ── Pseudo-element (::before / ::after) synthetic nodes ───────────────────────
+// ── Pseudo-element (::before / ::after / ::marker) synthetic nodes ────────────
 //
 // Generated content has no DOM node, but the pipeline is keyed by `NodeId` - so mint synthetic
 // ids the adapter resolves on the fly, letting the rest of the pipeline treat them as normal nodes.
 //
-// Encoding: top bit flags a synthetic id, next two bits are the role, the rest hold the owner
+// Encoding: top bit flags a synthetic id, next three bits are the role, the rest hold the owner
 // element id. Real DOM ids are small, so the high bits are free.
 const PSEUDO_FLAG: u64 = 1 << 62;
+const ROLE_BITS: u64 = 3;
 const ROLE_BEFORE_ELEM: u64 = 0; // the ::before pseudo-element box
 const ROLE_AFTER_ELEM: u64 = 1; // the ::after pseudo-element box
 const ROLE_BEFORE_TEXT: u64 = 2; // generated text child of ::before
 const ROLE_AFTER_TEXT: u64 = 3; // generated text child of ::after
+const ROLE_MARKER_ELEM: u64 = 4; // the ::marker box of a list item
+const ROLE_MARKER_TEXT: u64 = 5; // generated text child of ::marker
 
 const fn is_pseudo_id(id_val: u64) -> bool {
     id_val & PSEUDO_FLAG != 0
 }
 
 fn encode_pseudo(owner: NodeId, role: u64) -> NodeId {
-    NodeId::from(PSEUDO_FLAG | (u64::from(owner) << 2) | role)
+    NodeId::from(PSEUDO_FLAG | (u64::from(owner) << ROLE_BITS) | role)
 }
 
 fn decode_pseudo(id: NodeId) -> (NodeId, u64) {
     let v = u64::from(id) & !PSEUDO_FLAG;
-    (NodeId::from(v >> 2), v & 0b11)
+    (NodeId::from(v >> ROLE_BITS), v & ((1 << ROLE_BITS) - 1))
+}
+
+/// The element a generated (`::before` / `::after` / `::marker`) box or text id belongs to, or
+/// `None` for an ordinary DOM id.
+pub fn pseudo_owner(id: NodeId) -> Option<NodeId> {
+    is_pseudo_id(u64::from(id)).then(|| decode_pseudo(id).0)
 }
 
-const fn role_is_after(role: u64) -> bool {
-    matches!(role, ROLE_AFTER_ELEM | ROLE_AFTER_TEXT)
+const fn role_kind(role: u64) -> PseudoKind {
+    match role {
+        ROLE_AFTER_ELEM | ROLE_AFTER_TEXT => PseudoKind::After,
+        ROLE_MARKER_ELEM | ROLE_MARKER_TEXT => PseudoKind::Marker,
+        _ => PseudoKind::Before,
+    }
 }
 
 const fn role_is_text(role: u64) -> bool {
-    matches!(role, ROLE_BEFORE_TEXT | ROLE_AFTER_TEXT)
+    matches!(role, ROLE_BEFORE_TEXT | ROLE_AFTER_TEXT | ROLE_MARKER_TEXT)
+}
+
+/// Which generated box of an element a pseudo id refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum PseudoKind {
+    Before,
+    After,
+    Marker,
+}
+
+impl PseudoKind {
+    const fn name(self) -> &'static str {
+        match self {
+            PseudoKind::Before => "before",
+            PseudoKind::After => "after",
+            PseudoKind::Marker => "marker",
+        }
+    }
+
+    const fn elem_role(self) -> u64 {
+        match self {
+            PseudoKind::Before => ROLE_BEFORE_ELEM,
+            PseudoKind::After => ROLE_AFTER_ELEM,
+            PseudoKind::Marker => ROLE_MARKER_ELEM,
+        }
+    }
+
+    const fn text_role(self) -> u64 {
+        match self {
+            PseudoKind::Before => ROLE_BEFORE_TEXT,
+            PseudoKind::After => ROLE_AFTER_TEXT,
+            PseudoKind::Marker => ROLE_MARKER_TEXT,
+        }
+    }
+}
+
+/// One piece of a resolved `content` value. Counters stay symbolic until the text is needed,
+/// since their values depend on everything before the box in tree order.
+#[derive(Debug, Clone, PartialEq)]
+enum ContentItem {
+    Text(String),
+    /// `counter(name, style)`.
+    Counter {
+        name: String,
+        style: String,
+    },
+    /// `counters(name, separator, style)`.
+    Counters {
+        name: String,
+        separator: String,
+        style: String,
+    },
+    /// The default `::marker` text: the owner's `list-item` counter in `list-style-type`,
+    /// with the style's suffix.
+    Marker {
+        style: String,
+    },
 }
 
-/// A materialized pseudo-element: its computed style map plus the generated text (if the
-/// resolved `content` produced any). `text == None` means an empty box (e.g. `content: ""`).
+impl ContentItem {
+    fn needs_counters(&self) -> bool {
+        !matches!(self, ContentItem::Text(_))
+    }
+}
+
+/// A materialized pseudo-element: its computed style map plus its unresolved `content`. An empty
+/// `content` means an empty box (e.g. `content: ""`). `image` is the `list-style-image` of an
+/// image marker, which replaces the text.
 struct PseudoBox<P> {
     styles: Arc<P>,
-    text: Option<String>,
+    content: Vec<ContentItem>,
+    image: Option<String>,
+}
+
+/// Counter values at every box that can show one, plus the styles to format them with. Built by
+/// one walk over the document the first time a counter is displayed.
+struct CounterState {
+    snapshots: HashMap<NodeId, CounterSnapshot>,
+    styles: CounterStyles,
 }
 
 fn unquote(s: &str) -> String {
@@ -830,53 +1536,57 @@ fn content_token_to_string(s: &str) -> Option<String> {
     }
 }
 
-/// Counter state (counter-reset/-increment scoping) is not tracked yet, so counters resolve to
-/// empty text - generated boxes still appear, just without the number.
-fn resolve_content_function<S: CssSystem>(name: &str, _args: &[S::Value]) -> String {
-    if matches!(name, "counter" | "counters") {
-        log::debug!("content: {name}() is not yet supported; rendering empty");
+/// `counter(name[, style])` / `counters(name, separator[, style])`; other functions generate
+/// nothing.
+fn resolve_content_function<S: CssSystem>(name: &str, args: &[S::Value]) -> Option<ContentItem> {
+    let mut args = args.iter().filter(|a| !a.is_comma()).filter_map(|a| a.as_string());
+    let counter = args.next()?.to_string();
+    match name {
+        "counter" => Some(ContentItem::Counter {
+            name: counter,
+            style: args.next().unwrap_or("decimal").to_string(),
+        }),
+        "counters" => Some(ContentItem::Counters {
+            name: counter,
+            separator: unquote(args.next()?),
+            style: args.next().unwrap_or("decimal").to_string(),
+        }),
+        _ => None,
     }
-    String::new()
 }
 
-fn content_value_to_string<S: CssSystem>(v: &S::Value) -> Option<String> {
+fn push_content_value<S: CssSystem>(v: &S::Value, out: &mut Vec<ContentItem>) {
     if let Some(s) = v.as_string() {
-        return content_token_to_string(s);
-    }
-    if let Some((name, args)) = v.as_function() {
-        return Some(resolve_content_function::<S>(name, args));
-    }
-    if let Some(list) = v.as_list() {
-        let mut out = String::new();
+        if let Some(text) = content_token_to_string(s) {
+            out.push(ContentItem::Text(text));
+        }
+    } else if let Some((name, args)) = v.as_function() {
+        out.extend(resolve_content_function::<S>(name, args));
+    } else if let Some(list) = v.as_list() {
         for item in list {
-            if let Some(part) = content_value_to_string::<S>(item) {
-                out.push_str(&part);
-            }
+            push_content_value::<S>(item, out);
         }
-        return Some(out);
     }
-    None
 }
 
-/// `None` => generate no box (`content: none | normal`); `Some("")` => an empty box.
-fn resolve_content<S: CssSystem>(p: &S::Property) -> Option<String> {
+/// `None` => generate no box (`content: none | normal`); `Some(vec![])` => an empty box.
+fn resolve_content<S: CssSystem>(p: &S::Property) -> Option<Vec<ContentItem>> {
     // A single string/keyword token.
     if let Some(s) = p.as_string() {
-        return content_token_to_string(s);
+        return content_token_to_string(s).map(|text| vec![ContentItem::Text(text)]);
     }
+    let mut out = Vec::new();
     // A list of tokens (strings, attr()/var() already resolved upstream, counters, quotes).
     if let Some(list) = p.as_list() {
-        let mut out = String::new();
         for v in list {
-            if let Some(part) = content_value_to_string::<S>(v) {
-                out.push_str(&part);
-            }
+            push_content_value::<S>(v, &mut out);
         }
         return Some(out);
     }
     // A bare function value.
     if let Some((name, args)) = p.as_function() {
-        return Some(resolve_content_function::<S>(name, args));
+        out.extend(resolve_content_function::<S>(name, args));
+        return Some(out);
     }
     None
 }
@@ -889,15 +1599,45 @@ where
     C: HasDocument,
     <C::CssSystem as CssSystem>::PropertyMap: Send + Sync,
 {
-    pub doc: Arc<C::Document>,
+    /// The document, behind a lock so the engine can mutate it in place through the adapter
+    /// (see [`Self::edit_document`]).
+    doc: RwLock<Arc<C::Document>>,
     /// Per-node computed-style cache (from CSS selector matching). Populated lazily.
     style_cache: Mutex<HashMap<NodeId, Arc<<C::CssSystem as CssSystem>::PropertyMap>>>,
     /// Per-node inline-style cache (from the `style` attribute, highest specificity).
     inline_style_cache: Mutex<HashMap<NodeId, NodeStyle>>,
-    /// Materialized `::before` / `::after` pseudo-boxes, keyed by `(owner, is_after)`.
+    /// Materialized `::before` / `::after` / `::marker` pseudo-boxes, keyed by `(owner, kind)`.
     /// `None` means "no generated box". Populated lazily.
     #[allow(clippy::type_complexity)]
-    pseudo_cache: Mutex<HashMap<(NodeId, bool), Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>>>>,
+    pseudo_cache:
+        Mutex<HashMap<(NodeId, PseudoKind), Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>>>>,
+    /// Computed `::selection` properties per element; `None` when no rule targets it.
+    #[allow(clippy::type_complexity)]
+    selection_cache: Mutex<HashMap<NodeId, Option<Arc<<C::CssSystem as CssSystem>::PropertyMap>>>>,
+    /// Counter values for generated content, computed on first use. Any style change can move
+    /// every later counter, so it is dropped whole on invalidation.
+    counter_state: Mutex<Option<Arc<CounterState>>>,
+    /// Values of running animations and transitions, answered ahead of the cascade.
+    animated_styles: Arc<AnimatedStyles>,
+    /// Set on an adapter made from a [`StyleSnapshot`]: the damage the styles it had to compute
+    /// itself, lacking an old value, may hide.
+    snapshot_misses: Option<Mutex<Damage>>,
+}
+
+/// The styles an adapter has computed, taken by [`GosubDocumentAdapter::snapshot`] before its
+/// document is mutated in place: the styles the last rendering was made with.
+pub struct StyleSnapshot<C>
+where
+    C: HasDocument,
+    <C::CssSystem as CssSystem>::PropertyMap: Send + Sync,
+{
+    style_cache: HashMap<NodeId, Arc<<C::CssSystem as CssSystem>::PropertyMap>>,
+    inline_style_cache: HashMap<NodeId, NodeStyle>,
+    #[allow(clippy::type_complexity)]
+    pseudo_cache: HashMap<(NodeId, PseudoKind), Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>>>,
+    selection_cache: HashMap<NodeId, Option<Arc<<C::CssSystem as CssSystem>::PropertyMap>>>,
+    counter_state: Option<Arc<CounterState>>,
+    animated_styles: Arc<AnimatedStyles>,
 }
 
 impl<C> GosubDocumentAdapter<C>
@@ -908,63 +1648,408 @@ where
 {
     pub fn new(doc: Arc<C::Document>) -> Self {
         Self {
-            doc,
+            doc: RwLock::new(doc),
             style_cache: Mutex::new(HashMap::new()),
             inline_style_cache: Mutex::new(HashMap::new()),
             pseudo_cache: Mutex::new(HashMap::new()),
+            selection_cache: Mutex::new(HashMap::new()),
+            counter_state: Mutex::new(None),
+            animated_styles: Arc::new(AnimatedStyles::new()),
+            snapshot_misses: None,
+        }
+    }
+
+    /// Answers the animated values of `styles`, which an animation timeline keeps up to date.
+    pub fn with_animated_styles(mut self, styles: Arc<AnimatedStyles>) -> Self {
+        self.animated_styles = styles;
+        self
+    }
+
+    /// An adapter over `doc`, a mutated copy of this adapter's document, that keeps the cached
+    /// styles of every node except `invalidated` (and counters, which any change can move).
+    pub fn with_document(&self, doc: Arc<C::Document>, invalidated: &[NodeId]) -> Self {
+        let adapter = Self {
+            doc: RwLock::new(doc),
+            style_cache: Mutex::new(self.style_cache.lock().clone()),
+            inline_style_cache: Mutex::new(self.inline_style_cache.lock().clone()),
+            pseudo_cache: Mutex::new(self.pseudo_cache.lock().clone()),
+            selection_cache: Mutex::new(self.selection_cache.lock().clone()),
+            counter_state: Mutex::new(None),
+            animated_styles: self.animated_styles.clone(),
+            snapshot_misses: None,
+        };
+        adapter.invalidate_style_for_nodes(invalidated);
+        adapter
+    }
+
+    /// The styles computed so far, to compare a restyle against once the document has been
+    /// mutated in place (see [`Self::from_snapshot`]).
+    pub fn snapshot(&self) -> StyleSnapshot<C> {
+        StyleSnapshot {
+            style_cache: self.style_cache.lock().clone(),
+            inline_style_cache: self.inline_style_cache.lock().clone(),
+            pseudo_cache: self.pseudo_cache.lock().clone(),
+            selection_cache: self.selection_cache.lock().clone(),
+            counter_state: self.counter_state.lock().clone(),
+            animated_styles: self.animated_styles.clone(),
+        }
+    }
+
+    /// An adapter over `doc`, the mutated document, that answers the styles in `snapshot`. Any
+    /// style the snapshot lacks is computed from `doc`, so it can't stand for the old one:
+    /// [`Self::snapshot_damage`] reports how much damage such styles may hide.
+    pub fn from_snapshot(snapshot: StyleSnapshot<C>, doc: Arc<C::Document>) -> Self {
+        Self {
+            doc: RwLock::new(doc),
+            style_cache: Mutex::new(snapshot.style_cache),
+            inline_style_cache: Mutex::new(snapshot.inline_style_cache),
+            pseudo_cache: Mutex::new(snapshot.pseudo_cache),
+            selection_cache: Mutex::new(snapshot.selection_cache),
+            counter_state: Mutex::new(snapshot.counter_state),
+            animated_styles: snapshot.animated_styles,
+            snapshot_misses: Some(Mutex::new(Damage::None)),
+        }
+    }
+
+    /// For an adapter made by [`Self::from_snapshot`], the damage the styles it computed rather
+    /// than took from the snapshot may hide: a missing `::selection` style only affects paint,
+    /// anything else may change the boxes. `Damage::None` for any other adapter.
+    pub fn snapshot_damage(&self) -> Damage {
+        self.snapshot_misses
+            .as_ref()
+            .map_or(Damage::None, |misses| *misses.lock())
+    }
+
+    fn snapshot_miss(&self, damage: Damage) {
+        if let Some(misses) = &self.snapshot_misses {
+            let mut misses = misses.lock();
+            *misses = (*misses).max(damage);
         }
     }
 
+    /// The document this adapter reads.
+    pub fn document(&self) -> Arc<C::Document> {
+        Arc::clone(&self.doc.read())
+    }
+
+    /// Runs `f` against the document in place, without copying it. The cached styles stay as
+    /// they are; take a [`Self::snapshot`] first to compare the restyle against them. `None`,
+    /// without running `f`, while anything else holds a reference to the document.
+    pub fn edit_document<R>(&self, f: impl FnOnce(&mut C::Document) -> R) -> Option<R> {
+        Arc::get_mut(&mut self.doc.write()).map(f)
+    }
+
+    /// The `::selection` properties of element `id`, if a rule targets it. Cached on first access.
+    fn selection_style(&self, id: NodeId) -> Option<Arc<<C::CssSystem as CssSystem>::PropertyMap>> {
+        if let Some(cached) = self.selection_cache.lock().get(&id) {
+            return cached.clone();
+        }
+
+        let doc = self.document();
+        let result = if doc.node_type(id) == GosubNodeType::ElementNode {
+            let sheets = doc.stylesheets();
+            C::CssSystem::pseudo_properties_from_node::<C>(&*doc, id, sheets, "selection").map(|mut map| {
+                for (_, prop) in map.iter_mut() {
+                    prop.compute_value();
+                }
+                Arc::new(map)
+            })
+        } else {
+            None
+        };
+        self.snapshot_miss(Damage::Paint);
+        self.selection_cache.lock().insert(id, result.clone());
+        result
+    }
+
     /// `None` if no rule generates one. Computed and cached on first access.
     fn pseudo_box(
         &self,
         owner: NodeId,
-        is_after: bool,
+        kind: PseudoKind,
     ) -> Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>> {
-        if let Some(cached) = self.pseudo_cache.lock().get(&(owner, is_after)) {
+        if let Some(cached) = self.pseudo_cache.lock().get(&(owner, kind)) {
             return cached.clone();
         }
 
-        let result = self.compute_pseudo_box(owner, is_after);
-        self.pseudo_cache.lock().insert((owner, is_after), result.clone());
+        let result = self.compute_pseudo_box(owner, kind);
+        self.snapshot_miss(Damage::Rebuild);
+        self.pseudo_cache.lock().insert((owner, kind), result.clone());
         result
     }
 
     fn compute_pseudo_box(
         &self,
         owner: NodeId,
-        is_after: bool,
+        kind: PseudoKind,
     ) -> Option<Arc<PseudoBox<<C::CssSystem as CssSystem>::PropertyMap>>> {
+        let doc = self.document();
         // Pseudo-elements only hang off real elements.
-        if self.doc.node_type(owner) != GosubNodeType::ElementNode {
+        if doc.node_type(owner) != GosubNodeType::ElementNode {
             return None;
         }
-        let name = if is_after { "after" } else { "before" };
-        let sheets = self.doc.stylesheets();
-        let mut prop_map = C::CssSystem::pseudo_properties_from_node::<C>(&*self.doc, owner, sheets, name)?;
+        // Only list items get a marker.
+        if kind == PseudoKind::Marker
+            && !matches!(
+                self.get_own_style(owner, &StyleProperty::Display),
+                Some(Value::Display(Display::ListItem))
+            )
+        {
+            return None;
+        }
+        let sheets = doc.stylesheets();
+        let mut prop_map = C::CssSystem::pseudo_properties_from_node::<C>(&*doc, owner, sheets, kind.name())?;
         for (_, prop) in prop_map.iter_mut() {
             prop.compute_value();
         }
 
-        // Resolve `content` into generated text. `none`/`normal` means no box at all.
-        let content_prop = <_ as CssPropertyMap<C::CssSystem>>::get(&prop_map, "content")?;
-        let text = resolve_content::<C::CssSystem>(content_prop)?;
-
-        // `content: ""` (and any all-empty result) generates a box but no text child.
-        let text = if text.is_empty() { None } else { Some(text) };
+        // Resolve `content`. `none`/`normal` means no box at all - except on a marker, where
+        // `normal` (or no declaration) means the list-style marker.
+        let content = match <_ as CssPropertyMap<C::CssSystem>>::get(&prop_map, "content") {
+            Some(p) => resolve_content::<C::CssSystem>(p),
+            None => None,
+        };
+        let (content, image) = match (kind, content) {
+            (PseudoKind::Marker, Some(content)) => (content, None),
+            (PseudoKind::Marker, None) => {
+                let keyword = |prop: StyleProperty| match self.get_style(owner, &prop) {
+                    Value::Keyword(kw) => lookup(kw),
+                    _ => String::new(),
+                };
+                let image = Some(keyword(StyleProperty::ListStyleImage)).filter(|url| url != "none");
+                let style = keyword(StyleProperty::ListStyleType);
+                if image.is_none() && (style.is_empty() || style == "none") {
+                    return None;
+                }
+                (vec![ContentItem::Marker { style }], image)
+            }
+            (_, content) => (content?, None),
+        };
 
         Some(Arc::new(PseudoBox {
             styles: Arc::new(prop_map),
-            text,
+            content,
+            image,
         }))
     }
 
+    /// The generated text of a pseudo-element, with counters filled in. `None` when the box has
+    /// no text child (empty `content`, or an image marker).
+    fn pseudo_text(&self, owner: NodeId, kind: PseudoKind) -> Option<String> {
+        let pb = self.pseudo_box(owner, kind)?;
+        if pb.image.is_some() {
+            return None;
+        }
+        // Plain text content never pays for the counter walk.
+        let state = pb
+            .content
+            .iter()
+            .any(ContentItem::needs_counters)
+            .then(|| self.counter_state());
+        // A marker shows its owner's counters; ::before/::after get their own snapshot, taken
+        // after their own counter properties applied.
+        let snapshot_id = match kind {
+            PseudoKind::Marker => owner,
+            _ => encode_pseudo(owner, kind.elem_role()),
+        };
+        let snapshot = state
+            .as_ref()
+            .and_then(|state| state.snapshots.get(&snapshot_id).cloned())
+            .unwrap_or_default();
+        let mut text = String::new();
+        for item in &pb.content {
+            let state = match (item, state.as_deref()) {
+                (ContentItem::Text(t), _) => {
+                    text.push_str(t);
+                    continue;
+                }
+                (_, Some(state)) => state,
+                (_, None) => continue,
+            };
+            match item {
+                ContentItem::Text(_) => {}
+                ContentItem::Counter { name, style } => {
+                    text.push_str(&state.styles.format(snapshot.value(name), style));
+                }
+                ContentItem::Counters { name, separator, style } => {
+                    let parts: Vec<String> = snapshot
+                        .values(name)
+                        .into_iter()
+                        .map(|v| state.styles.format(v, style))
+                        .collect();
+                    text.push_str(&parts.join(separator));
+                }
+                ContentItem::Marker { style } => {
+                    // A string `list-style-type` is the marker text itself.
+                    if state.styles.is_known(style) {
+                        text.push_str(&state.styles.marker(snapshot.value("list-item"), style));
+                    } else {
+                        text.push_str(style);
+                    }
+                }
+            }
+        }
+        // The gap after an outside marker comes from its padding, not trailing spaces.
+        let text = if kind == PseudoKind::Marker {
+            text.trim().to_string()
+        } else {
+            text
+        };
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn counter_state(&self) -> Arc<CounterState> {
+        if let Some(state) = self.counter_state.lock().as_ref() {
+            return Arc::clone(state);
+        }
+        self.snapshot_miss(Damage::Rebuild);
+        let doc = self.document();
+        let mut state = CounterState {
+            snapshots: HashMap::new(),
+            styles: CounterStyles::new(doc.stylesheets().iter().flat_map(|s| s.counter_styles())),
+        };
+        let mut stack = CounterStack::new();
+        self.walk_counters(doc.root(), &mut stack, &mut state.snapshots);
+        let state = Arc::new(state);
+        *self.counter_state.lock() = Some(Arc::clone(&state));
+        state
+    }
+
+    /// Applies counter properties in tree order, recording a snapshot for each list item and
+    /// each `::before`/`::after` box. Depth is that of a node's sibling list, which is what
+    /// counter scopes are tied to.
+    fn walk_counters(&self, root: NodeId, stack: &mut CounterStack, snapshots: &mut HashMap<NodeId, CounterSnapshot>) {
+        enum Frame {
+            Enter(NodeId, usize),
+            Exit(NodeId, usize),
+        }
+
+        let generated = |id: NodeId, kind: PseudoKind| {
+            self.pseudo_box(id, kind)
+                .is_some()
+                .then(|| encode_pseudo(id, kind.elem_role()))
+        };
+
+        let doc = self.document();
+        let mut frames = vec![Frame::Enter(root, 0)];
+        while let Some(frame) = frames.pop() {
+            match frame {
+                Frame::Enter(id, depth) => {
+                    match doc.node_type(id) {
+                        GosubNodeType::ElementNode => {}
+                        GosubNodeType::DocumentNode => {
+                            frames.extend(doc.children(id).iter().rev().map(|&c| Frame::Enter(c, depth + 1)));
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                    // Boxes that are not rendered do not touch counters.
+                    if self.is_display_none(id) {
+                        continue;
+                    }
+                    self.apply_counters(id, depth, stack);
+                    if matches!(
+                        self.get_own_style(id, &StyleProperty::Display),
+                        Some(Value::Display(Display::ListItem))
+                    ) {
+                        snapshots.insert(id, stack.snapshot());
+                    }
+                    if let Some(before) = generated(id, PseudoKind::Before) {
+                        self.apply_counters(before, depth + 1, stack);
+                        snapshots.insert(before, stack.snapshot());
+                    }
+                    frames.push(Frame::Exit(id, depth));
+                    frames.extend(doc.children(id).iter().rev().map(|&c| Frame::Enter(c, depth + 1)));
+                }
+                Frame::Exit(id, depth) => {
+                    if let Some(after) = generated(id, PseudoKind::After) {
+                        self.apply_counters(after, depth + 1, stack);
+                        snapshots.insert(after, stack.snapshot());
+                    }
+                    stack.leave(depth + 1);
+                }
+            }
+        }
+    }
+
+    /// `counter-reset`, then `counter-increment`, then `counter-set` for one box, including the
+    /// HTML list behaviour: lists reset `list-item` (honouring `<ol start reversed>`), list items
+    /// step it, and `<li value>` sets it.
+    fn apply_counters(&self, id: NodeId, depth: usize, stack: &mut CounterStack) {
+        let doc = self.document();
+        let list = |prop: StyleProperty, default: i32| match self.get_own_style(id, &prop) {
+            Some(Value::Keyword(kw)) => parse_counter_list(&lookup(kw), default),
+            _ => Vec::new(),
+        };
+        let names = |items: &[(String, i32)]| items.iter().any(|(name, _)| name == "list-item");
+        let mut resets = list(StyleProperty::CounterReset, 0);
+        let mut increments = list(StyleProperty::CounterIncrement, 1);
+        let mut sets = list(StyleProperty::CounterSet, 0);
+
+        if !is_pseudo_id(u64::from(id)) {
+            let tag = doc.tag_name(id).unwrap_or_default();
+            let attr = |name: &str| doc.attributes(id).and_then(|attrs| attrs.get(name).cloned());
+            if matches!(tag, "ol" | "ul" | "menu" | "dir") && !names(&resets) {
+                let reversed = tag == "ol" && attr("reversed").is_some();
+                let start = attr("start").and_then(|s| s.trim().parse::<i32>().ok());
+                let value = if reversed {
+                    let items = self.list_item_count(id);
+                    start.unwrap_or(items).saturating_add(1)
+                } else {
+                    start.unwrap_or(1).saturating_sub(1)
+                };
+                resets.push(("list-item".to_string(), value));
+            }
+            let is_list_item = matches!(
+                self.get_own_style(id, &StyleProperty::Display),
+                Some(Value::Display(Display::ListItem))
+            );
+            if is_list_item && !names(&increments) {
+                let reversed = doc.parent(id).is_some_and(|parent| {
+                    doc.tag_name(parent) == Some("ol")
+                        && doc.attributes(parent).is_some_and(|a| a.contains_key("reversed"))
+                });
+                increments.push(("list-item".to_string(), if reversed { -1 } else { 1 }));
+            }
+            if is_list_item && tag == "li" && !names(&sets) {
+                if let Some(value) = attr("value").and_then(|v| v.trim().parse::<i32>().ok()) {
+                    sets.push(("list-item".to_string(), value));
+                }
+            }
+        }
+
+        for (name, value) in resets {
+            stack.reset(&name, value, depth);
+        }
+        for (name, by) in increments {
+            stack.increment(&name, by, depth);
+        }
+        for (name, value) in sets {
+            stack.set(&name, value, depth);
+        }
+    }
+
+    /// Rendered list items directly inside a list, the default start of a reversed `<ol>`.
+    fn list_item_count(&self, list: NodeId) -> i32 {
+        let doc = self.document();
+        doc.children(list)
+            .iter()
+            .filter(|&&child| {
+                matches!(
+                    self.get_own_style(child, &StyleProperty::Display),
+                    Some(Value::Display(Display::ListItem))
+                )
+            })
+            .count() as i32
+    }
+
     fn cached_styles(&self, id: NodeId) -> Arc<<C::CssSystem as CssSystem>::PropertyMap> {
         {
             if let Some(arc) = self.style_cache.lock().get(&id) {
                 return arc.clone();
             }
         }
+        self.snapshot_miss(Damage::Rebuild);
         let (prop_map, inline_ns) = self.compute_styles(id);
         let arc = Arc::new(prop_map);
         self.style_cache.lock().insert(id, arc.clone());
@@ -973,18 +2058,19 @@ where
     }
 
     fn compute_styles(&self, id: NodeId) -> (<C::CssSystem as CssSystem>::PropertyMap, NodeStyle) {
+        let doc = self.document();
         // CSS selectors cannot target text nodes - only elements.
-        if self.doc.node_type(id) == GosubNodeType::TextNode {
+        if doc.node_type(id) == GosubNodeType::TextNode {
             return (Default::default(), NodeStyle::new());
         }
-        let sheets = self.doc.stylesheets();
-        let mut prop_map = C::CssSystem::properties_from_node::<C>(&*self.doc, id, sheets).unwrap_or_default();
+        let sheets = doc.stylesheets();
+        let mut prop_map = C::CssSystem::properties_from_node::<C>(&*doc, id, sheets).unwrap_or_default();
         for (_, prop) in prop_map.iter_mut() {
             prop.compute_value();
         }
 
         // Inline `style` attribute has highest specificity - store separately.
-        let inline_ns = if let Some(attrs) = self.doc.attributes(id) {
+        let inline_ns = if let Some(attrs) = doc.attributes(id) {
             if let Some(style_attr) = attrs.get("style") {
                 crate::common::document::inline_style::parse_inline_style_attr(style_attr)
             } else {
@@ -1004,10 +2090,36 @@ where
         if role_is_text(role) {
             return None;
         }
-        let pb = self.pseudo_box(owner, role_is_after(role))?;
+        let kind = role_kind(role);
+        let pb = self.pseudo_box(owner, kind)?;
+        if kind == PseudoKind::Marker {
+            if let Some(v) = self.marker_box_style(owner, prop) {
+                return Some(v);
+            }
+        }
         self.style_from_map(id, prop, pb.styles.as_ref())
     }
 
+    /// The box a marker is laid out in. `::marker` only accepts a few properties, so the box
+    /// itself is not author-styleable: an outside marker hangs in the list item's left margin
+    /// (positioned against the item), an inside one is the first inline box of its content.
+    fn marker_box_style(&self, owner: NodeId, prop: &StyleProperty) -> Option<Value> {
+        let outside = !matches!(
+            self.get_style(owner, &StyleProperty::ListStylePosition),
+            Value::Keyword(kw) if lookup(kw) == "inside"
+        );
+        Some(match prop {
+            StyleProperty::Display if outside => Value::Display(Display::Block),
+            StyleProperty::Display => Value::Display(Display::Inline),
+            StyleProperty::Position if outside => Value::keyword("absolute"),
+            StyleProperty::InsetInlineEnd if outside => Value::Unit(100.0, Unit::Percent),
+            StyleProperty::InsetBlockStart if outside => Value::Unit(0.0, Unit::Px),
+            StyleProperty::PaddingRight => Value::Unit(0.5, Unit::Em),
+            StyleProperty::WhiteSpace => Value::keyword("nowrap"),
+            _ => return None,
+        })
+    }
+
     /// Bridges a computed `PropertyMap` to a single `Value`, shared by real elements and
     /// pseudo-elements. Handles the `text-decoration` / `background[-image]` shorthands and
     /// `currentColor`. `id` is only used to resolve `currentColor` against the node's `color`.
@@ -1063,6 +2175,7 @@ where
                 | StyleProperty::BorderRightColor
                 | StyleProperty::BorderBottomColor
                 | StyleProperty::BorderLeftColor
+                | StyleProperty::ColumnRuleColor
         ) {
             if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, css_name) {
                 if p.as_string().is_some_and(|s| s.eq_ignore_ascii_case("currentcolor")) {
@@ -1072,13 +2185,13 @@ where
         }
 
         // Inset properties are modelled with logical variants, but pages usually write the
-        // physical `top`/`right`/`bottom`/`left`. Accept either key (the physical aliasing is valid
-        // for the default horizontal-tb, ltr writing mode this engine assumes).
+        // physical `top`/`right`/`bottom`/`left`. Accept either key, the physical one being the
+        // side the logical property lands on in the element's writing mode and direction.
         let inset_physical = match prop {
-            StyleProperty::InsetBlockStart => Some("top"),
-            StyleProperty::InsetBlockEnd => Some("bottom"),
-            StyleProperty::InsetInlineStart => Some("left"),
-            StyleProperty::InsetInlineEnd => Some("right"),
+            StyleProperty::InsetBlockStart
+            | StyleProperty::InsetBlockEnd
+            | StyleProperty::InsetInlineStart
+            | StyleProperty::InsetInlineEnd => prop.inset_side(self.writing_mode(id), self.is_rtl(id)),
             _ => None,
         };
         if let Some(physical) = inset_physical {
@@ -1092,9 +2205,21 @@ where
             return None;
         }
 
-        if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, css_name) {
-            if let Some(v) = css_property_to_value::<C::CssSystem>(p, prop) {
-                return Some(v);
+        // Legacy and prefixed names still in wide use: `word-wrap`, `-webkit-line-clamp` and the
+        // CSS 2 `page-break-*` properties (whose `always` the paged layout reads as `page`).
+        let legacy_name = match prop {
+            StyleProperty::OverflowWrap => Some("word-wrap"),
+            StyleProperty::LineClamp => Some("-webkit-line-clamp"),
+            StyleProperty::BreakBefore => Some("page-break-before"),
+            StyleProperty::BreakAfter => Some("page-break-after"),
+            StyleProperty::BreakInside => Some("page-break-inside"),
+            _ => None,
+        };
+        for key in std::iter::once(css_name).chain(legacy_name) {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, key) {
+                if let Some(v) = css_property_to_value::<C::CssSystem>(p, prop) {
+                    return Some(v);
+                }
             }
         }
 
@@ -1107,16 +2232,156 @@ where
                 }
             }
         }
+
+        // Likewise pick the `list-style` shorthand apart when a longhand is absent.
+        if matches!(
+            prop,
+            StyleProperty::ListStyleType | StyleProperty::ListStylePosition | StyleProperty::ListStyleImage
+        ) {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, "list-style") {
+                return list_style_component::<C::CssSystem>(p, prop);
+            }
+        }
+
+        // ...and the multi-column shorthands (`gap` also sets `column-gap`).
+        let shorthand = match prop {
+            StyleProperty::ColumnCount | StyleProperty::ColumnWidth => Some("columns"),
+            StyleProperty::ColumnRuleWidth | StyleProperty::ColumnRuleStyle | StyleProperty::ColumnRuleColor => {
+                Some("column-rule")
+            }
+            StyleProperty::ColumnGap => Some("gap"),
+            _ => None,
+        };
+        if let Some(shorthand) = shorthand {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, shorthand) {
+                return multicol_component::<C::CssSystem>(p, prop);
+            }
+        }
+
+        // ...and `font-variant`, whose keywords each belong to exactly one longhand.
+        if matches!(
+            prop,
+            StyleProperty::FontVariantLigatures
+                | StyleProperty::FontVariantCaps
+                | StyleProperty::FontVariantNumeric
+                | StyleProperty::FontVariantPosition
+                | StyleProperty::FontVariantEastAsian
+                | StyleProperty::FontVariantAlternates
+        ) {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, "font-variant") {
+                let text = css_property_to_text::<C::CssSystem>(p)?;
+                let own: Vec<&str> = text
+                    .split_whitespace()
+                    .filter(|kw| font_variant_longhand(kw).as_ref() == Some(prop))
+                    .collect();
+                let value = if own.is_empty() {
+                    "normal".to_string()
+                } else {
+                    own.join(" ")
+                };
+                return Some(Value::Keyword(intern(&value)));
+            }
+        }
+
+        // ...and `transition` / `animation`, whose components are told apart by their syntax.
+        let shorthand = match prop {
+            StyleProperty::TransitionProperty
+            | StyleProperty::TransitionDuration
+            | StyleProperty::TransitionTimingFunction
+            | StyleProperty::TransitionDelay => Some("transition"),
+            StyleProperty::AnimationName
+            | StyleProperty::AnimationDuration
+            | StyleProperty::AnimationTimingFunction
+            | StyleProperty::AnimationDelay
+            | StyleProperty::AnimationIterationCount
+            | StyleProperty::AnimationDirection
+            | StyleProperty::AnimationFillMode
+            | StyleProperty::AnimationPlayState => Some("animation"),
+            _ => None,
+        };
+        if let Some(shorthand) = shorthand {
+            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, shorthand) {
+                let text = css_property_to_text::<C::CssSystem>(p)?;
+                return shorthand_component(&text, prop).map(|v| Value::Keyword(intern(&v)));
+            }
+        }
         None
     }
 
     fn find_child_by_tag(&self, parent: NodeId, tag: &str) -> Option<NodeId> {
-        self.doc
-            .children(parent)
+        let doc = self.document();
+        doc.children(parent)
             .iter()
-            .find(|&&child| self.doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case(tag)))
+            .find(|&&child| doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case(tag)))
             .copied()
     }
+
+    fn is_rtl(&self, id: NodeId) -> bool {
+        matches!(self.get_style(id, &StyleProperty::Direction), Value::Keyword(kw) if lookup(kw) == "rtl")
+    }
+
+    fn writing_mode(&self, id: NodeId) -> WritingMode {
+        match self.get_style(id, &StyleProperty::WritingMode) {
+            Value::Keyword(kw) => WritingMode::from_keyword(&lookup(kw)).unwrap_or_default(),
+            _ => WritingMode::HorizontalTb,
+        }
+    }
+
+    /// The HTML presentational hints for bidi: `dir` sets `direction` (`auto` and a bare `<bdi>`
+    /// take it from the text) and isolates the element's content from the surrounding paragraph.
+    /// The UA stylesheet covers `<bdi>` and `<bdo>`'s own `unicode-bidi`.
+    fn bidi_presentation(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
+        let doc = self.document();
+        let tag = doc.tag_name(id)?;
+        let dir = doc
+            .attributes(id)
+            .and_then(|attrs| attrs.get("dir"))
+            .map(|dir| dir.trim());
+        match prop {
+            StyleProperty::Direction => {
+                let direction = match dir {
+                    Some(dir) if dir.eq_ignore_ascii_case("ltr") => "ltr",
+                    Some(dir) if dir.eq_ignore_ascii_case("rtl") => "rtl",
+                    Some(dir) if dir.eq_ignore_ascii_case("auto") => self.auto_direction(id),
+                    None if tag.eq_ignore_ascii_case("bdi") => self.auto_direction(id),
+                    _ => return None,
+                };
+                Some(Value::keyword(direction))
+            }
+            StyleProperty::UnicodeBidi if dir.is_some() => Some(Value::keyword("isolate")),
+            _ => None,
+        }
+    }
+
+    /// `dir=auto`: the direction of the first strong character in the element's text, skipping
+    /// descendants with a `dir` of their own and script, style and textarea content. Text with
+    /// no strong character is `ltr`.
+    fn auto_direction(&self, id: NodeId) -> &'static str {
+        let doc = self.document();
+        let mut stack: Vec<NodeId> = doc.children(id).iter().rev().copied().collect();
+        while let Some(node) = stack.pop() {
+            match doc.node_type(node) {
+                GosubNodeType::TextNode => match get_base_direction(doc.text_value(node).unwrap_or("")) {
+                    BidiDirection::Ltr => return "ltr",
+                    BidiDirection::Rtl => return "rtl",
+                    BidiDirection::Mixed => {}
+                },
+                GosubNodeType::ElementNode => {
+                    let own_dir = doc.attributes(node).is_some_and(|attrs| attrs.contains_key("dir"));
+                    let opaque = doc.tag_name(node).is_some_and(|tag| {
+                        ["script", "style", "textarea"]
+                            .iter()
+                            .any(|skip| skip.eq_ignore_ascii_case(tag))
+                    });
+                    if !own_dir && !opaque {
+                        stack.extend(doc.children(node).iter().rev().copied());
+                    }
+                }
+                _ => {}
+            }
+        }
+        "ltr"
+    }
 }
 
 impl<C> PipelineDocument for GosubDocumentAdapter<C>
@@ -1126,7 +2391,7 @@ where
     <C::CssSystem as CssSystem>::PropertyMap: Send + Sync,
 {
     fn root(&self) -> Option<NodeId> {
-        self.html_node_id().or_else(|| Some(self.doc.root()))
+        self.html_node_id().or_else(|| Some(self.document().root()))
     }
 
     fn children(&self, id: NodeId) -> Vec<NodeId> {
@@ -1136,26 +2401,22 @@ where
             if role_is_text(role) {
                 return Vec::new();
             }
-            return match self.pseudo_box(owner, role_is_after(role)) {
-                Some(pb) if pb.text.is_some() => {
-                    let text_role = if role_is_after(role) {
-                        ROLE_AFTER_TEXT
-                    } else {
-                        ROLE_BEFORE_TEXT
-                    };
-                    vec![encode_pseudo(owner, text_role)]
-                }
-                _ => Vec::new(),
+            let kind = role_kind(role);
+            return match self.pseudo_text(owner, kind) {
+                Some(_) => vec![encode_pseudo(owner, kind.text_role())],
+                None => Vec::new(),
             };
         }
 
         let mut out = Vec::new();
-        // `::before` is inserted as the first child, `::after` as the last.
-        if self.pseudo_box(id, false).is_some() {
-            out.push(encode_pseudo(id, ROLE_BEFORE_ELEM));
+        // `::marker` comes first, then `::before`; `::after` is the last child.
+        for kind in [PseudoKind::Marker, PseudoKind::Before] {
+            if self.pseudo_box(id, kind).is_some() {
+                out.push(encode_pseudo(id, kind.elem_role()));
+            }
         }
-        out.extend(self.doc.children(id).iter().copied());
-        if self.pseudo_box(id, true).is_some() {
+        out.extend(self.document().children(id).iter().copied());
+        if self.pseudo_box(id, PseudoKind::After).is_some() {
             out.push(encode_pseudo(id, ROLE_AFTER_ELEM));
         }
         out
@@ -1170,7 +2431,7 @@ where
                 PipelineNodeKind::Element
             };
         }
-        match self.doc.node_type(id) {
+        match self.document().node_type(id) {
             GosubNodeType::TextNode => PipelineNodeKind::Text,
             GosubNodeType::CommentNode | GosubNodeType::DocTypeNode => PipelineNodeKind::Comment,
             GosubNodeType::ElementNode => PipelineNodeKind::Element,
@@ -1183,7 +2444,7 @@ where
         if is_pseudo_id(u64::from(id)) {
             return None;
         }
-        self.doc.tag_name(id).map(|s| s.to_string())
+        self.document().tag_name(id).map(|s| s.to_string())
     }
 
     fn is_display_none(&self, id: NodeId) -> bool {
@@ -1198,22 +2459,55 @@ where
             let (owner, role) = decode_pseudo(id);
             // Text child's parent is its pseudo-element; the pseudo-element's parent is the owner.
             return Some(if role_is_text(role) {
-                encode_pseudo(
-                    owner,
-                    if role_is_after(role) {
-                        ROLE_AFTER_ELEM
-                    } else {
-                        ROLE_BEFORE_ELEM
-                    },
-                )
+                encode_pseudo(owner, role_kind(role).elem_role())
             } else {
                 owner
             });
         }
-        self.doc.parent(id)
+        self.document().parent(id)
+    }
+
+    fn selection_colors(&self, id: NodeId) -> (Option<Color>, Option<Color>) {
+        let mut node = Some(id);
+        while let Some(id) = node {
+            if !is_pseudo_id(u64::from(id)) {
+                if let Some(map) = self.selection_style(id) {
+                    let color = |prop| match self.style_from_map(id, &prop, &map) {
+                        Some(Value::Color(r, g, b, a)) => Some(Color::from_rgba8(r, g, b, a)),
+                        _ => None,
+                    };
+                    return (color(StyleProperty::Color), color(StyleProperty::BackgroundColor));
+                }
+            }
+            node = self.parent(id);
+        }
+        (None, None)
+    }
+
+    fn language(&self, id: NodeId) -> Option<String> {
+        let doc = self.document();
+        let mut node = Some(id);
+        while let Some(id) = node {
+            if !is_pseudo_id(u64::from(id)) {
+                let lang = doc.attributes(id).and_then(|attrs| attrs.get("lang"));
+                if let Some(lang) = lang.map(|lang| lang.trim()).filter(|lang| !lang.is_empty()) {
+                    return Some(lang.cow_to_ascii_lowercase().into_owned());
+                }
+            }
+            node = self.parent(id);
+        }
+        None
     }
 
     fn get_own_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
+        // Animations override every declared value (transitions included) while they run.
+        if let Some(v) = self.animated_styles.get(id, prop) {
+            return Some(v);
+        }
+        self.get_own_base_style(id, prop)
+    }
+
+    fn get_own_base_style(&self, id: NodeId, prop: &StyleProperty) -> Option<Value> {
         // Generated content (::before / ::after) draws its styles from a separate map.
         if is_pseudo_id(u64::from(id)) {
             return self.pseudo_own_style(id, prop);
@@ -1221,9 +2515,24 @@ where
 
         let arc = self.cached_styles(id);
 
+        // The inline-style parser files `top`/`right`/`bottom`/`left` under the logical sides they
+        // are in horizontal LTR text; in another writing mode or direction an inset is read from
+        // the physical side it lands on. Resolved before taking the cache lock, which the
+        // `writing-mode` and `direction` lookups need too.
+        let inline_prop = match prop {
+            StyleProperty::InsetBlockStart
+            | StyleProperty::InsetBlockEnd
+            | StyleProperty::InsetInlineStart
+            | StyleProperty::InsetInlineEnd => prop
+                .inset_side(self.writing_mode(id), self.is_rtl(id))
+                .and_then(|side| StyleProperty::inset_on_side(side, WritingMode::HorizontalTb, false))
+                .unwrap_or_else(|| prop.clone()),
+            _ => prop.clone(),
+        };
+
         // Inline styles (from `style` attribute) have highest specificity.
         if let Some(inline) = self.inline_style_cache.lock().get(&id) {
-            if let Some(v) = inline.get_own(prop) {
+            if let Some(v) = inline.get_own(&inline_prop) {
                 return Some(v.clone());
             }
         }
@@ -1232,22 +2541,48 @@ where
             return Some(v);
         }
 
+        if let Some(v) = self.bidi_presentation(id, prop) {
+            return Some(v);
+        }
+
         // HTML presentation attributes (bgcolor, width, …) as lowest-specificity fallback.
-        if let Some(attrs) = self.doc.attributes(id) {
+        if let Some(attrs) = self.document().attributes(id) {
             return crate::common::document::inline_style::html_presentation_attr(attrs, prop);
         }
 
         None
     }
 
-    fn background_layers(&self, id: NodeId) -> Vec<Gradient> {
+    fn keyframes(&self, name: &str) -> Option<KeyframesRule> {
+        self.document()
+            .stylesheets()
+            .iter()
+            .flat_map(|s| s.keyframes())
+            .filter(|rule| rule.name == name)
+            .last()
+    }
+
+    fn page_rules(&self) -> Vec<PageRule> {
+        self.document()
+            .stylesheets()
+            .iter()
+            .flat_map(|s| s.page_rules())
+            .collect()
+    }
+
+    fn has_composited_animation(&self, id: NodeId) -> bool {
+        self.animated_styles.is_composited(id)
+    }
+
+    fn background_layers(&self, id: NodeId) -> Vec<BgLayer> {
         // Read the layers from the pseudo-element's own map, never the owner's.
-        let arc = if is_pseudo_id(u64::from(id)) {
+        let pseudo = is_pseudo_id(u64::from(id));
+        let arc = if pseudo {
             let (owner, role) = decode_pseudo(id);
             if role_is_text(role) {
                 return Vec::new();
             }
-            match self.pseudo_box(owner, role_is_after(role)) {
+            match self.pseudo_box(owner, role_kind(role)) {
                 Some(pb) => pb.styles.clone(),
                 None => return Vec::new(),
             }
@@ -1255,182 +2590,143 @@ where
             self.cached_styles(id)
         };
         let map = arc.as_ref();
+        let get = |key: &str| <_ as CssPropertyMap<C::CssSystem>>::get(map, key);
 
-        let mut layers = Vec::new();
+        let mut images = Vec::new();
         for key in ["background-image", "background"] {
-            if let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, key) {
-                layers = property_gradient_layers::<C::CssSystem>(p);
-                if !layers.is_empty() {
+            if let Some(p) = get(key) {
+                images = property_bg_images::<C::CssSystem>(p);
+                if images.iter().any(Option::is_some) {
                     break;
                 }
             }
         }
-        if layers.is_empty() {
+        // An inline `style` url (kept outside the CSS map) overrides the stylesheet layers.
+        if !pseudo {
+            if let Some(Value::Keyword(url)) = self
+                .inline_style_cache
+                .lock()
+                .get(&id)
+                .and_then(|inline| inline.get_own(&StyleProperty::BackgroundImage).cloned())
+            {
+                images = vec![Some(BgImage::Url(lookup(url)))];
+            }
+        }
+        if images.iter().all(Option::is_none) {
             return Vec::new();
         }
 
-        // `background-size/-position/-repeat` are per-layer lists cycled to the layer count.
-        let read_groups = |key: &str| {
-            <_ as CssPropertyMap<C::CssSystem>>::get(map, key)
-                .map(bg_token_groups::<C::CssSystem>)
-                .unwrap_or_default()
-        };
+        // The per-layer longhands are lists cycled to the layer count. Where a longhand is
+        // absent, fall back to the keywords of the matching `background` shorthand layer (its
+        // bare lengths are ambiguous between position and size, so only keywords are taken).
+        let read_groups = |key: &str| get(key).map(bg_token_groups::<C::CssSystem>).unwrap_or_default();
+        let shorthand = read_groups("background");
         let size_groups = read_groups("background-size");
         let pos_groups = read_groups("background-position");
         let rep_groups = read_groups("background-repeat");
-        let pick =
-            |groups: &[Vec<BgTok>], i: usize| -> Option<usize> { (!groups.is_empty()).then(|| i % groups.len()) };
-
-        for (i, g) in layers.iter_mut().enumerate() {
-            let Some((tw, th)) = pick(&size_groups, i).and_then(|j| resolve_bg_size(&size_groups[j])) else {
-                continue; // no explicit size → fill the box (no tiling)
-            };
-            if tw <= 0.0 || th <= 0.0 {
-                continue;
-            }
-            let position = pick(&pos_groups, i)
-                .map(|j| resolve_bg_position(&pos_groups[j]))
-                .unwrap_or((0.0, 0.0));
-            let repeat = pick(&rep_groups, i)
-                .map(|j| resolve_bg_repeat(&rep_groups[j]))
-                .unwrap_or((true, true));
-            g.tiling = Some(Tiling {
-                tile_size: (tw, th),
-                position,
-                repeat,
-            });
-        }
-
-        layers.into_iter().map(Gradient::Linear).collect()
-    }
-
-    fn background_image_layout(&self, id: NodeId) -> BgImageLayout {
-        let arc = self.cached_styles(id);
-        let map = arc.as_ref();
-
-        // The shorthand carries repeat/size keywords inline (`background: url(x) no-repeat center
-        // / contain`) and then the longhands are usually empty, so scan both.
-        let mut keywords: Vec<String> = Vec::new();
-        let mut explicit_size: Option<(f32, f32)> = None;
-        let mut position: Option<(f32, f32)> = None;
-
-        let mut scan = |key: &str, read_size: bool, read_pos: bool| {
-            let Some(p) = <_ as CssPropertyMap<C::CssSystem>>::get(map, key) else {
-                return;
-            };
-            let groups = bg_token_groups::<C::CssSystem>(p);
-            let Some(group) = groups.first() else {
-                return;
-            };
-            if read_size && explicit_size.is_none() {
-                explicit_size = resolve_bg_size(group);
-            }
-            if read_pos && position.is_none() {
-                let pos = resolve_bg_position(group);
-                if pos != (0.0, 0.0) {
-                    position = Some(pos);
+        let origin_groups = read_groups("background-origin");
+        let clip_groups = read_groups("background-clip");
+
+        images
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, image)| {
+                let image = image?;
+                let layer = |groups: &[Vec<BgTok>], keep: &[&str]| layer_tokens(groups, shorthand.get(i), keep, i);
+                let mut layout = BgImageLayout::default();
+                if let Some(g) = layer(&size_groups, &SIZE_KEYWORDS) {
+                    layout.size = resolve_bg_size(&g);
                 }
-            }
-            for t in group {
-                if let BgTok::Kw(k) = t {
-                    keywords.push(k.clone());
+                if let Some(g) = layer(&pos_groups, &POSITION_KEYWORDS) {
+                    layout.position = resolve_bg_position(&g);
                 }
-            }
-        };
-        // The shorthand mixes position and size (split by `/`); reading its bare lengths as a
-        // position is unreliable, so only take position/size from the dedicated longhands.
-        scan("background", false, false);
-        scan("background-repeat", false, false);
-        scan("background-size", true, false);
-        scan("background-position", false, true);
-
-        let has = |k: &str| keywords.iter().any(|s| s == k);
-        let repeat = if has("no-repeat") {
-            (false, false)
-        } else if has("repeat-x") {
-            (true, false)
-        } else if has("repeat-y") {
-            (false, true)
-        } else {
-            (true, true)
-        };
-        let size = match explicit_size {
-            Some((w, h)) => BgSize::Length(w, h),
-            None if has("cover") => BgSize::Cover,
-            None if has("contain") => BgSize::Contain,
-            None => BgSize::Auto,
-        };
-        // A length `background-position` wins; otherwise a bare `center` centers both axes.
-        let (position, center) = match position {
-            Some(pos) => (pos, (false, false)),
-            None if has("center") => ((0.0, 0.0), (true, true)),
-            None => ((0.0, 0.0), (false, false)),
-        };
-
-        BgImageLayout {
-            repeat,
-            position,
-            center,
-            size,
-        }
+                if let Some(g) = layer(&rep_groups, &REPEAT_KEYWORDS) {
+                    layout.repeat = resolve_bg_repeat(&g);
+                }
+                // The shorthand's first box is the origin and its second (or only) one the clip.
+                let origin = layer(&origin_groups, &BOX_KEYWORDS).map(|g| bg_boxes(&g));
+                let clip = layer(&clip_groups, &BOX_KEYWORDS).map(|g| bg_boxes(&g));
+                if let Some(origin) = origin.as_deref().and_then(<[BgBox]>::first) {
+                    layout.origin = *origin;
+                }
+                if let Some(clip) = clip.as_deref().and_then(<[BgBox]>::last) {
+                    layout.clip = *clip;
+                }
+                Some(BgLayer { image, layout })
+            })
+            .collect()
     }
 
     fn clear_style_cache(&self) {
         self.style_cache.lock().clear();
         self.inline_style_cache.lock().clear();
         self.pseudo_cache.lock().clear();
+        self.selection_cache.lock().clear();
+        *self.counter_state.lock() = None;
     }
 
     fn invalidate_style_for_nodes(&self, ids: &[NodeId]) {
         let mut cache = self.style_cache.lock();
         let mut inline_cache = self.inline_style_cache.lock();
         let mut pseudo_cache = self.pseudo_cache.lock();
+        let mut selection_cache = self.selection_cache.lock();
         for id in ids {
             cache.remove(id);
             inline_cache.remove(id);
-            // Drop both pseudo-boxes belonging to this owner.
-            pseudo_cache.remove(&(*id, false));
-            pseudo_cache.remove(&(*id, true));
+            selection_cache.remove(id);
+            // Drop every pseudo-box belonging to this owner.
+            for kind in [PseudoKind::Before, PseudoKind::After, PseudoKind::Marker] {
+                pseudo_cache.remove(&(*id, kind));
+            }
         }
+        *self.counter_state.lock() = None;
     }
 
     fn html_node_id(&self) -> Option<NodeId> {
-        let root = self.doc.root();
+        let root = self.document().root();
         self.find_child_by_tag(root, "html")
     }
 
     fn body_node_id(&self) -> Option<NodeId> {
-        let html = self.html_node_id().or_else(|| Some(self.doc.root()))?;
+        let html = self.html_node_id().or_else(|| Some(self.document().root()))?;
         self.find_child_by_tag(html, "body")
     }
 
     fn base_url(&self) -> String {
-        self.doc.url().map(|u| u.to_string()).unwrap_or_default()
+        self.document().url().map(|u| u.to_string()).unwrap_or_default()
     }
 
-    fn inner_html(&self, id: NodeId) -> String {
+    fn outer_xml(&self, id: NodeId) -> String {
         if is_pseudo_id(u64::from(id)) {
             return String::new();
         }
-        self.doc.write_from_node(id)
+        self.document().outer_xml(id)
     }
 
     fn get_node_by_id(&self, id: NodeId) -> Option<Node> {
         // Synthetic pseudo nodes: build a transient Element (the box) or Text (its content).
         if is_pseudo_id(u64::from(id)) {
             let (owner, role) = decode_pseudo(id);
+            let kind = role_kind(role);
             let node_type = if role_is_text(role) {
-                let text = self
-                    .pseudo_box(owner, role_is_after(role))
-                    .and_then(|pb| pb.text.clone());
-                NodeType::Text(text.unwrap_or_default())
+                NodeType::Text(self.pseudo_text(owner, kind).unwrap_or_default())
             } else {
                 // Carry the computed `display` on the synthetic element so the layouter's
                 // inline-vs-block grouping (which is tag-name based and would see an empty tag)
                 // treats the pseudo-element correctly. ::before/::after default to inline.
                 let mut style = NodeStyle::new();
                 style.set(StyleProperty::Display, self.get_style(id, &StyleProperty::Display));
-                NodeType::Element(ElementData::new(String::new(), Some(AttrMap::new()), Some(style)))
+                // An image marker is laid out as a replaced `<img>`.
+                let mut attrs = AttrMap::new();
+                let image = self.pseudo_box(owner, kind).and_then(|pb| pb.image.clone());
+                let tag = match image {
+                    Some(src) => {
+                        attrs.set("src", &src);
+                        "img"
+                    }
+                    None => "",
+                };
+                NodeType::Element(ElementData::new(tag.to_string(), Some(attrs), Some(style)))
             };
             return Some(Node {
                 node_id: id,
@@ -1440,23 +2736,24 @@ where
             });
         }
 
-        let parent_id = self.doc.parent(id);
-        let children = self.doc.children(id).to_vec();
+        let doc = self.document();
+        let parent_id = doc.parent(id);
+        let children = doc.children(id).to_vec();
 
-        let node_type = match self.doc.node_type(id) {
+        let node_type = match doc.node_type(id) {
             GosubNodeType::TextNode => {
-                let text = self.doc.text_value(id).unwrap_or("").to_string();
+                let text = doc.text_value(id).unwrap_or("").to_string();
                 // Text nodes carry no own style; inheritance handled by get_style() chain.
                 NodeType::Text(text)
             }
             GosubNodeType::CommentNode => {
-                let comment = self.doc.comment_value(id).unwrap_or("").to_string();
+                let comment = doc.comment_value(id).unwrap_or("").to_string();
                 NodeType::Comment(comment)
             }
             GosubNodeType::ElementNode => {
-                let tag_name = self.doc.tag_name(id).unwrap_or("").to_string();
+                let tag_name = doc.tag_name(id).unwrap_or("").to_string();
                 let mut attr_map = AttrMap::new();
-                if let Some(attrs) = self.doc.attributes(id) {
+                if let Some(attrs) = doc.attributes(id) {
                     for (k, v) in attrs {
                         attr_map.set(k, v);
                     }
@@ -1496,6 +2793,7 @@ fn border_width_peer_style(prop: &StyleProperty) -> Option<StyleProperty> {
         StyleProperty::BorderRightWidth => StyleProperty::BorderRightStyle,
         StyleProperty::BorderBottomWidth => StyleProperty::BorderBottomStyle,
         StyleProperty::BorderLeftWidth => StyleProperty::BorderLeftStyle,
+        StyleProperty::ColumnRuleWidth => StyleProperty::ColumnRuleStyle,
         _ => return None,
     })
 }
@@ -1,3 +1,4 @@
+use gosub_interface::font_system::WritingMode;
 use parking_lot::Mutex;
 use std::sync::OnceLock;
 
@@ -65,6 +66,8 @@ pub enum Display {
     TableHeaderGroup,
     TableRow,
     TableRowGroup,
+    /// A block box that also generates a `::marker` box.
+    ListItem,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -163,6 +166,7 @@ impl Value {
                 Display::TableHeaderGroup => "table-header-group",
                 Display::TableRow => "table-row",
                 Display::TableRowGroup => "table-row-group",
+                Display::ListItem => "list-item",
             }
             .to_string(),
             Value::FontWeight(fw) => match fw {
@@ -299,6 +303,60 @@ pub enum StyleProperty {
     ZIndex,
     LetterSpacing,
     MixBlendMode,
+    BoxShadow,
+    TextShadow,
+    Filter,
+    Isolation,
+    ListStyleType,
+    ListStylePosition,
+    ListStyleImage,
+    CounterReset,
+    CounterIncrement,
+    CounterSet,
+    ColumnCount,
+    ColumnWidth,
+    ColumnGap,
+    ColumnRuleWidth,
+    ColumnRuleStyle,
+    ColumnRuleColor,
+    ColumnSpan,
+    ColumnFill,
+    FontFeatureSettings,
+    FontVariantLigatures,
+    FontVariantCaps,
+    FontVariantNumeric,
+    FontVariantPosition,
+    FontVariantEastAsian,
+    FontVariantAlternates,
+    FontVariationSettings,
+    FontKerning,
+    FontOpticalSizing,
+    Direction,
+    UnicodeBidi,
+    WritingMode,
+    TextOrientation,
+    WordBreak,
+    OverflowWrap,
+    Hyphens,
+    TextOverflow,
+    LineClamp,
+    // Transitions, animations and transforms - kept as CSS text, parsed by `crate::animation`.
+    TransitionProperty,
+    TransitionDuration,
+    TransitionTimingFunction,
+    TransitionDelay,
+    AnimationName,
+    AnimationDuration,
+    AnimationTimingFunction,
+    AnimationDelay,
+    AnimationIterationCount,
+    AnimationDirection,
+    AnimationFillMode,
+    AnimationPlayState,
+    Transform,
+    BreakBefore,
+    BreakAfter,
+    BreakInside,
 }
 
 impl StyleProperty {
@@ -383,9 +441,67 @@ impl StyleProperty {
             StyleProperty::ZIndex => 75,
             StyleProperty::LetterSpacing => 76,
             StyleProperty::MixBlendMode => 77,
+            StyleProperty::BoxShadow => 78,
+            StyleProperty::TextShadow => 79,
+            StyleProperty::Filter => 80,
+            StyleProperty::Isolation => 81,
+            StyleProperty::ListStyleType => 82,
+            StyleProperty::ListStylePosition => 83,
+            StyleProperty::ListStyleImage => 84,
+            StyleProperty::CounterReset => 85,
+            StyleProperty::CounterIncrement => 86,
+            StyleProperty::CounterSet => 87,
+            StyleProperty::ColumnCount => 88,
+            StyleProperty::ColumnWidth => 89,
+            StyleProperty::ColumnGap => 90,
+            StyleProperty::ColumnRuleWidth => 91,
+            StyleProperty::ColumnRuleStyle => 92,
+            StyleProperty::ColumnRuleColor => 93,
+            StyleProperty::ColumnSpan => 94,
+            StyleProperty::ColumnFill => 95,
+            StyleProperty::FontFeatureSettings => 96,
+            StyleProperty::FontVariantLigatures => 97,
+            StyleProperty::FontVariantCaps => 98,
+            StyleProperty::FontVariantNumeric => 99,
+            StyleProperty::FontVariantPosition => 100,
+            StyleProperty::FontVariantEastAsian => 101,
+            StyleProperty::FontVariantAlternates => 102,
+            StyleProperty::FontVariationSettings => 103,
+            StyleProperty::FontKerning => 104,
+            StyleProperty::FontOpticalSizing => 105,
+            StyleProperty::Direction => 106,
+            StyleProperty::UnicodeBidi => 107,
+            StyleProperty::WritingMode => 108,
+            StyleProperty::TextOrientation => 109,
+            StyleProperty::WordBreak => 110,
+            StyleProperty::OverflowWrap => 111,
+            StyleProperty::Hyphens => 112,
+            StyleProperty::TextOverflow => 113,
+            StyleProperty::LineClamp => 114,
+            StyleProperty::TransitionProperty => 115,
+            StyleProperty::TransitionDuration => 116,
+            StyleProperty::TransitionTimingFunction => 117,
+            StyleProperty::TransitionDelay => 118,
+            StyleProperty::AnimationName => 119,
+            StyleProperty::AnimationDuration => 120,
+            StyleProperty::AnimationTimingFunction => 121,
+            StyleProperty::AnimationDelay => 122,
+            StyleProperty::AnimationIterationCount => 123,
+            StyleProperty::AnimationDirection => 124,
+            StyleProperty::AnimationFillMode => 125,
+            StyleProperty::AnimationPlayState => 126,
+            StyleProperty::Transform => 127,
+            StyleProperty::BreakBefore => 128,
+            StyleProperty::BreakAfter => 129,
+            StyleProperty::BreakInside => 130,
         }
     }
 
+    /// Every property, in id order
+    pub fn all() -> impl Iterator<Item = StyleProperty> {
+        (0..=u8::MAX).map_while(from_id)
+    }
+
     pub fn meta(&self) -> &'static PropertyMeta {
         property_meta(self.id())
     }
@@ -393,6 +509,70 @@ impl StyleProperty {
     pub fn css_name(&self) -> &'static str {
         self.meta().name
     }
+
+    /// The physical side (`top`, `right`, `bottom` or `left`) an `inset-*` property lands on in
+    /// `writing_mode`, with `rtl` reversing the inline axis. `None` for other properties.
+    pub fn inset_side(&self, writing_mode: WritingMode, rtl: bool) -> Option<&'static str> {
+        let (block_start, block_end) = match writing_mode {
+            WritingMode::HorizontalTb => ("top", "bottom"),
+            WritingMode::VerticalRl | WritingMode::SidewaysRl => ("right", "left"),
+            WritingMode::VerticalLr | WritingMode::SidewaysLr => ("left", "right"),
+        };
+        let (inline_start, inline_end) = match writing_mode {
+            WritingMode::HorizontalTb => ("left", "right"),
+            WritingMode::SidewaysLr => ("bottom", "top"),
+            _ => ("top", "bottom"),
+        };
+        let (inline_start, inline_end) = if rtl {
+            (inline_end, inline_start)
+        } else {
+            (inline_start, inline_end)
+        };
+        match self {
+            StyleProperty::InsetBlockStart => Some(block_start),
+            StyleProperty::InsetBlockEnd => Some(block_end),
+            StyleProperty::InsetInlineStart => Some(inline_start),
+            StyleProperty::InsetInlineEnd => Some(inline_end),
+            _ => None,
+        }
+    }
+
+    /// The `inset-*` property that lands on the physical `side` (see [`StyleProperty::inset_side`]).
+    pub fn inset_on_side(side: &str, writing_mode: WritingMode, rtl: bool) -> Option<StyleProperty> {
+        [
+            StyleProperty::InsetBlockStart,
+            StyleProperty::InsetBlockEnd,
+            StyleProperty::InsetInlineStart,
+            StyleProperty::InsetInlineEnd,
+        ]
+        .into_iter()
+        .find(|prop| prop.inset_side(writing_mode, rtl) == Some(side))
+    }
+}
+
+/// The `font-variant-*` longhand a `font-variant` shorthand keyword sets, if any.
+pub fn font_variant_longhand(keyword: &str) -> Option<StyleProperty> {
+    Some(match keyword {
+        "none"
+        | "common-ligatures"
+        | "no-common-ligatures"
+        | "discretionary-ligatures"
+        | "no-discretionary-ligatures"
+        | "historical-ligatures"
+        | "no-historical-ligatures"
+        | "contextual"
+        | "no-contextual" => StyleProperty::FontVariantLigatures,
+        "small-caps" | "all-small-caps" | "petite-caps" | "all-petite-caps" | "unicase" | "titling-caps" => {
+            StyleProperty::FontVariantCaps
+        }
+        "lining-nums" | "oldstyle-nums" | "proportional-nums" | "tabular-nums" | "diagonal-fractions"
+        | "stacked-fractions" | "ordinal" | "slashed-zero" => StyleProperty::FontVariantNumeric,
+        "sub" | "super" => StyleProperty::FontVariantPosition,
+        "jis78" | "jis83" | "jis90" | "jis04" | "simplified" | "traditional" | "full-width" | "proportional-width"
+        | "ruby" => StyleProperty::FontVariantEastAsian,
+        "historical-forms" => StyleProperty::FontVariantAlternates,
+        _ => return None,
+    })
 }
 
 // ── Property registry ─────────────────────────────────────────────────────────
@@ -917,6 +1097,324 @@ static PROPERTIES: &[PropertyMeta] = &[
         inherited: false,
         initial_kind: InitialKind::Keyword("normal"),
     },
+    // 78 box-shadow - stored as canonical CSS text, parsed at paint time
+    PropertyMeta {
+        name: "box-shadow",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 79 text-shadow - inherited, like the text colour it usually accompanies
+    PropertyMeta {
+        name: "text-shadow",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 80 filter - not inherited; a non-`none` value promotes the element to its own layer
+    PropertyMeta {
+        name: "filter",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 81 isolation - not inherited; `isolate` makes the element an isolated compositing group
+    PropertyMeta {
+        name: "isolation",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 82 list-style-type - inherited; a counter-style name or a quoted literal string
+    PropertyMeta {
+        name: "list-style-type",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("disc"),
+    },
+    // 83 list-style-position
+    PropertyMeta {
+        name: "list-style-position",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("outside"),
+    },
+    // 84 list-style-image - the resolved url, or `none`
+    PropertyMeta {
+        name: "list-style-image",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 85 counter-reset - stored as CSS text (`name [int]`…), read by the counter walk
+    PropertyMeta {
+        name: "counter-reset",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 86 counter-increment
+    PropertyMeta {
+        name: "counter-increment",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 87 counter-set
+    PropertyMeta {
+        name: "counter-set",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 88 column-count - a positive integer or `auto`
+    PropertyMeta {
+        name: "column-count",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 89 column-width - a length or `auto`
+    PropertyMeta {
+        name: "column-width",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 90 column-gap - `normal` resolves to 1em in the layouter
+    PropertyMeta {
+        name: "column-gap",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 91 column-rule-width - initial = medium = 3px; 0 when the rule style is none/hidden
+    PropertyMeta {
+        name: "column-rule-width",
+        inherited: false,
+        initial_kind: InitialKind::Unit(3.0, Unit::Px),
+    },
+    // 92 column-rule-style
+    PropertyMeta {
+        name: "column-rule-style",
+        inherited: false,
+        initial_kind: InitialKind::BorderStyle(BorderStyle::None),
+    },
+    // 93 column-rule-color - initial = currentColor (black)
+    PropertyMeta {
+        name: "column-rule-color",
+        inherited: false,
+        initial_kind: InitialKind::Color(0, 0, 0, 255),
+    },
+    // 94 column-span - `none` | `all`
+    PropertyMeta {
+        name: "column-span",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 95 column-fill - `balance` | `auto`
+    PropertyMeta {
+        name: "column-fill",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("balance"),
+    },
+    // 96 font-feature-settings - `normal` | `"liga" 0, "ss01"`
+    PropertyMeta {
+        name: "font-feature-settings",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 97 font-variant-ligatures
+    PropertyMeta {
+        name: "font-variant-ligatures",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 98 font-variant-caps
+    PropertyMeta {
+        name: "font-variant-caps",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 99 font-variant-numeric
+    PropertyMeta {
+        name: "font-variant-numeric",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 100 font-variant-position
+    PropertyMeta {
+        name: "font-variant-position",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 101 font-variant-east-asian
+    PropertyMeta {
+        name: "font-variant-east-asian",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 102 font-variant-alternates
+    PropertyMeta {
+        name: "font-variant-alternates",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 103 font-variation-settings - `normal` | `"wght" 650`
+    PropertyMeta {
+        name: "font-variation-settings",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 104 font-kerning - `auto` | `normal` | `none`
+    PropertyMeta {
+        name: "font-kerning",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 105 font-optical-sizing - `auto` | `none`
+    PropertyMeta {
+        name: "font-optical-sizing",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 106 direction - `ltr` | `rtl`; the `dir` attribute maps onto it
+    PropertyMeta {
+        name: "direction",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("ltr"),
+    },
+    // 107 unicode-bidi - `normal` | `embed` | `isolate` | `bidi-override` | `isolate-override` | `plaintext`
+    PropertyMeta {
+        name: "unicode-bidi",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 108 writing-mode - `horizontal-tb` | `vertical-rl` | `vertical-lr` | `sideways-rl` | `sideways-lr`
+    PropertyMeta {
+        name: "writing-mode",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("horizontal-tb"),
+    },
+    // 109 text-orientation - `mixed` | `upright` | `sideways`
+    PropertyMeta {
+        name: "text-orientation",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("mixed"),
+    },
+    // 110 word-break - `normal` | `break-all` | `keep-all`
+    PropertyMeta {
+        name: "word-break",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 111 overflow-wrap (legacy `word-wrap`) - `normal` | `anywhere` | `break-word`
+    PropertyMeta {
+        name: "overflow-wrap",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 112 hyphens - `none` | `manual` | `auto`
+    PropertyMeta {
+        name: "hyphens",
+        inherited: true,
+        initial_kind: InitialKind::Keyword("manual"),
+    },
+    // 113 text-overflow - `clip` | `ellipsis`
+    PropertyMeta {
+        name: "text-overflow",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("clip"),
+    },
+    // 114 line-clamp (or `-webkit-line-clamp`) - `none` | <integer>
+    PropertyMeta {
+        name: "line-clamp",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 115 transition-property - `all` | `none` | <property>#
+    PropertyMeta {
+        name: "transition-property",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("all"),
+    },
+    // 116 transition-duration - <time>#
+    PropertyMeta {
+        name: "transition-duration",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("0s"),
+    },
+    // 117 transition-timing-function - <easing-function>#
+    PropertyMeta {
+        name: "transition-timing-function",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("ease"),
+    },
+    // 118 transition-delay - <time>#
+    PropertyMeta {
+        name: "transition-delay",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("0s"),
+    },
+    // 119 animation-name - `none` | <keyframes-name>#
+    PropertyMeta {
+        name: "animation-name",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 120 animation-duration - <time>#
+    PropertyMeta {
+        name: "animation-duration",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("0s"),
+    },
+    // 121 animation-timing-function - <easing-function>#
+    PropertyMeta {
+        name: "animation-timing-function",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("ease"),
+    },
+    // 122 animation-delay - <time>#
+    PropertyMeta {
+        name: "animation-delay",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("0s"),
+    },
+    // 123 animation-iteration-count - `infinite` | <number>#
+    PropertyMeta {
+        name: "animation-iteration-count",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("1"),
+    },
+    // 124 animation-direction - `normal` | `reverse` | `alternate` | `alternate-reverse`#
+    PropertyMeta {
+        name: "animation-direction",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("normal"),
+    },
+    // 125 animation-fill-mode - `none` | `forwards` | `backwards` | `both`#
+    PropertyMeta {
+        name: "animation-fill-mode",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 126 animation-play-state - `running` | `paused`#
+    PropertyMeta {
+        name: "animation-play-state",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("running"),
+    },
+    // 127 transform - `none` | <transform-function>+
+    PropertyMeta {
+        name: "transform",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("none"),
+    },
+    // 128 break-before - `auto` | `avoid` | `page` | `left` | `right` | `column` | ...
+    PropertyMeta {
+        name: "break-before",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 129 break-after - `auto` | `avoid` | `page` | `left` | `right` | `column` | ...
+    PropertyMeta {
+        name: "break-after",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
+    // 130 break-inside - `auto` | `avoid` | `avoid-page` | `avoid-column`
+    PropertyMeta {
+        name: "break-inside",
+        inherited: false,
+        initial_kind: InitialKind::Keyword("auto"),
+    },
 ];
 
 // ── NodeStyle - replaces StylePropertyList ────────────────────────────────────
@@ -1056,6 +1554,59 @@ fn from_id(id: u8) -> Option<StyleProperty> {
         75 => Some(StyleProperty::ZIndex),
         76 => Some(StyleProperty::LetterSpacing),
         77 => Some(StyleProperty::MixBlendMode),
+        78 => Some(StyleProperty::BoxShadow),
+        79 => Some(StyleProperty::TextShadow),
+        80 => Some(StyleProperty::Filter),
+        81 => Some(StyleProperty::Isolation),
+        82 => Some(StyleProperty::ListStyleType),
+        83 => Some(StyleProperty::ListStylePosition),
+        84 => Some(StyleProperty::ListStyleImage),
+        85 => Some(StyleProperty::CounterReset),
+        86 => Some(StyleProperty::CounterIncrement),
+        87 => Some(StyleProperty::CounterSet),
+        88 => Some(StyleProperty::ColumnCount),
+        89 => Some(StyleProperty::ColumnWidth),
+        90 => Some(StyleProperty::ColumnGap),
+        91 => Some(StyleProperty::ColumnRuleWidth),
+        92 => Some(StyleProperty::ColumnRuleStyle),
+        93 => Some(StyleProperty::ColumnRuleColor),
+        94 => Some(StyleProperty::ColumnSpan),
+        95 => Some(StyleProperty::ColumnFill),
+        96 => Some(StyleProperty::FontFeatureSettings),
+        97 => Some(StyleProperty::FontVariantLigatures),
+        98 => Some(StyleProperty::FontVariantCaps),
+        99 => Some(StyleProperty::FontVariantNumeric),
+        100 => Some(StyleProperty::FontVariantPosition),
+        101 => Some(StyleProperty::FontVariantEastAsian),
+        102 => Some(StyleProperty::FontVariantAlternates),
+        103 => Some(StyleProperty::FontVariationSettings),
+        104 => Some(StyleProperty::FontKerning),
+        105 => Some(StyleProperty::FontOpticalSizing),
+        106 => Some(StyleProperty::Direction),
+        107 => Some(StyleProperty::UnicodeBidi),
+        108 => Some(StyleProperty::WritingMode),
+        109 => Some(StyleProperty::TextOrientation),
+        110 => Some(StyleProperty::WordBreak),
+        111 => Some(StyleProperty::OverflowWrap),
+        112 => Some(StyleProperty::Hyphens),
+        113 => Some(StyleProperty::TextOverflow),
+        114 => Some(StyleProperty::LineClamp),
+        115 => Some(StyleProperty::TransitionProperty),
+        116 => Some(StyleProperty::TransitionDuration),
+        117 => Some(StyleProperty::TransitionTimingFunction),
+        118 => Some(StyleProperty::TransitionDelay),
+        119 => Some(StyleProperty::AnimationName),
+        120 => Some(StyleProperty::AnimationDuration),
+        121 => Some(StyleProperty::AnimationTimingFunction),
+        122 => Some(StyleProperty::AnimationDelay),
+        123 => Some(StyleProperty::AnimationIterationCount),
+        124 => Some(StyleProperty::AnimationDirection),
+        125 => Some(StyleProperty::AnimationFillMode),
+        126 => Some(StyleProperty::AnimationPlayState),
+        127 => Some(StyleProperty::Transform),
+        128 => Some(StyleProperty::BreakBefore),
+        129 => Some(StyleProperty::BreakAfter),
+        130 => Some(StyleProperty::BreakInside),
         _ => None,
     }
 }
@@ -1097,6 +1648,59 @@ mod tests {
             StyleProperty::MarginTop,
             StyleProperty::Display,
             StyleProperty::FlexGrow,
+            StyleProperty::BoxShadow,
+            StyleProperty::TextShadow,
+            StyleProperty::Filter,
+            StyleProperty::Isolation,
+            StyleProperty::ListStyleType,
+            StyleProperty::ListStylePosition,
+            StyleProperty::ListStyleImage,
+            StyleProperty::CounterReset,
+            StyleProperty::CounterIncrement,
+            StyleProperty::CounterSet,
+            StyleProperty::ColumnCount,
+            StyleProperty::ColumnWidth,
+            StyleProperty::ColumnGap,
+            StyleProperty::ColumnRuleWidth,
+            StyleProperty::ColumnRuleStyle,
+            StyleProperty::ColumnRuleColor,
+            StyleProperty::ColumnSpan,
+            StyleProperty::ColumnFill,
+            StyleProperty::FontFeatureSettings,
+            StyleProperty::FontVariantLigatures,
+            StyleProperty::FontVariantCaps,
+            StyleProperty::FontVariantNumeric,
+            StyleProperty::FontVariantPosition,
+            StyleProperty::FontVariantEastAsian,
+            StyleProperty::FontVariantAlternates,
+            StyleProperty::FontVariationSettings,
+            StyleProperty::FontKerning,
+            StyleProperty::FontOpticalSizing,
+            StyleProperty::Direction,
+            StyleProperty::UnicodeBidi,
+            StyleProperty::WritingMode,
+            StyleProperty::TextOrientation,
+            StyleProperty::WordBreak,
+            StyleProperty::OverflowWrap,
+            StyleProperty::Hyphens,
+            StyleProperty::TextOverflow,
+            StyleProperty::LineClamp,
+            StyleProperty::TransitionProperty,
+            StyleProperty::TransitionDuration,
+            StyleProperty::TransitionTimingFunction,
+            StyleProperty::TransitionDelay,
+            StyleProperty::AnimationName,
+            StyleProperty::AnimationDuration,
+            StyleProperty::AnimationTimingFunction,
+            StyleProperty::AnimationDelay,
+            StyleProperty::AnimationIterationCount,
+            StyleProperty::AnimationDirection,
+            StyleProperty::AnimationFillMode,
+            StyleProperty::AnimationPlayState,
+            StyleProperty::Transform,
+            StyleProperty::BreakBefore,
+            StyleProperty::BreakAfter,
+            StyleProperty::BreakInside,
         ];
         for prop in &props {
             let id = prop.id();
@@ -1120,4 +1724,39 @@ mod tests {
             let _ = prop.meta(); // must not panic
         }
     }
+
+    #[test]
+    fn insets_follow_the_writing_mode() {
+        let side = |prop: StyleProperty, mode, rtl| prop.inset_side(mode, rtl);
+        assert_eq!(
+            side(StyleProperty::InsetBlockStart, WritingMode::HorizontalTb, false),
+            Some("top")
+        );
+        assert_eq!(
+            side(StyleProperty::InsetInlineStart, WritingMode::HorizontalTb, true),
+            Some("right")
+        );
+        assert_eq!(
+            side(StyleProperty::InsetBlockStart, WritingMode::VerticalRl, false),
+            Some("right")
+        );
+        assert_eq!(
+            side(StyleProperty::InsetInlineEnd, WritingMode::VerticalLr, false),
+            Some("bottom")
+        );
+        assert_eq!(
+            side(StyleProperty::InsetInlineStart, WritingMode::SidewaysLr, false),
+            Some("bottom")
+        );
+        assert_eq!(side(StyleProperty::Width, WritingMode::VerticalRl, false), None);
+
+        assert_eq!(
+            StyleProperty::inset_on_side("left", WritingMode::VerticalRl, false),
+            Some(StyleProperty::InsetBlockEnd)
+        );
+        assert_eq!(
+            StyleProperty::inset_on_side("top", WritingMode::VerticalRl, true),
+            Some(StyleProperty::InsetInlineEnd)
+        );
+    }
 }
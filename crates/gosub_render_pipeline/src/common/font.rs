@@ -1,3 +1,9 @@
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use gosub_interface::font_system::{
+    FontFeature, FontKerning, FontVariation, Hyphens, LineBreaking, OverflowWrap, TextDirection, TextOrientation,
+    TextOverflow, WordBreak, WritingMode,
+};
+
 #[derive(Debug, Clone)]
 pub enum FontAlignment {
     /// Start of the line (left for LTR, right for RTL)
@@ -26,4 +32,109 @@ pub struct FontInfo {
     pub alignment: FontAlignment,
     pub underline: bool,
     pub line_through: bool,
+    pub shaping: FontShaping,
+}
+
+/// The OpenType settings text is shaped with: `font-variant-*`, `font-feature-settings`,
+/// `font-variation-settings`, `font-kerning` and `font-optical-sizing`, plus the `direction` the
+/// run's bidi levels are resolved against and the `writing-mode`/`text-orientation` it is set in,
+/// and the line-breaking controls it wraps with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontShaping {
+    /// Variant features first, then `font-feature-settings`, which overrides them.
+    pub features: Vec<FontFeature>,
+    pub variations: Vec<FontVariation>,
+    pub kerning: FontKerning,
+    pub optical_sizing: bool,
+    pub direction: TextDirection,
+    pub writing_mode: WritingMode,
+    pub orientation: TextOrientation,
+    pub breaking: LineBreaking,
+}
+
+impl Default for FontShaping {
+    fn default() -> Self {
+        Self {
+            features: Vec::new(),
+            variations: Vec::new(),
+            kerning: FontKerning::Auto,
+            optical_sizing: true,
+            direction: TextDirection::Ltr,
+            writing_mode: WritingMode::HorizontalTb,
+            orientation: TextOrientation::Mixed,
+            breaking: LineBreaking::default(),
+        }
+    }
+}
+
+impl FontShaping {
+    /// Read the settings from an element's computed style.
+    pub fn from_style(get: impl Fn(&StyleProperty) -> Value) -> Self {
+        let text = |prop: &StyleProperty| match get(prop) {
+            Value::Keyword(id) => lookup(id),
+            _ => String::new(),
+        };
+
+        let mut features: Vec<FontFeature> = [
+            StyleProperty::FontVariantLigatures,
+            StyleProperty::FontVariantCaps,
+            StyleProperty::FontVariantNumeric,
+            StyleProperty::FontVariantPosition,
+            StyleProperty::FontVariantEastAsian,
+            StyleProperty::FontVariantAlternates,
+        ]
+        .iter()
+        .flat_map(|prop| FontFeature::from_variant(&text(prop)))
+        .collect();
+        features.extend(FontFeature::parse_settings(&text(&StyleProperty::FontFeatureSettings)).unwrap_or_default());
+
+        Self {
+            features,
+            variations: FontVariation::parse_settings(&text(&StyleProperty::FontVariationSettings)).unwrap_or_default(),
+            kerning: FontKerning::from_keyword(&text(&StyleProperty::FontKerning)).unwrap_or_default(),
+            optical_sizing: text(&StyleProperty::FontOpticalSizing) != "none",
+            direction: TextDirection::from_keyword(&text(&StyleProperty::Direction)).unwrap_or_default(),
+            writing_mode: WritingMode::from_keyword(&text(&StyleProperty::WritingMode)).unwrap_or_default(),
+            orientation: TextOrientation::from_keyword(&text(&StyleProperty::TextOrientation)).unwrap_or_default(),
+            breaking: LineBreaking {
+                word_break: WordBreak::from_keyword(&text(&StyleProperty::WordBreak)).unwrap_or_default(),
+                overflow_wrap: OverflowWrap::from_keyword(&text(&StyleProperty::OverflowWrap)).unwrap_or_default(),
+                hyphens: Hyphens::from_keyword(&text(&StyleProperty::Hyphens)).unwrap_or_default(),
+                ..LineBreaking::default()
+            },
+        }
+    }
+
+    /// Add `text-overflow` and `line-clamp`, which are not inherited and so are read from the
+    /// block container the text is laid out in rather than from the text itself.
+    pub fn with_block_style(mut self, get: impl Fn(&StyleProperty) -> Value) -> Self {
+        if let Value::Keyword(id) = get(&StyleProperty::TextOverflow) {
+            self.breaking.text_overflow = TextOverflow::from_keyword(&lookup(id)).unwrap_or_default();
+        }
+        self.breaking.line_clamp = match get(&StyleProperty::LineClamp) {
+            Value::Number(n) if n >= 1.0 => Some(n as u32),
+            _ => None,
+        };
+        self
+    }
+
+    /// A compact, hashable rendering of the settings, for measurement and tile caches.
+    pub fn cache_key(&self) -> String {
+        if *self == Self::default() {
+            return String::new();
+        }
+        let settings: Vec<String> = (self.features.iter().map(ToString::to_string))
+            .chain(self.variations.iter().map(ToString::to_string))
+            .collect();
+        format!(
+            "{:?}/{:?}/{:?}/{:?}/{}/{}/{:?}",
+            self.direction,
+            self.writing_mode,
+            self.orientation,
+            self.kerning,
+            self.optical_sizing,
+            settings.join(";"),
+            self.breaking
+        )
+    }
 }
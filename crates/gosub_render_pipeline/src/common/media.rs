@@ -7,10 +7,12 @@ mod media;
 mod media_store;
 
 pub use decoder::{
-    DecodedImage, DecodedMedia, ImageDecodeError, MediaDecoder, MediaDecoderRegistry, PixelBuffer, RasterDecoder,
-    SvgDecoder,
+    DecodedAnimation, DecodedFrame, DecodedImage, DecodedMedia, ImageDecodeError, LoopCount, MediaDecoder,
+    MediaDecoderRegistry, PixelBuffer, RasterDecoder, SvgDecoder,
 };
 
+pub use media::AnimationFrame;
+pub use media::ImageAnimation;
 pub use media::Media;
 pub use media::MediaId;
 pub use media::MediaImage;
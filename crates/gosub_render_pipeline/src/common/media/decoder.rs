@@ -2,8 +2,10 @@
 //! format-agnostic by handing raw bytes plus an optional MIME hint to a [`MediaDecoderRegistry`].
 //!
 //! Raster formats normalize to [`PixelBuffer::Rgba8`]; SVG stays a retained `usvg::Tree`
-//! ([`DecodedMedia::Vector`]) so it can be re-rasterized crisply at any size.
+//! ([`DecodedMedia::Vector`]) so it can be re-rasterized crisply at any size. Animated GIF, APNG
+//! and WebP keep every frame ([`DecodedMedia::Animated`]).
 
+mod animation;
 mod raster;
 mod svg;
 
@@ -11,6 +13,7 @@ pub use raster::RasterDecoder;
 pub use svg::SvgDecoder;
 
 use std::fmt;
+use std::time::Duration;
 
 /// Pixel storage for a decoded raster image. Only 8-bit RGBA is supported today; the enum
 /// leaves room for wider/greyscale buffers without churning the public surface.
@@ -91,10 +94,34 @@ impl fmt::Debug for DecodedImage {
     }
 }
 
-/// What a [`MediaDecoder`] produces: either a normalized raster image or a retained vector
-/// tree that downstream code re-rasterizes per render size.
+/// How many times an animation plays through before it stops on its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopCount {
+    Infinite,
+    Finite(u32),
+}
+
+/// One frame of an animated image: the whole canvas as it looks while the frame shows (earlier
+/// frames and disposal already composited in), and for how long it shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedFrame {
+    pub image: DecodedImage,
+    pub delay: Duration,
+}
+
+/// A decoded animated image. Always holds at least two frames; a single-frame animation decodes
+/// as a plain [`DecodedMedia::Raster`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedAnimation {
+    pub frames: Vec<DecodedFrame>,
+    pub loop_count: LoopCount,
+}
+
+/// What a [`MediaDecoder`] produces: a normalized raster image, the frames of an animated one,
+/// or a retained vector tree that downstream code re-rasterizes per render size.
 pub enum DecodedMedia {
     Raster(DecodedImage),
+    Animated(DecodedAnimation),
     // Boxed: a `usvg::Tree` is far larger than `DecodedImage`, so boxing keeps the enum small.
     Vector(Box<resvg::usvg::Tree>),
 }
@@ -103,6 +130,11 @@ impl fmt::Debug for DecodedMedia {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             DecodedMedia::Raster(img) => f.debug_tuple("Raster").field(img).finish(),
+            DecodedMedia::Animated(animation) => f
+                .debug_struct("Animated")
+                .field("frames", &animation.frames.len())
+                .field("loop_count", &animation.loop_count)
+                .finish(),
             DecodedMedia::Vector(_) => f.write_str("Vector(usvg::Tree)"),
         }
     }
@@ -240,7 +272,7 @@ mod tests {
     #[test]
     fn decodes_raster_formats_via_magic() {
         let registry = MediaDecoderRegistry::with_defaults();
-        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Gif] {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Gif, ImageFormat::WebP] {
             let bytes = encode(format);
             let media = registry
                 .decode(None, &bytes)
@@ -0,0 +1,221 @@
+//! Animated GIF, APNG and WebP. The `image` crate composites each frame onto the canvas and hands
+//! out its delay; how often the animation loops it leaves out, so that is read from the container
+//! here.
+
+use super::{DecodedAnimation, DecodedFrame, ImageDecodeError, LoopCount};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frames, ImageFormat};
+use std::io::Cursor;
+use std::time::Duration;
+
+/// Browsers show frames that ask for 10 ms or less for 100 ms instead: old encoders wrote 0 to
+/// mean "as fast as possible", and pages rely on the slowdown.
+const MIN_DELAY: Duration = Duration::from_millis(11);
+const CLAMPED_DELAY: Duration = Duration::from_millis(100);
+
+/// Decoded frames are full canvases, so a long animation of a large image adds up quickly. Past
+/// this many bytes of frames only the first frame is shown.
+const MAX_ANIMATION_BYTES: usize = 256 * 1024 * 1024;
+
+/// Decode `bytes` as an animation. `None` when they are not an animated GIF, APNG or WebP - or
+/// one with a single frame, or too large to hold - so the caller decodes a still image instead.
+pub(super) fn decode_animation(bytes: &[u8]) -> Option<Result<DecodedAnimation, ImageDecodeError>> {
+    let frames = match image::guess_format(bytes).ok()? {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes)).map(|d| d.into_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes)).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            decoder.apng().map(|d| d.into_frames())
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes)).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            Ok(decoder.into_frames())
+        }
+        _ => return None,
+    };
+    // A container the animation decoder can't open is left to the still-image path to report
+    let frames = match collect_frames(frames.ok()?)? {
+        Ok(frames) if frames.len() > 1 => frames,
+        Ok(_) => return None,
+        Err(e) => return Some(Err(e)),
+    };
+
+    Some(Ok(DecodedAnimation {
+        frames,
+        loop_count: loop_count(bytes),
+    }))
+}
+
+/// `None` when the frames outgrow [`MAX_ANIMATION_BYTES`].
+fn collect_frames(frames: Frames<'_>) -> Option<Result<Vec<DecodedFrame>, ImageDecodeError>> {
+    let mut out = Vec::new();
+    let mut total = 0usize;
+    for frame in frames {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(ImageDecodeError::Decode(e.to_string()))),
+        };
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0);
+        let image = frame.into_buffer();
+        total += image.as_raw().len();
+        if total > MAX_ANIMATION_BYTES {
+            log::warn!("animation exceeds {MAX_ANIMATION_BYTES} bytes of frames, showing its first frame only");
+            return None;
+        }
+        out.push(DecodedFrame {
+            image: image.into(),
+            delay: if delay < MIN_DELAY { CLAMPED_DELAY } else { delay },
+        });
+    }
+    Some(Ok(out))
+}
+
+/// How often the animation in `bytes` plays, read from its container. An animation that doesn't
+/// say plays once.
+fn loop_count(bytes: &[u8]) -> LoopCount {
+    let count = match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => gif_loop_count(bytes),
+        Ok(ImageFormat::Png) => apng_loop_count(bytes),
+        Ok(ImageFormat::WebP) => webp_loop_count(bytes),
+        _ => None,
+    };
+    count.unwrap_or(LoopCount::Finite(1))
+}
+
+/// The NETSCAPE2.0 application extension counts repeats after the first play, 0 meaning forever.
+fn gif_loop_count(bytes: &[u8]) -> Option<LoopCount> {
+    const EXTENSION: &[u8] = b"\x21\xff\x0bNETSCAPE2.0\x03\x01";
+    let at = bytes.windows(EXTENSION.len()).position(|w| w == EXTENSION)? + EXTENSION.len();
+    let repeats = u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?);
+    Some(match repeats {
+        0 => LoopCount::Infinite,
+        n => LoopCount::Finite(n as u32 + 1),
+    })
+}
+
+/// The `acTL` chunk, which precedes the image data, counts plays, 0 meaning forever.
+fn apng_loop_count(bytes: &[u8]) -> Option<LoopCount> {
+    let u32_at = |at: usize| -> Option<u32> { Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?)) };
+    let mut pos = 8;
+    while let (Some(len), Some(kind)) = (u32_at(pos), bytes.get(pos + 4..pos + 8)) {
+        match kind {
+            b"acTL" => {
+                return Some(match u32_at(pos + 12)? {
+                    0 => LoopCount::Infinite,
+                    n => LoopCount::Finite(n),
+                })
+            }
+            b"IDAT" => return None,
+            _ => {}
+        }
+        // Length, type, data and CRC
+        pos += 12 + len as usize;
+    }
+    None
+}
+
+/// The `ANIM` chunk counts plays after a 4-byte background colour, 0 meaning forever.
+fn webp_loop_count(bytes: &[u8]) -> Option<LoopCount> {
+    let mut pos = 12;
+    while let (Some(kind), Some(size)) = (bytes.get(pos..pos + 4), bytes.get(pos + 4..pos + 8)) {
+        let size = u32::from_le_bytes(size.try_into().ok()?) as usize;
+        if kind == b"ANIM" {
+            let loops = u16::from_le_bytes(bytes.get(pos + 12..pos + 14)?.try_into().ok()?);
+            return Some(match loops {
+                0 => LoopCount::Infinite,
+                n => LoopCount::Finite(n as u32),
+            });
+        }
+        // Chunks are padded to an even length
+        pos += 8 + size + (size & 1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame, Rgba, RgbaImage};
+
+    fn gif(delays_ms: &[u32], repeat: Option<Repeat>) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut out);
+            if let Some(repeat) = repeat {
+                encoder.set_repeat(repeat).expect("set repeat");
+            }
+            for (i, ms) in delays_ms.iter().enumerate() {
+                let colour = Rgba([(i * 80) as u8, 0, 0, 255]);
+                let frame = Frame::from_parts(
+                    RgbaImage::from_pixel(4, 2, colour),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(*ms, 1),
+                );
+                encoder.encode_frame(frame).expect("encode frame");
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn decodes_gif_frames_with_delays() {
+        let bytes = gif(&[50, 200, 0], Some(Repeat::Infinite));
+        let animation = decode_animation(&bytes).expect("animated").expect("decodes");
+        assert_eq!(animation.loop_count, LoopCount::Infinite);
+        let delays: Vec<_> = animation.frames.iter().map(|f| f.delay).collect();
+        assert_eq!(
+            delays,
+            [Duration::from_millis(50), Duration::from_millis(200), CLAMPED_DELAY],
+            "a zero delay is clamped like browsers do"
+        );
+        assert_eq!(
+            (animation.frames[1].image.width(), animation.frames[1].image.height()),
+            (4, 2)
+        );
+        assert_eq!(animation.frames[1].image.as_raw()[0], 80);
+    }
+
+    #[test]
+    fn gif_loop_count_counts_repeats() {
+        let once = gif(&[50, 50], None);
+        assert_eq!(loop_count(&once), LoopCount::Finite(1));
+        let thrice = gif(&[50, 50], Some(Repeat::Finite(2)));
+        assert_eq!(loop_count(&thrice), LoopCount::Finite(3));
+    }
+
+    #[test]
+    fn still_images_are_not_animations() {
+        assert!(decode_animation(&gif(&[50], Some(Repeat::Infinite))).is_none());
+        assert!(decode_animation(b"not an image").is_none());
+    }
+
+    #[test]
+    fn reads_apng_and_webp_loop_counts() {
+        // Signature, then an acTL chunk: 2 frames, 3 plays
+        let mut apng = b"\x89PNG\r\n\x1a\n".to_vec();
+        apng.extend_from_slice(&8u32.to_be_bytes());
+        apng.extend_from_slice(b"acTL");
+        apng.extend_from_slice(&2u32.to_be_bytes());
+        apng.extend_from_slice(&3u32.to_be_bytes());
+        assert_eq!(apng_loop_count(&apng), Some(LoopCount::Finite(3)));
+
+        // RIFF header, a VP8X chunk, then an ANIM chunk looping forever
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.extend_from_slice(&10u32.to_le_bytes());
+        webp.extend_from_slice(&[0; 10]);
+        webp.extend_from_slice(b"ANIM");
+        webp.extend_from_slice(&6u32.to_le_bytes());
+        webp.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        assert_eq!(webp_loop_count(&webp), Some(LoopCount::Infinite));
+    }
+}
@@ -1,7 +1,9 @@
+use super::animation::decode_animation;
 use super::{DecodedMedia, ImageDecodeError, MediaDecoder};
 
-/// Decodes every raster format the `image` crate is compiled with (PNG/JPEG/GIF today). `image`
-/// sniffs the real format from the bytes, so a wrong MIME hint between raster formats is harmless.
+/// Decodes every raster format the `image` crate is compiled with: PNG, JPEG, GIF and WebP, plus
+/// AVIF with the `avif` feature. `image` sniffs the real format from the bytes, so a wrong MIME
+/// hint between raster formats is harmless. Animated GIF, APNG and WebP keep all their frames.
 pub struct RasterDecoder;
 
 impl RasterDecoder {
@@ -29,6 +31,9 @@ impl MediaDecoder for RasterDecoder {
     }
 
     fn decode(&self, bytes: &[u8]) -> Result<DecodedMedia, ImageDecodeError> {
+        if let Some(animation) = decode_animation(bytes) {
+            return animation.map(DecodedMedia::Animated);
+        }
         match image::load_from_memory(bytes) {
             Ok(img) => Ok(DecodedMedia::Raster(img.to_rgba8().into())),
             // Browsers tolerate PNGs with bad chunk CRCs (some encoders emit them); the `image`
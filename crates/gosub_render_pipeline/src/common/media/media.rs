@@ -1,7 +1,9 @@
 use crate::common::hash::{hash_from_string, Sha256Hash};
 use crate::common::media::Image;
+use crate::common::media::LoopCount;
 use crate::common::media::Svg;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MediaId(u64);
@@ -40,7 +42,55 @@ pub struct MediaSvg {
 pub struct MediaImage {
     src: String,
     hash: Sha256Hash,
+    /// The image, or the first frame of an animated one.
     pub image: Image,
+    pub animation: Option<ImageAnimation>,
+}
+
+/// The frames of an animated image. Every frame is an image of its own in the store, the first
+/// being the animated image itself, so showing another frame is drawing another media id - which
+/// is also what tells the tile caches the pixels changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAnimation {
+    pub frames: Vec<AnimationFrame>,
+    pub loop_count: LoopCount,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFrame {
+    pub media_id: MediaId,
+    pub delay: Duration,
+}
+
+impl ImageAnimation {
+    fn cycle(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.delay).sum()
+    }
+
+    /// Whether the animation has played its last loop `elapsed` after it started.
+    pub fn is_finished(&self, elapsed: Duration) -> bool {
+        match self.loop_count {
+            LoopCount::Infinite => false,
+            LoopCount::Finite(plays) => elapsed >= self.cycle().saturating_mul(plays),
+        }
+    }
+
+    /// The frame shown `elapsed` after the animation started. A finished animation stays on its
+    /// last frame.
+    pub fn frame_at(&self, elapsed: Duration) -> Option<MediaId> {
+        let cycle = self.cycle();
+        if self.is_finished(elapsed) || cycle.is_zero() {
+            return self.frames.last().map(|frame| frame.media_id);
+        }
+        let mut t = Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos()) as u64);
+        for frame in &self.frames {
+            if t < frame.delay {
+                return Some(frame.media_id);
+            }
+            t -= frame.delay;
+        }
+        self.frames.last().map(|frame| frame.media_id)
+    }
 }
 
 #[derive(Clone)]
@@ -63,6 +113,17 @@ impl Media {
             src: src.to_string(),
             hash: hash_from_string(src),
             image,
+            animation: None,
+        }))
+    }
+
+    /// An animated image showing `first` until the animation starts.
+    pub fn animated_image(src: &str, first: Image, animation: ImageAnimation) -> Self {
+        Media::Image(Arc::new(MediaImage {
+            src: src.to_string(),
+            hash: hash_from_string(src),
+            image: first,
+            animation: Some(animation),
         }))
     }
 }
@@ -75,3 +136,40 @@ impl std::fmt::Debug for Media {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(loop_count: LoopCount) -> ImageAnimation {
+        let frame = |id, ms| AnimationFrame {
+            media_id: MediaId::new(id),
+            delay: Duration::from_millis(ms),
+        };
+        ImageAnimation {
+            frames: vec![frame(1, 100), frame(2, 50), frame(3, 50)],
+            loop_count,
+        }
+    }
+
+    #[test]
+    fn frames_follow_their_delays_and_loop() {
+        let anim = animation(LoopCount::Infinite);
+        let at = |ms| anim.frame_at(Duration::from_millis(ms)).map(MediaId::as_u64);
+        assert_eq!(at(0), Some(1));
+        assert_eq!(at(99), Some(1));
+        assert_eq!(at(100), Some(2));
+        assert_eq!(at(175), Some(3));
+        assert_eq!(at(210), Some(1), "wraps after a 200 ms cycle");
+        assert!(!anim.is_finished(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn finite_animation_stops_on_its_last_frame() {
+        let anim = animation(LoopCount::Finite(2));
+        assert_eq!(anim.frame_at(Duration::from_millis(250)), Some(MediaId::new(1)));
+        assert!(!anim.is_finished(Duration::from_millis(399)));
+        assert!(anim.is_finished(Duration::from_millis(400)));
+        assert_eq!(anim.frame_at(Duration::from_millis(450)), Some(MediaId::new(3)));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::common::hash::{hash_from_data, hash_from_string, Sha256Hash};
 use crate::common::media::{
-    DecodedMedia, Image, Media, MediaDecoderRegistry, MediaId, MediaImage, MediaSvg, MediaType, Svg,
+    AnimationFrame, DecodedAnimation, DecodedMedia, Image, ImageAnimation, Media, MediaDecoderRegistry, MediaId,
+    MediaImage, MediaSvg, MediaType, Svg,
 };
 use bytes::Bytes;
 use parking_lot::RwLock;
@@ -63,7 +64,9 @@ impl MediaStore {
             .expect("Failed to decode default svg")
         {
             DecodedMedia::Vector(tree) => Arc::new(Media::svg("gosub://default/svg", Svg::new(*tree))),
-            DecodedMedia::Raster(_) => unreachable!("default svg decoded as a raster image"),
+            DecodedMedia::Raster(_) | DecodedMedia::Animated(_) => {
+                unreachable!("default svg decoded as a raster image")
+            }
         };
 
         #[allow(clippy::expect_used)] // PANIC-SAFE: compiled-in asset, exercised by every pipeline test
@@ -73,6 +76,7 @@ impl MediaStore {
         {
             DecodedMedia::Raster(img) => Arc::new(Media::image("gosub://default/image", img)),
             DecodedMedia::Vector(_) => unreachable!("default image decoded as an svg"),
+            DecodedMedia::Animated(_) => unreachable!("default image decoded as an animation"),
         };
 
         let entries = HashMap::from([
@@ -126,21 +130,56 @@ impl MediaStore {
         MediaRequest::Pending
     }
 
+    /// The media already loaded for `src`, without starting a fetch when there is none. Lazy
+    /// images use this to show what is at hand while they are still far from the viewport.
+    pub fn cached(&self, src: &str) -> Option<MediaId> {
+        self.cache.read().get(&hash_from_string(src)).copied()
+    }
+
     /// Returns and clears the "background fetch completed" flag; `true` means the engine should
     /// re-lay-out the page to pick up the new media.
     pub fn take_completed(&self) -> bool {
         self.completed.swap(false, Ordering::Relaxed)
     }
 
-    /// Shared by the data, source and inline decode paths.
-    fn decode_media(&self, src: &str, mime: Option<&str>, data: &[u8]) -> anyhow::Result<Media> {
+    /// Shared by the data, source and inline decode paths. `media_id` is where the caller stores
+    /// the result; an animation's first frame is the media itself.
+    fn decode_media(&self, media_id: MediaId, src: &str, mime: Option<&str>, data: &[u8]) -> anyhow::Result<Media> {
         match self.decoders.decode(mime, data) {
             Ok(DecodedMedia::Raster(img)) => Ok(Media::image(src, img)),
+            Ok(DecodedMedia::Animated(animation)) => self.store_frames(media_id, src, animation),
             Ok(DecodedMedia::Vector(tree)) => Ok(Media::svg(src, Svg::new(*tree))),
             Err(e) => Err(anyhow::anyhow!("Failed to decode media from '{}': {}", src, e)),
         }
     }
 
+    /// Store every frame of `animation` after the first under an id of its own, and return the
+    /// animated image to store under `media_id`, which shows the first frame.
+    fn store_frames(&self, media_id: MediaId, src: &str, animation: DecodedAnimation) -> anyhow::Result<Media> {
+        let mut decoded = animation.frames.into_iter();
+        let first = decoded
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Animation from '{}' has no frames", src))?;
+        let mut frames = vec![AnimationFrame {
+            media_id,
+            delay: first.delay,
+        }];
+        let mut entries = self.entries.write();
+        for frame in decoded {
+            let frame_id = self.allocate_media_id();
+            entries.insert(frame_id, Arc::new(Media::image(src, frame.image)));
+            frames.push(AnimationFrame {
+                media_id: frame_id,
+                delay: frame.delay,
+            });
+        }
+        let animation = ImageAnimation {
+            frames,
+            loop_count: animation.loop_count,
+        };
+        Ok(Media::animated_image(src, first.image, animation))
+    }
+
     /// Loads `src` into the store, caching by src so repeat calls never reload. Fetch/decode
     /// failures cache the placeholder id, so a dead URL skips the network on later calls.
     pub fn load_media(&self, src: &str) -> anyhow::Result<MediaId> {
@@ -190,9 +229,9 @@ impl MediaStore {
             MediaType::Svg => Some("image/svg+xml"),
             MediaType::Image => None,
         };
-        let media = self.decode_media("gosub://data", mime, data)?;
-
         let media_id = self.allocate_media_id();
+        let media = self.decode_media(media_id, "gosub://data", mime, data)?;
+
         self.entries.write().insert(media_id, Arc::new(media));
         self.cache.write().insert(h, media_id);
 
@@ -223,18 +262,48 @@ impl MediaStore {
         Some(media_id)
     }
 
+    /// Store an image rendered by the engine itself (e.g. an iframe's nested document) under a
+    /// fresh id. Tile caching keys images by id, so a changed image must be inserted anew rather
+    /// than replacing an existing entry.
+    pub fn insert_image(&self, src: &str, image: Image) -> MediaId {
+        let media_id = self.allocate_media_id();
+        self.entries
+            .write()
+            .insert(media_id, Arc::new(Media::image(src, image)));
+        media_id
+    }
+
+    /// Drop `media_id`'s entry, e.g. an image superseded by [`Self::insert_image`], and the frames
+    /// of an animated image. Lookups of the id fall back to the default resource afterwards.
+    pub fn remove(&self, media_id: MediaId) {
+        let mut entries = self.entries.write();
+        if let Some(Media::Image(image)) = entries.remove(&media_id).as_deref() {
+            for frame in image.animation.iter().flat_map(|animation| &animation.frames) {
+                entries.remove(&frame.media_id);
+            }
+        }
+    }
+
+    /// The animation of image `media_id`, `None` for a still image or anything else.
+    pub fn animation(&self, media_id: MediaId) -> Option<ImageAnimation> {
+        match self.entries.read().get(&media_id).map(|media| &**media) {
+            Some(Media::Image(image)) => image.animation.clone(),
+            _ => None,
+        }
+    }
+
     fn load_media_from_source(&self, src: &str) -> anyhow::Result<MediaId> {
         log::debug!("Loading non-cached media from path: {}", src);
+        let media_id = self.allocate_media_id();
         // `data:` URIs carry the bytes inline - decode them directly instead of going to the network.
         let media = if let Some(rest) = src.strip_prefix("data:") {
             let (mime, bytes) = decode_data_uri(rest)?;
-            self.decode_media(src, mime.as_deref(), &bytes)?
+            self.decode_media(media_id, src, mime.as_deref(), &bytes)?
         } else {
             let (content_type, raw_data) = self.fetch_resource(src)?;
-            self.decode_media(src, content_type.as_deref(), &raw_data)?
+            self.decode_media(media_id, src, content_type.as_deref(), &raw_data)?
         };
 
-        let media_id = self.allocate_media_id();
         self.entries.write().insert(media_id, Arc::new(media));
 
         Ok(media_id)
@@ -424,6 +493,42 @@ mod tests {
         }
     }
 
+    /// An animated GIF keeps every frame: the first under its own id, the rest under ids of their
+    /// own that go away with it.
+    #[test]
+    fn animated_gif_stores_each_frame() {
+        use image::codecs::gif::{GifEncoder, Repeat};
+        use image::{Delay, Frame};
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder.set_repeat(Repeat::Infinite).expect("set repeat");
+            for red in [0u8, 255] {
+                let frame = Frame::from_parts(
+                    RgbaImage::from_pixel(3, 3, Rgba([red, 0, 0, 255])),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(100, 1),
+                );
+                encoder.encode_frame(frame).expect("encode frame");
+            }
+        }
+
+        let store = MediaStore::new();
+        let media_id = store
+            .load_media_from_data(MediaType::Image, &bytes)
+            .expect("animated gif loads");
+        let animation = store.animation(media_id).expect("kept as an animation");
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.frames[0].media_id, media_id);
+        let second = animation.frames[1].media_id;
+        assert_eq!(store.get_image(second).image.as_raw()[0], 255);
+
+        store.remove(media_id);
+        assert!(store.entries.read().get(&second).is_none(), "frames go with the image");
+    }
+
     /// SVG data must decode through `load_media_from_data` into a retained SVG (not the
     /// placeholder), so it can be re-rasterized at any size.
     #[test]
@@ -0,0 +1,246 @@
+//! Find-in-page: the matches of a query in the page's rendered text, the byte ranges of the text
+//! elements each one covers, which the painter overlays, and the box of a match to scroll to.
+//!
+//! Like selection offsets, ranges are bytes into [`ElementContextText::text`], so the search sees
+//! the text as rendered: `display: none` content has no text elements and `text-transform` has
+//! already been applied. A match may span the text elements of one block container but never runs
+//! on into the next block.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use gosub_interface::font_system::FontSystem;
+use parking_lot::Mutex;
+
+use crate::common::geo::Rect;
+use crate::layering::layer::LayerList;
+use crate::layouter::taffy::block_container;
+use crate::layouter::{ElementContext, LayoutElementId, LayoutTree};
+use crate::painter::shape_text_element;
+use crate::selection::{highlight_rects, text_elements};
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const NBSP: char = '\u{00A0}';
+
+/// The text ranges of one match, per text element in document order.
+type MatchRanges = Vec<(LayoutElementId, Range<usize>)>;
+
+/// Every match of a find query, and which of them is the active one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindMatches {
+    matches: Vec<MatchRanges>,
+    /// Index into `matches` of the match the host stepped to.
+    active: Option<usize>,
+    /// Per text element, its matched ranges and the index of the match each belongs to.
+    by_element: HashMap<LayoutElementId, Vec<(Range<usize>, usize)>>,
+}
+
+impl FindMatches {
+    /// The non-overlapping matches of `query` in `layout_tree`'s text, none of them active yet.
+    /// Case-insensitive search compares the lowercase of each character.
+    pub fn search(layout_tree: &LayoutTree, query: &str, case_sensitive: bool) -> Self {
+        let needle: Vec<char> = query
+            .chars()
+            .filter(|c| *c != SOFT_HYPHEN)
+            .map(|c| fold(c, case_sensitive))
+            .collect();
+        if needle.is_empty() {
+            return Self::default();
+        }
+
+        let doc = &layout_tree.render_tree.doc;
+        let elements: Vec<_> = text_elements(layout_tree)
+            .into_iter()
+            .map(|(id, ctx)| (id, ctx.text.as_str(), block_container(&**doc, ctx.node_id)))
+            .collect();
+        let mut matches = Vec::new();
+        for block in elements.chunk_by(|a, b| a.2 == b.2) {
+            let texts: Vec<_> = block.iter().map(|(id, text, _)| (*id, *text)).collect();
+            matches.extend(find_in_block(&texts, &needle, case_sensitive));
+        }
+        Self::from_matches(matches)
+    }
+
+    fn from_matches(matches: Vec<MatchRanges>) -> Self {
+        let mut by_element: HashMap<LayoutElementId, Vec<(Range<usize>, usize)>> = HashMap::new();
+        for (index, ranges) in matches.iter().enumerate() {
+            for (id, range) in ranges {
+                by_element.entry(*id).or_default().push((range.clone(), index));
+            }
+        }
+        Self {
+            matches,
+            active: None,
+            by_element,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Index of the active match, if the host has stepped to one.
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Makes the next match active, or the previous one when `forward` is false, wrapping around
+    /// the ends. Without an active match yet that is the first or the last one.
+    pub fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.active = Some(match (self.active, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        });
+    }
+
+    /// The matched ranges of `element`'s text, each flagged when it belongs to the active match.
+    pub fn ranges(&self, element: LayoutElementId) -> impl Iterator<Item = (Range<usize>, bool)> + '_ {
+        self.by_element
+            .get(&element)
+            .into_iter()
+            .flatten()
+            .map(|(range, index)| (range.clone(), Some(*index) == self.active))
+    }
+
+    /// The text elements with at least one match.
+    pub fn elements(&self) -> impl Iterator<Item = LayoutElementId> + '_ {
+        self.by_element.keys().copied()
+    }
+
+    /// The page-space box around the match at `index`, shaped with the font system the page was
+    /// painted with. Vertical text has no glyph boxes yet and yields its element's content box.
+    pub fn match_rect(
+        &self,
+        layer_list: &LayerList,
+        font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+        index: usize,
+    ) -> Option<Rect> {
+        let layout_tree = &layer_list.layout_tree;
+        let mut bounds: Option<(f64, f64, f64, f64)> = None;
+        for (id, range) in self.matches.get(index)? {
+            let Some(node) = layout_tree.get_node_by_id(*id) else {
+                continue;
+            };
+            let ElementContext::Text(ctx) = &node.context else {
+                continue;
+            };
+            let content_box = node.box_model.content_box;
+            let (shaped, _) = shape_text_element(font_system, ctx, content_box);
+            let mut rects = highlight_rects(&shaped, ctx.text.len(), range);
+            for rect in &mut rects {
+                rect.x += content_box.x;
+                rect.y += content_box.y;
+            }
+            if rects.is_empty() {
+                rects.push(content_box);
+            }
+            for r in rects {
+                let (x0, y0, x1, y1) = bounds.unwrap_or((r.x, r.y, r.x + r.width, r.y + r.height));
+                bounds = Some((x0.min(r.x), y0.min(r.y), x1.max(r.x + r.width), y1.max(r.y + r.height)));
+            }
+        }
+        bounds.map(|(x0, y0, x1, y1)| Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+}
+
+/// `c` as the search compares it: non-breaking spaces match spaces and, without
+/// `case_sensitive`, letters match their lowercase.
+fn fold(c: char, case_sensitive: bool) -> char {
+    let c = if c == NBSP { ' ' } else { c };
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// The non-overlapping matches of `needle` in the concatenated text of one block's elements.
+fn find_in_block(block: &[(LayoutElementId, &str)], needle: &[char], case_sensitive: bool) -> Vec<MatchRanges> {
+    // Every character the search sees, with the element and byte range it came from.
+    let chars: Vec<(char, LayoutElementId, Range<usize>)> = block
+        .iter()
+        .flat_map(|(id, text)| {
+            text.char_indices()
+                .filter(|(_, c)| *c != SOFT_HYPHEN)
+                .map(move |(i, c)| (fold(c, case_sensitive), *id, i..i + c.len_utf8()))
+        })
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + needle.len() <= chars.len() {
+        let candidate = &chars[start..start + needle.len()];
+        if !candidate.iter().zip(needle).all(|((c, _, _), n)| c == n) {
+            start += 1;
+            continue;
+        }
+        let mut ranges: MatchRanges = Vec::new();
+        for (_, id, range) in candidate {
+            match ranges.last_mut() {
+                Some((last, r)) if last == id => r.end = range.end,
+                _ => ranges.push((*id, range.clone())),
+            }
+        }
+        matches.push(ranges);
+        start += needle.len();
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: LayoutElementId = LayoutElementId::new(1);
+    const B: LayoutElementId = LayoutElementId::new(2);
+
+    fn needle(query: &str, case_sensitive: bool) -> Vec<char> {
+        query.chars().map(|c| fold(c, case_sensitive)).collect()
+    }
+
+    #[test]
+    fn matches_span_elements_of_a_block() {
+        let block = [(A, "Hello wor"), (B, "ld, world")];
+        let matches = find_in_block(&block, &needle("world", true), true);
+        assert_eq!(matches, vec![vec![(A, 6..9), (B, 0..2)], vec![(B, 4..9)]]);
+    }
+
+    #[test]
+    fn case_folding_and_invisible_characters() {
+        let block = [(A, "Stra\u{00AD}SSE\u{00A0}Nr")];
+        assert!(find_in_block(&block, &needle("strasse nr", true), true).is_empty());
+        let matches = find_in_block(&block, &needle("strasse nr", false), false);
+        assert_eq!(matches, vec![vec![(A, 0..13)]]);
+    }
+
+    #[test]
+    fn matches_do_not_overlap() {
+        let block = [(A, "aaaa")];
+        let matches = find_in_block(&block, &needle("aa", true), true);
+        assert_eq!(matches, vec![vec![(A, 0..2)], vec![(A, 2..4)]]);
+    }
+
+    #[test]
+    fn stepping_wraps_around() {
+        let mut found = FindMatches::from_matches(vec![vec![(A, 0..1)], vec![(A, 2..3)], vec![(B, 0..1)]]);
+        found.step(false);
+        assert_eq!(found.active(), Some(2));
+        found.step(true);
+        assert_eq!(found.active(), Some(0));
+        assert_eq!(found.ranges(A).collect::<Vec<_>>(), vec![(0..1, true), (2..3, false)]);
+        found.step(false);
+        assert_eq!(found.active(), Some(2));
+        assert_eq!(found.ranges(B).collect::<Vec<_>>(), vec![(0..1, true)]);
+    }
+}
@@ -0,0 +1,746 @@
+//! Responsive images: which URL an `<img>` loads, from its `srcset` and `sizes` or those of a
+//! `<source>` in its `<picture>`, for the viewport and device-pixel ratio it is shown at.
+//!
+//! Follows HTML's image source selection. A `<picture>`'s `<source>` children are tried in order
+//! and the first whose `media` matches and whose `type` can be decoded wins; without one the
+//! `<img>`'s own `srcset` and `src` apply. From the chosen set, the candidate with the smallest
+//! density that still covers the device-pixel ratio is used, else the densest one. Width (`w`)
+//! candidates get their density from the slot size `sizes` resolves to.
+
+use crate::common::document::node::{NodeId, NodeType};
+use crate::common::document::pipeline_doc::PipelineDocument;
+use cow_utils::CowUtils;
+
+/// Font size `em`/`rem`/`ch` lengths in `sizes` and media queries resolve against: the initial
+/// font size, as the spec requires.
+const FONT_SIZE: f64 = 16.0;
+
+/// What responsive image selection is evaluated against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageEnvironment {
+    /// Viewport size in CSS pixels.
+    pub viewport_width: f64,
+    pub viewport_height: f64,
+    pub device_pixel_ratio: f64,
+}
+
+impl Default for ImageEnvironment {
+    fn default() -> Self {
+        Self {
+            viewport_width: 1280.0,
+            viewport_height: 720.0,
+            device_pixel_ratio: 1.0,
+        }
+    }
+}
+
+/// The image an element loads: its URL as written (unresolved) and how many image pixels cover
+/// one CSS pixel, which divides the decoded size into the intrinsic size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageSource {
+    pub url: String,
+    pub density: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Descriptor {
+    /// `2x`
+    Density(f64),
+    /// `640w`
+    Width(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrcsetCandidate {
+    pub url: String,
+    pub descriptor: Descriptor,
+}
+
+/// The image `<img>` element `img` shows in `env`, or `None` when it names none.
+pub fn select_image_source(doc: &dyn PipelineDocument, img: NodeId, env: &ImageEnvironment) -> Option<ImageSource> {
+    let attr = |id: NodeId, name: &str| -> Option<String> {
+        match doc.get_node_by_id(id)?.node_type {
+            NodeType::Element(data) => data.get_attribute(name).cloned(),
+            _ => None,
+        }
+    };
+
+    let picture = doc
+        .parent(img)
+        .filter(|&p| doc.tag_name(p).is_some_and(|t| t.eq_ignore_ascii_case("picture")));
+    if let Some(picture) = picture {
+        for child in doc.children(picture) {
+            if child == img {
+                break;
+            }
+            if !doc.tag_name(child).is_some_and(|t| t.eq_ignore_ascii_case("source")) {
+                continue;
+            }
+            let Some(srcset) = attr(child, "srcset") else {
+                continue;
+            };
+            if attr(child, "media").is_some_and(|media| !media_matches(&media, env)) {
+                continue;
+            }
+            if attr(child, "type").is_some_and(|mime| !is_supported_type(&mime)) {
+                continue;
+            }
+            let candidates = parse_srcset(&srcset);
+            if candidates.is_empty() {
+                continue;
+            }
+            return choose(&candidates, attr(child, "sizes").as_deref(), env);
+        }
+    }
+
+    select_from_attributes(
+        attr(img, "srcset").as_deref(),
+        attr(img, "sizes").as_deref(),
+        attr(img, "src").as_deref(),
+        env,
+    )
+}
+
+/// The image an `<img>` outside a `<picture>` shows, from its `srcset`, `sizes` and `src`
+/// attributes. Also what the preload scanner fetches, which sees attributes but no tree.
+pub fn select_from_attributes(
+    srcset: Option<&str>,
+    sizes: Option<&str>,
+    src: Option<&str>,
+    env: &ImageEnvironment,
+) -> Option<ImageSource> {
+    let mut candidates = srcset.map(parse_srcset).unwrap_or_default();
+    // `src` is the 1x candidate, unless `srcset` has one or sizes its images by width
+    let has_1x_or_width = candidates.iter().any(|c| match c.descriptor {
+        Descriptor::Density(d) => d == 1.0,
+        Descriptor::Width(_) => true,
+    });
+    if let Some(src) = src.map(str::trim).filter(|s| !s.is_empty()) {
+        if !has_1x_or_width {
+            candidates.push(SrcsetCandidate {
+                url: src.to_string(),
+                descriptor: Descriptor::Density(1.0),
+            });
+        }
+    }
+    choose(&candidates, sizes, env)
+}
+
+/// Picks the candidate to load: the lowest density at or above the device-pixel ratio, else the
+/// highest.
+fn choose(candidates: &[SrcsetCandidate], sizes: Option<&str>, env: &ImageEnvironment) -> Option<ImageSource> {
+    let slot = source_size(sizes.unwrap_or(""), env);
+    let mut sources: Vec<ImageSource> = candidates
+        .iter()
+        .map(|c| ImageSource {
+            url: c.url.clone(),
+            density: match c.descriptor {
+                Descriptor::Density(d) => d,
+                Descriptor::Width(w) if slot > 0.0 => w as f64 / slot,
+                Descriptor::Width(_) => f64::INFINITY,
+            },
+        })
+        .filter(|s| s.density.is_finite() && s.density > 0.0)
+        .collect();
+    sources.sort_by(|a, b| a.density.total_cmp(&b.density));
+    let index = sources
+        .iter()
+        .position(|s| s.density >= env.device_pixel_ratio)
+        .unwrap_or(sources.len().saturating_sub(1));
+    (index < sources.len()).then(|| sources.swap_remove(index))
+}
+
+/// Whether `<source type>` names a format the media store decodes.
+fn is_supported_type(mime: &str) -> bool {
+    let mime = mime.split(';').next().unwrap_or(mime).trim().cow_to_ascii_lowercase();
+    match mime.as_ref() {
+        "image/png" | "image/apng" | "image/jpeg" | "image/jpg" | "image/gif" | "image/webp" | "image/svg+xml" => true,
+        "image/avif" => cfg!(feature = "avif"),
+        _ => false,
+    }
+}
+
+/// Parses a `srcset` attribute into its candidates. Candidates with invalid or conflicting
+/// descriptors are dropped, as browsers do; one without descriptors is `1x`.
+pub fn parse_srcset(input: &str) -> Vec<SrcsetCandidate> {
+    let mut out = Vec::new();
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return out;
+        }
+        let url_end = rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len());
+        let mut url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // A URL ending in commas ends its candidate; otherwise descriptors run to the next comma
+        // outside parentheses
+        let descriptors = if url.ends_with(',') {
+            url = url.trim_end_matches(',');
+            ""
+        } else {
+            let mut depth = 0usize;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| match c {
+                    '(' => {
+                        depth += 1;
+                        false
+                    }
+                    ')' => {
+                        depth = depth.saturating_sub(1);
+                        false
+                    }
+                    ',' => depth == 0,
+                    _ => false,
+                })
+                .map_or(rest.len(), |(i, _)| i);
+            let descriptors = &rest[..end];
+            rest = &rest[end..];
+            descriptors
+        };
+        if url.is_empty() {
+            continue;
+        }
+        if let Some(descriptor) = parse_descriptors(descriptors) {
+            out.push(SrcsetCandidate {
+                url: url.to_string(),
+                descriptor,
+            });
+        }
+    }
+}
+
+fn parse_descriptors(input: &str) -> Option<Descriptor> {
+    let (mut density, mut width, mut height) = (None, None, None);
+    for token in input.split_ascii_whitespace() {
+        let (value, kind) = token.split_at(token.len() - token.chars().last()?.len_utf8());
+        match kind {
+            "x" if density.is_none() && width.is_none() => {
+                density = Some(value.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0)?)
+            }
+            "w" if density.is_none() && width.is_none() => width = Some(value.parse::<u32>().ok().filter(|w| *w > 0)?),
+            // Only meaningful next to a width; kept for validation but otherwise unused
+            "h" if height.is_none() => height = Some(value.parse::<u32>().ok().filter(|h| *h > 0)?),
+            _ => return None,
+        }
+    }
+    match (density, width, height) {
+        (_, None, Some(_)) => None,
+        (Some(d), None, None) => Some(Descriptor::Density(d)),
+        (None, Some(w), _) => Some(Descriptor::Width(w)),
+        _ => Some(Descriptor::Density(1.0)),
+    }
+}
+
+/// The slot width in CSS pixels a `sizes` attribute resolves to: the length of its first entry
+/// whose media condition matches. Without a valid matching entry, the viewport width.
+pub fn source_size(sizes: &str, env: &ImageEnvironment) -> f64 {
+    for entry in split_top_level(sizes, ',') {
+        let entry = entry.trim();
+        // The size is the last component; what precedes it is the media condition
+        let split = last_component_start(entry);
+        let (condition, length) = entry.split_at(split);
+        let Some(size) = parse_length(length.trim(), env).filter(|s| *s >= 0.0) else {
+            continue;
+        };
+        let condition = condition.trim();
+        if condition.is_empty() || media_matches(condition, env) {
+            return size;
+        }
+    }
+    env.viewport_width
+}
+
+/// Where the last whitespace-separated component of `s` starts, parenthesised groups counting as
+/// one component.
+fn last_component_start(s: &str) -> usize {
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c.is_ascii_whitespace() && depth == 0 => start = i + 1,
+            _ => {}
+        }
+    }
+    start
+}
+
+/// Splits `s` on `separator` where it is not inside parentheses.
+fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A CSS length in pixels: an absolute, font- or viewport-relative length, a unitless 0, or a
+/// `calc()` of those. `None` for anything else, percentages included.
+fn parse_length(s: &str, env: &ImageEnvironment) -> Option<f64> {
+    let s = s.trim();
+    if let Some(inner) = s
+        .strip_prefix("calc(")
+        .or_else(|| s.strip_prefix("CALC("))
+        .and_then(|s| s.strip_suffix(')'))
+    {
+        return calc_sum(inner, env);
+    }
+    if s == "0" {
+        return Some(0.0);
+    }
+    let unit_start = s.find(|c: char| c.is_ascii_alphabetic()).filter(|&i| i > 0)?;
+    let value: f64 = s[..unit_start].parse().ok()?;
+    let unit = s[unit_start..].cow_to_ascii_lowercase();
+    let (vw, vh) = (env.viewport_width / 100.0, env.viewport_height / 100.0);
+    let px = match unit.as_ref() {
+        "px" => 1.0,
+        "em" | "rem" => FONT_SIZE,
+        "ch" | "ex" => FONT_SIZE / 2.0,
+        "vw" => vw,
+        "vh" => vh,
+        "vmin" => vw.min(vh),
+        "vmax" => vw.max(vh),
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "in" => 96.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+    Some(value * px)
+}
+
+/// A `calc()` body: lengths joined by ` + ` and ` - `, each optionally scaled by `* n` or `/ n`.
+fn calc_sum(s: &str, env: &ImageEnvironment) -> Option<f64> {
+    let mut total = 0.0;
+    let mut sign = 1.0;
+    let mut term = String::new();
+    let mut depth = 0usize;
+    for token in s.split_ascii_whitespace() {
+        depth += token.matches('(').count();
+        depth = depth.saturating_sub(token.matches(')').count());
+        if depth == 0 && (token == "+" || token == "-") {
+            total += sign * calc_product(&term, env)?;
+            sign = if token == "+" { 1.0 } else { -1.0 };
+            term.clear();
+        } else {
+            term.push_str(token);
+            term.push(' ');
+        }
+    }
+    Some(total + sign * calc_product(&term, env)?)
+}
+
+fn calc_product(s: &str, env: &ImageEnvironment) -> Option<f64> {
+    // Operands with the operator before them, split where `*` and `/` are outside parentheses
+    let mut factors = Vec::new();
+    let (mut depth, mut start, mut op) = (0usize, 0, '*');
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '*' | '/' if depth == 0 => {
+                factors.push((op, &s[start..i]));
+                (start, op) = (i + 1, c);
+            }
+            _ => {}
+        }
+    }
+    factors.push((op, &s[start..]));
+
+    let mut length = None;
+    let mut scale = 1.0;
+    for (op, operand) in factors {
+        let operand = operand.trim();
+        match operand.parse::<f64>() {
+            Ok(n) if op == '/' => scale /= n,
+            Ok(n) => scale *= n,
+            Err(_) if op == '/' || length.is_some() => return None,
+            Err(_) => {
+                length = Some(match operand.strip_prefix('(').and_then(|o| o.strip_suffix(')')) {
+                    Some(inner) => calc_sum(inner, env)?,
+                    None => parse_length(operand, env)?,
+                })
+            }
+        }
+    }
+    let value = length? * scale;
+    value.is_finite().then_some(value)
+}
+
+/// Whether a media query list (`media` of a `<source>`, or a `sizes` condition) matches `env`.
+/// Covers media types, `not`/`only`, `and`/`or`, and the viewport size, orientation, aspect
+/// ratio and resolution features in both the `min-`/`max-` and the range form. Unknown features
+/// don't match.
+pub fn media_matches(query_list: &str, env: &ImageEnvironment) -> bool {
+    let query_list = query_list.trim();
+    query_list.is_empty()
+        || split_top_level(query_list, ',')
+            .into_iter()
+            .any(|query| media_query(query, env).unwrap_or(false))
+}
+
+/// Words and parenthesised groups of `s`, in order.
+fn tokens(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut start) = (0usize, None);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    if let Some(st) = start.take() {
+                        out.push(&s[st..i]);
+                    }
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(st) = start.take() {
+                        out.push(&s[st..=i]);
+                    }
+                }
+            }
+            c if c.is_ascii_whitespace() && depth == 0 => {
+                if let Some(st) = start.take() {
+                    out.push(&s[st..i]);
+                }
+            }
+            _ => {
+                if start.is_none() {
+                    start = Some(i);
+                }
+            }
+        }
+    }
+    if let Some(st) = start {
+        out.push(&s[st..]);
+    }
+    out
+}
+
+fn media_query(query: &str, env: &ImageEnvironment) -> Option<bool> {
+    let tokens = tokens(query);
+    let is_group = |t: &str| t.starts_with('(');
+    let mut rest = tokens.as_slice();
+    let mut negate = false;
+    match rest.first().map(|t| t.cow_to_ascii_lowercase()) {
+        Some(t) if t == "only" => rest = &rest[1..],
+        Some(t) if t == "not" && rest.get(1).is_some_and(|t| !is_group(t)) => {
+            negate = true;
+            rest = &rest[1..];
+        }
+        _ => {}
+    }
+    let first = rest.first()?;
+    let matched = if is_group(first) || first.eq_ignore_ascii_case("not") {
+        media_condition(rest, env)?
+    } else {
+        let type_matches = match first.cow_to_ascii_lowercase().as_ref() {
+            "all" | "screen" => true,
+            "print" | "tv" | "speech" | "handheld" | "projection" | "tty" | "braille" | "embossed" | "aural" => false,
+            _ => return None,
+        };
+        let mut conditions = true;
+        for pair in rest[1..].chunks(2) {
+            match pair {
+                [and, group] if and.eq_ignore_ascii_case("and") => conditions &= in_parens(group, env)?,
+                _ => return None,
+            }
+        }
+        type_matches && conditions
+    };
+    Some(matched != negate)
+}
+
+/// `not (…)`, or groups all joined by `and` or all by `or`.
+fn media_condition(tokens: &[&str], env: &ImageEnvironment) -> Option<bool> {
+    match tokens {
+        [not, group] if not.eq_ignore_ascii_case("not") => Some(!in_parens(group, env)?),
+        [first, rest @ ..] => {
+            let mut result = in_parens(first, env)?;
+            let mut joiner: Option<String> = None;
+            for pair in rest.chunks(2) {
+                let [op, group] = pair else {
+                    return None;
+                };
+                let op = op.cow_to_ascii_lowercase().into_owned();
+                if joiner.as_ref().is_some_and(|j| *j != op) {
+                    return None;
+                }
+                let value = in_parens(group, env)?;
+                result = match op.as_str() {
+                    "and" => result && value,
+                    "or" => result || value,
+                    _ => return None,
+                };
+                joiner = Some(op);
+            }
+            Some(result)
+        }
+        [] => None,
+    }
+}
+
+/// A parenthesised group: a nested condition or a media feature.
+fn in_parens(group: &str, env: &ImageEnvironment) -> Option<bool> {
+    let inner = group.strip_prefix('(')?.strip_suffix(')')?.trim();
+    let nested = tokens(inner);
+    if nested
+        .first()
+        .is_some_and(|t| t.starts_with('(') || t.eq_ignore_ascii_case("not"))
+    {
+        return media_condition(&nested, env);
+    }
+    media_feature(inner, env)
+}
+
+#[derive(Clone, Copy)]
+enum Feature {
+    Width,
+    Height,
+    AspectRatio,
+    Resolution,
+}
+
+impl Feature {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "width" => Some(Feature::Width),
+            "height" => Some(Feature::Height),
+            "aspect-ratio" => Some(Feature::AspectRatio),
+            "resolution" | "-webkit-device-pixel-ratio" => Some(Feature::Resolution),
+            _ => None,
+        }
+    }
+
+    fn value(self, env: &ImageEnvironment) -> f64 {
+        match self {
+            Feature::Width => env.viewport_width,
+            Feature::Height => env.viewport_height,
+            Feature::AspectRatio => env.viewport_width / env.viewport_height.max(1.0),
+            Feature::Resolution => env.device_pixel_ratio,
+        }
+    }
+
+    fn parse_value(self, value: &str, env: &ImageEnvironment) -> Option<f64> {
+        match self {
+            Feature::Width | Feature::Height => parse_length(value, env),
+            Feature::AspectRatio => match value.split_once('/') {
+                Some((w, h)) => Some(w.trim().parse::<f64>().ok()? / h.trim().parse::<f64>().ok()?),
+                None => value.parse().ok(),
+            },
+            Feature::Resolution => {
+                let value = value.cow_to_ascii_lowercase();
+                if let Some(n) = value.strip_suffix("dppx").or_else(|| value.strip_suffix('x')) {
+                    n.parse().ok()
+                } else if let Some(n) = value.strip_suffix("dpi") {
+                    n.parse::<f64>().ok().map(|dpi| dpi / 96.0)
+                } else if let Some(n) = value.strip_suffix("dpcm") {
+                    n.parse::<f64>().ok().map(|dpcm| dpcm * 2.54 / 96.0)
+                } else {
+                    // The -webkit- ratio is a bare number
+                    value.parse().ok()
+                }
+            }
+        }
+    }
+}
+
+fn media_feature(inner: &str, env: &ImageEnvironment) -> Option<bool> {
+    if let Some((name, value)) = inner.split_once(':') {
+        let name = name.trim().cow_to_ascii_lowercase();
+        let value = value.trim();
+        let (prefix, base) = match name.strip_prefix("min-").or_else(|| name.strip_prefix("-webkit-min-")) {
+            Some(base) => (Some(true), base),
+            None => match name.strip_prefix("max-").or_else(|| name.strip_prefix("-webkit-max-")) {
+                Some(base) => (Some(false), base),
+                None => (None, name.as_ref()),
+            },
+        };
+        let base = if base == "device-pixel-ratio" {
+            "-webkit-device-pixel-ratio"
+        } else {
+            base
+        };
+        if let Some(feature) = Feature::parse(base) {
+            let actual = feature.value(env);
+            let wanted = feature.parse_value(value, env)?;
+            return Some(match prefix {
+                Some(true) => actual >= wanted,
+                Some(false) => actual <= wanted,
+                None => (actual - wanted).abs() < 1e-6,
+            });
+        }
+        let value = value.cow_to_ascii_lowercase();
+        return match (base, value.as_ref()) {
+            ("orientation", "portrait") => Some(env.viewport_height >= env.viewport_width),
+            ("orientation", "landscape") => Some(env.viewport_width > env.viewport_height),
+            ("prefers-color-scheme", scheme) => Some(scheme == "light"),
+            ("prefers-reduced-motion", motion) => Some(motion == "no-preference"),
+            ("hover" | "any-hover", hover) => Some(hover == "hover"),
+            ("pointer" | "any-pointer", pointer) => Some(pointer == "fine"),
+            _ => None,
+        };
+    }
+    if inner.contains(['<', '>', '=']) {
+        return range_feature(inner, env);
+    }
+    // A boolean feature: true when it isn't zero or none
+    match inner.cow_to_ascii_lowercase().as_ref() {
+        "width" | "height" | "color" | "hover" | "pointer" | "orientation" | "resolution" => Some(true),
+        "grid" | "monochrome" => Some(false),
+        _ => None,
+    }
+}
+
+/// The range form, `(width >= 600px)` or `(400px < width <= 700px)`.
+fn range_feature(inner: &str, env: &ImageEnvironment) -> Option<bool> {
+    let mut parts = Vec::new();
+    let mut rest = inner;
+    while let Some(at) = rest.find(['<', '>', '=']) {
+        parts.push(rest[..at].trim());
+        let op_len = if rest[at + 1..].starts_with('=') { 2 } else { 1 };
+        parts.push(&rest[at..at + op_len]);
+        rest = &rest[at + op_len..];
+    }
+    parts.push(rest.trim());
+
+    let compare = |a: f64, op: &str, b: f64| match op {
+        "<" => Some(a < b),
+        "<=" => Some(a <= b),
+        ">" => Some(a > b),
+        ">=" => Some(a >= b),
+        "=" => Some((a - b).abs() < 1e-6),
+        _ => None,
+    };
+    let feature_at = |i: usize| Feature::parse(parts[i].cow_to_ascii_lowercase().as_ref());
+    match parts.as_slice() {
+        [_, op, _] => match (feature_at(0), feature_at(2)) {
+            (Some(f), None) => compare(f.value(env), op, f.parse_value(parts[2], env)?),
+            (None, Some(f)) => compare(f.parse_value(parts[0], env)?, op, f.value(env)),
+            _ => None,
+        },
+        [low, op1, _, op2, high] => {
+            let f = feature_at(2)?;
+            let actual = f.value(env);
+            Some(compare(f.parse_value(low, env)?, op1, actual)? && compare(actual, op2, f.parse_value(high, env)?)?)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(width: f64, dpr: f64) -> ImageEnvironment {
+        ImageEnvironment {
+            viewport_width: width,
+            viewport_height: 800.0,
+            device_pixel_ratio: dpr,
+        }
+    }
+
+    fn candidate(url: &str, descriptor: Descriptor) -> SrcsetCandidate {
+        SrcsetCandidate {
+            url: url.to_string(),
+            descriptor,
+        }
+    }
+
+    #[test]
+    fn parses_srcset_candidates() {
+        assert_eq!(
+            parse_srcset("a.png, b.png 2x,c.png 1.5x ,d.png 640w 480h"),
+            [
+                candidate("a.png", Descriptor::Density(1.0)),
+                candidate("b.png", Descriptor::Density(2.0)),
+                candidate("c.png", Descriptor::Density(1.5)),
+                candidate("d.png", Descriptor::Width(640)),
+            ]
+        );
+        // Commas inside a URL stay in it; a trailing comma ends the candidate
+        assert_eq!(
+            parse_srcset("data:image/png;base64,AAA 2x, e.png,"),
+            [
+                candidate("data:image/png;base64,AAA", Descriptor::Density(2.0)),
+                candidate("e.png", Descriptor::Density(1.0)),
+            ]
+        );
+        // Invalid and conflicting descriptors drop the candidate
+        assert_eq!(
+            parse_srcset("f.png 2x 100w, g.png 0w, h.png 480h, i.png 3x"),
+            [candidate("i.png", Descriptor::Density(3.0))]
+        );
+    }
+
+    #[test]
+    fn sizes_pick_the_first_matching_entry() {
+        let sizes = "(max-width: 600px) 100vw, (min-width: 601px) and (max-width: 1000px) calc(50vw - 2em), 400px";
+        assert_eq!(source_size(sizes, &env(500.0, 1.0)), 500.0);
+        assert_eq!(source_size(sizes, &env(800.0, 1.0)), 368.0);
+        assert_eq!(source_size(sizes, &env(1200.0, 1.0)), 400.0);
+        assert_eq!(
+            source_size("", &env(900.0, 1.0)),
+            900.0,
+            "no sizes means the viewport width"
+        );
+        assert_eq!(
+            source_size("50%, 30em", &env(900.0, 1.0)),
+            480.0,
+            "invalid entries are skipped"
+        );
+        assert_eq!(source_size("calc((100vw - 40px) / 2)", &env(840.0, 1.0)), 400.0);
+        assert_eq!(source_size("calc(2 * (10px * 3) + 1em)", &env(840.0, 1.0)), 76.0);
+    }
+
+    #[test]
+    fn evaluates_media_queries() {
+        let e = env(800.0, 2.0);
+        assert!(media_matches("(min-width: 640px)", &e));
+        assert!(!media_matches("(min-width: 1024px)", &e));
+        // A square viewport is portrait.
+        assert!(media_matches("screen and (orientation: portrait)", &e));
+        assert!(media_matches("screen and (orientation: landscape)", &env(1000.0, 2.0)));
+        assert!(!media_matches("print", &e));
+        assert!(media_matches("print, (max-width: 800px)", &e));
+        assert!(media_matches("not print", &e));
+        assert!(media_matches("(min-resolution: 2dppx)", &e));
+        assert!(media_matches("(-webkit-min-device-pixel-ratio: 1.5)", &e));
+        assert!(media_matches("(400px <= width < 900px)", &e));
+        assert!(!media_matches("(width > 800px)", &e));
+        assert!(media_matches("not ((min-width: 1000px) or (max-width: 100px))", &e));
+        assert!(!media_matches("(unknown-feature: 1)", &e));
+    }
+
+    #[test]
+    fn chooses_the_smallest_density_covering_the_dpr() {
+        let candidates = parse_srcset("s.png 320w, m.png 640w, l.png 1280w");
+        let pick = |width, dpr| choose(&candidates, Some("50vw"), &env(width, dpr)).map(|s| s.url);
+        assert_eq!(pick(640.0, 1.0).as_deref(), Some("s.png"));
+        assert_eq!(pick(640.0, 2.0).as_deref(), Some("m.png"));
+        assert_eq!(pick(1280.0, 2.0).as_deref(), Some("l.png"));
+        assert_eq!(pick(4000.0, 2.0).as_deref(), Some("l.png"), "else the densest");
+
+        let x = parse_srcset("one.png, two.png 2x");
+        let two = choose(&x, None, &env(800.0, 2.0)).expect("a candidate");
+        assert_eq!((two.url.as_str(), two.density), ("two.png", 2.0));
+    }
+}
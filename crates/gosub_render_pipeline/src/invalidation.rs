@@ -0,0 +1,464 @@
+//! Incremental invalidation: from the DOM mutations a script or the engine made to which nodes
+//! need their styles recomputed, how far the new styles reach down the pipeline, and which
+//! page areas must be repainted.
+//!
+//! The selector side comes from the CSS system's [`InvalidationSets`]: a changed class, id or
+//! attribute restyles nothing when no selector tests it, the element when only compound
+//! selectors do, and more when it sits left of a combinator. What the restyle then costs is
+//! graded as [`Damage`] by comparing each restyled node's styles before and after.
+
+use std::collections::{HashMap, HashSet};
+
+use cow_utils::CowUtils;
+use gosub_interface::config::HasDocument;
+use gosub_interface::css3::{InvalidationScope, InvalidationSets};
+use gosub_interface::document::{Document, DomMutation};
+use gosub_shared::node::NodeId;
+
+use crate::common::document::pipeline_doc::{pseudo_owner, PipelineDocument};
+use crate::common::document::style::StyleProperty;
+use crate::common::geo::Rect;
+use crate::layering::layer::LayerList;
+use crate::layouter::{LayoutElementId, LayoutElementNode};
+use crate::painter::commands::filter::filters_overflow;
+use crate::painter::commands::shadow::shadows_overflow;
+use crate::painter::commands::Trbl;
+
+/// How far down the pipeline a change has to be redone. Ordered, so the damage of several
+/// changes is their maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Damage {
+    /// Nothing visible changed.
+    None,
+    /// Boxes stay put; the restyled elements are painted again.
+    Paint,
+    /// Box geometry changed, but not the shape of the layout tree: the existing tree can be laid
+    /// out again in place.
+    Layout,
+    /// The layout tree itself changes (display, fonts, text, generated content, added or removed
+    /// nodes, new layers) and is built again.
+    Rebuild,
+}
+
+/// Which nodes need their styles recomputed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restyle {
+    /// These nodes, each once, parents before children.
+    Nodes(Vec<NodeId>),
+    /// Every node: a stylesheet changed, or a changed selector can match anywhere.
+    All,
+}
+
+/// What a batch of mutations invalidates, before looking at the new styles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invalidation {
+    pub restyle: Restyle,
+    /// Damage the mutations cause regardless of styles: new text, new nodes, or attributes the
+    /// layouter reads itself (`src`, `width`, `colspan`, ...).
+    pub damage: Damage,
+}
+
+/// Works out which nodes of `doc` the `mutations` (made to it since the last layout) restyle.
+pub fn invalidate<C: HasDocument>(doc: &C::Document, sets: &InvalidationSets, mutations: &[DomMutation]) -> Invalidation {
+    let mut damage = Damage::None;
+    let mut roots: Vec<(NodeId, InvalidationScope)> = Vec::new();
+    let mut all = false;
+
+    for mutation in mutations {
+        match mutation {
+            DomMutation::Attribute { node, name, old_value } => {
+                let name = name.cow_to_lowercase();
+                let old_value = old_value.as_deref();
+                let new_value = doc.attribute(*node, &name);
+                let scope = match &*name {
+                    "class" => changed_classes(old_value, new_value)
+                        .into_iter()
+                        .filter_map(|class| sets.class_scope(class))
+                        .max()
+                        .max(sets.attribute_scope("class").filter(|_| old_value != new_value)),
+                    "id" => [old_value, new_value]
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|id| sets.id_scope(id))
+                        .max()
+                        .max(sets.attribute_scope("id").filter(|_| old_value != new_value)),
+                    "style" => Some(InvalidationScope::Element).max(sets.attribute_scope("style")),
+                    _ => sets.attribute_scope(&name),
+                };
+                if !is_inert_attribute(&name) {
+                    damage = Damage::Rebuild;
+                }
+                roots.extend(scope.map(|scope| (*node, scope)));
+            }
+            DomMutation::Class { node, name } => roots.extend(sets.class_scope(name).map(|scope| (*node, scope))),
+            DomMutation::Text { node } => {
+                damage = Damage::Rebuild;
+                // `:empty` and `:has()` look at text; the parent restyle covers the text node.
+                roots.extend(doc.parent(*node).map(|parent| (parent, relational(sets, InvalidationScope::Element))));
+            }
+            DomMutation::ChildList { parent } => {
+                damage = Damage::Rebuild;
+                // Structural pseudo-classes and sibling combinators reach the parent's other children.
+                roots.push((*parent, relational(sets, InvalidationScope::Subtree)));
+            }
+            DomMutation::Stylesheets => {
+                damage = Damage::Rebuild;
+                all = true;
+            }
+        }
+    }
+
+    if all || roots.iter().any(|(_, scope)| *scope == InvalidationScope::Document) {
+        return Invalidation {
+            restyle: Restyle::All,
+            damage,
+        };
+    }
+
+    let mut seen = HashSet::new();
+    let mut nodes = Vec::new();
+    for (node, scope) in roots {
+        let root = match scope {
+            // `var()` and `inherit` resolve against the parent's declarations, which the cached
+            // styles of the descendants hold on to, so an element restyle takes its subtree along.
+            InvalidationScope::Element | InvalidationScope::Subtree => node,
+            InvalidationScope::Siblings => doc.parent(node).unwrap_or(node),
+            InvalidationScope::Document => continue,
+        };
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            nodes.push(id);
+            stack.extend(doc.children(id).iter().rev());
+        }
+    }
+
+    Invalidation {
+        restyle: Restyle::Nodes(nodes),
+        damage,
+    }
+}
+
+/// `scope`, or the whole document when a `:has()` selector can look at the change from above.
+fn relational(sets: &InvalidationSets, scope: InvalidationScope) -> InvalidationScope {
+    if sets.relational {
+        InvalidationScope::Document
+    } else {
+        scope
+    }
+}
+
+/// Class names in exactly one of the two `class` attribute values.
+fn changed_classes<'a>(old: Option<&'a str>, new: Option<&'a str>) -> Vec<&'a str> {
+    let old: HashSet<&str> = old.unwrap_or_default().split_ascii_whitespace().collect();
+    let new: HashSet<&str> = new.unwrap_or_default().split_ascii_whitespace().collect();
+    old.symmetric_difference(&new).copied().collect()
+}
+
+/// Attributes the pipeline never reads itself, so a change to one only matters through the
+/// selectors testing it (and the `style` attribute through the cascade).
+fn is_inert_attribute(name: &str) -> bool {
+    const INERT: &[&str] = &[
+        "class",
+        "id",
+        "style",
+        "title",
+        "target",
+        "rel",
+        "role",
+        "tabindex",
+        "name",
+        "accesskey",
+        "draggable",
+        "spellcheck",
+        "translate",
+        "download",
+        "hreflang",
+        "referrerpolicy",
+    ];
+    INERT.contains(&name) || name.starts_with("data-") || name.starts_with("aria-") || name.starts_with("on")
+}
+
+/// The damage a change to `prop` on one element can do.
+pub fn property_damage(prop: &StyleProperty) -> Damage {
+    use StyleProperty::*;
+
+    match prop {
+        // Only read when the document is split into pages for print, which lays it out afresh.
+        BreakBefore | BreakAfter | BreakInside => Damage::None,
+
+        Color
+        | BackgroundColor
+        | BorderBottomColor
+        | BorderTopColor
+        | BorderLeftColor
+        | BorderRightColor
+        | BorderBottomLeftRadius
+        | BorderBottomRightRadius
+        | BorderTopLeftRadius
+        | BorderTopRightRadius
+        | BoxShadow
+        | TextShadow
+        | BackgroundImage
+        | ColumnRuleColor
+        | TransitionProperty
+        | TransitionDuration
+        | TransitionTimingFunction
+        | TransitionDelay
+        | AnimationName
+        | AnimationDuration
+        | AnimationTimingFunction
+        | AnimationDelay
+        | AnimationIterationCount
+        | AnimationDirection
+        | AnimationFillMode
+        | AnimationPlayState => Damage::Paint,
+
+        Width
+        | Height
+        | MinWidth
+        | MinHeight
+        | MaxWidth
+        | MaxHeight
+        | MarginTop
+        | MarginRight
+        | MarginBottom
+        | MarginLeft
+        | PaddingTop
+        | PaddingRight
+        | PaddingBottom
+        | PaddingLeft
+        | BorderBottomWidth
+        | BorderTopWidth
+        | BorderLeftWidth
+        | BorderRightWidth
+        | BorderTopStyle
+        | BorderRightStyle
+        | BorderBottomStyle
+        | BorderLeftStyle
+        | FlexBasis
+        | FlexDirection
+        | FlexGrow
+        | FlexShrink
+        | FlexWrap
+        | ScrollbarWidth
+        | AspectRatio
+        | Gap
+        | AlignItems
+        | AlignSelf
+        | AlignContent
+        | JustifyItems
+        | JustifySelf
+        | JustifyContent
+        | InsetBlockEnd
+        | InsetBlockStart
+        | InsetInlineEnd
+        | InsetInlineStart
+        | OverflowX
+        | OverflowY
+        | BoxSizing
+        | GridRow
+        | GridColumn
+        | GridAutoFlow
+        | GridTemplateRows
+        | GridTemplateColumns
+        | GridAutoRows
+        | GridAutoColumns => Damage::Layout,
+
+        // Taken into the layout tree itself (text runs, line boxes, markers, columns) or into the
+        // layers built from it.
+        Display
+        | Position
+        | ZIndex
+        | Opacity
+        | Transform
+        | Filter
+        | MixBlendMode
+        | Isolation
+        | FontSize
+        | FontWeight
+        | FontFamily
+        | FontStyle
+        | FontFeatureSettings
+        | FontVariantLigatures
+        | FontVariantCaps
+        | FontVariantNumeric
+        | FontVariantPosition
+        | FontVariantEastAsian
+        | FontVariantAlternates
+        | FontVariationSettings
+        | FontKerning
+        | FontOpticalSizing
+        | LineHeight
+        | TextWrap
+        | WhiteSpace
+        | TextDecorationLine
+        | Content
+        | TextTransform
+        | LetterSpacing
+        | ListStyleType
+        | ListStylePosition
+        | ListStyleImage
+        | CounterReset
+        | CounterIncrement
+        | CounterSet
+        | ColumnCount
+        | ColumnWidth
+        | ColumnGap
+        | ColumnRuleWidth
+        | ColumnRuleStyle
+        | ColumnSpan
+        | ColumnFill
+        | TextAlign
+        | Direction
+        | UnicodeBidi
+        | WritingMode
+        | TextOrientation
+        | WordBreak
+        | OverflowWrap
+        | Hyphens
+        | TextOverflow
+        | LineClamp => Damage::Rebuild,
+    }
+}
+
+/// The damage of restyling `nodes`, comparing their styles in `old` with those in `new`, the
+/// same document before and after the mutations.
+pub fn style_damage(old: &dyn PipelineDocument, new: &dyn PipelineDocument, nodes: &[NodeId]) -> Damage {
+    let mut damage = Damage::None;
+    for &id in nodes {
+        damage = damage.max(node_damage(old, new, id));
+        if damage == Damage::Rebuild {
+            break;
+        }
+    }
+    damage
+}
+
+fn node_damage(old: &dyn PipelineDocument, new: &dyn PipelineDocument, id: NodeId) -> Damage {
+    let children = new.children(id);
+    // Generated boxes appeared or went away.
+    if old.children(id) != children {
+        return Damage::Rebuild;
+    }
+    let mut damage = StyleProperty::all()
+        .filter(|prop| old.get_own_base_style(id, prop) != new.get_own_base_style(id, prop))
+        .map(|prop| property_damage(&prop))
+        .max()
+        .unwrap_or(Damage::None);
+    if damage == Damage::None
+        && (old.background_layers(id) != new.background_layers(id) || old.selection_colors(id) != new.selection_colors(id))
+    {
+        damage = Damage::Paint;
+    }
+    for child in children {
+        if pseudo_owner(child) == Some(id) {
+            damage = damage.max(node_damage(old, new, child));
+        }
+    }
+    damage
+}
+
+/// Page areas that look different in `next` than in `previous`: the boxes that moved, resized,
+/// appeared or went away, and the boxes of the `restyled` nodes in both. Each box is grown by
+/// the ink its shadows and its layer's filters leave outside it, as the tiler does.
+pub fn damage_rects(previous: &LayerList, next: &LayerList, restyled: &[NodeId]) -> Vec<Rect> {
+    let restyled: HashSet<NodeId> = restyled.iter().copied().collect();
+    let (previous_ink, next_ink) = (InkOutsets::new(previous), InkOutsets::new(next));
+    let mut rects = Vec::new();
+
+    for (id, node) in &next.layout_tree.arena {
+        match previous.layout_tree.arena.get(id) {
+            Some(old)
+                if old.dom_node_id == node.dom_node_id
+                    && old.box_model == node.box_model
+                    && old.column_rules == node.column_rules => {}
+            Some(old) => {
+                rects.push(previous_ink.rect(*id, old));
+                rects.push(next_ink.rect(*id, node));
+            }
+            None => rects.push(next_ink.rect(*id, node)),
+        }
+    }
+    for (id, old) in &previous.layout_tree.arena {
+        if !next.layout_tree.arena.contains_key(id) {
+            rects.push(previous_ink.rect(*id, old));
+        }
+    }
+
+    for (list, ink) in [(previous, &previous_ink), (next, &next_ink)] {
+        for (id, node) in &list.layout_tree.arena {
+            let owner = pseudo_owner(node.dom_node_id).unwrap_or(node.dom_node_id);
+            if restyled.contains(&node.dom_node_id) || restyled.contains(&owner) {
+                rects.push(ink.rect(*id, node));
+            }
+        }
+    }
+    rects
+}
+
+/// Where the elements of one layer list can leave ink outside their margin boxes.
+struct InkOutsets<'a> {
+    doc: &'a dyn PipelineDocument,
+    /// Filter overflow of the layer each element is painted in.
+    filters: HashMap<LayoutElementId, Trbl<f64>>,
+}
+
+impl<'a> InkOutsets<'a> {
+    fn new(list: &'a LayerList) -> Self {
+        let mut filters = HashMap::new();
+        for layer in list.layers.read().values() {
+            let overflow = filters_overflow(&layer.filters);
+            filters.extend(layer.elements.iter().map(|&id| (id, overflow.clone())));
+        }
+        InkOutsets {
+            doc: &*list.layout_tree.render_tree.doc,
+            filters,
+        }
+    }
+
+    /// The margin box of `node` grown by its outer box and text shadows and its layer's filters.
+    fn rect(&self, id: LayoutElementId, node: &LayoutElementNode) -> Rect {
+        let m = node.box_model.margin_box;
+        if m.width <= 0.0 || m.height <= 0.0 {
+            return m;
+        }
+        let shadows = shadows_overflow(&self.doc.box_shadows(node.dom_node_id))
+            .max(&shadows_overflow(&self.doc.text_shadows(node.dom_node_id)));
+        let filters = self.filters.get(&id).cloned().unwrap_or(Trbl::ZERO);
+        filters.outset(shadows.outset(m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_is_graded_by_property() {
+        assert_eq!(property_damage(&StyleProperty::Color), Damage::Paint);
+        assert_eq!(property_damage(&StyleProperty::BoxShadow), Damage::Paint);
+        assert_eq!(property_damage(&StyleProperty::Width), Damage::Layout);
+        assert_eq!(property_damage(&StyleProperty::Display), Damage::Rebuild);
+        assert_eq!(property_damage(&StyleProperty::FontSize), Damage::Rebuild);
+        assert_eq!(StyleProperty::all().map(|prop| property_damage(&prop)).max(), Some(Damage::Rebuild));
+    }
+
+    #[test]
+    fn class_diff_and_inert_attributes() {
+        let mut changed = changed_classes(Some("a b  c"), Some("c d a"));
+        changed.sort_unstable();
+        assert_eq!(changed, vec!["b", "d"]);
+        assert_eq!(changed_classes(None, Some("x")), vec!["x"]);
+
+        assert!(is_inert_attribute("data-state"));
+        assert!(is_inert_attribute("aria-expanded"));
+        assert!(is_inert_attribute("onclick"));
+        assert!(!is_inert_attribute("src"));
+        assert!(!is_inert_attribute("lang"));
+        // `:link` and `:any-link` match on it.
+        assert!(!is_inert_attribute("href"));
+    }
+}
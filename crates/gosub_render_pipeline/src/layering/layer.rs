@@ -1,8 +1,15 @@
+use crate::animation::transform_translation;
 use crate::common::document::node::NodeId;
+use crate::common::document::pipeline_doc::PipelineDocument;
 use crate::common::document::style::{lookup, StyleProperty, Unit, Value};
 use crate::layouter::{LayoutElementId, LayoutElementNode, LayoutTree};
-use crate::render::backend::{StickyConstraint, TileAnchor};
-use parking_lot::RwLock;
+use crate::painter::commands::filter::Filter;
+use crate::render::backend::{BlendMode, CompositeGroup, StickyConstraint, TileAnchor};
+use crate::selection::text_offset_at;
+use gosub_interface::font_system::FontSystem;
+use parking_lot::{Mutex, RwLock};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::RTree;
 use std::collections::{HashMap, HashSet};
 use std::ops::AddAssign;
 use std::sync::Arc;
@@ -34,30 +41,106 @@ impl std::fmt::Display for LayerId {
 #[derive(Clone)]
 pub struct Layer {
     pub layer_id: LayerId,
-    /// Stacking order (from the promoting element's `z-index`). The layer list is sorted by this
-    /// so higher-`z-index` layers composite in front.
+    /// Stacking order within the parent stacking context (the promoting element's `z-index`, 0 for
+    /// `auto`). Siblings are sorted by this so higher-`z-index` layers composite in front.
     pub order: isize,
+    /// The stacking context this layer composites into; `None` for the root layer.
+    pub parent: Option<LayerId>,
+    /// Child layers in composite order (sorted by `order`, DOM order breaking ties).
+    pub children: Vec<LayerId>,
     /// Group opacity: tiles rasterize normally and the compositor fades them as a unit.
     pub opacity: f32,
+    /// CSS `mix-blend-mode` of the promoting element: how the layer blends into its parent.
+    pub blend_mode: BlendMode,
+    /// Composited as an isolated group: the layer and its nested layers are flattened offscreen
+    /// before fading/blending into the parent. Set for `opacity < 1`, a blend mode,
+    /// `isolation: isolate`, and for a stacking context that holds a blending layer.
+    pub isolated: bool,
     /// How the layer responds to scroll - `Fixed` layers composite without the scroll offset.
     pub anchor: TileAnchor,
+    /// CSS `filter` chain of the promoting element, applied to the layer as a unit.
+    pub filters: Vec<Filter>,
+    /// Offset in CSS px from the promoting element's `transform`, relative to the parent layer.
+    /// Applied at composite time, so an animated transform moves the layer without a repaint.
+    pub translation: (f64, f64),
     pub elements: Vec<LayoutElementId>,
+    /// Page-space margin boxes of `elements`, each tagged with its index there (paint order).
+    /// Built once the layer is complete; see [`LayerList::find_element_at_page`].
+    hit_index: RTree<HitBox>,
 }
 
+type HitBox = GeomWithData<Rectangle<[f64; 2]>, usize>;
+
 impl Layer {
     pub fn new(layer_id: LayerId, order: isize) -> Layer {
         Layer {
             layer_id,
             order,
+            parent: None,
+            children: Vec::new(),
             opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            isolated: false,
             anchor: TileAnchor::Scroll,
+            filters: Vec::new(),
+            translation: (0.0, 0.0),
             elements: Vec::new(),
+            hit_index: RTree::new(),
         }
     }
 
     fn add_element(&mut self, element_id: LayoutElementId) {
         self.elements.push(element_id);
     }
+
+    /// Bulk-loads the hit-test index from the margin boxes of `elements`. Empty boxes can never
+    /// contain a point, so they are left out.
+    fn build_hit_index(&mut self, layout_tree: &LayoutTree) {
+        let boxes = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, id)| {
+                let r = layout_tree.get_node_by_id(*id)?.box_model.margin_box;
+                let valid = r.width > 0.0 && r.height > 0.0 && r.x.is_finite() && r.y.is_finite();
+                valid.then(|| {
+                    let rect = Rectangle::from_corners([r.x, r.y], [r.x + r.width, r.y + r.height]);
+                    GeomWithData::new(rect, index)
+                })
+            })
+            .collect();
+        self.hit_index = RTree::bulk_load(boxes);
+    }
+
+    /// The topmost element whose margin box contains the layer-space point `(x, y)`. Boxes are
+    /// half-open, so two abutting boxes never both claim the shared edge.
+    fn element_at(&self, x: f64, y: f64) -> Option<LayoutElementId> {
+        let index = self
+            .hit_index
+            .locate_all_at_point([x, y])
+            .filter(|hit| {
+                let upper = hit.geom().upper();
+                x < upper[0] && y < upper[1]
+            })
+            .map(|hit| hit.data)
+            .max()?;
+        self.elements.get(index).copied()
+    }
+}
+
+/// Everything under a point: the topmost element there, the elements it sits in and, on text,
+/// the caret offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitTestResult {
+    pub element: LayoutElementId,
+    /// Layout ancestors of `element`, nearest first, ending at the root.
+    pub ancestors: Vec<LayoutElementId>,
+    /// The point in the page space of the element's layer, where its boxes live.
+    pub x: f64,
+    pub y: f64,
+    /// Byte offset into the laid-out text of `element` nearest the point, when it is a text
+    /// element (see [`crate::selection`]).
+    pub text_offset: Option<usize>,
 }
 
 impl std::fmt::Debug for Layer {
@@ -69,7 +152,8 @@ impl std::fmt::Debug for Layer {
 /// A list of layers that is returned by the pipeline stage
 pub struct LayerList {
     pub layout_tree: Arc<LayoutTree>,
-    /// Sorted by stacking order; the compositor and hit-test both walk this.
+    /// Composite order (the stacking-context tree flattened back to front); the compositor and
+    /// hit-test both walk this.
     pub layer_ids: RwLock<Vec<LayerId>>,
     pub layers: RwLock<HashMap<LayerId, Layer>>,
     next_layer_id: RwLock<LayerId>,
@@ -113,13 +197,26 @@ impl LayerList {
         layer_list
     }
 
-    /// @TODO: This must be done through rstar!
     /// Topmost element at the given viewport coordinates. Element boxes are in page space, so a
     /// scrolling layer is hit-tested at `viewport + scroll`, a `fixed` layer at the raw viewport.
     pub fn find_element_at(&self, vp_x: f64, vp_y: f64, scroll_x: f64, scroll_y: f64) -> Option<LayoutElementId> {
-        // This assumes that the layers are ordered from top to bottom
+        self.find_element_at_page(vp_x, vp_y, scroll_x, scroll_y)
+            .map(|(element_id, _, _)| element_id)
+    }
+
+    /// As [`Self::find_element_at`], also returning the point in the page space of the hit
+    /// element's layer, where its boxes live. Each layer is queried through its R-tree, so the
+    /// cost grows with the number of layers and the boxes under the point, not the page size.
+    pub fn find_element_at_page(
+        &self,
+        vp_x: f64,
+        vp_y: f64,
+        scroll_x: f64,
+        scroll_y: f64,
+    ) -> Option<(LayoutElementId, f64, f64)> {
+        // Composite order is back to front, so the topmost layer is tested first.
+        let binding = self.layers.read();
         for layer_id in self.layer_ids.read().iter().rev() {
-            let binding = self.layers.read();
             let Some(layer) = binding.get(layer_id) else {
                 continue;
             };
@@ -134,28 +231,47 @@ impl LayerList {
                     (vp_x + scroll_x - dx, vp_y + scroll_y - dy)
                 }
             };
+            // ...and undo the layer's transform offset.
+            let (tx, ty) = chain_translation(&binding, *layer_id);
+            let (x, y) = (x - tx, y - ty);
 
-            for element_id in layer.elements.iter().rev() {
-                let Some(layout_element) = self.layout_tree.get_node_by_id(*element_id) else {
-                    log::warn!("Layout element {:?} not found during hit test", element_id);
-                    continue;
-                };
-                let box_model = &layout_element.box_model;
-
-                // @TODO: use rtree for this
-                if x >= box_model.margin_box.x
-                    && x < box_model.margin_box.x + box_model.margin_box.width
-                    && y >= box_model.margin_box.y
-                    && y < box_model.margin_box.y + box_model.margin_box.height
-                {
-                    return Some(*element_id);
-                }
+            if let Some(element_id) = layer.element_at(x, y) {
+                return Some((element_id, x, y));
             }
         }
 
         None
     }
 
+    /// Full hit-test at the given viewport coordinates: the topmost element, its ancestor chain
+    /// and, when it is text, the byte offset under the point. `font_system` shapes that text and
+    /// should be the one the page was laid out with.
+    pub fn hit_test(
+        &self,
+        vp_x: f64,
+        vp_y: f64,
+        scroll_x: f64,
+        scroll_y: f64,
+        font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+    ) -> Option<HitTestResult> {
+        let (element, x, y) = self.find_element_at_page(vp_x, vp_y, scroll_x, scroll_y)?;
+
+        let mut ancestors = Vec::new();
+        let mut current = self.layout_tree.get_node_by_id(element).and_then(|n| n.parent);
+        while let Some(id) = current {
+            ancestors.push(id);
+            current = self.layout_tree.get_node_by_id(id).and_then(|n| n.parent);
+        }
+
+        Some(HitTestResult {
+            element,
+            ancestors,
+            x,
+            y,
+            text_offset: text_offset_at(&self.layout_tree, font_system, element, x, y),
+        })
+    }
+
     /// Sticky constraint for a `position: sticky` element, else `None`. The cage should be the
     /// containing block's content box; we approximate it with the parent's, as there are no
     /// sub-scroll-containers yet. A root sticky element gets a zero-slack cage and never sticks.
@@ -195,14 +311,10 @@ impl LayerList {
         })
     }
 
-    /// Creates a new fully-opaque, scroll-anchored layer at the given order and returns its id.
-    pub fn new_layer(&self, order: isize) -> LayerId {
-        self.new_promoted_layer(order, 1.0, TileAnchor::Scroll)
-    }
-
-    pub fn new_promoted_layer(&self, order: isize, opacity: f32, anchor: TileAnchor) -> LayerId {
+    /// Creates a new fully-opaque layer at the given order inside `parent` and returns its id.
+    pub fn new_layer(&self, order: isize, parent: Option<LayerId>, anchor: TileAnchor) -> LayerId {
         let mut layer = Layer::new(self.next_layer_id(), order);
-        layer.opacity = opacity;
+        layer.parent = parent;
         layer.anchor = anchor;
         let layer_id = layer.layer_id;
         self.layer_ids.write().push(layer_id);
@@ -211,9 +323,67 @@ impl LayerList {
         layer_id
     }
 
-    /// Group opacity for a layer; 1.0 if the layer is unknown.
+    /// Effective group opacity for a layer: its own multiplied by every enclosing layer's, for
+    /// compositors that fade tiles without nesting groups. 1.0 if the layer is unknown.
     pub fn layer_opacity(&self, layer_id: LayerId) -> f32 {
-        self.layers.read().get(&layer_id).map(|l| l.opacity).unwrap_or(1.0)
+        let layers = self.layers.read();
+        let mut opacity = 1.0;
+        let mut current = layers.get(&layer_id);
+        while let Some(layer) = current {
+            opacity *= layer.opacity;
+            current = layer.parent.and_then(|p| layers.get(&p));
+        }
+        opacity
+    }
+
+    /// The isolated groups a layer's tiles composite through, outermost first (see
+    /// [`CompositeGroup`]). Empty when no enclosing layer is isolated.
+    pub fn layer_groups(&self, layer_id: LayerId) -> Arc<[CompositeGroup]> {
+        let layers = self.layers.read();
+        let mut groups = Vec::new();
+        let mut current = layers.get(&layer_id);
+        while let Some(layer) = current {
+            if layer.isolated {
+                groups.push(CompositeGroup {
+                    id: layer.layer_id.as_u64(),
+                    opacity: layer.opacity,
+                    blend_mode: layer.blend_mode,
+                });
+            }
+            current = layer.parent.and_then(|p| layers.get(&p));
+        }
+        groups.reverse();
+        groups.into()
+    }
+
+    /// Total composite-time offset of a layer in CSS px: its own `transform` translation plus
+    /// every enclosing layer's. (0, 0) if the layer is unknown.
+    pub fn layer_translation(&self, layer_id: LayerId) -> (f64, f64) {
+        chain_translation(&self.layers.read(), layer_id)
+    }
+
+    /// Re-reads the opacity and transform of an element with a composited animation into the
+    /// layer it promoted, so the compositor fades and moves the layer without a repaint. False
+    /// when `element` didn't get such a layer, e.g. because its animation started after layering;
+    /// the page must then be layered again.
+    pub fn update_composited_layer(&self, element: LayoutElementId) -> bool {
+        let Some(node) = self.layout_tree.get_node_by_id(element) else {
+            return false;
+        };
+        if !self.is_opacity_grouped(node.dom_node_id) {
+            return false;
+        }
+        let doc = &*self.layout_tree.render_tree.doc;
+        let mut layers = self.layers.write();
+        let Some(layer) = layers
+            .values_mut()
+            .find(|l| l.parent.is_some() && l.elements.first() == Some(&element))
+        else {
+            return false;
+        };
+        layer.opacity = element_opacity(doc, node.dom_node_id).clamp(0.0, 1.0);
+        layer.translation = element_translation(doc, node.dom_node_id);
+        true
     }
 
     /// Scroll anchor for a layer; `Scroll` if the layer is unknown.
@@ -221,6 +391,41 @@ impl LayerList {
         self.layers.read().get(&layer_id).map(|l| l.anchor).unwrap_or_default()
     }
 
+    /// CSS `filter` chain for a layer; empty if the layer is unknown or unfiltered.
+    pub fn layer_filters(&self, layer_id: LayerId) -> Vec<Filter> {
+        self.layers
+            .read()
+            .get(&layer_id)
+            .map(|l| l.filters.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether `other` has the same layers holding the same elements, composited the same way,
+    /// so tiles painted for one are valid for the other wherever the elements' boxes didn't move.
+    pub fn has_same_layers(&self, other: &LayerList) -> bool {
+        if *self.layer_ids.read() != *other.layer_ids.read()
+            || *self.opacity_group_nodes.read() != *other.opacity_group_nodes.read()
+        {
+            return false;
+        }
+        let (layers, other_layers) = (self.layers.read(), other.layers.read());
+        layers.len() == other_layers.len()
+            && layers.iter().all(|(id, layer)| {
+                other_layers.get(id).is_some_and(|other| {
+                    layer.elements == other.elements
+                        && layer.order == other.order
+                        && layer.parent == other.parent
+                        && layer.children == other.children
+                        && layer.opacity == other.opacity
+                        && layer.blend_mode == other.blend_mode
+                        && layer.isolated == other.isolated
+                        && layer.anchor == other.anchor
+                        && layer.filters == other.filters
+                        && layer.translation == other.translation
+                })
+            })
+    }
+
     /// True when this DOM node's paint must skip per-element opacity because it belongs to an
     /// opacity compositing group (the whole layer is faded once at composite time instead).
     pub fn is_opacity_grouped(&self, node_id: NodeId) -> bool {
@@ -250,115 +455,167 @@ impl LayerList {
         self.layers.write().clear();
 
         let root_id = self.layout_tree.root_id;
-        let default_layer_id = self.new_layer(0);
+        let default_layer_id = self.new_layer(0, None, TileAnchor::Scroll);
+
+        self.traverse(default_layer_id, root_id, false, false, TileAnchor::Scroll);
+
+        let mut layers = self.layers.write();
+
+        // A blending layer mixes with everything in its stacking context, and only with that:
+        // the context must be an isolated group so the blend can't reach past it. The root
+        // blends against the page background directly.
+        let blend_parents: Vec<LayerId> = layers
+            .values()
+            .filter(|l| l.blend_mode != BlendMode::Normal)
+            .filter_map(|l| l.parent)
+            .filter(|p| *p != default_layer_id)
+            .collect();
+        for parent in blend_parents {
+            if let Some(layer) = layers.get_mut(&parent) {
+                layer.isolated = true;
+            }
+        }
 
-        self.traverse(default_layer_id, root_id, false, false, 0);
+        // Children in stacking order. Layer ids grow in creation (DOM) order, so equal `z-index` keeps
+        // DOM order - the correct tie-break.
+        let mut ids: Vec<(LayerId, Option<LayerId>, isize)> =
+            layers.values().map(|l| (l.layer_id, l.parent, l.order)).collect();
+        ids.sort_by_key(|(id, _, order)| (*order, id.as_u64()));
+        for (id, parent, _) in ids {
+            if let Some(p) = parent.and_then(|p| layers.get_mut(&p)) {
+                p.children.push(id);
+            }
+        }
 
-        // Composite order = stacking order. Sort layers by their `order` (z-index level); the sort is
-        // stable, so layers at the same level keep DOM/creation order (the correct tie-break for
-        // equal z-index). The compositor and hit-test both walk `layer_ids` in this order.
-        let layers = self.layers.read();
-        self.layer_ids
-            .write()
-            .sort_by_key(|id| layers.get(id).map(|l| l.order).unwrap_or(0));
+        // Composite order = the stacking-context tree flattened back to front. Within a context,
+        // negative `z-index` children paint beneath the context's own layer, the rest above it.
+        let mut order = Vec::with_capacity(layers.len());
+        flatten_layers(&layers, default_layer_id, &mut order);
+        for layer in layers.values_mut() {
+            layer.build_hit_index(&self.layout_tree);
+        }
+        drop(layers);
+        *self.layer_ids.write() = order;
     }
 
     /// Walk the layout tree assigning each element to a layer. An element is *promoted* to its own
-    /// layer (with its subtree) for a compositing reason (`opacity < 1`, `position: fixed`/`sticky`)
-    /// even when nested, or once for a positioned `z-index`, which only re-levels its subtree.
+    /// layer (with its subtree) when it establishes a stacking context: a compositing reason
+    /// (`opacity < 1`, `filter`, `mix-blend-mode`, `isolation: isolate`, `position: fixed`/`sticky`,
+    /// `transform`, a running opacity or transform animation) or a positioned `z-index`. The new
+    /// layer is a child of the enclosing one, so nested contexts stack and composite inside their
+    /// parent.
     ///
     /// `in_promoted_group`: inside such a subtree, where images deliberately do NOT get their own
-    /// layer so they move/fade with the group. `group_faded`: the enclosing layer has `opacity < 1`,
-    /// which gates the per-element opacity skip. `inherited_order`: the enclosing stacking level.
+    /// layer so they move/fade with the group. `group_faded`: some enclosing layer has
+    /// `opacity < 1`, which gates the per-element opacity skip. `anchor`: the enclosing layer's
+    /// scroll anchor, which nested layers inherit so they stay pinned with a fixed/sticky parent.
     fn traverse(
         &self,
         layer_id: LayerId,
         layout_element_node_id: LayoutElementId,
         in_promoted_group: bool,
         group_faded: bool,
-        inherited_order: isize,
+        anchor: TileAnchor,
     ) {
         let Some(layout_element) = self.layout_tree.get_node_by_id(layout_element_node_id) else {
             return;
         };
         let doc = &self.layout_tree.render_tree.doc;
+        let node_id = layout_element.dom_node_id;
+        let keyword = |prop: &StyleProperty| match doc.get_own_style(node_id, prop) {
+            Some(Value::Keyword(id)) => Some(lookup(id)),
+            _ => None,
+        };
 
         // OWN (non-inherited) styles only: descendants inherit the group through the layer and
         // must not each re-promote.
-        let own_opacity = match doc.get_own_style(layout_element.dom_node_id, &StyleProperty::Opacity) {
-            Some(Value::Number(n)) | Some(Value::Unit(n, _)) => n,
-            _ => 1.0,
-        };
-        let is_fixed = matches!(
-            doc.get_own_style(layout_element.dom_node_id, &StyleProperty::Position),
-            Some(Value::Keyword(id)) if lookup(id) == "fixed"
-        );
+        let own_opacity = element_opacity(&**doc, node_id);
+        let position = keyword(&StyleProperty::Position);
+        let is_fixed = position.as_deref() == Some("fixed");
         // Sticky promotes like `fixed`, but its offset is resolved from scroll at composite time.
         let sticky = self.sticky_constraint(layout_element);
+        // A filter applies to the element and its subtree as one image, so it needs its own layer.
+        let own_filters = doc.filters(node_id);
+        let blend_mode = keyword(&StyleProperty::MixBlendMode)
+            .map(|kw| BlendMode::from_css_keyword(&kw))
+            .unwrap_or_default();
+        let isolate = keyword(&StyleProperty::Isolation).as_deref() == Some("isolate");
+        // A transform moves the element with its subtree, offset as a layer at composite time.
+        let transformed = keyword(&StyleProperty::Transform).is_some_and(|t| t != "none");
+        // An element animating opacity or transform gets its layer up front, so each frame only
+        // updates the layer.
+        let animated = doc.has_composited_animation(node_id);
 
         // `z-index` only takes effect on positioned elements; `auto`/non-positioned stays at 0.
-        let is_positioned = matches!(
-            doc.get_own_style(layout_element.dom_node_id, &StyleProperty::Position),
-            Some(Value::Keyword(id)) if matches!(lookup(id).as_str(), "relative" | "absolute" | "fixed" | "sticky")
-        );
+        let is_positioned = matches!(position.as_deref(), Some("relative" | "absolute" | "fixed" | "sticky"));
         let z_index: Option<isize> = if is_positioned {
-            match doc.get_own_style(layout_element.dom_node_id, &StyleProperty::ZIndex) {
+            match doc.get_own_style(node_id, &StyleProperty::ZIndex) {
                 Some(Value::Number(n)) => Some(n as isize),
                 _ => None,
             }
         } else {
             None
         };
-        // Stacking level for this element; the layer list is sorted by it after traversal, since
-        // DOM order alone would put a `z-index: 0` layer on top of a `z-index: 1` one.
-        let order = z_index.unwrap_or(inherited_order);
-
-        // A compositing reason forces a layer even when nested, so the effect is not swallowed by
-        // the parent layer; a plain `z-index` promotes once and otherwise carries `order` downward.
-        let compositing = own_opacity < 1.0 || is_fixed || sticky.is_some();
-        if compositing || (z_index.is_some() && !in_promoted_group) {
+
+        let compositing = own_opacity < 1.0
+            || !own_filters.is_empty()
+            || blend_mode != BlendMode::Normal
+            || isolate
+            || is_fixed
+            || sticky.is_some()
+            || transformed
+            || animated;
+        if compositing || z_index.is_some() {
             let layer_opacity = own_opacity.clamp(0.0, 1.0);
-            // Opacity is realised via `layer_opacity` regardless of the anchor, so a
-            // sticky+opacity element still composes correctly.
+            // Opacity is realised via the layer regardless of the anchor, so a sticky+opacity
+            // element still composes correctly.
             let anchor = if let Some(c) = sticky {
                 TileAnchor::Sticky(c)
             } else if is_fixed {
                 TileAnchor::Fixed
             } else {
-                TileAnchor::Scroll
+                anchor
             };
-            let group_layer_id = self.new_promoted_layer(order, layer_opacity, anchor);
+            let group_layer_id = self.new_layer(z_index.unwrap_or(0), Some(layer_id), anchor);
+            if let Some(layer) = self.layers.write().get_mut(&group_layer_id) {
+                layer.opacity = layer_opacity;
+                layer.blend_mode = blend_mode;
+                layer.isolated = layer_opacity < 1.0 || blend_mode != BlendMode::Normal || isolate || animated;
+                layer.filters = own_filters;
+                layer.translation = element_translation(&**doc, node_id);
+            }
             self.add_to_layer(group_layer_id, layout_element.id);
-            let faded = layer_opacity < 1.0;
+            // An animated layer may fade at any frame, so treat it as faded from the start.
+            let faded = layer_opacity < 1.0 || animated;
             // Only a faded layer risks double-darkening, so only then skip per-element opacity.
             if faded {
-                self.opacity_group_nodes.write().insert(layout_element.dom_node_id);
+                self.opacity_group_nodes.write().insert(node_id);
             }
             for &child_id in &layout_element.children {
-                self.traverse(group_layer_id, child_id, true, faded, order);
+                self.traverse(group_layer_id, child_id, true, group_faded || faded, anchor);
             }
             return;
         }
 
+        // A video repaints every frame, so it gets a layer of its own like an image does.
         let is_image = doc
-            .tag_name(layout_element.dom_node_id)
-            .map(|tag| tag.eq_ignore_ascii_case("img"))
+            .tag_name(node_id)
+            .map(|tag| tag.eq_ignore_ascii_case("img") || tag.eq_ignore_ascii_case("video"))
             .unwrap_or(false);
 
         if is_image && !in_promoted_group {
-            let image_layer_id = self.new_layer(order);
+            let image_layer_id = self.new_layer(0, Some(layer_id), anchor);
             self.add_to_layer(image_layer_id, layout_element.id);
         } else {
             self.add_to_layer(layer_id, layout_element.id);
             // In a faded group, an element with no own opacity relies entirely on the layer fade.
-            // One that declares its own keeps applying it per-element - an approximation.
             if in_promoted_group && group_faded && own_opacity >= 1.0 {
-                self.opacity_group_nodes.write().insert(layout_element.dom_node_id);
+                self.opacity_group_nodes.write().insert(node_id);
             }
         }
 
         for &child_id in &layout_element.children {
-            self.traverse(layer_id, child_id, in_promoted_group, group_faded, order);
+            self.traverse(layer_id, child_id, in_promoted_group, group_faded, anchor);
         }
     }
 
@@ -370,6 +627,54 @@ impl LayerList {
     }
 }
 
+/// Appends `layer_id` and its descendants to `out` in composite order: negative-`z-index`
+/// children, the layer itself, then the remaining children.
+fn flatten_layers(layers: &HashMap<LayerId, Layer>, layer_id: LayerId, out: &mut Vec<LayerId>) {
+    let Some(layer) = layers.get(&layer_id) else {
+        return;
+    };
+    let (below, above): (Vec<LayerId>, Vec<LayerId>) = layer
+        .children
+        .iter()
+        .copied()
+        .partition(|c| layers.get(c).is_some_and(|l| l.order < 0));
+    for child in below {
+        flatten_layers(layers, child, out);
+    }
+    out.push(layer_id);
+    for child in above {
+        flatten_layers(layers, child, out);
+    }
+}
+
+/// Sum of the `translation`s of `layer_id` and its enclosing layers.
+fn chain_translation(layers: &HashMap<LayerId, Layer>, layer_id: LayerId) -> (f64, f64) {
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut current = layers.get(&layer_id);
+    while let Some(layer) = current {
+        x += layer.translation.0;
+        y += layer.translation.1;
+        current = layer.parent.and_then(|p| layers.get(&p));
+    }
+    (x, y)
+}
+
+/// The element's own `opacity`, 1.0 when unset.
+fn element_opacity(doc: &dyn PipelineDocument, node_id: NodeId) -> f32 {
+    match doc.get_own_style(node_id, &StyleProperty::Opacity) {
+        Some(Value::Number(n)) | Some(Value::Unit(n, _)) => n,
+        _ => 1.0,
+    }
+}
+
+/// The offset of the translations in the element's own `transform`.
+fn element_translation(doc: &dyn PipelineDocument, node_id: NodeId) -> (f64, f64) {
+    match doc.get_own_style(node_id, &StyleProperty::Transform) {
+        Some(Value::Keyword(kw)) => transform_translation(&lookup(kw)),
+        _ => (0.0, 0.0),
+    }
+}
+
 /// Read a CSS length inset as px, treating unitless numbers as px. `None` for `auto` and non-px
 /// units - percentage/em insets aren't resolved here yet.
 fn read_px(value: Option<Value>) -> Option<f64> {
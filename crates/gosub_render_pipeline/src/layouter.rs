@@ -1,20 +1,24 @@
 use crate::common::document::node::NodeId as DomNodeId;
 use crate::common::font::FontInfo;
-use crate::common::geo::{Coordinate, Dimension};
+use crate::common::geo::{Coordinate, Dimension, Rect};
 use crate::common::media::MediaId;
-use crate::layouter::box_model::BoxModel;
 use crate::rendertree_builder::{RenderNodeId, RenderTree};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::ops::AddAssign;
 use std::sync::Arc;
 
+mod bidi;
 mod box_model;
 mod css_taffy_converter;
 mod inline_run;
+pub mod multicol;
 pub mod table;
 pub mod taffy;
 pub mod text;
+mod writing_mode;
+
+pub use box_model::{BoxModel, Edges};
 
 /// ID's for layout elements
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -82,13 +86,38 @@ pub struct ElementContextImage {
     pub alt: Option<String>,
 }
 
-/// Per-element data (text, image, svg) needed by later phases of the rendering pipeline.
+/// An `<iframe>`: a replaced element showing a nested document. The engine renders that document
+/// on its own and hands the painter the result (see `BrowserState::frames`).
+#[derive(Clone, Debug)]
+pub struct ElementContextFrame {
+    pub node_id: DomNodeId,
+    /// The default object size: the `width`/`height` attributes, else 300×150.
+    pub dimension: Dimension,
+}
+
+/// A `<video>` or `<audio>`: a replaced element whose frames and controls the engine drives (see
+/// `BrowserState::media`).
+#[derive(Clone, Debug)]
+pub struct ElementContextMedia {
+    pub node_id: DomNodeId,
+    /// The default object size: the `width`/`height` attributes, else 300×150.
+    pub dimension: Dimension,
+    pub audio: bool,
+    /// The `controls` attribute is set, so the element draws a play/pause button and progress.
+    pub controls: bool,
+    /// A loaded `poster` image and its pixel size, shown until the first video frame.
+    pub poster: Option<(MediaId, (u32, u32))>,
+}
+
+/// Per-element data (text, image, svg, frame, media) needed by later phases of the rendering pipeline.
 #[derive(Debug, Clone)]
 pub enum ElementContext {
     None,
     Text(ElementContextText),
     Image(ElementContextImage),
     Svg(ElementContextSvg),
+    Frame(ElementContextFrame),
+    Media(ElementContextMedia),
 }
 
 impl ElementContext {
@@ -135,6 +164,10 @@ impl ElementContext {
             dimension,
         })
     }
+
+    pub fn frame(dimension: Dimension, node_id: DomNodeId) -> ElementContext {
+        Self::Frame(ElementContextFrame { node_id, dimension })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -150,20 +183,37 @@ pub struct LayoutElementNode {
     pub children: Vec<LayoutElementId>,
     pub box_model: BoxModel,
     pub context: ElementContext,
-    /// Resolved CSS `background-image`, loaded into the media store during layout.
-    pub background_media: Option<BackgroundMedia>,
+    /// Resolved CSS `background-image` layers in source order (first listed paints on top).
+    /// `url()` images are loaded into the media store during layout.
+    pub background_layers: Vec<BackgroundLayer>,
+    /// `column-rule` segments of a multi-column container in absolute coordinates: zero-width
+    /// rects on the centre line of each column gap. Empty for every other element.
+    pub column_rules: Vec<Rect>,
 }
 
-/// A resolved CSS `background-image` and its media kind. The painter finalizes tile geometry once
-/// the border box is known. A tiling SVG is rasterized to an `Image` during layout so only one
-/// tiling path exists downstream; a `cover`/`contain` SVG stays `Svg`.
+/// One resolved `background-image` layer and its per-layer layout. The painter finalizes the
+/// geometry once the boxes are known.
+#[derive(Debug, Clone)]
+pub struct BackgroundLayer {
+    pub image: BackgroundImage,
+    pub layout: crate::common::document::pipeline_doc::BgImageLayout,
+}
+
+#[derive(Debug, Clone)]
+pub enum BackgroundImage {
+    Gradient(crate::painter::commands::gradient::Gradient),
+    Media(BackgroundMedia),
+}
+
+/// A loaded `url()` background image and its media kind. A tiling SVG is rasterized to an
+/// `Image` during layout so only one tiling path exists downstream; a `cover`/`contain` SVG stays
+/// `Svg`.
 #[derive(Debug, Clone, Copy)]
 pub enum BackgroundMedia {
     Image {
         media_id: MediaId,
         /// Intrinsic image size in px (for a rasterized SVG tile, the tile's pixel size).
         natural: (f32, f32),
-        layout: crate::common::document::pipeline_doc::BgImageLayout,
     },
     Svg(MediaId),
 }
@@ -175,6 +225,9 @@ pub struct LayoutTree {
     pub root_id: LayoutElementId,
     next_node_id: Arc<RwLock<LayoutElementId>>,
     pub root_dimension: Dimension,
+    /// `loading="lazy"` images laid out without fetching, and the URL each will load. The engine
+    /// requests one once its box comes near the viewport.
+    pub deferred_images: Vec<(LayoutElementId, String)>,
 }
 
 impl LayoutTree {
@@ -192,6 +245,63 @@ impl LayoutTree {
         *nid += 1;
         id
     }
+
+    /// Every `<iframe>` element in document order, with its page-space content box, the area its
+    /// nested document shows in.
+    pub fn frame_elements(&self) -> Vec<(LayoutElementId, &ElementContextFrame, Rect)> {
+        let mut out = Vec::new();
+        let mut stack = vec![self.root_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.get_node_by_id(id) else {
+                continue;
+            };
+            if let ElementContext::Frame(ctx) = &node.context {
+                out.push((id, ctx, node.box_model.content_box));
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        out
+    }
+
+    /// Every `<video>` and `<audio>` element in document order, with its page-space content box.
+    pub fn media_elements(&self) -> Vec<(LayoutElementId, &ElementContextMedia, Rect)> {
+        let mut out = Vec::new();
+        let mut stack = vec![self.root_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.get_node_by_id(id) else {
+                continue;
+            };
+            if let ElementContext::Media(ctx) = &node.context {
+                out.push((id, ctx, node.box_model.content_box));
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        out
+    }
+
+    /// Every raster image an element paints, as (element, image media id) pairs in document
+    /// order: `<img>` content, `url()` background layers and video posters.
+    pub fn image_elements(&self) -> Vec<(LayoutElementId, MediaId)> {
+        let mut out = Vec::new();
+        let mut stack = vec![self.root_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.get_node_by_id(id) else {
+                continue;
+            };
+            match &node.context {
+                ElementContext::Image(ctx) => out.push((id, ctx.media_id)),
+                ElementContext::Media(ctx) => out.extend(ctx.poster.map(|(media_id, _)| (id, media_id))),
+                _ => {}
+            }
+            for layer in &node.background_layers {
+                if let BackgroundImage::Media(BackgroundMedia::Image { media_id, .. }) = layer.image {
+                    out.push((id, media_id));
+                }
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        out
+    }
 }
 
 impl std::fmt::Debug for LayoutTree {
@@ -0,0 +1,176 @@
+//! Bidirectional inline layout (UAX #9).
+//!
+//! A line box is a flex row of inline items; for an RTL block that row runs `row-reverse`, so the
+//! base direction is handled by Taffy. What is left is rule L2 *above* the base level: runs of
+//! the other direction (English in an Arabic paragraph, a `dir=rtl` span in English text) keep
+//! their own reading order. [`visual_order`] resolves the embedding levels over the text of a
+//! line box's items and reverses those runs, and each item is then shaped by the font system with
+//! the same base direction, which reorders the characters inside it.
+//!
+//! Items are reordered before the flex row wraps, so an embedded run that wraps keeps its order
+//! across the break instead of being reordered per line.
+
+use crate::common::document::node::{Node, NodeId as DomNodeId, NodeType};
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use unicode_bidi::{BidiInfo, Level};
+
+/// U+FFFC OBJECT REPLACEMENT CHARACTER: a neutral stand-in for an atomic inline box.
+const OBJECT: char = '\u{FFFC}';
+
+/// Whether `id` computes to `direction: rtl`.
+pub fn is_rtl(doc: &dyn PipelineDocument, id: DomNodeId) -> bool {
+    matches!(doc.get_style(id, &StyleProperty::Direction), Value::Keyword(kw) if lookup(kw) == "rtl")
+}
+
+/// The element's `unicode-bidi` keyword (`normal` when unset).
+pub fn bidi_mode(doc: &dyn PipelineDocument, id: DomNodeId) -> String {
+    match doc.get_style(id, &StyleProperty::UnicodeBidi) {
+        Value::Keyword(kw) => lookup(kw),
+        _ => "normal".to_string(),
+    }
+}
+
+/// The text an inline item contributes to its line's bidi paragraph. A text node gives its own
+/// text; an inline box gives its text content when `unicode-bidi: normal`, and otherwise a
+/// stand-in wrapped in the embedding, override or isolate controls its `unicode-bidi` and
+/// `direction` ask for.
+pub fn item_text(doc: &dyn PipelineDocument, node: &Node) -> String {
+    if let NodeType::Text(text) = &node.node_type {
+        return text.clone();
+    }
+    let rtl = is_rtl(doc, node.node_id);
+    let (embed, override_, isolate) = if rtl {
+        ('\u{202B}', '\u{202E}', '\u{2067}')
+    } else {
+        ('\u{202A}', '\u{202D}', '\u{2066}')
+    };
+    const PDF: char = '\u{202C}';
+    const PDI: char = '\u{2069}';
+    const FSI: char = '\u{2068}';
+
+    match bidi_mode(doc, node.node_id).as_str() {
+        "embed" => [embed, OBJECT, PDF].iter().collect(),
+        "bidi-override" => [override_, OBJECT, PDF].iter().collect(),
+        "isolate" => [isolate, OBJECT, PDI].iter().collect(),
+        "isolate-override" => [isolate, override_, OBJECT, PDF, PDI].iter().collect(),
+        "plaintext" => [FSI, OBJECT, PDI].iter().collect(),
+        _ => {
+            let mut text = String::new();
+            collect_text(doc, node.node_id, &mut text);
+            if text.trim().is_empty() {
+                OBJECT.to_string()
+            } else {
+                text
+            }
+        }
+    }
+}
+
+fn collect_text(doc: &dyn PipelineDocument, id: DomNodeId, out: &mut String) {
+    for child in doc.children(id) {
+        match doc.get_node_by_id(child) {
+            Some(Node {
+                node_type: NodeType::Text(text),
+                ..
+            }) => out.push_str(&text),
+            Some(_) => collect_text(doc, child, out),
+            None => {}
+        }
+    }
+}
+
+/// The order to place a line box's items in along its main axis, given each item's text (see
+/// [`item_text`]) in logical order and the block's base direction.
+pub fn visual_order(texts: &[&str], rtl: bool) -> Vec<usize> {
+    let mut paragraph = String::new();
+    let mut ranges = Vec::with_capacity(texts.len());
+    for text in texts {
+        let start = paragraph.len();
+        // Raw text still carries source newlines, which would split the bidi paragraph.
+        paragraph.extend(text.chars().map(|c| if c.is_ascii_whitespace() { ' ' } else { c }));
+        ranges.push(start..paragraph.len());
+    }
+
+    let base = if rtl { Level::rtl() } else { Level::ltr() };
+    let info = BidiInfo::new(&paragraph, Some(base));
+    // An item is atomic, so it sits at the lowest level any of its characters resolved to. The
+    // embedding and override controls are skipped: X9 removes them and their level is
+    // meaningless; isolate controls do count, as they carry the level outside the isolate.
+    let levels: Vec<u8> = ranges
+        .into_iter()
+        .map(|range| {
+            paragraph[range.clone()]
+                .char_indices()
+                .filter(|(_, c)| !matches!(c, '\u{202A}'..='\u{202E}'))
+                .map(|(i, _)| info.levels[range.start + i].number())
+                .min()
+                .unwrap_or(base.number())
+        })
+        .collect();
+    reorder_above(&levels, base.number())
+}
+
+/// Rule L2 for the levels above `base`: from the highest level down to `base + 1`, reverse every
+/// maximal run of items at that level or higher.
+fn reorder_above(levels: &[u8], base: u8) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let highest = levels.iter().copied().max().unwrap_or(base);
+    for level in (base + 1..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            if levels[order[i]] < level {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn l2_reverses_runs_above_the_base_level() {
+        assert_eq!(reorder_above(&[0, 0, 0], 0), vec![0, 1, 2]);
+        assert_eq!(reorder_above(&[0, 1, 1, 0], 0), vec![0, 2, 1, 3]);
+        // A level-2 run nested in a level-1 run flips twice, keeping its own order.
+        assert_eq!(reorder_above(&[1, 2, 2, 1], 0), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn embedded_runs_keep_their_reading_order() {
+        // Hebrew words in an English line read right to left...
+        assert_eq!(
+            visual_order(&["one", " ", "שלום", " ", "עולם", " ", "two"], false),
+            vec![0, 1, 4, 3, 2, 5, 6]
+        );
+        // ...and English words in a Hebrew line left to right (the row itself runs reversed).
+        assert_eq!(
+            visual_order(&["שלום", " ", "one", " ", "two", " ", "עולם"], true),
+            vec![0, 1, 4, 3, 2, 5, 6]
+        );
+        // Same-direction text never moves.
+        assert_eq!(visual_order(&["שלום", " ", "עולם"], true), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn isolates_sit_at_the_outer_level() {
+        let rtl_isolate = "\u{2067}\u{FFFC}\u{2069}";
+        // Two adjacent RTL isolates in an LTR line stay in logical order; an embedding joins the
+        // surrounding RTL run instead.
+        assert_eq!(
+            visual_order(&["a", rtl_isolate, rtl_isolate, "b"], false),
+            vec![0, 1, 2, 3]
+        );
+        let rtl_embed = "\u{202B}\u{FFFC}\u{202C}";
+        assert_eq!(visual_order(&["a ", "שלום", rtl_embed, "b"], false), vec![0, 2, 1, 3]);
+    }
+}
@@ -3,6 +3,9 @@ use crate::common::document::pipeline_doc::PipelineDocument;
 use crate::common::document::style::{
     lookup, Display as CssDisplay, StyleProperty, TextAlign as CssTextAlign, Unit as CssUnit, Value,
 };
+use crate::layouter::bidi;
+use crate::layouter::writing_mode::{is_vertical_block, physical_flex_direction, writing_mode};
+use gosub_interface::font_system::WritingMode;
 use taffy::prelude::{
     minmax, span, FromFr, FromLength, MaxTrackSizingFunction, MinTrackSizingFunction, TaffyAuto, TaffyGridLine,
     TaffyMaxContent, TaffyMinContent, TaffyZero,
@@ -28,6 +31,14 @@ impl<'a> CssTaffyConverter<'a> {
         self.doc.get_own_style(self.node_id, prop)
     }
 
+    fn is_rtl(&self) -> bool {
+        bidi::is_rtl(self.doc, self.node_id)
+    }
+
+    fn writing_mode(&self) -> WritingMode {
+        writing_mode(self.doc, self.node_id)
+    }
+
     /// Returns this element's *computed* font-size in px (resolving inheritance and
     /// em/rem), or 16px if unresolvable. Used to resolve font-relative lengths such as
     /// `em`/`ch` on other properties (e.g. `max-width: 17ch`).
@@ -157,6 +168,61 @@ impl<'a> CssTaffyConverter<'a> {
             _ => {}
         }
 
+        // Taffy lays out in physical axes only. A flex row follows the inline axis and a column the
+        // block axis, so both turn with the writing mode, and the inline one reverses in RTL; a
+        // vertical block container stacks its blocks as a flex row. A vertical grid is transposed,
+        // and a grid lists its tracks from the right (or bottom) where its axes run that way.
+        // Grid items take the axes of the grid they sit in; auto-placed ones still fill from the
+        // top left.
+        let mode = self.writing_mode();
+        let rtl = self.is_rtl();
+        if mode.is_vertical() {
+            if is_vertical_block(self.doc, self.node_id) {
+                ts.display = Display::Flex;
+                ts.flex_direction = FlexDirection::Column;
+                ts.flex_wrap = FlexWrap::NoWrap;
+            }
+            std::mem::swap(&mut ts.grid_template_rows, &mut ts.grid_template_columns);
+            std::mem::swap(&mut ts.grid_auto_rows, &mut ts.grid_auto_columns);
+            ts.grid_auto_flow = match ts.grid_auto_flow {
+                GridAutoFlow::Row => GridAutoFlow::Column,
+                GridAutoFlow::Column => GridAutoFlow::Row,
+                GridAutoFlow::RowDense => GridAutoFlow::ColumnDense,
+                GridAutoFlow::ColumnDense => GridAutoFlow::RowDense,
+            };
+            ts.gap = Size {
+                width: ts.gap.height,
+                height: ts.gap.width,
+            };
+        }
+        ts.flex_direction = physical_flex_direction(ts.flex_direction, mode, rtl);
+        let (columns_reversed, rows_reversed) = grid_reversal(mode, rtl);
+        if columns_reversed {
+            ts.grid_template_columns.reverse();
+        }
+        if rows_reversed {
+            ts.grid_template_rows.reverse();
+        }
+
+        if let Some(parent) = self.doc.parent(self.node_id) {
+            let parent_mode = writing_mode(self.doc, parent);
+            if parent_mode.is_vertical() {
+                std::mem::swap(&mut ts.grid_row, &mut ts.grid_column);
+                // Blocks stacked across the page overflow it rather than shrink, as they would
+                // overflow downwards in horizontal text.
+                if is_vertical_block(self.doc, parent) {
+                    ts.flex_shrink = 0.0;
+                }
+            }
+            let (columns_reversed, rows_reversed) = grid_reversal(parent_mode, bidi::is_rtl(self.doc, parent));
+            if columns_reversed {
+                ts.grid_column = mirror_grid_line(ts.grid_column);
+            }
+            if rows_reversed {
+                ts.grid_row = mirror_grid_line(ts.grid_row);
+            }
+        }
+
         ts
     }
 
@@ -200,7 +266,7 @@ impl<'a> CssTaffyConverter<'a> {
     fn get_display(&self, default: Display) -> Display {
         match self.get_own(&StyleProperty::Display) {
             Some(Value::Display(val)) => match val {
-                CssDisplay::Block => Display::Block,
+                CssDisplay::Block | CssDisplay::ListItem => Display::Block,
                 CssDisplay::InlineBlock => Display::Block, // We override this later
                 CssDisplay::Inline => Display::Block,      // We override this later
                 CssDisplay::Flex => Display::Flex,
@@ -344,25 +410,37 @@ impl<'a> CssTaffyConverter<'a> {
     }
 
     /// `text-align` inherits, so this must read the computed value - `get_own` sees nothing on a
-    /// descendant that inherits it. `left`/`right` collapse onto `start`/`end` as elsewhere (LTR).
+    /// descendant that inherits it. Taffy's legacy alignments are physical, so `start`/`end`
+    /// resolve against the direction.
     fn get_text_align(&self, default: TextAlign) -> TextAlign {
+        let rtl = self.is_rtl();
         match self.doc.get_style(self.node_id, &StyleProperty::TextAlign) {
             Value::TextAlign(val) => match val {
                 CssTextAlign::Center => TextAlign::LegacyCenter,
-                CssTextAlign::Start | CssTextAlign::Left => TextAlign::LegacyLeft,
-                CssTextAlign::End | CssTextAlign::Right => TextAlign::LegacyRight,
+                CssTextAlign::Left => TextAlign::LegacyLeft,
+                CssTextAlign::Right => TextAlign::LegacyRight,
+                CssTextAlign::Start if rtl => TextAlign::LegacyRight,
+                CssTextAlign::End if rtl => TextAlign::LegacyLeft,
+                CssTextAlign::Start => TextAlign::LegacyLeft,
+                CssTextAlign::End => TextAlign::LegacyRight,
                 _ => default,
             },
             _ => default,
         }
     }
 
+    /// The logical insets onto the physical sides they land on in the writing mode and direction.
     fn get_inset(&self, default: Rect<LengthPercentageAuto>) -> Rect<LengthPercentageAuto> {
+        let (mode, rtl) = (self.writing_mode(), self.is_rtl());
+        let side = |side: &str, default: LengthPercentageAuto| match StyleProperty::inset_on_side(side, mode, rtl) {
+            Some(prop) => self.get_lpa(prop, default),
+            None => default,
+        };
         Rect {
-            top: self.get_lpa(StyleProperty::InsetBlockStart, default.top),
-            right: self.get_lpa(StyleProperty::InsetInlineEnd, default.right),
-            bottom: self.get_lpa(StyleProperty::InsetBlockEnd, default.bottom),
-            left: self.get_lpa(StyleProperty::InsetInlineStart, default.left),
+            top: side("top", default.top),
+            right: side("right", default.right),
+            bottom: side("bottom", default.bottom),
+            left: side("left", default.left),
         }
     }
 
@@ -458,6 +536,16 @@ impl<'a> CssTaffyConverter<'a> {
     }
 }
 
+/// Whether a grid's physical columns and rows run from the right and from the bottom: its columns
+/// follow the inline axis in horizontal text and the block axis in vertical text.
+fn grid_reversal(mode: WritingMode, rtl: bool) -> (bool, bool) {
+    if mode.is_vertical() {
+        (mode.is_block_rtl(), rtl != (mode == WritingMode::SidewaysLr))
+    } else {
+        (rtl, false)
+    }
+}
+
 /// Parse a single grid track token ("1fr", "200px", "auto", "50%") into a TrackSizingFunction.
 fn parse_grid_track(token: &str) -> Option<TrackSizingFunction> {
     let token = token.trim();
@@ -569,6 +657,19 @@ fn parse_grid_template(s: &str) -> Option<Vec<GridTemplateComponent<String>>> {
     }
 }
 
+/// A column placement seen from the other side of the grid: line `n` becomes line `-n` (counted
+/// from the end), and start and end trade places so a span still runs away from the start line.
+fn mirror_grid_line(line: Line<GridPlacement>) -> Line<GridPlacement> {
+    let flip = |placement: GridPlacement| match placement {
+        GridPlacement::Line(index) => GridPlacement::from_line_index(-index.as_i16()),
+        other => other,
+    };
+    Line {
+        start: flip(line.end),
+        end: flip(line.start),
+    }
+}
+
 /// Parse a grid-column/row placement value ("auto", "span 2", "1", "2 / 4", …).
 fn parse_grid_placement(s: &str) -> Option<Line<GridPlacement>> {
     let s = s.trim();
@@ -610,7 +711,9 @@ fn parse_single_placement(s: &str) -> GridPlacement {
 
 #[cfg(test)]
 mod grid_template_tests {
-    use super::{parse_grid_template, split_grid_tokens};
+    use super::{mirror_grid_line, parse_grid_placement, parse_grid_template, split_grid_tokens};
+    use taffy::prelude::{span, TaffyGridLine};
+    use taffy::{GridPlacement, Line};
 
     #[test]
     fn splits_keep_functions_whole() {
@@ -652,4 +755,25 @@ mod grid_template_tests {
         // Garbage token -> None
         assert!(parse_grid_template("bogus").is_none());
     }
+
+    #[test]
+    fn rtl_placement_counts_lines_from_the_end() {
+        let mirrored = |s: &str| mirror_grid_line(parse_grid_placement(s).unwrap());
+        assert_eq!(
+            mirrored("1 / 3"),
+            Line {
+                start: GridPlacement::from_line_index(-3),
+                end: GridPlacement::from_line_index(-1),
+            }
+        );
+        // A span keeps running away from the (now mirrored) start line.
+        assert_eq!(
+            mirrored("2 / span 2"),
+            Line {
+                start: span(2),
+                end: GridPlacement::from_line_index(-2),
+            }
+        );
+        assert_eq!(mirrored("auto"), parse_grid_placement("auto").unwrap());
+    }
 }
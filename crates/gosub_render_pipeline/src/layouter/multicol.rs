@@ -0,0 +1,227 @@
+use gosub_lattice::multicol::{ColumnFill, ColumnFragment, ColumnSpan, ColumnStyle, ColumnTree};
+
+use crate::common::document::node::NodeId as DomNodeId;
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::document::style::{lookup, Display, StyleProperty, Unit, Value};
+use crate::common::geo::Coordinate;
+use crate::layouter::table::translate_box_model;
+use crate::layouter::{LayoutElementId, LayoutElementNode, LayoutTree};
+use std::collections::HashMap;
+use taffy::prelude::{minmax, FromLength, MaxTrackSizingFunction, MinTrackSizingFunction, TaffyGridLine};
+use taffy::{AlignItems, GridPlacement, GridTemplateComponent, LengthPercentage, Line, Size, Style};
+
+/// NodeId of the multi-column container itself in [`PipelineColumnTree`]; its flow units are
+/// numbered `0..n`.
+pub const CONTAINER: usize = usize::MAX;
+
+/// Slack (in CSS px) when deciding whether a box starts below everything above it.
+const BREAK_EPSILON: f64 = 0.5;
+
+/// Resolves the multi-column properties of `id`, or `None` when it is not a multi-column
+/// container. Only block containers can be multi-column containers; flex, grid and table boxes
+/// ignore `column-count`/`column-width`. `content_width` resolves a percentage `column-gap`.
+pub fn multicol_style(doc: &dyn PipelineDocument, id: DomNodeId, content_width: f32) -> Option<ColumnStyle> {
+    match doc.get_own_style(id, &StyleProperty::Display) {
+        Some(Value::Display(Display::Block | Display::ListItem | Display::InlineBlock)) => {}
+        _ => return None,
+    }
+
+    let count = match doc.get_own_style(id, &StyleProperty::ColumnCount) {
+        Some(Value::Number(n)) if n >= 1.0 => Some(n as u32),
+        _ => None,
+    };
+    let width = match doc.get_style(id, &StyleProperty::ColumnWidth) {
+        Value::Unit(px, Unit::Px) if px > 0.0 => Some(px),
+        _ => None,
+    };
+    let style = ColumnStyle {
+        count,
+        width,
+        // `normal` is 1em for multi-column containers.
+        gap: match doc.get_style(id, &StyleProperty::ColumnGap) {
+            Value::Unit(px, Unit::Px) => px,
+            Value::Unit(pct, Unit::Percent) | Value::Percentage(pct) => pct / 100.0 * content_width,
+            _ => doc.get_style_f32(id, &StyleProperty::FontSize),
+        },
+        fill: match doc.get_style(id, &StyleProperty::ColumnFill) {
+            Value::Keyword(kw) if lookup(kw) == "auto" => ColumnFill::Auto,
+            _ => ColumnFill::Balance,
+        },
+        height: None,
+    };
+    style.is_multicol().then_some(style)
+}
+
+/// `column-span: all` on a child of a multi-column container.
+pub fn is_column_spanner(doc: &dyn PipelineDocument, id: DomNodeId) -> bool {
+    matches!(
+        doc.get_own_style(id, &StyleProperty::ColumnSpan),
+        Some(Value::Keyword(kw)) if lookup(kw) == "all"
+    )
+}
+
+/// Turns a multi-column container's Taffy style into a grid of `count` fixed tracks with the
+/// column gap between them. Laying the container out as a grid is only a measuring device: each
+/// child is sized at the column width, and the column pass then moves it to its real column.
+pub fn columnize_style(style: &mut Style, count: usize, width: f32, gap: f32) {
+    style.display = taffy::Display::Grid;
+    style.grid_template_columns = (0..count)
+        .map(|_| {
+            GridTemplateComponent::Single(minmax(
+                MinTrackSizingFunction::from_length(width),
+                MaxTrackSizingFunction::from_length(width),
+            ))
+        })
+        .collect();
+    style.grid_template_rows = Vec::new();
+    style.gap = Size {
+        width: LengthPercentage::length(gap),
+        height: LengthPercentage::length(0.0),
+    };
+    // Children keep their content height instead of stretching to their grid row.
+    style.align_items = Some(AlignItems::START);
+}
+
+/// Makes a `column-span: all` child span every track of the measuring grid.
+pub fn span_all_columns(style: &mut Style) {
+    style.grid_column = Line {
+        start: GridPlacement::from_line_index(1),
+        end: GridPlacement::from_line_index(-1),
+    };
+}
+
+/// One in-flow piece of a multi-column container: a block-level child, or the inline children
+/// that share one anonymous line container.
+pub struct FlowUnit {
+    pub members: Vec<LayoutElementId>,
+    /// Absolute top-left of the unit's margin box (or of the anonymous container).
+    pub origin: Coordinate,
+    pub height: f64,
+    pub span: ColumnSpan,
+}
+
+/// Adapter that bridges `gosub_lattice`'s `ColumnTree` with the render pipeline's `LayoutTree`.
+/// Unit heights come from the Taffy pass that measured the children at the column width;
+/// fragments are staged and applied to the arena by [`PipelineColumnTree::apply_fragments`].
+pub struct PipelineColumnTree<'a> {
+    layout_tree: &'a mut LayoutTree,
+    style: ColumnStyle,
+    units: Vec<FlowUnit>,
+    fragments: HashMap<usize, Vec<ColumnFragment>>,
+}
+
+impl<'a> PipelineColumnTree<'a> {
+    pub fn new(layout_tree: &'a mut LayoutTree, style: ColumnStyle, units: Vec<FlowUnit>) -> Self {
+        Self {
+            layout_tree,
+            style,
+            units,
+            fragments: HashMap::new(),
+        }
+    }
+
+    /// Move every element of every unit to the fragment its top edge falls in. `content_origin`
+    /// is the absolute top-left of the container's content box.
+    ///
+    /// A box that is split keeps its own decorations in its first fragment; its descendants
+    /// follow their lines into later columns.
+    pub fn apply_fragments(self, content_origin: Coordinate) {
+        let arena = &mut self.layout_tree.arena;
+        for (index, unit) in self.units.iter().enumerate() {
+            let Some(fragments) = self.fragments.get(&index) else {
+                continue;
+            };
+            for id in subtree(arena, &unit.members) {
+                let Some(element) = arena.get_mut(&id) else {
+                    continue;
+                };
+                let rel_top = element.box_model.border_box.y - unit.origin.y;
+                let Some(fragment) = fragments
+                    .iter()
+                    .rev()
+                    .find(|f| f.offset as f64 <= rel_top + BREAK_EPSILON)
+                    .or(fragments.first())
+                else {
+                    continue;
+                };
+                let offset = Coordinate::new(
+                    content_origin.x + fragment.position.x as f64 - unit.origin.x,
+                    content_origin.y + (fragment.position.y - fragment.offset) as f64 - unit.origin.y,
+                );
+                translate_box_model(&mut element.box_model, offset);
+            }
+        }
+    }
+}
+
+/// `roots` and all their descendants, in pre-order.
+fn subtree(arena: &HashMap<LayoutElementId, LayoutElementNode>, roots: &[LayoutElementId]) -> Vec<LayoutElementId> {
+    let mut out = Vec::new();
+    let mut stack: Vec<LayoutElementId> = roots.iter().rev().copied().collect();
+    while let Some(id) = stack.pop() {
+        out.push(id);
+        if let Some(element) = arena.get(&id) {
+            stack.extend(element.children.iter().rev().copied());
+        }
+    }
+    out
+}
+
+impl ColumnTree for PipelineColumnTree<'_> {
+    type NodeId = usize;
+
+    fn children(&self, id: usize) -> Vec<usize> {
+        if id == CONTAINER {
+            (0..self.units.len()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn column_style(&self, _id: usize) -> ColumnStyle {
+        self.style
+    }
+
+    fn column_span(&self, id: usize) -> ColumnSpan {
+        self.units.get(id).map(|u| u.span).unwrap_or_default()
+    }
+
+    fn block_size(&mut self, id: usize, _inline_size: f32) -> f32 {
+        // Taffy already laid the unit out at the column (or, for spanners, container) width.
+        self.units.get(id).map(|u| u.height as f32).unwrap_or(0.0)
+    }
+
+    fn break_offsets(&self, id: usize) -> Vec<f32> {
+        // A unit may break at the top of any leaf box that starts below every leaf before it -
+        // in practice between line boxes, and between the blocks nested inside the unit.
+        let Some(unit) = self.units.get(id) else {
+            return Vec::new();
+        };
+        let arena = &self.layout_tree.arena;
+        let mut leaves: Vec<(f64, f64)> = subtree(arena, &unit.members)
+            .into_iter()
+            .filter_map(|id| arena.get(&id))
+            .filter(|el| el.children.is_empty())
+            .map(|el| {
+                let bb = el.box_model.border_box;
+                (bb.y - unit.origin.y, bb.y + bb.height - unit.origin.y)
+            })
+            .collect();
+        leaves.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut offsets = Vec::new();
+        let mut reach: Option<f64> = None;
+        for (top, bottom) in leaves {
+            if reach.is_some_and(|reach| top >= reach - BREAK_EPSILON) && top > 0.0 {
+                offsets.push(top as f32);
+            }
+            reach = Some(reach.map_or(bottom, |reach| reach.max(bottom)));
+        }
+        offsets.dedup();
+        offsets
+    }
+
+    fn set_fragments(&mut self, id: usize, fragments: Vec<ColumnFragment>) {
+        self.fragments.insert(id, fragments);
+    }
+}
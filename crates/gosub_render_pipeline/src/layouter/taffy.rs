@@ -1,27 +1,35 @@
 use cow_utils::CowUtils;
 
 use crate::common::document::node::{Node, NodeId as DomNodeId, NodeType};
-use crate::common::document::pipeline_doc::BgSize;
-use crate::common::document::style::{lookup, FontWeight, StyleProperty, TextAlign, Unit, Value};
-use crate::common::font::{FontAlignment, FontInfo};
+use crate::common::document::pipeline_doc::{pseudo_owner, BgImage, BgImageLayout, PipelineDocument};
+use crate::common::document::style::{self, lookup, FontWeight, StyleProperty, TextAlign, Unit, Value};
+use crate::common::font::{FontAlignment, FontInfo, FontShaping};
 use crate::common::geo;
 use crate::common::geo::Coordinate;
 use crate::common::media::MediaStore;
 use crate::common::media::{Media, MediaId, MediaRequest, MediaType};
+use crate::image_source::{select_image_source, ImageEnvironment};
+use crate::layouter::bidi;
 use crate::layouter::box_model::Edges;
 use crate::layouter::css_taffy_converter::CssTaffyConverter;
+use crate::layouter::multicol::{
+    columnize_style, is_column_spanner, multicol_style, span_all_columns, FlowUnit, PipelineColumnTree, CONTAINER,
+};
 use crate::layouter::table::post_process_tables;
 use crate::layouter::text::get_text_layout;
+use crate::layouter::writing_mode::{is_vertical_block, physical_flex_direction, writing_mode};
 use crate::layouter::{
-    box_model, BackgroundMedia, CanLayout, ElementContext, ElementContextImage, ElementContextSvg, ElementContextText,
-    LayoutElementId, LayoutElementNode, LayoutTree,
+    box_model, BackgroundImage, BackgroundLayer, BackgroundMedia, CanLayout, ElementContext, ElementContextFrame,
+    ElementContextImage, ElementContextMedia, ElementContextSvg, ElementContextText, LayoutElementId,
+    LayoutElementNode, LayoutTree,
 };
 use crate::rendertree_builder::{RenderNodeId, RenderTree};
 use gosub_fontmanager::ParleyFontSystem;
-use gosub_interface::font_system::FontSystem;
+use gosub_interface::font_system::{FontSystem, Hyphens, OverflowWrap, WritingMode};
+use gosub_lattice::multicol::{compute_multicol_layout, resolve_columns, ColumnSpan, MultiColLayout};
 use parking_lot::{Mutex, RwLock};
-use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use taffy::prelude::*;
 use taffy::NodeId as TaffyNodeId;
@@ -29,6 +37,19 @@ use taffy::NodeId as TaffyNodeId;
 const DEFAULT_FONT_SIZE: f64 = 16.0;
 const DEFAULT_FONT_FAMILY: &str = "sans-serif";
 
+/// Upper bound on the extra Taffy passes spent settling multi-column containers.
+const MULTICOL_PASSES: usize = 4;
+
+/// Column widths and heights closer than this (CSS px) count as settled.
+const MULTICOL_EPSILON: f32 = 0.01;
+
+/// U+00AD SOFT HYPHEN: an invisible hyphenation point, drawn as `-` only where a line breaks.
+const SOFT_HYPHEN: char = '\u{00AD}';
+
+/// The default object size of an `<iframe>` (HTML §15.4.3).
+const DEFAULT_FRAME_WIDTH: f32 = 300.0;
+const DEFAULT_FRAME_HEIGHT: f32 = 150.0;
+
 /// Parse an HTML presentational length attribute (e.g. `<img width="80">`) into pixels.
 /// Accepts a bare integer/float or a trailing `px`; ignores `%` and other units.
 fn parse_px_attr(v: &str) -> Option<f32> {
@@ -38,31 +59,47 @@ fn parse_px_attr(v: &str) -> Option<f32> {
 }
 
 // Cache key: (text, font_family, size_bits, line_height_bits, weight, max_width_bits,
-// letter_spacing_bits). Floats are stored as their bit pattern so the tuple is Hash + Eq.
-type MeasureKey = (String, String, u32, u32, i32, u32, u32);
+// letter_spacing_bits, shaping). Floats are stored as their bit pattern so the tuple is Hash + Eq.
+type MeasureKey = (String, String, u32, u32, i32, u32, u32, String);
 
 /// CSS `text-align` on a block, as `justify_content` for the anonymous flex containers holding its
 /// line boxes. A line box *is* that container, so this is what positions a run too short to fill it
 /// - a run that wraps already fills the line and is aligned by the shaper instead.
 ///
 /// `justify` stays `None`: the shaper stretches a wrapped run itself, and flexing a single item
-/// can't emulate that.
-fn line_box_justify(align: &Value) -> Option<taffy::JustifyContent> {
+/// can't emulate that. An RTL line box runs `row-reverse`, so `start` is its flex-start too; only
+/// the physical `left`/`right` swap.
+fn line_box_justify(align: &Value, rtl: bool) -> Option<taffy::JustifyContent> {
     let Value::TextAlign(ta) = align else {
         return None;
     };
     match ta {
         TextAlign::Center => Some(taffy::JustifyContent::CENTER),
-        TextAlign::End | TextAlign::Right => Some(taffy::JustifyContent::FLEX_END),
+        TextAlign::End => Some(taffy::JustifyContent::FLEX_END),
+        TextAlign::Right if !rtl => Some(taffy::JustifyContent::FLEX_END),
+        TextAlign::Left if rtl => Some(taffy::JustifyContent::FLEX_END),
         _ => None,
     }
 }
 
+/// How a block's line boxes run, read once from the block's computed style.
+#[derive(Clone, Copy)]
+struct LineBoxStyle {
+    justify: Option<taffy::JustifyContent>,
+    /// `direction: rtl`: the line box runs `row-reverse`.
+    rtl: bool,
+    /// `unicode-bidi: bidi-override` (or `isolate-override`): items keep their logical order.
+    override_bidi: bool,
+    /// A vertical line box is a column, its wrapped lines stacking across the block.
+    writing_mode: WritingMode,
+}
+
 /// One entry in a run of inline content awaiting layout. `Item`s are normal inline boxes/text
-/// laid out inside an anonymous flex container; `Break` is a `<br>` that ends the current line box
-/// and, when standing alone, contributes an empty line of the carried line-height.
+/// laid out inside an anonymous flex container, with the text they contribute to the line's bidi
+/// paragraph (see [`bidi::item_text`]); `Break` is a `<br>` that ends the current line box and,
+/// when standing alone, contributes an empty line of the carried line-height.
 enum InlineEntry {
-    Item(LayoutElementId, TaffyNodeId),
+    Item(LayoutElementId, TaffyNodeId, String),
     Break(f64),
 }
 
@@ -89,6 +126,24 @@ pub struct TaffyLayouter {
     measure_cache: HashMap<MeasureKey, Size<f32>>,
     /// Reverse index used by the table post-processing pass.
     dom_to_layout_mapping: HashMap<DomNodeId, LayoutElementId>,
+    /// Multi-column containers in pre-order, with the geometry settled so far.
+    multicol: Vec<MultiColContainer>,
+    /// Viewport and device-pixel ratio of the current pass, which `srcset`, `sizes` and
+    /// `<picture>` sources are chosen against.
+    image_environment: ImageEnvironment,
+    /// `loading="lazy"` images left unfetched this pass, with the URL they would load. Behind a
+    /// lock because they are found while extracting node data, which only borrows the layouter.
+    deferred_images: Mutex<Vec<(DomNodeId, String)>>,
+}
+
+/// A multi-column container and the state the column passes carry between Taffy runs.
+struct MultiColContainer {
+    layout_id: LayoutElementId,
+    dom_id: DomNodeId,
+    /// Resolved `(count, width, gap)` the container's Taffy style was last columnized with.
+    columns: Option<(usize, f32, f32)>,
+    /// Content height the container's Taffy style was last pinned to.
+    height: Option<f32>,
 }
 
 /// Apply the CSS `text-transform` keyword to a text run. `uppercase`/`lowercase` map the whole
@@ -121,12 +176,76 @@ fn apply_text_transform(text: String, transform: Value) -> String {
     }
 }
 
+/// The nearest ancestor of `id` that is not an inline box: the block container whose lines its
+/// text is set in.
+pub(crate) fn block_container(doc: &dyn PipelineDocument, id: DomNodeId) -> DomNodeId {
+    let mut node = id;
+    while let Some(parent) = doc.parent(node) {
+        node = parent;
+        let display = doc.get_style(node, &StyleProperty::Display);
+        if !matches!(display, Value::Display(style::Display::Inline)) {
+            break;
+        }
+    }
+    node
+}
+
+/// Apply CSS `hyphens` to a text run, leaving soft hyphens (U+00AD) at exactly the points the
+/// font system may hyphenate at: `none` removes them, `auto` adds a dictionary's for the text's
+/// language (`lang`) to every word that has none of its own, and `manual` keeps the author's.
+/// `auto` in a language without a dictionary behaves as `manual`.
+fn apply_hyphens(text: String, hyphens: Hyphens, lang: Option<&str>) -> String {
+    match hyphens {
+        Hyphens::Manual => text,
+        Hyphens::None => {
+            if text.contains(SOFT_HYPHEN) {
+                text.chars().filter(|&ch| ch != SOFT_HYPHEN).collect()
+            } else {
+                text
+            }
+        }
+        Hyphens::Auto => {
+            let Some(lang) = lang
+                .and_then(|lang| <[u8; 2]>::try_from(lang.as_bytes().get(..2)?).ok())
+                .and_then(hypher::Lang::from_iso)
+            else {
+                return text;
+            };
+            let mut out = String::with_capacity(text.len() + text.len() / 4);
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find(char::is_alphabetic) {
+                out.push_str(&rest[..start]);
+                rest = &rest[start..];
+                let end = rest
+                    .find(|ch: char| !ch.is_alphabetic() && ch != SOFT_HYPHEN)
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                if word.contains(SOFT_HYPHEN) {
+                    out.push_str(word);
+                } else {
+                    for (i, syllable) in hypher::hyphenate(word, lang).enumerate() {
+                        if i > 0 {
+                            out.push(SOFT_HYPHEN);
+                        }
+                        out.push_str(syllable);
+                    }
+                }
+                rest = &rest[end..];
+            }
+            out.push_str(rest);
+            out
+        }
+    }
+}
+
 /// Context structures to pass to taffy measure functions so we can calculate the size of the text or images.
 #[derive(Clone, Debug)]
 pub enum TaffyContext {
     Text(ElementContextText),
     Image(ElementContextImage),
     Svg(ElementContextSvg),
+    Frame(ElementContextFrame),
+    Media(ElementContextMedia),
 }
 
 impl TaffyContext {
@@ -201,6 +320,9 @@ impl TaffyLayouter {
             font_system,
             measure_cache: HashMap::new(),
             dom_to_layout_mapping: HashMap::new(),
+            multicol: Vec::new(),
+            image_environment: ImageEnvironment::default(),
+            deferred_images: Mutex::new(Vec::new()),
         }
     }
 
@@ -231,8 +353,9 @@ impl CanLayout for TaffyLayouter {
         &mut self,
         render_tree: RenderTree,
         viewport: Option<geo::Dimension>,
-        // DPI scaling is applied later in the pipeline; text is measured in CSS pixels.
-        _dpi_scale_factor: f32,
+        // DPI scaling is applied later in the pipeline; text is measured in CSS pixels. Only the
+        // choice of responsive image sources depends on it.
+        dpi_scale_factor: f32,
     ) -> LayoutTree {
         let Some(root_id) = render_tree.root_id else {
             log::error!("Render tree has no root node; was parse() called? Returning empty layout.");
@@ -242,20 +365,159 @@ impl CanLayout for TaffyLayouter {
                 root_id: LayoutElementId::new(0),
                 next_node_id: Arc::new(RwLock::new(LayoutElementId::new(0))),
                 root_dimension: geo::Dimension::ZERO,
+                deferred_images: Vec::new(),
             };
         };
+        let defaults = ImageEnvironment::default();
+        self.image_environment = ImageEnvironment {
+            viewport_width: viewport.map_or(defaults.viewport_width, |v| v.width),
+            viewport_height: viewport.map_or(defaults.viewport_height, |v| v.height),
+            device_pixel_ratio: if dpi_scale_factor > 0.0 {
+                dpi_scale_factor as f64
+            } else {
+                1.0
+            },
+        };
         // let root_id = RenderNodeId::new(2);
         let mut layout_tree = self.generate_tree(render_tree, root_id);
+        layout_tree.deferred_images = std::mem::take(&mut *self.deferred_images.lock())
+            .into_iter()
+            .filter_map(|(dom_id, url)| Some((*self.dom_to_layout_mapping.get(&dom_id)?, url)))
+            .collect();
 
-        // // Compute the layout based on the viewport
-        let size = match viewport {
-            Some(viewport) => Size {
-                width: AvailableSpace::Definite(viewport.width as f32),
-                height: AvailableSpace::Definite(viewport.height as f32),
-            },
-            None => Size::MAX_CONTENT,
-        };
+        self.finish_layout(&mut layout_tree, available_space(viewport));
+        layout_tree
+    }
+}
+
+impl TaffyLayouter {
+    /// Runs Taffy over the generated tree and converts the result into `layout_tree`'s box models,
+    /// settling multi-column containers and tables on the way.
+    fn finish_layout(&mut self, layout_tree: &mut LayoutTree, size: Size<AvailableSpace>) {
+        if !self.compute_taffy_layout(size) {
+            return;
+        }
+
+        // Since we are not interested in taffy layout after this stage in the pipeline, we convert
+        // the taffy layout to a box model layout tree. This makes the rest of the pipeline
+        // layout-engine agnostic.
+        let root_id = layout_tree.root_id;
+        let root_width = layout_tree.root_dimension.width;
 
+        // Multi-column containers need more than one Taffy pass: their column width depends on
+        // their own used width, and their height on how the measured children balance over the
+        // columns. Each pass feeds the previous result back until nothing changes (one extra pass
+        // per nesting level at worst).
+        for pass in 0..=MULTICOL_PASSES {
+            self.populate_boxmodel(layout_tree, root_id, Coordinate::ZERO, root_width);
+            if self.multicol.is_empty() {
+                break;
+            }
+            let last_pass = pass == MULTICOL_PASSES;
+            if !self.columnize(layout_tree) || last_pass {
+                let layouts = self.lay_out_columns(layout_tree);
+                if !self.pin_multicol_heights(layout_tree, &layouts) || last_pass {
+                    set_column_rules(layout_tree, &layouts);
+                    break;
+                }
+            }
+            if !self.compute_taffy_layout(size) {
+                return;
+            }
+        }
+        post_process_tables(layout_tree, &self.dom_to_layout_mapping);
+
+        if let Some(root) = layout_tree.get_node_by_id(root_id) {
+            let w = root.box_model.margin_box.width as f32;
+            let h = root.box_model.margin_box.height as f32;
+            layout_tree.root_dimension = geo::Dimension::new(w as f64, h as f64);
+        }
+    }
+
+    /// `previous` over `doc`, after a restyle that changed no property layout depends on: every box
+    /// stays where it was and only the restyled elements' background layers are resolved again.
+    pub fn restyle(&self, previous: &LayoutTree, doc: Arc<dyn PipelineDocument>, restyled: &[DomNodeId]) -> LayoutTree {
+        let restyled: HashSet<DomNodeId> = restyled.iter().copied().collect();
+        let mut layout_tree = previous.clone();
+        layout_tree.render_tree.doc = doc;
+        let ids: Vec<(LayoutElementId, DomNodeId)> = layout_tree
+            .arena
+            .values()
+            .filter(|node| is_restyled(&restyled, node.dom_node_id))
+            .map(|node| (node.id, node.dom_node_id))
+            .collect();
+        for (id, dom_id) in ids {
+            let background_layers = self.resolve_background_layers(&layout_tree, dom_id);
+            if let Some(node) = layout_tree.get_node_by_id_mut(id) {
+                node.background_layers = background_layers;
+            }
+        }
+        layout_tree
+    }
+
+    /// Lays `previous` out again over `doc`, after a restyle that changed box geometry but not the
+    /// shape of the tree: the restyled elements get fresh Taffy styles in place and Taffy only
+    /// recomputes what they dirtied. `previous` must be the last tree this layouter produced.
+    ///
+    /// Returns `None` when the tree can't be patched in place (multi-column containers, or a
+    /// restyled text node that was split into word boxes); the caller lays out from scratch.
+    pub fn relayout(
+        &mut self,
+        previous: &LayoutTree,
+        doc: Arc<dyn PipelineDocument>,
+        restyled: &[DomNodeId],
+        viewport: Option<geo::Dimension>,
+    ) -> Option<LayoutTree> {
+        if !self.multicol.is_empty() {
+            return None;
+        }
+        let restyled: HashSet<DomNodeId> = restyled.iter().copied().collect();
+        let mut layout_tree = previous.clone();
+        layout_tree.render_tree.doc = doc;
+        // As `generate_tree` leaves it, so the box models come out exactly as a full layout's.
+        layout_tree.root_dimension = geo::Dimension::ZERO;
+
+        let mut targets = Vec::new();
+        for node in layout_tree.arena.values() {
+            if !is_restyled(&restyled, node.dom_node_id) {
+                continue;
+            }
+            // Word boxes share their text node's id and have no mapping slot of their own.
+            if self.dom_to_layout_mapping.get(&node.dom_node_id) != Some(&node.id) {
+                return None;
+            }
+            targets.push((node.id, node.dom_node_id));
+        }
+
+        self.deferred_images.lock().clear();
+        for (id, dom_id) in targets {
+            let dom_node = layout_tree.render_tree.doc.get_node_by_id(dom_id)?;
+            let (taffy_context, taffy_style) = self.extract_taffy_data(&layout_tree, &dom_node)?;
+            let taffy_id = *self.layout_taffy_mapping.get(&id)?;
+            let context = to_element_context(taffy_context.as_ref());
+            self.tree.set_style(taffy_id, taffy_style).ok()?;
+            self.tree.set_node_context(taffy_id, taffy_context).ok()?;
+            let background_layers = self.resolve_background_layers(&layout_tree, dom_id);
+            let node = layout_tree.get_node_by_id_mut(id)?;
+            node.context = context;
+            node.background_layers = background_layers;
+        }
+        for (dom_id, url) in std::mem::take(&mut *self.deferred_images.lock()) {
+            let Some(&id) = self.dom_to_layout_mapping.get(&dom_id) else {
+                continue;
+            };
+            if !layout_tree.deferred_images.iter().any(|(deferred, _)| *deferred == id) {
+                layout_tree.deferred_images.push((id, url));
+            }
+        }
+
+        self.finish_layout(&mut layout_tree, available_space(viewport));
+        Some(layout_tree)
+    }
+
+    /// Runs Taffy over the whole tree, measuring text and replaced elements. Returns `false` (and
+    /// logs) when Taffy fails.
+    fn compute_taffy_layout(&mut self, size: Size<AvailableSpace>) -> bool {
         // Clone the Arc and take the measure cache so the closure can capture them
         // without holding a borrow of `self` while `self.tree` is mutably borrowed.
         let font_system = Arc::clone(&self.font_system);
@@ -271,25 +533,40 @@ impl CanLayout for TaffyLayouter {
 
                 match v_nc {
                     Some(TaffyContext::Text(text_ctx)) => {
+                        // Vertical text wraps at the available height instead.
+                        let vertical = text_ctx.font_info.shaping.writing_mode.is_vertical();
+                        let available = if vertical { v_as.height } else { v_as.width };
                         let max_width = if text_ctx.no_wrap {
                             // white-space: nowrap - measure at unlimited width so text never wraps
                             1_000_000_000.0_f64
                         } else {
-                            match v_as.width {
+                            match available {
                                 AvailableSpace::Definite(width) => width as f64,
                                 AvailableSpace::MaxContent => 1_000_000_000.0, // f64::MAX doesn't work. Seems some kind of overflow. Same goes for f32::MAX
                                 AvailableSpace::MinContent => 0.0,
                             }
                         };
 
+                        // `overflow-wrap: break-word` breaks words only once they overflow, so its
+                        // break points do not count towards the min-content width.
+                        let break_word = text_ctx.font_info.shaping.breaking.overflow_wrap == OverflowWrap::BreakWord;
+                        let font_info = if break_word && matches!(available, AvailableSpace::MinContent) {
+                            let mut font_info = text_ctx.font_info.clone();
+                            font_info.shaping.breaking.overflow_wrap = OverflowWrap::Normal;
+                            Cow::Owned(font_info)
+                        } else {
+                            Cow::Borrowed(&text_ctx.font_info)
+                        };
+
                         let cache_key: MeasureKey = (
                             text_ctx.text.clone(),
-                            text_ctx.font_info.family.clone(),
-                            (text_ctx.font_info.size as f32).to_bits(),
-                            (text_ctx.font_info.line_height as f32).to_bits(),
-                            text_ctx.font_info.weight,
+                            font_info.family.clone(),
+                            (font_info.size as f32).to_bits(),
+                            (font_info.line_height as f32).to_bits(),
+                            font_info.weight,
                             (max_width as f32).to_bits(),
-                            (text_ctx.font_info.letter_spacing as f32).to_bits(),
+                            (font_info.letter_spacing as f32).to_bits(),
+                            font_info.shaping.cache_key(),
                         );
                         if let Some(&cached) = measure_cache.get(&cache_key) {
                             return cached;
@@ -300,7 +577,7 @@ impl CanLayout for TaffyLayouter {
                         // rasterizer) can interleave without contention.
                         let text_layout = {
                             let mut fs = font_system.lock();
-                            get_text_layout(text_ctx.text.as_str(), &text_ctx.font_info, max_width, &mut *fs)
+                            get_text_layout(text_ctx.text.as_str(), &font_info, max_width, &mut *fs)
                         };
                         match text_layout {
                             Ok(text_layout) => {
@@ -310,6 +587,9 @@ impl CanLayout for TaffyLayouter {
                                 // the text requires and wraps. Ceiling ensures allocated width ≥
                                 // natural text width, preventing spurious wrapping at the boundary.
                                 let mut width = text_layout.width.ceil() as f32;
+                                // Ceil height so the layout height matches the integer-pixel surface
+                                // that pango creates (prevents descenders from overflowing the box).
+                                let mut height = text_layout.height.ceil() as f32;
 
                                 // Parley strips trailing whitespace (including NBSP) from the line-box
                                 // advance width. When we appended U+00A0 as a trailing-space marker
@@ -319,15 +599,15 @@ impl CanLayout for TaffyLayouter {
                                 // Whitespace-only nodes ("\u{00A0}") have their width fixed explicitly
                                 // in the taffy style, so the measure callback is not invoked for them.
                                 if text_ctx.text.ends_with('\u{00A0}') && text_ctx.text != "\u{00A0}" {
-                                    width += (text_ctx.font_info.size * 0.3) as f32;
+                                    let space = (text_ctx.font_info.size * 0.3) as f32;
+                                    if vertical {
+                                        height += space;
+                                    } else {
+                                        width += space;
+                                    }
                                 }
 
-                                let result = Size {
-                                    width,
-                                    // Ceil height so the layout height matches the integer-pixel surface
-                                    // that pango creates (prevents descenders from overflowing the box).
-                                    height: text_layout.height.ceil() as f32,
-                                };
+                                let result = Size { width, height };
                                 measure_cache.insert(cache_key, result);
                                 result
                             }
@@ -342,35 +622,29 @@ impl CanLayout for TaffyLayouter {
                     // SVG-backed <img> elements carry their intrinsic size the same way.
                     // Without this arm they measured as 0×0 and collapsed (e.g. the HN logo).
                     Some(TaffyContext::Svg(svg_ctx)) => measure_replaced(v_kd, svg_ctx.dimension),
+                    // An iframe has a default size but no intrinsic aspect ratio: each axis CSS
+                    // leaves auto falls back on its own.
+                    Some(TaffyContext::Frame(frame_ctx)) => Size {
+                        width: v_kd.width.unwrap_or(frame_ctx.dimension.width as f32),
+                        height: v_kd.height.unwrap_or(frame_ctx.dimension.height as f32),
+                    },
+                    // Likewise for media: the decoded video's size only arrives after layout.
+                    Some(TaffyContext::Media(media_ctx)) => Size {
+                        width: v_kd.width.unwrap_or(media_ctx.dimension.width as f32),
+                        height: v_kd.height.unwrap_or(media_ctx.dimension.height as f32),
+                    },
                     _ => Size::ZERO,
                 }
             })
         {
             log::error!("Failed to compute taffy layout: {:?}", e);
             self.measure_cache = measure_cache;
-            return layout_tree;
+            return false;
         }
         self.measure_cache = measure_cache;
-
-        // Since we are not interested in taffy layout after this stage in the pipeline, we convert
-        // the taffy layout to a box model layout tree. This makes the rest of the pipeline
-        // layout-engine agnostic.
-        let root_id = layout_tree.root_id;
-        let root_width = layout_tree.root_dimension.width;
-        self.populate_boxmodel(&mut layout_tree, root_id, Coordinate::ZERO, root_width);
-        post_process_tables(&mut layout_tree, &self.dom_to_layout_mapping);
-
-        if let Some(root) = layout_tree.get_node_by_id(root_id) {
-            let w = root.box_model.margin_box.width as f32;
-            let h = root.box_model.margin_box.height as f32;
-            layout_tree.root_dimension = geo::Dimension::new(w as f64, h as f64);
-        }
-
-        layout_tree
+        true
     }
-}
 
-impl TaffyLayouter {
     fn populate_boxmodel(
         &self,
         layout_tree: &mut LayoutTree,
@@ -410,6 +684,9 @@ impl TaffyLayouter {
         let is_inline_node = self.anon_container_map.contains_key(&layout_node_id);
         let content_width_for_children = if is_inline_node {
             parent_content_width
+        } else if let Some((_, column_width, _)) = self.columns_of(layout_node_id) {
+            // Children of a multi-column container wrap at the column width.
+            column_width as f64
         } else {
             my_content_width
         };
@@ -440,6 +717,215 @@ impl TaffyLayouter {
         }
     }
 
+    /// The resolved `(count, width, gap)` of `id` if it is a columnized multi-column container.
+    fn columns_of(&self, id: LayoutElementId) -> Option<(usize, f32, f32)> {
+        self.multicol.iter().find(|c| c.layout_id == id).and_then(|c| c.columns)
+    }
+
+    /// Resolves every multi-column container's columns from its current content width and turns
+    /// its Taffy style into the measuring grid (see [`columnize_style`]). Returns `true` when any
+    /// container changed, i.e. Taffy has to run again.
+    fn columnize(&mut self, layout_tree: &LayoutTree) -> bool {
+        let doc = &*layout_tree.render_tree.doc;
+        let mut changed = false;
+        for index in 0..self.multicol.len() {
+            let (layout_id, dom_id, previous) = {
+                let c = &self.multicol[index];
+                (c.layout_id, c.dom_id, c.columns)
+            };
+            let Some(element) = layout_tree.get_node_by_id(layout_id) else {
+                continue;
+            };
+            let content_width = element.box_model.content_box.width as f32;
+            let Some(style) = multicol_style(doc, dom_id, content_width) else {
+                continue;
+            };
+            let (count, width) = resolve_columns(content_width, &style);
+            let columns = (count, width, style.gap.max(0.0));
+            let unchanged = previous.is_some_and(|(c, w, g)| {
+                c == columns.0 && (w - columns.1).abs() < MULTICOL_EPSILON && (g - columns.2).abs() < MULTICOL_EPSILON
+            });
+            if unchanged {
+                continue;
+            }
+
+            let Some(&taffy_id) = self.layout_taffy_mapping.get(&layout_id) else {
+                continue;
+            };
+            let Ok(taffy_style) = self.tree.style(taffy_id) else {
+                continue;
+            };
+            let mut taffy_style = taffy_style.clone();
+            columnize_style(&mut taffy_style, columns.0, columns.1, columns.2);
+            if let Err(e) = self.tree.set_style(taffy_id, taffy_style) {
+                log::warn!("Failed to columnize multi-column container {:?}: {:?}", layout_id, e);
+                continue;
+            }
+            for child_id in element.children.iter() {
+                let Some(child) = layout_tree.get_node_by_id(*child_id) else {
+                    continue;
+                };
+                if self.anon_container_map.contains_key(child_id) || !is_column_spanner(doc, child.dom_node_id) {
+                    continue;
+                }
+                let Some(&child_taffy_id) = self.layout_taffy_mapping.get(child_id) else {
+                    continue;
+                };
+                if let Ok(child_style) = self.tree.style(child_taffy_id) {
+                    let mut child_style = child_style.clone();
+                    span_all_columns(&mut child_style);
+                    if let Err(e) = self.tree.set_style(child_taffy_id, child_style) {
+                        log::warn!("Failed to span column spanner {:?}: {:?}", child_id, e);
+                    }
+                }
+            }
+
+            self.multicol[index].columns = Some(columns);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Runs the multi-column algorithm for every container, innermost first, and moves the
+    /// children of each into their columns. An outer container then moves an inner one together
+    /// with its already-placed columns.
+    fn lay_out_columns(&self, layout_tree: &mut LayoutTree) -> Vec<(LayoutElementId, MultiColLayout)> {
+        let doc = Arc::clone(&layout_tree.render_tree.doc);
+        let mut layouts = Vec::new();
+        for container in self.multicol.iter().rev() {
+            let Some(element) = layout_tree.get_node_by_id(container.layout_id) else {
+                continue;
+            };
+            let content_box = element.box_model.content_box;
+            let border_box = element.box_model.border_box;
+            let Some(mut style) = multicol_style(&*doc, container.dom_id, content_box.width as f32) else {
+                continue;
+            };
+            // Only an explicit height constrains the columns; a pinned one is our own output.
+            if matches!(
+                doc.get_style(container.dom_id, &StyleProperty::Height),
+                Value::Unit(_, Unit::Px)
+            ) {
+                style.height = Some(content_box.height as f32);
+            }
+
+            let mut units: Vec<FlowUnit> = Vec::new();
+            let mut current_line: Option<TaffyNodeId> = None;
+            for child_id in element.children.iter() {
+                let Some(child) = layout_tree.get_node_by_id(*child_id) else {
+                    continue;
+                };
+                if let Some(&anon_id) = self.anon_container_map.get(child_id) {
+                    // Inline children are grouped by the anonymous line box they sit in.
+                    match units.last_mut() {
+                        Some(unit) if current_line == Some(anon_id) => unit.members.push(*child_id),
+                        _ => {
+                            let Ok(anon) = self.tree.layout(anon_id) else {
+                                continue;
+                            };
+                            units.push(FlowUnit {
+                                members: vec![*child_id],
+                                origin: Coordinate::new(
+                                    border_box.x + anon.location.x as f64,
+                                    border_box.y + anon.location.y as f64,
+                                ),
+                                height: anon.size.height as f64,
+                                span: ColumnSpan::None,
+                            });
+                            current_line = Some(anon_id);
+                        }
+                    }
+                    continue;
+                }
+                current_line = None;
+
+                // Out-of-flow children stay where Taffy put them.
+                let out_of_flow = self
+                    .layout_taffy_mapping
+                    .get(child_id)
+                    .and_then(|id| self.tree.style(*id).ok())
+                    .is_some_and(|s| s.position == Position::Absolute);
+                if out_of_flow {
+                    continue;
+                }
+                let margin_box = child.box_model.margin_box;
+                units.push(FlowUnit {
+                    members: vec![*child_id],
+                    origin: Coordinate::new(margin_box.x, margin_box.y),
+                    height: margin_box.height,
+                    span: if is_column_spanner(&*doc, child.dom_node_id) {
+                        ColumnSpan::All
+                    } else {
+                        ColumnSpan::None
+                    },
+                });
+            }
+
+            let mut column_tree = PipelineColumnTree::new(layout_tree, style, units);
+            match compute_multicol_layout(&mut column_tree, CONTAINER, content_box.width as f32) {
+                Ok(layout) => {
+                    column_tree.apply_fragments(Coordinate::new(content_box.x, content_box.y));
+                    layouts.push((container.layout_id, layout));
+                }
+                Err(e) => log::warn!("Multi-column layout failed for {:?}: {:?}", container.layout_id, e),
+            }
+        }
+        layouts
+    }
+
+    /// Pins the Taffy height of every container without an explicit `height` to its balanced
+    /// column height, so content after it moves up. Returns `true` when any height changed.
+    fn pin_multicol_heights(
+        &mut self,
+        layout_tree: &LayoutTree,
+        layouts: &[(LayoutElementId, MultiColLayout)],
+    ) -> bool {
+        let doc = &*layout_tree.render_tree.doc;
+        let mut changed = false;
+        for (layout_id, layout) in layouts {
+            let Some(container) = self.multicol.iter_mut().find(|c| c.layout_id == *layout_id) else {
+                continue;
+            };
+            if matches!(
+                doc.get_style(container.dom_id, &StyleProperty::Height),
+                Value::Unit(_, Unit::Px)
+            ) {
+                continue;
+            }
+            if container
+                .height
+                .is_some_and(|h| (h - layout.height).abs() < MULTICOL_EPSILON)
+            {
+                continue;
+            }
+            let (Some(&taffy_id), Some(element)) = (
+                self.layout_taffy_mapping.get(layout_id),
+                layout_tree.get_node_by_id(*layout_id),
+            ) else {
+                continue;
+            };
+            let Ok(taffy_style) = self.tree.style(taffy_id) else {
+                continue;
+            };
+            let mut taffy_style = taffy_style.clone();
+            let bm = &element.box_model;
+            let height = match taffy_style.box_sizing {
+                BoxSizing::BorderBox => {
+                    layout.height as f64 + bm.padding.top + bm.padding.bottom + bm.border.top + bm.border.bottom
+                }
+                BoxSizing::ContentBox => layout.height as f64,
+            };
+            taffy_style.size.height = Dimension::from_length(height as f32);
+            if let Err(e) = self.tree.set_style(taffy_id, taffy_style) {
+                log::warn!("Failed to pin multi-column container height {:?}: {:?}", layout_id, e);
+                continue;
+            }
+            container.height = Some(layout.height);
+            changed = true;
+        }
+        changed
+    }
+
     fn generate_tree(&mut self, render_tree: RenderTree, root_id: RenderNodeId) -> LayoutTree {
         self.measure_cache.clear();
         self.tree = TaffyTree::new();
@@ -453,6 +939,8 @@ impl TaffyLayouter {
         self.layout_taffy_mapping.clear();
         self.anon_container_map.clear();
         self.dom_to_layout_mapping.clear();
+        self.multicol.clear();
+        self.deferred_images.lock().clear();
 
         let mut layout_tree = LayoutTree {
             render_tree,
@@ -460,6 +948,7 @@ impl TaffyLayouter {
             root_id: LayoutElementId::new(0), // Will be filled in later
             next_node_id: Arc::new(RwLock::new(LayoutElementId::new(0))),
             root_dimension: geo::Dimension::ZERO,
+            deferred_images: Vec::new(),
         };
 
         let Some((layout_element_root_id, taffy_root_id)) = self.generate_taffy_element(&mut layout_tree, root_id)
@@ -483,7 +972,7 @@ impl TaffyLayouter {
         current_inline_group: &[InlineEntry],
         element_node: &mut LayoutElementNode,
         leaf_id: TaffyNodeId,
-        justify: Option<taffy::JustifyContent>,
+        line: LineBoxStyle,
     ) {
         log::debug!("Processing inline elements: {:?}", current_inline_group.len());
 
@@ -494,47 +983,62 @@ impl TaffyLayouter {
         // Split the run into line boxes at `<br>` boundaries. An empty segment (consecutive `<br>`s
         // or a leading `<br>`) still emits a line box of the break's line-height, so runs of `<br>`
         // produce blank lines rather than collapsing.
-        let mut segment: Vec<(LayoutElementId, TaffyNodeId)> = Vec::new();
+        let mut segment: Vec<(LayoutElementId, TaffyNodeId, &str)> = Vec::new();
         for entry in current_inline_group {
             match entry {
-                InlineEntry::Item(id, taffy) => segment.push((*id, *taffy)),
+                InlineEntry::Item(id, taffy, text) => segment.push((*id, *taffy, text.as_str())),
                 InlineEntry::Break(lh) => {
                     if segment.is_empty() {
-                        self.emit_line(&[], Some(*lh), element_node, leaf_id, justify);
+                        self.emit_line(&[], Some(*lh), element_node, leaf_id, line);
                     } else {
-                        self.emit_line(&segment, None, element_node, leaf_id, justify);
+                        self.emit_line(&segment, None, element_node, leaf_id, line);
                         segment.clear();
                     }
                 }
             }
         }
         if !segment.is_empty() {
-            self.emit_line(&segment, None, element_node, leaf_id, justify);
+            self.emit_line(&segment, None, element_node, leaf_id, line);
         }
     }
 
     /// Emit one line box as an anonymous flex container holding `items`. When `items` is empty and
     /// `empty_line_height` is `Some`, the container is pinned to that height so a blank line (from a
     /// standalone `<br>`) keeps its vertical extent; an empty line with no height is skipped.
+    ///
+    /// Items go into the container in bidi visual order; `element_node.children` keeps them in
+    /// logical (DOM) order.
     fn emit_line(
         &mut self,
-        items: &[(LayoutElementId, TaffyNodeId)],
+        items: &[(LayoutElementId, TaffyNodeId, &str)],
         empty_line_height: Option<f64>,
         element_node: &mut LayoutElementNode,
         leaf_id: TaffyNodeId,
-        justify: Option<taffy::JustifyContent>,
+        line: LineBoxStyle,
     ) {
         // All inline elements (even a single one) are wrapped in an anonymous flex container.
         // This ensures the text measure function always receives AvailableSpace::Definite from
         // the flex algorithm, preventing single-child text nodes from getting MaxContent width
         // (which would make them lay out on one line and overflow their block parent).
+        let vertical = line.writing_mode.is_vertical();
         let mut style = Style {
             display: Display::Flex,
-            flex_direction: FlexDirection::Row,
-            flex_wrap: FlexWrap::Wrap,
+            // Taffy has no `direction` or `writing-mode`; the line box runs along the inline axis
+            // instead, e.g. filling an RTL line from the right.
+            flex_direction: physical_flex_direction(FlexDirection::Row, line.writing_mode, line.rtl),
+            flex_wrap: if line.writing_mode.is_block_rtl() {
+                FlexWrap::WrapReverse
+            } else {
+                FlexWrap::Wrap
+            },
             // The block's `text-align`: positions runs that don't fill the line box.
-            justify_content: justify,
-            align_self: Some(AlignSelf::FLEX_START),
+            justify_content: line.justify,
+            // A vertical line box stretches to the block's height, the length its lines wrap at.
+            align_self: match (vertical, line.rtl) {
+                (true, _) => None,
+                (false, true) => Some(AlignSelf::FLEX_END),
+                (false, false) => Some(AlignSelf::FLEX_START),
+            },
             // FlexStart ensures multi-row intrinsic height = sum of all row heights.
             // Taffy's default (None = Stretch) fails to include wrapped rows in the
             // container's auto height, causing rows beyond the first to overflow.
@@ -552,6 +1056,7 @@ impl TaffyLayouter {
         if items.is_empty() {
             match empty_line_height {
                 // No child can give the line height, so pin it to the break's line-height.
+                Some(lh) if vertical => style.size.width = Dimension::from_length(lh as f32),
                 Some(lh) => style.size.height = Dimension::from_length(lh as f32),
                 None => return,
             }
@@ -564,10 +1069,19 @@ impl TaffyLayouter {
             log::warn!("Failed to add anonymous container to taffy tree: {:?}", e);
         }
 
-        for (inline_layout_element_id, inline_taffy_node_id) in items {
-            if let Err(e) = self.tree.add_child(taffy_container_id, *inline_taffy_node_id) {
+        let order = if line.override_bidi {
+            (0..items.len()).collect()
+        } else {
+            let texts: Vec<&str> = items.iter().map(|(_, _, text)| *text).collect();
+            bidi::visual_order(&texts, line.rtl)
+        };
+        for &i in &order {
+            if let Err(e) = self.tree.add_child(taffy_container_id, items[i].1) {
                 log::warn!("Failed to add inline child to taffy tree: {:?}", e);
             }
+        }
+
+        for (inline_layout_element_id, _, _) in items {
             element_node.children.push(*inline_layout_element_id);
             // Record that this layout element sits inside an anonymous container so that
             // populate_boxmodel can add the container's taffy-computed offset.
@@ -622,9 +1136,9 @@ impl TaffyLayouter {
 
         for tok in tokens {
             let mut token_node = text_node.clone();
-            token_node.node_type = NodeType::Text(tok);
-            if let Some(pair) = self.build_text_word_leaf(layout_tree, &token_node, render_node_id) {
-                group.push(InlineEntry::Item(pair.0, pair.1));
+            token_node.node_type = NodeType::Text(tok.clone());
+            if let Some((id, taffy)) = self.build_text_word_leaf(layout_tree, &token_node, render_node_id) {
+                group.push(InlineEntry::Item(id, taffy, tok));
             }
         }
     }
@@ -652,7 +1166,8 @@ impl TaffyLayouter {
             box_model: box_model::BoxModel::ZERO,
             children: vec![],
             context: element_context,
-            background_media: None,
+            background_layers: Vec::new(),
+            column_rules: Vec::new(),
         };
         let layout_element_id = element_node.id;
         layout_tree.arena.insert(layout_element_id, element_node);
@@ -675,19 +1190,25 @@ impl TaffyLayouter {
 
         let (taffy_context, taffy_style) = self.extract_taffy_data(layout_tree, &dom_node)?;
 
-        // `text-align` inherits, so this is the block's computed value; the line boxes below are
-        // anonymous and have no style of their own to read.
-        let line_justify = line_box_justify(
-            &layout_tree
-                .render_tree
-                .doc
-                .get_style(dom_node.node_id, &StyleProperty::TextAlign),
-        );
+        // `text-align` and `direction` inherit, so these are the block's computed values; the line
+        // boxes below are anonymous and have no style of their own to read.
+        let line = {
+            let doc = &*layout_tree.render_tree.doc;
+            let rtl = bidi::is_rtl(doc, dom_node.node_id);
+            LineBoxStyle {
+                justify: line_box_justify(&doc.get_style(dom_node.node_id, &StyleProperty::TextAlign), rtl),
+                rtl,
+                override_bidi: bidi::bidi_mode(doc, dom_node.node_id).ends_with("override"),
+                writing_mode: writing_mode(doc, dom_node.node_id),
+            }
+        };
 
         // Flex and grid containers are formatting contexts where ALL children - inline or block -
         // are direct layout participants. Wrapping inline children in an anonymous flex container
-        // would insert an extra level that breaks the parent's `gap`, `align-items`, etc.
-        let parent_is_flex_or_grid = matches!(taffy_style.display, Display::Flex | Display::Grid);
+        // would insert an extra level that breaks the parent's `gap`, `align-items`, etc. A
+        // vertical block is a flex row only to Taffy, and keeps its line boxes.
+        let parent_is_flex_or_grid = matches!(taffy_style.display, Display::Flex | Display::Grid)
+            && !is_vertical_block(&*layout_tree.render_tree.doc, dom_node.node_id);
 
         // The context will be moved to the taffy tree, so we need to convert it before that happens.
         let element_context = match taffy_context {
@@ -704,7 +1225,7 @@ impl TaffyLayouter {
             return None;
         };
 
-        let background_media = self.resolve_background_media(layout_tree, dom_node.node_id);
+        let background_layers = self.resolve_background_layers(layout_tree, dom_node.node_id);
 
         let mut element_node = LayoutElementNode {
             id: layout_tree.next_node_id(),
@@ -715,8 +1236,18 @@ impl TaffyLayouter {
             box_model: box_model::BoxModel::ZERO,
             children: vec![],
             context: element_context,
-            background_media,
+            background_layers,
+            column_rules: Vec::new(),
         };
+        // Registered before the children so `multicol` stays in pre-order.
+        if multicol_style(&*layout_tree.render_tree.doc, dom_node.node_id, 0.0).is_some() {
+            self.multicol.push(MultiColContainer {
+                layout_id: element_node.id,
+                dom_id: dom_node.node_id,
+                columns: None,
+                height: None,
+            });
+        }
 
         // Children are tracked in both the taffy tree and the element_node's children vec.
         let mut current_inline_group = Vec::new();
@@ -823,7 +1354,8 @@ impl TaffyLayouter {
                 };
 
                 log::debug!("Pushing element as inline: {:?}", child_node.node_id);
-                current_inline_group.push(InlineEntry::Item(child_layout_element_id, child_taffy_id));
+                let bidi_text = bidi::item_text(&*layout_tree.render_tree.doc, &child_node);
+                current_inline_group.push(InlineEntry::Item(child_layout_element_id, child_taffy_id, bidi_text));
                 if is_ws {
                     trailing_ws_count += 1;
                 } else {
@@ -836,7 +1368,7 @@ impl TaffyLayouter {
 
             // Strip trailing whitespace before flushing, then flush.
             current_inline_group.truncate(current_inline_group.len().saturating_sub(trailing_ws_count));
-            self.process_inlines(&current_inline_group, &mut element_node, leaf_id, line_justify);
+            self.process_inlines(&current_inline_group, &mut element_node, leaf_id, line);
             current_inline_group = Vec::new();
             trailing_ws_count = 0;
 
@@ -848,7 +1380,7 @@ impl TaffyLayouter {
 
         // Strip trailing whitespace and deal with any remaining inline elements
         current_inline_group.truncate(current_inline_group.len().saturating_sub(trailing_ws_count));
-        self.process_inlines(&current_inline_group, &mut element_node, leaf_id, line_justify);
+        self.process_inlines(&current_inline_group, &mut element_node, leaf_id, line);
 
         // The layout-tree is the structure handed to the rest of the pipeline; taffy stays
         // internal to this layouter so other layout engines can be swapped in.
@@ -871,20 +1403,35 @@ impl TaffyLayouter {
         Some((layout_element_id, leaf_id))
     }
 
-    /// Resolves the element's CSS `background-image` (if any) to a media id: reads the computed
-    /// value, resolves the URL against the document base URL, and loads it into the media store.
-    /// Returns `None` when there is no background image or it fails to load.
-    fn resolve_background_media(&self, layout_tree: &LayoutTree, dom_node_id: DomNodeId) -> Option<BackgroundMedia> {
+    /// Resolves the element's CSS `background-image` layers: gradients pass through, and each
+    /// `url()` is resolved against the document base URL and loaded into the media store. A layer
+    /// whose image is still fetching or fails to load is dropped.
+    fn resolve_background_layers(&self, layout_tree: &LayoutTree, dom_node_id: DomNodeId) -> Vec<BackgroundLayer> {
         let doc = &layout_tree.render_tree.doc;
-        let url = match doc.get_style(dom_node_id, &StyleProperty::BackgroundImage) {
-            Value::Keyword(id) => lookup(id),
-            _ => return None,
-        };
+        doc.background_layers(dom_node_id)
+            .into_iter()
+            .filter_map(|layer| {
+                let image = match layer.image {
+                    BgImage::Gradient(g) => BackgroundImage::Gradient(g),
+                    BgImage::Url(url) => {
+                        BackgroundImage::Media(self.resolve_background_media(&url, &doc.base_url(), &layer.layout)?)
+                    }
+                };
+                Some(BackgroundLayer {
+                    image,
+                    layout: layer.layout,
+                })
+            })
+            .collect()
+    }
+
+    /// Loads one `url()` background image. Returns `None` when it is still fetching or fails.
+    fn resolve_background_media(&self, url: &str, base_url: &str, layout: &BgImageLayout) -> Option<BackgroundMedia> {
         if url.is_empty() || url.eq_ignore_ascii_case("none") {
             return None;
         }
 
-        let abs = to_absolute_url(&url, &doc.base_url());
+        let abs = to_absolute_url(url, base_url);
         // Non-blocking: while the background image is still fetching, render without it; the reflow
         // after the fetch completes paints it in.
         let media_id = match self.media_store.request_media(&abs) {
@@ -892,33 +1439,26 @@ impl TaffyLayouter {
             MediaRequest::Pending => return None,
         };
 
-        let layout = doc.background_image_layout(dom_node_id);
-
-        // Store the intrinsic size + layout; the painter finalizes the tile geometry once the box
-        // is known. A raster image is used directly. An SVG background is rasterized to a raster
-        // tile at a box-independent size (its intrinsic size, or an explicit `background-size`
-        // length) so it reuses the single raster path for repeat / cover / contain; `compute_bg_tiling`
-        // then scales that raster for cover/contain once the box is known. (An SVG intrinsic size is
-        // typically large - e.g. 400×300 - so cover/contain downscale and stay crisp.)
+        // Store the intrinsic size; the painter finalizes the tile geometry once the box is known.
+        // A raster image is used directly. An SVG background is rasterized to a raster tile at a
+        // box-independent size (its intrinsic size, or an explicit px `background-size`) so it
+        // reuses the single raster path for repeat / cover / contain; `compute_bg_tiling` then
+        // scales that raster once the box is known. (An SVG intrinsic size is typically large -
+        // e.g. 400×300 - so cover/contain downscale and stay crisp.)
         match &*self.media_store.get(media_id, MediaType::Image) {
             Media::Image(mi) => Some(BackgroundMedia::Image {
                 media_id,
                 natural: (mi.image.width() as f32, mi.image.height() as f32),
-                layout,
             }),
             Media::Svg(ms) => {
                 let size = ms.svg.tree.size();
-                let (rw, rh) = match layout.size {
-                    BgSize::Length(w, h) => (w, h),
-                    _ => (size.width(), size.height()),
-                };
+                let (rw, rh) = layout.size.lengths().unwrap_or((size.width(), size.height()));
                 let rw = (rw.round() as u32).max(1);
                 let rh = (rh.round() as u32).max(1);
                 match self.media_store.svg_raster_tile(media_id, rw, rh) {
                     Some(raster_id) => Some(BackgroundMedia::Image {
                         media_id: raster_id,
                         natural: (rw as f32, rh as f32),
-                        layout,
                     }),
                     // Rasterization failed - fall back to the (stretch) SVG paint path.
                     None => Some(BackgroundMedia::Svg(media_id)),
@@ -927,6 +1467,24 @@ impl TaffyLayouter {
         }
     }
 
+    /// The `poster` image of a video and its pixel size, once loaded. Like `<img>`, an uncached
+    /// poster starts a background fetch and the reflow after it lands picks it up; a poster that
+    /// fails to load or is an SVG shows nothing.
+    fn poster(&self, layout_tree: &LayoutTree, poster: Option<&str>) -> Option<(MediaId, (u32, u32))> {
+        let poster = poster.map(str::trim).filter(|s| !s.is_empty())?;
+        let src = to_absolute_url(poster, &layout_tree.render_tree.doc.base_url());
+        let MediaRequest::Ready(media_id) = self.media_store.request_media(src.as_str()) else {
+            return None;
+        };
+        if self.media_store.is_placeholder(media_id) {
+            return None;
+        }
+        match self.media_store.get(media_id, MediaType::Image).borrow() {
+            Media::Image(media_image) => Some((media_id, (media_image.image.width(), media_image.image.height()))),
+            Media::Svg(_) => None,
+        }
+    }
+
     /// Extracts taffy variables based the DOM node. It will generate the taffy style based on the node CSS properties,
     /// any context that might be needed (images, svg, text).
     fn extract_taffy_data(&self, layout_tree: &LayoutTree, dom_node: &Node) -> Option<(Option<TaffyContext>, Style)> {
@@ -941,19 +1499,32 @@ impl TaffyLayouter {
                 // Images get a taffy context so their intrinsic size participates in layout.
                 if data.tag_name.eq_ignore_ascii_case("img") {
                     let base_url = layout_tree.render_tree.doc.base_url();
-                    let Some(src) = data.get_attribute("src") else {
+                    let doc = &*layout_tree.render_tree.doc;
+                    let Some(source) = select_image_source(doc, dom_node.node_id, &self.image_environment) else {
                         log::warn!("img element missing src attribute");
                         return None;
                     };
-                    let src = to_absolute_url(src, &base_url);
+                    let src = to_absolute_url(&source.url, &base_url);
 
                     log::debug!("Loading (image) resource: {}", src);
 
                     // Non-blocking: an uncached image kicks off a background fetch and returns
                     // Pending without stalling layout. The element is kept with a placeholder size
                     // (HTML width/height attrs if present, else 0×0); a reflow lands once the fetch
-                    // completes and installs the real intrinsic size.
-                    match self.media_store.request_media(src.as_str()) {
+                    // completes and installs the real intrinsic size. A lazy image isn't fetched
+                    // at all until the engine finds its box near the viewport.
+                    let lazy = data
+                        .get_attribute("loading")
+                        .is_some_and(|l| l.trim().eq_ignore_ascii_case("lazy"));
+                    let request = match self.media_store.cached(src.as_str()) {
+                        Some(media_id) => MediaRequest::Ready(media_id),
+                        None if lazy => {
+                            self.deferred_images.lock().push((dom_node.node_id, src.clone()));
+                            MediaRequest::Pending
+                        }
+                        None => self.media_store.request_media(src.as_str()),
+                    };
+                    match request {
                         MediaRequest::Ready(media_id) => {
                             let media = self.media_store.get(media_id, MediaType::Image);
                             // When the media is a placeholder (load failed), use a small fixed
@@ -972,7 +1543,10 @@ impl TaffyLayouter {
                                         geo::Dimension::new(32.0, 32.0)
                                     } else {
                                         let size = media_svg.svg.tree.size();
-                                        geo::Dimension::new(size.width() as f64, size.height() as f64)
+                                        geo::Dimension::new(
+                                            size.width() as f64 / source.density,
+                                            size.height() as f64 / source.density,
+                                        )
                                     };
                                     (d, true, false)
                                 }
@@ -980,9 +1554,10 @@ impl TaffyLayouter {
                                     let d = if is_placeholder {
                                         geo::Dimension::new(32.0, 32.0)
                                     } else {
+                                        // A `2x` candidate covers half as many CSS pixels
                                         geo::Dimension::new(
-                                            media_image.image.width() as f64,
-                                            media_image.image.height() as f64,
+                                            media_image.image.width() as f64 / source.density,
+                                            media_image.image.height() as f64 / source.density,
                                         )
                                     };
                                     // `.all()` short-circuits on the first opaque pixel, so this is
@@ -1060,11 +1635,52 @@ impl TaffyLayouter {
                     }
                 }
 
+                // An iframe is replaced by its nested document; the HTML width/height attributes
+                // override the 300×150 default object size.
+                if data.tag_name.eq_ignore_ascii_case("iframe") {
+                    let attr = |name| data.get_attribute(name).and_then(|s| parse_px_attr(s));
+                    let dimension = geo::Dimension::new(
+                        attr("width").unwrap_or(DEFAULT_FRAME_WIDTH) as f64,
+                        attr("height").unwrap_or(DEFAULT_FRAME_HEIGHT) as f64,
+                    );
+                    taffy_context = Some(TaffyContext::Frame(ElementContextFrame {
+                        node_id: dom_node.node_id,
+                        dimension,
+                    }));
+                }
+
+                // Video and audio are replaced by what the engine decodes. A loaded poster gives the
+                // element its size when the width/height attributes don't.
+                let audio = data.tag_name.eq_ignore_ascii_case("audio");
+                if audio || data.tag_name.eq_ignore_ascii_case("video") {
+                    let poster = if audio {
+                        None
+                    } else {
+                        self.poster(layout_tree, data.get_attribute("poster").map(String::as_str))
+                    };
+                    let attr = |name| data.get_attribute(name).and_then(|s| parse_px_attr(s));
+                    let (default_width, default_height) = match poster {
+                        Some((_, (w, h))) => (w as f32, h as f32),
+                        None => (DEFAULT_FRAME_WIDTH, DEFAULT_FRAME_HEIGHT),
+                    };
+                    let dimension = geo::Dimension::new(
+                        attr("width").unwrap_or(default_width) as f64,
+                        attr("height").unwrap_or(default_height) as f64,
+                    );
+                    taffy_context = Some(TaffyContext::Media(ElementContextMedia {
+                        node_id: dom_node.node_id,
+                        dimension,
+                        audio,
+                        controls: data.get_attribute("controls").is_some(),
+                        poster,
+                    }));
+                }
+
                 if data.tag_name.eq_ignore_ascii_case("svg") {
-                    let inner_html = layout_tree.render_tree.doc.inner_html(dom_node.node_id);
+                    let svg_source = layout_tree.render_tree.doc.outer_xml(dom_node.node_id);
                     match self
                         .media_store
-                        .load_media_from_data(MediaType::Svg, inner_html.into_bytes().as_slice())
+                        .load_media_from_data(MediaType::Svg, svg_source.into_bytes().as_slice())
                     {
                         Ok(media_id) => {
                             let media = self.media_store.get(media_id, MediaType::Svg);
@@ -1124,13 +1740,15 @@ impl TaffyLayouter {
                     Value::Keyword(id) if lookup(id) == "italic"
                 );
 
-                // `left`/`right` are physical and `start`/`end` logical; they only coincide in LTR,
-                // which is all the pipeline handles today. Collapse them here rather than in the
-                // cascade, so the distinction survives for when direction is honoured.
+                // `left`/`right` are physical and `start`/`end` logical, and the font system aligns
+                // relative to the text's direction - so the physical sides swap in RTL.
+                let rtl = bidi::is_rtl(&**doc, dom_node.node_id);
                 let alignment = match doc.get_style(dom_node.node_id, &StyleProperty::TextAlign) {
                     Value::TextAlign(value) => match value {
                         TextAlign::Center => FontAlignment::Center,
-                        TextAlign::End | TextAlign::Right => FontAlignment::End,
+                        TextAlign::End => FontAlignment::End,
+                        TextAlign::Right if !rtl => FontAlignment::End,
+                        TextAlign::Left if rtl => FontAlignment::End,
                         TextAlign::Justify => FontAlignment::Justify,
                         _ => FontAlignment::Start,
                     },
@@ -1178,8 +1796,12 @@ impl TaffyLayouter {
                     // spaces when called with MinContent (max_advance=0), causing the flex item to
                     // collapse. flex_shrink=0 prevents the space from being squeezed away.
                     text = "\u{00A0}".to_string();
-                    let space_width = (font_size * 0.3) as f32;
-                    taffy_style.size.width = Dimension::from_length(space_width);
+                    let space_width = Dimension::from_length((font_size * 0.3) as f32);
+                    if writing_mode(&**doc, dom_node.node_id).is_vertical() {
+                        taffy_style.size.height = space_width;
+                    } else {
+                        taffy_style.size.width = space_width;
+                    }
                     taffy_style.flex_shrink = 0.0;
                 }
                 // if inline_element_counter > 0 {
@@ -1200,6 +1822,17 @@ impl TaffyLayouter {
                 // so transforming here keeps layout width and drawn glyphs in sync.
                 let text = apply_text_transform(text, doc.get_style(dom_node.node_id, &StyleProperty::TextTransform));
 
+                // `hyphens` decides which soft hyphens reach the font system; `text-overflow` and
+                // `line-clamp` are not inherited and apply to the text's block container.
+                let mut shaping = FontShaping::from_style(|prop| doc.get_style(dom_node.node_id, prop))
+                    .with_block_style(|prop| doc.get_style(block_container(&**doc, dom_node.node_id), prop));
+                shaping.breaking.nowrap = no_wrap;
+                let text = apply_hyphens(
+                    text,
+                    shaping.breaking.hyphens,
+                    doc.language(dom_node.node_id).as_deref(),
+                );
+
                 let text_decoration = match doc.get_style(dom_node.node_id, &StyleProperty::TextDecorationLine) {
                     Value::Keyword(id) => lookup(id),
                     _ => String::new(),
@@ -1223,6 +1856,7 @@ impl TaffyLayouter {
                     alignment,
                     underline: text_decoration.contains("underline"),
                     line_through: text_decoration.contains("line-through"),
+                    shaping,
                 };
 
                 taffy_context = Some(TaffyContext::text(
@@ -1244,6 +1878,46 @@ impl TaffyLayouter {
     }
 }
 
+/// Taffy's available space for a viewport; unbounded (max-content) without one.
+fn available_space(viewport: Option<geo::Dimension>) -> Size<AvailableSpace> {
+    match viewport {
+        Some(viewport) => Size {
+            width: AvailableSpace::Definite(viewport.width as f32),
+            height: AvailableSpace::Definite(viewport.height as f32),
+        },
+        None => Size::MAX_CONTENT,
+    }
+}
+
+/// Whether a layout node belongs to a restyled element: the element itself, or one of its
+/// generated boxes.
+fn is_restyled(restyled: &HashSet<DomNodeId>, dom_id: DomNodeId) -> bool {
+    restyled.contains(&dom_id) || pseudo_owner(dom_id).is_some_and(|owner| restyled.contains(&owner))
+}
+
+/// Stores each container's `column-rule` segments as absolute rects, relative to where its
+/// content box ended up after every column pass.
+fn set_column_rules(layout_tree: &mut LayoutTree, layouts: &[(LayoutElementId, MultiColLayout)]) {
+    for (layout_id, layout) in layouts {
+        let Some(element) = layout_tree.get_node_by_id_mut(*layout_id) else {
+            continue;
+        };
+        let content_box = element.box_model.content_box;
+        element.column_rules = layout
+            .rules
+            .iter()
+            .map(|rule| {
+                geo::Rect::new(
+                    content_box.x + rule.x as f64,
+                    content_box.y + rule.y as f64,
+                    0.0,
+                    rule.height as f64,
+                )
+            })
+            .collect();
+    }
+}
+
 // Convert a URI to an absolute URL based on the base URL if this is needed
 fn to_absolute_url(uri: &str, base_uri: &str) -> String {
     // Already-absolute references (http(s)://, file://, data:, blob:, …) are returned as-is.
@@ -1310,6 +1984,8 @@ fn to_element_context(taffy_context: Option<&TaffyContext>) -> ElementContext {
             svg_ctx.dimension,
             svg_ctx.node_id,
         ),
+        Some(TaffyContext::Frame(frame_ctx)) => ElementContext::frame(frame_ctx.dimension, frame_ctx.node_id),
+        Some(TaffyContext::Media(media_ctx)) => ElementContext::Media(media_ctx.clone()),
         None => ElementContext::None,
     }
 }
@@ -1347,8 +2023,9 @@ pub fn taffy_layout_to_boxmodel(layout: &Layout, offset: Coordinate) -> box_mode
 
 #[cfg(test)]
 mod tests {
-    use super::{apply_text_transform, to_absolute_url};
+    use super::{apply_hyphens, apply_text_transform, to_absolute_url};
     use crate::common::document::style::{intern, Value};
+    use gosub_interface::font_system::Hyphens;
 
     fn kw(s: &str) -> Value {
         Value::Keyword(intern(s))
@@ -1368,6 +2045,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hyphens_auto_inserts_soft_hyphens_for_the_language() {
+        let text = apply_hyphens("An extensive test\u{AD}ing".to_string(), Hyphens::Auto, Some("en-gb"));
+        // Words with the author's own soft hyphens keep just those.
+        assert_eq!(text, "An ex\u{AD}ten\u{AD}sive test\u{AD}ing");
+        // No dictionary for the language: behaves as `manual`.
+        assert_eq!(
+            apply_hyphens("extensive".to_string(), Hyphens::Auto, Some("xx")),
+            "extensive"
+        );
+        assert_eq!(apply_hyphens("extensive".to_string(), Hyphens::Auto, None), "extensive");
+    }
+
+    #[test]
+    fn hyphens_none_removes_soft_hyphens() {
+        assert_eq!(
+            apply_hyphens("test\u{AD}ing".to_string(), Hyphens::None, None),
+            "testing"
+        );
+        assert_eq!(
+            apply_hyphens("test\u{AD}ing".to_string(), Hyphens::Manual, None),
+            "test\u{AD}ing"
+        );
+    }
+
     #[test]
     fn text_transform_none_and_unsupported_passthrough() {
         assert_eq!(apply_text_transform("Working".to_string(), kw("none")), "Working");
@@ -23,8 +23,16 @@ pub fn get_text_layout(
         // Alignment shifts lines within max_width but never changes the bounding box, so
         // measurement always shapes start-aligned.
         align: TextAlign::Start,
+        direction: font_info.shaping.direction,
+        writing_mode: font_info.shaping.writing_mode,
+        orientation: font_info.shaping.orientation,
         // The layouter works in CSS pixels; DPI scaling is applied later in the pipeline.
         display_scale: 1.0,
+        features: font_info.shaping.features.clone(),
+        variations: font_info.shaping.variations.clone(),
+        kerning: font_info.shaping.kerning,
+        optical_sizing: font_info.shaping.optical_sizing,
+        line_breaking: font_info.shaping.breaking,
     };
 
     let (width, height) = font_system.measure(text, &style);
@@ -0,0 +1,107 @@
+//! Vertical writing modes.
+//!
+//! Taffy only knows physical axes, so the logical flow is mapped onto them: a flex `row` runs
+//! along the inline axis and a `column` along the block axis (see [`physical_flex_direction`]). In
+//! a vertical writing mode a block container becomes a flex row stacking its blocks towards the
+//! left (`*-rl`) or right (`*-lr`), and a line box a wrapping flex column whose lines stack the same
+//! way. Text is measured and shaped vertically by the font system, wrapping at the available
+//! height.
+
+use crate::common::document::node::NodeId as DomNodeId;
+use crate::common::document::pipeline_doc::PipelineDocument;
+use crate::common::document::style::{lookup, Display, StyleProperty, Value};
+use gosub_interface::font_system::WritingMode;
+use taffy::FlexDirection;
+
+/// The element's computed `writing-mode`.
+pub fn writing_mode(doc: &dyn PipelineDocument, id: DomNodeId) -> WritingMode {
+    match doc.get_style(id, &StyleProperty::WritingMode) {
+        Value::Keyword(kw) => WritingMode::from_keyword(&lookup(kw)).unwrap_or_default(),
+        _ => WritingMode::HorizontalTb,
+    }
+}
+
+/// Whether the element is a block container in a vertical writing mode, which Taffy lays out as a
+/// flex row.
+pub fn is_vertical_block(doc: &dyn PipelineDocument, id: DomNodeId) -> bool {
+    matches!(
+        doc.get_own_style(id, &StyleProperty::Display),
+        Some(Value::Display(Display::Block | Display::ListItem))
+    ) && writing_mode(doc, id).is_vertical()
+}
+
+/// The physical direction Taffy lays out a logical flex direction in: `row` follows the inline
+/// axis (reversed by `rtl`), `column` the block axis.
+pub fn physical_flex_direction(direction: FlexDirection, mode: WritingMode, rtl: bool) -> FlexDirection {
+    // `sideways-lr` lines read bottom to top.
+    let inline_reversed = rtl != (mode == WritingMode::SidewaysLr);
+    let (inline, block) = if mode.is_vertical() {
+        let block = if mode.is_block_rtl() {
+            FlexDirection::RowReverse
+        } else {
+            FlexDirection::Row
+        };
+        (FlexDirection::Column, block)
+    } else {
+        (FlexDirection::Row, FlexDirection::Column)
+    };
+    let inline = if inline_reversed { reversed(inline) } else { inline };
+    match direction {
+        FlexDirection::Row => inline,
+        FlexDirection::RowReverse => reversed(inline),
+        FlexDirection::Column => block,
+        FlexDirection::ColumnReverse => reversed(block),
+    }
+}
+
+fn reversed(direction: FlexDirection) -> FlexDirection {
+    match direction {
+        FlexDirection::Row => FlexDirection::RowReverse,
+        FlexDirection::RowReverse => FlexDirection::Row,
+        FlexDirection::Column => FlexDirection::ColumnReverse,
+        FlexDirection::ColumnReverse => FlexDirection::Column,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_directions_land_on_physical_axes() {
+        let physical = physical_flex_direction;
+        assert_eq!(
+            physical(FlexDirection::Row, WritingMode::HorizontalTb, false),
+            FlexDirection::Row
+        );
+        assert_eq!(
+            physical(FlexDirection::Row, WritingMode::HorizontalTb, true),
+            FlexDirection::RowReverse
+        );
+        assert_eq!(
+            physical(FlexDirection::Column, WritingMode::HorizontalTb, true),
+            FlexDirection::Column
+        );
+
+        assert_eq!(
+            physical(FlexDirection::Row, WritingMode::VerticalRl, false),
+            FlexDirection::Column
+        );
+        assert_eq!(
+            physical(FlexDirection::Column, WritingMode::VerticalRl, false),
+            FlexDirection::RowReverse
+        );
+        assert_eq!(
+            physical(FlexDirection::ColumnReverse, WritingMode::VerticalLr, false),
+            FlexDirection::RowReverse
+        );
+        assert_eq!(
+            physical(FlexDirection::Row, WritingMode::VerticalLr, true),
+            FlexDirection::ColumnReverse
+        );
+        assert_eq!(
+            physical(FlexDirection::Row, WritingMode::SidewaysLr, false),
+            FlexDirection::ColumnReverse
+        );
+    }
+}
@@ -1,10 +1,17 @@
+pub mod animation;
 pub mod common;
+pub mod find;
+pub mod image_source;
+pub mod invalidation;
 pub mod layering;
 pub mod layouter;
+pub mod media_element;
+pub mod paged;
 pub mod painter;
 pub mod rasterizer;
 pub mod render;
 pub mod rendertree_builder;
+pub mod selection;
 #[cfg(test)]
 mod tests;
 pub mod tiler;
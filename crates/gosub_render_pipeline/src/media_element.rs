@@ -0,0 +1,153 @@
+//! `<video>` and `<audio>` elements: what the engine hands the painter for each of them, and the
+//! geometry of the native controls.
+//!
+//! The engine decodes and times the media; the pipeline only paints the result. A video shows its
+//! current frame - or its poster until playback starts - letterboxed into the content box the way
+//! `object-fit: contain` places an image. With `controls`, a bar along the bottom of the box holds
+//! a play/pause button and a progress track. An audio element has nothing to show but the bar, so
+//! its bar fills the box.
+
+use crate::common::geo::Rect;
+use crate::common::media::{MediaId, MediaStore, MediaType};
+
+/// Height of a video's controls bar in CSS pixels.
+pub const CONTROLS_HEIGHT: f64 = 32.0;
+const BUTTON_SIZE: f64 = 20.0;
+const TRACK_HEIGHT: f64 = 4.0;
+const PADDING: f64 = 6.0;
+
+const PLAY_ICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 20 20"><path d="M6 4 L16 10 L6 16 Z" fill="#ffffff"/></svg>"##;
+const PAUSE_ICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 20 20"><rect x="5" y="4" width="3.5" height="12" fill="#ffffff"/><rect x="11.5" y="4" width="3.5" height="12" fill="#ffffff"/></svg>"##;
+
+/// The playback state of one media element, as the painter draws it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MediaPresentation {
+    /// The video frame on screen. `None` before one is decoded, and always for audio; the poster
+    /// (if any) shows instead.
+    pub frame: Option<MediaId>,
+    /// Pixel size of `frame`.
+    pub frame_size: (u32, u32),
+    pub playing: bool,
+    /// Playback position as a fraction of the duration, in `0.0..=1.0`.
+    pub progress: f32,
+    /// The play/pause button's icon, see [`control_icon`].
+    pub icon: Option<MediaId>,
+}
+
+/// The button icon for an element that is `playing` (pause) or not (play). The icons are cached
+/// by content, so asking again returns the same id.
+pub fn control_icon(media_store: &MediaStore, playing: bool) -> Option<MediaId> {
+    let svg = if playing { PAUSE_ICON } else { PLAY_ICON };
+    match media_store.load_media_from_data(MediaType::Svg, svg.as_bytes()) {
+        Ok(media_id) => Some(media_id),
+        Err(e) => {
+            log::warn!("Failed to load media control icon: {e}");
+            None
+        }
+    }
+}
+
+/// Where the parts of a media element's controls sit, in page space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaControls {
+    pub bar: Rect,
+    /// The play/pause button.
+    pub button: Rect,
+    /// The progress track, filled up to the playback position.
+    pub track: Rect,
+}
+
+impl MediaControls {
+    /// The controls of a media element with this content box: along its bottom edge for video,
+    /// the whole box for audio.
+    pub fn new(content_box: Rect, audio: bool) -> Self {
+        let height = if audio {
+            content_box.height
+        } else {
+            CONTROLS_HEIGHT.min(content_box.height)
+        };
+        let bar = Rect::new(
+            content_box.x,
+            content_box.y + content_box.height - height,
+            content_box.width,
+            height,
+        );
+        let button_size = BUTTON_SIZE.min(height);
+        let button = Rect::new(
+            bar.x + PADDING,
+            bar.y + (height - button_size) / 2.0,
+            button_size,
+            button_size,
+        );
+        let track_x = button.x + button.width + PADDING;
+        let track_height = TRACK_HEIGHT.min(height);
+        let track = Rect::new(
+            track_x,
+            bar.y + (height - track_height) / 2.0,
+            (bar.x + bar.width - PADDING - track_x).max(0.0),
+            track_height,
+        );
+        Self { bar, button, track }
+    }
+
+    /// The played part of the track at `progress` (`0.0..=1.0`).
+    pub fn played(&self, progress: f32) -> Rect {
+        let mut played = self.track;
+        played.width *= progress.clamp(0.0, 1.0) as f64;
+        played
+    }
+
+    /// Whether page point `(x, y)` is on the play/pause button.
+    pub fn button_contains(&self, x: f64, y: f64) -> bool {
+        let b = self.button;
+        x >= b.x && x < b.x + b.width && y >= b.y && y < b.y + b.height
+    }
+}
+
+/// Where an image of `natural` pixel size is drawn in `area` under `object-fit: contain`: as large
+/// as fits with its aspect ratio kept, centred.
+pub fn contain_rect(area: Rect, natural: (u32, u32)) -> Rect {
+    let (w, h) = (natural.0 as f64, natural.1 as f64);
+    if w <= 0.0 || h <= 0.0 {
+        return area;
+    }
+    let scale = (area.width / w).min(area.height / h);
+    let (width, height) = (w * scale, h * scale);
+    Rect::new(
+        area.x + (area.width - width) / 2.0,
+        area.y + (area.height - height) / 2.0,
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contain_letterboxes_and_centres() {
+        let area = Rect::new(10.0, 20.0, 300.0, 150.0);
+        // 4:3 in a 2:1 box: full height, pillarboxed
+        assert_eq!(contain_rect(area, (640, 480)), Rect::new(60.0, 20.0, 200.0, 150.0));
+        // 4:1 in a 2:1 box: full width, letterboxed
+        assert_eq!(contain_rect(area, (400, 100)), Rect::new(10.0, 57.5, 300.0, 75.0));
+        assert_eq!(contain_rect(area, (0, 0)), area);
+    }
+
+    #[test]
+    fn controls_sit_along_the_bottom_of_a_video() {
+        let controls = MediaControls::new(Rect::new(0.0, 0.0, 300.0, 150.0), false);
+        assert_eq!(controls.bar, Rect::new(0.0, 118.0, 300.0, CONTROLS_HEIGHT));
+        assert!(controls.button_contains(10.0, 130.0));
+        assert!(!controls.button_contains(10.0, 50.0));
+        assert_eq!(controls.track.x, 32.0);
+        assert_eq!(controls.track.width, 262.0);
+        assert_eq!(controls.played(0.5).width, 131.0);
+        assert_eq!(controls.played(2.0).width, 262.0);
+
+        let audio = MediaControls::new(Rect::new(0.0, 0.0, 300.0, 54.0), true);
+        assert_eq!(audio.bar, Rect::new(0.0, 0.0, 300.0, 54.0));
+        assert_eq!(audio.button.y, 17.0);
+    }
+}
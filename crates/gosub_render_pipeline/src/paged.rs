@@ -0,0 +1,686 @@
+//! Paged media: laying a document out on printed pages.
+//!
+//! The document is laid out once, at the width of the page content box (with `@media print` rules
+//! in effect), and painted as a whole. [`fragment`] then cuts the laid-out page into page-high
+//! slices: at forced breaks (`break-before`/`break-after: page`), and otherwise as late as possible
+//! without splitting a line of text, a replaced element or a `break-inside: avoid` box. [`paginate`]
+//! hands every page the paint commands that touch its slice, plus the page-margin boxes of its
+//! `@page` rules (`@bottom-center { content: counter(page) }`), for a paged backend such as a PDF
+//! writer to draw.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use cow_utils::CowUtils;
+use gosub_interface::css3::{PageMarginRule, PageRule};
+
+use crate::common::browser_state::BrowserState;
+use crate::common::document::style::{lookup, StyleProperty, Value};
+use crate::common::font::{FontAlignment, FontInfo, FontShaping};
+use crate::common::geo::{Dimension, Rect};
+use crate::common::media::MediaStore;
+use crate::layouter::{ElementContext, LayoutElementId, LayoutTree};
+use crate::painter::commands::brush::Brush;
+use crate::painter::commands::color::Color;
+use crate::painter::commands::shadow::{parse_length, shadows_overflow};
+use crate::painter::commands::{PaintCommand, Trbl};
+use crate::painter::Painter;
+use crate::render::backend::TileAnchor;
+
+/// Page size when no `@page` rule sets one: ISO A4 (210mm x 297mm) in CSS px.
+pub const DEFAULT_PAGE_SIZE: Dimension = Dimension {
+    width: 793.7,
+    height: 1122.5,
+};
+
+/// Page margin when no `@page` rule sets one: 1cm on every side.
+const DEFAULT_MARGIN: f64 = 96.0 / 2.54;
+
+/// Font size of page-margin box content unless the box sets `font-size`.
+const MARGIN_BOX_FONT_SIZE: f64 = 12.0;
+
+/// A document laid out on pages, ready for a paged backend.
+#[derive(Clone)]
+pub struct PrintedDocument {
+    pub pages: Vec<PrintedPage>,
+    pub media_store: Arc<MediaStore>,
+}
+
+impl std::fmt::Debug for PrintedDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrintedDocument")
+            .field("pages", &self.pages)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One printed page.
+#[derive(Clone, Debug)]
+pub struct PrintedPage {
+    /// The page size in CSS px.
+    pub size: Dimension,
+    /// The page area inside the margins, in page coordinates.
+    pub content_box: Rect,
+    /// The part of the laid-out document this page shows, in document coordinates. It is drawn at
+    /// the top-left of `content_box` and clipped to its own size, which is at most the content box
+    /// (less where a break was moved up to keep a line together).
+    pub slice: Rect,
+    /// The document's paint commands that reach into `slice`, in document coordinates.
+    pub commands: Vec<PaintCommand>,
+    /// The page-margin boxes' content, in page coordinates.
+    pub margin_commands: Vec<PaintCommand>,
+}
+
+/// The resolved `@page` properties of one page.
+#[derive(Clone, Debug)]
+pub struct PageStyle {
+    /// The page size in CSS px.
+    pub size: Dimension,
+    pub margin: Trbl<f64>,
+    /// Page-margin boxes with their declarations merged in cascade order.
+    pub margin_boxes: Vec<PageMarginRule>,
+}
+
+impl PageStyle {
+    /// The page area inside the margins, in page coordinates.
+    pub fn content_box(&self) -> Rect {
+        Rect::new(
+            self.margin.left,
+            self.margin.top,
+            (self.size.width - self.margin.left - self.margin.right).max(1.0),
+            (self.size.height - self.margin.top - self.margin.bottom).max(1.0),
+        )
+    }
+}
+
+/// The named page size `name` (`A4`, `letter`, ...) in portrait orientation, in CSS px.
+pub fn named_page_size(name: &str) -> Option<Dimension> {
+    let mm = |w: f64, h: f64| Dimension::new(w * 96.0 / 25.4, h * 96.0 / 25.4);
+    let inch = |w: f64, h: f64| Dimension::new(w * 96.0, h * 96.0);
+    let size = match name.cow_to_ascii_lowercase().as_ref() {
+        "a5" => mm(148.0, 210.0),
+        "a4" => mm(210.0, 297.0),
+        "a3" => mm(297.0, 420.0),
+        "b5" => mm(176.0, 250.0),
+        "b4" => mm(250.0, 353.0),
+        "jis-b5" => mm(182.0, 257.0),
+        "jis-b4" => mm(257.0, 364.0),
+        "letter" => inch(8.5, 11.0),
+        "legal" => inch(8.5, 14.0),
+        "ledger" => inch(11.0, 17.0),
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// Whether page `index` (0-based) is a right page. The first page of a left-to-right document is
+/// a right page.
+fn is_right_page(index: usize) -> bool {
+    index.is_multiple_of(2)
+}
+
+/// Resolves the `@page` rules that apply to page `index` (0-based). Rules cascade by selector
+/// specificity, then source order: a bare `@page`, then `:left`/`:right`, then `:first`. Named
+/// pages are not supported, so rules for them are skipped.
+pub fn page_style(rules: &[PageRule], index: usize, default_size: Dimension) -> PageStyle {
+    let specificity = |selector: &str| -> Option<u8> {
+        match selector.trim() {
+            "" => Some(0),
+            ":left" => (!is_right_page(index)).then_some(1),
+            ":right" => is_right_page(index).then_some(1),
+            ":first" => (index == 0).then_some(2),
+            _ => None,
+        }
+    };
+    let mut matching: Vec<(u8, &PageRule)> = rules
+        .iter()
+        .filter_map(|rule| Some((rule.selectors.iter().filter_map(|s| specificity(s)).max()?, rule)))
+        .collect();
+    matching.sort_by_key(|(specificity, _)| *specificity);
+
+    let mut style = PageStyle {
+        size: default_size,
+        margin: Trbl {
+            top: DEFAULT_MARGIN,
+            right: DEFAULT_MARGIN,
+            bottom: DEFAULT_MARGIN,
+            left: DEFAULT_MARGIN,
+        },
+        margin_boxes: Vec::new(),
+    };
+    for (_, rule) in matching {
+        for (property, value) in &rule.declarations {
+            apply_page_declaration(&mut style, property, value, default_size);
+        }
+        for margin_box in &rule.margin_boxes {
+            match style.margin_boxes.iter_mut().find(|b| b.name == margin_box.name) {
+                Some(existing) => existing.declarations.extend(margin_box.declarations.iter().cloned()),
+                None => style.margin_boxes.push(margin_box.clone()),
+            }
+        }
+    }
+    style
+}
+
+fn apply_page_declaration(style: &mut PageStyle, property: &str, value: &str, default_size: Dimension) {
+    let length = |token: &str| parse_length(token, 16.0).map(f64::from);
+    match property {
+        "size" => {
+            if let Some(size) = page_size(value, default_size) {
+                style.size = size;
+            }
+        }
+        "margin" => {
+            let values: Vec<f64> = value.split_whitespace().filter_map(length).collect();
+            let [top, right, bottom, left] = match values.as_slice() {
+                [all] => [*all; 4],
+                [vertical, horizontal] => [*vertical, *horizontal, *vertical, *horizontal],
+                [top, horizontal, bottom] => [*top, *horizontal, *bottom, *horizontal],
+                [top, right, bottom, left] => [*top, *right, *bottom, *left],
+                _ => return,
+            };
+            style.margin = Trbl {
+                top,
+                right,
+                bottom,
+                left,
+            };
+        }
+        "margin-top" => style.margin.top = length(value).unwrap_or(style.margin.top),
+        "margin-right" => style.margin.right = length(value).unwrap_or(style.margin.right),
+        "margin-bottom" => style.margin.bottom = length(value).unwrap_or(style.margin.bottom),
+        "margin-left" => style.margin.left = length(value).unwrap_or(style.margin.left),
+        _ => {}
+    }
+}
+
+/// The `size` descriptor: `auto`, a named size, an orientation, both, or one or two lengths.
+fn page_size(value: &str, default_size: Dimension) -> Option<Dimension> {
+    let mut size = default_size;
+    let mut lengths = Vec::new();
+    let mut orientation = None;
+    for token in value.split_whitespace() {
+        match token.cow_to_ascii_lowercase().as_ref() {
+            "auto" => {}
+            "portrait" | "landscape" => orientation = Some(token.eq_ignore_ascii_case("landscape")),
+            _ => match named_page_size(token) {
+                Some(named) => size = named,
+                None => lengths.push(parse_length(token, 16.0).map(f64::from)?),
+            },
+        }
+    }
+    match lengths.as_slice() {
+        [] => {}
+        [side] => size = Dimension::new(*side, *side),
+        [width, height] => size = Dimension::new(*width, *height),
+        _ => return None,
+    }
+    match orientation {
+        Some(true) if size.height > size.width => Some(Dimension::new(size.height, size.width)),
+        Some(false) if size.width > size.height => Some(Dimension::new(size.height, size.width)),
+        _ => Some(size),
+    }
+}
+
+/// The side of the next page a forced break asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BreakSide {
+    Any,
+    Left,
+    Right,
+}
+
+fn forced_break(keyword: &str) -> Option<BreakSide> {
+    match keyword {
+        "page" | "always" => Some(BreakSide::Any),
+        "left" | "verso" => Some(BreakSide::Left),
+        "right" | "recto" => Some(BreakSide::Right),
+        _ => None,
+    }
+}
+
+/// Splits the laid-out document into page slices, as ranges of document y. `page_height` gives the
+/// content height of each page by index. A slice is empty for the blank page a `break-before:
+/// right` (or `left`) inserts to land on the requested side.
+pub fn fragment(layout_tree: &LayoutTree, page_height: impl Fn(usize) -> f64) -> Vec<Range<f64>> {
+    let doc = &layout_tree.render_tree.doc;
+    let keyword = |id: LayoutElementId, prop: &StyleProperty| {
+        let node = layout_tree.get_node_by_id(id)?;
+        match doc.get_own_style(node.dom_node_id, prop) {
+            Some(Value::Keyword(kw)) => Some(lookup(kw)),
+            _ => None,
+        }
+    };
+
+    let mut breaks = Vec::new();
+    let mut keeps = Vec::new();
+    let mut stack = vec![layout_tree.root_id];
+    while let Some(id) = stack.pop() {
+        let Some(node) = layout_tree.get_node_by_id(id) else {
+            continue;
+        };
+        let margin_box = node.box_model.margin_box;
+        if let Some(side) = keyword(id, &StyleProperty::BreakBefore)
+            .as_deref()
+            .and_then(forced_break)
+        {
+            breaks.push((margin_box.y, side));
+        }
+        if let Some(side) = keyword(id, &StyleProperty::BreakAfter)
+            .as_deref()
+            .and_then(forced_break)
+        {
+            breaks.push((margin_box.y + margin_box.height, side));
+        }
+
+        let border_box = node.box_model.border_box;
+        match &node.context {
+            // Each line of a text run stays whole.
+            ElementContext::Text(ctx) if ctx.font_info.line_height > 0.0 => {
+                let content_box = node.box_model.content_box;
+                let line_height = ctx.font_info.line_height;
+                let mut top = content_box.y;
+                while top < content_box.y + content_box.height {
+                    keeps.push(top..top + line_height);
+                    top += line_height;
+                }
+            }
+            // So do replaced elements and other leaves.
+            _ if node.children.is_empty() => keeps.push(border_box.y..border_box.y + border_box.height),
+            _ => {
+                if matches!(
+                    keyword(id, &StyleProperty::BreakInside).as_deref(),
+                    Some("avoid" | "avoid-page")
+                ) {
+                    keeps.push(border_box.y..border_box.y + border_box.height);
+                }
+            }
+        }
+        stack.extend(node.children.iter().rev());
+    }
+    breaks.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    split_pages(layout_tree.root_dimension.height, &breaks, &keeps, page_height)
+}
+
+/// The page-splitting core of [`fragment`], over the document height, its forced breaks (document
+/// y and requested side, sorted) and the ranges that should not be split across pages.
+fn split_pages(
+    doc_height: f64,
+    breaks: &[(f64, BreakSide)],
+    keeps: &[Range<f64>],
+    page_height: impl Fn(usize) -> f64,
+) -> Vec<Range<f64>> {
+    let mut pages: Vec<Range<f64>> = Vec::new();
+    let mut start = 0.0;
+    let mut next_break = 0;
+    let mut side = BreakSide::Any;
+    while start < doc_height || pages.is_empty() {
+        // A forced break onto a given side skips a page when the next one is on the other side.
+        let wrong_side = match side {
+            BreakSide::Any => false,
+            BreakSide::Left => is_right_page(pages.len()),
+            BreakSide::Right => !is_right_page(pages.len()),
+        };
+        if wrong_side {
+            pages.push(start..start);
+            continue;
+        }
+
+        let height = page_height(pages.len()).max(1.0);
+        let mut end = (start + height).min(doc_height.max(start));
+
+        // Breaks at the very top of a page are already satisfied.
+        while breaks.get(next_break).is_some_and(|(y, _)| *y <= start + 0.5) {
+            next_break += 1;
+        }
+        side = BreakSide::Any;
+        if let Some((y, break_side)) = breaks.get(next_break).filter(|(y, _)| *y <= end) {
+            end = *y;
+            side = *break_side;
+            next_break += 1;
+        } else if end < doc_height {
+            // Move the break up to the start of whatever it would cut through, unless that starts
+            // at the top of the page (it is taller than the page and has to be split anyway).
+            while let Some(top) = keeps
+                .iter()
+                .filter(|keep| keep.start > start + 0.5 && keep.start < end && keep.end > end + 0.5)
+                .map(|keep| keep.start)
+                .min_by(f64::total_cmp)
+            {
+                end = top;
+            }
+        }
+
+        pages.push(start..end);
+        if end <= start {
+            break;
+        }
+        start = end;
+    }
+    pages
+}
+
+/// Lays the painted document out on pages. `commands` is the whole document painted in one list
+/// (see [`Painter::paint_all`]); `rules` are the document's `@page` rules.
+pub fn paginate(
+    layout_tree: &LayoutTree,
+    painter: &Painter,
+    state: &BrowserState,
+    rules: &[PageRule],
+    default_size: Dimension,
+) -> Vec<PrintedPage> {
+    let styles = |index: usize| page_style(rules, index, default_size);
+    let slices = fragment(layout_tree, |index| styles(index).content_box().height);
+    let commands = painter.paint_all(state);
+    let page_count = slices.len();
+
+    slices
+        .into_iter()
+        .enumerate()
+        .map(|(index, range)| {
+            let style = styles(index);
+            let content_box = style.content_box();
+            let slice = Rect::new(0.0, range.start, content_box.width, range.end - range.start);
+            PrintedPage {
+                size: style.size,
+                content_box,
+                commands: if slice.height > 0.0 {
+                    commands_in_slice(&commands, slice)
+                } else {
+                    Vec::new()
+                },
+                margin_commands: margin_box_commands(&style, painter, index + 1, page_count),
+                slice,
+            }
+        })
+        .collect()
+}
+
+/// The commands of `commands` that reach into the document slice `slice`. Layer groups are kept
+/// so they stay balanced; a `position: fixed` group repeats on every page, at the same place.
+fn commands_in_slice(commands: &[PaintCommand], slice: Rect) -> Vec<PaintCommand> {
+    let mut out = Vec::new();
+    // The page-space offset of the enclosing group's content.
+    let mut offsets = vec![0.0];
+    for command in commands {
+        let offset = offsets.last().copied().unwrap_or_default();
+        let bounds = match command {
+            PaintCommand::PushLayer {
+                opacity,
+                anchor,
+                blend_mode,
+                isolated,
+                filters,
+                translate,
+            } => {
+                let shift = if matches!(anchor, TileAnchor::Fixed) {
+                    slice.y
+                } else {
+                    0.0
+                };
+                offsets.push(translate.1 + shift);
+                out.push(PaintCommand::PushLayer {
+                    opacity: *opacity,
+                    anchor: *anchor,
+                    blend_mode: *blend_mode,
+                    isolated: *isolated,
+                    filters: filters.clone(),
+                    translate: (translate.0, translate.1 + shift),
+                });
+                continue;
+            }
+            PaintCommand::PopLayer => {
+                offsets.pop();
+                out.push(PaintCommand::PopLayer);
+                continue;
+            }
+            PaintCommand::Text(text) => shadows_overflow(&text.shadows).outset(text.rect),
+            PaintCommand::Rectangle(rectangle) => rectangle.rect(),
+            PaintCommand::Svg(svg) => svg.rect.rect(),
+            PaintCommand::BoxShadow(shadow) => shadow.ink_rect(),
+        };
+        let top = bounds.y + offset;
+        if top < slice.y + slice.height && top + bounds.height > slice.y {
+            out.push(command.clone());
+        }
+    }
+    out
+}
+
+/// The content of the page-margin boxes of `style` for page `page` of `pages` (1-based), in page
+/// coordinates.
+fn margin_box_commands(style: &PageStyle, painter: &Painter, page: usize, pages: usize) -> Vec<PaintCommand> {
+    style
+        .margin_boxes
+        .iter()
+        .filter_map(|margin_box| {
+            let (rect, alignment) = margin_box_rect(style, &margin_box.name)?;
+            let declaration = |name: &str| {
+                margin_box
+                    .declarations
+                    .iter()
+                    .rev()
+                    .find(|(property, _)| property == name)
+                    .map(|(_, value)| value.as_str())
+            };
+            let text = margin_box_content(declaration("content")?, page, pages);
+            if text.is_empty() {
+                return None;
+            }
+
+            let size = declaration("font-size")
+                .and_then(|v| parse_length(v, MARGIN_BOX_FONT_SIZE as f32))
+                .map_or(MARGIN_BOX_FONT_SIZE, f64::from);
+            let weight = match declaration("font-weight") {
+                Some("bold" | "bolder") => 700,
+                Some(weight) => weight.parse().unwrap_or(400),
+                None => 400,
+            };
+            let font_info = FontInfo {
+                family: declaration("font-family")
+                    .map_or("sans-serif", |f| f.trim_matches(['"', '\'']))
+                    .to_string(),
+                size,
+                weight,
+                width: 100,
+                slant: 0,
+                line_height: size * 1.2,
+                letter_spacing: 0.0,
+                alignment,
+                underline: false,
+                line_through: false,
+                shaping: FontShaping::default(),
+            };
+            let color = declaration("color")
+                .and_then(Color::try_from_css)
+                .unwrap_or(Color::BLACK);
+
+            // One line, centred vertically in the box.
+            let line = Rect::new(
+                rect.x,
+                rect.y + ((rect.height - font_info.line_height) / 2.0).max(0.0),
+                rect.width,
+                font_info.line_height,
+            );
+            Some(painter.text_command(line, &text, &font_info, Brush::solid(color)))
+        })
+        .collect()
+}
+
+/// The area of margin box `name` on the page, and how its content is aligned in it. The boxes
+/// along each edge split it into three equal parts.
+fn margin_box_rect(style: &PageStyle, name: &str) -> Option<(Rect, FontAlignment)> {
+    let Dimension { width, height } = style.size;
+    let Trbl {
+        top,
+        right,
+        bottom,
+        left,
+    } = style.margin;
+    let inner_width = width - left - right;
+    let inner_height = height - top - bottom;
+    let third_x = |i: f64| left + inner_width * i / 3.0;
+    let third_y = |i: f64| top + inner_height * i / 3.0;
+
+    let (rect, alignment) = match name {
+        "top-left-corner" => (Rect::new(0.0, 0.0, left, top), FontAlignment::End),
+        "top-left" => (
+            Rect::new(third_x(0.0), 0.0, inner_width / 3.0, top),
+            FontAlignment::Start,
+        ),
+        "top-center" => (
+            Rect::new(third_x(1.0), 0.0, inner_width / 3.0, top),
+            FontAlignment::Center,
+        ),
+        "top-right" => (Rect::new(third_x(2.0), 0.0, inner_width / 3.0, top), FontAlignment::End),
+        "top-right-corner" => (Rect::new(width - right, 0.0, right, top), FontAlignment::Start),
+        "bottom-left-corner" => (Rect::new(0.0, height - bottom, left, bottom), FontAlignment::End),
+        "bottom-left" => (
+            Rect::new(third_x(0.0), height - bottom, inner_width / 3.0, bottom),
+            FontAlignment::Start,
+        ),
+        "bottom-center" => (
+            Rect::new(third_x(1.0), height - bottom, inner_width / 3.0, bottom),
+            FontAlignment::Center,
+        ),
+        "bottom-right" => (
+            Rect::new(third_x(2.0), height - bottom, inner_width / 3.0, bottom),
+            FontAlignment::End,
+        ),
+        "bottom-right-corner" => (
+            Rect::new(width - right, height - bottom, right, bottom),
+            FontAlignment::Start,
+        ),
+        "left-top" => (
+            Rect::new(0.0, third_y(0.0), left, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        "left-middle" => (
+            Rect::new(0.0, third_y(1.0), left, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        "left-bottom" => (
+            Rect::new(0.0, third_y(2.0), left, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        "right-top" => (
+            Rect::new(width - right, third_y(0.0), right, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        "right-middle" => (
+            Rect::new(width - right, third_y(1.0), right, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        "right-bottom" => (
+            Rect::new(width - right, third_y(2.0), right, inner_height / 3.0),
+            FontAlignment::Center,
+        ),
+        _ => return None,
+    };
+    (rect.width > 0.0 && rect.height > 0.0).then_some((rect, alignment))
+}
+
+/// The text of a margin box's `content`: its strings, with `counter(page)` and `counter(pages)`
+/// replaced by the page number and count. Other values are dropped.
+fn margin_box_content(content: &str, page: usize, pages: usize) -> String {
+    let mut out = String::new();
+    let mut rest = content.trim();
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '\'' {
+            let body = &rest[1..];
+            let end = body.find(c).unwrap_or(body.len());
+            out.push_str(&body[..end]);
+            rest = body.get(end + 1..).unwrap_or_default();
+        } else if let Some(args) = rest.strip_prefix("counter(") {
+            let end = args.find(')').unwrap_or(args.len());
+            match args[..end].split(',').next().map(str::trim) {
+                Some("page") => out.push_str(&page.to_string()),
+                Some("pages") => out.push_str(&pages.to_string()),
+                _ => {}
+            }
+            rest = args.get(end + 1..).unwrap_or_default();
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(selector: &str, declarations: &[(&str, &str)]) -> PageRule {
+        PageRule {
+            selectors: vec![selector.to_string()],
+            declarations: declarations
+                .iter()
+                .map(|(p, v)| (p.to_string(), v.to_string()))
+                .collect(),
+            margin_boxes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn page_rules_cascade_by_page() {
+        let rules = [
+            rule(":first", &[("margin-top", "2in")]),
+            rule("", &[("size", "letter landscape"), ("margin", "1in 0.5in")]),
+            rule(":left", &[("margin-left", "1in")]),
+        ];
+        let first = page_style(&rules, 0, DEFAULT_PAGE_SIZE);
+        assert_eq!((first.size.width, first.size.height), (1056.0, 816.0));
+        assert_eq!((first.margin.top, first.margin.left), (192.0, 48.0));
+        let second = page_style(&rules, 1, DEFAULT_PAGE_SIZE);
+        assert_eq!((second.margin.top, second.margin.left), (96.0, 96.0));
+        assert_eq!(second.content_box(), Rect::new(96.0, 96.0, 912.0, 624.0));
+    }
+
+    #[test]
+    fn page_sizes() {
+        let a4 = page_size("A4", DEFAULT_PAGE_SIZE).unwrap();
+        assert!((a4.width - DEFAULT_PAGE_SIZE.width).abs() < 0.1);
+        let square = page_size("10cm", DEFAULT_PAGE_SIZE).unwrap();
+        assert_eq!(square.width, square.height);
+        let landscape = page_size("landscape", DEFAULT_PAGE_SIZE).unwrap();
+        assert!(landscape.width > landscape.height);
+        assert!(page_size("bogus", DEFAULT_PAGE_SIZE).is_none());
+    }
+
+    #[test]
+    fn margin_box_content_substitutes_counters() {
+        assert_eq!(
+            margin_box_content(r#""Page " counter(page) " of " counter(pages)"#, 2, 5),
+            "Page 2 of 5"
+        );
+        assert_eq!(margin_box_content("'Invoice' attr(x)", 1, 1), "Invoice");
+    }
+
+    #[test]
+    fn split_pages_keeps_lines_whole() {
+        let keeps: Vec<Range<f64>> = (0..25).map(|i| i as f64 * 20.0..i as f64 * 20.0 + 20.0).collect();
+        // 500px of 20px lines on 110px pages: each page ends on a line boundary.
+        let pages = split_pages(500.0, &[], &keeps, |_| 110.0);
+        assert_eq!(pages[0], 0.0..100.0);
+        assert_eq!(pages[1], 100.0..200.0);
+        assert_eq!(pages.last().map(|p| p.end), Some(500.0));
+    }
+
+    #[test]
+    fn split_pages_honours_forced_breaks() {
+        let breaks = [(50.0, BreakSide::Any), (120.0, BreakSide::Left)];
+        let pages = split_pages(300.0, &breaks, &[], |_| 1000.0);
+        // The third page is a right page, so the break onto a left page leaves it blank.
+        assert_eq!(pages, vec![0.0..50.0, 50.0..120.0, 120.0..120.0, 120.0..300.0]);
+    }
+
+    #[test]
+    fn split_pages_splits_what_does_not_fit() {
+        // A 250px block on 100px pages has to be split regardless.
+        let pages = split_pages(250.0, &[], &[0.0..250.0], |_| 100.0);
+        assert_eq!(pages, vec![0.0..100.0, 100.0..200.0, 200.0..250.0]);
+    }
+}
@@ -2,27 +2,47 @@ pub mod commands;
 
 use crate::common::browser_state::{BrowserState, WireframeState};
 use crate::common::document::node::NodeId;
-use crate::common::document::pipeline_doc::{BgImageLayout, BgSize};
+use crate::common::document::pipeline_doc::{BgBox, BgImageLayout, BgSize};
 use crate::common::document::style::{lookup, BorderStyle as CssBorderStyle, Display, StyleProperty, Value};
-use crate::common::font::{FontAlignment, FontInfo};
+use crate::common::font::{FontAlignment, FontInfo, FontShaping};
 use crate::common::geo::Rect;
 use crate::common::media::MediaStore;
-use crate::layering::layer::LayerList;
-use crate::layouter::{BackgroundMedia, ElementContext, LayoutElementId, LayoutElementNode};
+use crate::layering::layer::{LayerId, LayerList};
+use crate::layouter::BoxModel;
+use crate::layouter::{
+    BackgroundImage, BackgroundLayer, BackgroundMedia, ElementContext, ElementContextMedia, ElementContextText,
+    LayoutElementId, LayoutElementNode,
+};
+use crate::media_element::{contain_rect, MediaControls};
 use crate::painter::commands::border::{Border, BorderStyle};
 use crate::painter::commands::brush::Brush;
 use crate::painter::commands::color::Color;
-use crate::painter::commands::gradient::{Gradient, Tiling};
-use crate::painter::commands::rectangle::{BlendMode, Radius, Rectangle};
+use crate::painter::commands::gradient::Tiling;
+use crate::painter::commands::rectangle::{Radius, Rectangle};
+use crate::painter::commands::shadow::{BoxShadow, Shadow};
 use crate::painter::commands::text::Text;
 use crate::painter::commands::PaintCommand;
 use crate::render::backend::TileAnchor;
+use crate::selection;
 use crate::tiler::TiledLayoutElement;
 use gosub_interface::font::FontStyle;
-use gosub_interface::font_system::{FontStretch, FontSystem, FontWeight, ShapedText, TextAlign, TextStyle};
+use gosub_interface::font_system::{
+    FontStretch, FontSystem, FontWeight, ShapedText, TextAlign, TextOverflow, TextStyle,
+};
 use parking_lot::Mutex;
+use std::ops::Range;
 use std::sync::Arc;
 
+/// Highlight behind selected text when no `::selection` rule sets a `background-color`.
+const DEFAULT_SELECTION_BACKGROUND: Color = Color::from_rgba8(0x33, 0x90, 0xff, 0x66);
+/// Overlay on find-in-page matches, and on the one the host stepped to.
+const FIND_MATCH_HIGHLIGHT: Color = Color::from_rgba8(0xff, 0xe0, 0x00, 0x73);
+const FIND_ACTIVE_HIGHLIGHT: Color = Color::from_rgba8(0xff, 0x8c, 0x00, 0x8c);
+/// The native media controls: a translucent bar with a light track, filled where played.
+const MEDIA_CONTROLS_BAR: Color = Color::from_rgba8(0x00, 0x00, 0x00, 0x99);
+const MEDIA_CONTROLS_TRACK: Color = Color::from_rgba8(0xff, 0xff, 0xff, 0x4d);
+const MEDIA_CONTROLS_PLAYED: Color = Color::from_rgba8(0xff, 0xff, 0xff, 0xff);
+
 /// A whole-viewport paint command list for the GPU-scene path, translated by a backend's `render`
 /// into its native scene. Replaces the tile/rasterize/composite stages for GPU backends.
 pub struct PaintScene {
@@ -35,9 +55,11 @@ pub struct PaintScene {
 
 /// The same [`TextStyle`] mapping the layouter measured with, so shaping reproduces its box.
 ///
-/// Start-aligned text wraps at the layouter's container width to reproduce its line breaks (a
-/// fragment can carry a whole multi-line paragraph). Center/End/Justify wrap at the fragment's
-/// own box instead - glyphs shifted outside it would land in tiles that never repaint the command.
+/// Start-aligned LTR text wraps at the layouter's container width to reproduce its line breaks (a
+/// fragment can carry a whole multi-line paragraph). Center/End/Justify - and RTL, whose start is
+/// the right edge - wrap at the fragment's own box instead: glyphs shifted outside it would land in
+/// tiles that never repaint the command. `text-overflow: ellipsis` cuts at the container width,
+/// which unwrapped text may overflow.
 fn paint_text_style(font_info: &FontInfo, rect_width: f64, available_width: f64) -> TextStyle {
     let align = match font_info.alignment {
         FontAlignment::Start => TextAlign::Start,
@@ -46,7 +68,8 @@ fn paint_text_style(font_info: &FontInfo, rect_width: f64, available_width: f64)
         FontAlignment::Justify => TextAlign::Justify,
     };
     let max_width = match align {
-        TextAlign::Start => available_width.max(rect_width).max(1.0) as f32,
+        _ if font_info.shaping.breaking.text_overflow == TextOverflow::Ellipsis => available_width.max(1.0) as f32,
+        TextAlign::Start if !font_info.shaping.direction.is_rtl() => available_width.max(rect_width).max(1.0) as f32,
         _ => rect_width.max(1.0) as f32,
     };
     TextStyle {
@@ -63,9 +86,60 @@ fn paint_text_style(font_info: &FontInfo, rect_width: f64, available_width: f64)
         letter_spacing: font_info.letter_spacing as f32,
         max_width: Some(max_width),
         align,
+        direction: font_info.shaping.direction,
+        writing_mode: font_info.shaping.writing_mode,
+        orientation: font_info.shaping.orientation,
         // Paint commands are in CSS pixels; DPI scaling is applied later in the pipeline.
         display_scale: 1.0,
+        features: font_info.shaping.features.clone(),
+        variations: font_info.shaping.variations.clone(),
+        kerning: font_info.shaping.kerning,
+        optical_sizing: font_info.shaping.optical_sizing,
+        line_breaking: font_info.shaping.breaking,
+    }
+}
+
+/// Shape `text` into the positioned glyph runs a glyph-based rasterizer will paint.
+fn shape(
+    font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+    text: &str,
+    font_info: &FontInfo,
+    rect_width: f64,
+    available_width: f64,
+) -> ShapedText {
+    let Some(fs) = font_system else {
+        return ShapedText::empty();
+    };
+    if text.is_empty() || font_info.size <= 0.0 {
+        return ShapedText::empty();
     }
+    let style = paint_text_style(font_info, rect_width, available_width);
+    fs.lock().shape(text, &style)
+}
+
+/// Shapes a text element exactly as it is painted into `content_box`, returning the runs and the
+/// wrap width they were shaped at. Hit-testing a selection shares this so offsets map to the
+/// glyphs on screen.
+pub(crate) fn shape_text_element(
+    font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+    ctx: &ElementContextText,
+    content_box: Rect,
+) -> (ShapedText, f64) {
+    let avail_w = if ctx.available_width > 0.0 {
+        ctx.available_width
+    } else {
+        1_000_000_000.0
+    };
+    // Vertical lines run down the box, so they wrap at its height as they were measured.
+    let (line_box, avail_w) = if ctx.font_info.shaping.writing_mode.is_vertical() {
+        (content_box.height, content_box.height)
+    } else {
+        (content_box.width, avail_w)
+    };
+    (
+        shape(font_system, &ctx.text, &ctx.font_info, line_box, avail_w),
+        avail_w,
+    )
 }
 
 /// Turns the layout tree into paint commands for the renderer.
@@ -87,51 +161,78 @@ impl Painter {
 
     /// Shape `text` into the positioned glyph runs a glyph-based rasterizer will paint.
     fn shape_text(&self, text: &str, font_info: &FontInfo, rect_width: f64, available_width: f64) -> ShapedText {
-        let Some(ref fs) = self.font_system else {
-            return ShapedText::empty();
-        };
-        if text.is_empty() || font_info.size <= 0.0 {
-            return ShapedText::empty();
-        }
-        let style = paint_text_style(font_info, rect_width, available_width);
-        fs.lock().shape(text, &style)
+        shape(self.font_system.as_ref(), text, font_info, rect_width, available_width)
+    }
+
+    /// A text command for `text` that is not part of the layout tree, such as the content of a
+    /// page-margin box, shaped to wrap within `rect`.
+    pub fn text_command(&self, rect: Rect, text: &str, font_info: &FontInfo, brush: Brush) -> PaintCommand {
+        let shaped = self.shape_text(text, font_info, rect.width, rect.width);
+        PaintCommand::text(Text::new(rect, text, font_info, brush, rect.width, shaped))
     }
 
     pub fn paint(&self, element: &TiledLayoutElement, state: &BrowserState) -> Vec<PaintCommand> {
         self.paint_element(element.id, state)
     }
 
-    /// Flattens every element into one command list, in z-order (`layer_ids`) then paint order
-    /// (`layer.elements`) - matching the tiler's z-ordering. For GPU-scene backends that render
-    /// the whole viewport in one pass.
+    /// Flattens every element into one command list, in composite order (`layer_ids`) then paint
+    /// order (`layer.elements`) - matching the tiler's z-ordering. For GPU-scene backends that
+    /// render the whole viewport in one pass.
     pub fn paint_all(&self, state: &BrowserState) -> Vec<PaintCommand> {
         let mut out = Vec::new();
-        let layer_ids = self.layer_list.layer_ids.read();
-        let layers = self.layer_list.layers.read();
-        for layer_id in layer_ids.iter() {
-            let Some(layer) = layers.get(layer_id) else {
-                continue;
-            };
-            // A promoted layer (faded by group opacity, or pinned/sticky) becomes a compositing
-            // group the scene backend fades + positions as a unit. The base scroll layer at full
-            // opacity needs no wrapper.
-            let promoted = layer.opacity < 1.0 || !matches!(layer.anchor, TileAnchor::Scroll);
-            if promoted {
-                out.push(PaintCommand::PushLayer {
-                    opacity: layer.opacity,
-                    anchor: layer.anchor,
-                });
-            }
-            for &element_id in &layer.elements {
-                out.extend(self.paint_element(element_id, state));
-            }
-            if promoted {
-                out.push(PaintCommand::PopLayer);
-            }
+        let root = self.layer_list.layer_ids.read().iter().copied().find(|id| {
+            let layers = self.layer_list.layers.read();
+            layers.get(id).is_some_and(|l| l.parent.is_none())
+        });
+        if let Some(root) = root {
+            self.paint_layer(root, state, &mut out);
         }
         out
     }
 
+    /// Paints a layer and its child layers, nested the way the stacking contexts are: negative
+    /// `z-index` children beneath the layer's own elements, the rest above.
+    fn paint_layer(&self, layer_id: LayerId, state: &BrowserState, out: &mut Vec<PaintCommand>) {
+        let Some(layer) = self.layer_list.layers.read().get(&layer_id).cloned() else {
+            return;
+        };
+        // A promoted layer (faded by group opacity, filtered, blended, isolated, pinned/sticky or
+        // transformed) becomes a compositing group the scene backend fades, filters, blends +
+        // positions as a unit. Layers that merely stack (a plain `z-index`) need no wrapper.
+        let translate = self.layer_list.layer_translation(layer_id);
+        let grouped = layer.isolated
+            || !layer.filters.is_empty()
+            || !matches!(layer.anchor, TileAnchor::Scroll)
+            || translate != (0.0, 0.0);
+        if grouped {
+            out.push(PaintCommand::PushLayer {
+                opacity: layer.opacity,
+                // Nested layers carry their pinned ancestor's anchor, so this is absolute.
+                anchor: layer.anchor,
+                blend_mode: layer.blend_mode,
+                isolated: layer.isolated,
+                filters: layer.filters.clone(),
+                translate,
+            });
+        }
+        let (below, above): (Vec<LayerId>, Vec<LayerId>) = layer.children.iter().copied().partition(|c| {
+            let layers = self.layer_list.layers.read();
+            layers.get(c).is_some_and(|l| l.order < 0)
+        });
+        for child in below {
+            self.paint_layer(child, state, out);
+        }
+        for &element_id in &layer.elements {
+            out.extend(self.paint_element(element_id, state));
+        }
+        for child in above {
+            self.paint_layer(child, state, out);
+        }
+        if grouped {
+            out.push(PaintCommand::PopLayer);
+        }
+    }
+
     pub fn paint_element(&self, element_id: LayoutElementId, state: &BrowserState) -> Vec<PaintCommand> {
         let mut commands = Vec::new();
 
@@ -149,11 +250,11 @@ impl Painter {
                 commands.extend(self.generate_wireframe_commands(layout_element));
             }
             WireframeState::Both => {
-                commands.extend(self.generate_element_commands(layout_element, dom_node_id));
+                commands.extend(self.generate_element_commands(layout_element, dom_node_id, state));
                 commands.extend(self.generate_wireframe_commands(layout_element));
             }
             WireframeState::None => {
-                commands.extend(self.generate_element_commands(layout_element, dom_node_id));
+                commands.extend(self.generate_element_commands(layout_element, dom_node_id, state));
             }
         }
 
@@ -198,34 +299,154 @@ impl Painter {
         }
     }
 
-    /// The element's CSS `mix-blend-mode`. Blends against whatever is already painted beneath it
-    /// (tile content for canvas backends, the scene for Vello) - stacking-context isolation is
-    /// not modelled.
-    fn mix_blend_mode(&self, node_id: NodeId) -> BlendMode {
+    /// Shadow colours faded by the element's `opacity`, like its other paint.
+    fn fade_shadows(&self, node_id: NodeId, mut shadows: Vec<Shadow>) -> Vec<Shadow> {
+        for shadow in &mut shadows {
+            if let Brush::Solid(c) = self.apply_opacity(node_id, Brush::solid(shadow.color.clone())) {
+                shadow.color = c;
+            }
+        }
+        shadows
+    }
+
+    /// `box-shadow` commands for the element's border box, split into (outer, inset). CSS paints
+    /// the first-listed shadow on top, so each list is emitted back-to-front. Outer shadows go
+    /// beneath the background, inset ones above it.
+    fn box_shadow_commands(&self, dom_node_id: NodeId, border_box: Rect) -> (Vec<PaintCommand>, Vec<PaintCommand>) {
         let doc = &self.layer_list.layout_tree.render_tree.doc;
-        match doc.get_style(node_id, &StyleProperty::MixBlendMode) {
-            Value::Keyword(kw) => BlendMode::from_css_keyword(&lookup(kw)),
-            _ => BlendMode::Normal,
+        let shadows = self.fade_shadows(dom_node_id, doc.box_shadows(dom_node_id));
+        if shadows.is_empty() {
+            return (Vec::new(), Vec::new());
         }
+        // The shadow follows the border box's radii and (for inset) its border widths.
+        let shape = self.decorate_with_border_and_radius(dom_node_id, Rectangle::new(border_box));
+        let mut outer = Vec::new();
+        let mut inset = Vec::new();
+        for shadow in shadows.into_iter().rev() {
+            let target = if shadow.inset { &mut inset } else { &mut outer };
+            target.push(PaintCommand::box_shadow(BoxShadow::new(shape.clone(), shadow)));
+        }
+        (outer, inset)
     }
 
-    /// Base fill plus overlay `background-image` gradient layers to paint on top, back-to-front.
+    /// `background-color` plus every `background-image` layer, with the element's border on top.
     ///
-    /// A lone non-tiled gradient becomes the base brush directly, so border/radius decorate the
-    /// same rect. Multiple or tiled layers instead stack as separate rects over `background-color`.
-    fn background_fill(&self, node_id: NodeId) -> (Brush, Vec<Gradient>) {
-        let doc = &self.layer_list.layout_tree.render_tree.doc;
-        let layers = doc.background_layers(node_id);
+    /// A lone untiled gradient clipped to the border box becomes the base brush directly, so
+    /// border/radius decorate the same rect. Otherwise the colour fills the bottom layer's clip
+    /// box, the layers stack over it back-to-front, and the border is painted last.
+    fn background_commands(
+        &self,
+        layout_element: &LayoutElementNode,
+        dom_node_id: NodeId,
+        state: &BrowserState,
+    ) -> Vec<PaintCommand> {
+        let box_model = &layout_element.box_model;
+        let layers = &layout_element.background_layers;
+        let shape = self.decorate_with_border_and_radius(dom_node_id, Rectangle::new(box_model.border_box));
         let color = self.get_brush(
-            node_id,
+            dom_node_id,
             &StyleProperty::BackgroundColor,
             Brush::solid(Color::TRANSPARENT),
         );
-        match layers.as_slice() {
-            [] => (color, Vec::new()),
-            [Gradient::Linear(g)] if g.tiling.is_none() => (Brush::gradient(Gradient::Linear(g.clone())), Vec::new()),
-            _ => (color, layers),
+
+        let folded = match layers.as_slice() {
+            [layer] if layer.layout.clip == BgBox::Border => {
+                match self.background_layer_brush(layer, box_model, &shape, state) {
+                    Some((_, Brush::Gradient(g))) if g.tiling().is_none() => Some(Brush::gradient(g)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let base = match (folded, layers.last()) {
+            (Some(brush), _) => Some(brush),
+            (None, None) => Some(color.clone()),
+            (None, Some(_)) => None,
+        };
+        if let Some(brush) = base {
+            if matches!(&brush, Brush::Solid(c) if c.a() == 0.0) && !self.has_border(dom_node_id) {
+                return Vec::new();
+            }
+            return vec![PaintCommand::rectangle(shape.with_background(brush))];
+        }
+
+        let mut commands = Vec::new();
+        if let Some(bottom) = layers.last() {
+            if !matches!(&color, Brush::Solid(c) if c.a() == 0.0) {
+                let clip = bg_clip_shape(&shape, box_model, bottom.layout.clip);
+                commands.push(PaintCommand::rectangle(clip.with_background(color)));
+            }
         }
+        commands.extend(self.background_layer_commands(layout_element, dom_node_id, state));
+        if self.has_border(dom_node_id) {
+            commands.push(PaintCommand::rectangle(shape));
+        }
+        commands
+    }
+
+    /// The `background-image` layers alone, back-to-front (CSS paints the first-listed on top),
+    /// each clipped to its `background-clip` box.
+    fn background_layer_commands(
+        &self,
+        layout_element: &LayoutElementNode,
+        dom_node_id: NodeId,
+        state: &BrowserState,
+    ) -> Vec<PaintCommand> {
+        let box_model = &layout_element.box_model;
+        let shape = self.decorate_with_border_and_radius(dom_node_id, Rectangle::new(box_model.border_box));
+        let mut commands = Vec::new();
+        for layer in layout_element.background_layers.iter().rev() {
+            // SVGs go through the SVG paint path (e.g. HN's `triangle.svg` votearrow), which
+            // `Brush::image` cannot render; they stretch over the clip box.
+            if let BackgroundImage::Media(BackgroundMedia::Svg(media_id)) = layer.image {
+                let clip = bg_clip_shape(&shape, box_model, layer.layout.clip);
+                commands.push(PaintCommand::svg(media_id, Rectangle::new(clip.rect())));
+                continue;
+            }
+            if let Some((clip, brush)) = self.background_layer_brush(layer, box_model, &shape, state) {
+                commands.push(PaintCommand::rectangle(clip.with_background(brush)));
+            }
+        }
+        commands
+    }
+
+    /// A gradient or raster layer as its clip shape plus a brush whose tiling is resolved against
+    /// the `background-origin` box and expressed relative to the clip shape it paints. `None` for
+    /// SVG layers and degenerate boxes.
+    fn background_layer_brush(
+        &self,
+        layer: &BackgroundLayer,
+        box_model: &BoxModel,
+        shape: &Rectangle,
+        state: &BrowserState,
+    ) -> Option<(Rectangle, Brush)> {
+        let clip = bg_clip_shape(shape, box_model, layer.layout.clip);
+        let origin = bg_box_rect(box_model, layer.layout.origin);
+        let natural = match &layer.image {
+            BackgroundImage::Gradient(_) => None,
+            BackgroundImage::Media(BackgroundMedia::Image { natural, .. }) => Some(*natural),
+            BackgroundImage::Media(BackgroundMedia::Svg(_)) => return None,
+        };
+        let mut tiling = compute_bg_tiling(natural, &layer.layout, origin.width as f32, origin.height as f32)?;
+        let c = clip.rect();
+        tiling.position.0 += (origin.x - c.x) as f32;
+        tiling.position.1 += (origin.y - c.y) as f32;
+        // A single tile exactly covering the clip box is just a stretched fill.
+        let tiling =
+            (tiling.position != (0.0, 0.0) || tiling.tile_size != (c.width as f32, c.height as f32)).then_some(tiling);
+
+        let brush = match &layer.image {
+            BackgroundImage::Gradient(g) => {
+                let mut g = g.clone();
+                g.set_tiling(tiling);
+                Brush::gradient(g)
+            }
+            BackgroundImage::Media(BackgroundMedia::Image { media_id, .. }) => {
+                Brush::image_tiled(state.image_frame(*media_id), tiling)
+            }
+            BackgroundImage::Media(BackgroundMedia::Svg(_)) => return None,
+        };
+        Some((clip, brush))
     }
 
     /// Paints an image's `alt` text inside its box. `icon_offset_x` is the width taken by a
@@ -278,6 +499,7 @@ impl Painter {
             alignment: FontAlignment::Start,
             underline: false,
             line_through: false,
+            shaping: FontShaping::default(),
         }
     }
 
@@ -357,62 +579,39 @@ impl Painter {
         vec![PaintCommand::rectangle(r)]
     }
 
-    /// Paint commands for an element's CSS `background-image`, filling the border box.
-    /// Raster images are blitted via `Brush::image`; SVGs go through the SVG paint path
-    /// (e.g. HN's `triangle.svg` votearrow), which `Brush::image` cannot render.
-    fn background_media_commands(
+    fn generate_element_commands(
         &self,
-        bg: BackgroundMedia,
         layout_element: &LayoutElementNode,
         dom_node_id: NodeId,
+        state: &BrowserState,
     ) -> Vec<PaintCommand> {
-        let border_box = layout_element.box_model.border_box;
-        match bg {
-            BackgroundMedia::Image {
-                media_id,
-                natural,
-                layout,
-            } => {
-                // Finalize the tile geometry now that the box is known (cover/contain need it).
-                let tiling = compute_bg_tiling(natural, &layout, border_box.width as f32, border_box.height as f32);
-                let brush = Brush::image_tiled(media_id, tiling);
-                let r = Rectangle::new(border_box)
-                    .with_background(brush)
-                    .with_blend_mode(self.mix_blend_mode(dom_node_id));
-                let r = self.decorate_with_border_and_radius(dom_node_id, r);
-                vec![PaintCommand::rectangle(r)]
-            }
-            BackgroundMedia::Svg(media_id) => vec![PaintCommand::svg(media_id, Rectangle::new(border_box))],
-        }
-    }
-
-    fn generate_element_commands(&self, layout_element: &LayoutElementNode, dom_node_id: NodeId) -> Vec<PaintCommand> {
         let mut commands = Vec::new();
 
-        // CSS background-image. For plain block elements (`None` context) it is painted just
-        // after the background-color in that branch below (correct CSS layering). For
-        // replaced/text content we paint it first so the element's own content stays on top.
-        let bg_media = layout_element.background_media;
-        if let Some(bg) = bg_media {
-            if !matches!(layout_element.context, ElementContext::None) {
-                commands.extend(self.background_media_commands(bg, layout_element, dom_node_id));
-            }
+        // CSS background-image. Block and image elements paint it with their background-color
+        // in the branches below (correct CSS layering). For text/SVG content we paint it first so
+        // the element's own content stays on top.
+        if matches!(layout_element.context, ElementContext::Text(_) | ElementContext::Svg(_)) {
+            commands.extend(self.background_layer_commands(layout_element, dom_node_id, state));
         }
 
         match &layout_element.context {
+            // Text waiting for a web font in its block period is left out until the font arrives.
+            ElementContext::Text(ctx) if state.font_blocked(&ctx.font_info.family, &ctx.text) => {}
             ElementContext::Text(ctx) => {
                 let brush = self.get_parent_brush(dom_node_id, &StyleProperty::Color, Brush::solid(Color::BLACK));
                 let brush = self.apply_opacity(dom_node_id, brush);
 
                 let r = layout_element.box_model.content_box;
-                let avail_w = if ctx.available_width > 0.0 {
-                    ctx.available_width
-                } else {
-                    1_000_000_000.0
-                };
-                let shaped = self.shape_text(&ctx.text, &ctx.font_info, r.width, avail_w);
-                let t = Text::new(r, &ctx.text, &ctx.font_info, brush, avail_w, shaped);
-                commands.push(PaintCommand::text(t));
+                let (shaped, avail_w) = shape_text_element(self.font_system.as_ref(), ctx, r);
+                let doc = &self.layer_list.layout_tree.render_tree.doc;
+                let shadows = self.fade_shadows(dom_node_id, doc.text_shadows(dom_node_id));
+                let t = Text::new(r, &ctx.text, &ctx.font_info, brush, avail_w, shaped).with_shadows(shadows);
+                let find_overlay = self.find_overlay_commands(&t, layout_element.id, state);
+                match state.selection.range(layout_element.id) {
+                    Some(range) => commands.extend(self.selected_text_commands(t, range, dom_node_id)),
+                    None => commands.push(PaintCommand::text(t)),
+                }
+                commands.extend(find_overlay);
             }
             ElementContext::Svg(svg_ctx) => {
                 let border_box = layout_element.box_model.border_box;
@@ -427,21 +626,18 @@ impl Painter {
             }
             ElementContext::Image(image_ctx) => {
                 let border_box = layout_element.box_model.border_box;
-                let blend = self.mix_blend_mode(dom_node_id);
+                let (outer_shadows, inset_shadows) = self.box_shadow_commands(dom_node_id, border_box);
+                commands.extend(outer_shadows);
 
-                // CSS paints background-color behind the (possibly transparent) replaced content,
+                // CSS paints the background behind the (possibly transparent) replaced content,
                 // e.g. a transparent PNG on `<img style="background:#3a7">` shows green through.
-                let (bg_brush, _) = self.background_fill(dom_node_id);
-                if !matches!(&bg_brush, Brush::Solid(c) if c.a() == 0.0) {
-                    let bg_r = Rectangle::new(border_box)
-                        .with_background(bg_brush)
-                        .with_blend_mode(blend);
-                    commands.push(PaintCommand::rectangle(
-                        self.decorate_with_border_and_radius(dom_node_id, bg_r),
-                    ));
-                }
+                commands.extend(self.background_commands(layout_element, dom_node_id, state));
 
-                let brush = Brush::image(image_ctx.media_id);
+                // Inset shadows sit above the background but, as in browsers, beneath the replaced
+                // content itself.
+                commands.extend(inset_shadows);
+
+                let brush = Brush::image(state.image_frame(image_ctx.media_id));
                 // A broken-image placeholder is drawn at its natural icon size in the top-left of
                 // the reserved box (like Firefox) rather than stretched to fill it.
                 let draw_box = if image_ctx.placeholder {
@@ -451,7 +647,7 @@ impl Painter {
                 } else {
                     border_box
                 };
-                let r = Rectangle::new(draw_box).with_background(brush).with_blend_mode(blend);
+                let r = Rectangle::new(draw_box).with_background(brush);
                 // The border/radius belongs to the element box, not the shrunk icon rect.
                 let border_target = if image_ctx.placeholder { border_box } else { draw_box };
                 let border_r = self.decorate_with_border_and_radius(dom_node_id, Rectangle::new(border_target));
@@ -475,36 +671,179 @@ impl Painter {
                     }
                 }
             }
-            ElementContext::None => {
-                let (brush, overlay_layers) = self.background_fill(dom_node_id);
+            ElementContext::Frame(_) => {
                 let border_box = layout_element.box_model.border_box;
-                let r = Rectangle::new(border_box)
-                    .with_background(brush)
-                    .with_blend_mode(self.mix_blend_mode(dom_node_id));
-                let r = self.decorate_with_border_and_radius(dom_node_id, r);
-                commands.push(PaintCommand::rectangle(r));
-
-                // background-image paints on top of the background-color.
-                if let Some(bg) = bg_media {
-                    commands.extend(self.background_media_commands(bg, layout_element, dom_node_id));
-                }
-
-                // Stacked gradient layers (multi-layer / tiled backgrounds, e.g. a CSS
-                // checkerboard). CSS paints the first-listed layer on top, so emit them
-                // back-to-front over the base fill.
-                let blend = self.mix_blend_mode(dom_node_id);
-                for layer in overlay_layers.into_iter().rev() {
-                    let r = Rectangle::new(border_box)
-                        .with_background(Brush::gradient(layer))
-                        .with_blend_mode(blend);
+                let (outer_shadows, inset_shadows) = self.box_shadow_commands(dom_node_id, border_box);
+                commands.extend(outer_shadows);
+                commands.extend(self.background_commands(layout_element, dom_node_id, state));
+                commands.extend(inset_shadows);
+
+                // The nested document's rendering fills the content box, so the iframe's border
+                // and padding frame it and nothing it draws spills outside.
+                if let Some(media_id) = state.frames.get(&dom_node_id) {
+                    let content_box = layout_element.box_model.content_box;
+                    let r = Rectangle::new(content_box).with_background(Brush::image(*media_id));
                     commands.push(PaintCommand::rectangle(r));
                 }
             }
+            ElementContext::Media(media_ctx) => {
+                let border_box = layout_element.box_model.border_box;
+                let (outer_shadows, inset_shadows) = self.box_shadow_commands(dom_node_id, border_box);
+                commands.extend(outer_shadows);
+                commands.extend(self.background_commands(layout_element, dom_node_id, state));
+                commands.extend(inset_shadows);
+                commands.extend(self.media_commands(media_ctx, layout_element.box_model.content_box, state));
+            }
+            ElementContext::None => {
+                let border_box = layout_element.box_model.border_box;
+                let (outer_shadows, inset_shadows) = self.box_shadow_commands(dom_node_id, border_box);
+                commands.extend(outer_shadows);
+                commands.extend(self.background_commands(layout_element, dom_node_id, state));
+
+                // Inset shadows paint over every background layer.
+                commands.extend(inset_shadows);
+
+                // Column rules sit between the background and the columns' content.
+                commands.extend(self.column_rule_commands(layout_element, dom_node_id));
+            }
         }
 
         commands
     }
 
+    /// A video's current frame (or poster) letterboxed into its content box, then the controls bar
+    /// when the element has `controls`.
+    fn media_commands(
+        &self,
+        media_ctx: &ElementContextMedia,
+        content_box: Rect,
+        state: &BrowserState,
+    ) -> Vec<PaintCommand> {
+        let dom_node_id = media_ctx.node_id;
+        let presentation = state.media.get(&dom_node_id).copied().unwrap_or_default();
+        let mut commands = Vec::new();
+
+        let image = match presentation.frame {
+            Some(frame) => Some((frame, presentation.frame_size)),
+            None => media_ctx.poster.map(|(poster, size)| (state.image_frame(poster), size)),
+        };
+        if let (Some((media_id, size)), false) = (image, media_ctx.audio) {
+            let r = Rectangle::new(contain_rect(content_box, size)).with_background(Brush::image(media_id));
+            commands.push(PaintCommand::rectangle(r));
+        }
+
+        if media_ctx.controls {
+            let controls = MediaControls::new(content_box, media_ctx.audio);
+            let fill = |rect: Rect, color: Color| {
+                PaintCommand::rectangle(
+                    Rectangle::new(rect).with_background(self.apply_opacity(dom_node_id, Brush::solid(color))),
+                )
+            };
+            commands.push(fill(controls.bar, MEDIA_CONTROLS_BAR));
+            if let Some(icon) = presentation.icon {
+                commands.push(PaintCommand::svg(icon, Rectangle::new(controls.button)));
+            }
+            commands.push(fill(controls.track, MEDIA_CONTROLS_TRACK));
+            let played = controls.played(presentation.progress);
+            if played.width > 0.0 {
+                commands.push(fill(played, MEDIA_CONTROLS_PLAYED));
+            }
+        }
+        commands
+    }
+
+    /// A text command with part of its text selected: the highlight behind the selected glyphs,
+    /// then the text. A `::selection` colour moves the selected glyphs into a second command in
+    /// that colour, drawn without decorations so the unselected copy's lines aren't doubled.
+    fn selected_text_commands(&self, mut text: Text, range: Range<usize>, dom_node_id: NodeId) -> Vec<PaintCommand> {
+        let doc = &self.layer_list.layout_tree.render_tree.doc;
+        let (color, background) = doc.selection_colors(dom_node_id);
+        let background = self.apply_opacity(
+            dom_node_id,
+            Brush::solid(background.unwrap_or(DEFAULT_SELECTION_BACKGROUND)),
+        );
+        let mut commands: Vec<PaintCommand> = selection::highlight_rects(&text.shaped, text.text.len(), &range)
+            .into_iter()
+            .map(|rect| {
+                let rect = Rect::new(text.rect.x + rect.x, text.rect.y + rect.y, rect.width, rect.height);
+                PaintCommand::rectangle(Rectangle::new(rect).with_background(background.clone()))
+            })
+            .collect();
+        if let Some(color) = color {
+            let (unselected, selected) = selection::split_glyphs(&text.shaped, &range);
+            let mut selected_text = text.clone();
+            selected_text.shaped = selected;
+            selected_text.brush = self.apply_opacity(dom_node_id, Brush::solid(color));
+            selected_text.font_info.underline = false;
+            selected_text.font_info.line_through = false;
+            text.shaped = unselected;
+            commands.push(PaintCommand::text(text));
+            commands.push(PaintCommand::text(selected_text));
+        } else {
+            commands.push(PaintCommand::text(text));
+        }
+        commands
+    }
+
+    /// Translucent boxes over the find-in-page matches in `text`, the active match in its own
+    /// colour. They paint above the glyphs, so they show through any background or selection.
+    fn find_overlay_commands(
+        &self,
+        text: &Text,
+        element_id: LayoutElementId,
+        state: &BrowserState,
+    ) -> Vec<PaintCommand> {
+        let mut commands = Vec::new();
+        for (range, active) in state.find.ranges(element_id) {
+            let color = if active {
+                FIND_ACTIVE_HIGHLIGHT
+            } else {
+                FIND_MATCH_HIGHLIGHT
+            };
+            for rect in selection::highlight_rects(&text.shaped, text.text.len(), &range) {
+                let rect = Rect::new(text.rect.x + rect.x, text.rect.y + rect.y, rect.width, rect.height);
+                commands.push(PaintCommand::rectangle(
+                    Rectangle::new(rect).with_background(Brush::solid(color.clone())),
+                ));
+            }
+        }
+        commands
+    }
+
+    /// `column-rule` segments of a multi-column container, each drawn as the left border of a
+    /// rule-wide rect centred on its gap.
+    fn column_rule_commands(&self, layout_element: &LayoutElementNode, dom_node_id: NodeId) -> Vec<PaintCommand> {
+        if layout_element.column_rules.is_empty() {
+            return Vec::new();
+        }
+        let doc = &self.layer_list.layout_tree.render_tree.doc;
+        // Resolves to 0 for `column-rule-style: none | hidden`.
+        let width = doc.get_style_f32(dom_node_id, &StyleProperty::ColumnRuleWidth);
+        if width <= 0.0 {
+            return Vec::new();
+        }
+        let style = match doc.get_style(dom_node_id, &StyleProperty::ColumnRuleStyle) {
+            Value::BorderStyle(s) => css_border_style_to_paint(&s),
+            _ => BorderStyle::Solid,
+        };
+        let brush = self.get_brush(dom_node_id, &StyleProperty::ColumnRuleColor, Brush::solid(Color::BLACK));
+
+        let w = width as f64;
+        layout_element
+            .column_rules
+            .iter()
+            .map(|rule| {
+                let rect = Rect::new(rule.x - w / 2.0, rule.y, w, rule.height);
+                let border = Border::new_per_side(
+                    [0.0, 0.0, 0.0, width],
+                    [BorderStyle::None, BorderStyle::None, BorderStyle::None, style.clone()],
+                    [brush.clone(), brush.clone(), brush.clone(), brush.clone()],
+                );
+                PaintCommand::rectangle(Rectangle::new(rect).with_border(border))
+            })
+            .collect()
+    }
+
     fn has_border(&self, dom_node_id: NodeId) -> bool {
         let doc = &self.layer_list.layout_tree.render_tree.doc;
         doc.get_style_f32(dom_node_id, &StyleProperty::BorderTopWidth) != 0.0
@@ -604,46 +943,73 @@ fn css_border_style_to_paint(s: &CssBorderStyle) -> BorderStyle {
     }
 }
 
-/// Resolves `background-size`/`-position` into a [`Tiling`], now that the border box is known.
-/// `cover`/`contain` yield a single aspect-preserved tile (no repeat), so the backend paints it
-/// once and lets the box clip (cover) or the background-color show (contain).
-fn compute_bg_tiling(natural: (f32, f32), layout: &BgImageLayout, box_w: f32, box_h: f32) -> Option<Tiling> {
-    let (nw, nh) = natural;
-    if nw <= 0.0 || nh <= 0.0 || box_w <= 0.0 || box_h <= 0.0 {
+/// The `background-origin` / `background-clip` reference box.
+fn bg_box_rect(box_model: &BoxModel, bg_box: BgBox) -> Rect {
+    match bg_box {
+        BgBox::Border => box_model.border_box,
+        BgBox::Padding => box_model.padding_box,
+        BgBox::Content => box_model.content_box,
+    }
+}
+
+/// The border-box `shape` shrunk to `bg_box`, with correspondingly inset corner radii.
+fn bg_clip_shape(shape: &Rectangle, box_model: &BoxModel, bg_box: BgBox) -> Rectangle {
+    let (b, p) = (&box_model.border, &box_model.padding);
+    let edges = match bg_box {
+        BgBox::Border => [0.0; 4],
+        BgBox::Padding => [b.top, b.right, b.bottom, b.left],
+        BgBox::Content => [b.top + p.top, b.right + p.right, b.bottom + p.bottom, b.left + p.left],
+    };
+    shape.inset(edges)
+}
+
+/// Resolves `background-size`/`-position` into a [`Tiling`] relative to the `background-origin`
+/// box, now that it is known. `natural` is the image's intrinsic size; a gradient has none, so
+/// `auto`/`cover`/`contain` make it the size of the box. An `auto` axis next to an explicit one
+/// keeps the natural aspect ratio.
+fn compute_bg_tiling(natural: Option<(f32, f32)>, layout: &BgImageLayout, box_w: f32, box_h: f32) -> Option<Tiling> {
+    if box_w <= 0.0 || box_h <= 0.0 {
+        return None;
+    }
+    if matches!(natural, Some((nw, nh)) if nw <= 0.0 || nh <= 0.0) {
         return None;
     }
 
-    let (tw, th) = match layout.size {
-        BgSize::Auto => (nw, nh),
-        BgSize::Length(w, h) => (w, h),
+    let (tw, th) = match (layout.size, natural) {
+        (BgSize::Auto, Some(n)) => n,
+        (BgSize::Auto | BgSize::Cover | BgSize::Contain, None) => (box_w, box_h),
         // Preserve aspect: contain fits inside the box, cover fills it.
-        BgSize::Contain => {
+        (BgSize::Contain, Some((nw, nh))) => {
             let s = (box_w / nw).min(box_h / nh);
             (nw * s, nh * s)
         }
-        BgSize::Cover => {
+        (BgSize::Cover, Some((nw, nh))) => {
             let s = (box_w / nw).max(box_h / nh);
             (nw * s, nh * s)
         }
+        (BgSize::Explicit(w, h), natural) => {
+            let w = w.map(|l| l.resolve(box_w));
+            let h = h.map(|l| l.resolve(box_h));
+            match (w, h, natural) {
+                (Some(w), Some(h), _) => (w, h),
+                (Some(w), None, Some((nw, nh))) => (w, w * nh / nw),
+                (None, Some(h), Some((nw, nh))) => (h * nw / nh, h),
+                (Some(w), None, None) => (w, box_h),
+                (None, Some(h), None) => (box_w, h),
+                (None, None, natural) => natural.unwrap_or((box_w, box_h)),
+            }
+        }
     };
     if tw <= 0.0 || th <= 0.0 {
         return None;
     }
 
-    let px = if layout.center.0 {
-        (box_w - tw) / 2.0
-    } else {
-        layout.position.0
-    };
-    let py = if layout.center.1 {
-        (box_h - th) / 2.0
-    } else {
-        layout.position.1
-    };
-
     Some(Tiling {
         tile_size: (tw, th),
-        position: (px, py),
+        position: (
+            layout.position.0.resolve(box_w - tw),
+            layout.position.1.resolve(box_h - th),
+        ),
         repeat: layout.repeat,
     })
 }
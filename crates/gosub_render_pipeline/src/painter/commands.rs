@@ -1,14 +1,20 @@
+use crate::common::geo::Rect;
 use crate::common::media::MediaId;
+use crate::painter::commands::color::Color;
+use crate::painter::commands::filter::Filter;
 use crate::painter::commands::rectangle::Rectangle;
+use crate::painter::commands::shadow::BoxShadow;
 use crate::painter::commands::text::Text;
-use crate::render::backend::TileAnchor;
+use crate::render::backend::{BlendMode, TileAnchor};
 
 pub mod border;
 pub mod brush;
 pub mod color;
+pub mod filter;
 pub mod gradient;
 pub mod image;
 pub mod rectangle;
+pub mod shadow;
 pub mod text;
 
 #[derive(Clone, Debug)]
@@ -19,6 +25,35 @@ pub struct Trbl<T> {
     pub left: T,
 }
 
+impl Trbl<f64> {
+    pub const ZERO: Trbl<f64> = Trbl {
+        top: 0.0,
+        right: 0.0,
+        bottom: 0.0,
+        left: 0.0,
+    };
+
+    /// Per-side maximum - the union of two overflow extents.
+    pub fn max(&self, other: &Trbl<f64>) -> Trbl<f64> {
+        Trbl {
+            top: self.top.max(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+            left: self.left.max(other.left),
+        }
+    }
+
+    /// `rect` grown outward by these per-side extents.
+    pub fn outset(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x - self.left,
+            rect.y - self.top,
+            rect.width + self.left + self.right,
+            rect.height + self.top + self.bottom,
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PaintSvg {
     pub rect: Rectangle,
@@ -30,13 +65,27 @@ pub enum PaintCommand {
     Text(Text),
     Rectangle(Rectangle),
     Svg(PaintSvg),
-    /// Begin a compositing group for a promoted layer (`opacity < 1`, `position: fixed`/`sticky`):
-    /// everything up to the matching [`PaintCommand::PopLayer`] is composited as a unit.
-    /// Only the scene path (`Painter::paint_all`) emits these - the tile path applies opacity/anchor
-    /// at composite time, so tile rasterizers can ignore both variants.
+    /// One `box-shadow` layer, emitted just before (outer) or after (inset) the box background.
+    BoxShadow(BoxShadow),
+    /// Begin a compositing group for a promoted layer (`opacity < 1`, `position: fixed`/`sticky`,
+    /// `filter`, `mix-blend-mode`, `isolation`): everything up to the matching
+    /// [`PaintCommand::PopLayer`] is composited as a unit. Groups nest like the stacking contexts
+    /// they come from. Only the scene path (`Painter::paint_all`) emits these - the tile path
+    /// applies opacity/anchor/blending at composite time and filters per tile (see `Tile::filters`),
+    /// so tile rasterizers can ignore both variants.
     PushLayer {
         opacity: f32,
         anchor: TileAnchor,
+        /// How the flattened group blends into what is beneath it.
+        blend_mode: BlendMode,
+        /// The group must be flattened offscreen even at full opacity (`isolation: isolate`, or it
+        /// holds a blending layer that must not see past it).
+        isolated: bool,
+        /// CSS `filter` chain applied to the whole group, in order. Empty for no filter.
+        filters: Vec<Filter>,
+        /// Page-space offset of the group from CSS `transform`, in CSS px. Includes every
+        /// enclosing layer's, so like `anchor` it is absolute.
+        translate: (f64, f64),
     },
     /// End the most recent [`PaintCommand::PushLayer`] group.
     PopLayer,
@@ -54,4 +103,24 @@ impl PaintCommand {
     pub fn rectangle(rectangle: Rectangle) -> Self {
         PaintCommand::Rectangle(rectangle)
     }
+
+    pub fn box_shadow(shadow: BoxShadow) -> Self {
+        PaintCommand::BoxShadow(shadow)
+    }
+
+    /// A copy with every paint colour passed through `f`, for backends that realise colour-only
+    /// filters by recolouring commands instead of filtering pixels. Image and SVG content is
+    /// left as is.
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> PaintCommand {
+        match self {
+            PaintCommand::Text(t) => PaintCommand::Text(t.map_colors(f)),
+            PaintCommand::Rectangle(r) => PaintCommand::Rectangle(r.map_colors(f)),
+            PaintCommand::BoxShadow(bs) => {
+                let mut bs = bs.clone();
+                bs.shadow.color = f(&bs.shadow.color);
+                PaintCommand::BoxShadow(bs)
+            }
+            PaintCommand::Svg(_) | PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => self.clone(),
+        }
+    }
 }
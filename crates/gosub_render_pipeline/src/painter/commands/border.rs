@@ -1,4 +1,5 @@
 use crate::painter::commands::brush::Brush;
+use crate::painter::commands::color::Color;
 use crate::painter::commands::Trbl;
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -119,4 +120,11 @@ impl Border {
     pub fn radius(&self) -> Option<Trbl<BorderRadius>> {
         self.radius.clone()
     }
+
+    /// A copy with every side's brush colours passed through `f`.
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> Border {
+        let mut border = self.clone();
+        border.brushes = self.brushes.clone().map(|b| b.map_colors(f));
+        border
+    }
 }
@@ -28,4 +28,14 @@ impl Brush {
     pub fn gradient(gradient: Gradient) -> Self {
         Brush::Gradient(gradient)
     }
+
+    /// A copy with every colour passed through `f` (solid fill, gradient stops). Image pixels
+    /// are not reachable from a brush and stay untouched.
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> Brush {
+        match self {
+            Brush::Solid(c) => Brush::Solid(f(c)),
+            Brush::Gradient(g) => Brush::Gradient(g.map_colors(f)),
+            Brush::Image(..) => self.clone(),
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use csscolorparser::Color as ccpColor;
 
 /// Channels are stored as f32 in `0.0..=1.0`; use `r8`/`g8`/`b8`/`a8` for the u8 (0-255) form.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Color {
     r: f32,
     g: f32,
@@ -92,7 +92,7 @@ impl Color {
         }
     }
 
-    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+    pub const fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
         Color {
             r: r as f32 / 255.0,
             g: g as f32 / 255.0,
@@ -0,0 +1,440 @@
+use crate::painter::commands::color::Color;
+use crate::painter::commands::shadow::{parse_length, parse_shadow, split_top_level, Shadow};
+use crate::painter::commands::Trbl;
+use crate::render::backend::PixelFormat;
+use cow_utils::CowUtils;
+
+/// One CSS `filter` function. Unsupported functions (`contrast()`, `url()`, ...) are dropped at
+/// parse time rather than invalidating the chain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// `blur(<length>)`: the length is the Gaussian standard deviation, in CSS px.
+    Blur(f32),
+    /// `drop-shadow()`: a blurred, offset copy of the content's alpha, painted beneath it.
+    DropShadow(Shadow),
+    /// `grayscale(<amount>)`, `0.0..=1.0`.
+    Grayscale(f32),
+    /// `brightness(<amount>)`: linear RGB multiplier, `1.0` = unchanged.
+    Brightness(f32),
+}
+
+impl Filter {
+    /// Whether the filter maps every pixel independently (no neighbourhood, no offset), so it
+    /// can equally be applied to paint colours instead of rendered pixels.
+    pub fn is_color_only(&self) -> bool {
+        matches!(self, Filter::Grayscale(_) | Filter::Brightness(_))
+    }
+
+    /// Applies a per-pixel filter to one colour; identity for blur and drop-shadow.
+    pub fn map_color(&self, color: &Color) -> Color {
+        let [r, g, b] = self.map_rgb([color.r(), color.g(), color.b()]);
+        Color::from_rgba(r, g, b, color.a())
+    }
+
+    /// Per-side ink overflow the filter adds around its input.
+    pub fn overflow(&self) -> Trbl<f64> {
+        match self {
+            Filter::Blur(sigma) => {
+                let extent = *sigma as f64 * 3.0;
+                Trbl {
+                    top: extent,
+                    right: extent,
+                    bottom: extent,
+                    left: extent,
+                }
+            }
+            Filter::DropShadow(shadow) => shadow.overflow(),
+            Filter::Grayscale(_) | Filter::Brightness(_) => Trbl::ZERO,
+        }
+    }
+
+    /// The filter's matrix on straight RGB. Linear without offset, so it is equally valid on
+    /// premultiplied components.
+    fn map_rgb(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        match self {
+            Filter::Grayscale(amount) => {
+                // Filter Effects 1 `grayscale()` matrix.
+                let s = 1.0 - amount.clamp(0.0, 1.0);
+                [
+                    (0.2126 + 0.7874 * s) * r + (0.7152 - 0.7152 * s) * g + (0.0722 - 0.0722 * s) * b,
+                    (0.2126 - 0.2126 * s) * r + (0.7152 + 0.2848 * s) * g + (0.0722 - 0.0722 * s) * b,
+                    (0.2126 - 0.2126 * s) * r + (0.7152 - 0.7152 * s) * g + (0.0722 + 0.9278 * s) * b,
+                ]
+            }
+            Filter::Brightness(amount) => [r * amount, g * amount, b * amount],
+            Filter::Blur(_) | Filter::DropShadow(_) => [r, g, b],
+        }
+    }
+}
+
+/// Total ink overflow of a filter chain. Each filter grows the output of the previous one, so
+/// the extents add up.
+pub fn filters_overflow(filters: &[Filter]) -> Trbl<f64> {
+    filters.iter().fold(Trbl::ZERO, |acc, f| {
+        let o = f.overflow();
+        Trbl {
+            top: acc.top + o.top,
+            right: acc.right + o.right,
+            bottom: acc.bottom + o.bottom,
+            left: acc.left + o.left,
+        }
+    })
+}
+
+/// Parses a `filter` value into its function chain. An invalid argument invalidates the whole
+/// chain, as in CSS.
+pub fn parse_filters(text: &str, current_color: &Color, font_size: f32) -> Vec<Filter> {
+    let text = text.trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    let mut filters = Vec::new();
+    for function in split_top_level(text, char::is_whitespace) {
+        let Some((name, args)) = function.strip_suffix(')').and_then(|f| f.split_once('(')) else {
+            log::debug!("Ignoring invalid filter: {text}");
+            return Vec::new();
+        };
+        let args = args.trim();
+        let filter = match name.trim().cow_to_ascii_lowercase().as_ref() {
+            "blur" if args.is_empty() => Some(Filter::Blur(0.0)),
+            "blur" => parse_length(args, font_size).filter(|v| *v >= 0.0).map(Filter::Blur),
+            "grayscale" => parse_amount(args).map(|v| Filter::Grayscale(v.min(1.0))),
+            "brightness" => parse_amount(args).map(Filter::Brightness),
+            "drop-shadow" => parse_shadow(args, current_color, font_size, false).map(Filter::DropShadow),
+            other => {
+                log::debug!("Unsupported filter function `{other}()`, skipping");
+                continue;
+            }
+        };
+        match filter {
+            Some(f) => filters.push(f),
+            None => {
+                log::debug!("Ignoring invalid filter: {text}");
+                return Vec::new();
+            }
+        }
+    }
+    filters
+}
+
+/// `<number> | <percentage>`, defaulting to 1 when omitted. Negative amounts are invalid.
+fn parse_amount(args: &str) -> Option<f32> {
+    if args.is_empty() {
+        return Some(1.0);
+    }
+    let value = match args.strip_suffix('%') {
+        Some(pct) => pct.trim().parse::<f32>().ok()? / 100.0,
+        None => args.parse::<f32>().ok()?,
+    };
+    (value >= 0.0).then_some(value)
+}
+
+// ── CPU filter application ──────────────────────────────────────────────────────
+//
+// Shared by the CPU rasterizers (Cairo, Skia) so both produce the same pixels. Buffers are
+// premultiplied 8-bit, 4 bytes per pixel, `stride` bytes per row.
+
+/// Byte offsets of R, G, B within a pixel; alpha is always the 4th byte.
+fn rgb_offsets(format: PixelFormat) -> [usize; 3] {
+    match format {
+        PixelFormat::PreMulArgb32 => [2, 1, 0],
+        PixelFormat::Rgba8 => [0, 1, 2],
+    }
+}
+
+/// Applies `filters` in order to a premultiplied buffer. `scale` maps CSS px to buffer pixels
+/// (the device pixel ratio). The caller must leave enough transparent margin around the content
+/// for blur and drop-shadow overflow (see [`filters_overflow`]).
+pub fn apply_filters(
+    pixels: &mut [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: PixelFormat,
+    filters: &[Filter],
+    scale: f32,
+) {
+    for filter in filters {
+        match filter {
+            Filter::Blur(sigma) => blur_premultiplied(pixels, width, height, stride, sigma * scale),
+            Filter::DropShadow(shadow) => drop_shadow(pixels, width, height, stride, format, shadow, scale),
+            Filter::Grayscale(_) | Filter::Brightness(_) => {
+                let [ri, gi, bi] = rgb_offsets(format);
+                for y in 0..height {
+                    let row = &mut pixels[y * stride..y * stride + width * 4];
+                    for px in row.chunks_exact_mut(4) {
+                        if px[3] == 0 {
+                            continue;
+                        }
+                        let a = px[3] as f32;
+                        let rgb = [px[ri] as f32, px[gi] as f32, px[bi] as f32];
+                        let [r, g, b] = filter.map_rgb(rgb);
+                        // Premultiplied components can never exceed alpha.
+                        px[ri] = r.round().clamp(0.0, a) as u8;
+                        px[gi] = g.round().clamp(0.0, a) as u8;
+                        px[bi] = b.round().clamp(0.0, a) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Paints a blurred, offset, colourised copy of the buffer's alpha beneath its content.
+fn drop_shadow(
+    pixels: &mut [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: PixelFormat,
+    shadow: &Shadow,
+    scale: f32,
+) {
+    let [ri, gi, bi] = rgb_offsets(format);
+    let dx = (shadow.offset_x * scale).round() as isize;
+    let dy = (shadow.offset_y * scale).round() as isize;
+    let color = &shadow.color;
+
+    let mut layer = vec![0u8; stride * height];
+    for y in 0..height {
+        let sy = y as isize - dy;
+        if sy < 0 || sy >= height as isize {
+            continue;
+        }
+        for x in 0..width {
+            let sx = x as isize - dx;
+            if sx < 0 || sx >= width as isize {
+                continue;
+            }
+            let src_a = pixels[sy as usize * stride + sx as usize * 4 + 3] as f32 / 255.0;
+            let a = src_a * color.a();
+            if a <= 0.0 {
+                continue;
+            }
+            let i = y * stride + x * 4;
+            layer[i + ri] = (color.r() * a * 255.0).round() as u8;
+            layer[i + gi] = (color.g() * a * 255.0).round() as u8;
+            layer[i + bi] = (color.b() * a * 255.0).round() as u8;
+            layer[i + 3] = (a * 255.0).round() as u8;
+        }
+    }
+    blur_premultiplied(&mut layer, width, height, stride, shadow.sigma() * scale);
+
+    // Source-over: content on top of its shadow.
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * stride + x * 4;
+            let inv = 255 - pixels[i + 3] as u32;
+            for c in 0..4 {
+                let v = pixels[i + c] as u32 + (layer[i + c] as u32 * inv + 127) / 255;
+                pixels[i + c] = v.min(255) as u8;
+            }
+        }
+    }
+}
+
+/// Gaussian blur of a premultiplied buffer, approximated by three successive box blurs per
+/// axis (within a few percent of a true Gaussian, at O(1) cost per pixel regardless of sigma).
+/// Pixels outside the buffer count as transparent.
+pub fn blur_premultiplied(pixels: &mut [u8], width: usize, height: usize, stride: usize, sigma: f32) {
+    if sigma < 0.5 || width == 0 || height == 0 {
+        return;
+    }
+    let mut scratch = vec![0u8; pixels.len()];
+    for radius in box_radii_for_gauss(sigma) {
+        if radius == 0 {
+            continue;
+        }
+        box_blur_pass(pixels, &mut scratch, height, width, stride, 4, radius);
+        box_blur_pass(&scratch, pixels, width, height, 4, stride, radius);
+    }
+}
+
+/// Radii of three box filters whose combination approximates a Gaussian of `sigma`.
+fn box_radii_for_gauss(sigma: f32) -> [usize; 3] {
+    let n = 3.0f32;
+    let w_ideal = (12.0 * sigma * sigma / n + 1.0).sqrt();
+    let mut wl = w_ideal.floor() as i64;
+    if wl % 2 == 0 {
+        wl -= 1;
+    }
+    let wu = wl + 2;
+    let wlf = wl as f32;
+    let m_ideal = (12.0 * sigma * sigma - n * wlf * wlf - 4.0 * n * wlf - 3.0 * n) / (-4.0 * wlf - 4.0);
+    let m = m_ideal.round() as i64;
+    [0i64, 1, 2].map(|i| {
+        let w = if i < m { wl } else { wu };
+        ((w - 1) / 2).max(0) as usize
+    })
+}
+
+/// One sliding-window box blur along `len`-pixel lines. Pixel `i` of line `l` lives at
+/// `l * line_step + i * px_step`; horizontal and vertical passes differ only in the steps.
+fn box_blur_pass(
+    src: &[u8],
+    dst: &mut [u8],
+    lines: usize,
+    len: usize,
+    line_step: usize,
+    px_step: usize,
+    radius: usize,
+) {
+    let div = (2 * radius + 1) as u32;
+    for line in 0..lines {
+        let base = line * line_step;
+        for c in 0..4 {
+            let at = |i: usize| base + i * px_step + c;
+            let mut acc: u32 = (0..=radius.min(len - 1)).map(|i| src[at(i)] as u32).sum();
+            for i in 0..len {
+                dst[at(i)] = ((acc + div / 2) / div) as u8;
+                if i + radius + 1 < len {
+                    acc += src[at(i + radius + 1)] as u32;
+                }
+                if i >= radius {
+                    acc -= src[at(i - radius)] as u32;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filter_chain() {
+        let filters = parse_filters(
+            "blur(2px) grayscale(50%) brightness(1.5) drop-shadow(1px 2px 3px red)",
+            &Color::BLACK,
+            16.0,
+        );
+        assert_eq!(filters.len(), 4);
+        assert!(matches!(filters[0], Filter::Blur(v) if v == 2.0));
+        assert!(matches!(filters[1], Filter::Grayscale(v) if v == 0.5));
+        assert!(matches!(filters[2], Filter::Brightness(v) if v == 1.5));
+        match &filters[3] {
+            Filter::DropShadow(s) => {
+                assert_eq!((s.offset_x, s.offset_y, s.blur), (1.0, 2.0, 3.0));
+                assert_eq!(s.color.r8(), 255);
+            }
+            other => panic!("expected drop-shadow, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unsupported_functions_are_skipped_and_invalid_args_reject() {
+        let filters = parse_filters("contrast(2) grayscale()", &Color::BLACK, 16.0);
+        assert_eq!(filters.len(), 1);
+        assert!(matches!(filters[0], Filter::Grayscale(v) if v == 1.0));
+
+        assert!(parse_filters("blur(-1px)", &Color::BLACK, 16.0).is_empty());
+        assert!(parse_filters("brightness(red)", &Color::BLACK, 16.0).is_empty());
+        assert!(parse_filters("none", &Color::BLACK, 16.0).is_empty());
+    }
+
+    #[test]
+    fn grayscale_maps_colour_to_luminance() {
+        let c = Filter::Grayscale(1.0).map_color(&Color::from_rgb8(255, 0, 0));
+        assert!((c.r() - 0.2126).abs() < 0.001);
+        assert!((c.g() - 0.2126).abs() < 0.001);
+        assert!((c.b() - 0.2126).abs() < 0.001);
+
+        let c = Filter::Grayscale(0.0).map_color(&Color::from_rgb8(255, 0, 0));
+        assert_eq!(c.r8(), 255);
+        assert_eq!(c.g8(), 0);
+    }
+
+    #[test]
+    fn brightness_stays_premultiplied() {
+        // Half-transparent white, premultiplied: [B, G, R, A] = 128s.
+        let mut px = vec![128u8, 128, 128, 128];
+        apply_filters(
+            &mut px,
+            1,
+            1,
+            4,
+            PixelFormat::PreMulArgb32,
+            &[Filter::Brightness(2.0)],
+            1.0,
+        );
+        assert_eq!(px, vec![128, 128, 128, 128]);
+
+        let mut px = vec![40u8, 40, 40, 128];
+        apply_filters(
+            &mut px,
+            1,
+            1,
+            4,
+            PixelFormat::PreMulArgb32,
+            &[Filter::Brightness(0.5)],
+            1.0,
+        );
+        assert_eq!(px, vec![20, 20, 20, 128]);
+    }
+
+    #[test]
+    fn blur_spreads_energy_and_conserves_it() {
+        let (w, h) = (21, 21);
+        let mut px = vec![0u8; w * h * 4];
+        // A 3x3 opaque block in the middle.
+        for y in 9..12 {
+            for x in 9..12 {
+                px[(y * w + x) * 4..(y * w + x) * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+            }
+        }
+        let before: u32 = px.iter().skip(3).step_by(4).map(|&a| a as u32).sum();
+        blur_premultiplied(&mut px, w, h, w * 4, 2.0);
+        let after: u32 = px.iter().skip(3).step_by(4).map(|&a| a as u32).sum();
+
+        let centre = px[(10 * w + 10) * 4 + 3];
+        let edge = px[(10 * w + 15) * 4 + 3];
+        assert!(
+            centre < 255 && centre > edge && edge > 0,
+            "centre {centre}, edge {edge}"
+        );
+        // Rounding per pass loses a little; nothing reaches the border, so the rest is kept.
+        assert!(
+            (before as i64 - after as i64).abs() < (before / 20) as i64,
+            "{before} vs {after}"
+        );
+    }
+
+    #[test]
+    fn drop_shadow_paints_offset_copy_beneath() {
+        let (w, h) = (8, 8);
+        let mut px = vec![0u8; w * h * 4];
+        let i = (2 * w + 2) * 4;
+        px[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+        let shadow = Shadow {
+            offset_x: 3.0,
+            offset_y: 1.0,
+            blur: 0.0,
+            spread: 0.0,
+            color: Color::from_rgb8(255, 0, 0),
+            inset: false,
+        };
+        apply_filters(
+            &mut px,
+            w,
+            h,
+            w * 4,
+            PixelFormat::Rgba8,
+            &[Filter::DropShadow(shadow)],
+            1.0,
+        );
+
+        // Content unchanged, red copy at (+3, +1).
+        assert_eq!(&px[i..i + 4], &[255, 255, 255, 255]);
+        let s = (3 * w + 5) * 4;
+        assert_eq!(&px[s..s + 4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn overflow_adds_up_across_the_chain() {
+        let filters = parse_filters("blur(2px) drop-shadow(4px 0 0 black)", &Color::BLACK, 16.0);
+        let o = filters_overflow(&filters);
+        assert_eq!(o.left, 6.0);
+        assert_eq!(o.right, 10.0);
+    }
+}
@@ -1,12 +1,26 @@
 use crate::painter::commands::color::Color;
 
+/// Largest side, in device pixels, of a gradient rasterized on the CPU. Larger boxes are sampled
+/// at this size and scaled up by the backend; gradients are smooth enough that this is invisible.
+pub const MAX_RASTER_SIZE: u32 = 2048;
+
+/// A resolved colour stop.
 #[derive(Clone, Debug)]
 pub struct ColorStop {
-    /// Position along the gradient line, `0.0` (start) .. `1.0` (end).
+    /// Position along the gradient ray, `0.0` (start) .. `1.0` (end). Repeating gradients and
+    /// out-of-box positions may fall outside that range.
     pub offset: f32,
     pub color: Color,
 }
 
+/// A colour stop as written. A stop without a position is spread evenly between its neighbours
+/// once the ray length is known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    pub color: Color,
+    pub position: Option<LengthPercent>,
+}
+
 /// Gradient as a repeated `background-image` layer: paints one `tile_size` cell and repeats it.
 /// Absent means the gradient fills the whole box (the plain `linear-gradient(...)` case).
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -20,12 +34,238 @@ pub struct Tiling {
     pub repeat: (bool, bool),
 }
 
-#[derive(Clone, Debug)]
+/// A `<length-percentage>` kept as `px + percent% of a basis`, the form every CSS position
+/// reduces to (`right 10px` is `100% - 10px`).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LengthPercent {
+    pub px: f32,
+    pub percent: f32,
+}
+
+impl LengthPercent {
+    pub const CENTER: LengthPercent = LengthPercent::percent(50.0);
+
+    pub const fn px(px: f32) -> Self {
+        LengthPercent { px, percent: 0.0 }
+    }
+
+    pub const fn percent(percent: f32) -> Self {
+        LengthPercent { px: 0.0, percent }
+    }
+
+    pub fn resolve(&self, basis: f32) -> f32 {
+        self.px + self.percent / 100.0 * basis
+    }
+}
+
+/// How hues interpolate in a polar colour space (`<hue-interpolation-method>`).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HueInterpolation {
+    #[default]
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+/// Colour space the stops are mixed in (`in <color-space>`). Spaces without their own variant
+/// map to the nearest supported one when parsed (`lab` → Oklab, `lch`/`hsl`/`hwb` → Oklch).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorInterpolation {
+    #[default]
+    Srgb,
+    SrgbLinear,
+    Oklab,
+    Oklch(HueInterpolation),
+}
+
+impl ColorInterpolation {
+    /// Mixes `a` and `b` at `f` (0 = `a`, 1 = `b`) with premultiplied alpha, as CSS Color 4
+    /// requires, so a fade to `transparent` doesn't darken.
+    pub fn mix(&self, a: &Color, b: &Color, f: f32) -> Color {
+        let alpha = a.a() + (b.a() - a.a()) * f;
+        if alpha <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+        let (ca, cb) = (self.encode(a), self.encode(b));
+        let hue = match self {
+            ColorInterpolation::Oklch(method) => Some(*method),
+            _ => None,
+        };
+        let out: [f32; 3] = std::array::from_fn(|i| match hue {
+            Some(method) if i == 2 => {
+                // Hue is never premultiplied; an achromatic colour takes the other side's hue.
+                let (mut h1, mut h2) = (ca[2], cb[2]);
+                if ca[1] < ACHROMATIC {
+                    h1 = h2;
+                } else if cb[1] < ACHROMATIC {
+                    h2 = h1;
+                }
+                let (h1, h2) = fixup_hues(h1, h2, method);
+                (h1 + (h2 - h1) * f).rem_euclid(360.0)
+            }
+            _ => {
+                let (pa, pb) = (ca[i] * a.a(), cb[i] * b.a());
+                (pa + (pb - pa) * f) / alpha
+            }
+        });
+        self.decode(out, alpha)
+    }
+
+    fn encode(&self, c: &Color) -> [f32; 3] {
+        let rgb = [c.r(), c.g(), c.b()];
+        match self {
+            ColorInterpolation::Srgb => rgb,
+            ColorInterpolation::SrgbLinear => rgb.map(srgb_to_linear),
+            ColorInterpolation::Oklab => linear_to_oklab(rgb.map(srgb_to_linear)),
+            ColorInterpolation::Oklch(_) => {
+                let [l, a, b] = linear_to_oklab(rgb.map(srgb_to_linear));
+                [l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)]
+            }
+        }
+    }
+
+    fn decode(&self, v: [f32; 3], alpha: f32) -> Color {
+        let rgb = match self {
+            ColorInterpolation::Srgb => v,
+            ColorInterpolation::SrgbLinear => v.map(linear_to_srgb),
+            ColorInterpolation::Oklab => oklab_to_linear(v).map(linear_to_srgb),
+            ColorInterpolation::Oklch(_) => {
+                let h = v[2].to_radians();
+                oklab_to_linear([v[0], v[1] * h.cos(), v[1] * h.sin()]).map(linear_to_srgb)
+            }
+        };
+        let [r, g, b] = rgb.map(|c| c.clamp(0.0, 1.0));
+        Color::from_rgba(r, g, b, alpha.clamp(0.0, 1.0))
+    }
+}
+
+/// Oklch chroma below which a colour's hue is treated as missing.
+const ACHROMATIC: f32 = 1e-4;
+
+fn fixup_hues(mut h1: f32, mut h2: f32, method: HueInterpolation) -> (f32, f32) {
+    let d = h2 - h1;
+    match method {
+        HueInterpolation::Shorter if d > 180.0 => h1 += 360.0,
+        HueInterpolation::Shorter if d < -180.0 => h2 += 360.0,
+        HueInterpolation::Longer if d > 0.0 && d < 180.0 => h1 += 360.0,
+        HueInterpolation::Longer if d > -180.0 && d <= 0.0 => h2 += 360.0,
+        HueInterpolation::Increasing if d < 0.0 => h2 += 360.0,
+        HueInterpolation::Decreasing if d > 0.0 => h1 += 360.0,
+        _ => {}
+    }
+    (h1, h2)
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.max(0.0).powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+fn oklab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+}
+
+/// Resolves written stops against a ray `length` px long: anchors the endpoints, spreads
+/// positionless stops evenly between their neighbours and keeps positions non-decreasing.
+pub fn resolve_stops(stops: &[GradientStop], length: f32) -> Vec<ColorStop> {
+    let n = stops.len();
+    let mut offsets: Vec<Option<f32>> = stops
+        .iter()
+        .map(|s| {
+            s.position.map(|p| {
+                if length > 0.0 {
+                    p.resolve(length) / length
+                } else {
+                    p.percent / 100.0
+                }
+            })
+        })
+        .collect();
+    if n == 0 {
+        return Vec::new();
+    }
+    if offsets[0].is_none() {
+        offsets[0] = Some(0.0);
+    }
+    if offsets[n - 1].is_none() {
+        offsets[n - 1] = Some(1.0);
+    }
+
+    // A stop positioned before an earlier one moves up to it (the CSS fix-up rule).
+    let mut running = f32::NEG_INFINITY;
+    for off in offsets.iter_mut().flatten() {
+        *off = off.max(running);
+        running = *off;
+    }
+
+    let mut i = 0;
+    while i < n {
+        if offsets[i].is_some() {
+            i += 1;
+            continue;
+        }
+        let start = i - 1; // resolved (endpoints are anchored)
+        let mut end = i;
+        while end < n && offsets[end].is_none() {
+            end += 1;
+        }
+        let a = offsets[start].unwrap_or(0.0);
+        let b = offsets.get(end).and_then(|o| *o).unwrap_or(1.0);
+        let steps = (end - start) as f32;
+        for (k, slot) in offsets.iter_mut().enumerate().take(end).skip(start + 1) {
+            *slot = Some(a + (b - a) * ((k - start) as f32) / steps);
+        }
+        i = end;
+    }
+
+    stops
+        .iter()
+        .zip(offsets)
+        .map(|(s, off)| ColorStop {
+            offset: off.unwrap_or(0.0),
+            color: s.color.clone(),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct LinearGradient {
     /// CSS degrees: `0` = to top, `90` = to right, `180` = to bottom, increasing clockwise.
     pub angle_deg: f32,
-    /// Source order, each with a resolved `offset` in `0.0..=1.0`.
-    pub stops: Vec<ColorStop>,
+    /// Source order; lengths resolve against the gradient line.
+    pub stops: Vec<GradientStop>,
+    pub repeating: bool,
+    pub interpolation: ColorInterpolation,
     /// Tiling for a repeated `background-image` layer, or `None` to fill the whole box.
     pub tiling: Option<Tiling>,
 }
@@ -45,61 +285,306 @@ impl LinearGradient {
         ((cx - dx * half, cy - dy * half), (cx + dx * half, cy + dy * half))
     }
 
-    /// Interpolated colour at `t` (0.0 = line start, 1.0 = line end). Stops must be sorted by
-    /// non-decreasing offset; two stops sharing one offset yield a hard edge.
-    pub fn color_at(&self, t: f32) -> Color {
-        match self.stops.as_slice() {
-            [] => Color::TRANSPARENT,
-            [only] => only.color.clone(),
-            stops => {
-                if t <= stops[0].offset {
-                    return stops[0].color.clone();
-                }
-                let last = &stops[stops.len() - 1];
-                if t >= last.offset {
-                    return last.color.clone();
+    /// Stops resolved against the gradient line of a `w`×`h` box.
+    pub fn color_stops(&self, w: f32, h: f32) -> Vec<ColorStop> {
+        let ((x0, y0), (x1, y1)) = self.line(w, h);
+        resolve_stops(&self.stops, (x1 - x0).hypot(y1 - y0))
+    }
+}
+
+/// `circle` or `ellipse` ending shape of a radial gradient.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RadialShape {
+    Circle,
+    #[default]
+    Ellipse,
+}
+
+/// `<radial-extent>` keyword sizing the ending shape against the box.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RadialExtent {
+    ClosestSide,
+    FarthestSide,
+    ClosestCorner,
+    #[default]
+    FarthestCorner,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RadialSize {
+    Extent(RadialExtent),
+    /// Explicit radii; a circle uses the first for both axes.
+    Explicit(LengthPercent, LengthPercent),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialGradient {
+    pub shape: RadialShape,
+    pub size: RadialSize,
+    /// Centre (`at <position>`), resolved against the box.
+    pub center: (LengthPercent, LengthPercent),
+    /// Source order; lengths resolve against the horizontal radius.
+    pub stops: Vec<GradientStop>,
+    pub repeating: bool,
+    pub interpolation: ColorInterpolation,
+    pub tiling: Option<Tiling>,
+}
+
+impl RadialGradient {
+    /// Centre and `(rx, ry)` radii of the ending shape within a `w`×`h` box.
+    pub fn geometry(&self, w: f32, h: f32) -> ((f32, f32), (f32, f32)) {
+        let (cx, cy) = (self.center.0.resolve(w), self.center.1.resolve(h));
+        let circle = self.shape == RadialShape::Circle;
+        let (near_x, far_x) = (cx.abs().min((w - cx).abs()), cx.abs().max((w - cx).abs()));
+        let (near_y, far_y) = (cy.abs().min((h - cy).abs()), cy.abs().max((h - cy).abs()));
+
+        let radii = match self.size {
+            RadialSize::Explicit(rx, ry) => {
+                let rx = rx.resolve(w);
+                if circle {
+                    (rx, rx)
+                } else {
+                    (rx, ry.resolve(h))
                 }
-                for pair in stops.windows(2) {
-                    let (a, b) = (&pair[0], &pair[1]);
-                    if t >= a.offset && t <= b.offset {
-                        let span = b.offset - a.offset;
-                        if span <= f32::EPSILON {
-                            // Hard stop: pick the colour on the far side of the edge.
-                            return b.color.clone();
+            }
+            RadialSize::Extent(extent) => {
+                let side = |closest: bool| {
+                    let (x, y) = if closest { (near_x, near_y) } else { (far_x, far_y) };
+                    if circle {
+                        let r = if closest { x.min(y) } else { x.max(y) };
+                        (r, r)
+                    } else {
+                        (x, y)
+                    }
+                };
+                match extent {
+                    RadialExtent::ClosestSide => side(true),
+                    RadialExtent::FarthestSide => side(false),
+                    RadialExtent::ClosestCorner | RadialExtent::FarthestCorner => {
+                        let closest = extent == RadialExtent::ClosestCorner;
+                        let (dx, dy) = if closest { (near_x, near_y) } else { (far_x, far_y) };
+                        if circle {
+                            let r = dx.hypot(dy);
+                            (r, r)
+                        } else {
+                            // Same aspect ratio as the matching `-side` ellipse, grown until it
+                            // passes through the corner.
+                            let (sx, sy) = side(closest);
+                            if sx <= 0.0 || sy <= 0.0 {
+                                (0.0, 0.0)
+                            } else {
+                                let ratio = sx / sy;
+                                let ry = (dx / ratio).hypot(dy);
+                                (ry * ratio, ry)
+                            }
                         }
-                        let f = (t - a.offset) / span;
-                        return Color::from_rgba(
-                            a.color.r() + (b.color.r() - a.color.r()) * f,
-                            a.color.g() + (b.color.g() - a.color.g()) * f,
-                            a.color.b() + (b.color.b() - a.color.b()) * f,
-                            a.color.a() + (b.color.a() - a.color.a()) * f,
-                        );
                     }
                 }
-                last.color.clone()
             }
+        };
+        ((cx, cy), radii)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConicGradient {
+    /// Start angle in CSS degrees, clockwise from the top (`from <angle>`).
+    pub from_deg: f32,
+    pub center: (LengthPercent, LengthPercent),
+    /// Source order; positions are percentages of a full turn.
+    pub stops: Vec<GradientStop>,
+    pub repeating: bool,
+    pub interpolation: ColorInterpolation,
+    pub tiling: Option<Tiling>,
+}
+
+/// A CSS gradient `<image>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Gradient {
+    Linear(LinearGradient),
+    Radial(RadialGradient),
+    Conic(ConicGradient),
+}
+
+/// A gradient rasterized for a backend that cannot draw it natively.
+pub struct GradientRaster {
+    /// Straight-alpha RGBA8, row-major.
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// CSS px covered by the whole raster; the backend scales it onto this size.
+    pub size: (f32, f32),
+    /// Offset of the raster's origin from the painted box, in CSS px.
+    pub position: (f32, f32),
+    /// Whether the raster repeats along the x / y axis.
+    pub repeat: (bool, bool),
+}
+
+impl Gradient {
+    pub fn stops(&self) -> &[GradientStop] {
+        match self {
+            Gradient::Linear(g) => &g.stops,
+            Gradient::Radial(g) => &g.stops,
+            Gradient::Conic(g) => &g.stops,
         }
     }
 
-    /// Rasterize one `tw`×`th` tile into straight-alpha RGBA8 (row-major, 4 bytes per pixel),
-    /// to be repeated across a tiled `background-image` layer.
-    pub fn rasterize_tile(&self, tw: u32, th: u32) -> Vec<u8> {
-        let (w, h) = (tw as f32, th as f32);
-        let ((x0, y0), (x1, y1)) = self.line(w, h);
-        let (dx, dy) = (x1 - x0, y1 - y0);
-        let len2 = dx * dx + dy * dy;
-        let mut out = vec![0u8; (tw as usize) * (th as usize) * 4];
-        for py in 0..th {
-            for px in 0..tw {
-                // Sample at the pixel centre and project onto the gradient line.
-                let (sx, sy) = (px as f32 + 0.5, py as f32 + 0.5);
-                let t = if len2 <= 0.0 {
+    fn stops_mut(&mut self) -> &mut Vec<GradientStop> {
+        match self {
+            Gradient::Linear(g) => &mut g.stops,
+            Gradient::Radial(g) => &mut g.stops,
+            Gradient::Conic(g) => &mut g.stops,
+        }
+    }
+
+    pub fn repeating(&self) -> bool {
+        match self {
+            Gradient::Linear(g) => g.repeating,
+            Gradient::Radial(g) => g.repeating,
+            Gradient::Conic(g) => g.repeating,
+        }
+    }
+
+    pub fn interpolation(&self) -> ColorInterpolation {
+        match self {
+            Gradient::Linear(g) => g.interpolation,
+            Gradient::Radial(g) => g.interpolation,
+            Gradient::Conic(g) => g.interpolation,
+        }
+    }
+
+    pub fn tiling(&self) -> Option<&Tiling> {
+        match self {
+            Gradient::Linear(g) => g.tiling.as_ref(),
+            Gradient::Radial(g) => g.tiling.as_ref(),
+            Gradient::Conic(g) => g.tiling.as_ref(),
+        }
+    }
+
+    pub fn set_tiling(&mut self, tiling: Option<Tiling>) {
+        match self {
+            Gradient::Linear(g) => g.tiling = tiling,
+            Gradient::Radial(g) => g.tiling = tiling,
+            Gradient::Conic(g) => g.tiling = tiling,
+        }
+    }
+
+    /// The plain `linear-gradient()` every backend draws with its own shader, with its stops
+    /// resolved for a `w`×`h` box: sRGB, not repeating, filling the box, every stop on the
+    /// gradient line. Everything else goes through [`Gradient::raster`], so all backends sample
+    /// the same pixels.
+    pub fn as_native_linear(&self, w: f32, h: f32) -> Option<(&LinearGradient, Vec<ColorStop>)> {
+        let Gradient::Linear(g) = self else {
+            return None;
+        };
+        if g.repeating || g.tiling.is_some() || g.interpolation != ColorInterpolation::Srgb {
+            return None;
+        }
+        let stops = g.color_stops(w, h);
+        stops
+            .iter()
+            .all(|s| (0.0..=1.0).contains(&s.offset))
+            .then_some((g, stops))
+    }
+
+    /// A copy with every stop colour passed through `f`.
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> Gradient {
+        let mut g = self.clone();
+        for stop in g.stops_mut() {
+            stop.color = f(&stop.color);
+        }
+        g
+    }
+
+    /// Stops resolved for a `w`×`h` box, with the ray length the gradient kind measures against.
+    pub fn color_stops(&self, w: f32, h: f32) -> Vec<ColorStop> {
+        match self {
+            Gradient::Linear(g) => g.color_stops(w, h),
+            Gradient::Radial(g) => resolve_stops(&g.stops, g.geometry(w, h).1 .0),
+            Gradient::Conic(g) => resolve_stops(&g.stops, 0.0),
+        }
+    }
+
+    /// Position along the gradient ray of the point `(x, y)` in a `w`×`h` box.
+    fn ray_position(&self, x: f32, y: f32, w: f32, h: f32) -> f32 {
+        match self {
+            Gradient::Linear(g) => {
+                let ((x0, y0), (x1, y1)) = g.line(w, h);
+                let (dx, dy) = (x1 - x0, y1 - y0);
+                let len2 = dx * dx + dy * dy;
+                if len2 <= 0.0 {
                     0.0
                 } else {
-                    (((sx - x0) * dx + (sy - y0) * dy) / len2).clamp(0.0, 1.0)
-                };
-                let c = self.color_at(t);
-                let i = ((py * tw + px) * 4) as usize;
+                    ((x - x0) * dx + (y - y0) * dy) / len2
+                }
+            }
+            Gradient::Radial(g) => {
+                let ((cx, cy), (rx, ry)) = g.geometry(w, h);
+                if rx <= 0.0 || ry <= 0.0 {
+                    // A degenerate ending shape shows the last stop everywhere.
+                    return f32::INFINITY;
+                }
+                ((x - cx) / rx).hypot((y - cy) / ry)
+            }
+            Gradient::Conic(g) => {
+                let (cx, cy) = (g.center.0.resolve(w), g.center.1.resolve(h));
+                // Clockwise from the top, as CSS angles run.
+                let angle = (x - cx).atan2(cy - y).to_degrees();
+                (angle - g.from_deg).rem_euclid(360.0) / 360.0
+            }
+        }
+    }
+
+    /// Colour at ray position `t` among resolved `stops` (sorted by non-decreasing offset; two
+    /// stops sharing one offset yield a hard edge).
+    pub fn color_at(&self, stops: &[ColorStop], t: f32) -> Color {
+        let (first, last) = match stops {
+            [] => return Color::TRANSPARENT,
+            [only] => return only.color.clone(),
+            [first, .., last] => (first, last),
+        };
+        let mut t = t;
+        if self.repeating() {
+            let span = last.offset - first.offset;
+            if span <= f32::EPSILON {
+                return last.color.clone();
+            }
+            if t.is_finite() {
+                t = first.offset + (t - first.offset).rem_euclid(span);
+            }
+        }
+        if t <= first.offset {
+            return first.color.clone();
+        }
+        if t >= last.offset {
+            return last.color.clone();
+        }
+        let interpolation = self.interpolation();
+        for pair in stops.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if t >= a.offset && t <= b.offset {
+                let span = b.offset - a.offset;
+                if span <= f32::EPSILON {
+                    // Hard stop: pick the colour on the far side of the edge.
+                    return b.color.clone();
+                }
+                return interpolation.mix(&a.color, &b.color, (t - a.offset) / span);
+            }
+        }
+        last.color.clone()
+    }
+
+    /// Rasterizes the gradient for a `w`×`h` CSS px box into a `pw`×`ph` straight-alpha RGBA8
+    /// image (row-major, 4 bytes per pixel), sampling each pixel at its centre.
+    pub fn rasterize(&self, w: f32, h: f32, pw: u32, ph: u32) -> Vec<u8> {
+        let stops = self.color_stops(w, h);
+        let (sx, sy) = (w / pw.max(1) as f32, h / ph.max(1) as f32);
+        let mut out = vec![0u8; (pw as usize) * (ph as usize) * 4];
+        for py in 0..ph {
+            for px in 0..pw {
+                let t = self.ray_position((px as f32 + 0.5) * sx, (py as f32 + 0.5) * sy, w, h);
+                let c = self.color_at(&stops, t);
+                let i = ((py * pw + px) * 4) as usize;
                 out[i] = c.r8();
                 out[i + 1] = c.g8();
                 out[i + 2] = c.b8();
@@ -108,23 +593,60 @@ impl LinearGradient {
         }
         out
     }
-}
 
-/// A CSS gradient. Only `linear-gradient()` is supported today; the enum leaves room for
-/// radial/conic variants.
-#[derive(Clone, Debug)]
-pub enum Gradient {
-    Linear(LinearGradient),
+    /// One rasterized copy of the gradient for a `box_w`×`box_h` box at `scale` device pixels
+    /// per CSS px: a single `background-size` tile when tiled, else the whole box. `None` for an
+    /// empty box or tile.
+    pub fn raster(&self, box_w: f32, box_h: f32, scale: f32) -> Option<GradientRaster> {
+        let (size, position, repeat) = match self.tiling() {
+            Some(t) => (t.tile_size, t.position, t.repeat),
+            None => ((box_w, box_h), (0.0, 0.0), (false, false)),
+        };
+        if size.0 <= 0.0 || size.1 <= 0.0 {
+            return None;
+        }
+        let pixels = |css: f32| ((css * scale.max(1.0)).round() as u32).clamp(1, MAX_RASTER_SIZE);
+        let (width, height) = (pixels(size.0), pixels(size.1));
+        Some(GradientRaster {
+            pixels: self.rasterize(size.0, size.1, width, height),
+            width,
+            height,
+            size,
+            position,
+            repeat,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn stop(color: Color, pct: Option<f32>) -> GradientStop {
+        GradientStop {
+            color,
+            position: pct.map(LengthPercent::percent),
+        }
+    }
+
     fn lg(angle_deg: f32) -> LinearGradient {
         LinearGradient {
             angle_deg,
             stops: Vec::new(),
+            repeating: false,
+            interpolation: ColorInterpolation::Srgb,
+            tiling: None,
+        }
+    }
+
+    fn rg(shape: RadialShape, size: RadialSize) -> RadialGradient {
+        RadialGradient {
+            shape,
+            size,
+            center: (LengthPercent::CENTER, LengthPercent::CENTER),
+            stops: Vec::new(),
+            repeating: false,
+            interpolation: ColorInterpolation::Srgb,
             tiling: None,
         }
     }
@@ -133,6 +655,11 @@ mod tests {
         assert!((a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01, "{a:?} != {b:?}");
     }
 
+    fn pixel(raster: &[u8], w: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * w + x) * 4) as usize;
+        [raster[i], raster[i + 1], raster[i + 2], raster[i + 3]]
+    }
+
     #[test]
     fn to_bottom_runs_top_to_bottom() {
         // 180deg = `to bottom`: start at top-centre, end at bottom-centre.
@@ -154,4 +681,94 @@ mod tests {
         approx(start, (50.0, 200.0));
         approx(end, (50.0, 0.0));
     }
+
+    #[test]
+    fn positionless_stops_spread_and_px_resolve_against_the_line() {
+        let stops = [
+            stop(Color::BLACK, None),
+            stop(Color::BLACK, None),
+            GradientStop {
+                color: Color::BLACK,
+                position: Some(LengthPercent::px(50.0)),
+            },
+            stop(Color::BLACK, Some(20.0)),
+        ];
+        let offsets: Vec<f32> = resolve_stops(&stops, 100.0).iter().map(|s| s.offset).collect();
+        // The last stop (20%) sits before the 50px one, so it moves up to it.
+        assert_eq!(offsets, vec![0.0, 0.25, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn radial_extents_size_the_ending_shape() {
+        // Box 200×100, centred: sides are 100 / 50 px away.
+        let geo = |shape, extent| rg(shape, RadialSize::Extent(extent)).geometry(200.0, 100.0).1;
+        approx(geo(RadialShape::Circle, RadialExtent::ClosestSide), (50.0, 50.0));
+        approx(geo(RadialShape::Circle, RadialExtent::FarthestSide), (100.0, 100.0));
+        approx(geo(RadialShape::Ellipse, RadialExtent::ClosestSide), (100.0, 50.0));
+        let corner = 100.0f32.hypot(50.0);
+        approx(geo(RadialShape::Circle, RadialExtent::FarthestCorner), (corner, corner));
+        // The ellipse keeps the side ratio (2:1) and passes through the corner: √2 × sides.
+        let s = std::f32::consts::SQRT_2;
+        approx(
+            geo(RadialShape::Ellipse, RadialExtent::FarthestCorner),
+            (100.0 * s, 50.0 * s),
+        );
+    }
+
+    #[test]
+    fn conic_starts_at_the_from_angle_and_runs_clockwise() {
+        let g = Gradient::Conic(ConicGradient {
+            from_deg: 90.0,
+            center: (LengthPercent::CENTER, LengthPercent::CENTER),
+            stops: Vec::new(),
+            repeating: false,
+            interpolation: ColorInterpolation::Srgb,
+            tiling: None,
+        });
+        // Straight right of the centre is the start; straight below is a quarter turn on.
+        assert!(g.ray_position(100.0, 50.0, 100.0, 100.0).abs() < 0.001);
+        assert!((g.ray_position(50.0, 100.0, 100.0, 100.0) - 0.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn repeating_gradients_wrap_the_stop_span() {
+        let mut g = lg(90.0);
+        g.repeating = true;
+        g.stops = vec![
+            GradientStop {
+                color: Color::BLACK,
+                position: Some(LengthPercent::px(0.0)),
+            },
+            GradientStop {
+                color: Color::WHITE,
+                position: Some(LengthPercent::px(10.0)),
+            },
+        ];
+        let g = Gradient::Linear(g);
+        let raster = g.rasterize(40.0, 1.0, 40, 1);
+        // Every 10px the ramp restarts, so pixels 0, 10, 20 and 30 are equally dark.
+        assert_eq!(pixel(&raster, 40, 0, 0), pixel(&raster, 40, 20, 0));
+        assert_eq!(pixel(&raster, 40, 10, 0), pixel(&raster, 40, 30, 0));
+        assert!(pixel(&raster, 40, 9, 0)[0] > 200);
+    }
+
+    #[test]
+    fn oklch_takes_the_short_way_round_the_hue_wheel() {
+        let red = Color::from_rgba(1.0, 0.0, 0.0, 1.0);
+        let blue = Color::from_rgba(0.0, 0.0, 1.0, 1.0);
+        let srgb = ColorInterpolation::Srgb.mix(&red, &blue, 0.5);
+        let oklch = ColorInterpolation::Oklch(HueInterpolation::Shorter).mix(&red, &blue, 0.5);
+        // sRGB's midpoint is a dull purple; oklch keeps the chroma and lands on a vivid magenta.
+        assert!(oklch.r() > srgb.r() && oklch.b() > srgb.b(), "{oklch:?} vs {srgb:?}");
+    }
+
+    #[test]
+    fn premultiplied_fade_to_transparent_keeps_the_hue() {
+        let red = Color::from_rgba(1.0, 0.0, 0.0, 1.0);
+        let mid = ColorInterpolation::Srgb.mix(&red, &Color::TRANSPARENT, 0.5);
+        assert!(
+            (mid.r() - 1.0).abs() < 0.001 && (mid.a() - 0.5).abs() < 0.001,
+            "{mid:?}"
+        );
+    }
 }
@@ -1,58 +1,9 @@
 use crate::common::geo::Rect;
 use crate::painter::commands::border::Border;
 use crate::painter::commands::brush::Brush;
+use crate::painter::commands::color::Color;
 
-/// CSS `mix-blend-mode`: how a box's pixels combine with the backdrop beneath it.
-/// `Normal` is plain source-over.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
-pub enum BlendMode {
-    #[default]
-    Normal,
-    Multiply,
-    Screen,
-    Overlay,
-    Darken,
-    Lighten,
-    ColorDodge,
-    ColorBurn,
-    HardLight,
-    SoftLight,
-    Difference,
-    Exclusion,
-    Hue,
-    Saturation,
-    Color,
-    Luminosity,
-}
-
-impl BlendMode {
-    /// Parse a CSS `mix-blend-mode` keyword; unknown keywords fall back to `Normal`.
-    pub fn from_css_keyword(keyword: &str) -> Self {
-        match keyword {
-            "multiply" => BlendMode::Multiply,
-            "screen" => BlendMode::Screen,
-            "overlay" => BlendMode::Overlay,
-            "darken" => BlendMode::Darken,
-            "lighten" => BlendMode::Lighten,
-            "color-dodge" => BlendMode::ColorDodge,
-            "color-burn" => BlendMode::ColorBurn,
-            "hard-light" => BlendMode::HardLight,
-            "soft-light" => BlendMode::SoftLight,
-            "difference" => BlendMode::Difference,
-            "exclusion" => BlendMode::Exclusion,
-            "hue" => BlendMode::Hue,
-            "saturation" => BlendMode::Saturation,
-            "color" => BlendMode::Color,
-            "luminosity" => BlendMode::Luminosity,
-            _ => BlendMode::Normal,
-        }
-    }
-
-    /// Stable small integer for content hashing.
-    pub fn id(&self) -> u8 {
-        *self as u8
-    }
-}
+pub use crate::render::backend::BlendMode;
 
 #[derive(Clone, Debug, Copy)]
 pub struct Radius {
@@ -192,4 +143,33 @@ impl Rectangle {
             self.radius_left.y,
         )
     }
+
+    /// A plain rectangle shrunk by `[top, right, bottom, left]`, with each corner radius shrunk
+    /// by its adjacent edges (the CSS inner border radius). Background and border are dropped.
+    pub fn inset(&self, edges: [f64; 4]) -> Rectangle {
+        let [top, right, bottom, left] = edges;
+        let r = self.rect;
+        let inner = Rect::new(
+            r.x + left,
+            r.y + top,
+            (r.width - left - right).max(0.0),
+            (r.height - top - bottom).max(0.0),
+        );
+        let (rx_tl, rx_tr, rx_br, rx_bl) = self.radius_x();
+        let (ry_tl, ry_tr, ry_br, ry_bl) = self.radius_y();
+        Rectangle::new(inner).with_radius_tlrb(
+            Radius::new_double((rx_tl - left).max(0.0), (ry_tl - top).max(0.0)),
+            Radius::new_double((rx_tr - right).max(0.0), (ry_tr - top).max(0.0)),
+            Radius::new_double((rx_br - right).max(0.0), (ry_br - bottom).max(0.0)),
+            Radius::new_double((rx_bl - left).max(0.0), (ry_bl - bottom).max(0.0)),
+        )
+    }
+
+    /// A copy with background and border colours passed through `f` (colour-only filters).
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> Rectangle {
+        let mut r = self.clone();
+        r.background = self.background.as_ref().map(|b| b.map_colors(f));
+        r.border = self.border.map_colors(f);
+        r
+    }
 }
@@ -0,0 +1,370 @@
+use crate::common::geo::Rect;
+use crate::painter::commands::color::Color;
+use crate::painter::commands::rectangle::{Radius, Rectangle};
+use crate::painter::commands::Trbl;
+use cow_utils::CowUtils;
+
+/// One parsed `box-shadow` / `text-shadow` / `drop-shadow()` layer, lengths in CSS px.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// CSS blur radius; the Gaussian standard deviation is half of it (see [`Shadow::sigma`]).
+    pub blur: f32,
+    /// Grows (or, negative, shrinks) the shadow shape. Always 0 for text shadows.
+    pub spread: f32,
+    pub color: Color,
+    /// `inset` box shadows paint inside the padding box instead of outside the border box.
+    pub inset: bool,
+}
+
+impl Shadow {
+    /// Gaussian standard deviation for the blur radius, per css-backgrounds: `sigma = blur / 2`.
+    pub fn sigma(&self) -> f32 {
+        self.blur / 2.0
+    }
+
+    /// How far the blurred edge reaches past the unblurred shape. 3σ keeps the clipped tail
+    /// below one 8-bit step, so a tile cut there shows no seam.
+    pub fn blur_extent(&self) -> f64 {
+        self.sigma() as f64 * 3.0
+    }
+
+    /// Per-side ink overflow past the element box. Inset shadows never leave the box.
+    pub fn overflow(&self) -> Trbl<f64> {
+        if self.inset {
+            return Trbl::ZERO;
+        }
+        let reach = self.blur_extent() + self.spread as f64;
+        let (dx, dy) = (self.offset_x as f64, self.offset_y as f64);
+        Trbl {
+            top: (reach - dy).max(0.0),
+            right: (reach + dx).max(0.0),
+            bottom: (reach + dy).max(0.0),
+            left: (reach - dx).max(0.0),
+        }
+    }
+}
+
+/// Union of the ink overflow of every shadow in `shadows`.
+pub fn shadows_overflow(shadows: &[Shadow]) -> Trbl<f64> {
+    shadows.iter().fold(Trbl::ZERO, |acc, s| acc.max(&s.overflow()))
+}
+
+/// A single `box-shadow` layer for one element box.
+#[derive(Clone, Debug)]
+pub struct BoxShadow {
+    /// The element's border box, carrying its corner radii and border widths.
+    pub rect: Rectangle,
+    pub shadow: Shadow,
+}
+
+impl BoxShadow {
+    pub fn new(rect: Rectangle, shadow: Shadow) -> Self {
+        BoxShadow { rect, shadow }
+    }
+
+    /// The area the shadow may show in: outer shadows paint only *outside* this shape (the border
+    /// box), inset shadows only *inside* it (the padding box).
+    pub fn clip(&self) -> Rectangle {
+        if !self.shadow.inset {
+            return self.rect.clone();
+        }
+        self.rect.inset(self.rect.border().widths().map(|w| w as f64))
+    }
+
+    /// The shape whose (blurred) silhouette is the shadow: the clip box offset and grown by
+    /// `spread` (outer) or shrunk by it (inset), with the corner radii following the edges.
+    pub fn shape(&self) -> Rectangle {
+        let clip = self.clip();
+        let spread = if self.shadow.inset {
+            -self.shadow.spread as f64
+        } else {
+            self.shadow.spread as f64
+        };
+        let r = clip.rect();
+        let rect = Rect::new(
+            r.x + self.shadow.offset_x as f64 - spread,
+            r.y + self.shadow.offset_y as f64 - spread,
+            (r.width + 2.0 * spread).max(0.0),
+            (r.height + 2.0 * spread).max(0.0),
+        );
+        let (rx_tl, rx_tr, rx_br, rx_bl) = clip.radius_x();
+        let (ry_tl, ry_tr, ry_br, ry_bl) = clip.radius_y();
+        let corner = |rx: f64, ry: f64| Radius::new_double(spread_radius(rx, spread), spread_radius(ry, spread));
+        Rectangle::new(rect).with_radius_tlrb(
+            corner(rx_tl, ry_tl),
+            corner(rx_tr, ry_tr),
+            corner(rx_br, ry_br),
+            corner(rx_bl, ry_bl),
+        )
+    }
+
+    /// Page-space bounds of every pixel this shadow can touch.
+    pub fn ink_rect(&self) -> Rect {
+        if self.shadow.inset {
+            return self.clip().rect();
+        }
+        let extent = self.shadow.blur_extent();
+        let s = self.shape().rect();
+        Rect::new(
+            s.x - extent,
+            s.y - extent,
+            s.width + 2.0 * extent,
+            s.height + 2.0 * extent,
+        )
+    }
+}
+
+/// A corner radius adjusted for `spread`, per css-backgrounds: a sharp corner stays sharp, and
+/// a radius smaller than a positive spread grows along a cubic so small radii don't balloon.
+fn spread_radius(radius: f64, spread: f64) -> f64 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    if spread > 0.0 && radius < spread {
+        let ratio = radius / spread;
+        return radius + spread * (1.0 + (ratio - 1.0).powi(3));
+    }
+    (radius + spread).max(0.0)
+}
+
+/// Parses a `box-shadow` value. Any invalid layer invalidates the whole list, as in CSS.
+pub fn parse_box_shadows(text: &str, current_color: &Color, font_size: f32) -> Vec<Shadow> {
+    parse_shadow_list(text, current_color, font_size, true)
+}
+
+/// Parses a `text-shadow` value: like `box-shadow` but without `inset` or a spread length.
+pub fn parse_text_shadows(text: &str, current_color: &Color, font_size: f32) -> Vec<Shadow> {
+    parse_shadow_list(text, current_color, font_size, false)
+}
+
+fn parse_shadow_list(text: &str, current_color: &Color, font_size: f32, box_shadow: bool) -> Vec<Shadow> {
+    let text = text.trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    let mut shadows = Vec::new();
+    for layer in split_top_level(text, |c| c == ',') {
+        match parse_shadow(layer, current_color, font_size, box_shadow) {
+            Some(shadow) => shadows.push(shadow),
+            None => {
+                log::debug!("Ignoring invalid shadow list: {text}");
+                return Vec::new();
+            }
+        }
+    }
+    shadows
+}
+
+/// One shadow layer: 2-4 lengths (2-3 for text), an optional colour and, for boxes, `inset`.
+/// A missing colour is `currentcolor`.
+pub(crate) fn parse_shadow(layer: &str, current_color: &Color, font_size: f32, box_shadow: bool) -> Option<Shadow> {
+    let mut lengths = Vec::with_capacity(4);
+    let mut color = None;
+    let mut inset = false;
+    for token in split_top_level(layer, char::is_whitespace) {
+        if token.eq_ignore_ascii_case("inset") {
+            if !box_shadow || inset {
+                return None;
+            }
+            inset = true;
+        } else if let Some(len) = parse_length(token, font_size) {
+            lengths.push(len);
+        } else if color.is_none() {
+            color = Some(parse_color(token, current_color)?);
+        } else {
+            return None;
+        }
+    }
+
+    let max_lengths = if box_shadow { 4 } else { 3 };
+    if lengths.len() < 2 || lengths.len() > max_lengths {
+        return None;
+    }
+    let blur = lengths.get(2).copied().unwrap_or(0.0);
+    if blur < 0.0 {
+        return None;
+    }
+    Some(Shadow {
+        offset_x: lengths[0],
+        offset_y: lengths[1],
+        blur,
+        spread: lengths.get(3).copied().unwrap_or(0.0),
+        color: color.unwrap_or_else(|| current_color.clone()),
+        inset,
+    })
+}
+
+/// `currentcolor` or any colour `csscolorparser` accepts.
+pub(crate) fn parse_color(token: &str, current_color: &Color) -> Option<Color> {
+    if token.eq_ignore_ascii_case("currentcolor") {
+        return Some(current_color.clone());
+    }
+    Color::try_from_css(token)
+}
+
+/// A CSS length in px. Unitless values are only valid as `0`. Font-relative units resolve
+/// against `font_size`; `rem` against the fixed 16px root.
+pub(crate) fn parse_length(token: &str, font_size: f32) -> Option<f32> {
+    let token = token.trim();
+    let split = token
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(token.len());
+    let (num, unit) = token.split_at(split);
+    let value: f32 = num.parse().ok()?;
+    let px = match unit.cow_to_ascii_lowercase().as_ref() {
+        "" if value == 0.0 => 0.0,
+        "px" => value,
+        "em" => value * font_size,
+        "rem" => value * 16.0,
+        "ex" => value * font_size * 0.5,
+        "ch" => value * font_size * 0.55,
+        "pt" => value * 4.0 / 3.0,
+        "pc" => value * 16.0,
+        "in" => value * 96.0,
+        "cm" => value * 96.0 / 2.54,
+        "mm" => value * 96.0 / 25.4,
+        "q" => value * 96.0 / 101.6,
+        _ => return None,
+    };
+    Some(px)
+}
+
+/// Splits `text` on `is_sep` characters outside parentheses, so `rgba(0, 0, 0, .5)` and
+/// `drop-shadow(1px 1px red)` stay whole. Empty pieces are dropped.
+pub(crate) fn split_top_level(text: &str, is_sep: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && is_sep(c) => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts.into_iter().map(str::trim).filter(|p| !p.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::painter::commands::border::{Border, BorderStyle};
+    use crate::painter::commands::brush::Brush;
+
+    fn approx(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    #[test]
+    fn parses_box_shadow_layers() {
+        let shadows = parse_box_shadows(
+            "2px 3px 4px 1px rgba(0, 0, 0, 0.5), inset 0 0 1em red",
+            &Color::BLACK,
+            10.0,
+        );
+        assert_eq!(shadows.len(), 2);
+
+        let outer = &shadows[0];
+        assert_eq!(
+            (outer.offset_x, outer.offset_y, outer.blur, outer.spread),
+            (2.0, 3.0, 4.0, 1.0)
+        );
+        assert!(!outer.inset);
+        assert_eq!(outer.color.a8(), 127);
+
+        let inner = &shadows[1];
+        assert!(inner.inset);
+        assert_eq!(inner.blur, 10.0);
+        assert_eq!(inner.color.r8(), 255);
+    }
+
+    #[test]
+    fn missing_colour_is_current_color() {
+        let current = Color::from_rgb8(0, 0, 255);
+        let shadows = parse_text_shadows("1px 1px", &current, 16.0);
+        assert_eq!(shadows.len(), 1);
+        assert_eq!(shadows[0].color.b8(), 255);
+        assert_eq!(shadows[0].blur, 0.0);
+
+        let shadows = parse_box_shadows("currentcolor 1px 1px", &current, 16.0);
+        assert_eq!(shadows[0].color.b8(), 255);
+    }
+
+    #[test]
+    fn invalid_layer_invalidates_list() {
+        assert!(parse_box_shadows("1px 1px red, 1px", &Color::BLACK, 16.0).is_empty());
+        assert!(parse_box_shadows("1px 1px -2px red", &Color::BLACK, 16.0).is_empty());
+        // Text shadows take neither `inset` nor a spread length.
+        assert!(parse_text_shadows("inset 1px 1px red", &Color::BLACK, 16.0).is_empty());
+        assert!(parse_text_shadows("1px 1px 1px 1px red", &Color::BLACK, 16.0).is_empty());
+        assert!(parse_box_shadows("none", &Color::BLACK, 16.0).is_empty());
+    }
+
+    #[test]
+    fn overflow_follows_offset_blur_and_spread() {
+        let shadow = &parse_box_shadows("4px -2px 2px 1px black", &Color::BLACK, 16.0)[0];
+        // sigma = 1, extent = 3, reach = 3 + 1 spread.
+        let o = shadow.overflow();
+        approx(o.left, 0.0);
+        approx(o.right, 8.0);
+        approx(o.top, 6.0);
+        approx(o.bottom, 2.0);
+
+        let inset = &parse_box_shadows("inset 4px 4px 8px black", &Color::BLACK, 16.0)[0];
+        approx(inset.overflow().right, 0.0);
+    }
+
+    #[test]
+    fn shape_grows_with_spread_and_keeps_radii() {
+        let rect = Rectangle::new(Rect::new(10.0, 10.0, 100.0, 50.0)).with_radius(Radius::new(8.0));
+        let shadow = parse_box_shadows("5px 5px 0 2px black", &Color::BLACK, 16.0).remove(0);
+        let shape = BoxShadow::new(rect, shadow).shape();
+        let r = shape.rect();
+        approx(r.x, 13.0);
+        approx(r.y, 13.0);
+        approx(r.width, 104.0);
+        approx(r.height, 54.0);
+        approx(shape.radius_x().0, 10.0);
+    }
+
+    #[test]
+    fn inset_shape_sits_inside_padding_box() {
+        let border = Border::new(
+            2.0,
+            BorderStyle::Solid,
+            std::array::from_fn(|_| Brush::solid(Color::BLACK)),
+        );
+        let rect = Rectangle::new(Rect::new(0.0, 0.0, 100.0, 100.0))
+            .with_border(border)
+            .with_radius(Radius::new(4.0));
+        let shadow = parse_box_shadows("inset 0 0 0 3px black", &Color::BLACK, 16.0).remove(0);
+        let bs = BoxShadow::new(rect, shadow);
+
+        let clip = bs.clip().rect();
+        approx(clip.x, 2.0);
+        approx(clip.width, 96.0);
+        approx(bs.clip().radius_x().0, 2.0);
+
+        let shape = bs.shape();
+        approx(shape.rect().x, 5.0);
+        approx(shape.rect().width, 90.0);
+        // The 2px padding-box radius is swallowed by the 3px inward spread.
+        approx(shape.radius_x().0, 0.0);
+    }
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(parse_length("0", 16.0), Some(0.0));
+        assert_eq!(parse_length("2em", 10.0), Some(20.0));
+        assert_eq!(parse_length("1in", 16.0), Some(96.0));
+        assert_eq!(parse_length("3", 16.0), None);
+        assert_eq!(parse_length("red", 16.0), None);
+    }
+}
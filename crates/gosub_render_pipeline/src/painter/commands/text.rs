@@ -1,6 +1,8 @@
 use crate::common::font::FontInfo;
 use crate::common::geo::Rect;
 use crate::painter::commands::brush::Brush;
+use crate::painter::commands::color::Color;
+use crate::painter::commands::shadow::Shadow;
 use gosub_interface::font_system::ShapedText;
 
 #[derive(Clone, Debug)]
@@ -16,6 +18,9 @@ pub struct Text {
     /// by construction the measured ones. Glyph-based rasterizers paint these runs; engine-native
     /// ones (Pango, Parley, Skia textlayout) re-shape from `text` + `font_info` and ignore this.
     pub shaped: ShapedText,
+    /// `text-shadow` layers in source order; the first listed paints on top, all beneath the
+    /// glyphs.
+    pub shadows: Vec<Shadow>,
 }
 
 impl Text {
@@ -34,6 +39,22 @@ impl Text {
             brush,
             available_width,
             shaped,
+            shadows: Vec::new(),
         }
     }
+
+    pub fn with_shadows(mut self, shadows: Vec<Shadow>) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// A copy with the text and shadow colours passed through `f`.
+    pub fn map_colors(&self, f: &dyn Fn(&Color) -> Color) -> Text {
+        let mut t = self.clone();
+        t.brush = self.brush.map_colors(f);
+        for shadow in &mut t.shadows {
+            shadow.color = f(&shadow.color);
+        }
+        t
+    }
 }
@@ -70,10 +70,28 @@ pub struct BakedTile {
     pub pixels: TilePixels,
     /// In-memory byte order of the pixels (CPU variant), set by the rasterizer that produced it.
     pub format: crate::render::backend::PixelFormat,
-    /// Group opacity (1.0 = opaque) of this tile's layer, applied by the compositor.
+    /// Effective group opacity (1.0 = opaque) of this tile's layer, applied by the compositor.
     pub opacity: f32,
+    /// Isolated groups the tile composites through, outermost first (see [`CachedTile::groups`]).
+    pub groups: Arc<[crate::render::backend::CompositeGroup]>,
     /// How this tile's layer responds to scroll (normal flow vs. `position: fixed`).
     pub anchor: crate::render::backend::TileAnchor,
+    /// Composite-time offset of this tile's layer from CSS `transform`, in CSS px. `page_x` /
+    /// `page_y` stay where the tile was rasterized, so carry-over keys are unaffected.
+    pub translate: (f64, f64),
+}
+
+/// Re-reads each tile's layer opacity, groups and transform offset from `layer_list`: the
+/// compositor-only update for `opacity` and `transform` animations, which fades and moves tiles
+/// without re-rasterizing them.
+pub fn refresh_compositing(baked: &mut [BakedTile], layer_list: &crate::layering::layer::LayerList) {
+    use crate::layering::layer::LayerId;
+    for tile in baked {
+        let layer_id = LayerId::new(tile.layer_id);
+        tile.opacity = layer_list.layer_opacity(layer_id);
+        tile.groups = layer_list.layer_groups(layer_id);
+        tile.translate = layer_list.layer_translation(layer_id);
+    }
 }
 
 /// Key that uniquely identifies a tile's content for cache lookup.
@@ -90,7 +108,7 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
     use crate::painter::commands::{
         border::{BorderRadius, BorderStyle},
         brush::Brush,
-        gradient::Gradient,
+        filter::Filter,
         PaintCommand,
     };
 
@@ -157,21 +175,29 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
                         None => hbool!(false),
                     }
                 }
-                Brush::Gradient(Gradient::Linear(g)) => {
+                Brush::Gradient(g) => {
+                    // The Debug form covers every field: kind, geometry, stops, tiling.
                     fnv!(&[2]);
-                    hf32!(g.angle_deg);
-                    for stop in &g.stops {
-                        hf32!(stop.offset);
-                        hf32!(stop.color.r());
-                        hf32!(stop.color.g());
-                        hf32!(stop.color.b());
-                        hf32!(stop.color.a());
-                    }
+                    hstr!(&format!("{g:?}"));
                 }
             }
         };
     }
 
+    macro_rules! hash_shadow {
+        ($s:expr) => {
+            hf32!($s.offset_x);
+            hf32!($s.offset_y);
+            hf32!($s.blur);
+            hf32!($s.spread);
+            hbool!($s.inset);
+            hf32!($s.color.r());
+            hf32!($s.color.g());
+            hf32!($s.color.b());
+            hf32!($s.color.a());
+        };
+    }
+
     match tile.bgcolor {
         Some((r, g, b, a)) => {
             hbool!(true);
@@ -189,6 +215,10 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
         hf64!(elem.rect.y);
         hf64!(elem.rect.width);
         hf64!(elem.rect.height);
+        hf64!(elem.ink.x);
+        hf64!(elem.ink.y);
+        hf64!(elem.ink.width);
+        hf64!(elem.ink.height);
 
         for cmd in &elem.paint_commands {
             match cmd {
@@ -271,7 +301,13 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
                     hu64!(t.font_info.slant as u64);
                     hbool!(t.font_info.underline);
                     hbool!(t.font_info.line_through);
+                    hstr!(&t.font_info.shaping.cache_key());
+                    // A selection splits the glyphs of one text over two commands.
+                    hu64!(t.shaped.runs.iter().map(|run| run.glyphs.len() as u64).sum::<u64>());
                     hash_brush!(&t.brush);
+                    for s in &t.shadows {
+                        hash_shadow!(s);
+                    }
                 }
                 PaintCommand::Svg(s) => {
                     fnv!(&[2u8]);
@@ -282,6 +318,49 @@ fn tile_cache_key(tile: &crate::tiler::Tile) -> TileCacheKey {
                     hf64!(rect.width);
                     hf64!(rect.height);
                 }
+                PaintCommand::BoxShadow(bs) => {
+                    fnv!(&[3u8]);
+                    let rect = bs.rect.rect();
+                    hf64!(rect.x);
+                    hf64!(rect.y);
+                    hf64!(rect.width);
+                    hf64!(rect.height);
+                    let (tl, tr, br, bl) = bs.rect.radius_x();
+                    hf64!(tl);
+                    hf64!(tr);
+                    hf64!(br);
+                    hf64!(bl);
+                    let (tl, tr, br, bl) = bs.rect.radius_y();
+                    hf64!(tl);
+                    hf64!(tr);
+                    hf64!(br);
+                    hf64!(bl);
+                    for w in bs.rect.border().widths() {
+                        hf32!(w);
+                    }
+                    hash_shadow!(&bs.shadow);
+                }
+            }
+        }
+    }
+
+    for f in &tile.filters {
+        match f {
+            Filter::Blur(sigma) => {
+                fnv!(&[0u8]);
+                hf32!(*sigma);
+            }
+            Filter::DropShadow(s) => {
+                fnv!(&[1u8]);
+                hash_shadow!(s);
+            }
+            Filter::Grayscale(amount) => {
+                fnv!(&[2u8]);
+                hf32!(*amount);
+            }
+            Filter::Brightness(amount) => {
+                fnv!(&[3u8]);
+                hf32!(*amount);
             }
         }
     }
@@ -341,7 +420,9 @@ pub fn rasterize_sequential(
                     pixels: tex.pixels.clone(),
                     format: tex.format,
                     opacity: tile_list.layer_list.layer_opacity(tile.layer_id),
+                    groups: tile_list.layer_list.layer_groups(tile.layer_id),
                     anchor: tile_list.layer_list.layer_anchor(tile.layer_id),
+                    translate: tile_list.layer_list.layer_translation(tile.layer_id),
                 });
             }
         }
@@ -402,7 +483,9 @@ pub fn rasterize_parallel(
                     pixels: data.clone(),
                     format: tile_format,
                     opacity: tile_list.layer_list.layer_opacity(tile.layer_id),
+                    groups: tile_list.layer_list.layer_groups(tile.layer_id),
                     anchor: tile_list.layer_list.layer_anchor(tile.layer_id),
+                    translate: tile_list.layer_list.layer_translation(tile.layer_id),
                 };
                 return (tile_id, Some(baked), None);
             }
@@ -420,7 +503,9 @@ pub fn rasterize_parallel(
                     pixels: tex.pixels.clone(),
                     format: tex.format,
                     opacity: tile_list.layer_list.layer_opacity(tile.layer_id),
+                    groups: tile_list.layer_list.layer_groups(tile.layer_id),
                     anchor: tile_list.layer_list.layer_anchor(tile.layer_id),
+                    translate: tile_list.layer_list.layer_translation(tile.layer_id),
                 });
 
             let cache_entry = baked.as_ref().map(|b| (key, (b.width, b.height, b.pixels.clone())));
@@ -460,13 +545,14 @@ pub fn cpu_cached_tiles(baked: &[BakedTile]) -> Vec<CachedTile> {
         .iter()
         .filter_map(|t| match &t.pixels {
             TilePixels::Cpu(d) => Some(CachedTile {
-                page_x: t.page_x as f32,
-                page_y: t.page_y as f32,
+                page_x: (t.page_x + t.translate.0) as f32,
+                page_y: (t.page_y + t.translate.1) as f32,
                 width: t.width,
                 height: t.height,
                 data: d.clone(),
                 format: t.format,
                 opacity: t.opacity,
+                groups: Arc::clone(&t.groups),
                 anchor: t.anchor,
                 // Alpha is the 4th byte in both supported formats ([B,G,R,A] / [R,G,B,A]). Scanned
                 // once here (per cache build, not per scroll) so the compositor can fast-path it.
@@ -485,8 +571,8 @@ pub fn collect_placed_gpu_tiles(baked: &[BakedTile]) -> Vec<crate::render::backe
         .filter_map(|t| {
             if let TilePixels::Gpu(id) = t.pixels {
                 Some(crate::render::backend::PlacedGpuTile {
-                    page_x: t.page_x as f32,
-                    page_y: t.page_y as f32,
+                    page_x: (t.page_x + t.translate.0) as f32,
+                    page_y: (t.page_y + t.translate.1) as f32,
                     width: t.width,
                     height: t.height,
                     texture_id: id,
@@ -11,8 +11,18 @@
 //! composite into a [`TileTarget`] region of it, and then either present the `u32` buffer directly
 //! (softbuffer ignores the high byte) or convert it to RGBA8 for a GPU texture via
 //! [`argb_u32_to_rgba8`].
+//!
+//! Tiles of an isolated group (nested opacity, `mix-blend-mode`, `isolation`) are flattened into
+//! a transparent offscreen buffer first, which is then faded and blended into its parent as one
+//! image. Tiles arrive in composite order, so a group's tiles are contiguous and a stack of open
+//! groups is all the state needed. A group's buffer only covers its own tiles, and buffers are
+//! reused from one composite to the next.
+
+use std::cell::RefCell;
 
-use crate::render::backend::{anchored_tile_pos, blend_over_argb_u32, scale_premul_argb_u32, CachedTile};
+use crate::render::backend::{
+    anchored_tile_pos, blend_mode_argb_u32, blend_over_argb_u32, scale_premul_argb_u32, CachedTile, CompositeGroup,
+};
 
 /// A rectangular region of a premultiplied-ARGB (`0xAARRGGBB`) `u32` buffer that tiles composite
 /// into.
@@ -31,62 +41,242 @@ pub struct TileTarget<'a> {
     pub height: usize,
 }
 
+/// How many freed group buffers a thread keeps for the next composite.
+const POOLED_GROUP_BUFFERS: usize = 8;
+
+thread_local! {
+    /// Group buffers of earlier composites on this thread, so nested groups don't allocate and
+    /// free their buffers on every frame.
+    static GROUP_BUFFERS: RefCell<Vec<Vec<u32>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A device-pixel rectangle of a [`TileTarget`] region, relative to its origin.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Bounds {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// An open group: its tiles are flattened into `buf`, which covers `bounds` of the target region.
+struct OpenGroup {
+    group: CompositeGroup,
+    bounds: Bounds,
+    buf: Vec<u32>,
+}
+
+impl OpenGroup {
+    fn target(&mut self) -> TileTarget<'_> {
+        TileTarget {
+            buf: &mut self.buf,
+            stride: self.bounds.width,
+            origin_x: 0,
+            origin_y: 0,
+            width: self.bounds.width,
+            height: self.bounds.height,
+        }
+    }
+}
+
 /// Source-over composite the visible `tiles` into `target`, the CPU counterpart to a GPU backend's
 /// own tile compositing.
 ///
 /// Placement resolves each tile's anchor against the page `scroll` (CSS px) via
 /// [`anchored_tile_pos`], then scales to device pixels by `dpr`. Tiles are premultiplied; per-tile
-/// `opacity` fades the layer as a whole before the blend.
+/// `opacity` fades the layer as a whole before the blend. Tiles with [`CachedTile::groups`] are
+/// composited through those groups instead.
 pub fn composite_tiles(tiles: &[CachedTile], dpr: u32, scroll: (f32, f32), target: &mut TileTarget<'_>) {
+    if tiles.iter().all(|t| t.groups.is_empty()) {
+        for tile in tiles {
+            blit_tile(tile, tile.opacity, dpr, scroll, (0, 0), target);
+        }
+        return;
+    }
+
+    let region = (target.width, target.height);
+    // Open groups, outermost first, each with a transparent buffer over its tiles.
+    let mut stack: Vec<OpenGroup> = Vec::new();
+    for (index, tile) in tiles.iter().enumerate() {
+        let shared = stack
+            .iter()
+            .zip(tile.groups.iter())
+            .take_while(|(open, g)| open.group.id == g.id)
+            .count();
+        while stack.len() > shared {
+            close_group(&mut stack, target);
+        }
+        for (depth, group) in tile.groups.iter().enumerate().skip(shared) {
+            let bounds = group_bounds(&tiles[index..], depth, group.id, dpr, scroll, region);
+            stack.push(OpenGroup {
+                group: *group,
+                bounds,
+                buf: take_buffer(bounds.width * bounds.height),
+            });
+        }
+        match stack.last_mut() {
+            Some(open) => {
+                let offset = (open.bounds.x as i64, open.bounds.y as i64);
+                blit_tile(tile, 1.0, dpr, scroll, offset, &mut open.target());
+            }
+            None => blit_tile(tile, tile.opacity, dpr, scroll, (0, 0), target),
+        }
+    }
+    while !stack.is_empty() {
+        close_group(&mut stack, target);
+    }
+}
+
+/// A zeroed buffer of `len` pixels, reusing a pooled allocation when there is one.
+fn take_buffer(len: usize) -> Vec<u32> {
+    let mut buf = GROUP_BUFFERS.with(|pool| pool.borrow_mut().pop()).unwrap_or_default();
+    buf.clear();
+    buf.resize(len, 0);
+    buf
+}
+
+/// Hands a group buffer back to the pool.
+fn recycle_buffer(buf: Vec<u32>) {
+    GROUP_BUFFERS.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < POOLED_GROUP_BUFFERS {
+            pool.push(buf);
+        }
+    });
+}
+
+/// The device-pixel area of the target `region` covered by the group `id` at `depth`, whose
+/// tiles are the leading run of `tiles` in it.
+fn group_bounds(
+    tiles: &[CachedTile],
+    depth: usize,
+    id: u64,
+    dpr: u32,
+    scroll: (f32, f32),
+    region: (usize, usize),
+) -> Bounds {
+    let (mut x0, mut y0, mut x1, mut y1) = (i64::MAX, i64::MAX, 0, 0);
+    for tile in tiles
+        .iter()
+        .take_while(|t| t.groups.get(depth).is_some_and(|g| g.id == id))
+    {
+        let (px, py) = tile_origin(tile, dpr, scroll);
+        let left = px.max(0);
+        let top = py.max(0);
+        let right = (px + tile.width as i64).min(region.0 as i64);
+        let bottom = (py + tile.height as i64).min(region.1 as i64);
+        if left < right && top < bottom {
+            x0 = x0.min(left);
+            y0 = y0.min(top);
+            x1 = x1.max(right);
+            y1 = y1.max(bottom);
+        }
+    }
+    if x0 >= x1 || y0 >= y1 {
+        return Bounds::default();
+    }
+    Bounds {
+        x: x0 as usize,
+        y: y0 as usize,
+        width: (x1 - x0) as usize,
+        height: (y1 - y0) as usize,
+    }
+}
+
+/// Pops the innermost open group, fades/blends it into the next one out (or `target`) and
+/// recycles its buffer.
+fn close_group(stack: &mut Vec<OpenGroup>, target: &mut TileTarget<'_>) {
+    let Some(open) = stack.pop() else {
+        return;
+    };
+    match stack.last_mut() {
+        // A nested group's tiles are some of its parent's, so it lies inside the parent's bounds.
+        Some(parent) => {
+            let offset = (open.bounds.x - parent.bounds.x, open.bounds.y - parent.bounds.y);
+            blend_group(&open, offset, &mut parent.target());
+        }
+        None => blend_group(&open, (open.bounds.x, open.bounds.y), target),
+    }
+    recycle_buffer(open.buf);
+}
+
+/// Fades a flattened group by its opacity and blends it into `target` with its blend mode, with
+/// the group's buffer placed at `offset` in the target region.
+fn blend_group(open: &OpenGroup, offset: (usize, usize), target: &mut TileTarget<'_>) {
+    let w = open.bounds.width;
+    for y in 0..open.bounds.height {
+        let dst_row = (target.origin_y + offset.1 + y) * target.stride + target.origin_x + offset.0;
+        for (x, &px) in open.buf[y * w..(y + 1) * w].iter().enumerate() {
+            if px >> 24 == 0 {
+                continue;
+            }
+            let faded = scale_premul_argb_u32(px, open.group.opacity);
+            target.buf[dst_row + x] = blend_mode_argb_u32(faded, target.buf[dst_row + x], open.group.blend_mode);
+        }
+    }
+}
+
+/// Where `tile` lands in the target region, in device pixels: its anchor resolved against the
+/// engine's authoritative `scroll` (CSS px), then scaled by `dpr`.
+fn tile_origin(tile: &CachedTile, dpr: u32, scroll: (f32, f32)) -> (i64, i64) {
     let dpr_f = dpr as f64;
-    let (scroll_x, scroll_y) = scroll;
+    let (vx, vy) = anchored_tile_pos(
+        tile.page_x as f64,
+        tile.page_y as f64,
+        scroll.0 as f64,
+        scroll.1 as f64,
+        tile.anchor,
+    );
+    ((vx * dpr_f).round() as i64, (vy * dpr_f).round() as i64)
+}
+
+/// Source-over one tile into `target`, faded by `opacity`. `target` starts at `offset` of the
+/// composite's target region (a group buffer's bounds), so the tile moves up/left by it.
+fn blit_tile(
+    tile: &CachedTile,
+    opacity: f32,
+    dpr: u32,
+    scroll: (f32, f32),
+    offset: (i64, i64),
+    target: &mut TileTarget<'_>,
+) {
     let clip_w = target.width as i64;
     let clip_h = target.height as i64;
 
-    for tile in tiles {
-        // Viewport position in CSS px from the engine's authoritative scroll, then device px.
-        let (vx, vy) = anchored_tile_pos(
-            tile.page_x as f64,
-            tile.page_y as f64,
-            scroll_x as f64,
-            scroll_y as f64,
-            tile.anchor,
-        );
-        let px = (vx * dpr_f).round() as i64;
-        let py = (vy * dpr_f).round() as i64;
-        let tw = tile.width as i64;
-        let th = tile.height as i64;
+    let (px, py) = tile_origin(tile, dpr, scroll);
+    let (px, py) = (px - offset.0, py - offset.1);
+    let tw = tile.width as i64;
+    let th = tile.height as i64;
 
-        if px >= clip_w || py >= clip_h || px + tw <= 0 || py + th <= 0 {
-            continue;
-        }
+    if px >= clip_w || py >= clip_h || px + tw <= 0 || py + th <= 0 {
+        return;
+    }
 
-        // Leading tile columns/rows that fall off the top/left edge.
-        let col0 = (-px).max(0) as usize;
-        let row0 = (-py).max(0) as usize;
-        let dst_x = px.max(0) as usize;
-        let dst_y0 = py.max(0) as usize;
-        let tw = tw as usize;
-        let th = th as usize;
+    // Leading tile columns/rows that fall off the top/left edge.
+    let col0 = (-px).max(0) as usize;
+    let row0 = (-py).max(0) as usize;
+    let dst_x = px.max(0) as usize;
+    let dst_y0 = py.max(0) as usize;
+    let tw = tw as usize;
+    let th = th as usize;
 
-        let src_u32 = bytemuck::cast_slice::<u8, u32>(&tile.data);
+    let src_u32 = bytemuck::cast_slice::<u8, u32>(&tile.data);
 
-        for tile_row in row0..th {
-            let dst_y = dst_y0 + (tile_row - row0);
-            if dst_y >= target.height {
-                break;
-            }
-            let copy_w = (tw - col0).min(target.width - dst_x);
-            if copy_w == 0 {
-                break;
-            }
-            let buf_row = (target.origin_y + dst_y) * target.stride + target.origin_x + dst_x;
-            let src_row = tile_row * tw + col0;
-            for col in 0..copy_w {
-                let src_argb = tile.format.pixel_to_argb_u32(src_u32[src_row + col]);
-                target.buf[buf_row + col] =
-                    blend_over_argb_u32(scale_premul_argb_u32(src_argb, tile.opacity), target.buf[buf_row + col]);
-            }
+    for tile_row in row0..th {
+        let dst_y = dst_y0 + (tile_row - row0);
+        if dst_y >= target.height {
+            break;
+        }
+        let copy_w = (tw - col0).min(target.width - dst_x);
+        if copy_w == 0 {
+            break;
+        }
+        let buf_row = (target.origin_y + dst_y) * target.stride + target.origin_x + dst_x;
+        let src_row = tile_row * tw + col0;
+        for col in 0..copy_w {
+            let src_argb = tile.format.pixel_to_argb_u32(src_u32[src_row + col]);
+            target.buf[buf_row + col] =
+                blend_over_argb_u32(scale_premul_argb_u32(src_argb, opacity), target.buf[buf_row + col]);
         }
     }
 }
@@ -111,8 +301,9 @@ pub fn argb_u32_to_rgba8(buf: &[u32]) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::render::backend::{PixelFormat, TileAnchor};
+    use crate::render::backend::{BlendMode, PixelFormat, TileAnchor};
     use bytes::Bytes;
+    use std::sync::Arc;
 
     const WHITE: u32 = 0xFFFF_FFFF;
 
@@ -126,11 +317,20 @@ mod tests {
             data: Bytes::copy_from_slice(&rgba),
             format: PixelFormat::Rgba8,
             opacity: 1.0,
+            groups: Arc::from([]),
             anchor: TileAnchor::Scroll,
             opaque: rgba[3] == 255,
         }
     }
 
+    fn group(id: u64, opacity: f32, blend_mode: BlendMode) -> CompositeGroup {
+        CompositeGroup {
+            id,
+            opacity,
+            blend_mode,
+        }
+    }
+
     fn target_2x2(buf: &mut [u32]) -> TileTarget<'_> {
         TileTarget {
             buf,
@@ -200,6 +400,75 @@ mod tests {
         assert_eq!(buf[2], 0xFF00_00FF, "tile lands in the offset region (row 1) as blue");
     }
 
+    #[test]
+    fn nested_opacity_groups_multiply() {
+        // Black inside two nested 50% groups covers a quarter of the white page.
+        let mut tile = tile_rgba(0.0, 0.0, [0, 0, 0, 255]);
+        tile.groups = Arc::from([group(1, 0.5, BlendMode::Normal), group(2, 0.5, BlendMode::Normal)]);
+        let mut buf = [WHITE; 4];
+        composite_tiles(&[tile], 1, (0.0, 0.0), &mut target_2x2(&mut buf));
+        assert_eq!(buf[0], 0xFFBF_BFBF, "0.25 black over white");
+        assert_eq!(buf[1], WHITE, "transparent group pixels leave the page alone");
+    }
+
+    #[test]
+    fn group_fades_overlapping_tiles_as_one() {
+        // Two overlapping opaque tiles in one 50% group: the top one hides the bottom one
+        // before the fade, so the result is half red, not red-over-half-blue.
+        let mut blue = tile_rgba(0.0, 0.0, [0, 0, 255, 255]);
+        let mut red = tile_rgba(0.0, 0.0, [255, 0, 0, 255]);
+        blue.groups = Arc::from([group(1, 0.5, BlendMode::Normal)]);
+        red.groups = Arc::clone(&blue.groups);
+        let mut buf = [WHITE; 4];
+        composite_tiles(&[blue, red], 1, (0.0, 0.0), &mut target_2x2(&mut buf));
+        assert_eq!(buf[0], 0xFFFF_7F7F);
+    }
+
+    #[test]
+    fn blend_group_mixes_with_backdrop() {
+        // A grey page tile, then a multiply group: multiply grey by grey.
+        let page = tile_rgba(0.0, 0.0, [128, 128, 128, 255]);
+        let mut top = tile_rgba(0.0, 0.0, [128, 128, 128, 255]);
+        top.groups = Arc::from([group(1, 1.0, BlendMode::Multiply)]);
+        let mut buf = [WHITE; 4];
+        composite_tiles(&[page, top], 1, (0.0, 0.0), &mut target_2x2(&mut buf));
+        assert_eq!(buf[0] >> 24, 0xFF);
+        let r = (buf[0] >> 16) & 0xFF;
+        assert!((63..=65).contains(&r), "128 * 128 / 255 = 64, got {r}");
+    }
+
+    #[test]
+    fn group_buffer_covers_only_its_tiles() {
+        let mut inside = tile_rgba(1.0, 1.0, [0, 0, 0, 255]);
+        inside.groups = Arc::from([group(1, 0.5, BlendMode::Normal)]);
+        let outside = tile_rgba(0.0, 0.0, [255, 0, 0, 255]);
+        let tiles = [inside, outside];
+        assert_eq!(
+            group_bounds(&tiles, 0, 1, 1, (0.0, 0.0), (2, 2)),
+            Bounds {
+                x: 1,
+                y: 1,
+                width: 1,
+                height: 1
+            }
+        );
+        // Offscreen tiles give an empty buffer.
+        assert_eq!(group_bounds(&tiles, 0, 1, 1, (0.0, 10.0), (2, 2)), Bounds::default());
+    }
+
+    #[test]
+    fn offset_group_lands_on_its_tiles() {
+        let mut tile = tile_rgba(1.0, 1.0, [0, 0, 0, 255]);
+        tile.groups = Arc::from([group(1, 0.5, BlendMode::Normal), group(2, 0.5, BlendMode::Normal)]);
+        // The second composite reuses the first one's buffers, which must start transparent.
+        for _ in 0..2 {
+            let mut buf = [WHITE; 4];
+            composite_tiles(&[tile.clone()], 1, (0.0, 0.0), &mut target_2x2(&mut buf));
+            assert_eq!(buf[3], 0xFFBF_BFBF, "0.25 black over white at (1, 1)");
+            assert_eq!(&buf[..3], &[WHITE; 3]);
+        }
+    }
+
     #[test]
     fn argb_to_rgba8_channel_order() {
         // 0xAARRGGBB red → [R,G,B,255].
@@ -98,6 +98,11 @@ impl RenderTree {
 
 const INVISIBLE_ELEMENTS: [&str; 6] = ["head", "style", "script", "meta", "link", "title"];
 
+/// Elements whose DOM children never render: an iframe shows its nested document instead, and
+/// video and audio their media (their children are only fallback content for user agents without
+/// support, and `<source>`/`<track>` elements).
+const CHILDLESS_ELEMENTS: [&str; 3] = ["iframe", "video", "audio"];
+
 impl RenderTree {
     /// Dump each element's computed CSS to JSON: an array sorted by node_id, of
     /// `{"node_id": 5, "tag": "p", "id": "", "class": "foo", "styles": {"color": "red", ...}}`.
@@ -206,7 +211,10 @@ impl RenderTree {
                         results.push(None);
                         continue;
                     }
-                    let children = self.doc.children(node_id);
+                    let children = match self.doc.tag_name(node_id) {
+                        Some(tag) if CHILDLESS_ELEMENTS.contains(&tag.as_str()) => Vec::new(),
+                        _ => self.doc.children(node_id),
+                    };
                     let num_children = children.len();
                     stack.push(Frame::Collect { node_id, num_children });
                     for child_id in children.into_iter().rev() {
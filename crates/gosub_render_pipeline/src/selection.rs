@@ -0,0 +1,509 @@
+//! Text selection: hit-testing a point down to a byte offset in a text element's shaped glyphs,
+//! word and paragraph expansion for double and triple clicks, and the per-element byte ranges a
+//! selection covers, which the painter highlights and the host copies.
+//!
+//! Offsets are bytes into [`ElementContextText::text`] - the text as laid out, after whitespace
+//! collapsing, `text-transform` and hyphenation - so they map straight onto glyph clusters.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use gosub_interface::font_system::{FontSystem, GlyphRotation, ShapedText};
+use parking_lot::Mutex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::common::geo::Rect;
+use crate::layering::layer::LayerList;
+use crate::layouter::taffy::block_container;
+use crate::layouter::{ElementContext, ElementContextText, LayoutElementId, LayoutTree};
+use crate::painter::shape_text_element;
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const NBSP: char = '\u{00A0}';
+
+/// A caret position: a byte offset into a text element's laid-out text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextPosition {
+    pub element: LayoutElementId,
+    pub offset: usize,
+}
+
+/// The byte range each selected text element contributes, in document order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextSelection {
+    order: Vec<LayoutElementId>,
+    ranges: HashMap<LayoutElementId, Range<usize>>,
+}
+
+impl TextSelection {
+    /// Everything between `anchor` and `focus`, whichever comes first in document order. Empty
+    /// when either position is no longer in `layout_tree` or both are the same caret.
+    pub fn between(layout_tree: &LayoutTree, anchor: TextPosition, focus: TextPosition) -> Self {
+        let elements = text_elements(layout_tree);
+        let index = |pos: TextPosition| elements.iter().position(|(id, _)| *id == pos.element);
+        let (Some(a), Some(f)) = (index(anchor), index(focus)) else {
+            return Self::default();
+        };
+        let ((first, start), (last, end)) = if (a, anchor.offset) <= (f, focus.offset) {
+            ((a, anchor.offset), (f, focus.offset))
+        } else {
+            ((f, focus.offset), (a, anchor.offset))
+        };
+
+        let mut selection = Self::default();
+        for (i, (id, ctx)) in elements.iter().enumerate().take(last + 1).skip(first) {
+            let from = if i == first {
+                floor_char_boundary(&ctx.text, start)
+            } else {
+                0
+            };
+            let to = if i == last {
+                floor_char_boundary(&ctx.text, end)
+            } else {
+                ctx.text.len()
+            };
+            if from < to {
+                selection.order.push(*id);
+                selection.ranges.insert(*id, from..to);
+            }
+        }
+        selection
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// The selected bytes of `element`'s text, if any.
+    pub fn range(&self, element: LayoutElementId) -> Option<Range<usize>> {
+        self.ranges.get(&element).cloned()
+    }
+
+    /// The text elements with a selected range, in document order.
+    pub fn elements(&self) -> &[LayoutElementId] {
+        &self.order
+    }
+
+    /// The selected text as a host would put it on the clipboard: non-breaking spaces become
+    /// spaces, soft hyphens are dropped and text in different blocks is separated by newlines.
+    pub fn text(&self, layout_tree: &LayoutTree) -> String {
+        let doc = &layout_tree.render_tree.doc;
+        let mut out = String::new();
+        let mut last_block = None;
+        for id in &self.order {
+            let Some((ctx, range)) = text_context(layout_tree, *id).zip(self.ranges.get(id)) else {
+                continue;
+            };
+            let block = block_container(&**doc, ctx.node_id);
+            if last_block.is_some_and(|last| last != block) {
+                out.push('\n');
+            }
+            last_block = Some(block);
+            let Some(text) = ctx.text.get(range.clone()) else {
+                continue;
+            };
+            out.extend(
+                text.chars()
+                    .filter(|c| *c != SOFT_HYPHEN)
+                    .map(|c| if c == NBSP { ' ' } else { c }),
+            );
+        }
+        out
+    }
+}
+
+/// Every text element in document order, with its text context.
+pub fn text_elements(layout_tree: &LayoutTree) -> Vec<(LayoutElementId, &ElementContextText)> {
+    let mut out = Vec::new();
+    let mut stack = vec![layout_tree.root_id];
+    while let Some(id) = stack.pop() {
+        let Some(node) = layout_tree.get_node_by_id(id) else {
+            continue;
+        };
+        if let ElementContext::Text(ctx) = &node.context {
+            out.push((id, ctx));
+        }
+        stack.extend(node.children.iter().rev());
+    }
+    out
+}
+
+fn text_context(layout_tree: &LayoutTree, id: LayoutElementId) -> Option<&ElementContextText> {
+    match &layout_tree.get_node_by_id(id)?.context {
+        ElementContext::Text(ctx) => Some(ctx),
+        _ => None,
+    }
+}
+
+/// The caret position under viewport point `(vp_x, vp_y)`. On a text element that is the glyph
+/// edge nearest the point; on any other element, the nearest spot in the text inside it. `None`
+/// when there is no text there.
+pub fn hit_test(
+    layer_list: &LayerList,
+    font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+    vp_x: f64,
+    vp_y: f64,
+    scroll_x: f64,
+    scroll_y: f64,
+) -> Option<TextPosition> {
+    let layout_tree = &layer_list.layout_tree;
+    let (hit, x, y) = layer_list.find_element_at_page(vp_x, vp_y, scroll_x, scroll_y)?;
+
+    // Off the text itself (padding, a gap between lines, past the last line), use the text in
+    // the hit element closest to the point: on its line first, then along it.
+    let element = match layout_tree.get_node_by_id(hit)?.context {
+        ElementContext::Text(_) => hit,
+        _ => {
+            let mut candidates = Vec::new();
+            let mut stack = vec![hit];
+            while let Some(id) = stack.pop() {
+                let Some(node) = layout_tree.get_node_by_id(id) else {
+                    continue;
+                };
+                if matches!(node.context, ElementContext::Text(_)) {
+                    candidates.push(id);
+                }
+                stack.extend(node.children.iter().copied());
+            }
+            candidates.into_iter().min_by(|a, b| {
+                let distance = |id| {
+                    let r = layout_tree
+                        .get_node_by_id(id)
+                        .map_or(Rect::ZERO, |n| n.box_model.content_box);
+                    let dx = (r.x - x).max(x - (r.x + r.width)).max(0.0);
+                    let dy = (r.y - y).max(y - (r.y + r.height)).max(0.0);
+                    (dy, dx)
+                };
+                distance(*a)
+                    .partial_cmp(&distance(*b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })?
+        }
+    };
+
+    let offset = text_offset_at(layout_tree, font_system, element, x, y)?;
+    Some(TextPosition { element, offset })
+}
+
+/// The byte offset in text element `element` nearest the page-space point `(x, y)`. `None` when
+/// `element` is not a text element.
+pub(crate) fn text_offset_at(
+    layout_tree: &LayoutTree,
+    font_system: Option<&Arc<Mutex<dyn FontSystem>>>,
+    element: LayoutElementId,
+    x: f64,
+    y: f64,
+) -> Option<usize> {
+    let node = layout_tree.get_node_by_id(element)?;
+    let ElementContext::Text(ctx) = &node.context else {
+        return None;
+    };
+    let content_box = node.box_model.content_box;
+    // Vertical text has no glyph cells yet; it selects whole, split at the middle of the line.
+    if ctx.font_info.shaping.writing_mode.is_vertical() {
+        let offset = if y < content_box.y + content_box.height / 2.0 {
+            0
+        } else {
+            ctx.text.len()
+        };
+        return Some(offset);
+    }
+
+    let (shaped, _) = shape_text_element(font_system, ctx, content_box);
+    Some(offset_at(
+        &shaped,
+        ctx.text.len(),
+        (x - content_box.x) as f32,
+        (y - content_box.y) as f32,
+    ))
+}
+
+/// The word (or run of spaces or punctuation) around `pos`, as an anchor and a focus.
+pub fn word_at(layout_tree: &LayoutTree, pos: TextPosition) -> (TextPosition, TextPosition) {
+    let Some(ctx) = text_context(layout_tree, pos.element) else {
+        return (pos, pos);
+    };
+    let mut words = ctx
+        .text
+        .split_word_bound_indices()
+        .map(|(start, word)| start..start + word.len());
+    // A caret at a boundary belongs to the word after it, except at the very end of the text.
+    let word = words.find(|word| pos.offset < word.end).or_else(|| {
+        ctx.text
+            .split_word_bound_indices()
+            .next_back()
+            .map(|(start, word)| start..start + word.len())
+    });
+    match word {
+        Some(word) => (
+            TextPosition {
+                element: pos.element,
+                offset: word.start,
+            },
+            TextPosition {
+                element: pos.element,
+                offset: word.end,
+            },
+        ),
+        None => (pos, pos),
+    }
+}
+
+/// The paragraph around `pos`: the consecutive text elements set in the same block container,
+/// as an anchor and a focus.
+pub fn paragraph_at(layout_tree: &LayoutTree, pos: TextPosition) -> (TextPosition, TextPosition) {
+    let doc = &layout_tree.render_tree.doc;
+    let elements = text_elements(layout_tree);
+    let Some(index) = elements.iter().position(|(id, _)| *id == pos.element) else {
+        return (pos, pos);
+    };
+    let block = block_container(&**doc, elements[index].1.node_id);
+    let same_block = |i: &usize| block_container(&**doc, elements[*i].1.node_id) == block;
+    let first = (0..index).rev().take_while(same_block).last().unwrap_or(index);
+    let last = (index + 1..elements.len())
+        .take_while(same_block)
+        .last()
+        .unwrap_or(index);
+    (
+        TextPosition {
+            element: elements[first].0,
+            offset: 0,
+        },
+        TextPosition {
+            element: elements[last].0,
+            offset: elements[last].1.text.len(),
+        },
+    )
+}
+
+/// One glyph's box on its line, relative to the shaped block, and the text it was shaped from.
+#[derive(Debug, Clone, Copy)]
+struct GlyphCell {
+    left: f32,
+    right: f32,
+    top: f32,
+    bottom: f32,
+    start: usize,
+    end: usize,
+    /// The glyph sits in a right-to-left run, so its text starts at its right edge.
+    rtl: bool,
+}
+
+/// The cell of every glyph in an unrotated run, sorted by line and then left to right. A glyph
+/// spans to the next glyph on its run or the run's end; its text to the next cluster in the
+/// string.
+fn glyph_cells(shaped: &ShapedText, text_len: usize) -> Vec<GlyphCell> {
+    let mut clusters: Vec<usize> = shaped
+        .runs
+        .iter()
+        .flat_map(|run| run.glyphs.iter().map(|g| g.cluster as usize))
+        .collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    let cluster_end = |start: usize| {
+        let next = clusters.partition_point(|c| *c <= start);
+        clusters.get(next).copied().unwrap_or(text_len).min(text_len)
+    };
+    let line_height = if shaped.line_height > 0.0 {
+        shaped.line_height
+    } else {
+        shaped.height
+    };
+
+    let mut cells = Vec::new();
+    for run in shaped.runs.iter().filter(|run| run.rotation == GlyphRotation::None) {
+        let mut glyphs: Vec<_> = run.glyphs.iter().collect();
+        glyphs.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let rtl = match (glyphs.first(), glyphs.last()) {
+            (Some(first), Some(last)) => first.cluster > last.cluster,
+            _ => false,
+        };
+        let top = run.baseline - shaped.ascent;
+        for (i, g) in glyphs.iter().enumerate() {
+            let start = (g.cluster as usize).min(text_len);
+            cells.push(GlyphCell {
+                left: g.x,
+                right: glyphs.get(i + 1).map_or(run.x + run.width, |next| next.x).max(g.x),
+                top,
+                bottom: top + line_height,
+                start,
+                end: cluster_end(start),
+                rtl,
+            });
+        }
+    }
+    cells.sort_by(|a, b| a.top.total_cmp(&b.top).then(a.left.total_cmp(&b.left)));
+    cells
+}
+
+/// The caret offset nearest `(x, y)` (relative to the shaped block): the line closest to `y`,
+/// then the glyph edge closest to `x` on it.
+fn offset_at(shaped: &ShapedText, text_len: usize, x: f32, y: f32) -> usize {
+    let cells = glyph_cells(shaped, text_len);
+    let line_distance = |cell: &GlyphCell| (cell.top - y).max(y - cell.bottom).max(0.0);
+    let Some(line) = cells
+        .iter()
+        .min_by(|a, b| line_distance(a).total_cmp(&line_distance(b)))
+    else {
+        return 0;
+    };
+    let glyph_distance = |cell: &GlyphCell| (cell.left - x).max(x - cell.right).max(0.0);
+    let Some(cell) = cells
+        .iter()
+        .filter(|cell| cell.top == line.top)
+        .min_by(|a, b| glyph_distance(a).total_cmp(&glyph_distance(b)))
+    else {
+        return 0;
+    };
+    let before_middle = x < (cell.left + cell.right) / 2.0;
+    if before_middle != cell.rtl {
+        cell.start
+    } else {
+        cell.end
+    }
+}
+
+/// Boxes behind the glyphs whose text starts inside `range`, relative to the shaped block. Each
+/// spans the full line height, with neighbouring glyphs on a line merged into one box.
+pub fn highlight_rects(shaped: &ShapedText, text_len: usize, range: &Range<usize>) -> Vec<Rect> {
+    let mut rects: Vec<Rect> = Vec::new();
+    for cell in glyph_cells(shaped, text_len) {
+        if !range.contains(&cell.start) {
+            continue;
+        }
+        let (left, top) = (cell.left as f64, cell.top as f64);
+        if let Some(last) = rects.last_mut() {
+            if last.y == top && (last.x + last.width - left).abs() < 0.5 {
+                last.width = (cell.right as f64 - last.x).max(last.width);
+                continue;
+            }
+        }
+        rects.push(Rect::new(
+            left,
+            top,
+            (cell.right - cell.left) as f64,
+            (cell.bottom - cell.top) as f64,
+        ));
+    }
+    rects
+}
+
+/// `shaped` split into its unselected and selected glyphs, every run kept in both so decorations
+/// still span the whole run.
+pub fn split_glyphs(shaped: &ShapedText, range: &Range<usize>) -> (ShapedText, ShapedText) {
+    let mut unselected = shaped.clone();
+    let mut selected = shaped.clone();
+    for run in &mut unselected.runs {
+        run.glyphs.retain(|g| !range.contains(&(g.cluster as usize)));
+    }
+    for run in &mut selected.runs {
+        run.glyphs.retain(|g| range.contains(&(g.cluster as usize)));
+    }
+    (unselected, selected)
+}
+
+/// `offset` moved back to the nearest char boundary in `text`, clamped to its length.
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gosub_interface::font::{FontBlob, FontStyle};
+    use gosub_interface::font_system::{FontStretch, FontWeight, ResolvedFont, RunMetrics, ShapedGlyph, ShapedRun};
+
+    /// `text` as one 10px-per-byte line per entry of `lines`, each a `(start, end)` byte range.
+    fn shaped(lines: &[(usize, usize)], rtl: bool) -> ShapedText {
+        let runs = lines
+            .iter()
+            .enumerate()
+            .map(|(line, &(start, end))| {
+                let width = (end - start) as f32 * 10.0;
+                let glyphs = (start..end)
+                    .map(|cluster| {
+                        let i = (cluster - start) as f32;
+                        let x = if rtl { width - (i + 1.0) * 10.0 } else { i * 10.0 };
+                        ShapedGlyph {
+                            id: 1,
+                            x,
+                            y: 16.0 + line as f32 * 20.0,
+                            cluster: cluster as u32,
+                        }
+                    })
+                    .collect();
+                ShapedRun {
+                    font: ResolvedFont {
+                        family: "test".to_string(),
+                        style: FontStyle::Normal,
+                        weight: FontWeight::NORMAL,
+                        stretch: FontStretch::NORMAL,
+                        blob: FontBlob::new(Arc::new(Vec::<u8>::new()), 0),
+                    },
+                    font_size: 16.0,
+                    x: 0.0,
+                    baseline: 16.0 + line as f32 * 20.0,
+                    width,
+                    metrics: RunMetrics::default(),
+                    glyphs,
+                    rotation: GlyphRotation::None,
+                }
+            })
+            .collect();
+        ShapedText {
+            runs,
+            width: 100.0,
+            height: lines.len() as f32 * 20.0,
+            line_height: 20.0,
+            ascent: 16.0,
+        }
+    }
+
+    #[test]
+    fn offsets_snap_to_the_nearest_glyph_edge() {
+        let shaped = shaped(&[(0, 6), (6, 10)], false);
+        assert_eq!(offset_at(&shaped, 10, 12.0, 5.0), 1);
+        assert_eq!(offset_at(&shaped, 10, 17.0, 5.0), 2);
+        // The second line, past its end and below the block.
+        assert_eq!(offset_at(&shaped, 10, 95.0, 25.0), 10);
+        assert_eq!(offset_at(&shaped, 10, 0.0, 200.0), 6);
+        assert_eq!(offset_at(&ShapedText::empty(), 0, 5.0, 5.0), 0);
+    }
+
+    #[test]
+    fn right_to_left_offsets_start_at_the_right_edge() {
+        let shaped = shaped(&[(0, 4)], true);
+        // Glyph 0 covers 30..40: its start is on the right.
+        assert_eq!(offset_at(&shaped, 4, 38.0, 5.0), 0);
+        assert_eq!(offset_at(&shaped, 4, 32.0, 5.0), 1);
+        assert_eq!(offset_at(&shaped, 4, 1.0, 5.0), 4);
+    }
+
+    #[test]
+    fn highlights_merge_per_line() {
+        let shaped = shaped(&[(0, 6), (6, 10)], false);
+        let rects = highlight_rects(&shaped, 10, &(2..8));
+        assert_eq!(rects.len(), 2);
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].width, rects[0].height),
+            (20.0, 0.0, 40.0, 20.0)
+        );
+        assert_eq!((rects[1].x, rects[1].y, rects[1].width), (0.0, 20.0, 20.0));
+
+        let (unselected, selected) = split_glyphs(&shaped, &(2..8));
+        let count = |s: &ShapedText| s.runs.iter().map(|r| r.glyphs.len()).sum::<usize>();
+        assert_eq!((count(&unselected), count(&selected)), (4, 6));
+        assert_eq!(selected.runs.len(), 2);
+    }
+
+    #[test]
+    fn char_boundaries_round_down() {
+        assert_eq!(floor_char_boundary("héllo", 2), 1);
+        assert_eq!(floor_char_boundary("héllo", 99), 6);
+    }
+}
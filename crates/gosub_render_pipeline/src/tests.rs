@@ -110,6 +110,58 @@ mod rendertree_from_engine {
         }
     }
 
+    #[test]
+    fn iframe_fallback_content_is_excluded() {
+        let html = r#"
+            <html>
+            <body><iframe src="inner.html">Your browser does not support frames</iframe></body>
+            </html>
+        "#;
+
+        let rt = parse_to_rendertree(html);
+        let doc_ref = rt.doc.clone();
+
+        let mut iframes = 0;
+        for render_id in rt.arena.keys() {
+            let node_id = gosub_shared::node::NodeId::from(*render_id);
+            if doc_ref.tag_name(node_id).as_deref() == Some("iframe") {
+                iframes += 1;
+                assert!(
+                    rt.arena[render_id].children.is_empty(),
+                    "an iframe's DOM children must not render"
+                );
+            }
+        }
+        assert_eq!(iframes, 1, "the iframe itself renders");
+    }
+
+    #[test]
+    fn video_sources_and_fallback_content_are_excluded() {
+        let html = r#"
+            <html>
+            <body><video controls><source src="clip.y4m">Your browser does not support video</video></body>
+            </html>
+        "#;
+
+        let rt = parse_to_rendertree(html);
+        let doc_ref = rt.doc.clone();
+
+        let mut videos = 0;
+        for render_id in rt.arena.keys() {
+            let node_id = gosub_shared::node::NodeId::from(*render_id);
+            let tag = doc_ref.tag_name(node_id);
+            assert_ne!(tag.as_deref(), Some("source"), "a video's <source> must not render");
+            if tag.as_deref() == Some("video") {
+                videos += 1;
+                assert!(
+                    rt.arena[render_id].children.is_empty(),
+                    "a video's DOM children must not render"
+                );
+            }
+        }
+        assert_eq!(videos, 1, "the video itself renders");
+    }
+
     #[test]
     fn css_dimensions_are_extracted() {
         let html = r#"
@@ -132,8 +184,9 @@ mod rendertree_from_engine {
 
         let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
 
-        let root = adapter.doc.root();
-        let box_node_id = find_node_by_id_attr(&adapter.doc, root, "box");
+        let doc = adapter.document();
+        let root = doc.root();
+        let box_node_id = find_node_by_id_attr(&doc, root, "box");
         assert!(box_node_id.is_some(), "should find #box element");
 
         let id = box_node_id.unwrap();
@@ -172,8 +225,9 @@ mod rendertree_from_engine {
         doc.add_stylesheet(ua);
         let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
 
-        let root = adapter.doc.root();
-        let m = find_node_by_class_dfs(&adapter.doc, root, "m").expect("find .m");
+        let doc = adapter.document();
+        let root = doc.root();
+        let m = find_node_by_class_dfs(&doc, root, "m").expect("find .m");
 
         // On the element itself: 0.14em * 20px = 2.8px.
         let ls = adapter.get_style(m, &StyleProperty::LetterSpacing);
@@ -218,8 +272,9 @@ mod rendertree_from_engine {
         doc.add_stylesheet(ua);
         let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
 
-        let root = adapter.doc.root();
-        let p = find_node_by_class_dfs(&adapter.doc, root, "zone-intro").expect("find p");
+        let doc = adapter.document();
+        let root = doc.root();
+        let p = find_node_by_class_dfs(&doc, root, "zone-intro").expect("find p");
         let text_child = adapter
             .children(p)
             .into_iter()
@@ -248,7 +303,7 @@ mod rendertree_from_engine {
 
         let html = r#"
             <html>
-            <head><style>.wreck { mix-blend-mode: multiply; }</style></head>
+            <head><style>.wreck { mix-blend-mode: multiply; isolation: isolate; }</style></head>
             <body><img class="wreck" src="x.png"></body>
             </html>
         "#;
@@ -258,8 +313,9 @@ mod rendertree_from_engine {
         doc.add_stylesheet(ua);
         let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
 
-        let root = adapter.doc.root();
-        let img = find_node_by_class_dfs(&adapter.doc, root, "wreck").expect("find img");
+        let doc = adapter.document();
+        let root = doc.root();
+        let img = find_node_by_class_dfs(&doc, root, "wreck").expect("find img");
 
         let v = adapter.get_style(img, &StyleProperty::MixBlendMode);
         let kw = match v {
@@ -269,6 +325,12 @@ mod rendertree_from_engine {
         assert_eq!(kw, "multiply");
         assert_eq!(BlendMode::from_css_keyword(&kw), BlendMode::Multiply);
 
+        // `isolation` travels with it; it makes the element its own compositing group.
+        match adapter.get_style(img, &StyleProperty::Isolation) {
+            Value::Keyword(kw) => assert_eq!(lookup(kw), "isolate"),
+            other => panic!("expected keyword for isolation, got {other:?}"),
+        }
+
         // Elements without the property default to Normal.
         let body = adapter.body_node_id().expect("body");
         let v = adapter.get_style(body, &StyleProperty::MixBlendMode);
@@ -279,6 +341,341 @@ mod rendertree_from_engine {
         assert_eq!(BlendMode::from_css_keyword(&kw), BlendMode::Normal);
     }
 
+    #[test]
+    fn shadows_and_filters_reach_element_style() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::painter::commands::filter::Filter;
+
+        let html = r#"
+            <html>
+            <head><style>
+                .card { box-shadow: 2px 4px 6px rgba(0, 0, 0, 0.5), inset 0 0 0 1px red; filter: grayscale(1) blur(3px); }
+                .title { text-shadow: 1px 1px 2px blue; }
+            </style></head>
+            <body><div class="card"><p class="title">Hi</p></div></body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let card = find_node_by_class_dfs(&doc, root, "card").expect("find card");
+        let title = find_node_by_class_dfs(&doc, root, "title").expect("find title");
+
+        let shadows = adapter.box_shadows(card);
+        assert_eq!(shadows.len(), 2);
+        assert_eq!(
+            (shadows[0].offset_x, shadows[0].offset_y, shadows[0].blur),
+            (2.0, 4.0, 6.0)
+        );
+        assert!(!shadows[0].inset);
+        assert!((shadows[0].color.a() - 0.5).abs() < 0.01);
+        assert!(shadows[1].inset);
+        assert_eq!(shadows[1].spread, 1.0);
+
+        let filters = adapter.filters(card);
+        assert_eq!(filters.len(), 2);
+        assert!(matches!(filters[0], Filter::Grayscale(v) if v == 1.0));
+        assert!(matches!(filters[1], Filter::Blur(v) if v == 3.0));
+        // `filter` is not inherited; `box-shadow` neither.
+        assert!(adapter.filters(title).is_empty());
+        assert!(adapter.box_shadows(title).is_empty());
+
+        let text_shadows = adapter.text_shadows(title);
+        assert_eq!(text_shadows.len(), 1);
+        assert_eq!(text_shadows[0].color.b8(), 255);
+        assert!(adapter.text_shadows(card).is_empty());
+    }
+
+    #[test]
+    fn column_properties_reach_element_style() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::document::style::{BorderStyle, StyleProperty, Unit, Value};
+        use crate::layouter::multicol::{is_column_spanner, multicol_style};
+        use gosub_lattice::multicol::ColumnFill;
+
+        let html = r#"
+            <html>
+            <head><style>
+                .cols { columns: 180px 3; column-gap: 24px; column-rule: 2px dashed red; column-fill: auto; }
+                .wide { column-span: all; }
+                .flex { display: flex; column-count: 2; }
+            </style></head>
+            <body>
+                <div class="cols"><h2 class="wide">Title</h2><p class="body">Text</p></div>
+                <div class="flex">x</div>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let cols = find_node_by_class_dfs(&doc, root, "cols").expect("find cols");
+        let wide = find_node_by_class_dfs(&doc, root, "wide").expect("find wide");
+        let body = find_node_by_class_dfs(&doc, root, "body").expect("find body");
+        let flex = find_node_by_class_dfs(&doc, root, "flex").expect("find flex");
+
+        assert_eq!(adapter.get_style(cols, &StyleProperty::ColumnCount), Value::Number(3.0));
+        assert_eq!(
+            adapter.get_style(cols, &StyleProperty::ColumnRuleStyle),
+            Value::BorderStyle(BorderStyle::Dashed)
+        );
+        assert_eq!(
+            adapter.get_style(cols, &StyleProperty::ColumnRuleWidth),
+            Value::Unit(2.0, Unit::Px)
+        );
+        assert_eq!(
+            adapter.get_style(cols, &StyleProperty::ColumnRuleColor),
+            Value::Color(255, 0, 0, 255)
+        );
+
+        let style = multicol_style(&adapter, cols, 600.0).expect("cols is a multi-column container");
+        assert_eq!(style.count, Some(3));
+        assert_eq!(style.width, Some(180.0));
+        assert_eq!(style.gap, 24.0);
+        assert_eq!(style.fill, ColumnFill::Auto);
+
+        assert!(is_column_spanner(&adapter, wide));
+        assert!(!is_column_spanner(&adapter, body));
+        // Column properties don't make a flex container multi-column.
+        assert!(multicol_style(&adapter, flex, 600.0).is_none());
+    }
+
+    #[test]
+    fn font_shaping_settings_reach_element_style() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::font::FontShaping;
+        use gosub_interface::font_system::{FontFeature, FontKerning, FontVariation};
+
+        let html = r#"
+            <html>
+            <head><style>
+                .fancy {
+                    font-variant: small-caps tabular-nums;
+                    font-feature-settings: "liga" 0, "ss01";
+                    font-variation-settings: "wght" 650;
+                    font-kerning: none;
+                    font-optical-sizing: none;
+                }
+            </style></head>
+            <body>
+                <p class="fancy">Fancy <span class="inner">text</span></p>
+                <p class="plain">Plain</p>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let fancy = find_node_by_class_dfs(&doc, root, "fancy").expect("find fancy");
+        let inner = find_node_by_class_dfs(&doc, root, "inner").expect("find inner");
+        let plain = find_node_by_class_dfs(&doc, root, "plain").expect("find plain");
+        let shaping = |node| FontShaping::from_style(|prop| adapter.get_style(node, prop));
+
+        let fancy = shaping(fancy);
+        assert_eq!(
+            fancy.features,
+            vec![
+                FontFeature::new(b"smcp", 1),
+                FontFeature::new(b"tnum", 1),
+                FontFeature::new(b"liga", 0),
+                FontFeature::new(b"ss01", 1),
+            ]
+        );
+        assert_eq!(fancy.variations, vec![FontVariation::new(b"wght", 650.0)]);
+        assert_eq!(fancy.kerning, FontKerning::None);
+        assert!(!fancy.optical_sizing);
+        assert_eq!(shaping(inner), fancy, "the settings are inherited");
+        assert_eq!(shaping(plain), FontShaping::default());
+    }
+
+    #[test]
+    fn direction_comes_from_css_and_the_dir_attribute() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+        use crate::common::document::style::{lookup, StyleProperty, Unit, Value};
+
+        let html = r#"
+            <html>
+            <head><style>
+                .rtl { direction: rtl; }
+                .pinned { position: absolute; left: 10px; }
+            </style></head>
+            <body>
+                <div class="rtl"><span class="css-child">x</span></div>
+                <p class="attr" dir="rtl">text</p>
+                <p class="auto-he" dir="auto"><b dir="ltr">skipped</b> שלום</p>
+                <p class="auto-none" dir="auto">123</p>
+                <bdo class="bdo" dir="rtl">abc</bdo>
+                <div dir="rtl"><div class="pinned">x</div></div>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let node = |class| find_node_by_class_dfs(&doc, root, class).expect("find node");
+        let keyword = |class, prop| match adapter.get_style(node(class), &prop) {
+            Value::Keyword(kw) => lookup(kw),
+            other => format!("{other:?}"),
+        };
+
+        assert_eq!(keyword("rtl", StyleProperty::Direction), "rtl");
+        assert_eq!(
+            keyword("css-child", StyleProperty::Direction),
+            "rtl",
+            "direction inherits"
+        );
+        assert_eq!(keyword("attr", StyleProperty::Direction), "rtl");
+        assert_eq!(keyword("attr", StyleProperty::UnicodeBidi), "isolate", "`dir` isolates");
+        assert_eq!(keyword("rtl", StyleProperty::UnicodeBidi), "normal");
+        assert_eq!(
+            keyword("auto-he", StyleProperty::Direction),
+            "rtl",
+            "`dir=auto` skips descendants with their own `dir`"
+        );
+        assert_eq!(keyword("auto-none", StyleProperty::Direction), "ltr");
+        assert_eq!(keyword("bdo", StyleProperty::UnicodeBidi), "bidi-override");
+
+        // `left` is the inline end of an RTL element.
+        let pinned = node("pinned");
+        assert!(matches!(
+            adapter.get_own_style(pinned, &StyleProperty::InsetInlineEnd),
+            Some(Value::Unit(v, Unit::Px)) if v == 10.0
+        ));
+        assert!(adapter
+            .get_own_style(pinned, &StyleProperty::InsetInlineStart)
+            .is_none());
+    }
+
+    #[test]
+    fn selection_colors_come_from_the_nearest_selection_rule() {
+        use crate::common::document::pipeline_doc::PipelineDocument;
+
+        let html = r#"
+            <html>
+            <head><style>
+                .quote::selection { color: white; background-color: #800000; }
+                .note::selection { background: yellow; }
+                .plain::selection { font-weight: bold; }
+            </style></head>
+            <body>
+                <div class="quote"><span class="inner">a</span></div>
+                <p class="note">b</p>
+                <p class="plain">c</p>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let colors = |class| {
+            let (color, background) =
+                adapter.selection_colors(find_node_by_class_dfs(&doc, root, class).expect("find node"));
+            (color.map(|c| c.r8()), background.map(|c| (c.r8(), c.g8(), c.b8())))
+        };
+
+        assert_eq!(colors("quote"), (Some(255), Some((128, 0, 0))));
+        assert_eq!(colors("inner"), colors("quote"), "the nearest ancestor's rule applies");
+        assert_eq!(colors("note"), (None, Some((255, 255, 0))));
+        // A rule without colours leaves the defaults.
+        assert_eq!(colors("plain"), (None, None));
+    }
+
+    #[test]
+    fn background_layers_reach_element_style() {
+        use crate::common::document::pipeline_doc::{BgBox, BgImage, BgSize, PipelineDocument};
+        use crate::painter::commands::gradient::{ColorInterpolation, Gradient, LengthPercent};
+
+        let html = r#"
+            <html>
+            <head><style>
+                .bg {
+                    background-image: radial-gradient(circle at 25% 75%, red, blue), conic-gradient(from 90deg, red, blue);
+                    background-size: 10px 20px, cover;
+                    background-position: right 5px top;
+                    background-repeat: no-repeat;
+                    background-clip: content-box;
+                }
+                .oklch { background: repeating-linear-gradient(to right in oklch, red, blue 10px); }
+            </style></head>
+            <body><div class="bg"></div><div class="oklch"></div></body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+
+        let doc = adapter.document();
+        let root = doc.root();
+        let bg = find_node_by_class_dfs(&doc, root, "bg").expect("find bg");
+        let oklch = find_node_by_class_dfs(&doc, root, "oklch").expect("find oklch");
+
+        let layers = adapter.background_layers(bg);
+        assert_eq!(layers.len(), 2);
+        let BgImage::Gradient(Gradient::Radial(radial)) = &layers[0].image else {
+            panic!("first layer is not a radial gradient: {:?}", layers[0].image);
+        };
+        assert_eq!(
+            radial.center,
+            (LengthPercent::percent(25.0), LengthPercent::percent(75.0))
+        );
+        assert!(matches!(layers[1].image, BgImage::Gradient(Gradient::Conic(ref c)) if c.from_deg == 90.0));
+
+        // Per-layer longhands; a single value repeats for every layer.
+        assert_eq!(
+            layers[0].layout.size,
+            BgSize::Explicit(Some(LengthPercent::px(10.0)), Some(LengthPercent::px(20.0)))
+        );
+        assert_eq!(layers[1].layout.size, BgSize::Cover);
+        for layer in &layers {
+            assert_eq!(
+                layer.layout.position.0,
+                LengthPercent {
+                    px: -5.0,
+                    percent: 100.0
+                }
+            );
+            assert_eq!(layer.layout.position.1, LengthPercent::percent(0.0));
+            assert_eq!(layer.layout.repeat, (false, false));
+            assert_eq!(layer.layout.clip, BgBox::Content);
+            assert_eq!(layer.layout.origin, BgBox::Padding);
+        }
+
+        let layers = adapter.background_layers(oklch);
+        assert_eq!(layers.len(), 1);
+        let BgImage::Gradient(g) = &layers[0].image else {
+            panic!("shorthand gradient not parsed: {:?}", layers[0].image);
+        };
+        assert!(g.repeating());
+        assert!(matches!(g.interpolation(), ColorInterpolation::Oklch(_)));
+    }
+
     #[test]
     fn html_and_body_node_ids_are_found() {
         use crate::common::document::pipeline_doc::PipelineDocument;
@@ -298,6 +695,54 @@ mod rendertree_from_engine {
         assert_eq!(adapter.tag_name(body_id.unwrap()), Some("body".to_string()));
     }
 
+    #[test]
+    fn list_markers_and_counters_generate_text() {
+        use crate::common::document::node::NodeType;
+        use crate::common::document::pipeline_doc::PipelineDocument;
+
+        let html = r#"
+            <html>
+            <head><style>
+                .roman { list-style-type: upper-roman; }
+                h2 { counter-increment: chapter; }
+                h2::before { content: "Ch. " counter(chapter) ": "; }
+            </style></head>
+            <body>
+                <ol start="3"><li class="first">a</li><li class="second">b</li></ol>
+                <ul class="roman"><li value="4" class="fourth">c</li></ul>
+                <ul><li class="plain" style="list-style: none">d</li></ul>
+                <h2>One</h2><h2 class="two">Two</h2>
+            </body>
+            </html>
+        "#;
+
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let doc = adapter.document();
+        let root = doc.root();
+
+        // The generated text under the first generated child of `owner`.
+        let generated_text = |owner| {
+            let pseudo = *adapter.children(owner).first()?;
+            let text = *adapter.children(pseudo).first()?;
+            match adapter.get_node_by_id(text)?.node_type {
+                NodeType::Text(t) => Some(t),
+                _ => None,
+            }
+        };
+        let by_class = |class| find_node_by_class_dfs(&doc, root, class).expect("find node");
+
+        assert_eq!(generated_text(by_class("first")).as_deref(), Some("3."));
+        assert_eq!(generated_text(by_class("second")).as_deref(), Some("4."));
+        assert_eq!(generated_text(by_class("fourth")).as_deref(), Some("IV."));
+        // `list-style: none` suppresses the marker; the first child is the text content.
+        let plain = by_class("plain");
+        assert_eq!(adapter.children(plain), doc.children(plain).to_vec());
+        assert_eq!(generated_text(by_class("two")).as_deref(), Some("Ch. 2: "));
+    }
+
     fn find_node_by_class_dfs(
         doc: &DocumentImpl<Config>,
         node: gosub_shared::node::NodeId,
@@ -343,27 +788,66 @@ mod rendertree_from_engine {
         let ua = Css3System::load_default_useragent_stylesheet();
         doc.add_stylesheet(ua);
         let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
-        let root = adapter.doc.root();
+        let doc = adapter.document();
+        let root = doc.root();
 
         let url_of = |id| match adapter.get_style(id, &StyleProperty::BackgroundImage) {
             Value::Keyword(k) => lookup(k),
             other => panic!("expected keyword url, got {other:?}"),
         };
 
-        let longhand = find_node_by_id_attr(&adapter.doc, root, "longhand").expect("find #longhand");
+        let longhand = find_node_by_id_attr(&doc, root, "longhand").expect("find #longhand");
         assert_eq!(url_of(longhand), "pic.png", "longhand url not read");
 
-        let votearrow = find_node_by_class_dfs(&adapter.doc, root, "votearrow").expect("find .votearrow");
+        let votearrow = find_node_by_class_dfs(&doc, root, "votearrow").expect("find .votearrow");
         assert_eq!(url_of(votearrow), "grayarrow.gif", "shorthand url not read");
 
-        let inline = find_node_by_id_attr(&adapter.doc, root, "inline").expect("find #inline");
+        let inline = find_node_by_id_attr(&doc, root, "inline").expect("find #inline");
         assert_eq!(url_of(inline), "inline.gif", "inline url not read");
 
         // An element without a background-image gets the initial value `none`.
-        let plain = find_node_by_id_attr(&adapter.doc, root, "plain").expect("find #plain");
+        let plain = find_node_by_id_attr(&doc, root, "plain").expect("find #plain");
         assert_eq!(url_of(plain), "none", "plain element should be `none`");
     }
 
+    // The first `<source>` whose media matches and whose type decodes wins over the `<img>`.
+    #[test]
+    fn picture_selects_the_first_usable_source() {
+        use crate::image_source::{select_image_source, ImageEnvironment};
+
+        let html = r#"
+            <html><body>
+                <picture>
+                    <source media="(min-width: 2000px)" srcset="huge.png">
+                    <source type="image/jxl" srcset="photo.jxl">
+                    <source srcset="photo.png 1x, photo@2x.png 2x">
+                    <img id="photo" src="fallback.png">
+                </picture>
+                <img id="plain" src="plain.png" srcset="plain@2x.png 2x">
+            </body></html>
+        "#;
+        let mut doc = html_compile::<Config>(html);
+        let ua = Css3System::load_default_useragent_stylesheet();
+        doc.add_stylesheet(ua);
+        let adapter = GosubDocumentAdapter::<Config>::new(Arc::new(doc));
+        let doc = adapter.document();
+        let root = doc.root();
+        let photo = find_node_by_id_attr(&doc, root, "photo").expect("find #photo");
+        let plain = find_node_by_id_attr(&doc, root, "plain").expect("find #plain");
+
+        let env = |width, dpr| ImageEnvironment {
+            viewport_width: width,
+            viewport_height: 800.0,
+            device_pixel_ratio: dpr,
+        };
+        let url = |id, env| select_image_source(&adapter, id, &env).map(|s| s.url);
+        assert_eq!(url(photo, env(1000.0, 1.0)).as_deref(), Some("photo.png"));
+        assert_eq!(url(photo, env(1000.0, 2.0)).as_deref(), Some("photo@2x.png"));
+        assert_eq!(url(photo, env(2400.0, 1.0)).as_deref(), Some("huge.png"));
+        assert_eq!(url(plain, env(1000.0, 1.0)).as_deref(), Some("plain.png"));
+        assert_eq!(url(plain, env(1000.0, 2.0)).as_deref(), Some("plain@2x.png"));
+    }
+
     fn find_node_by_id_attr(
         doc: &DocumentImpl<Config>,
         node: gosub_shared::node::NodeId,
@@ -383,4 +867,460 @@ mod rendertree_from_engine {
         }
         None
     }
+
+    /// Lay out `html` in a 400x300 viewport and layer it.
+    fn layer_html(html: &str) -> crate::layering::layer::LayerList {
+        use crate::common::geo::Dimension;
+        use crate::layouter::taffy::TaffyLayouter;
+        use crate::layouter::CanLayout as _;
+
+        let layout_tree =
+            TaffyLayouter::new().layout(parse_to_rendertree(html), Some(Dimension::new(400.0, 300.0)), 1.0);
+        crate::layering::layer::LayerList::new(layout_tree)
+    }
+
+    #[test]
+    fn indexed_hit_test_matches_a_linear_scan() {
+        let html = r#"
+            <html><body style="margin: 0">
+                <div style="height: 40px">Header text</div>
+                <div style="position: absolute; left: 20px; top: 10px; width: 100px; height: 100px; z-index: 2"></div>
+                <div style="position: absolute; left: 60px; top: 50px; width: 100px; height: 100px">
+                    <span>Overlapping</span>
+                </div>
+                <p style="margin: 8px 0">Some paragraph text that wraps inside the viewport.</p>
+            </body></html>
+        "#;
+        let layer_list = layer_html(html);
+        let layers = layer_list.layers.read();
+        let layer_ids = layer_list.layer_ids.read().clone();
+
+        // The pre-index hit test: every box of every layer, topmost first.
+        let linear = |x: f64, y: f64| {
+            layer_ids.iter().rev().find_map(|id| {
+                layers[id].elements.iter().rev().copied().find(|e| {
+                    let r = layer_list.layout_tree.get_node_by_id(*e).unwrap().box_model.margin_box;
+                    x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+                })
+            })
+        };
+        for y in (0..300).step_by(5) {
+            for x in (0..400).step_by(5) {
+                let (x, y) = (x as f64, y as f64);
+                assert_eq!(
+                    layer_list.find_element_at(x, y, 0.0, 0.0),
+                    linear(x, y),
+                    "at ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hit_test_reports_ancestors_and_text_offset() {
+        let layer_list = layer_html(r#"<html><body><p id="p">Hello world</p></body></html>"#);
+        let tree = &layer_list.layout_tree;
+        let (text, ctx) = crate::selection::text_elements(tree)[0];
+        let content_box = tree.get_node_by_id(text).unwrap().box_model.content_box;
+
+        let (x, y) = (content_box.x + 1.0, content_box.y + content_box.height / 2.0);
+        let hit = layer_list.hit_test(x, y, 0.0, 0.0, None).unwrap();
+        assert_eq!(hit.element, text);
+        assert_eq!(hit.ancestors.last(), Some(&tree.root_id));
+        let parent = tree.get_node_by_id(text).unwrap().parent;
+        assert_eq!(hit.ancestors.first().copied(), parent);
+        assert!(hit.text_offset.is_some_and(|offset| offset <= ctx.text.len()));
+
+        // Scrolling moves the page under the viewport point.
+        let scrolled = layer_list.hit_test(x, y - 5.0, 0.0, 5.0, None).unwrap();
+        assert_eq!(scrolled.element, text);
+        assert_eq!((scrolled.x, scrolled.y), (hit.x, hit.y));
+    }
+
+    #[test]
+    fn shadow_only_tiles_keep_the_margin_box_rect() {
+        use crate::common::geo::Dimension;
+        use crate::tiler::TileList;
+
+        let layer_list = layer_html(
+            r#"<html><body style="margin: 0">
+                <div style="width: 100px; height: 40px; box-shadow: 30px 0 0 black"></div>
+            </body></html>"#,
+        );
+        let card = layer_list
+            .layout_tree
+            .arena
+            .iter()
+            .find(|(_, node)| node.box_model.margin_box.width == 100.0 && node.box_model.margin_box.height == 40.0)
+            .map(|(id, _)| *id)
+            .unwrap();
+        let mut tile_list = TileList::new(layer_list, Dimension::new(120.0, 120.0));
+        tile_list.generate();
+
+        let mut parts: Vec<_> = tile_list
+            .arena
+            .values()
+            .flat_map(|tile| tile.elements.iter().filter(|e| e.id == card))
+            .collect();
+        parts.sort_by(|a, b| a.ink.x.total_cmp(&b.ink.x));
+        assert_eq!(parts.len(), 2, "the shadow reaches the second tile");
+
+        assert_eq!((parts[0].rect.width, parts[0].rect.height), (100.0, 40.0));
+        assert_eq!((parts[0].position.x, parts[0].position.y), (0.0, 0.0));
+        assert_eq!(parts[0].ink.width, 120.0);
+
+        // Only the shadow is in the second tile: no part of the margin box, 10px of ink.
+        assert_eq!(parts[1].rect.width, 0.0);
+        assert_eq!((parts[1].ink.x, parts[1].ink.width), (120.0, 10.0));
+    }
+
+    #[test]
+    fn blocked_web_font_hides_only_its_text() {
+        use crate::common::browser_state::{BlockedFont, BrowserState, WireframeState};
+        use crate::common::geo::Rect;
+        use crate::painter::commands::PaintCommand;
+        use crate::painter::Painter;
+        use gosub_interface::font_system::UnicodeRange;
+        use std::sync::Arc;
+
+        let layer_list = Arc::new(layer_html(
+            r#"<html><body style="margin: 0">
+                <div style="height: 40px; background: red; font-family: 'Web', serif">Waiting</div>
+                <div style="font-family: serif">Fallback</div>
+            </body></html>"#,
+        ));
+        let painted = |blocked_fonts: Vec<BlockedFont>| {
+            let state = BrowserState {
+                visible_layer_list: vec![true; layer_list.layer_ids.read().len()],
+                wireframed: WireframeState::None,
+                debug_hover: false,
+                show_tilegrid: false,
+                debug_table_cells: false,
+                current_hovered_element: None,
+                selection: Default::default(),
+                find: Default::default(),
+                frames: Default::default(),
+                media: Default::default(),
+                image_frames: Default::default(),
+                blocked_fonts,
+                viewport: Rect::new(0.0, 0.0, 400.0, 300.0),
+                tile_list: None,
+                dpi_scale_factor: 1.0,
+            };
+            let commands = Painter::new(Arc::clone(&layer_list), None).paint_all(&state);
+            let texts: Vec<String> = commands
+                .iter()
+                .filter_map(|c| match c {
+                    PaintCommand::Text(t) => Some(t.text.clone()),
+                    _ => None,
+                })
+                .collect();
+            let rects = commands
+                .iter()
+                .filter(|c| matches!(c, PaintCommand::Rectangle(_)))
+                .count();
+            (texts, rects)
+        };
+
+        let (texts, rects) = painted(Vec::new());
+        assert_eq!(texts, ["Waiting", "Fallback"]);
+
+        let blocked = |range| BlockedFont {
+            family: "web".into(),
+            range,
+        };
+        let (blocked_texts, blocked_rects) = painted(vec![blocked(UnicodeRange::all())]);
+        assert_eq!(blocked_texts, ["Fallback"], "only text in the loading family waits");
+        assert_eq!(blocked_rects, rects, "the background still paints");
+
+        // A subset covering none of the text does not hold it back.
+        let (texts, _) = painted(vec![blocked(UnicodeRange::parse("U+0400-04FF").unwrap())]);
+        assert_eq!(texts, ["Waiting", "Fallback"]);
+    }
+
+    #[test]
+    fn fragment_honours_break_before() {
+        let layer_list = layer_html(
+            r#"<html><body style="margin: 0">
+                <div style="height: 40px">One</div>
+                <div style="break-before: page; height: 40px">Two</div>
+                <div style="page-break-before: always; height: 40px">Three</div>
+            </body></html>"#,
+        );
+        let pages = crate::paged::fragment(&layer_list.layout_tree, |_| 1000.0);
+        assert_eq!(pages.len(), 3);
+        assert_eq!((pages[1].start, pages[2].start), (40.0, 80.0));
+    }
+
+    /// Parse `html` with the UA stylesheet and lay it out in a 400x300 viewport, keeping the
+    /// layouter and adapter so the page can be laid out again incrementally.
+    fn layout_incrementally(
+        html: &str,
+    ) -> (
+        crate::layouter::taffy::TaffyLayouter,
+        Arc<GosubDocumentAdapter<Config>>,
+        crate::layouter::LayoutTree,
+    ) {
+        use crate::common::geo::Dimension;
+        use crate::layouter::CanLayout as _;
+
+        let mut doc = html_compile::<Config>(html);
+        doc.add_stylesheet(Css3System::load_default_useragent_stylesheet());
+        let adapter = Arc::new(GosubDocumentAdapter::<Config>::new(Arc::new(doc)));
+        let mut rt = RenderTree::new(adapter.clone());
+        rt.parse().expect("failed to build render tree");
+        let mut layouter = crate::layouter::taffy::TaffyLayouter::new();
+        let tree = layouter.layout(rt, Some(Dimension::new(400.0, 300.0)), 1.0);
+        (layouter, adapter, tree)
+    }
+
+    /// A full layout of `doc` in the same viewport, to compare incremental results against.
+    fn full_layout(doc: Arc<DocumentImpl<Config>>) -> crate::layouter::LayoutTree {
+        use crate::common::geo::Dimension;
+        use crate::layouter::CanLayout as _;
+
+        let mut rt = RenderTree::new(Arc::new(GosubDocumentAdapter::<Config>::new(doc)));
+        rt.parse().expect("failed to build render tree");
+        crate::layouter::taffy::TaffyLayouter::new().layout(rt, Some(Dimension::new(400.0, 300.0)), 1.0)
+    }
+
+    /// Applies `mutate` to a copy of the adapter's document and returns the copy, the nodes the
+    /// recorded mutations restyle, and the adapter over the copy.
+    fn mutate(
+        adapter: &GosubDocumentAdapter<Config>,
+        f: impl FnOnce(&mut DocumentImpl<Config>),
+    ) -> (
+        Arc<DocumentImpl<Config>>,
+        Vec<gosub_shared::node::NodeId>,
+        Arc<GosubDocumentAdapter<Config>>,
+    ) {
+        use crate::invalidation::{invalidate, Restyle};
+
+        let doc = adapter.document();
+        let mut doc = (*doc).clone();
+        doc.record_mutations(true);
+        f(&mut doc);
+        let mutations = doc.take_mutations();
+        let sets = Css3System::invalidation_sets(doc.stylesheets());
+        let Restyle::Nodes(restyled) = invalidate::<Config>(&doc, &sets, &mutations).restyle else {
+            panic!("expected a node restyle");
+        };
+        let doc = Arc::new(doc);
+        let next = Arc::new(adapter.with_document(doc.clone(), &restyled));
+        (doc, restyled, next)
+    }
+
+    fn assert_same_boxes(incremental: &crate::layouter::LayoutTree, full: &crate::layouter::LayoutTree) {
+        assert_eq!(incremental.arena.len(), full.arena.len());
+        for (id, node) in &full.arena {
+            let other = &incremental.arena[id];
+            assert_eq!(other.dom_node_id, node.dom_node_id);
+            assert!(other.box_model == node.box_model, "box of {id} differs");
+        }
+        assert!(incremental.root_dimension == full.root_dimension);
+    }
+
+    #[test]
+    fn class_change_restyles_only_what_selectors_reach() {
+        use crate::invalidation::{invalidate, Damage, Restyle};
+
+        let mut doc = html_compile::<Config>(
+            r#"<html><head><style>.hl { color: red } .wide p { width: 10px }</style></head>
+               <body><div id="a"><p id="ap">x</p></div><div id="b"><p>y</p></div></body></html>"#,
+        );
+        let sets = Css3System::invalidation_sets(doc.stylesheets());
+        let root = doc.root();
+        let a = find_node_by_id_attr(&doc, root, "a").unwrap();
+        let ap = find_node_by_id_attr(&doc, root, "ap").unwrap();
+        let b = find_node_by_id_attr(&doc, root, "b").unwrap();
+
+        doc.record_mutations(true);
+        doc.set_attribute(b, "class", "unused");
+        let mutations = doc.take_mutations();
+        let untouched = invalidate::<Config>(&doc, &sets, &mutations);
+        assert_eq!(untouched.restyle, Restyle::Nodes(Vec::new()));
+        assert_eq!(untouched.damage, Damage::None);
+
+        doc.set_attribute(a, "class", "hl");
+        let mutations = doc.take_mutations();
+        let Restyle::Nodes(nodes) = invalidate::<Config>(&doc, &sets, &mutations).restyle else {
+            panic!("expected a node restyle");
+        };
+        assert_eq!(nodes.first(), Some(&a));
+        assert!(nodes.contains(&ap));
+        assert!(!nodes.contains(&b));
+
+        doc.set_attribute(ap, "width", "20");
+        let mutations = doc.take_mutations();
+        assert_eq!(invalidate::<Config>(&doc, &sets, &mutations).damage, Damage::Rebuild);
+    }
+
+    #[test]
+    fn href_change_restyles_the_link() {
+        use crate::invalidation::{invalidate, Damage, Restyle};
+
+        let mut doc = html_compile::<Config>(
+            r#"<html><head><style>a:any-link { color: red }</style></head>
+               <body><a id="a">x</a><p id="p">y</p></body></html>"#,
+        );
+        let sets = Css3System::invalidation_sets(doc.stylesheets());
+        let root = doc.root();
+        let a = find_node_by_id_attr(&doc, root, "a").unwrap();
+        let p = find_node_by_id_attr(&doc, root, "p").unwrap();
+
+        // Gaining an `href` makes the anchor a link, so `:link`/`:any-link` rules start to apply.
+        doc.record_mutations(true);
+        doc.set_attribute(a, "href", "/next");
+        let mutations = doc.take_mutations();
+        let invalidation = invalidate::<Config>(&doc, &sets, &mutations);
+        let Restyle::Nodes(nodes) = invalidation.restyle else {
+            panic!("expected a node restyle");
+        };
+        assert_eq!(nodes.first(), Some(&a));
+        assert!(!nodes.contains(&p));
+        assert_eq!(invalidation.damage, Damage::Rebuild);
+    }
+
+    #[test]
+    fn relayout_after_a_geometry_change_matches_a_full_layout() {
+        use crate::invalidation::{damage_rects, style_damage, Damage};
+        use crate::layering::layer::LayerList;
+
+        let (mut layouter, adapter, previous) = layout_incrementally(
+            r#"<html><body style="margin: 0">
+                <div id="a" style="width: 100px; padding: 4px"><p>Some text in a narrow box</p></div>
+                <div id="b" style="height: 20px; background: blue"></div>
+            </body></html>"#,
+        );
+        let doc = adapter.document();
+        let a = find_node_by_id_attr(&doc, doc.root(), "a").unwrap();
+        let (doc, restyled, next) = mutate(&adapter, |doc| {
+            doc.set_attribute(a, "style", "width: 250px; padding: 4px");
+        });
+        assert_eq!(style_damage(&*adapter, &*next, &restyled), Damage::Layout);
+
+        let relaid = layouter
+            .relayout(
+                &previous,
+                next,
+                &restyled,
+                Some(crate::common::geo::Dimension::new(400.0, 300.0)),
+            )
+            .expect("the tree can be laid out in place");
+        assert_same_boxes(&relaid, &full_layout(doc));
+        let (previous, relaid) = (LayerList::new(previous), LayerList::new(relaid));
+        assert!(!damage_rects(&previous, &relaid, &restyled).is_empty());
+    }
+
+    #[test]
+    fn restyle_after_a_color_change_keeps_boxes_and_layers() {
+        use crate::invalidation::{damage_rects, style_damage, Damage};
+        use crate::layering::layer::LayerList;
+
+        let (layouter, adapter, previous) = layout_incrementally(
+            r#"<html><body style="margin: 0">
+                <div id="a" style="width: 100px"><p>Some text</p></div>
+                <div id="b" style="height: 20px"></div>
+            </body></html>"#,
+        );
+        let doc = adapter.document();
+        let a = find_node_by_id_attr(&doc, doc.root(), "a").unwrap();
+        let (doc, restyled, next) = mutate(&adapter, |doc| {
+            doc.set_attribute(a, "style", "width: 100px; color: red");
+        });
+        assert_eq!(style_damage(&*adapter, &*next, &restyled), Damage::Paint);
+
+        let restyled_tree = layouter.restyle(&previous, next, &restyled);
+        assert_same_boxes(&restyled_tree, &full_layout(doc));
+
+        // Only the restyled element's area is repainted.
+        let a_box = previous
+            .arena
+            .values()
+            .find(|node| node.dom_node_id == a)
+            .unwrap()
+            .box_model
+            .margin_box;
+        let (before, after) = (LayerList::new(previous), LayerList::new(restyled_tree));
+        let rects = damage_rects(&before, &after, &restyled);
+        assert!(!rects.is_empty());
+        for r in rects {
+            assert!(r.x >= a_box.x && r.y >= a_box.y, "{r:?} outside {a_box:?}");
+            assert!(r.x + r.width <= a_box.x + a_box.width && r.y + r.height <= a_box.y + a_box.height);
+        }
+        assert!(before.has_same_layers(&after));
+    }
+
+    #[test]
+    fn snapshot_keeps_the_styles_from_before_an_in_place_edit() {
+        use crate::invalidation::{invalidate, style_damage, Damage, Restyle};
+
+        let (_, adapter, _) = layout_incrementally(
+            r#"<html><body style="margin: 0">
+                <div id="a" style="width: 100px"><p>Some text</p></div>
+            </body></html>"#,
+        );
+        let doc = adapter.document();
+        let a = find_node_by_id_attr(&doc, doc.root(), "a").unwrap();
+        let sets = Css3System::invalidation_sets(doc.stylesheets());
+        let before = Arc::as_ptr(&doc);
+        // Nothing is edited while another reference to the document is held.
+        assert!(adapter.edit_document(|_| ()).is_none());
+        drop(doc);
+
+        let snapshot = adapter.snapshot();
+        let mutations = adapter
+            .edit_document(|doc| {
+                doc.record_mutations(true);
+                doc.set_attribute(a, "style", "width: 100px; color: red");
+                doc.take_mutations()
+            })
+            .expect("the adapter holds the only reference");
+        let doc = adapter.document();
+        assert_eq!(Arc::as_ptr(&doc), before);
+
+        let Restyle::Nodes(restyled) = invalidate::<Config>(&doc, &sets, &mutations).restyle else {
+            panic!("expected a node restyle");
+        };
+        let next = adapter.with_document(Arc::clone(&doc), &restyled);
+        let old = GosubDocumentAdapter::<Config>::from_snapshot(snapshot, doc);
+        assert_eq!(style_damage(&old, &next, &restyled), Damage::Paint);
+        // Only the `::selection` styles, which no paint needed, were missing from the snapshot.
+        assert_eq!(old.snapshot_damage(), Damage::Paint);
+        assert_eq!(adapter.snapshot_damage(), Damage::None);
+    }
+
+    #[test]
+    fn damage_reaches_as_far_as_the_layer_filter_blurs() {
+        use crate::invalidation::damage_rects;
+        use crate::layering::layer::LayerList;
+
+        let (layouter, adapter, previous) = layout_incrementally(
+            r#"<html><body style="margin: 0">
+                <div style="filter: blur(4px); padding: 40px">
+                    <div id="a" style="width: 100px; height: 20px; background: blue"></div>
+                </div>
+            </body></html>"#,
+        );
+        let doc = adapter.document();
+        let a = find_node_by_id_attr(&doc, doc.root(), "a").unwrap();
+        let (_, restyled, next) = mutate(&adapter, |doc| {
+            doc.set_attribute(a, "style", "width: 100px; height: 20px; background: red");
+        });
+        let a_box = previous
+            .arena
+            .values()
+            .find(|node| node.dom_node_id == a)
+            .unwrap()
+            .box_model
+            .margin_box;
+
+        let restyled_tree = layouter.restyle(&previous, next, &restyled);
+        let (before, after) = (LayerList::new(previous), LayerList::new(restyled_tree));
+        // blur(4px) spreads ink 12px (three sigma) past the box on every side.
+        let rects = damage_rects(&before, &after, &restyled);
+        assert!(rects
+            .iter()
+            .any(|r| r.x <= a_box.x - 12.0 && r.x + r.width >= a_box.x + a_box.width + 12.0));
+    }
 }
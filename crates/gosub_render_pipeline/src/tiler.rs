@@ -5,6 +5,8 @@ use crate::common::geo::{Coordinate, Dimension, Rect};
 use crate::common::texture::TextureId;
 use crate::layering::layer::{LayerId, LayerList};
 use crate::layouter::LayoutElementId;
+use crate::painter::commands::filter::{filters_overflow, Filter};
+use crate::painter::commands::shadow::shadows_overflow;
 use crate::painter::commands::PaintCommand;
 use parking_lot::RwLock;
 use rstar::primitives::GeomWithData;
@@ -39,11 +41,16 @@ impl std::fmt::Display for TileId {
 #[derive(Debug, Clone)]
 pub struct TiledLayoutElement {
     pub id: LayoutElementId,
-    /// Part of the element to draw, in element-local coordinates (a subset when the
-    /// element spans multiple tiles). See the diagram below.
+    /// Part of the element's margin box to draw, in element-local coordinates (a subset when the
+    /// element spans multiple tiles). Empty when only the element's ink reaches the tile. See the
+    /// diagram below.
     pub rect: Rect,
-    /// Where inside the tile the element starts. See the diagram below.
+    /// Where inside the tile the element's margin box starts. See the diagram below.
     pub position: Coordinate,
+    /// Part of the element's ink box that falls within the tile, in layer coordinates. The ink box
+    /// is the margin box grown by box and text shadows and the layer's filter overflow; the
+    /// element is assigned to every tile it reaches.
+    pub ink: Rect,
     pub paint_commands: Vec<PaintCommand>,
 }
 
@@ -60,6 +67,9 @@ In tile 4, the rect of element 67 is (50, 25, 50, 25). The position is (0, 0).
 So in tile 1 the element starts at 50x25; even though the element is 100x50, the rect
 covers only 0,0..50,25 - the top left quarter of the element.
 
+Rect and position follow the margin box. An element whose shadow or filter spills over into a
+neighbouring tile is listed in that tile too, with an empty rect; its `ink` says what it covers.
+
     0                 100             200
     +------------------+----------------+
     |                  |                |
@@ -99,6 +109,9 @@ pub struct Tile {
     pub rect: Rect,
     /// Background color of the whole canvas, not of this tile. We should deal with this differently.
     pub bgcolor: Option<(f32, f32, f32, f32)>,
+    /// CSS `filter` chain of the tile's layer. Filters like `blur()` read neighbouring pixels, so
+    /// the rasterizer paints an enlarged area and crops it back to the tile.
+    pub filters: Vec<Filter>,
 }
 
 /// Each layer has a list of tiles. Each tile has a list of elements that are laid out in that tile.
@@ -248,6 +261,27 @@ impl TileList {
                 continue;
             };
 
+            // Where each element can leave ink: its margin box grown by shadows and the layer's
+            // filters, which paint outside the box.
+            let layer_overflow = filters_overflow(&layer.filters);
+            let ink_rects: HashMap<LayoutElementId, (Rect, Rect)> = layer
+                .elements
+                .iter()
+                .filter_map(|&eid| {
+                    let el = self.layer_list.layout_tree.get_node_by_id(eid)?;
+                    let doc = &self.layer_list.layout_tree.render_tree.doc;
+                    let shadows = shadows_overflow(&doc.box_shadows(el.dom_node_id))
+                        .max(&shadows_overflow(&doc.text_shadows(el.dom_node_id)));
+                    let m = el.box_model.margin_box;
+                    let ink = if m.width > 0.0 && m.height > 0.0 {
+                        layer_overflow.outset(shadows.outset(m))
+                    } else {
+                        m
+                    };
+                    Some((eid, (m, ink)))
+                })
+                .collect();
+
             // Only tile the union bounding box of the layer's elements. Layer 0 is the exception:
             // it carries the canvas background color, so it needs full-page coverage.
             let (row_start, row_end, col_start, col_end) = if layer_idx == 0 || layer.elements.is_empty() {
@@ -258,8 +292,7 @@ impl TileList {
                 let mut max_x = f64::MIN;
                 let mut max_y = f64::MIN;
                 for &eid in &layer.elements {
-                    if let Some(el) = self.layer_list.layout_tree.get_node_by_id(eid) {
-                        let m = el.box_model.margin_box;
+                    if let Some(&(_, m)) = ink_rects.get(&eid) {
                         if m.width > 0.0 && m.height > 0.0 {
                             min_x = min_x.min(m.x);
                             min_y = min_y.min(m.y);
@@ -271,6 +304,7 @@ impl TileList {
                 if min_x > max_x || min_y > max_y {
                     continue;
                 }
+                // Ink above/left of the page origin is clipped: `as usize` saturates at 0.
                 let cs = (min_x / tile_w).floor() as usize;
                 let ce = ((max_x / tile_w).ceil() as usize).min(max_cols);
                 let rs = (min_y / tile_h).floor() as usize;
@@ -292,6 +326,7 @@ impl TileList {
                         texture_id: None,
                         rect: Rect::new(x as f64 * tile_w, y as f64 * tile_h, tile_w, tile_h),
                         bgcolor,
+                        filters: layer.filters.clone(),
                     };
 
                     self.arena.insert(tile_id, tile);
@@ -328,13 +363,12 @@ impl TileList {
             };
 
             for &element_id in &layer.elements {
-                let Some(element) = self.layer_list.layout_tree.get_node_by_id(element_id) else {
+                let Some(&(margin_box, ink_box)) = ink_rects.get(&element_id) else {
                     log::warn!("Warning: Element {:?} not found in layout tree!", element_id);
                     continue;
                 };
-                let margin_box = element.box_model.margin_box;
 
-                let matching_tile_ids = tile_layer.intersects_with(margin_box);
+                let matching_tile_ids = tile_layer.intersects_with(ink_box);
                 for tile_id in &matching_tile_ids {
                     let Some(tile) = self.arena.get_mut(tile_id) else {
                         log::warn!("Tile {:?} missing from arena while assigning elements", tile_id);
@@ -348,16 +382,26 @@ impl TileList {
                     let dimension = Rect::new(
                         tile.rect.x.max(margin_box.x) - margin_box.x,
                         tile.rect.y.max(margin_box.y) - margin_box.y,
-                        (tile.rect.x + tile.rect.width).min(margin_box.x + margin_box.width)
-                            - tile.rect.x.max(margin_box.x),
-                        (tile.rect.y + tile.rect.height).min(margin_box.y + margin_box.height)
-                            - tile.rect.y.max(margin_box.y),
+                        ((tile.rect.x + tile.rect.width).min(margin_box.x + margin_box.width)
+                            - tile.rect.x.max(margin_box.x))
+                        .max(0.0),
+                        ((tile.rect.y + tile.rect.height).min(margin_box.y + margin_box.height)
+                            - tile.rect.y.max(margin_box.y))
+                        .max(0.0),
+                    );
+
+                    let ink = Rect::new(
+                        tile.rect.x.max(ink_box.x),
+                        tile.rect.y.max(ink_box.y),
+                        (tile.rect.x + tile.rect.width).min(ink_box.x + ink_box.width) - tile.rect.x.max(ink_box.x),
+                        (tile.rect.y + tile.rect.height).min(ink_box.y + ink_box.height) - tile.rect.y.max(ink_box.y),
                     );
 
                     let tiled_element = TiledLayoutElement {
                         id: element_id,
                         rect: dimension,
                         position,
+                        ink,
                         paint_commands: vec![],
                     };
 
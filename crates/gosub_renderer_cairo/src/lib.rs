@@ -1,4 +1,6 @@
 pub mod backend;
+#[cfg(feature = "pdf")]
+pub mod pdf;
 pub mod rasterizer;
 
 pub use backend::{CairoBackend, CairoSurface};
@@ -0,0 +1,117 @@
+//! Vector PDF output for paged documents.
+//!
+//! Draws the pages of a [`PrintedDocument`](gosub_render_pipeline::paged::PrintedDocument) onto a
+//! cairo PDF surface with the same command painters the tile rasterizer uses, so text stays text
+//! (glyphs with embedded fonts) and boxes stay paths. Layer groups are honoured for their opacity
+//! and transform offset; their filters and blend modes are not applied.
+
+use anyhow::anyhow;
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::common::media::MediaStore;
+use gosub_render_pipeline::paged::{PrintedPage, DEFAULT_PAGE_SIZE};
+use gosub_render_pipeline::painter::commands::PaintCommand;
+
+use crate::rasterizer::paint_command;
+
+/// PDF user space is in points (1/72in); CSS px are 1/96in.
+const PT_PER_PX: f64 = 72.0 / 96.0;
+
+/// Resolution multiplier for content cairo cannot keep as vectors (SVG images, blurred shadows).
+const RASTER_SCALE: i32 = 2;
+
+/// Renders `pages` to a PDF document, one PDF page per printed page.
+///
+/// # Errors
+/// Returns an error when cairo fails to create the surface or to write the document.
+pub fn render_pdf(pages: &[PrintedPage], media_store: &MediaStore) -> anyhow::Result<Vec<u8>> {
+    let first = pages.first().map_or(DEFAULT_PAGE_SIZE, |page| page.size);
+    let surface = cairo::PdfSurface::for_stream(first.width * PT_PER_PX, first.height * PT_PER_PX, Vec::<u8>::new())?;
+    surface.set_metadata(cairo::PdfMetadata::Creator, "Gosub")?;
+
+    {
+        let cr = cairo::Context::new(&surface)?;
+        for page in pages {
+            // Each page can have its own size; it must be set before anything is drawn on it.
+            surface.set_size(page.size.width * PT_PER_PX, page.size.height * PT_PER_PX)?;
+            cr.save()?;
+            cr.scale(PT_PER_PX, PT_PER_PX);
+            paint_page(&cr, page, media_store)?;
+            cr.restore()?;
+            cr.show_page()?;
+        }
+    }
+
+    let stream = surface
+        .finish_output_stream()
+        .map_err(|e| anyhow!("failed to write PDF: {}", e.error))?;
+    stream
+        .downcast::<Vec<u8>>()
+        .map(|bytes| *bytes)
+        .map_err(|_| anyhow!("PDF output stream has an unexpected type"))
+}
+
+/// Paints one page: its document slice clipped to the content box, then the margin boxes.
+fn paint_page(cr: &cairo::Context, page: &PrintedPage, media_store: &MediaStore) -> Result<(), cairo::Error> {
+    let content = page.content_box;
+    cr.save()?;
+    cr.rectangle(content.x, content.y, page.slice.width, page.slice.height);
+    cr.clip();
+    cr.translate(content.x, content.y);
+    paint_commands(cr, page.slice, &page.commands, media_store)?;
+    cr.restore()?;
+
+    let page_area = Rect::new(0.0, 0.0, page.size.width, page.size.height);
+    paint_commands(cr, page_area, &page.margin_commands, media_store)
+}
+
+/// Paints `commands` with `area`'s origin at the context origin, turning layer groups into cairo
+/// groups.
+fn paint_commands(
+    cr: &cairo::Context,
+    area: Rect,
+    commands: &[PaintCommand],
+    media_store: &MediaStore,
+) -> Result<(), cairo::Error> {
+    // Per open group: its opacity when it was pushed as a cairo group, and its page-space offset.
+    let mut groups: Vec<(Option<f32>, (f64, f64))> = Vec::new();
+    for command in commands {
+        match command {
+            PaintCommand::PushLayer {
+                opacity,
+                isolated,
+                translate,
+                ..
+            } => {
+                // `translate` includes every enclosing group's, so move by the difference.
+                let (parent_x, parent_y) = groups.last().map_or((0.0, 0.0), |(_, offset)| *offset);
+                cr.save()?;
+                cr.translate(translate.0 - parent_x, translate.1 - parent_y);
+                let grouped = *opacity < 1.0 || *isolated;
+                if grouped {
+                    cr.push_group();
+                }
+                groups.push((grouped.then_some(*opacity), *translate));
+            }
+            PaintCommand::PopLayer => {
+                let Some((opacity, _)) = groups.pop() else {
+                    continue;
+                };
+                if let Some(opacity) = opacity {
+                    cr.pop_group_to_source()?;
+                    cr.paint_with_alpha(f64::from(opacity))?;
+                }
+                cr.restore()?;
+            }
+            _ => paint_command(cr, area, command, media_store, RASTER_SCALE),
+        }
+    }
+    // Close groups left open by an unbalanced list so the caller's save/restore pairs still match.
+    while let Some((opacity, _)) = groups.pop() {
+        if let Some(opacity) = opacity {
+            cr.pop_group_to_source()?;
+            cr.paint_with_alpha(f64::from(opacity))?;
+        }
+        cr.restore()?;
+    }
+    Ok(())
+}
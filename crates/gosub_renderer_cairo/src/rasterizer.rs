@@ -1,8 +1,10 @@
 use cairo;
 use gosub_interface::font_system::FontSystem;
+use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::common::texture::TextureId;
 use gosub_render_pipeline::common::TextureStore;
+use gosub_render_pipeline::painter::commands::filter::{apply_filters, filters_overflow};
 use gosub_render_pipeline::painter::commands::PaintCommand;
 use gosub_render_pipeline::rasterizer::Rasterable;
 use gosub_render_pipeline::tiler::Tile;
@@ -11,6 +13,7 @@ use std::sync::Arc;
 
 mod brush;
 mod rectangle;
+mod shadow;
 mod svg;
 mod text;
 
@@ -57,7 +60,17 @@ impl Rasterable for CairoRasterizer {
         let tile_w = tile.rect.width as i32 * dpr;
         let tile_h = tile.rect.height as i32 * dpr;
 
-        let Ok(mut surface) = cairo::ImageSurface::create(cairo::Format::ARgb32, tile_w, tile_h) else {
+        // A filtered layer needs the content around the tile too (a blur pulls it in, a drop
+        // shadow offsets it), so paint into a surface padded on every side and crop afterwards.
+        let pad = if tile.filters.is_empty() {
+            0
+        } else {
+            let o = filters_overflow(&tile.filters);
+            (o.top.max(o.right).max(o.bottom).max(o.left) * dpr as f64).ceil() as i32
+        };
+
+        let Ok(mut surface) = cairo::ImageSurface::create(cairo::Format::ARgb32, tile_w + 2 * pad, tile_h + 2 * pad)
+        else {
             log::error!("Failed to create Cairo image surface");
             return None;
         };
@@ -69,25 +82,11 @@ impl Rasterable for CairoRasterizer {
             };
             // Scale the context so all CSS-pixel coordinates map to physical pixels.
             cr.scale(dpr as f64, dpr as f64);
+            cr.translate(pad as f64 / dpr as f64, pad as f64 / dpr as f64);
 
             for element in &tile.elements {
                 for command in &element.paint_commands {
-                    match command {
-                        // The tile path applies layer opacity/anchor at composite, so these
-                        // scene-only group markers never appear here - ignore them.
-                        PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => {}
-                        PaintCommand::Svg(command) => {
-                            svg::do_paint_svg(&cr.clone(), tile, &command.rect, command.media_id, media_store, dpr);
-                        }
-                        PaintCommand::Rectangle(command) => {
-                            rectangle::do_paint_rectangle(&cr.clone(), tile, command, media_store);
-                        }
-                        PaintCommand::Text(command) => {
-                            if let Err(e) = text::glyphs::do_paint_text(&cr, tile, command, media_store) {
-                                log::warn!("Failed to paint text: {:?}", e);
-                            }
-                        }
-                    }
+                    paint_command(&cr, tile.rect, command, media_store, dpr);
                 }
             }
 
@@ -96,19 +95,59 @@ impl Rasterable for CairoRasterizer {
 
         let w = surface.width() as usize;
         let h = surface.height() as usize;
+        let stride = surface.stride() as usize;
 
-        let Ok(data) = surface.data() else {
+        let Ok(mut data) = surface.data() else {
             log::error!("Failed to get Cairo surface data");
             return None;
         };
 
-        let texture_id = texture_store.add(
-            w,
-            h,
-            data.to_vec(),
-            gosub_render_pipeline::render::backend::PixelFormat::PreMulArgb32,
-        );
+        let format = gosub_render_pipeline::render::backend::PixelFormat::PreMulArgb32;
+        let (w, h, pixels) = if tile.filters.is_empty() {
+            (w, h, data.to_vec())
+        } else {
+            apply_filters(&mut data, w, h, stride, format, &tile.filters, dpr as f32);
+            // Crop the padding back off, leaving a tightly packed tile.
+            let (pad, tw, th) = (pad as usize, tile_w as usize, tile_h as usize);
+            let mut cropped = Vec::with_capacity(tw * th * 4);
+            for row in data.chunks(stride).skip(pad).take(th) {
+                cropped.extend_from_slice(&row[pad * 4..(pad + tw) * 4]);
+            }
+            (tw, th, cropped)
+        };
+
+        let texture_id = texture_store.add(w, h, pixels, format);
 
         Some(texture_id)
     }
 }
+
+/// Paints one command onto `cr`, whose origin is the page-space point `area.x, area.y`.
+///
+/// The tile path applies layer opacity/anchor at composite, so the scene-only group markers are
+/// ignored here; a caller that honours them (the PDF writer) handles them itself.
+pub(crate) fn paint_command(
+    cr: &cairo::Context,
+    area: Rect,
+    command: &PaintCommand,
+    media_store: &MediaStore,
+    dpr: i32,
+) {
+    match command {
+        PaintCommand::PushLayer { .. } | PaintCommand::PopLayer => {}
+        PaintCommand::Svg(command) => {
+            svg::do_paint_svg(cr, area, &command.rect, command.media_id, media_store, dpr);
+        }
+        PaintCommand::Rectangle(command) => {
+            rectangle::do_paint_rectangle(cr, area, command, media_store);
+        }
+        PaintCommand::Text(command) => {
+            if let Err(e) = text::glyphs::do_paint_text(cr, area, command, media_store) {
+                log::warn!("Failed to paint text: {:?}", e);
+            }
+        }
+        PaintCommand::BoxShadow(command) => {
+            shadow::do_paint_box_shadow(cr, area, command);
+        }
+    }
+}
@@ -2,31 +2,32 @@ use cairo::Context;
 use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::painter::commands::brush::Brush;
-use gosub_render_pipeline::painter::commands::gradient::{Gradient, LinearGradient, Tiling};
+use gosub_render_pipeline::painter::commands::gradient::{Gradient, GradientRaster};
+use gosub_render_pipeline::render::DEVICE_PIXEL_RATIO;
 
 pub fn set_brush(cr: &Context, brush: &Brush, rect: Rect, media_store: &MediaStore) {
     match brush {
         Brush::Solid(color) => {
             cr.set_source_rgba(color.r() as f64, color.g() as f64, color.b() as f64, color.a() as f64);
         }
-        Brush::Gradient(Gradient::Linear(g)) => {
+        Brush::Gradient(g) => {
             if rect.width == 0.0 || rect.height == 0.0 {
                 return;
             }
-            // Tiled `background-image` layer (repeated `background-size` cell): rasterize one
-            // tile and paint it as a repeating pattern rather than filling the whole box.
-            if let Some(tiling) = &g.tiling {
-                set_tiled_gradient(cr, g, tiling, rect);
+            let (w, h) = (rect.width as f32, rect.height as f32);
+            let Some((lg, stops)) = g.as_native_linear(w, h) else {
+                // Radial, conic, repeating, tiled or non-sRGB: paint the shared raster instead.
+                set_gradient_raster(cr, g, rect);
                 return;
-            }
-            let ((x0, y0), (x1, y1)) = g.line(rect.width as f32, rect.height as f32);
+            };
+            let ((x0, y0), (x1, y1)) = lg.line(w, h);
             let pattern = cairo::LinearGradient::new(
                 rect.x + x0 as f64,
                 rect.y + y0 as f64,
                 rect.x + x1 as f64,
                 rect.y + y1 as f64,
             );
-            for stop in &g.stops {
+            for stop in &stops {
                 pattern.add_color_stop_rgba(
                     stop.offset as f64,
                     stop.color.r() as f64,
@@ -121,25 +122,36 @@ pub fn set_brush(cr: &Context, brush: &Brush, rect: Rect, media_store: &MediaSto
     }
 }
 
-/// Rasterize one `background-size` tile and install it as a repeating pattern offset by
-/// `background-position`. The caller's already-built fill path clips it to the element box.
-fn set_tiled_gradient(cr: &Context, g: &LinearGradient, tiling: &Tiling, rect: Rect) {
-    let tw = (tiling.tile_size.0.round() as i32).max(1);
-    let th = (tiling.tile_size.1.round() as i32).max(1);
+/// Rasterizes the gradient (one `background-size` tile when tiled, else the whole box) at device
+/// resolution and installs it as a pattern anchored at `background-position`. The caller's
+/// already-built fill path clips it to the element box.
+fn set_gradient_raster(cr: &Context, g: &Gradient, rect: Rect) {
+    let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed) as f32;
+    let Some(GradientRaster {
+        pixels,
+        width,
+        height,
+        size,
+        position,
+        repeat,
+    }) = g.raster(rect.width as f32, rect.height as f32, dpr)
+    else {
+        return;
+    };
+    let (tw, th) = (width as i32, height as i32);
 
-    // Straight-alpha RGBA tile → premultiplied ARGB32 (host byte order: BGRA on little-endian).
-    let rgba = g.rasterize_tile(tw as u32, th as u32);
-    let stride = cairo::Format::ARgb32.stride_for_width(tw as u32).unwrap_or(tw * 4);
+    // Straight-alpha RGBA → premultiplied ARGB32 (host byte order: BGRA on little-endian).
+    let stride = cairo::Format::ARgb32.stride_for_width(width).unwrap_or(tw * 4);
     let mut data = vec![0u8; (stride * th) as usize];
     for row in 0..th as usize {
         for col in 0..tw as usize {
             let si = (row * tw as usize + col) * 4;
             let di = row * stride as usize + col * 4;
             let (r, gg, b, a) = (
-                rgba[si] as u32,
-                rgba[si + 1] as u32,
-                rgba[si + 2] as u32,
-                rgba[si + 3] as u32,
+                pixels[si] as u32,
+                pixels[si + 1] as u32,
+                pixels[si + 2] as u32,
+                pixels[si + 3] as u32,
             );
             data[di] = (b * a / 255) as u8;
             data[di + 1] = (gg * a / 255) as u8;
@@ -151,25 +163,30 @@ fn set_tiled_gradient(cr: &Context, g: &LinearGradient, tiling: &Tiling, rect: R
     match cairo::ImageSurface::create_for_data(data, cairo::Format::ARgb32, tw, th, stride) {
         Ok(surface) => {
             let pattern = cairo::SurfacePattern::create(&surface);
-            // Nearest keeps the hard tile edges crisp and avoids bleeding across the wrap seam.
-            pattern.set_filter(cairo::Filter::Nearest);
-            // Cairo's surface extend is 2D; honour full-repeat (the default) and no-repeat.
-            // Single-axis repeat (rare) approximates to full repeat.
-            let extend = if tiling.repeat.0 || tiling.repeat.1 {
-                cairo::Extend::Repeat
+            if repeat.0 || repeat.1 {
+                // Nearest keeps hard tile edges crisp and avoids bleeding across the wrap seam.
+                // Cairo's surface extend is 2D; single-axis repeat (rare) approximates to full.
+                pattern.set_filter(cairo::Filter::Nearest);
+                pattern.set_extend(cairo::Extend::Repeat);
             } else {
-                cairo::Extend::None
-            };
-            pattern.set_extend(extend);
-            // The pattern matrix maps user space → pattern (tile) space, so anchoring the tile
-            // origin at (rect + position) is expressed as the inverse translation.
-            let ox = rect.x + tiling.position.0 as f64;
-            let oy = rect.y + tiling.position.1 as f64;
-            pattern.set_matrix(cairo::Matrix::new(1.0, 0.0, 0.0, 1.0, -ox, -oy));
+                pattern.set_filter(cairo::Filter::Bilinear);
+                pattern.set_extend(if g.tiling().is_some() {
+                    cairo::Extend::None
+                } else {
+                    cairo::Extend::Pad
+                });
+            }
+            // The pattern matrix maps user space → pattern (raster pixel) space, so the
+            // translation is expressed in pattern units (pre-scaled by sx/sy).
+            let sx = width as f64 / size.0 as f64;
+            let sy = height as f64 / size.1 as f64;
+            let ox = rect.x + position.0 as f64;
+            let oy = rect.y + position.1 as f64;
+            pattern.set_matrix(cairo::Matrix::new(sx, 0.0, 0.0, sy, -ox * sx, -oy * sy));
             if let Err(e) = cr.set_source(&pattern) {
-                log::warn!("Failed to set Cairo tiled-gradient source: {e:?}");
+                log::warn!("Failed to set Cairo gradient raster source: {e:?}");
             }
         }
-        Err(e) => log::warn!("Failed to create Cairo gradient tile surface: {e:?}"),
+        Err(e) => log::warn!("Failed to create Cairo gradient surface: {e:?}"),
     }
 }
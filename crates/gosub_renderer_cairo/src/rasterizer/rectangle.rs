@@ -1,9 +1,9 @@
 use crate::rasterizer::brush::set_brush;
 use cairo::{Context, Operator};
+use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::painter::commands::border::BorderStyle;
 use gosub_render_pipeline::painter::commands::rectangle::{BlendMode, Rectangle};
-use gosub_render_pipeline::tiler::Tile;
 
 /// CSS `mix-blend-mode` → cairo compositing operator. The operator blends against the tile
 /// surface content already painted beneath the element.
@@ -28,7 +28,7 @@ fn to_cairo_operator(mode: BlendMode) -> Operator {
     }
 }
 
-pub(crate) fn do_paint_rectangle(cr: &Context, tile: &Tile, rectangle: &Rectangle, media_store: &MediaStore) {
+pub(crate) fn do_paint_rectangle(cr: &Context, area: Rect, rectangle: &Rectangle, media_store: &MediaStore) {
     _ = cr.save();
 
     // Element-level mix-blend-mode; cr.restore() at the end returns the operator to Over.
@@ -36,13 +36,13 @@ pub(crate) fn do_paint_rectangle(cr: &Context, tile: &Tile, rectangle: &Rectangl
         cr.set_operator(to_cairo_operator(rectangle.blend_mode()));
     }
 
-    // Translate so the tile origin maps to the surface origin.
+    // Translate so the origin of `area` (the tile) maps to the surface origin.
     // No explicit clip: Cairo's image surface boundary clips to exact pixel boundaries
     // without anti-aliasing, preventing the semi-transparent edge pixels that the
     // old cr.clip() produced and caused visible seams at tile borders.
     // cr.clip() also cleared the current path; replace that with an explicit new_path()
     // so setup_rectangle_path always starts from a clean slate.
-    cr.translate(-tile.rect.x, -tile.rect.y);
+    cr.translate(-area.x, -area.y);
     cr.new_path();
 
     if let Some(brush) = rectangle.background() {
@@ -151,7 +151,7 @@ fn paint_per_side_border(cr: &Context, rectangle: &Rectangle, media_store: &Medi
     }
 }
 
-fn setup_rectangle_path(cr: &Context, rect: &Rectangle) {
+pub(crate) fn setup_rectangle_path(cr: &Context, rect: &Rectangle) {
     let (r_tl, r_tr, r_br, r_bl) = rect.radius_x();
 
     if r_tl == 0.0 && r_tr == 0.0 && r_br == 0.0 && r_bl == 0.0 {
@@ -0,0 +1,121 @@
+use crate::rasterizer::rectangle::setup_rectangle_path;
+use cairo::{Context, Error, FillRule, ImageSurface};
+use gosub_render_pipeline::common::geo::Rect;
+use gosub_render_pipeline::painter::commands::color::Color;
+use gosub_render_pipeline::painter::commands::filter::blur_premultiplied;
+use gosub_render_pipeline::painter::commands::shadow::BoxShadow;
+use gosub_render_pipeline::render::DEVICE_PIXEL_RATIO;
+
+/// Grows `r` by `by` on every side.
+pub(crate) fn outset(r: Rect, by: f64) -> Rect {
+    Rect::new(r.x - by, r.y - by, r.width + 2.0 * by, r.height + 2.0 * by)
+}
+
+fn intersect(a: Rect, b: Rect) -> Option<Rect> {
+    let x0 = a.x.max(b.x);
+    let y0 = a.y.max(b.y);
+    let x1 = (a.x + a.width).min(b.x + b.width);
+    let y1 = (a.y + a.height).min(b.y + b.height);
+    (x1 > x0 && y1 > y0).then(|| Rect::new(x0, y0, x1 - x0, y1 - y0))
+}
+
+pub(crate) fn set_color(cr: &Context, color: &Color) {
+    cr.set_source_rgba(color.r() as f64, color.g() as f64, color.b() as f64, color.a() as f64);
+}
+
+/// Paints whatever `draw` produces through a Gaussian blur of `sigma` CSS px. `draw` gets a
+/// context in page coordinates on an offscreen surface covering `area`; `cr` must already map
+/// page coordinates onto its surface.
+pub(crate) fn paint_blurred(
+    cr: &Context,
+    area: Rect,
+    sigma: f32,
+    draw: impl Fn(&Context) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed) as f64;
+    let w = (area.width * dpr).ceil() as i32;
+    let h = (area.height * dpr).ceil() as i32;
+    if w <= 0 || h <= 0 {
+        return Ok(());
+    }
+
+    let mut surface = ImageSurface::create(cairo::Format::ARgb32, w, h)?;
+    surface.set_device_scale(dpr, dpr);
+    {
+        let offscreen = Context::new(&surface)?;
+        offscreen.translate(-area.x, -area.y);
+        draw(&offscreen)?;
+    }
+    surface.flush();
+
+    let stride = surface.stride() as usize;
+    match surface.data() {
+        Ok(mut data) => blur_premultiplied(&mut data, w as usize, h as usize, stride, sigma * dpr as f32),
+        Err(e) => log::warn!("Failed to access shadow surface data: {:?}", e),
+    }
+
+    cr.set_source_surface(&surface, area.x, area.y)?;
+    cr.paint()
+}
+
+/// Paints one `box-shadow` layer: outer shadows only outside the border box, inset shadows only
+/// inside the padding box.
+pub(crate) fn do_paint_box_shadow(cr: &Context, area: Rect, cmd: &BoxShadow) {
+    let color = &cmd.shadow.color;
+    if color.a() <= 0.0 {
+        return;
+    }
+
+    let extent = cmd.shadow.blur_extent();
+    let inset = cmd.shadow.inset;
+    let clip = cmd.clip();
+    let shape = cmd.shape();
+
+    // Only the part near the painted area matters; the blur reads up to `extent` past it.
+    let reach = if inset {
+        outset(clip.rect(), extent)
+    } else {
+        cmd.ink_rect()
+    };
+    let Some(shadow_area) = intersect(reach, outset(area, extent)) else {
+        return;
+    };
+
+    _ = cr.save();
+    cr.new_path();
+    cr.translate(-area.x, -area.y);
+
+    if inset {
+        setup_rectangle_path(cr, &clip);
+    } else {
+        cr.set_fill_rule(FillRule::EvenOdd);
+        cr.rectangle(shadow_area.x, shadow_area.y, shadow_area.width, shadow_area.height);
+        setup_rectangle_path(cr, &clip);
+    }
+    cr.clip();
+    cr.set_fill_rule(FillRule::Winding);
+
+    // An inset shadow is the blurred area *around* the shape, cast inwards.
+    let draw = |c: &Context| -> Result<(), Error> {
+        set_color(c, color);
+        c.new_path();
+        if inset {
+            c.set_fill_rule(FillRule::EvenOdd);
+            c.rectangle(shadow_area.x, shadow_area.y, shadow_area.width, shadow_area.height);
+        }
+        setup_rectangle_path(c, &shape);
+        c.fill()
+    };
+
+    let sigma = cmd.shadow.sigma();
+    let res = if sigma < 0.5 {
+        draw(cr)
+    } else {
+        paint_blurred(cr, shadow_area, sigma, draw)
+    };
+    if let Err(e) = res {
+        log::warn!("Failed to paint box shadow: {:?}", e);
+    }
+
+    _ = cr.restore();
+}
@@ -1,14 +1,13 @@
 use cairo::Context;
-use gosub_render_pipeline::common::geo::Dimension;
+use gosub_render_pipeline::common::geo::{Dimension, Rect};
 use gosub_render_pipeline::common::media::{MediaId, MediaStore};
 use gosub_render_pipeline::painter::commands::rectangle::Rectangle;
 use gosub_render_pipeline::render::backend::PixelFormat;
-use gosub_render_pipeline::tiler::Tile;
 use resvg::usvg::Transform;
 
 pub(crate) fn do_paint_svg(
     cr: &Context,
-    tile: &Tile,
+    area: Rect,
     rect: &Rectangle,
     media_id: MediaId,
     media_store: &MediaStore,
@@ -23,8 +22,8 @@ pub(crate) fn do_paint_svg(
     // integer CSS coordinate maps to an integer device pixel - keeping the glyph/icon on the
     // device grid. Without this the surface lands on a fractional device pixel (very visible
     // at dpr ≥ 2) and looks soft and shifted.
-    let dest_x = (rect.rect().x - tile.rect.x).round();
-    let dest_y = (rect.rect().y - tile.rect.y).round();
+    let dest_x = (rect.rect().x - area.x).round();
+    let dest_y = (rect.rect().y - area.y).round();
 
     // Rasterize at physical resolution (CSS size × dpr) so the icon is crisp instead of being
     // upscaled from CSS-pixel resolution by the dpr-scaled context. `set_device_scale(dpr)`
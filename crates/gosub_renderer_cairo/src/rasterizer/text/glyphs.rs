@@ -7,10 +7,12 @@
 //! fonts may still fall back to monochrome outlines depending on the cairo version.
 
 use crate::rasterizer::brush::set_brush;
+use crate::rasterizer::shadow::{outset, paint_blurred, set_color};
 use cairo::{Antialias, Context, Error, FontOptions, Glyph, HintMetrics, HintStyle};
+use gosub_interface::font_system::{GlyphRotation, ShapedRun};
+use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::painter::commands::text::Text;
-use gosub_render_pipeline::tiler::Tile;
 use std::collections::HashMap;
 use std::rc::Rc;
 
@@ -83,11 +85,10 @@ fn cairo_face_for(blob: &gosub_interface::font::FontBlob) -> Option<cairo::FontF
     cache.faces.get(&key).and_then(|e| e.as_ref().map(|(_, ff)| ff.clone()))
 }
 
-pub(crate) fn do_paint_text(cr: &Context, tile: &Tile, cmd: &Text, media_store: &MediaStore) -> Result<(), Error> {
+pub(crate) fn do_paint_text(cr: &Context, area: Rect, cmd: &Text, media_store: &MediaStore) -> Result<(), Error> {
     // Shaping happened once at paint-command build time (the pipeline Painter, with the same
     // font system the layouter measured with); this function only paints the glyph runs.
-    let shaped = &cmd.shaped;
-    if shaped.is_empty() {
+    if cmd.shaped.is_empty() {
         return Ok(());
     }
 
@@ -97,8 +98,40 @@ pub(crate) fn do_paint_text(cr: &Context, tile: &Tile, cmd: &Text, media_store:
     // be included in our decoration `fill()`, painting its whole rect in the text colour.
     cr.new_path();
     // Map page coordinates onto the tile; the context's existing scale handles DPR.
-    cr.translate(-tile.rect.x, -tile.rect.y);
+    cr.translate(-area.x, -area.y);
+    set_font_options(cr);
 
+    // Shadows paint beneath the glyphs, the first listed on top.
+    for shadow in cmd.shadows.iter().rev() {
+        if shadow.color.a() <= 0.0 {
+            continue;
+        }
+        let (dx, dy) = (shadow.offset_x as f64, shadow.offset_y as f64);
+        let sigma = shadow.sigma();
+        if sigma < 0.5 {
+            set_color(cr, &shadow.color);
+            show_runs(cr, cmd, dx, dy)?;
+            continue;
+        }
+        // Glyphs may overhang the line box, so leave some room beyond the blur reach.
+        let slack = shadow.blur_extent() + cmd.font_info.size / 2.0;
+        let r = cmd.rect;
+        let shadow_area = outset(Rect::new(r.x + dx, r.y + dy, r.width, r.height), slack);
+        paint_blurred(cr, shadow_area, sigma, |c| {
+            set_font_options(c);
+            set_color(c, &shadow.color);
+            show_runs(c, cmd, dx, dy)
+        })?;
+    }
+
+    set_brush(cr, &cmd.brush, cmd.rect, media_store);
+    show_runs(cr, cmd, 0.0, 0.0)?;
+
+    cr.restore()?;
+    Ok(())
+}
+
+fn set_font_options(cr: &Context) {
     if let Ok(mut font_opts) = FontOptions::new() {
         font_opts.set_antialias(Antialias::Gray);
         // Match the Pango-native path: slight hinting nudges stems toward the pixel grid for
@@ -107,50 +140,63 @@ pub(crate) fn do_paint_text(cr: &Context, tile: &Tile, cmd: &Text, media_store:
         font_opts.set_hint_metrics(HintMetrics::On);
         cr.set_font_options(&font_opts);
     }
-    set_brush(cr, &cmd.brush, cmd.rect, media_store);
+}
 
-    for run in &shaped.runs {
-        let Some(face) = cairo_face_for(&run.font.blob) else {
+/// Paints every glyph run plus its decorations with the current source, shifted by `(dx, dy)`.
+fn show_runs(cr: &Context, cmd: &Text, dx: f64, dy: f64) -> Result<(), Error> {
+    let x = cmd.rect.x + dx;
+    let y = cmd.rect.y + dy;
+    for run in &cmd.shaped.runs {
+        // Sideways runs in vertical text are laid out in a frame turned about the block's origin.
+        if run.rotation == GlyphRotation::None {
+            show_run(cr, cmd, run, x, y)?;
             continue;
-        };
-        cr.set_font_face(&face);
-        cr.set_font_size(run.font_size as f64);
-
-        let glyphs: Vec<Glyph> = run
-            .glyphs
-            .iter()
-            .filter(|g| g.id & PANGO_GLYPH_UNKNOWN_FLAG == 0)
-            .map(|g| {
-                Glyph::new(
-                    g.id as std::os::raw::c_ulong,
-                    cmd.rect.x + g.x as f64,
-                    cmd.rect.y + g.y as f64,
-                )
-            })
-            .collect();
-        if !glyphs.is_empty() {
-            cr.show_glyphs(&glyphs)?;
         }
+        cr.save()?;
+        cr.translate(x, y);
+        cr.rotate(run.rotation.radians());
+        cr.translate(-x, -y);
+        let shown = show_run(cr, cmd, run, x, y);
+        cr.restore()?;
+        shown?;
+    }
+    Ok(())
+}
 
-        // Text decorations: a filled rect per run, using the run font's own metrics.
-        let decoration = |offset: f32, size: f32| -> Result<(), Error> {
-            cr.rectangle(
-                cmd.rect.x + run.x as f64,
-                cmd.rect.y + (run.baseline + offset) as f64,
-                run.width as f64,
-                size.max(1.0) as f64,
-            );
-            cr.fill()
-        };
-        if cmd.font_info.underline {
-            decoration(run.metrics.underline_offset, run.metrics.underline_size)?;
-        }
-        if cmd.font_info.line_through {
-            decoration(run.metrics.strikethrough_offset, run.metrics.strikethrough_size)?;
-        }
+/// Paints one glyph run plus its decorations, with the block's origin at `(x, y)`.
+fn show_run(cr: &Context, cmd: &Text, run: &ShapedRun, x: f64, y: f64) -> Result<(), Error> {
+    let Some(face) = cairo_face_for(&run.font.blob) else {
+        return Ok(());
+    };
+    cr.set_font_face(&face);
+    cr.set_font_size(run.font_size as f64);
+
+    let glyphs: Vec<Glyph> = run
+        .glyphs
+        .iter()
+        .filter(|g| g.id & PANGO_GLYPH_UNKNOWN_FLAG == 0)
+        .map(|g| Glyph::new(g.id as std::os::raw::c_ulong, x + g.x as f64, y + g.y as f64))
+        .collect();
+    if !glyphs.is_empty() {
+        cr.show_glyphs(&glyphs)?;
     }
 
-    cr.restore()?;
+    // Text decorations: a filled rect per run, using the run font's own metrics.
+    let decoration = |offset: f32, size: f32| -> Result<(), Error> {
+        cr.rectangle(
+            x + run.x as f64,
+            y + (run.baseline + offset) as f64,
+            run.width as f64,
+            size.max(1.0) as f64,
+        );
+        cr.fill()
+    };
+    if cmd.font_info.underline {
+        decoration(run.metrics.underline_offset, run.metrics.underline_size)?;
+    }
+    if cmd.font_info.line_through {
+        decoration(run.metrics.strikethrough_offset, run.metrics.strikethrough_size)?;
+    }
     Ok(())
 }
 
@@ -159,7 +205,7 @@ mod tests {
     use super::*;
     use gosub_fontmanager::PangoFontSystem;
     use gosub_interface::font_system::{FontSystem, TextStyle};
-    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo};
+    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo, FontShaping};
     use gosub_render_pipeline::common::geo::Rect as GeoRect;
     use gosub_render_pipeline::painter::commands::brush::Brush;
     use gosub_render_pipeline::painter::commands::color::Color;
@@ -190,6 +236,7 @@ mod tests {
             alignment: FontAlignment::Start,
             underline: true,
             line_through: false,
+            shaping: FontShaping::default(),
         };
         let mut style = TextStyle::new("sans-serif", 24.0);
         style.line_height = Some(28.0);
@@ -203,18 +250,8 @@ mod tests {
             180.0,
             shaped,
         );
-        let tile = Tile {
-            id: gosub_render_pipeline::tiler::TileId::new(0),
-            layer_id: gosub_render_pipeline::layering::layer::LayerId::new(0),
-            elements: Vec::new(),
-            texture_id: None,
-            state: gosub_render_pipeline::tiler::TileState::Dirty,
-            rect: GeoRect::new(0.0, 0.0, 200.0, 60.0),
-            bgcolor: None,
-        };
-
         let media_store = MediaStore::new();
-        let res = do_paint_text(&cr, &tile, &cmd, &media_store);
+        let res = do_paint_text(&cr, GeoRect::new(0.0, 0.0, 200.0, 60.0), &cmd, &media_store);
         assert!(res.is_ok(), "painting failed: {res:?}");
 
         drop(cr);
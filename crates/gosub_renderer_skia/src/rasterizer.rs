@@ -3,6 +3,7 @@ use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::common::texture::TextureId;
 use gosub_render_pipeline::common::TextureStore;
 use gosub_render_pipeline::layering::layer::LayerId;
+use gosub_render_pipeline::painter::commands::filter::{apply_filters, filters_overflow};
 use gosub_render_pipeline::painter::commands::PaintCommand;
 use gosub_render_pipeline::rasterizer::Rasterable;
 use gosub_render_pipeline::render::DEVICE_PIXEL_RATIO;
@@ -12,6 +13,7 @@ use skia_safe::Rect;
 use std::sync::Arc;
 
 mod rectangle;
+mod shadow;
 mod svg;
 mod text;
 
@@ -52,6 +54,15 @@ impl Rasterable for SkiaRasterizer {
         let width = tile.rect.width as u32 * dpr;
         let height = tile.rect.height as u32 * dpr;
 
+        // A filtered layer needs the content around the tile too (a blur pulls it in, a drop
+        // shadow offsets it), so paint into a surface padded on every side and crop afterwards.
+        let pad = if tile.filters.is_empty() {
+            0
+        } else {
+            let o = filters_overflow(&tile.filters);
+            (o.top.max(o.right).max(o.bottom).max(o.left) * dpr as f64).ceil() as u32
+        };
+
         if tile.layer_id != LayerId::new(0) && tile.elements.is_empty() {
             return None;
         }
@@ -60,7 +71,7 @@ impl Rasterable for SkiaRasterizer {
         // linear light, the way browsers do. Without a colour space Skia blends in gamma-encoded
         // space, which fattens glyph edges and makes text look heavier than Firefox.
         let info = skia_safe::ImageInfo::new(
-            skia_safe::ISize::new(width as i32, height as i32),
+            skia_safe::ISize::new((width + 2 * pad) as i32, (height + 2 * pad) as i32),
             skia_safe::ColorType::BGRA8888,
             skia_safe::AlphaType::Premul,
             Some(skia_safe::ColorSpace::new_srgb()),
@@ -82,8 +93,15 @@ impl Rasterable for SkiaRasterizer {
 
         // Draw in CSS coordinates scaled up to physical pixels, so commands stay DPR-agnostic.
         canvas.scale((dpr as f32, dpr as f32));
+        let pad_css = pad as f32 / dpr as f32;
+        canvas.translate((pad_css, pad_css));
         canvas.clip_rect(
-            Rect::new(0.0, 0.0, tile.rect.width as f32, tile.rect.height as f32),
+            Rect::new(
+                -pad_css,
+                -pad_css,
+                tile.rect.width as f32 + pad_css,
+                tile.rect.height as f32 + pad_css,
+            ),
             None,
             None,
         );
@@ -104,6 +122,9 @@ impl Rasterable for SkiaRasterizer {
                     PaintCommand::Svg(command) => {
                         svg::do_paint_svg(canvas, tile, command.media_id, &command.rect, media_store, dpr as i32);
                     }
+                    PaintCommand::BoxShadow(command) => {
+                        shadow::do_paint_box_shadow(canvas, command);
+                    }
                 }
             }
         }
@@ -116,7 +137,32 @@ impl Rasterable for SkiaRasterizer {
             log::error!("Failed to get bytes from Skia pixel info");
             return None;
         };
-        let pixels = bytes.to_vec();
+        let mut pixels = bytes.to_vec();
+
+        if !tile.filters.is_empty() {
+            let (w, h, stride) = (
+                (width + 2 * pad) as usize,
+                (height + 2 * pad) as usize,
+                peek.row_bytes(),
+            );
+            apply_filters(
+                &mut pixels,
+                w,
+                h,
+                stride,
+                gosub_render_pipeline::render::backend::PixelFormat::PreMulArgb32,
+                &tile.filters,
+                dpr as f32,
+            );
+            // Crop the padding back off, leaving a tightly packed tile.
+            let (pad, tw) = (pad as usize, width as usize);
+            pixels = pixels
+                .chunks(stride)
+                .skip(pad)
+                .take(height as usize)
+                .flat_map(|row| row[pad * 4..(pad + tw) * 4].iter().copied())
+                .collect();
+        }
 
         let texture_id = texture_store.add(
             width as usize,
@@ -1,8 +1,9 @@
 use gosub_render_pipeline::common::media::{MediaId, MediaStore};
 use gosub_render_pipeline::painter::commands::border::BorderStyle;
 use gosub_render_pipeline::painter::commands::brush::Brush;
-use gosub_render_pipeline::painter::commands::gradient::{Gradient, LinearGradient, Tiling};
+use gosub_render_pipeline::painter::commands::gradient::{ColorStop, Gradient, LinearGradient, Tiling};
 use gosub_render_pipeline::painter::commands::rectangle::{BlendMode as CssBlendMode, Rectangle};
+use gosub_render_pipeline::render::DEVICE_PIXEL_RATIO;
 use gosub_render_pipeline::tiler::Tile;
 use skia_safe::gradient::{shaders, Colors as GradientColors, Gradient as SkGradient, Interpolation};
 use skia_safe::{
@@ -53,12 +54,11 @@ pub fn do_paint_rectangle(canvas: &Canvas, _tile: &Tile, cmd: &Rectangle, media_
             let mut paint = Paint::new(brush_to_color4f(brush), None);
             paint.set_anti_alias(true);
             paint.set_blend_mode(to_skia_blend_mode(cmd.blend_mode()));
-            if let Brush::Gradient(Gradient::Linear(g)) = brush {
-                match &g.tiling {
-                    Some(tiling) => apply_tiled_gradient(&mut paint, g, tiling, r.x as f32, r.y as f32),
-                    None => {
-                        apply_linear_gradient(&mut paint, g, r.x as f32, r.y as f32, r.width as f32, r.height as f32)
-                    }
+            if let Brush::Gradient(g) = brush {
+                let (x, y, w, h) = (r.x as f32, r.y as f32, r.width as f32, r.height as f32);
+                match g.as_native_linear(w, h) {
+                    Some((lg, stops)) => apply_linear_gradient(&mut paint, lg, &stops, x, y, w, h),
+                    None => apply_gradient_raster(&mut paint, g, x, y, w, h),
                 }
             }
             draw_rect_or_rounded(
@@ -135,7 +135,7 @@ fn paint_per_side_border(canvas: &Canvas, cmd: &Rectangle) {
 
 /// `radius_x`/`radius_y` yield corners in CSS order (top-left, top-right, bottom-right,
 /// bottom-left), which is also the order Skia's radii array expects.
-fn rounded_rect(cmd: &Rectangle, rect: Rect) -> RRect {
+pub(crate) fn rounded_rect(cmd: &Rectangle, rect: Rect) -> RRect {
     let (x_tl, x_tr, x_br, x_bl) = cmd.radius_x();
     let (y_tl, y_tr, y_br, y_bl) = cmd.radius_y();
     RRect::new_rect_radii(
@@ -243,17 +243,16 @@ fn brush_to_color4f(brush: &Brush) -> Color4f {
 
 /// Install a linear-gradient shader on `paint` for a box at `(x, y)` of size `w`×`h`.
 /// Falls back to leaving the paint's solid colour when the shader can't be built.
-fn apply_linear_gradient(paint: &mut Paint, g: &LinearGradient, x: f32, y: f32, w: f32, h: f32) {
-    if g.stops.is_empty() {
+fn apply_linear_gradient(paint: &mut Paint, g: &LinearGradient, stops: &[ColorStop], x: f32, y: f32, w: f32, h: f32) {
+    if stops.is_empty() {
         return;
     }
     let ((x0, y0), (x1, y1)) = g.line(w, h);
-    let colors: Vec<Color4f> = g
-        .stops
+    let colors: Vec<Color4f> = stops
         .iter()
         .map(|s| Color::from_argb(s.color.a8(), s.color.r8(), s.color.g8(), s.color.b8()).into())
         .collect();
-    let positions: Vec<f32> = g.stops.iter().map(|s| s.offset).collect();
+    let positions: Vec<f32> = stops.iter().map(|s| s.offset).collect();
 
     let gradient = SkGradient::new(
         GradientColors::new(colors.as_slice(), Some(positions.as_slice()), TileMode::Clamp, None),
@@ -269,28 +268,43 @@ fn apply_linear_gradient(paint: &mut Paint, g: &LinearGradient, x: f32, y: f32,
     }
 }
 
-/// Install a repeating image shader for a tiled `background-image` gradient layer: rasterize
-/// one `background-size` tile and tile it in 2D, offset by `background-position`. The box at
-/// `(x, y)` is filled by the shader (the caller draws the rect).
-fn apply_tiled_gradient(paint: &mut Paint, g: &LinearGradient, tiling: &Tiling, x: f32, y: f32) {
-    let tw = (tiling.tile_size.0.round() as i32).max(1);
-    let th = (tiling.tile_size.1.round() as i32).max(1);
-
-    let rgba = g.rasterize_tile(tw as u32, th as u32);
+/// Install an image shader for a gradient Skia can't draw natively (radial, conic, repeating,
+/// tiled or non-sRGB): rasterize it once at device resolution (one `background-size` tile when
+/// tiled) and scale it onto the box at `(x, y)`, offset by `background-position`. The caller
+/// draws the rect the shader fills.
+fn apply_gradient_raster(paint: &mut Paint, g: &Gradient, x: f32, y: f32, w: f32, h: f32) {
+    let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed) as f32;
+    let Some(raster) = g.raster(w, h, dpr) else {
+        return;
+    };
+    let (tw, th) = (raster.width as i32, raster.height as i32);
     let info = ImageInfo::new(ISize::new(tw, th), ColorType::RGBA8888, AlphaType::Unpremul, None);
     let row_bytes = tw as usize * 4;
-    let Some(image) = images::raster_from_data(&info, Data::new_copy(&rgba), row_bytes) else {
-        log::warn!("Failed to build Skia gradient tile image");
+    let Some(image) = images::raster_from_data(&info, Data::new_copy(&raster.pixels), row_bytes) else {
+        log::warn!("Failed to build Skia gradient raster image");
         return;
     };
 
-    // Full-repeat (the default) tiles both axes; no-repeat clamps to the single tile. Skia
-    // tile modes are per-axis, so honour each independently.
-    let mode = |repeat: bool| if repeat { TileMode::Repeat } else { TileMode::Decal };
-    let tile_modes = (mode(tiling.repeat.0), mode(tiling.repeat.1));
-    let local = Matrix::translate((x + tiling.position.0, y + tiling.position.1));
-    // Nearest keeps the tile edges crisp and avoids bleeding across the repeat seam.
-    let sampling = SamplingOptions::new(FilterMode::Nearest, MipmapMode::None);
+    // Skia tile modes are per-axis, so honour each repeat independently. An untiled raster
+    // covers the whole box and clamps at its edges.
+    let tiled = g.tiling().is_some();
+    let mode = |repeat: bool| match (repeat, tiled) {
+        (true, _) => TileMode::Repeat,
+        (false, true) => TileMode::Decal,
+        (false, false) => TileMode::Clamp,
+    };
+    let tile_modes = (mode(raster.repeat.0), mode(raster.repeat.1));
+    let mut local = Matrix::translate((x + raster.position.0, y + raster.position.1));
+    local.pre_scale(
+        (
+            raster.size.0 / raster.width as f32,
+            raster.size.1 / raster.height as f32,
+        ),
+        None,
+    );
+    // Nearest keeps repeated tile edges crisp and avoids bleeding across the repeat seam.
+    let filter = if tiled { FilterMode::Nearest } else { FilterMode::Linear };
+    let sampling = SamplingOptions::new(filter, MipmapMode::None);
     if let Some(shader) = image.to_shader(tile_modes, sampling, Some(&local)) {
         paint.set_shader(shader);
     }
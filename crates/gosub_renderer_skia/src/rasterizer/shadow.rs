@@ -0,0 +1,52 @@
+use crate::rasterizer::rectangle::rounded_rect;
+use gosub_render_pipeline::painter::commands::rectangle::Rectangle;
+use gosub_render_pipeline::painter::commands::shadow::{BoxShadow, Shadow};
+use skia_safe::{BlurStyle, Canvas, ClipOp, Color4f, MaskFilter, Paint, RRect, Rect};
+
+fn sk_rrect(r: &Rectangle) -> RRect {
+    let rect = r.rect();
+    rounded_rect(
+        r,
+        Rect::from_xywh(rect.x as f32, rect.y as f32, rect.width as f32, rect.height as f32),
+    )
+}
+
+/// A fill paint in the shadow colour, blurred by the shadow's sigma. The mask filter respects
+/// the canvas transform, so sigma stays in CSS px at any DPR.
+pub fn shadow_paint(shadow: &Shadow) -> Paint {
+    let c = &shadow.color;
+    let mut paint = Paint::new(Color4f::new(c.r(), c.g(), c.b(), c.a()), None);
+    paint.set_anti_alias(true);
+    let sigma = shadow.sigma();
+    if sigma >= 0.5 {
+        paint.set_mask_filter(MaskFilter::blur(BlurStyle::Normal, sigma, None));
+    }
+    paint
+}
+
+/// Paints one `box-shadow` layer: outer shadows only outside the border box, inset shadows only
+/// inside the padding box.
+pub fn do_paint_box_shadow(canvas: &Canvas, cmd: &BoxShadow) {
+    if cmd.shadow.color.a() <= 0.0 {
+        return;
+    }
+    let paint = shadow_paint(&cmd.shadow);
+    let clip = sk_rrect(&cmd.clip());
+    let shape = sk_rrect(&cmd.shape());
+
+    canvas.save();
+    if cmd.shadow.inset {
+        canvas.clip_rrect(clip, ClipOp::Intersect, true);
+        // The inset shadow is everything around the shape, so fill a ring reaching past the
+        // blur on every side of both boxes.
+        let mut outer = *clip.rect();
+        outer.join(shape.rect());
+        let extent = cmd.shadow.blur_extent() as f32 + 1.0;
+        let outer = RRect::new_rect(outer.with_outset((extent, extent)));
+        canvas.draw_drrect(outer, shape, &paint);
+    } else {
+        canvas.clip_rrect(clip, ClipOp::Difference, true);
+        canvas.draw_rrect(shape, &paint);
+    }
+    canvas.restore();
+}
@@ -3,8 +3,9 @@
 //! Font-system agnostic: the contract is font bytes + glyph IDs, not engine internals, so shaped
 //! runs from any [`FontSystem`] paint as Skia text blobs built from the runs' raw font bytes.
 
+use crate::rasterizer::shadow::shadow_paint;
+use gosub_interface::font_system::GlyphRotation;
 use gosub_render_pipeline::painter::commands::brush::Brush;
-use gosub_render_pipeline::painter::commands::gradient::Gradient;
 use gosub_render_pipeline::painter::commands::text::Text;
 use skia_safe::{Canvas, Color4f, Font as SkFont, FontMgr, Paint, Point, Rect, TextBlobBuilder, Typeface};
 use std::cell::RefCell;
@@ -44,20 +45,38 @@ fn typeface_for(blob: &gosub_interface::font::FontBlob) -> Option<Typeface> {
 pub fn do_paint_text(canvas: &Canvas, cmd: &Text, _dpi_scale_factor: f32) -> Result<(), anyhow::Error> {
     // Shaping happened once at paint-command build time (the pipeline Painter, with the same
     // font system the layouter measured with); this function only paints the glyph runs.
-    let shaped = &cmd.shaped;
-    if shaped.is_empty() {
+    if cmd.shaped.is_empty() {
         return Ok(());
     }
 
+    // Shadows paint beneath the glyphs, the first listed on top.
+    for shadow in cmd.shadows.iter().rev() {
+        if shadow.color.a() > 0.0 {
+            draw_runs(canvas, cmd, &shadow_paint(shadow), shadow.offset_x, shadow.offset_y);
+        }
+    }
+
     let mut paint = Paint::new(brush_to_color4f(&cmd.brush), None);
     paint.set_anti_alias(true);
+    draw_runs(canvas, cmd, &paint, 0.0, 0.0);
+
+    Ok(())
+}
 
-    let (x0, y0) = (cmd.rect.x as f32, cmd.rect.y as f32);
+/// Draws every glyph run plus its decorations with `paint`, shifted by `(dx, dy)`.
+fn draw_runs(canvas: &Canvas, cmd: &Text, paint: &Paint, dx: f32, dy: f32) {
+    let (x0, y0) = (cmd.rect.x as f32 + dx, cmd.rect.y as f32 + dy);
 
-    for run in &shaped.runs {
+    for run in &cmd.shaped.runs {
         let Some(typeface) = typeface_for(&run.font.blob) else {
             continue;
         };
+        // Sideways runs in vertical text are laid out in a frame turned about the block's origin.
+        let rotated = run.rotation != GlyphRotation::None;
+        if rotated {
+            canvas.save();
+            canvas.rotate(run.rotation.radians().to_degrees() as f32, Some(Point::new(x0, y0)));
+        }
         let font = SkFont::from_typeface(typeface, run.font_size);
 
         let mut builder = TextBlobBuilder::new();
@@ -67,14 +86,14 @@ pub fn do_paint_text(canvas: &Canvas, cmd: &Text, _dpi_scale_factor: f32) -> Res
             points[i] = Point::new(g.x, g.y);
         }
         if let Some(text_blob) = builder.make() {
-            canvas.draw_text_blob(&text_blob, (x0, y0), &paint);
+            canvas.draw_text_blob(&text_blob, (x0, y0), paint);
         }
 
         // Text decorations: a filled rect per run, using the run font's own metrics.
         let decoration = |offset: f32, size: f32| {
             let dx = x0 + run.x;
             let dy = y0 + run.baseline + offset;
-            canvas.draw_rect(Rect::new(dx, dy, dx + run.width, dy + size.max(1.0)), &paint);
+            canvas.draw_rect(Rect::new(dx, dy, dx + run.width, dy + size.max(1.0)), paint);
         };
         if cmd.font_info.underline {
             decoration(run.metrics.underline_offset, run.metrics.underline_size);
@@ -82,9 +101,10 @@ pub fn do_paint_text(canvas: &Canvas, cmd: &Text, _dpi_scale_factor: f32) -> Res
         if cmd.font_info.line_through {
             decoration(run.metrics.strikethrough_offset, run.metrics.strikethrough_size);
         }
+        if rotated {
+            canvas.restore();
+        }
     }
-
-    Ok(())
 }
 
 fn brush_to_color4f(brush: &Brush) -> Color4f {
@@ -92,7 +112,7 @@ fn brush_to_color4f(brush: &Brush) -> Color4f {
         Brush::Solid(c) => Color4f::new(c.r(), c.g(), c.b(), c.a()),
         // Gradient text fills aren't supported in the text path; approximate with the
         // first colour stop so glyphs stay visible rather than defaulting to black.
-        Brush::Gradient(Gradient::Linear(g)) => match g.stops.first() {
+        Brush::Gradient(g) => match g.stops().first() {
             Some(stop) => Color4f::new(stop.color.r(), stop.color.g(), stop.color.b(), stop.color.a()),
             None => Color4f::new(0.0, 0.0, 0.0, 1.0),
         },
@@ -105,7 +125,7 @@ mod tests {
     use super::*;
     use gosub_fontmanager::SkiaFontSystem;
     use gosub_interface::font_system::{FontSystem, TextStyle};
-    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo};
+    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo, FontShaping};
     use gosub_render_pipeline::common::geo::Rect as GeoRect;
     use gosub_render_pipeline::painter::commands::color::Color;
 
@@ -138,6 +158,7 @@ mod tests {
             alignment: FontAlignment::Start,
             underline: true,
             line_through: false,
+            shaping: FontShaping::default(),
         };
         let mut style = TextStyle::new("sans-serif", 24.0);
         style.line_height = Some(28.0);
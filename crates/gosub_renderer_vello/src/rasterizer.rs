@@ -6,14 +6,14 @@ use gosub_render_pipeline::common::texture::TextureId;
 use gosub_render_pipeline::common::TextureStore;
 use gosub_render_pipeline::painter::commands::PaintCommand;
 use gosub_render_pipeline::rasterizer::Rasterable;
-use gosub_render_pipeline::render::backend::TileAnchor;
+use gosub_render_pipeline::render::backend::{BlendMode, TileAnchor};
 use gosub_render_pipeline::tiler::Tile;
 
 use crate::backend::WgpuResources;
 use parking_lot::Mutex;
 use std::sync::Arc;
 use vello::kurbo::{Affine, Rect, Vec2};
-use vello::peniko::{Color, Fill, Mix};
+use vello::peniko::{Color, Fill};
 use vello::{AaConfig, RenderParams, Scene};
 
 /// The transform a promoted layer's commands draw under. Mirrors `anchored_tile_pos`: normal layers
@@ -30,7 +30,9 @@ fn layer_affine(anchor: TileAnchor, sx: f64, sy: f64) -> Affine {
 }
 
 mod brush;
+mod filter;
 mod rectangle;
+mod shadow;
 mod svg;
 mod text;
 
@@ -44,30 +46,91 @@ pub(crate) fn paint_commands_to_scene(
     affine: Affine,
     scroll: (f64, f64),
     media_store: &MediaStore,
+) {
+    paint_commands_shifted(scene, commands, size, affine, scroll, Affine::IDENTITY, media_store);
+}
+
+/// Index of the `PopLayer` closing the `PushLayer` at `start`, or `commands.len()` if unclosed.
+fn matching_pop(commands: &[PaintCommand], start: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, command) in commands.iter().enumerate().skip(start) {
+        match command {
+            PaintCommand::PushLayer { .. } => depth += 1,
+            PaintCommand::PopLayer => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    commands.len()
+}
+
+/// [`paint_commands_to_scene`] with an extra page-space `shift` that also moves nested layers,
+/// which otherwise reset to their anchor transform. Filters use it to paint offset copies.
+fn paint_commands_shifted(
+    scene: &mut Scene,
+    commands: &[PaintCommand],
+    size: Dimension,
+    affine: Affine,
+    scroll: (f64, f64),
+    shift: Affine,
+    media_store: &MediaStore,
 ) {
     let (sx, sy) = scroll;
     // Starts at the caller's affine and is swapped to a layer's anchor transform between
     // PushLayer/PopLayer. The tile path never emits those, so it paints under the initial affine.
     let mut cur = affine;
-    // (transform to restore, whether we pushed an opacity group) for each open PushLayer.
+    // (transform to restore, whether we pushed an offscreen group) for each open PushLayer.
     let mut stack: Vec<(Affine, bool)> = Vec::new();
-    for command in commands {
-        match command {
-            PaintCommand::PushLayer { opacity, anchor } => {
-                // Fade only when actually translucent (avoids a wasted offscreen group at α=1).
-                let faded = *opacity < 1.0;
-                if faded {
-                    // Clip to the viewport so the group's backing buffer stays viewport-sized; the
-                    // commands position themselves via `cur`, so the layer transform is identity.
-                    let clip = Rect::new(0.0, 0.0, size.width, size.height);
-                    scene.push_layer(Fill::NonZero, Mix::Normal, *opacity, Affine::IDENTITY, &clip);
+    let mut i = 0;
+    while i < commands.len() {
+        match &commands[i] {
+            PaintCommand::PushLayer {
+                opacity,
+                anchor,
+                blend_mode,
+                isolated,
+                filters,
+                translate,
+            } => {
+                // Clip to the viewport so the group's backing buffer stays viewport-sized; the
+                // commands position themselves via `cur`, so the layer transform is identity.
+                let clip = Rect::new(0.0, 0.0, size.width, size.height);
+                // Only open an offscreen group when it changes the result (avoids a wasted one for
+                // a merely pinned layer). Vello layers are isolated; the mix blends the flattened
+                // group into what is beneath it.
+                let grouped = *isolated || *opacity < 1.0 || *blend_mode != BlendMode::Normal;
+                if grouped {
+                    scene.push_layer(
+                        Fill::NonZero,
+                        rectangle::to_vello_mix(*blend_mode),
+                        *opacity,
+                        Affine::IDENTITY,
+                        &clip,
+                    );
+                }
+                stack.push((cur, grouped));
+                cur = layer_affine(*anchor, sx, sy) * shift * Affine::translate(Vec2::new(translate.0, translate.1));
+
+                if !filters.is_empty() {
+                    // The whole group up to its PopLayer goes through the filter chain at once;
+                    // resume at the PopLayer so it closes the group as usual.
+                    let end = matching_pop(commands, i);
+                    let inner = &commands[i + 1..end];
+                    let base = cur;
+                    filter::paint_filtered(scene, inner, filters, &clip, &|scene, cmds, s| {
+                        paint_commands_shifted(scene, cmds, size, base * s, scroll, shift * s, media_store);
+                    });
+                    i = end;
+                    continue;
                 }
-                stack.push((cur, faded));
-                cur = layer_affine(*anchor, sx, sy);
             }
             PaintCommand::PopLayer => {
-                if let Some((prev, faded)) = stack.pop() {
-                    if faded {
+                if let Some((prev, grouped)) = stack.pop() {
+                    if grouped {
                         scene.pop_layer();
                     }
                     cur = prev;
@@ -84,7 +147,11 @@ pub(crate) fn paint_commands_to_scene(
                     log::warn!("Failed to paint text: {:?}", e);
                 }
             }
+            PaintCommand::BoxShadow(command) => {
+                shadow::do_paint_box_shadow(scene, command, cur);
+            }
         }
+        i += 1;
     }
 }
 
@@ -123,17 +190,29 @@ impl Rasterable for VelloRasterizer {
 
         let affine = Affine::translate(Vec2::new(-tile.rect.x, -tile.rect.y));
 
-        for element in &tile.elements {
-            // The tile path applies opacity/anchor at composite, so per-element commands carry no
-            // PushLayer/PopLayer - scroll is irrelevant here.
-            paint_commands_to_scene(
-                &mut scene,
-                &element.paint_commands,
-                tile_size,
-                affine,
-                (0.0, 0.0),
-                media_store,
-            );
+        if tile.filters.is_empty() {
+            for element in &tile.elements {
+                // The tile path applies opacity/anchor at composite, so per-element commands carry
+                // no PushLayer/PopLayer - scroll is irrelevant here.
+                paint_commands_to_scene(
+                    &mut scene,
+                    &element.paint_commands,
+                    tile_size,
+                    affine,
+                    (0.0, 0.0),
+                    media_store,
+                );
+            }
+        } else {
+            // A filter acts on the layer as one image, so filter the tile's elements together.
+            let commands: Vec<PaintCommand> = tile
+                .elements
+                .iter()
+                .flat_map(|element| element.paint_commands.iter().cloned())
+                .collect();
+            filter::paint_filtered(&mut scene, &commands, &tile.filters, &clip, &|scene, cmds, s| {
+                paint_commands_to_scene(scene, cmds, tile_size, affine * s, (0.0, 0.0), media_store);
+            });
         }
 
         scene.pop_layer();
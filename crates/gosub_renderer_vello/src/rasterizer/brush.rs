@@ -1,7 +1,8 @@
 use gosub_render_pipeline::common::geo::Rect;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::painter::commands::brush::Brush;
-use gosub_render_pipeline::painter::commands::gradient::{Gradient as CssGradient, LinearGradient, Tiling};
+use gosub_render_pipeline::painter::commands::gradient::Gradient as CssGradient;
+use gosub_render_pipeline::render::DEVICE_PIXEL_RATIO;
 use vello::kurbo::Affine;
 use vello::peniko::color::{AlphaColor, DynamicColor, Rgba8};
 use vello::peniko::{
@@ -19,15 +20,13 @@ pub fn set_brush(brush: &Brush, rect: Rect, media_store: &MediaStore) -> (VelloB
             let c = Rgba8::from_u8_array([color.r8(), color.g8(), color.b8(), color.a8()]);
             (VelloBrush::Solid(AlphaColor::from(c)), None)
         }
-        Brush::Gradient(CssGradient::Linear(g)) => {
-            // Tiled `background-image` layer: rasterize one `background-size` cell and repeat it
-            // as an image brush, offset by `background-position`.
-            if let Some(tiling) = &g.tiling {
-                return tiled_gradient_brush(g, tiling, rect);
-            }
-            let ((x0, y0), (x1, y1)) = g.line(rect.width as f32, rect.height as f32);
-            let stops: Vec<ColorStop> = g
-                .stops
+        Brush::Gradient(g) => {
+            let Some((lg, stops)) = g.as_native_linear(rect.width as f32, rect.height as f32) else {
+                // Radial, conic, repeating, tiled or non-sRGB: use the shared raster as an image.
+                return gradient_raster_brush(g, rect);
+            };
+            let ((x0, y0), (x1, y1)) = lg.line(rect.width as f32, rect.height as f32);
+            let stops: Vec<ColorStop> = stops
                 .iter()
                 .map(|s| ColorStop {
                     offset: s.offset,
@@ -91,26 +90,32 @@ pub fn set_brush(brush: &Brush, rect: Rect, media_store: &MediaStore) -> (VelloB
     }
 }
 
-/// Tiled `background-image` gradient layer: rasterize one tile at `background-size` and let the
-/// sampler repeat it, offset by `background-position`. The fill shape clips the infinite tiling.
-fn tiled_gradient_brush(g: &LinearGradient, tiling: &Tiling, rect: Rect) -> (VelloBrush, Option<Affine>) {
-    let tw = (tiling.tile_size.0.round() as u32).max(1);
-    let th = (tiling.tile_size.1.round() as u32).max(1);
-    let rgba = g.rasterize_tile(tw, th);
+/// A gradient Vello can't draw natively, rasterized at device resolution (one `background-size`
+/// tile when tiled, else the whole box) and scaled onto `rect` as an image brush, offset by
+/// `background-position`. The fill shape clips the infinite tiling.
+fn gradient_raster_brush(g: &CssGradient, rect: Rect) -> (VelloBrush, Option<Affine>) {
+    let dpr = DEVICE_PIXEL_RATIO.load(std::sync::atomic::Ordering::Relaxed) as f32;
+    let Some(raster) = g.raster(rect.width as f32, rect.height as f32, dpr) else {
+        return (VelloBrush::Solid(AlphaColor::TRANSPARENT), None);
+    };
     let image_data = ImageData {
-        data: Blob::<u8>::from(rgba),
+        data: Blob::<u8>::from(raster.pixels),
         format: ImageFormat::Rgba8,
         // Straight (unpremultiplied) alpha, matching the raster-image brush above.
         alpha_type: ImageAlphaType::Alpha,
-        width: tw,
-        height: th,
+        width: raster.width,
+        height: raster.height,
     };
-    // Full-repeat (the default) tiles both axes; no-repeat pads (clamps) instead.
+    // Repeated axes tile; the rest pad (clamp) at the raster edge.
     let extend = |repeat: bool| if repeat { Extend::Repeat } else { Extend::Pad };
     let sampler = ImageSampler::default()
-        .with_x_extend(extend(tiling.repeat.0))
-        .with_y_extend(extend(tiling.repeat.1));
-    let transform = Affine::translate((rect.x + tiling.position.0 as f64, rect.y + tiling.position.1 as f64));
+        .with_x_extend(extend(raster.repeat.0))
+        .with_y_extend(extend(raster.repeat.1));
+    let transform = Affine::translate((rect.x + raster.position.0 as f64, rect.y + raster.position.1 as f64))
+        * Affine::scale_non_uniform(
+            raster.size.0 as f64 / raster.width as f64,
+            raster.size.1 as f64 / raster.height as f64,
+        );
     (
         VelloBrush::Image(ImageBrush {
             image: image_data,
@@ -6,9 +6,10 @@ use vello::kurbo;
 use vello::kurbo::{Affine, PathEl, Point, Rect, RoundedRect, Shape};
 use vello::peniko::{Fill, Mix};
 
-/// CSS `mix-blend-mode` → Vello mix mode. Applied by wrapping the element's drawing in a
-/// blend layer, which composites it against the scene content painted beneath it.
-fn to_vello_mix(mode: BlendMode) -> Mix {
+/// CSS `mix-blend-mode` → Vello mix mode. Applied by wrapping the drawing (a rectangle, or a
+/// whole stacking-context group) in a blend layer, which composites it against the scene content
+/// painted beneath it.
+pub(crate) fn to_vello_mix(mode: BlendMode) -> Mix {
     match mode {
         BlendMode::Normal => Mix::Normal,
         BlendMode::Multiply => Mix::Multiply,
@@ -186,7 +187,7 @@ fn draw_double_border(scene: &mut vello::Scene, rect: &Rectangle, affine: Affine
     );
 }
 
-enum ShapeEnum {
+pub(crate) enum ShapeEnum {
     Rect(Rect),
     RoundedRect(RoundedRect),
 }
@@ -230,7 +231,7 @@ impl Shape for ShapeEnum {
     }
 }
 
-fn setup_rectangle_path(rect: &Rectangle) -> ShapeEnum {
+pub(crate) fn setup_rectangle_path(rect: &Rectangle) -> ShapeEnum {
     if rect.is_rounded() {
         let (r_tl, r_tr, r_br, r_bl) = rect.radius_x();
         return ShapeEnum::RoundedRect(RoundedRect::new(
@@ -0,0 +1,68 @@
+use crate::rasterizer::rectangle::setup_rectangle_path;
+use gosub_render_pipeline::painter::commands::shadow::BoxShadow;
+use vello::kurbo::{Affine, BezPath, Rect, Shape};
+use vello::peniko::{BlendMode, Color, Compose, Fill, Mix};
+use vello::Scene;
+
+/// Paints one `box-shadow` layer: outer shadows only outside the border box, inset shadows only
+/// inside the padding box. Vello's blurred rounded rect takes one radius for all corners, so
+/// differing corner radii blur with the largest one.
+pub(crate) fn do_paint_box_shadow(scene: &mut Scene, cmd: &BoxShadow, affine: Affine) {
+    let c = &cmd.shadow.color;
+    if c.a() <= 0.0 {
+        return;
+    }
+    let color = Color::new([c.r(), c.g(), c.b(), c.a()]);
+    let black = Color::new([0.0, 0.0, 0.0, 1.0]);
+
+    let clip = cmd.clip();
+    let clip_shape = setup_rectangle_path(&clip);
+    let shape = cmd.shape();
+    let s = shape.rect();
+    let shape_rect = Rect::new(s.x, s.y, s.x + s.width, s.y + s.height);
+    let (tl, tr, br, bl) = shape.radius_x();
+    let radius = tl.max(tr).max(br).max(bl);
+    let sigma = cmd.shadow.sigma() as f64;
+
+    // Reaches past the blur on every side of both boxes.
+    let extent = cmd.shadow.blur_extent() + 1.0;
+    let b = clip.rect();
+    let area = Rect::new(b.x, b.y, b.x + b.width, b.y + b.height)
+        .union(shape_rect)
+        .inflate(extent, extent);
+
+    // Paints the shadow shape, exactly when unblurred.
+    let draw_shape = |scene: &mut Scene, brush: Color| {
+        if sigma < 0.5 {
+            scene.fill(Fill::NonZero, affine, brush, None, &setup_rectangle_path(&shape));
+        } else {
+            scene.draw_blurred_rounded_rect(affine, shape_rect, brush, radius, sigma);
+        }
+    };
+
+    if cmd.shadow.inset {
+        scene.push_clip_layer(Fill::NonZero, affine, &clip_shape);
+        // The inset shadow is everything around the shape: fill the area, then knock the
+        // (blurred) shape back out of it inside an isolated group.
+        scene.push_layer(Fill::NonZero, Mix::Normal, 1.0, affine, &area);
+        scene.fill(Fill::NonZero, affine, color, None, &area);
+        scene.push_layer(
+            Fill::NonZero,
+            BlendMode::new(Mix::Normal, Compose::DestOut),
+            1.0,
+            affine,
+            &area,
+        );
+        draw_shape(scene, black);
+        scene.pop_layer();
+        scene.pop_layer();
+        scene.pop_layer();
+    } else {
+        let mut outside = BezPath::new();
+        outside.extend(area.path_elements(0.1));
+        outside.extend(clip_shape.path_elements(0.1));
+        scene.push_clip_layer(Fill::EvenOdd, affine, &outside);
+        draw_shape(scene, color);
+        scene.pop_layer();
+    }
+}
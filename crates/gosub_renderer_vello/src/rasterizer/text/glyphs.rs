@@ -4,12 +4,13 @@
 //! [`FontSystem`] implementation works here.
 
 use crate::rasterizer::brush::set_brush;
+use crate::rasterizer::filter::paint_blurred;
 use gosub_interface::font_system::ShapedRun;
 use gosub_render_pipeline::common::geo::Dimension;
 use gosub_render_pipeline::common::media::MediaStore;
 use gosub_render_pipeline::painter::commands::text::Text;
-use vello::kurbo::{Affine, Rect as KurboRect};
-use vello::peniko::{Blob, Fill, FontData};
+use vello::kurbo::{Affine, Point, Rect as KurboRect, Vec2};
+use vello::peniko::{Blob, Brush, Color, Fill, FontData};
 use vello::Scene;
 
 fn peniko_font(run: &ShapedRun) -> FontData {
@@ -25,20 +26,45 @@ pub fn do_paint_text(
 ) -> Result<(), anyhow::Error> {
     // Shaping already happened at paint-command build time, with the same font system the layouter
     // measured with; this function only paints the runs.
-    let shaped = &cmd.shaped;
-    if shaped.is_empty() {
+    if cmd.shaped.is_empty() {
         return Ok(());
     }
 
+    // Shadows paint beneath the glyphs, the first listed on top.
+    for shadow in cmd.shadows.iter().rev() {
+        let c = &shadow.color;
+        if c.a() <= 0.0 {
+            continue;
+        }
+        let brush = Brush::Solid(Color::new([c.r(), c.g(), c.b(), c.a()]));
+        let offset = Affine::translate(Vec2::new(shadow.offset_x as f64, shadow.offset_y as f64));
+        // Glyphs may overhang the line box, so leave some room beyond the blur reach.
+        let slack = shadow.blur_extent() + cmd.font_info.size / 2.0;
+        let r = cmd.rect;
+        let bounds = KurboRect::new(r.x, r.y, r.x + r.width, r.y + r.height).inflate(slack, slack);
+        let clip = (affine * offset).transform_rect_bbox(bounds);
+        paint_blurred(scene, shadow.sigma(), &clip, &|scene, shift| {
+            draw_runs(scene, cmd, &brush, affine * offset * shift);
+        });
+    }
+
     // Glyph runs take only the brush; an image brush transform has no meaningful mapping onto
     // individual glyphs, so it is intentionally dropped here.
     let (vello_brush, _) = set_brush(&cmd.brush, cmd.rect, media_store);
+    draw_runs(scene, cmd, &vello_brush, affine);
+
+    Ok(())
+}
 
-    for run in &shaped.runs {
+/// Paints every glyph run plus its decorations with `brush`.
+fn draw_runs(scene: &mut Scene, cmd: &Text, brush: &Brush, affine: Affine) {
+    for run in &cmd.shaped.runs {
         let font = peniko_font(run);
+        // Sideways runs in vertical text are laid out in a frame turned about the block's origin.
+        let affine = affine * Affine::rotate_about(run.rotation.radians(), Point::new(cmd.rect.x, cmd.rect.y));
         scene
             .draw_glyphs(&font)
-            .brush(&vello_brush)
+            .brush(brush)
             .font_size(run.font_size)
             .hint(true)
             .transform(affine)
@@ -56,7 +82,7 @@ pub fn do_paint_text(
             let x0 = cmd.rect.x + run.x as f64;
             let y0 = cmd.rect.y + (run.baseline + offset) as f64;
             let rect = KurboRect::new(x0, y0, x0 + run.width as f64, y0 + size.max(1.0) as f64);
-            scene.fill(Fill::NonZero, affine, &vello_brush, None, &rect);
+            scene.fill(Fill::NonZero, affine, brush, None, &rect);
         };
         if cmd.font_info.underline {
             decoration(run.metrics.underline_offset, run.metrics.underline_size);
@@ -65,8 +91,6 @@ pub fn do_paint_text(
             decoration(run.metrics.strikethrough_offset, run.metrics.strikethrough_size);
         }
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -74,7 +98,7 @@ mod tests {
     use super::*;
     use gosub_fontmanager::ParleyFontSystem;
     use gosub_interface::font_system::{FontSystem, TextStyle};
-    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo};
+    use gosub_render_pipeline::common::font::{FontAlignment, FontInfo, FontShaping};
     use gosub_render_pipeline::common::geo::Rect as GeoRect;
     use gosub_render_pipeline::painter::commands::brush::Brush;
     use gosub_render_pipeline::painter::commands::color::Color;
@@ -96,6 +120,7 @@ mod tests {
             alignment: FontAlignment::Start,
             underline: true,
             line_through: false,
+            shaping: FontShaping::default(),
         };
         let mut style = TextStyle::new("sans-serif", 24.0);
         style.line_height = Some(28.0);
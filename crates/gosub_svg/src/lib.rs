@@ -32,8 +32,9 @@ impl SVGDocument {
         Ok(Self { tree })
     }
 
-    pub fn from_html_doc<C: HasDocument>(id: NodeId, doc: C::Document) -> Result<Self> {
-        let str = doc.write_from_node(id);
+    pub fn from_html_doc<C: HasDocument>(id: NodeId, doc: &C::Document) -> Result<Self> {
+        // usvg reads XML, so the subtree is serialized as XML to get the SVG namespace declared
+        let str = doc.outer_xml(id);
 
         Self::from_str(&str)
     }
@@ -40,6 +40,8 @@ enum UiEvent {
     NavigationStarted,
     NavigationFinished,
     HoverUrl(Option<String>),
+    SelectionText(String),
+    FindResult { active: usize, total: usize },
 }
 
 struct BrowserApp {
@@ -51,6 +53,15 @@ struct BrowserApp {
     tab_id: TabId,
     compositor: Arc<DefaultCompositor>,
     url_input: String,
+    /// The address bar has keyboard focus, so copy belongs to it rather than the page.
+    addr_focused: bool,
+    /// The find bar is shown (Ctrl-F), with its query and the last `FindResult`.
+    find_open: bool,
+    find_query: String,
+    find_result: (usize, usize),
+    /// Focus the find field on the next frame, after Ctrl-F opens it.
+    find_focus: bool,
+    find_focused: bool,
     status_url: String,
     texture: Option<egui::TextureHandle>,
     last_panel_size: egui::Vec2,
@@ -92,6 +103,10 @@ impl BrowserApp {
                                 ..
                             } => Some(UiEvent::NavigationFinished),
                             EngineEvent::HoverUrl { url, .. } => Some(UiEvent::HoverUrl(url)),
+                            EngineEvent::SelectionText { text, .. } => Some(UiEvent::SelectionText(text)),
+                            EngineEvent::FindResult { active, total, .. } => {
+                                Some(UiEvent::FindResult { active, total })
+                            }
                             _ => None,
                         };
                         if let Some(ev) = out {
@@ -146,6 +161,12 @@ impl BrowserApp {
             tab_id,
             compositor,
             url_input: initial_url,
+            addr_focused: false,
+            find_open: false,
+            find_query: String::new(),
+            find_result: (0, 0),
+            find_focus: false,
+            find_focused: false,
             status_url: String::new(),
             texture: None,
             last_panel_size: egui::Vec2::ZERO,
@@ -174,6 +195,31 @@ impl BrowserApp {
         });
     }
 
+    /// Steps find-in-page through the matches of the current query.
+    fn find(&self, forward: bool) {
+        let tab = self.tab.clone();
+        let query = self.find_query.clone();
+        TOKIO_RT.spawn(async move {
+            let _ = tab
+                .send(TabCommand::Find {
+                    query,
+                    case_sensitive: false,
+                    forward,
+                })
+                .await;
+        });
+    }
+
+    fn close_find(&mut self) {
+        self.find_open = false;
+        self.find_focused = false;
+        self.find_result = (0, 0);
+        let tab = self.tab.clone();
+        TOKIO_RT.spawn(async move {
+            let _ = tab.send(TabCommand::StopFind).await;
+        });
+    }
+
     fn refresh_texture(&mut self, ctx: &egui::Context) {
         let Some(handle) = self.compositor.frame_for(self.tab_id) else {
             return;
@@ -295,9 +341,26 @@ impl eframe::App for BrowserApp {
                 UiEvent::NavigationStarted => self.is_loading = true,
                 UiEvent::NavigationFinished => self.is_loading = false,
                 UiEvent::HoverUrl(url) => self.status_url = url.unwrap_or_default(),
+                UiEvent::SelectionText(text) => {
+                    if !text.is_empty() {
+                        ctx.copy_text(text);
+                    }
+                }
+                UiEvent::FindResult { active, total } => self.find_result = (active, total),
             }
         }
 
+        // Copy → ask the engine for the page's selected text; it arrives as SelectionText.
+        if !self.addr_focused
+            && !self.find_focused
+            && ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)))
+        {
+            let tab = self.tab.clone();
+            TOKIO_RT.spawn(async move {
+                let _ = tab.send(TabCommand::GetSelectionText).await;
+            });
+        }
+
         // Update local scroll synchronously so refresh_texture composites at the new position.
         // Raw mouse-wheel deltas (NOT egui's smoothed scroll): the engine owns scroll smoothing
         // now, so forwarding egui's `smooth_scroll_delta` double-smooths (slow ramp, no ease-out,
@@ -362,9 +425,48 @@ impl eframe::App for BrowserApp {
                     if r.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                         self.navigate();
                     }
+                    self.addr_focused = r.has_focus();
                 });
             });
 
+        // Find bar: Ctrl-F opens it, Enter / Shift-Enter step through the matches, Esc closes it.
+        if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::F)) {
+            self.find_open = true;
+            self.find_focus = true;
+        }
+        if self.find_open {
+            egui::Panel::top("find")
+                .frame(egui::Frame::default().inner_margin(egui::Margin::symmetric(8, 4)))
+                .show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let r = ui.add(
+                            egui::TextEdit::singleline(&mut self.find_query)
+                                .desired_width(240.0)
+                                .hint_text("Find in page…"),
+                        );
+                        if self.find_focus {
+                            r.request_focus();
+                            self.find_focus = false;
+                        }
+                        if r.changed() {
+                            self.find(true);
+                        }
+                        if r.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            self.find(!ui.input(|i| i.modifiers.shift));
+                            r.request_focus();
+                        }
+                        self.find_focused = r.has_focus();
+                        if !self.find_query.is_empty() {
+                            let (active, total) = self.find_result;
+                            ui.label(format!("{active} of {total}"));
+                        }
+                        if ui.button("✕").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                            self.close_find();
+                        }
+                    });
+                });
+        }
+
         // Status bar
         egui::Panel::bottom("status")
             .frame(egui::Frame::default().inner_margin(egui::Margin::symmetric(4, 2)))
@@ -407,10 +509,11 @@ impl eframe::App for BrowserApp {
                     }
                 }
 
-                // Click → links
-                if response.clicked() {
-                    if let Some(pos) = ctx.input(|i| i.pointer.interact_pos()) {
-                        let rel = pos - response.rect.min;
+                // Press → links and text selection; moves in between drag the selection.
+                let (pressed, released) = ctx.input(|i| (i.pointer.primary_pressed(), i.pointer.primary_released()));
+                if let Some(pos) = ctx.input(|i| i.pointer.interact_pos()) {
+                    let rel = pos - response.rect.min;
+                    if pressed && response.rect.contains(pos) {
                         let tab = self.tab.clone();
                         TOKIO_RT.spawn(async move {
                             let _ = tab
@@ -422,6 +525,18 @@ impl eframe::App for BrowserApp {
                                 .await;
                         });
                     }
+                    if released {
+                        let tab = self.tab.clone();
+                        TOKIO_RT.spawn(async move {
+                            let _ = tab
+                                .send(TabCommand::MouseUp {
+                                    x: rel.x,
+                                    y: rel.y,
+                                    button: gosub_engine::events::MouseButton::Left,
+                                })
+                                .await;
+                        });
+                    }
                 }
             } else {
                 ui.centered_and_justified(|ui| {
@@ -191,6 +191,7 @@ fn print_stylesheet(sheet: &CssStylesheet) {
                         CssSelectorPart::Id(i) => println!("        [Id] #{i}"),
                         CssSelectorPart::Universal => println!("        [Universal] *"),
                         CssSelectorPart::PseudoClass(p) => println!("        [PseudoClass] :{p}"),
+                        CssSelectorPart::PseudoFunction(p) => println!("        [PseudoFunction] :{}()", p.name()),
                         CssSelectorPart::PseudoElement(p) => println!("        [PseudoElement] ::{p}"),
                         CssSelectorPart::Combinator(c) => println!("        [Combinator] {c:?}"),
                         CssSelectorPart::Attribute(a) => println!("        [Attribute] [{}]", a.name),